@@ -0,0 +1,49 @@
+//! Benchmarks of preparing uploaded schedule images for the parser.
+//!
+//! Run with `cargo bench --bench preprocess`. The images are drawn here instead of read from
+//! disk: a phone photo of a printed schedule, larger than the parser's limit so it's shrunk
+//! and re-encoded, and a screenshot that is passed on unchanged after decoding.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use mussubotti::web::preprocess::{preprocess_image, MAX_PARSE_EDGE};
+use std::io::Cursor;
+
+/// A schedule-like grid of dark lines on paper with a little noise, encoded as `format`
+fn schedule_image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        if x % 120 < 3 || y % 60 < 3 {
+            Rgb([30, 30, 30])
+        } else {
+            let grain = ((x * 7 + y * 13) % 16) as u8;
+            Rgb([225 + grain, 222 + grain, 215 + grain])
+        }
+    });
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut Cursor::new(&mut encoded), format)
+        .expect("encoding the fixture image");
+    encoded
+}
+
+fn bench_preprocess(c: &mut Criterion) {
+    let photo = schedule_image(MAX_PARSE_EDGE + 1000, 3000, ImageFormat::Jpeg);
+    let screenshot = schedule_image(1600, 1000, ImageFormat::Png);
+
+    let mut group = c.benchmark_group("preprocess_image");
+    group.sample_size(10);
+    for (name, data) in [("oversized_photo", &photo), ("screenshot", &screenshot)] {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || Cursor::new(data.as_slice()),
+                |reader| preprocess_image(reader).expect("preprocessing the fixture image"),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_preprocess);
+criterion_main!(benches);
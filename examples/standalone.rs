@@ -0,0 +1,31 @@
+//! Print an employee's stored work schedule without connecting to Discord.
+//!
+//! Reads the same environment as the bot, e.g.
+//! `STORAGE_BACKEND=sqlite cargo run --example standalone --features sqlite -- "Anna"`.
+
+use mussubotti::builder::BotComponents;
+use mussubotti::config::Config;
+use mussubotti::error::{config_error, BotResult};
+
+#[tokio::main]
+async fn main() -> BotResult<()> {
+    let employee = std::env::args()
+        .nth(1)
+        .ok_or_else(|| config_error("Usage: standalone <employee>"))?;
+
+    let components = BotComponents::new(Config::load()?)
+        .work_schedule()
+        .build()
+        .await?;
+
+    match &components.work_schedule {
+        Some(schedule) => {
+            for entry in schedule.get_schedule_for_employee(employee).await?.schedule {
+                println!("{}: {}", entry.date, entry.format());
+            }
+        }
+        None => eprintln!("The work_schedule component is disabled"),
+    }
+
+    components.shutdown.await
+}
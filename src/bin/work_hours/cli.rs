@@ -0,0 +1,243 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use mussubotti::components::work_schedule::parse_failures::ParseFailure;
+use mussubotti::utils::logging::LogArgs;
+
+use crate::web::db::RedisDB;
+use crate::web::locks::EmployeeLocks;
+use crate::web::model::{WorkHoursDb, WorkSchedule};
+use crate::web::parser::{
+    convert_to_work_schedule, extract_json_array, extract_schedule_days, Provider,
+};
+use crate::web::preprocess::preprocess_image;
+use crate::web::replay::{replay, ReplayFilter};
+use crate::web::validation::{reject_suspect_parse, validate_schedule, ValidationReport};
+
+/// Work schedule web interface and tools
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the web server (default)
+    Serve(ServeArgs),
+    /// Merge schedules stored under variant spellings of the same employee
+    MigrateEmployeeIds,
+    /// Parse a schedule image offline and print the result
+    Parse(ParseArgs),
+    /// Convert the kept model output of earlier uploads again with the current code
+    Replay(ReplayArgs),
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Serve(ServeArgs::default())
+    }
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ParseArgs {
+    /// Schedule image to parse
+    #[arg(long, required_unless_present = "failure")]
+    pub image: Option<PathBuf>,
+    /// Employee whose row to read
+    #[arg(long, required_unless_present = "failure")]
+    pub employee: Option<String>,
+    /// Read the model response of a parse failure saved from
+    /// `GET /api/v1/parse-failures/{id}` again, instead of parsing an image
+    #[arg(long, conflicts_with_all = ["image", "employee", "provider", "dump_preprocessed"])]
+    pub failure: Option<PathBuf>,
+    /// First date the schedule should cover (YYYY-MM-DD)
+    #[arg(long)]
+    pub start: Option<NaiveDate>,
+    /// Last date the schedule should cover (YYYY-MM-DD)
+    #[arg(long)]
+    pub end: Option<NaiveDate>,
+    /// Model provider reading the image
+    #[arg(long, value_enum, default_value_t)]
+    pub provider: Provider,
+    /// Write the preprocessed image next to the input
+    #[arg(long)]
+    pub dump_preprocessed: bool,
+    /// Store the parsed schedule in this Redis instance
+    #[arg(long, value_name = "REDIS_URL")]
+    pub store: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// Only replay this employee's upload
+    #[arg(long)]
+    pub employee: Option<String>,
+    /// Only replay uploads made on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Print what would change without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Path the preprocessed image is dumped to, e.g. `week.preprocessed.png` for `week.jpg`
+fn preprocessed_path(image: &Path, extension: &str) -> PathBuf {
+    let stem = image
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    image.with_file_name(format!("{stem}.preprocessed.{extension}"))
+}
+
+/// Run the parse pipeline on a local image, printing the schedule, the validation report and
+/// how long each stage took
+pub async fn run_parse(args: ParseArgs) -> Result<(), String> {
+    if let Some(path) = &args.failure {
+        return run_failure(path, &args).await;
+    }
+    // Clap requires both without --failure
+    let (Some(image_path), Some(employee)) = (&args.image, &args.employee) else {
+        return Err("--image and --employee are required".to_string());
+    };
+
+    let mut timings: Vec<(&str, Duration)> = Vec::new();
+
+    let started = Instant::now();
+    let image = std::fs::File::open(image_path)
+        .map_err(|e| format!("Failed to read {}: {e}", image_path.display()))?;
+    timings.push(("read", started.elapsed()));
+
+    let started = Instant::now();
+    let (image, format) = preprocess_image(std::io::BufReader::new(image))?;
+    timings.push(("preprocess", started.elapsed()));
+
+    if args.dump_preprocessed {
+        let path = preprocessed_path(image_path, format.extension());
+        std::fs::write(&path, &image)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        eprintln!("Preprocessed image written to {}", path.display());
+    }
+
+    let started = Instant::now();
+    let days = extract_schedule_days(employee, &image, args.provider)
+        .await
+        .map_err(|e| e.message)?;
+    let schedule = convert_to_work_schedule(employee, days.clone())?;
+    timings.push(("parse", started.elapsed()));
+
+    let started = Instant::now();
+    let report = validate_schedule(&days, &schedule, args.start, args.end);
+    timings.push(("validate", started.elapsed()));
+
+    print_schedule(&schedule, &report)?;
+    println!("\nTimings:");
+    for (stage, elapsed) in &timings {
+        println!("  {stage:<10} {:>8.1} ms", elapsed.as_secs_f64() * 1000.0);
+    }
+
+    store_schedule(&args, employee, &schedule).await
+}
+
+/// Print the schedule as JSON and the validation report after it
+fn print_schedule(schedule: &WorkSchedule, report: &ValidationReport) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(schedule)
+        .map_err(|e| format!("JSON serialization error: {e}"))?;
+    println!("{json}");
+    println!("\nValidation: {report}");
+    Ok(())
+}
+
+/// Store the schedule when asked to with `--store`
+async fn store_schedule(
+    args: &ParseArgs,
+    employee: &str,
+    schedule: &WorkSchedule,
+) -> Result<(), String> {
+    if let Some(url) = &args.store {
+        RedisDB::with_url(url)?
+            .set_schedule(employee, schedule)
+            .await?;
+        eprintln!("\nStored schedule for {} in {}", employee, url);
+    }
+    Ok(())
+}
+
+/// Read a stored parse failure's model response again, running the same extraction and
+/// checks as the upload did, e.g. after changing them
+async fn run_failure(path: &Path, args: &ParseArgs) -> Result<(), String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let failure: ParseFailure =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid parse failure: {e}"))?;
+    let exchange = &failure.exchange;
+    eprintln!(
+        "Parse failure {} ({} with {}/{}): {}",
+        failure.id, failure.stage, exchange.provider, exchange.model, failure.error
+    );
+    if failure.truncated {
+        eprintln!("The stored response was truncated");
+    }
+
+    let days = extract_json_array(&exchange.response)?;
+    reject_suspect_parse(&days, &exchange.markdown, &exchange.employee)?;
+    let schedule = convert_to_work_schedule(&exchange.employee, days.clone())?;
+    let report = validate_schedule(&days, &schedule, args.start, args.end);
+    print_schedule(&schedule, &report)?;
+
+    store_schedule(args, &exchange.employee, &schedule).await
+}
+
+/// Replay the kept uploads, printing each one's changes as JSON
+pub async fn run_replay(args: ReplayArgs) -> Result<(), String> {
+    let db = RedisDB::new()?;
+    if !args.dry_run {
+        if let Some(maintenance) = db.get_maintenance().await? {
+            return Err(format!(
+                "The bot is in maintenance mode ({}), only --dry-run is allowed",
+                maintenance.message()
+            ));
+        }
+    }
+
+    let filter = ReplayFilter {
+        employee: args.employee,
+        since: args.since,
+    };
+    let report = replay(
+        &db,
+        &EmployeeLocks::default(),
+        &filter,
+        convert_to_work_schedule,
+        args.dry_run,
+        "work_hours replay",
+    )
+    .await?;
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("JSON serialization error: {e}"))?;
+    println!("{json}");
+
+    let changed = report
+        .uploads
+        .iter()
+        .filter(|upload| !upload.changes.is_empty())
+        .count();
+    if args.dry_run {
+        println!(
+            "\n{changed} of {} uploads would change",
+            report.uploads.len()
+        );
+    } else {
+        println!("\n{changed} of {} uploads changed", report.uploads.len());
+    }
+    Ok(())
+}
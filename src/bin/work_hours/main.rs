@@ -1,48 +1,39 @@
-#![cfg_attr(not(feature = "web-interface"), allow(dead_code, unused_imports))]
+#[macro_use]
+extern crate rust_i18n;
+
+// Initialize i18n
+i18n!("locales", fallback = "en");
 
 // Import modules
-mod auth;
-mod db;
-mod handlers;
-mod model;
-mod parser;
+#[cfg(feature = "web-interface")]
+mod cli;
+#[cfg(feature = "web-interface")]
+use mussubotti::web;
 
+#[cfg(feature = "web-interface")]
 use std::sync::Arc;
 
 #[cfg(feature = "web-interface")]
-use axum::{
-    body::Body,
-    extract::DefaultBodyLimit,
-    http::Request,
-    middleware::Next,
-    response::{IntoResponse, Redirect, Response},
-    routing::get,
-    Router,
-};
+use clap::Parser;
 #[cfg(feature = "web-interface")]
-use std::net::SocketAddr;
+use mussubotti::utils::logging::LogArgs;
 #[cfg(feature = "web-interface")]
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use mussubotti::utils::{redact, telemetry};
 #[cfg(feature = "web-interface")]
 use tracing::info;
 #[cfg(feature = "web-interface")]
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::auth::AuthService;
-use crate::db::RedisDB;
-use crate::handlers::{
-    dashboard_handler, health_handler, index_handler, login_form_handler, login_handler,
-    upload_form_handler, upload_handler,
-};
-use crate::model::WorkHoursDb;
-
-#[derive(Clone)]
-pub struct AppState {
-    /// Auth service for JWT operations
-    pub auth_service: Arc<AuthService>,
-    /// Database for work hours
-    pub db: Arc<dyn WorkHoursDb>,
-}
+#[cfg(feature = "web-interface")]
+use crate::cli::{Cli, Command};
+#[cfg(feature = "web-interface")]
+use crate::web::db::RedisDB;
+#[cfg(feature = "web-interface")]
+use crate::web::model::WorkHoursDb;
+#[cfg(feature = "web-interface")]
+use crate::web::{listen_addr, serve};
+#[cfg(feature = "web-interface")]
+use crate::web::{model, AppState};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -54,130 +45,88 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     #[cfg(feature = "web-interface")]
     {
+        let cli = Cli::parse();
+
         // Load environment variables
         dotenvy::dotenv().ok();
 
-        // Initialize tracing
-        tracing_subscriber::registry()
-            .with(tracing_subscriber::EnvFilter::new(
-                std::env::var("RUST_LOG").unwrap_or_else(|_| "info,tower_http=debug".into()),
-            ))
-            .with(tracing_subscriber::fmt::layer())
-            .init();
-
-        info!("Starting work hours web server");
-
-        let auth_config = auth::AuthConfig::default();
-        info!(
-            "Using admin credentials from environment: username={}",
-            auth_config.admin_username
+        // Initialize tracing. Parse output goes to stdout, so logs go to stderr there
+        let command = cli.command.unwrap_or_default();
+        let log_config = match &command {
+            Command::Serve(args) => args.log.clone(),
+            _ => LogArgs::default(),
+        }
+        .config(
+            std::env::var("RUST_LOG").ok().as_deref(),
+            "info,tower_http=debug",
+        );
+        let registry = tracing_subscriber::registry()
+            .with(log_config.env_filter())
+            .with(telemetry::export_layer("work_hours"));
+        if matches!(command, Command::Parse(_) | Command::Replay(_)) {
+            registry.with(log_config.fmt_layer(std::io::stderr)).init();
+        } else {
+            registry.with(log_config.fmt_layer(std::io::stdout)).init();
+        }
+        redact::set_log_redaction(
+            std::env::var("LOG_REDACTION")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
         );
-        let auth_service = Arc::new(AuthService::new(auth_config));
-
-        // Initialize database with direct Redis connection
-        let db: Arc<dyn WorkHoursDb> = match RedisDB::new() {
-            Ok(redis_db) => {
-                info!("Connected to Redis successfully");
-                Arc::new(redis_db)
-            }
-            Err(e) => {
-                // Log the error and fall back to a mock implementation
-                tracing::error!("Failed to connect to Redis: {}", e);
-                #[cfg(not(feature = "web-interface"))]
-                panic!("Redis connection failed: {}", e);
 
-                #[cfg(feature = "web-interface")]
-                {
-                    info!("Using in-memory database as fallback");
-                    Arc::new(model::InMemoryDb::default())
+        // Flush the spans still waiting for export however the command ends
+        let result: Result<(), Box<dyn std::error::Error>> = async {
+            match command {
+                Command::Serve(_) => {}
+                // One-shot maintenance commands
+                Command::MigrateEmployeeIds => {
+                    let migrated = RedisDB::new()?.migrate_employee_ids().await?;
+                    info!("Migrated {} employees to canonical ids", migrated);
+                    return Ok(());
+                }
+                Command::Parse(args) => {
+                    cli::run_parse(args).await?;
+                    return Ok(());
+                }
+                Command::Replay(args) => {
+                    cli::run_replay(args).await?;
+                    return Ok(());
                 }
             }
-        };
-
-        let state = AppState {
-            auth_service: auth_service.clone(),
-            db,
-        };
-
-        // Authentication middleware
-        async fn auth_middleware(
-            req: Request<Body>,
-            next: Next,
-            auth_service: Arc<AuthService>,
-        ) -> Result<Response, Response> {
-            // Public routes are always allowed
-            let path = req.uri().path();
-            if path == "/" || path == "/login" || path.starts_with("/assets") || path == "/health" {
-                return Ok(next.run(req).await);
-            }
-
-            // Extract parts to use with extract_token
-            let (parts, body) = req.into_parts();
 
-            // Use the extract_token function from auth module
-            match auth::extract_token(&parts) {
-                Ok(token) => {
-                    // Validate the token
-                    match auth_service.validate_token(&token) {
-                        Ok(claims) => {
-                            // Create JwtAuth to pass along
-                            let auth = auth::JwtAuth { claims };
+            info!("Starting work hours web server");
 
-                            // Reconstruct the request with auth data
-                            let mut req = Request::from_parts(parts, body);
-                            req.extensions_mut().insert(auth);
+            // Use the same locale as the bot for user-facing messages
+            rust_i18n::set_locale(&std::env::var("BOT_LOCALE").unwrap_or_else(|_| "en-US".into()));
 
-                            // User is authenticated, proceed
-                            Ok(next.run(req).await)
-                        }
-                        Err(_) => {
-                            // Invalid token, redirect to login
-                            Err(Redirect::to("/login").into_response())
-                        }
-                    }
+            // Initialize database with direct Redis connection
+            let db: Arc<dyn WorkHoursDb> = match RedisDB::new() {
+                Ok(redis_db) => {
+                    info!("Connected to Redis successfully");
+                    Arc::new(redis_db)
                 }
-                Err(_) => {
-                    // No token found, redirect to login
-                    Err(Redirect::to("/login").into_response())
+                Err(e) => {
+                    // Log the error and fall back to a mock implementation
+                    tracing::error!("Failed to connect to Redis: {}", e);
+                    info!("Using in-memory database as fallback");
+                    Arc::new(model::InMemoryDb::default())
                 }
-            }
+            };
+
+            let state = AppState::from_env(db);
+
+            // Bind to address and run server
+            let addr = listen_addr();
+            info!("Listening on {}", addr);
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            serve(listener, state, async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await?;
+            Ok(())
         }
-
-        // Create middleware with auth service
-        let auth_service_for_middleware = auth_service.clone();
-        let auth_middleware = move |req: Request<Body>, next: Next| {
-            auth_middleware(req, next, auth_service_for_middleware.clone())
-        };
-
-        // Build the router
-        let app = Router::new()
-            .route("/", get(index_handler))
-            .route("/login", get(login_form_handler).post(login_handler))
-            .route("/health", get(health_handler))
-            .route("/upload", get(upload_form_handler).post(upload_handler))
-            .route("/dashboard", get(dashboard_handler))
-            // Apply auth middleware
-            .layer(axum::middleware::from_fn(auth_middleware))
-            // Serve static files
-            .nest_service("/assets", ServeDir::new("assets"))
-            // Other middlewares
-            .layer(DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB limit
-            .layer(TraceLayer::new_for_http())
-            .layer(CorsLayer::permissive())
-            .with_state(state);
-
-        // Bind to address and run server
-        let port = std::env::var("PORT")
-            .ok()
-            .and_then(|p| p.parse::<u16>().ok())
-            .unwrap_or(3000);
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        info!("Listening on {}", addr);
-
-        // Start the server
-        let listener = tokio::net::TcpListener::bind(addr).await?;
-        axum::serve(listener, app).await?;
+        .await;
+        telemetry::shutdown();
+        result
     }
-
-    Ok(())
 }
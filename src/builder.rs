@@ -0,0 +1,130 @@
+//! Running the bot's components without Discord.
+//!
+//! Other binaries can reuse the work schedule and Google Calendar actors through their handles:
+//!
+//! ```no_run
+//! # async fn run() -> mussubotti::error::BotResult<()> {
+//! use mussubotti::builder::BotComponents;
+//! use mussubotti::config::Config;
+//!
+//! let components = BotComponents::new(Config::load()?)
+//!     .work_schedule()
+//!     .build()
+//!     .await?;
+//! if let Some(schedule) = &components.work_schedule {
+//!     println!("{:?}", schedule.get_employees().await?);
+//! }
+//! components.shutdown.await
+//! # }
+//! ```
+
+use crate::components::google_calendar::GoogleCalendar;
+use crate::components::redis_service::{spawn_store, RedisActorHandle};
+use crate::components::work_schedule::{WorkSchedule, WorkScheduleHandle};
+use crate::components::{ComponentManager, GoogleCalendarHandle};
+use crate::config::Config;
+use crate::error::BotResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Selects the components to run and the store they share
+pub struct BotComponents {
+    config: Arc<RwLock<Config>>,
+    redis_handle: Option<RedisActorHandle>,
+    work_schedule: bool,
+    google_calendar: bool,
+}
+
+/// Handles of the components a [`BotComponents`] started
+pub struct Components {
+    pub redis: RedisActorHandle,
+    pub work_schedule: Option<WorkScheduleHandle>,
+    pub google_calendar: Option<GoogleCalendarHandle>,
+    /// Shuts the components down and then the store
+    pub shutdown: Pin<Box<dyn Future<Output = BotResult<()>> + Send>>,
+}
+
+impl BotComponents {
+    /// Start from a config; nothing is registered until selected
+    pub fn new(config: Config) -> Self {
+        Self::with_shared_config(Arc::new(RwLock::new(config)))
+    }
+
+    /// Start from a config shared with the caller
+    pub fn with_shared_config(config: Arc<RwLock<Config>>) -> Self {
+        Self {
+            config,
+            redis_handle: None,
+            work_schedule: false,
+            google_calendar: false,
+        }
+    }
+
+    /// Use an existing store, such as a fake one, instead of the one the config selects
+    pub fn redis(mut self, redis_handle: RedisActorHandle) -> Self {
+        self.redis_handle = Some(redis_handle);
+        self
+    }
+
+    /// Run the work schedule actor
+    pub fn work_schedule(mut self) -> Self {
+        self.work_schedule = true;
+        self
+    }
+
+    /// Run the Google Calendar actor
+    pub fn google_calendar(mut self) -> Self {
+        self.google_calendar = true;
+        self
+    }
+
+    /// Start the store and the selected components. Components the config disables are left
+    /// out, and so are their handles. No schedulers run, since they post to Discord.
+    pub async fn build(self) -> BotResult<Components> {
+        let redis_handle = match self.redis_handle {
+            Some(redis_handle) => redis_handle,
+            None => spawn_store(self.config.clone()).await?,
+        };
+
+        let mut manager = ComponentManager::new(self.config.clone());
+        if self.work_schedule {
+            manager.register(WorkSchedule::new);
+        }
+        if self.google_calendar {
+            manager.register(GoogleCalendar::new);
+        }
+        manager
+            .create_all(self.config.clone(), redis_handle.clone())
+            .await?;
+
+        let work_schedule = match manager.get_component_by_name("work_schedule") {
+            Some(component) => match component.as_any().downcast_ref::<WorkSchedule>() {
+                Some(work_schedule) => work_schedule.get_handle().await,
+                None => None,
+            },
+            None => None,
+        };
+        let google_calendar = match manager.get_component_by_name("google_calendar") {
+            Some(component) => match component.as_any().downcast_ref::<GoogleCalendar>() {
+                Some(google_calendar) => google_calendar.get_handle().await,
+                None => None,
+            },
+            None => None,
+        };
+
+        let store = redis_handle.clone();
+        let shutdown = Box::pin(async move {
+            manager.shutdown_all().await?;
+            store.shutdown().await
+        });
+
+        Ok(Components {
+            redis: redis_handle,
+            work_schedule,
+            google_calendar,
+            shutdown,
+        })
+    }
+}
@@ -0,0 +1,264 @@
+use crate::components::redis_service::{spawn_store, RedisActorHandle};
+use crate::components::{google_calendar, work_schedule, EventBus};
+use crate::config::Config;
+use crate::error::{other_error, BotResult};
+use crate::utils::logging::LogArgs;
+use crate::utils::notifier::Sinks;
+use crate::utils::pending::{send_with_retry, RETRY_DELAY, SEND_ATTEMPTS};
+use crate::utils::scheduler::{
+    claim_in_redis, held_for_maintenance, release_claim, send_with_http, NotificationType,
+};
+use crate::utils::time::get_weekly_date_range;
+use chrono::Local;
+use clap::Parser;
+use poise::serenity_prelude as serenity;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+/// Discord bot for calendar events and work schedules
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Send one notification without connecting to the gateway and exit, e.g. `work:weekly`
+    /// or `calendar:daily`
+    #[arg(long, value_name = "COMPONENT:TYPE")]
+    pub send_notification: Option<OneShotNotification>,
+    /// Check the running bot's health for a container liveness probe, exiting 1 if it's
+    /// unhealthy
+    #[arg(long, conflicts_with = "send_notification")]
+    pub probe: bool,
+    /// Read the enabled components from this TOML file instead of config/components.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Also serve the work hours web app on `PORT`, sharing the bot's store
+    #[arg(long, conflicts_with_all = ["send_notification", "probe"])]
+    pub with_web: bool,
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// Component a one-shot notification is sent for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationComponent {
+    Work,
+    Calendar,
+}
+
+impl NotificationComponent {
+    /// Component type the schedulers claim notifications under
+    pub fn component_type(&self) -> &'static str {
+        match self {
+            NotificationComponent::Work => "work_schedule",
+            NotificationComponent::Calendar => "google_calendar",
+        }
+    }
+}
+
+/// Notification requested with `--send-notification`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OneShotNotification {
+    pub component: NotificationComponent,
+    pub notification_type: NotificationType,
+}
+
+impl FromStr for OneShotNotification {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (component, notification_type) = value
+            .split_once(':')
+            .ok_or_else(|| format!("expected COMPONENT:TYPE, got '{value}'"))?;
+
+        let component = match component.trim().to_lowercase().as_str() {
+            "work" => NotificationComponent::Work,
+            "calendar" => NotificationComponent::Calendar,
+            other => return Err(format!("unknown component '{other}', use work or calendar")),
+        };
+        let notification_type = match notification_type.trim().to_lowercase().as_str() {
+            "daily" => NotificationType::Daily,
+            "weekly" => NotificationType::Weekly,
+            other => return Err(format!("unknown type '{other}', use daily or weekly")),
+        };
+
+        Ok(Self {
+            component,
+            notification_type,
+        })
+    }
+}
+
+/// Send a single notification to the configured channel, e.g. from a Kubernetes CronJob.
+///
+/// Only Redis and the component's actor are started, and messages go through a standalone
+/// HTTP client. The notification is claimed in Redis like the schedulers do, so a running bot
+/// skips it once this has sent it.
+pub async fn send_one_shot(
+    config: Arc<RwLock<Config>>,
+    notification: OneShotNotification,
+) -> BotResult<()> {
+    let (token, channel_id) = {
+        let config = config.read().await;
+        crate::utils::i18n::set_locale(&config.bot_locale);
+        crate::utils::redact::set_log_redaction(config.log_redaction);
+        (config.discord_token.clone(), config.calendar_channel_id)
+    };
+
+    let redis_handle = spawn_store(Arc::clone(&config)).await?;
+    let result = claim_and_send(&config, &redis_handle, &token, channel_id, &notification).await;
+    let _ = redis_handle.shutdown().await;
+    result
+}
+
+/// Claim the notification for the current day or week and send it, releasing the claim on failure
+async fn claim_and_send(
+    config: &Arc<RwLock<Config>>,
+    redis_handle: &RedisActorHandle,
+    token: &str,
+    channel_id: u64,
+    notification: &OneShotNotification,
+) -> BotResult<()> {
+    let component_type = notification.component.component_type();
+    let notification_type = &notification.notification_type;
+
+    let now = Local::now();
+    let date = match notification_type {
+        NotificationType::Daily => now.format("%Y-%m-%d").to_string(),
+        NotificationType::Weekly => {
+            get_weekly_date_range(&now, config.read().await.week_starts_on).0
+        }
+    };
+
+    // Left unclaimed for the bot to send once maintenance ends
+    if held_for_maintenance(
+        redis_handle,
+        component_type,
+        notification_type,
+        &date,
+        channel_id,
+    )
+    .await
+    {
+        return Ok(());
+    }
+
+    if !claim_in_redis(redis_handle, component_type, notification_type, &date).await? {
+        info!(
+            "[{}] {:?} notification for {} was already sent",
+            component_type, notification_type, date
+        );
+        return Ok(());
+    }
+
+    let bus = EventBus::new();
+    let handler = match notification.component {
+        NotificationComponent::Work => work_schedule::notification_handler(
+            work_schedule::WorkScheduleHandle::new(Arc::clone(config), redis_handle.clone(), bus),
+            redis_handle.clone(),
+            Arc::clone(config),
+        ),
+        NotificationComponent::Calendar => {
+            google_calendar::notification_handler(
+                google_calendar::GoogleCalendarHandle::new(
+                    Arc::clone(config),
+                    redis_handle.clone(),
+                    bus,
+                ),
+                redis_handle.clone(),
+                Arc::clone(config),
+            )
+            .await
+        }
+    };
+
+    let http = Arc::new(serenity::Http::new(token));
+    let result = send_with_retry(
+        |sinks| {
+            send_with_http(
+                &http,
+                handler.as_ref(),
+                notification_type,
+                channel_id,
+                sinks,
+            )
+        },
+        &mut Sinks::all(),
+        SEND_ATTEMPTS,
+        RETRY_DELAY,
+    )
+    .await;
+
+    if let Err(e) = result {
+        error!(
+            "[{}] Failed to send {:?} notification: {}",
+            component_type, notification_type, e
+        );
+        if let Err(e) = release_claim(redis_handle, component_type, notification_type, &date).await
+        {
+            warn!("[{}] Failed to release the claim: {}", component_type, e);
+        }
+        return Err(other_error(&format!(
+            "Sending the {component_type} {notification_type:?} notification failed: {e}"
+        )));
+    }
+
+    info!(
+        "[{}] Sent {:?} notification for {}",
+        component_type, notification_type, date
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("mussubotti").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_send_notification_flag() {
+        assert_eq!(parse(&[]).unwrap().send_notification, None);
+
+        let cli = parse(&["--send-notification", "work:weekly"]).unwrap();
+        assert_eq!(
+            cli.send_notification,
+            Some(OneShotNotification {
+                component: NotificationComponent::Work,
+                notification_type: NotificationType::Weekly,
+            })
+        );
+
+        let cli = parse(&["--send-notification=Calendar:Daily"]).unwrap();
+        let notification = cli.send_notification.unwrap();
+        assert_eq!(notification.component.component_type(), "google_calendar");
+        assert_eq!(notification.notification_type, NotificationType::Daily);
+    }
+
+    #[test]
+    fn test_probe_flag() {
+        assert!(!parse(&[]).unwrap().probe);
+        assert!(parse(&["--probe"]).unwrap().probe);
+        assert!(parse(&["--probe", "--send-notification", "work:daily"]).is_err());
+    }
+
+    #[test]
+    fn test_with_web_flag() {
+        assert!(!parse(&[]).unwrap().with_web);
+        assert!(parse(&["--with-web"]).unwrap().with_web);
+        assert!(parse(&["--with-web", "--probe"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_send_notification_flag() {
+        for value in ["work", "work:monthly", "email:daily", ""] {
+            assert!(
+                parse(&["--send-notification", value]).is_err(),
+                "{value} should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Combined mode: the work hours web app served from the bot's process with `--with-web`.
+//!
+//! The app reads and writes through the bot's Redis actor instead of opening its own
+//! connection, so both run against one store, and its health check includes the gateway.
+//! Schedules it stores are published on the bot's event bus, so posted notifications follow.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::EventBus;
+use crate::probe::{shard_states, GATEWAY_POLL_INTERVAL};
+use crate::web::db::RedisDB;
+use crate::web::{listen_addr, serve, AppState};
+use poise::serenity_prelude as serenity;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// The web app's state on the bot's store and bus, reporting `gateway_connected` in its health
+/// check
+pub fn web_state(
+    redis_handle: RedisActorHandle,
+    bus: EventBus,
+    gateway_connected: Arc<AtomicBool>,
+) -> AppState {
+    AppState {
+        gateway_connected: Some(gateway_connected),
+        bus: Some(bus),
+        ..AppState::from_env(Arc::new(RedisDB::with_handle(redis_handle)))
+    }
+}
+
+/// Serve the web app from `listener` until the bot shuts down
+pub fn spawn_web_server(
+    listener: TcpListener,
+    state: AppState,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let stopped = async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        };
+        match serve(listener, state, stopped).await {
+            Ok(()) => info!("Web server stopped"),
+            Err(e) => error!("Web server failed: {}", e),
+        }
+    })
+}
+
+/// Bind the web app's address and serve it there, or log why it can't be
+pub async fn start_web_server(
+    state: AppState,
+    shutdown: watch::Receiver<bool>,
+) -> Option<JoinHandle<()>> {
+    let addr = listen_addr();
+    match TcpListener::bind(addr).await {
+        Ok(listener) => {
+            info!("Web server listening on {}", addr);
+            Some(spawn_web_server(listener, state, shutdown))
+        }
+        Err(e) => {
+            error!("Failed to start the web server on {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+/// Keep `connected` telling whether every shard is connected to the gateway, until shutdown
+pub fn spawn_gateway_watch(
+    shard_manager: Arc<serenity::ShardManager>,
+    connected: Arc<AtomicBool>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut poll = tokio::time::interval(GATEWAY_POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {
+                    let shards = shard_states(&shard_manager).await;
+                    let all_connected =
+                        !shards.is_empty() && shards.iter().all(|(_, connected, _)| *connected);
+                    connected.store(all_connected, Ordering::Relaxed);
+                }
+                _ = shutdown.changed() => return,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::event_bus::EventBus;
+    use crate::components::work_schedule::uploads::{
+        UploadPipeline, UploadResponse, WorkHoursUploads,
+    };
+    use crate::components::work_schedule::WorkScheduleHandle;
+    use crate::config::Config;
+    use crate::web::auth::{AuthConfig, AuthService};
+    use crate::web::parser::{convert_to_work_schedule, extract_json_array};
+    use tokio::sync::RwLock;
+
+    const CLEAN_WEEK: &str = include_str!("../tests/fixtures/schedule_clean_week.json");
+
+    fn test_config() -> Arc<RwLock<Config>> {
+        Arc::new(RwLock::new(Config::for_tests()))
+    }
+
+    /// A schedule imported through the web app is read back by the bot from the same store
+    #[tokio::test]
+    async fn test_web_import_is_read_by_the_bot() {
+        let redis_handle = RedisActorHandle::fake();
+        let state = web_state(
+            redis_handle.clone(),
+            EventBus::default(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let token = state
+            .auth_service
+            .generate_token("admin", None, "admin")
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (shutdown, shutdown_recv) = watch::channel(false);
+        let server = spawn_web_server(listener, state, shutdown_recv);
+
+        let days = extract_json_array(CLEAN_WEEK).unwrap();
+        let schedule = convert_to_work_schedule("Anna", days).unwrap();
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{base}/api/v1/schedule/Anna/import"))
+            .bearer_auth(&token)
+            .json(&schedule.days)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let health: serde_json::Value = client
+            .get(format!("{base}/health"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["redis"], "ok");
+        assert_eq!(health["gateway"], "ok");
+
+        // The server finishes once the bot's background tasks are told to stop
+        shutdown.send(true).unwrap();
+        server.await.unwrap();
+
+        let handle = WorkScheduleHandle::new(test_config(), redis_handle, EventBus::new());
+        let anna = handle
+            .get_schedule_for_date_range("Anna", "2025-03-10", "2025-03-16")
+            .await
+            .unwrap();
+        assert_eq!(anna.schedule.len(), 7);
+        assert_eq!(anna.schedule[0].shifts[0].start.as_deref(), Some("07:00"));
+        assert_eq!(anna.schedule[4].shifts[0].start.as_deref(), Some("12:00"));
+        assert!(anna.schedule[5].is_day_off);
+    }
+
+    /// Channel uploads reach the app in the same process with the static service token
+    #[tokio::test]
+    async fn test_channel_uploads_use_the_service_token() {
+        let mut state = web_state(
+            RedisActorHandle::fake(),
+            EventBus::default(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        state.auth_service = Arc::new(AuthService::new(AuthConfig {
+            service_token: Some("service_secret".to_string()),
+            ..AuthConfig::default()
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (shutdown, shutdown_recv) = watch::channel(false);
+        let server = spawn_web_server(listener, state, shutdown_recv);
+
+        let mut config = Config::for_tests();
+        config.work_hours_url = base;
+        config.work_hours_api_token = "service_secret".to_string();
+        // Authenticated, the upload is checked and turned down for not being an image
+        let response = WorkHoursUploads::from_config(&config)
+            .upload("Anna", b"not an image".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            UploadResponse::Rejected {
+                code: "bad_format".to_string(),
+                detail: None
+            }
+        );
+
+        config.work_hours_api_token = "expired".to_string();
+        assert!(WorkHoursUploads::from_config(&config)
+            .upload("Anna", b"not an image".to_vec())
+            .await
+            .is_err());
+
+        shutdown.send(true).unwrap();
+        server.await.unwrap();
+    }
+}
@@ -1,10 +1,29 @@
-use crate::commands::{CommandResult, Context};
+use crate::commands::{calendar_enabled, calendar_rate_limit, send_view, CommandResult, Context};
+use crate::components::google_calendar::models::visible_events;
+use crate::components::google_calendar::oncall::{get_on_call, linked_users, oncall_entry};
+use crate::components::google_calendar::time::EventWindow;
+use crate::components::google_calendar::{render, GoogleCalendar};
+use crate::components::EventBus;
 use crate::components::GoogleCalendarHandle;
-use crate::error::google_calendar_error;
+use crate::config::Config;
+use crate::error::{google_calendar_error, BotResult};
+use crate::guild_config::get_guild_config;
+use crate::user_preferences::{
+    get_user_preferences, linked_employees, resolve_timezone, TimezoneSource,
+};
+use crate::utils::render::View;
 use chrono_tz::Tz;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
 
 /// Get this week's calendar events
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "calendar_enabled",
+    check = "calendar_rate_limit"
+)]
 pub async fn this_week(
     ctx: Context<'_>,
     #[description = "Optional timezone (e.g. 'Europe/London')"] timezone: Option<String>,
@@ -21,72 +40,56 @@ pub async fn this_week(
     let config = ctx.data().config.clone();
 
     // Get Google Calendar handle
-    let handle = if let Some(cm) = &ctx.data().component_manager {
-        // Try to get the actual GoogleCalendar component from ComponentManager
-        if let Some(component) = cm.get_component_by_name("google_calendar") {
-            // Try to downcast to get the actual Google Calendar component
-            if let Some(calendar_component) = component
-                .as_any()
-                .downcast_ref::<crate::components::google_calendar::GoogleCalendar>(
-            ) {
-                tracing::debug!("Using Google Calendar component from ComponentManager");
-                // Get the handle from the component
-                if let Some(handle) = calendar_component.get_handle().await {
-                    handle
-                } else {
-                    // Create a new handle if we couldn't get one
-                    tracing::debug!("No handle in Google Calendar component, creating new one");
-                    let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-                    GoogleCalendarHandle::new(config.clone(), redis_handle)
-                }
-            } else {
-                tracing::debug!("Could not downcast Google Calendar component");
-                let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-                GoogleCalendarHandle::new(config.clone(), redis_handle)
-            }
-        } else {
-            tracing::debug!("Google Calendar component not found in ComponentManager");
-            let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-            GoogleCalendarHandle::new(config.clone(), redis_handle)
-        }
-    } else {
-        tracing::debug!("ComponentManager not available, creating standalone handle");
-        let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-        GoogleCalendarHandle::new(config.clone(), redis_handle)
-    };
+    let handle = get_calendar_handle(ctx.data().component_manager.as_ref(), config.clone()).await;
 
-    // Get timezone from user input or default
-    let timezone_str = match &timezone {
-        Some(tz) => tz.clone(),
-        None => {
-            let config_read = config.read().await;
-            config_read.timezone.clone()
-        }
-    };
+    let (timezone, source) = command_timezone(ctx, timezone.as_deref()).await?;
 
-    // Parse timezone
-    let timezone: Tz = match timezone_str.parse() {
-        Ok(tz) => tz,
-        Err(_) => {
+    // Get upcoming events and format them
+    let events = match handle.get_upcoming_events().await {
+        Ok(events) => visible_events(events, shows_private(ctx).await),
+        Err(e) => {
             // Simply send a new message instead of editing
-            let error_msg = t!("calendar_invalid_timezone", timezone = timezone_str);
+            let error_msg = t!("calendar_error_fetching", error = e.to_string());
             ctx.send(
                 poise::CreateReply::default()
                     .content(error_msg)
                     .ephemeral(true),
             )
             .await?;
-            return Err(google_calendar_error(&format!(
-                "Invalid timezone: {timezone_str}"
-            )));
+            return Err(e);
         }
     };
 
-    // Get upcoming events and format them
+    let today = chrono::Utc::now().with_timezone(&timezone).date_naive();
+    let title = t!("calendar_this_week_title", timezone = timezone.name());
+    let view = render::week_events(title.to_string(), &events, &timezone, today)
+        .footer(timezone_footer(&timezone, source));
+
+    let _ = response.delete(ctx).await;
+    send_view(ctx, view, false).await?;
+
+    Ok(())
+}
+
+/// Get the next upcoming calendar event
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "calendar_enabled",
+    check = "calendar_rate_limit"
+)]
+pub async fn next(
+    ctx: Context<'_>,
+    #[description = "Optional timezone (e.g. 'Europe/London')"] timezone: Option<String>,
+) -> CommandResult {
+    let config = ctx.data().config.clone();
+    let handle = get_calendar_handle(ctx.data().component_manager.as_ref(), config.clone()).await;
+
+    let (timezone, source) = command_timezone(ctx, timezone.as_deref()).await?;
+
     let events = match handle.get_upcoming_events().await {
-        Ok(events) => events,
+        Ok(events) => visible_events(events, shows_private(ctx).await),
         Err(e) => {
-            // Simply send a new message instead of editing
             let error_msg = t!("calendar_error_fetching", error = e.to_string());
             ctx.send(
                 poise::CreateReply::default()
@@ -98,98 +101,243 @@ pub async fn this_week(
         }
     };
 
-    // Format events into a weekly view
+    // Events come ordered by start time, so the first one that hasn't started yet is next
     let now = chrono::Utc::now().with_timezone(&timezone);
-    let week_start = now.date_naive();
-    let week_end = week_start + chrono::Duration::days(7);
-
-    // Filter events for this week
-    let mut weekly_events = events
-        .iter()
-        .filter(|e| {
-            if let Some(date_time) = &e.start_date_time {
-                // date_time is a string in RFC3339 format
-                if let Ok(event_time) = chrono::DateTime::parse_from_rfc3339(date_time) {
-                    let event_date = event_time.with_timezone(&timezone).date_naive();
-                    return event_date >= week_start && event_date < week_end;
-                }
-            } else if let Some(date) = &e.start_date {
-                // date is a string in YYYY-MM-DD format
-                if let Ok(event_date) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
-                    return event_date >= week_start && event_date < week_end;
-                }
-            }
-            false
-        })
-        .collect::<Vec<_>>();
-
-    // Sort by date
-    weekly_events.sort_by(|a, b| {
-        let a_date = get_event_date(a, &timezone);
-        let b_date = get_event_date(b, &timezone);
-        a_date.cmp(&b_date)
+    let next_event = events.iter().find(|event| {
+        if let Some(date_time) = &event.start_date_time {
+            chrono::DateTime::parse_from_rfc3339(date_time)
+                .map(|dt| dt.with_timezone(&timezone) >= now)
+                .unwrap_or(false)
+        } else {
+            render::event_date(event, &timezone) > now.date_naive()
+        }
     });
 
-    let mut message = t!("calendar_this_week_title", timezone = timezone_str).to_string();
-    message.push_str("\n\n");
+    let Some(event) = next_event else {
+        send_view(
+            ctx,
+            View::info(
+                &t!("calendar_next_title"),
+                &t!("calendar_no_upcoming_events"),
+            )
+            .footer(timezone_footer(&timezone, source)),
+            false,
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let view = render::next_event(event, &timezone).footer(timezone_footer(&timezone, source));
+    send_view(ctx, view, false).await?;
 
-    if weekly_events.is_empty() {
-        message.push_str(&t!("calendar_no_events"));
-    } else {
-        let mut current_date = None;
+    Ok(())
+}
+
+/// Get yesterday's calendar events
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "calendar_enabled",
+    check = "calendar_rate_limit"
+)]
+pub async fn eilen(
+    ctx: Context<'_>,
+    #[description = "Optional timezone (e.g. 'Europe/London')"] timezone: Option<String>,
+) -> CommandResult {
+    let config = ctx.data().config.clone();
+    let handle = get_calendar_handle(ctx.data().component_manager.as_ref(), config.clone()).await;
 
-        for event in weekly_events {
-            let event_date = get_event_date(event, &timezone);
+    let (timezone, source) = command_timezone(ctx, timezone.as_deref()).await?;
 
-            if current_date != Some(event_date) {
-                current_date = Some(event_date);
-                if let Some(date) = current_date {
-                    message.push_str(&format!("\n**{}**\n", date.format("%A, %B %d")));
-                }
-            }
+    // From yesterday's midnight until today's in the chosen timezone
+    let today = chrono::Utc::now().with_timezone(&timezone).date_naive();
+    let yesterday = today - chrono::Duration::days(1);
+    let midnight = |date: chrono::NaiveDate| {
+        date.and_time(chrono::NaiveTime::MIN)
+            .and_local_timezone(timezone)
+            .earliest()
+            .map(|dt| dt.to_utc())
+    };
+    let (Some(start), Some(end)) = (midnight(yesterday), midnight(today)) else {
+        return Err(google_calendar_error(
+            "Failed to calculate yesterday's range",
+        ));
+    };
 
-            let title = event
-                .summary
-                .clone()
-                .unwrap_or(t!("calendar_unnamed_event").to_string());
-            let start_time = if let Some(date_time) = &event.start_date_time {
-                // date_time is a string in RFC3339 format
-                if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date_time) {
-                    format!("{}", dt.with_timezone(&timezone).format("%H:%M"))
-                } else {
-                    t!("calendar_unknown_time").to_string()
-                }
-            } else {
-                t!("calendar_all_day").to_string()
-            };
+    let events = match handle.get_events_in_range(EventWindow { start, end }).await {
+        Ok(events) => visible_events(events, shows_private(ctx).await),
+        Err(e) => {
+            let error_msg = t!("calendar_error_fetching", error = e.to_string());
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(error_msg)
+                    .ephemeral(true),
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let title = t!("calendar_yesterday_title", timezone = timezone.name());
+    let view = render::yesterday_events(title.to_string(), &events, &timezone, yesterday)
+        .footer(timezone_footer(&timezone, source));
+    send_view(ctx, view, false).await?;
+
+    Ok(())
+}
+
+/// Show who's on call today, mentioning the users linked to them
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "päivystys",
+    check = "calendar_enabled",
+    check = "calendar_rate_limit"
+)]
+pub async fn paivystys(ctx: Context<'_>) -> CommandResult {
+    let config = ctx.data().config.clone();
+    if config.read().await.oncall_calendar_id.is_none() {
+        send_view(
+            ctx,
+            View::warning(&t!("oncall_title"), &t!("oncall_not_configured")),
+            true,
+        )
+        .await?;
+        return Ok(());
+    }
 
-            message.push_str(&format!("• **{start_time}** - {title}\n"));
+    let handle = get_calendar_handle(ctx.data().component_manager.as_ref(), config).await;
+    let on_call = match get_on_call(&handle, chrono::Local::now().date_naive()).await {
+        Ok(on_call) => on_call,
+        Err(e) => {
+            let error_msg = t!("calendar_error_fetching", error = e.to_string());
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(error_msg)
+                    .ephemeral(true),
+            )
+            .await?;
+            return Err(e);
         }
+    };
+
+    if on_call.is_empty() {
+        send_view(
+            ctx,
+            View::info(&t!("oncall_title"), &t!("oncall_nobody")),
+            false,
+        )
+        .await?;
+        return Ok(());
     }
 
-    // Update with the calendar data by deleting and sending a new message
-    let _ = response.delete(ctx).await;
-    ctx.say(message).await?;
+    // Without the links the names are still worth showing
+    let linked = linked_employees(&ctx.data().redis())
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to read the users linked to employees: {}", e);
+            Vec::new()
+        });
+    let lines: Vec<String> = on_call
+        .iter()
+        .map(|on_call| oncall_entry(on_call, &linked_users(&on_call.name, &linked)))
+        .collect();
+    send_view(
+        ctx,
+        View::info(&t!("oncall_title"), &lines.join("\n")),
+        false,
+    )
+    .await?;
 
     Ok(())
 }
 
-// Helper function to get event date
-fn get_event_date(
-    event: &crate::components::google_calendar::models::CalendarEvent,
-    timezone: &Tz,
-) -> chrono::NaiveDate {
-    if let Some(date_time) = &event.start_date_time {
-        // date_time is a string in RFC3339 format
-        if let Ok(event_time) = chrono::DateTime::parse_from_rfc3339(date_time) {
-            return event_time.with_timezone(timezone).date_naive();
+/// Whether private events may be shown with their details in the channel the command was
+/// used in
+async fn shows_private(ctx: Context<'_>) -> bool {
+    ctx.data()
+        .config
+        .read()
+        .await
+        .shows_private_event_details(ctx.channel_id().get())
+}
+
+/// Resolve the timezone for a calendar command, telling the user when their parameter is invalid
+async fn command_timezone(
+    ctx: Context<'_>,
+    parameter: Option<&str>,
+) -> BotResult<(Tz, TimezoneSource)> {
+    let redis_handle = ctx.data().redis();
+    let user = get_user_preferences(&redis_handle, ctx.author().id.get())
+        .await
+        .timezone;
+    let guild = match ctx.guild_id() {
+        Some(guild_id) => {
+            get_guild_config(&redis_handle, guild_id.get())
+                .await
+                .timezone
+        }
+        None => None,
+    };
+    let global = ctx.data().config.read().await.timezone.clone();
+
+    match resolve_timezone(parameter, user.as_deref(), guild.as_deref(), &global) {
+        Ok(resolved) => Ok(resolved),
+        Err(name) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(t!("calendar_invalid_timezone", timezone = name))
+                    .ephemeral(true),
+            )
+            .await?;
+            Err(google_calendar_error(&format!("Invalid timezone: {name}")))
         }
-    } else if let Some(date) = &event.start_date {
-        // date is a string in YYYY-MM-DD format
-        if let Ok(event_date) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
-            return event_date;
+    }
+}
+
+/// Footer naming the timezone and where it came from
+fn timezone_footer(timezone: &Tz, source: TimezoneSource) -> String {
+    t!(
+        "timezone_footer",
+        timezone = timezone.name(),
+        source = t!(source.locale_key())
+    )
+    .to_string()
+}
+
+/// Helper to get the Google Calendar handle
+pub async fn get_calendar_handle(
+    component_manager: Option<&Arc<crate::components::ComponentManager>>,
+    config: Arc<RwLock<Config>>,
+) -> GoogleCalendarHandle {
+    // Try to get the handle from the component manager
+    if let Some(component_manager) = component_manager {
+        if let Some(component) = component_manager.get_component_by_name("google_calendar") {
+            // Try to downcast to get the actual Google Calendar component
+            if let Some(calendar_component) = component.as_any().downcast_ref::<GoogleCalendar>() {
+                debug!("Using Google Calendar component from ComponentManager");
+                // Get the handle from the component
+                if let Some(handle) = calendar_component.get_handle().await {
+                    handle
+                } else {
+                    // Create a new handle if we couldn't get one
+                    debug!("No handle in Google Calendar component, creating new one");
+                    let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
+                    GoogleCalendarHandle::new(config.clone(), redis_handle, component_manager.bus())
+                }
+            } else {
+                debug!("Could not downcast Google Calendar component");
+                let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
+                GoogleCalendarHandle::new(config.clone(), redis_handle, component_manager.bus())
+            }
+        } else {
+            debug!("Google Calendar component not found in ComponentManager");
+            let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
+            GoogleCalendarHandle::new(config.clone(), redis_handle, component_manager.bus())
         }
+    } else {
+        debug!("ComponentManager not available, creating standalone handle");
+        let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
+        GoogleCalendarHandle::new(config.clone(), redis_handle, EventBus::new())
     }
-    // Default to today if we can't parse the date
-    chrono::Utc::now().with_timezone(timezone).date_naive()
 }
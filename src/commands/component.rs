@@ -0,0 +1,107 @@
+use crate::commands::{
+    create_error_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::leader::Leadership;
+use rust_i18n::t;
+
+/// Manage the bot's components
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("restart"),
+    subcommand_required
+)]
+pub async fn component(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Registered component names starting with what was typed
+async fn autocomplete_component(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let Some(component_manager) = &ctx.data().component_manager else {
+        return Vec::new();
+    };
+    component_manager
+        .component_states()
+        .into_iter()
+        .filter(|(name, enabled)| *enabled && name.starts_with(partial))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Restart a component without restarting the bot, e.g. after fixing its credentials
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn restart(
+    ctx: Context<'_>,
+    #[description = "Component to restart"]
+    #[autocomplete = "autocomplete_component"]
+    name: String,
+) -> CommandResult {
+    ctx.defer_ephemeral().await?;
+    let title = t!("component_restart_title");
+    let name = name.trim();
+
+    let report = match &ctx.data().component_manager {
+        Some(component_manager) => {
+            // Only the leader runs background tasks, so a follower restarts without them
+            let leading = ctx
+                .data()
+                .leadership
+                .as_ref()
+                .is_none_or(Leadership::is_leader);
+            component_manager
+                .restart(name, ctx.data().redis(), leading)
+                .await
+        }
+        None => None,
+    };
+
+    let embed = match report {
+        None => {
+            let names: Vec<&str> = ctx
+                .data()
+                .component_manager
+                .iter()
+                .flat_map(|manager| manager.component_states())
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name)
+                .collect();
+            create_warning_embed(
+                &title,
+                &t!(
+                    "component_restart_unknown",
+                    component = name,
+                    components = names.join(", ")
+                ),
+            )
+        }
+        Some(report) => {
+            let millis = report.elapsed.as_millis();
+            let mut message = match &report.init_error {
+                None => t!("component_restart_done", component = name, millis = millis),
+                Some(error) => t!(
+                    "component_restart_failed",
+                    component = name,
+                    millis = millis,
+                    error = error
+                ),
+            }
+            .to_string();
+            if let Some(error) = &report.shutdown_error {
+                message.push_str(&format!(
+                    "\n{}",
+                    t!("component_restart_shutdown_error", error = error)
+                ));
+            }
+            if report.succeeded() {
+                create_success_embed(&title, &message)
+            } else {
+                create_error_embed(&title, &message)
+            }
+        }
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
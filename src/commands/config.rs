@@ -0,0 +1,237 @@
+use crate::commands::{create_success_embed, create_warning_embed, CommandResult, Context};
+use crate::guild_config::{get_guild_config, set_guild_config};
+use crate::prefix::{validate_prefix, MAX_PREFIX_LEN};
+use crate::theme::{
+    parse_hex_color, validate_footer, validate_thumbnail_url, Theme, ThemeColor, ThemeConfig,
+};
+use rust_i18n::t;
+
+/// Change this server's settings
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("set"),
+    subcommand_required
+)]
+pub async fn config(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Set a server setting
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("prefix", "theme"),
+    subcommand_required
+)]
+pub async fn set(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Set the prefix for text commands in this server, or go back to the default by leaving it out
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn prefix(
+    ctx: Context<'_>,
+    #[description = "New prefix, up to 5 characters without spaces"] prefix: Option<String>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let prefix = match prefix {
+        Some(prefix) => match validate_prefix(&prefix) {
+            Some(prefix) => Some(prefix),
+            None => {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .embed(create_warning_embed(
+                            &t!("config_title"),
+                            &t!("config_prefix_invalid", max = MAX_PREFIX_LEN),
+                        ))
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let redis_handle = ctx.data().redis();
+    let mut config = get_guild_config(&redis_handle, guild_id.get()).await;
+    config.prefix = prefix.clone();
+    set_guild_config(&redis_handle, guild_id.get(), &config).await?;
+    ctx.data().prefix_cache.invalidate(guild_id.get()).await;
+
+    let message = match prefix {
+        Some(prefix) => t!("config_prefix_set", prefix = prefix),
+        None => t!(
+            "config_prefix_reset",
+            prefix = ctx.data().config.read().await.command_prefix
+        ),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("config_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Apply the given theme settings on top of `current`, checking each one. Colors are stored as
+/// `#RRGGBB`.
+fn updated_theme(
+    current: &ThemeConfig,
+    colors: [(ThemeColor, Option<String>); 4],
+    thumbnail: Option<String>,
+    footer: Option<String>,
+) -> Result<ThemeConfig, String> {
+    let mut theme = current.clone();
+    for (role, value) in colors {
+        let Some(value) = value else {
+            continue;
+        };
+        let color = Some(format!("#{:06X}", parse_hex_color(&value)?));
+        match role {
+            ThemeColor::Primary => theme.primary = color,
+            ThemeColor::Success => theme.success = color,
+            ThemeColor::Warning => theme.warning = color,
+            ThemeColor::Error => theme.error = color,
+        }
+    }
+    if let Some(thumbnail) = thumbnail {
+        theme.thumbnail_url = Some(validate_thumbnail_url(&thumbnail)?);
+    }
+    if let Some(footer) = footer {
+        theme.footer_text = Some(validate_footer(&footer)?);
+    }
+    Ok(theme)
+}
+
+/// Set the colors, logo and footer of this server's messages
+///
+/// Settings left out are kept; `reset` goes back to the default look first.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn theme(
+    ctx: Context<'_>,
+    #[description = "Accent of info messages and notifications, e.g. #1E90FF"] primary: Option<
+        String,
+    >,
+    #[description = "Color of success messages"] success: Option<String>,
+    #[description = "Color of warnings"] warning: Option<String>,
+    #[description = "Color of errors"] error: Option<String>,
+    #[description = "URL of a small logo shown in notifications"] thumbnail: Option<String>,
+    #[description = "Footer text of notifications"] footer: Option<String>,
+    #[description = "Go back to the default look before applying the rest"] reset: Option<bool>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let redis_handle = ctx.data().redis();
+    let mut config = get_guild_config(&redis_handle, guild_id.get()).await;
+    if reset.unwrap_or(false) {
+        config.theme = ThemeConfig::default();
+    }
+
+    let colors = [
+        (ThemeColor::Primary, primary),
+        (ThemeColor::Success, success),
+        (ThemeColor::Warning, warning),
+        (ThemeColor::Error, error),
+    ];
+    config.theme = match updated_theme(&config.theme, colors, thumbnail, footer) {
+        Ok(theme) => theme,
+        Err(e) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .embed(create_warning_embed(
+                        &t!("config_title"),
+                        &t!("config_theme_invalid", error = e),
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    set_guild_config(&redis_handle, guild_id.get(), &config).await?;
+
+    // Show the result in the new theme
+    let theme = Theme::from_config(&config.theme);
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(theme.brand(theme.embed(
+                ThemeColor::Success,
+                &t!("config_title"),
+                &t!("config_theme_set"),
+            )))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_settings_are_validated_and_merged() {
+        let current = ThemeConfig {
+            primary: Some("#112233".to_string()),
+            footer_text: Some("Myymälä".to_string()),
+            ..Default::default()
+        };
+
+        let updated = updated_theme(
+            &current,
+            [
+                (ThemeColor::Primary, None),
+                (ThemeColor::Success, Some("0x00aa00".to_string())),
+                (ThemeColor::Warning, None),
+                (ThemeColor::Error, None),
+            ],
+            Some("https://example.com/logo.png".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            ThemeConfig {
+                primary: Some("#112233".to_string()),
+                success: Some("#00AA00".to_string()),
+                thumbnail_url: Some("https://example.com/logo.png".to_string()),
+                footer_text: Some("Myymälä".to_string()),
+                ..Default::default()
+            }
+        );
+
+        let invalid_color = updated_theme(
+            &current,
+            [
+                (ThemeColor::Primary, Some("blue".to_string())),
+                (ThemeColor::Success, None),
+                (ThemeColor::Warning, None),
+                (ThemeColor::Error, None),
+            ],
+            None,
+            None,
+        );
+        assert!(invalid_color.is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::commands::{
+    create_info_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::components::work_schedule::stats::{load_contract_hours, set_contract_hours};
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::i18n::format_hours;
+use rust_i18n::t;
+
+/// Most hours a week can have
+const MAX_HOURS_PER_WEEK: f64 = 168.0;
+
+/// Manage employees' weekly contract hours
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("set", "list"),
+    subcommand_required
+)]
+pub async fn contract_hours(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Set an employee's weekly contract hours, or remove them by leaving the hours out
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "Hours per week (e.g. 37.5)"] hours: Option<f64>,
+) -> CommandResult {
+    let name = EmployeeId::new(&employee);
+    let valid_hours =
+        hours.is_none_or(|h| h.is_finite() && (0.0..=MAX_HOURS_PER_WEEK).contains(&h));
+    if name.is_empty() || !valid_hours {
+        ctx.send(
+            poise::CreateReply::default()
+                .embed(create_warning_embed(
+                    &t!("contract_hours_title"),
+                    &t!("contract_hours_invalid"),
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    set_contract_hours(&ctx.data().redis(), &employee, hours).await?;
+
+    let message = match hours {
+        Some(hours) => t!(
+            "contract_hours_set",
+            employee = name.display(),
+            hours = format_hours(hours, &rust_i18n::locale())
+        ),
+        None => t!("contract_hours_cleared", employee = name.display()),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("contract_hours_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List employees' weekly contract hours
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn list(ctx: Context<'_>) -> CommandResult {
+    let contracts = load_contract_hours(&ctx.data().redis()).await?;
+
+    let lines = if contracts.is_empty() {
+        t!("contract_hours_none").to_string()
+    } else {
+        contracts
+            .iter()
+            .map(|contract| {
+                format!(
+                    "**{}**: {}",
+                    contract.employee,
+                    format_hours(contract.hours_per_week, &rust_i18n::locale())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_info_embed(&t!("contract_hours_title"), &lines))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::commands::{
+    create_info_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::components::work_schedule::inspect::{
+    stored_dates, stored_entry, Inconsistency, StoredEntry,
+};
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::embed::{limit_fields, split_field, truncate, DESCRIPTION_LIMIT};
+use crate::utils::event_log::{self, LoggedEvent};
+use crate::utils::time::parse_user_date;
+use chrono::Local;
+use poise::serenity_prelude::CreateEmbed;
+use rust_i18n::t;
+
+/// Inspect what the bot has stored in Redis
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("entry", "keys", "events"),
+    subcommand_required
+)]
+pub async fn debug(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Show the raw stored entry of an employee on a day
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn entry(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "Date: YYYY-MM-DD, d.m., vko27, today, tomorrow or a weekday"] date: String,
+) -> CommandResult {
+    let Some(date) = parse_user_date(&date, Local::now().date_naive(), &rust_i18n::locale()) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .embed(create_warning_embed(
+                    &t!("error_title", context = "debug"),
+                    &t!("work_schedule_invalid_date"),
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let date = date.format("%Y-%m-%d").to_string();
+
+    let employee = EmployeeId::new(&employee);
+    let stored = stored_entry(&ctx.data().redis(), &employee, &date).await?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(entry_embed(&employee, &date, &stored))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List the dates stored for an employee
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn keys(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+) -> CommandResult {
+    let employee = EmployeeId::new(&employee);
+    let dates = stored_dates(&ctx.data().redis(), &employee).await?;
+
+    let title = t!("debug_keys_title", employee = employee.display()).to_string();
+    let embed = if dates.is_empty() {
+        create_info_embed(&title, &t!("debug_keys_none"))
+    } else {
+        let mut embed = CreateEmbed::new().title(&title).color(0x0099FF);
+        for (name, value) in limit_fields(split_field("\u{200B}", &dates), title.chars().count()) {
+            embed = embed.field(name, value, false);
+        }
+        embed
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Most recent events `/debug events show` lists
+const EVENTS_SHOWN: usize = 50;
+
+/// What `/debug events` does
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum EventsAction {
+    #[name = "on"]
+    On,
+    #[name = "off"]
+    Off,
+    #[name = "show"]
+    Show,
+}
+
+/// Capture gateway events, e.g. to see whether a command reached the bot, or show the captured
+/// ones
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn events(
+    ctx: Context<'_>,
+    #[description = "Start or stop capturing, or show the latest events"] action: EventsAction,
+    #[description = "Only events containing this text, e.g. interaction or a command name"]
+    filter: Option<String>,
+) -> CommandResult {
+    let title = t!("debug_events_title");
+    let embed = match action {
+        EventsAction::On => {
+            event_log::start_capture(filter.clone());
+            let message = match filter.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+                Some(filter) => t!("debug_events_on_filtered", filter = filter),
+                None => t!("debug_events_on"),
+            };
+            create_success_embed(&title, &message)
+        }
+        EventsAction::Off => {
+            event_log::stop_capture();
+            create_success_embed(&title, &t!("debug_events_off"))
+        }
+        EventsAction::Show => {
+            let events = event_log::recent_events(filter.as_deref(), EVENTS_SHOWN);
+            events_embed(&events, event_log::capture_state())
+        }
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// List captured events in a code block, dropping the oldest ones that don't fit
+fn events_embed(
+    events: &[LoggedEvent],
+    (capturing, filter): (bool, Option<String>),
+) -> CreateEmbed {
+    let state = match (capturing, filter) {
+        (true, Some(filter)) => t!("debug_events_capturing_filtered", filter = filter),
+        (true, None) => t!("debug_events_capturing"),
+        (false, _) => t!("debug_events_not_capturing"),
+    };
+    if events.is_empty() {
+        return create_info_embed(
+            &t!("debug_events_title"),
+            &format!("{state}\n\n{}", t!("debug_events_none")),
+        );
+    }
+
+    // Leave room for the state line and the code fences
+    let budget = DESCRIPTION_LIMIT - state.chars().count() - 12;
+    let mut lines: Vec<String> = Vec::new();
+    let mut used = 0;
+    for event in events.iter().rev() {
+        let line = event.format();
+        used += line.chars().count() + 1;
+        if used > budget {
+            break;
+        }
+        lines.push(line);
+    }
+    lines.reverse();
+
+    create_info_embed(
+        &t!("debug_events_title"),
+        &format!("{state}\n```\n{}\n```", lines.join("\n")),
+    )
+}
+
+/// Describe a stored entry, pretty-printing its JSON and warning about an inconsistent index
+fn entry_embed(employee: &EmployeeId, date: &str, stored: &StoredEntry) -> CreateEmbed {
+    let warning = stored
+        .inconsistency()
+        .map(|inconsistency| match inconsistency {
+            Inconsistency::MissingFromDates => t!("debug_missing_from_dates"),
+            Inconsistency::MissingEntry => t!("debug_missing_entry"),
+        });
+
+    let body = match &stored.raw {
+        Some(raw) => {
+            // Show the value as stored when it isn't valid JSON
+            let pretty = serde_json::from_str::<serde_json::Value>(raw)
+                .and_then(|json| serde_json::to_string_pretty(&json))
+                .unwrap_or_else(|_| raw.clone());
+            let used = warning.as_ref().map_or(0, |w| w.chars().count() + 2);
+            // Leave room for the code fences
+            let pretty = truncate(&pretty, DESCRIPTION_LIMIT - used - 12);
+            format!("```json\n{pretty}\n```")
+        }
+        None => t!("debug_entry_missing").to_string(),
+    };
+    let description = match &warning {
+        Some(warning) => format!("{warning}\n\n{body}"),
+        None => body,
+    };
+
+    let title = t!(
+        "debug_entry_title",
+        employee = employee.display(),
+        date = date
+    );
+    let ttl = match stored.ttl_secs {
+        Some(seconds) => t!("debug_ttl_secs", seconds = seconds),
+        None => t!("debug_ttl_none"),
+    };
+    let in_dates = if stored.in_dates {
+        t!("debug_yes")
+    } else {
+        t!("debug_no")
+    };
+
+    let embed = if warning.is_some() {
+        create_warning_embed(&title, &description)
+    } else {
+        create_info_embed(&title, &description)
+    };
+    embed
+        .field(t!("debug_key"), format!("`{}`", stored.key), false)
+        .field(t!("debug_ttl"), ttl, true)
+        .field(t!("debug_in_dates"), in_dates, true)
+}
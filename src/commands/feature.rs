@@ -0,0 +1,189 @@
+use crate::commands::{
+    create_info_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::error::BotResult;
+use crate::features::{get_guild_features, set_guild_features, Feature, FeatureFlags};
+use rust_i18n::t;
+
+/// Manage experimental features for this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("enable", "disable", "list"),
+    subcommand_required
+)]
+pub async fn feature(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Enable an experimental feature in this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn enable(
+    ctx: Context<'_>,
+    #[description = "Feature to enable"] feature: Feature,
+) -> CommandResult {
+    update_feature(ctx, feature, true).await
+}
+
+/// Disable an experimental feature in this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn disable(
+    ctx: Context<'_>,
+    #[description = "Feature to disable"] feature: Feature,
+) -> CommandResult {
+    update_feature(ctx, feature, false).await
+}
+
+/// List experimental features and whether they're enabled in this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn list(ctx: Context<'_>) -> CommandResult {
+    let flags = guild_features(ctx).await;
+
+    let lines = Feature::ALL
+        .iter()
+        .map(|feature| {
+            let marker = if flags.contains(*feature) {
+                "✅"
+            } else {
+                "❌"
+            };
+            format!("{marker} `{feature}`")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_info_embed(&t!("feature_list_title"), &lines))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Check if a feature is enabled in the current guild, replying with a polite notice if it's not
+pub async fn ctx_has_feature(ctx: Context<'_>, feature: Feature) -> BotResult<bool> {
+    if guild_features(ctx).await.contains(feature) {
+        return Ok(true);
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_warning_embed(
+                &t!("feature_not_enabled_title"),
+                &t!("feature_not_enabled", feature = feature.key()),
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(false)
+}
+
+/// Feature a command is gated on, declared with `custom_data = Feature::X` on the command
+pub fn required_feature<U, E>(command: &poise::Command<U, E>) -> Option<Feature> {
+    command.custom_data.downcast_ref::<Feature>().copied()
+}
+
+/// Command check run before every command: commands gated on a feature only run in guilds
+/// that have it enabled
+pub async fn feature_gate(ctx: Context<'_>) -> BotResult<bool> {
+    match required_feature(ctx.command()) {
+        Some(feature) => ctx_has_feature(ctx, feature).await,
+        None => Ok(true),
+    }
+}
+
+/// Resolve the feature flags for the guild the command was invoked in
+async fn guild_features(ctx: Context<'_>) -> FeatureFlags {
+    let defaults = FeatureFlags::from_names(&ctx.data().config.read().await.default_features);
+
+    match ctx.guild_id() {
+        Some(guild_id) => get_guild_features(&ctx.data().redis(), guild_id.get(), defaults).await,
+        None => defaults,
+    }
+}
+
+/// Toggle a feature for the current guild and report the result
+async fn update_feature(ctx: Context<'_>, feature: Feature, enabled: bool) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let mut flags = guild_features(ctx).await;
+    if enabled {
+        flags.enable(feature);
+    } else {
+        flags.disable(feature);
+    }
+
+    set_guild_features(&ctx.data().redis(), guild_id.get(), flags).await?;
+
+    let message = if enabled {
+        t!("feature_enabled", feature = feature.key())
+    } else {
+        t!("feature_disabled", feature = feature.key())
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("feature_updated_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandContext;
+    use crate::components::redis_service::RedisActorHandle;
+    use crate::error::Error;
+    use crate::features::get_guild_features;
+
+    #[tokio::test]
+    async fn test_disabled_feature_blocks_gated_command() {
+        let gated = poise::Command::<CommandContext, Error> {
+            custom_data: Box::new(Feature::AiQuestions),
+            ..Default::default()
+        };
+        let ungated = poise::Command::<CommandContext, Error>::default();
+        assert_eq!(required_feature(&gated), Some(Feature::AiQuestions));
+        assert_eq!(required_feature(&ungated), None);
+
+        // Enabled by default, but disabled in this guild
+        let redis_handle = RedisActorHandle::fake();
+        let defaults = FeatureFlags::from_names(&["ai_questions"]);
+        let mut flags = defaults;
+        flags.disable(Feature::AiQuestions);
+        set_guild_features(&redis_handle, 1, flags).await.unwrap();
+
+        let feature = required_feature(&gated).unwrap();
+        assert!(!get_guild_features(&redis_handle, 1, defaults)
+            .await
+            .contains(feature));
+        assert!(get_guild_features(&redis_handle, 2, defaults)
+            .await
+            .contains(feature));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::commands::{
+    create_info_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::components::work_schedule::glossary::{
+    add_term, load_glossary, missing_terms, remove_term, term_name, MISSING_TERMS_SHOWN,
+};
+use rust_i18n::t;
+
+/// Manage how the notes in schedule cells, e.g. "Toive vp", are shown in each language
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("add", "list", "remove", "missing"),
+    subcommand_required
+)]
+pub async fn sanasto(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Reply to the invoker only
+async fn reply(ctx: Context<'_>, embed: poise::serenity_prelude::CreateEmbed) -> CommandResult {
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Show a note in a language, replacing what was shown there before
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Note as it appears in the schedule"] term: String,
+    #[description = "Locale, e.g. en or fi-FI"] locale: String,
+    #[description = "How the note is shown in that locale"] display: String,
+) -> CommandResult {
+    let (term, locale) = (term_name(&term), locale.trim().to_string());
+    if term.is_empty() || locale.is_empty() || display.trim().is_empty() {
+        return reply(
+            ctx,
+            create_warning_embed(&t!("glossary_title"), &t!("glossary_invalid")),
+        )
+        .await;
+    }
+
+    add_term(&ctx.data().redis(), &term, &locale, &display).await?;
+    let message = t!(
+        "glossary_added",
+        term = term,
+        locale = locale,
+        display = display.trim()
+    );
+    reply(ctx, create_success_embed(&t!("glossary_title"), &message)).await
+}
+
+/// List the notes and how they're shown in each language
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn list(ctx: Context<'_>) -> CommandResult {
+    let glossary = load_glossary(&ctx.data().redis()).await?;
+
+    let lines: Vec<String> = glossary
+        .iter()
+        .map(|(term, translations)| {
+            let translations: Vec<String> = translations
+                .iter()
+                .map(|(locale, display)| format!("{locale}: {display}"))
+                .collect();
+            format!("**{term}** → {}", translations.join(", "))
+        })
+        .collect();
+    let text = if lines.is_empty() {
+        t!("glossary_none").to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    reply(ctx, create_info_embed(&t!("glossary_title"), &text)).await
+}
+
+/// Stop translating a note in one language, or in every language when none is given
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Note as it appears in the schedule"] term: String,
+    #[description = "Locale (leave empty for every locale)"] locale: Option<String>,
+) -> CommandResult {
+    let term = term_name(&term);
+    let locale = locale.map(|locale| locale.trim().to_string());
+
+    if remove_term(&ctx.data().redis(), &term, locale.as_deref()).await? {
+        let message = match &locale {
+            Some(locale) => t!("glossary_removed_locale", term = term, locale = locale),
+            None => t!("glossary_removed", term = term),
+        };
+        reply(ctx, create_success_embed(&t!("glossary_title"), &message)).await
+    } else {
+        let message = t!("glossary_not_found", term = term);
+        reply(ctx, create_warning_embed(&t!("glossary_title"), &message)).await
+    }
+}
+
+/// List the untranslated notes shown most often
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn missing(ctx: Context<'_>) -> CommandResult {
+    let terms = missing_terms(&ctx.data().redis(), MISSING_TERMS_SHOWN).await?;
+
+    let text = if terms.is_empty() {
+        t!("glossary_no_missing").to_string()
+    } else {
+        terms
+            .iter()
+            .map(|(term, count)| t!("glossary_missing_line", term = term, count = count))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    reply(ctx, create_info_embed(&t!("glossary_missing_title"), &text)).await
+}
@@ -0,0 +1,124 @@
+use crate::commands::{
+    create_info_embed, create_success_embed, create_warning_embed, CommandResult, Context,
+};
+use crate::components::work_schedule::groups::{
+    add_group_member, group_name, load_employee_groups, remove_group_member,
+};
+use crate::components::work_schedule::EmployeeId;
+use rust_i18n::t;
+
+/// Manage the employee groups notifications are routed and commands filtered by
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("add", "remove", "list"),
+    subcommand_required
+)]
+pub async fn employee_groups(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Reply to the invoker only
+async fn reply(ctx: Context<'_>, embed: poise::serenity_prelude::CreateEmbed) -> CommandResult {
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Add an employee to a group, creating the group if needed
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Group name"] group: String,
+    #[description = "Employee name"] employee: String,
+) -> CommandResult {
+    let name = EmployeeId::new(&employee);
+    let group = group_name(&group);
+    if name.is_empty() || group.is_empty() {
+        return reply(
+            ctx,
+            create_warning_embed(&t!("employee_groups_title"), &t!("employee_groups_invalid")),
+        )
+        .await;
+    }
+
+    let message = if add_group_member(&ctx.data().redis(), &group, &employee).await? {
+        t!(
+            "employee_groups_added",
+            employee = name.display(),
+            group = group
+        )
+    } else {
+        t!(
+            "employee_groups_already_member",
+            employee = name.display(),
+            group = group
+        )
+    };
+    reply(
+        ctx,
+        create_success_embed(&t!("employee_groups_title"), &message),
+    )
+    .await
+}
+
+/// Remove an employee from a group. Groups without employees are removed.
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Group name"] group: String,
+    #[description = "Employee name"] employee: String,
+) -> CommandResult {
+    let name = EmployeeId::new(&employee);
+    let group = group_name(&group);
+
+    if remove_group_member(&ctx.data().redis(), &group, &employee).await? {
+        let message = t!(
+            "employee_groups_removed",
+            employee = name.display(),
+            group = group
+        );
+        reply(
+            ctx,
+            create_success_embed(&t!("employee_groups_title"), &message),
+        )
+        .await
+    } else {
+        let message = t!(
+            "employee_groups_not_member",
+            employee = name.display(),
+            group = group
+        );
+        reply(
+            ctx,
+            create_warning_embed(&t!("employee_groups_title"), &message),
+        )
+        .await
+    }
+}
+
+/// List the employee groups and the channels their notifications go to
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn list(ctx: Context<'_>) -> CommandResult {
+    let groups = load_employee_groups(&ctx.data().redis()).await?;
+    let routes = ctx.data().config.read().await.notification_routes.clone();
+
+    let lines: Vec<String> = groups
+        .iter()
+        .map(|(group, employees)| {
+            let line = format!("**{group}**: {}", employees.join(", "));
+            match routes.get(group) {
+                Some(channel_id) => format!("{line} → <#{channel_id}>"),
+                None => line,
+            }
+        })
+        .collect();
+    let text = if lines.is_empty() {
+        t!("employee_groups_none").to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    reply(ctx, create_info_embed(&t!("employee_groups_title"), &text)).await
+}
@@ -0,0 +1,61 @@
+use crate::commands::{create_success_embed, CommandResult, Context};
+use crate::maintenance::{set_maintenance, Maintenance};
+use rust_i18n::t;
+use tracing::info;
+
+/// Pause notifications and schedule changes while the bot keeps answering queries
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("on", "off"),
+    subcommand_required
+)]
+pub async fn maintenance(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Turn maintenance mode on
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn on(
+    ctx: Context<'_>,
+    #[description = "Message shown when a change is refused (leave empty for the default)"]
+    message: Option<String>,
+) -> CommandResult {
+    let maintenance = Maintenance {
+        message: message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty()),
+        enabled_by: ctx.author().id.get(),
+        enabled_at: chrono::Utc::now().timestamp(),
+    };
+    set_maintenance(&ctx.data().redis(), Some(&maintenance)).await?;
+    info!("Maintenance mode turned on by {}", ctx.author().id);
+
+    let description = t!("maintenance_enabled", message = maintenance.message());
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("maintenance_title"), &description))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Turn maintenance mode off; held back notifications are sent if they're still current
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn off(ctx: Context<'_>) -> CommandResult {
+    set_maintenance(&ctx.data().redis(), None).await?;
+    info!("Maintenance mode turned off by {}", ctx.author().id);
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(
+                &t!("maintenance_title"),
+                &t!("maintenance_disabled"),
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,12 +1,36 @@
+use crate::components::redis_service::RedisActorHandle;
 use crate::components::ComponentManager;
 use crate::config::Config;
 use crate::error::BotResult;
-use poise::serenity_prelude::CreateEmbed;
+use crate::leader::Leadership;
+use crate::maintenance::get_maintenance;
+use crate::prefix::PrefixCache;
+use crate::presence::PresenceHandle;
+use crate::theme::{Theme, ThemeColor};
+use crate::user_preferences::{get_user_preferences, OutputFormat};
+use crate::utils::rate_limits::{check_rate_limit, CommandCategory, RateLimitDecision};
+use crate::utils::render::View;
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use rust_i18n::t;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 // Export submodules
 pub mod calendar;
+pub mod component;
+pub mod config;
+pub mod contract;
+pub mod debug;
+pub mod feature;
+pub mod glossary;
+pub mod groups;
+pub mod maintenance;
+pub mod preferences;
+pub mod presence;
+pub mod preview;
+pub mod profiles;
+pub mod setup;
+pub mod timeout;
 pub mod util;
 pub mod work;
 
@@ -16,6 +40,12 @@ pub struct CommandContext {
     // Add any shared command context here
     pub config: Arc<RwLock<Config>>,
     pub component_manager: Option<Arc<ComponentManager>>,
+    pub redis_handle: Option<RedisActorHandle>,
+    pub presence_handle: Option<PresenceHandle>,
+    /// Whether this replica runs the schedulers
+    pub leadership: Option<Leadership>,
+    /// Guild prefixes for text commands
+    pub prefix_cache: Arc<PrefixCache>,
 }
 
 impl CommandContext {
@@ -24,6 +54,10 @@ impl CommandContext {
         Self {
             config,
             component_manager: None,
+            redis_handle: None,
+            presence_handle: None,
+            leadership: None,
+            prefix_cache: Arc::new(PrefixCache::default()),
         }
     }
 
@@ -32,6 +66,36 @@ impl CommandContext {
         self.component_manager = Some(component_manager);
         self
     }
+
+    /// Set the Redis handle
+    pub fn with_redis_handle(mut self, redis_handle: RedisActorHandle) -> Self {
+        self.redis_handle = Some(redis_handle);
+        self
+    }
+
+    /// Set the presence updater handle
+    pub fn with_presence_handle(mut self, presence_handle: PresenceHandle) -> Self {
+        self.presence_handle = Some(presence_handle);
+        self
+    }
+
+    /// Set the leadership of this replica
+    pub fn with_leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = Some(leadership);
+        self
+    }
+
+    /// Get the Redis handle, or an empty one if Redis isn't available
+    pub fn redis(&self) -> RedisActorHandle {
+        self.redis_handle
+            .clone()
+            .unwrap_or_else(RedisActorHandle::empty)
+    }
+
+    /// Theme of the guild a command runs in, or the default one in DMs
+    pub async fn theme(&self, guild_id: Option<serenity::GuildId>) -> Theme {
+        Theme::resolve(&self.redis(), guild_id.map(|id| id.get())).await
+    }
 }
 
 /// Type alias for command result
@@ -40,36 +104,175 @@ pub type CommandResult = BotResult<()>;
 /// Type alias for poise context
 pub type Context<'a> = poise::Context<'a, CommandContext, crate::error::Error>;
 
-/// Helper function to create a success embed
+/// Helper function to create a success embed in the default theme
 pub fn create_success_embed(title: &str, description: &str) -> CreateEmbed {
-    CreateEmbed::new()
-        .title(title)
-        .description(description)
-        .color(0x00FF00) // Green color
+    Theme::default().embed(ThemeColor::Success, title, description)
 }
 
-/// Helper function to create an info embed
+/// Helper function to create an info embed in the default theme
 pub fn create_info_embed(title: &str, description: &str) -> CreateEmbed {
-    CreateEmbed::new()
-        .title(title)
-        .description(description)
-        .color(0x0099FF) // Blue color
+    Theme::default().embed(ThemeColor::Primary, title, description)
 }
 
-/// Helper function to create a warning embed
+/// Helper function to create a warning embed in the default theme
 pub fn create_warning_embed(title: &str, description: &str) -> CreateEmbed {
-    CreateEmbed::new()
-        .title(title)
-        .description(description)
-        .color(0xFFAA00) // Orange color
+    Theme::default().embed(ThemeColor::Warning, title, description)
 }
 
-/// Helper function to create an error embed
+/// Helper function to create an error embed in the default theme
 pub fn create_error_embed(title: &str, description: &str) -> CreateEmbed {
-    CreateEmbed::new()
-        .title(title)
-        .description(description)
-        .color(0xFF0000) // Red color
+    Theme::default().embed(ThemeColor::Error, title, description)
+}
+
+/// Send a reply in the invoking user's output format, as an embed or as plain text messages
+pub async fn send_view(ctx: Context<'_>, view: View, ephemeral: bool) -> CommandResult {
+    let view = with_maintenance_notice(ctx.data(), view).await;
+    let format = get_user_preferences(&ctx.data().redis(), ctx.author().id.get())
+        .await
+        .output_format;
+    match format {
+        OutputFormat::Embed => {
+            let theme = ctx.data().theme(ctx.guild_id()).await;
+            ctx.send(
+                poise::CreateReply::default()
+                    .embed(view.to_themed_embed(&theme))
+                    .ephemeral(ephemeral),
+            )
+            .await?;
+        }
+        OutputFormat::Text => {
+            for message in view.to_text() {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content(message)
+                        .ephemeral(ephemeral),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Mark a reply as possibly out of date while maintenance mode is on
+pub async fn with_maintenance_notice(data: &CommandContext, view: View) -> View {
+    match get_maintenance(&data.redis()).await {
+        Some(_) => view.footer_note(&t!("maintenance_footer")),
+        None => view,
+    }
+}
+
+/// Check whether the invoking member has administrator permissions
+pub async fn is_admin(ctx: Context<'_>) -> bool {
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+
+    // Interactions carry the resolved permissions; prefix commands fall back to the cache
+    if let Some(permissions) = member.permissions {
+        return permissions.administrator();
+    }
+
+    ctx.guild()
+        .and_then(|guild| {
+            let channel = guild.channels.get(&ctx.channel_id())?;
+            Some(guild.user_permissions_in(channel, &member).administrator())
+        })
+        .unwrap_or(false)
+}
+
+/// Enforce the rate limit of a command category, telling the user when to retry
+async fn enforce_rate_limit(ctx: Context<'_>, category: CommandCategory) -> BotResult<bool> {
+    if is_admin(ctx).await {
+        return Ok(true);
+    }
+
+    let limits = ctx.data().config.read().await.rate_limits.clone();
+    let decision = check_rate_limit(
+        &ctx.data().redis(),
+        &limits,
+        category,
+        ctx.author().id.get(),
+        ctx.guild_id().map(|id| id.get()),
+    )
+    .await;
+
+    match decision {
+        RateLimitDecision::Allowed => Ok(true),
+        RateLimitDecision::Limited { retry_after_secs } => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .embed(create_warning_embed(
+                        &t!("rate_limited_title"),
+                        &t!("rate_limited", seconds = retry_after_secs),
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Command check limiting work schedule commands
+pub async fn schedule_rate_limit(ctx: Context<'_>) -> BotResult<bool> {
+    enforce_rate_limit(ctx, CommandCategory::Schedule).await
+}
+
+/// Command check limiting calendar commands that hit the Google API
+pub async fn calendar_rate_limit(ctx: Context<'_>) -> BotResult<bool> {
+    enforce_rate_limit(ctx, CommandCategory::Calendar).await
+}
+
+/// Tell the user when a component is disabled on this instance. Returns whether it's enabled.
+pub async fn require_component(ctx: Context<'_>, name: &str) -> BotResult<bool> {
+    let disabled = ctx
+        .data()
+        .component_manager
+        .as_ref()
+        .is_some_and(|component_manager| component_manager.is_disabled(name));
+    if !disabled {
+        return Ok(true);
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_warning_embed(
+                &t!("component_disabled_title"),
+                &t!("component_disabled"),
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Command check refusing changes to schedules while maintenance mode is on
+pub async fn not_in_maintenance(ctx: Context<'_>) -> BotResult<bool> {
+    let Some(maintenance) = get_maintenance(&ctx.data().redis()).await else {
+        return Ok(true);
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_warning_embed(
+                &t!("maintenance_title"),
+                &maintenance.message(),
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Command check for commands that need the work schedule component
+pub async fn work_schedule_enabled(ctx: Context<'_>) -> BotResult<bool> {
+    require_component(ctx, "work_schedule").await
+}
+
+/// Command check for commands that need the Google Calendar component
+pub async fn calendar_enabled(ctx: Context<'_>) -> BotResult<bool> {
+    require_component(ctx, "google_calendar").await
 }
 
 /// All application commands and event listeners
@@ -77,16 +280,48 @@ pub fn get_all_application_commands() -> Vec<poise::Command<CommandContext, crat
     let mut commands = vec![
         // Utility commands
         util::ping(),
+        util::status(),
     ];
 
     // Add calendar commands
     commands.push(calendar::this_week());
+    commands.push(calendar::next());
+    commands.push(calendar::eilen());
+    commands.push(calendar::paivystys());
+
+    // Add personal settings
+    commands.push(preferences::preferences());
+
+    // Add admin commands
+    commands.push(component::component());
+    commands.push(config::config());
+    commands.push(contract::contract_hours());
+    commands.push(debug::debug());
+    commands.push(feature::feature());
+    commands.push(glossary::sanasto());
+    commands.push(groups::employee_groups());
+    commands.push(maintenance::maintenance());
+    commands.push(presence::presence());
+    commands.push(preview::preview());
+    commands.push(profiles::tyontekija());
+    commands.push(setup::setup());
 
     // Add work schedule commands
     commands.push(work::tyovuorot());
     commands.push(work::day());
     commands.push(work::employee());
+    commands.push(work::seuraava_vuoro());
     commands.push(work::ensiviikko());
+    commands.push(work::duplikaatit());
+    commands.push(work::ehdota_korjausta());
+    commands.push(work::kattavuus());
+    commands.push(work::lomat());
+    commands.push(work::vuorot_viikonloppu());
+    commands.push(work::laatu());
+    commands.push(work::parse_failures());
+    commands.push(work::liita_viesti());
+    commands.push(work::toteuma());
+    commands.push(work::tilastot());
 
-    commands
+    commands.into_iter().map(timeout::with_timeout).collect()
 }
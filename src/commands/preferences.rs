@@ -0,0 +1,190 @@
+use crate::commands::{create_success_embed, create_warning_embed, CommandResult, Context};
+use crate::error::BotResult;
+use crate::guild_config::{get_guild_config, set_guild_config};
+use crate::user_preferences::{
+    get_user_preferences, set_user_preferences, OutputFormat, ScheduleStyle,
+};
+use chrono_tz::Tz;
+use rust_i18n::t;
+
+/// Manage your personal settings
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("timezone", "server_timezone", "employee", "format", "style"),
+    subcommand_required
+)]
+pub async fn preferences(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Parse a timezone given to a command, telling the user the expected format if it's invalid
+async fn parse_timezone(ctx: Context<'_>, name: &str) -> BotResult<Option<String>> {
+    match name.trim().parse::<Tz>() {
+        Ok(tz) => Ok(Some(tz.name().to_string())),
+        Err(_) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .embed(create_warning_embed(
+                        &t!("preferences_title"),
+                        &t!("calendar_invalid_timezone", timezone = name),
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Set the timezone used for your calendar commands, or clear it by leaving it out
+#[poise::command(slash_command, prefix_command)]
+pub async fn timezone(
+    ctx: Context<'_>,
+    #[description = "IANA timezone name (e.g. 'Europe/Helsinki')"] timezone: Option<String>,
+) -> CommandResult {
+    let timezone = match timezone {
+        Some(name) => match parse_timezone(ctx, &name).await? {
+            Some(timezone) => Some(timezone),
+            None => return Ok(()),
+        },
+        None => None,
+    };
+
+    let redis_handle = ctx.data().redis();
+    let user_id = ctx.author().id.get();
+    let mut preferences = get_user_preferences(&redis_handle, user_id).await;
+    preferences.timezone = timezone.clone();
+    set_user_preferences(&redis_handle, user_id, &preferences).await?;
+
+    let message = match timezone {
+        Some(timezone) => t!("preferences_timezone_set", timezone = timezone),
+        None => t!("preferences_timezone_cleared"),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("preferences_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Set the default timezone for calendar commands in this server, or clear it by leaving it out
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn server_timezone(
+    ctx: Context<'_>,
+    #[description = "IANA timezone name (e.g. 'Europe/Helsinki')"] timezone: Option<String>,
+) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let timezone = match timezone {
+        Some(name) => match parse_timezone(ctx, &name).await? {
+            Some(timezone) => Some(timezone),
+            None => return Ok(()),
+        },
+        None => None,
+    };
+
+    let redis_handle = ctx.data().redis();
+    let mut config = get_guild_config(&redis_handle, guild_id.get()).await;
+    config.timezone = timezone.clone();
+    set_guild_config(&redis_handle, guild_id.get(), &config).await?;
+
+    let message = match timezone {
+        Some(timezone) => t!("preferences_server_timezone_set", timezone = timezone),
+        None => t!("preferences_server_timezone_cleared"),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("preferences_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Link yourself to an employee in the work schedule, or unlink by leaving the name out
+#[poise::command(slash_command, prefix_command)]
+pub async fn employee(
+    ctx: Context<'_>,
+    #[description = "Employee name as it appears in the work schedule"] name: Option<String>,
+) -> CommandResult {
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+
+    let redis_handle = ctx.data().redis();
+    let user_id = ctx.author().id.get();
+    let mut preferences = get_user_preferences(&redis_handle, user_id).await;
+    preferences.employee = name.clone();
+    set_user_preferences(&redis_handle, user_id, &preferences).await?;
+
+    let message = match name {
+        Some(name) => t!("preferences_employee_set", employee = name),
+        None => t!("preferences_employee_cleared"),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("preferences_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Choose whether schedule and calendar commands reply with embeds or plain text
+#[poise::command(slash_command, prefix_command)]
+pub async fn format(
+    ctx: Context<'_>,
+    #[description = "Plain text is easier to follow with a screen reader"] format: OutputFormat,
+) -> CommandResult {
+    let redis_handle = ctx.data().redis();
+    let user_id = ctx.author().id.get();
+    let mut preferences = get_user_preferences(&redis_handle, user_id).await;
+    preferences.output_format = format;
+    set_user_preferences(&redis_handle, user_id, &preferences).await?;
+
+    let message = match format {
+        OutputFormat::Embed => t!("preferences_format_embed"),
+        OutputFormat::Text => t!("preferences_format_text"),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("preferences_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Choose the default layout of /tyovuorot, /ensiviikko and /day replies
+#[poise::command(slash_command, prefix_command)]
+pub async fn style(
+    ctx: Context<'_>,
+    #[description = "Compact tables don't wrap on a phone"] style: ScheduleStyle,
+) -> CommandResult {
+    let redis_handle = ctx.data().redis();
+    let user_id = ctx.author().id.get();
+    let mut preferences = get_user_preferences(&redis_handle, user_id).await;
+    preferences.schedule_style = style;
+    set_user_preferences(&redis_handle, user_id, &preferences).await?;
+
+    let message = match style {
+        ScheduleStyle::Normal => t!("preferences_style_normal"),
+        ScheduleStyle::Compact => t!("preferences_style_compact"),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(create_success_embed(&t!("preferences_title"), &message))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
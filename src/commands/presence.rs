@@ -0,0 +1,30 @@
+use crate::commands::{create_success_embed, create_warning_embed, CommandResult, Context};
+use rust_i18n::t;
+
+/// Manage the bot's presence
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    subcommands("refresh"),
+    subcommand_required
+)]
+pub async fn presence(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Update the bot's presence right away
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn refresh(ctx: Context<'_>) -> CommandResult {
+    let embed = match &ctx.data().presence_handle {
+        Some(handle) => {
+            handle.refresh();
+            create_success_embed(&t!("presence_title"), &t!("presence_refreshed"))
+        }
+        None => create_warning_embed(&t!("presence_title"), &t!("presence_unavailable")),
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::commands::calendar::get_calendar_handle;
+use crate::commands::work::get_work_schedule_handle;
+use crate::commands::{create_warning_embed, require_component, CommandResult, Context};
+use crate::components::work_schedule::groups::EmployeeFilter;
+use crate::components::work_schedule::stats::weekly_budget;
+use crate::components::{google_calendar, work_schedule};
+use crate::utils::time::{parse_user_date, week_bounds};
+use chrono::Local;
+use rust_i18n::t;
+
+/// Component whose notification is previewed
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum PreviewComponent {
+    #[name = "work"]
+    Work,
+    #[name = "calendar"]
+    Calendar,
+}
+
+/// Kind of notification previewed
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum PreviewType {
+    #[name = "daily"]
+    Daily,
+    #[name = "weekly"]
+    Weekly,
+}
+
+/// Show the notification the scheduler would send for a date, without sending it
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn preview(
+    ctx: Context<'_>,
+    #[description = "Component"] component: PreviewComponent,
+    #[description = "Notification type"]
+    #[rename = "type"]
+    notification_type: PreviewType,
+    #[description = "Date: YYYY-MM-DD, d.m., vko27, tomorrow or a weekday; today by default"] date: Option<
+        String,
+    >,
+) -> CommandResult {
+    let today = Local::now().date_naive();
+    let date = match date.as_deref() {
+        Some(date) => match parse_user_date(date, today, &rust_i18n::locale()) {
+            Some(date) => date,
+            None => {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .embed(create_warning_embed(
+                            &t!("error_title", context = "preview"),
+                            &t!("work_schedule_invalid_date"),
+                        ))
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => today,
+    };
+
+    let component_name = match component {
+        PreviewComponent::Work => "work_schedule",
+        PreviewComponent::Calendar => "google_calendar",
+    };
+    if !require_component(ctx, component_name).await? {
+        return Ok(());
+    }
+
+    let config = ctx.data().config.read().await.clone();
+    let component_manager = ctx.data().component_manager.as_ref();
+    let shared_config = ctx.data().config.clone();
+    let theme = ctx.data().theme(ctx.guild_id()).await;
+
+    // Build the notification with the same code the scheduler uses
+    let notification = match component {
+        PreviewComponent::Work => {
+            let handle = get_work_schedule_handle(component_manager, shared_config).await;
+            match notification_type {
+                PreviewType::Daily => {
+                    work_schedule::build_daily_notification(
+                        &handle,
+                        &date.format("%Y-%m-%d").to_string(),
+                        &EmployeeFilter::All,
+                        &theme,
+                    )
+                    .await?
+                }
+                PreviewType::Weekly => {
+                    let (first, last) = week_bounds(date, config.week_starts_on);
+                    let budget = weekly_budget(&ctx.data().redis(), &config).await;
+                    work_schedule::build_weekly_notification(
+                        &handle,
+                        &first.format("%Y-%m-%d").to_string(),
+                        &last.format("%Y-%m-%d").to_string(),
+                        &budget,
+                        &EmployeeFilter::All,
+                        &theme,
+                    )
+                    .await?
+                }
+            }
+        }
+        PreviewComponent::Calendar => {
+            let handle = get_calendar_handle(component_manager, shared_config).await;
+            // Private events look the way the calendar channel would see them
+            let show_private =
+                config.notifications_show_private_event_details(config.calendar_channel_id);
+            match notification_type {
+                PreviewType::Daily => {
+                    google_calendar::build_daily_notification(&handle, date, show_private, &theme)
+                        .await?
+                }
+                PreviewType::Weekly => {
+                    google_calendar::build_weekly_notification(
+                        &handle,
+                        date,
+                        config.show_empty_days,
+                        config.week_starts_on,
+                        show_private,
+                        &theme,
+                    )
+                    .await?
+                }
+            }
+        }
+    };
+
+    let mut content = t!(
+        "preview_note",
+        date = date.format("%Y-%m-%d").to_string(),
+        channel = format!("<#{}>", config.calendar_channel_id)
+    )
+    .to_string();
+    if let Some(message) = notification.content {
+        content.push_str("\n\n");
+        content.push_str(&message);
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .embed(notification.embed)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::commands::{create_success_embed, create_warning_embed, CommandResult, Context};
+use crate::components::work_schedule::profiles::{
+    check_avatar_image, fallback_emoji, parse_emoji, set_employee_avatar, set_employee_emoji,
+    validate_avatar_url,
+};
+use crate::components::work_schedule::EmployeeId;
+use rust_i18n::t;
+use tracing::info;
+
+/// Manage how employees are shown in schedule embeds
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "tyontekijä",
+    required_permissions = "ADMINISTRATOR",
+    subcommands("emoji", "avatar"),
+    subcommand_required
+)]
+pub async fn tyontekija(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Reply to the invoker only
+async fn reply(ctx: Context<'_>, embed: poise::serenity_prelude::CreateEmbed) -> CommandResult {
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Warn the invoker that the employee name was blank
+async fn reply_invalid_employee(ctx: Context<'_>) -> CommandResult {
+    reply(
+        ctx,
+        create_warning_embed(
+            &t!("employee_profiles_title"),
+            &t!("employee_profiles_invalid_employee"),
+        ),
+    )
+    .await
+}
+
+/// Set the emoji shown before an employee's name. Leave it empty to go back to the default.
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn emoji(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "Emoji to show (leave empty for the default)"] emoji: Option<String>,
+) -> CommandResult {
+    let name = EmployeeId::new(&employee);
+    if name.is_empty() {
+        return reply_invalid_employee(ctx).await;
+    }
+
+    let emoji = match emoji.as_deref().map(parse_emoji).transpose() {
+        Ok(emoji) => emoji,
+        Err(e) => {
+            info!("Rejected employee emoji: {}", e);
+            return reply(
+                ctx,
+                create_warning_embed(
+                    &t!("employee_profiles_title"),
+                    &t!("employee_profiles_invalid_emoji"),
+                ),
+            )
+            .await;
+        }
+    };
+
+    let message = match &emoji {
+        Some(emoji) => t!(
+            "employee_profiles_emoji_set",
+            employee = name.display(),
+            emoji = emoji
+        ),
+        None => t!(
+            "employee_profiles_emoji_cleared",
+            employee = name.display(),
+            emoji = fallback_emoji(name.display())
+        ),
+    };
+    set_employee_emoji(&ctx.data().redis(), name.display(), emoji).await?;
+    reply(
+        ctx,
+        create_success_embed(&t!("employee_profiles_title"), &message),
+    )
+    .await
+}
+
+/// Set the picture shown in an employee's own schedule. Leave it empty to remove it.
+#[poise::command(slash_command, prefix_command, required_permissions = "ADMINISTRATOR")]
+pub async fn avatar(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "https link to an image (leave empty to remove)"] url: Option<String>,
+) -> CommandResult {
+    let name = EmployeeId::new(&employee);
+    if name.is_empty() {
+        return reply_invalid_employee(ctx).await;
+    }
+
+    let Some(url) = url else {
+        set_employee_avatar(&ctx.data().redis(), name.display(), None).await?;
+        let message = t!(
+            "employee_profiles_avatar_cleared",
+            employee = name.display()
+        );
+        return reply(
+            ctx,
+            create_success_embed(&t!("employee_profiles_title"), &message),
+        )
+        .await;
+    };
+
+    let url = match validate_avatar_url(&url) {
+        Ok(url) => url,
+        Err(e) => {
+            info!("Rejected employee avatar: {}", e);
+            return reply(
+                ctx,
+                create_warning_embed(
+                    &t!("employee_profiles_title"),
+                    &t!("employee_profiles_invalid_avatar"),
+                ),
+            )
+            .await;
+        }
+    };
+    ctx.defer_ephemeral().await?;
+    if let Err(e) = check_avatar_image(&url).await {
+        info!("Rejected employee avatar: {}", e);
+        return reply(
+            ctx,
+            create_warning_embed(
+                &t!("employee_profiles_title"),
+                &t!("employee_profiles_avatar_not_image"),
+            ),
+        )
+        .await;
+    }
+
+    set_employee_avatar(&ctx.data().redis(), name.display(), Some(url.to_string())).await?;
+    let message = t!("employee_profiles_avatar_set", employee = name.display());
+    reply(
+        ctx,
+        create_success_embed(&t!("employee_profiles_title"), &message).thumbnail(url.as_str()),
+    )
+    .await
+}
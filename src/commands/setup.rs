@@ -0,0 +1,686 @@
+use crate::commands::{
+    create_error_embed, create_info_embed, create_success_embed, create_warning_embed,
+    CommandResult, Context,
+};
+use crate::error::BotResult;
+use crate::features::{get_guild_features, set_guild_features, Feature, FeatureFlags};
+use crate::guild_config::{apply_locale, get_guild_config, set_guild_config, GuildConfig};
+use crate::utils::notifier::{DiscordNotifier, Notification, Notifier};
+use crate::utils::time::parse_time;
+use poise::serenity_prelude as serenity;
+use rust_i18n::t;
+
+/// How long the wizard waits for the next interaction before closing
+const SETUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// Steps of the setup wizard, in the order a first run goes through them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupStep {
+    Channel,
+    Times,
+    Locale,
+    Features,
+    Summary,
+}
+
+impl SetupStep {
+    /// Steps that can be changed from the summary
+    pub const EDITABLE: [SetupStep; 4] = [
+        SetupStep::Channel,
+        SetupStep::Times,
+        SetupStep::Locale,
+        SetupStep::Features,
+    ];
+
+    /// Identifier used in component custom ids
+    fn key(&self) -> &'static str {
+        match self {
+            SetupStep::Channel => "channel",
+            SetupStep::Times => "times",
+            SetupStep::Locale => "locale",
+            SetupStep::Features => "features",
+            SetupStep::Summary => "summary",
+        }
+    }
+
+    /// Parse a step from its custom id identifier
+    fn from_key(key: &str) -> Option<Self> {
+        Self::EDITABLE.into_iter().find(|step| step.key() == key)
+    }
+
+    /// Step following this one on a first run
+    fn next(&self) -> Self {
+        match self {
+            SetupStep::Channel => SetupStep::Times,
+            SetupStep::Times => SetupStep::Locale,
+            SetupStep::Locale => SetupStep::Features,
+            SetupStep::Features | SetupStep::Summary => SetupStep::Summary,
+        }
+    }
+}
+
+/// Input received from the wizard's components
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetupInput {
+    Channel(u64),
+    Times {
+        daily: String,
+        weekly: String,
+    },
+    Locale(String),
+    Features(Vec<String>),
+    /// Keep the current value and move on
+    Skip,
+    /// Go back to change a single step
+    Edit(SetupStep),
+}
+
+/// State of a running setup wizard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupState {
+    pub step: SetupStep,
+    pub config: GuildConfig,
+    pub features: FeatureFlags,
+    /// Set when a single step is being changed, returning to the summary after it
+    editing: bool,
+}
+
+impl SetupState {
+    /// Start the wizard from the guild's current settings.
+    ///
+    /// A fresh guild starts from the first step, a partially configured one from the first
+    /// missing value and a fully configured one from the summary.
+    pub fn new(config: GuildConfig, features: FeatureFlags) -> Self {
+        let step = if config.notification_channel_id.is_none() {
+            SetupStep::Channel
+        } else if config.daily_notification_time.is_none()
+            || config.weekly_notification_time.is_none()
+        {
+            SetupStep::Times
+        } else if config.locale.is_none() {
+            SetupStep::Locale
+        } else {
+            SetupStep::Summary
+        };
+
+        Self {
+            step,
+            config,
+            features,
+            editing: false,
+        }
+    }
+
+    /// Apply an input to the current step, returning the locale key of the error if it's invalid
+    pub fn apply(&mut self, input: SetupInput) -> Result<(), &'static str> {
+        match (self.step, input) {
+            (_, SetupInput::Edit(step)) => {
+                self.step = step;
+                self.editing = true;
+                return Ok(());
+            }
+            (SetupStep::Summary, _) => return Err("setup_error_unexpected_input"),
+            (_, SetupInput::Skip) => {}
+            (SetupStep::Channel, SetupInput::Channel(channel_id)) => {
+                self.config.notification_channel_id = Some(channel_id);
+            }
+            (SetupStep::Times, SetupInput::Times { daily, weekly }) => {
+                let daily = normalize_time(&daily).ok_or("setup_error_invalid_time")?;
+                let weekly = normalize_time(&weekly).ok_or("setup_error_invalid_time")?;
+                self.config.daily_notification_time = Some(daily);
+                self.config.weekly_notification_time = Some(weekly);
+            }
+            (SetupStep::Locale, SetupInput::Locale(locale)) => {
+                if !available_locales().contains(&locale.as_str()) {
+                    return Err("setup_error_unknown_locale");
+                }
+                self.config.locale = Some(locale);
+            }
+            (SetupStep::Features, SetupInput::Features(names)) => {
+                let mut flags = FeatureFlags::default();
+                for name in names {
+                    let feature = name
+                        .parse::<Feature>()
+                        .map_err(|_| "setup_error_unknown_feature")?;
+                    flags.enable(feature);
+                }
+                self.features = flags;
+            }
+            _ => return Err("setup_error_unexpected_input"),
+        }
+
+        self.step = if self.editing {
+            SetupStep::Summary
+        } else {
+            self.step.next()
+        };
+        Ok(())
+    }
+}
+
+/// Validate a notification time, normalizing it to HH:MM or HH:MM:SS
+fn normalize_time(time: &str) -> Option<String> {
+    parse_time(time).ok().map(|time| time.to_string())
+}
+
+/// Locales the bot has translations for
+fn available_locales() -> Vec<&'static str> {
+    rust_i18n::available_locales!()
+}
+
+/// Modal asking for the notification times
+struct TimesModal {
+    daily: String,
+    weekly: String,
+}
+
+impl poise::Modal for TimesModal {
+    fn create(defaults: Option<Self>, custom_id: String) -> serenity::CreateInteractionResponse {
+        let (daily, weekly) = defaults
+            .map(|defaults| (defaults.daily, defaults.weekly))
+            .unwrap_or_default();
+        let input = |id: &str, label: String, value: String| {
+            serenity::CreateActionRow::InputText(
+                serenity::CreateInputText::new(serenity::InputTextStyle::Short, label, id)
+                    .placeholder("08:00")
+                    .min_length(4)
+                    .max_length(5)
+                    .value(value),
+            )
+        };
+
+        serenity::CreateInteractionResponse::Modal(
+            serenity::CreateModal::new(custom_id, t!("setup_times_modal_title")).components(vec![
+                input("daily", t!("setup_daily_time").to_string(), daily),
+                input("weekly", t!("setup_weekly_time").to_string(), weekly),
+            ]),
+        )
+    }
+
+    fn parse(mut data: serenity::ModalInteractionData) -> Result<Self, &'static str> {
+        Ok(Self {
+            daily: poise::find_modal_text(&mut data, "daily").ok_or("missing daily time")?,
+            weekly: poise::find_modal_text(&mut data, "weekly").ok_or("missing weekly time")?,
+        })
+    }
+}
+
+/// Configure the notification channel, times, locale and features of this server
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn setup(ctx: Context<'_>) -> CommandResult {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    ctx.defer_ephemeral().await?;
+
+    let redis_handle = ctx.data().redis();
+    let defaults = FeatureFlags::from_names(&ctx.data().config.read().await.default_features);
+    let mut state = SetupState::new(
+        get_guild_config(&redis_handle, guild_id.get()).await,
+        get_guild_features(&redis_handle, guild_id.get(), defaults).await,
+    );
+
+    let prefix = format!("{}:setup", ctx.id());
+    let reply = ctx
+        .send(render(&prefix, &state, None).ephemeral(true))
+        .await?;
+
+    loop {
+        let filter_prefix = prefix.clone();
+        let Some(press) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+            .author_id(ctx.author().id)
+            .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+            .timeout(SETUP_TIMEOUT)
+            .await
+        else {
+            break;
+        };
+
+        let action = press
+            .data
+            .custom_id
+            .strip_prefix(&prefix)
+            .and_then(|action| action.strip_prefix(':'))
+            .unwrap_or_default();
+
+        let input = match (action, &press.data.kind) {
+            ("times", _) => {
+                // The modal is the response to the button press
+                let defaults = TimesModal {
+                    daily: state
+                        .config
+                        .daily_notification_time
+                        .clone()
+                        .unwrap_or_default(),
+                    weekly: state
+                        .config
+                        .weekly_notification_time
+                        .clone()
+                        .unwrap_or_default(),
+                };
+                match poise::execute_modal_on_component_interaction(
+                    ctx,
+                    press.clone(),
+                    Some(defaults),
+                    Some(SETUP_TIMEOUT),
+                )
+                .await?
+                {
+                    Some(times) => SetupInput::Times {
+                        daily: times.daily,
+                        weekly: times.weekly,
+                    },
+                    None => continue,
+                }
+            }
+            ("test", _) => {
+                send_test_notification(ctx, &state, &press).await?;
+                continue;
+            }
+            (action, kind) => {
+                press
+                    .create_response(
+                        ctx.serenity_context(),
+                        serenity::CreateInteractionResponse::Acknowledge,
+                    )
+                    .await?;
+
+                match (action, kind) {
+                    ("done", _) => break,
+                    ("skip", _) => SetupInput::Skip,
+                    (
+                        "channel",
+                        serenity::ComponentInteractionDataKind::ChannelSelect { values },
+                    ) => match values.first() {
+                        Some(channel_id) => SetupInput::Channel(channel_id.get()),
+                        None => continue,
+                    },
+                    ("locale", serenity::ComponentInteractionDataKind::StringSelect { values }) => {
+                        match values.first() {
+                            Some(locale) => SetupInput::Locale(locale.clone()),
+                            None => continue,
+                        }
+                    }
+                    (
+                        "features",
+                        serenity::ComponentInteractionDataKind::StringSelect { values },
+                    ) => SetupInput::Features(values.clone()),
+                    (action, _) => match action.strip_prefix("edit:").and_then(SetupStep::from_key)
+                    {
+                        Some(step) => SetupInput::Edit(step),
+                        None => continue,
+                    },
+                }
+            }
+        };
+
+        // Save after every change so an abandoned wizard can be picked up later
+        let notice = match state.apply(input) {
+            Ok(()) => {
+                save(ctx, guild_id.get(), &state).await?;
+                None
+            }
+            Err(key) => Some(t!(key).to_string()),
+        };
+
+        reply
+            .edit(ctx, render(&prefix, &state, notice.as_deref()))
+            .await?;
+    }
+
+    // Leave the final settings without the components
+    reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(summary_embed(&state))
+                .components(Vec::new()),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Write the wizard's settings to Redis
+async fn save(ctx: Context<'_>, guild_id: u64, state: &SetupState) -> BotResult<()> {
+    let redis_handle = ctx.data().redis();
+    set_guild_config(&redis_handle, guild_id, &state.config).await?;
+    set_guild_features(&redis_handle, guild_id, state.features).await?;
+
+    // The schedulers post in the bot guild's locale, so switch to it right away
+    let config = &ctx.data().config;
+    if guild_id == config.read().await.guild_id {
+        apply_locale(&redis_handle, config).await;
+    }
+    Ok(())
+}
+
+/// Post a test notification to the chosen channel and tell the admin how it went
+async fn send_test_notification(
+    ctx: Context<'_>,
+    state: &SetupState,
+    press: &serenity::ComponentInteraction,
+) -> CommandResult {
+    let result_embed = match state.config.notification_channel_id {
+        Some(channel_id) => {
+            let notification = Notification {
+                content: None,
+                embed: create_info_embed(
+                    &t!("setup_test_notification_title"),
+                    &t!("setup_test_notification"),
+                ),
+            };
+            match DiscordNotifier::new(ctx.serenity_context())
+                .send(channel_id, notification)
+                .await
+            {
+                Ok(_) => create_success_embed(
+                    &t!("setup_title"),
+                    &t!("setup_test_sent", channel = format!("<#{channel_id}>")),
+                ),
+                Err(e) => create_error_embed(&t!("error_title", context = "setup"), &e.to_string()),
+            }
+        }
+        None => create_warning_embed(&t!("setup_title"), &t!("setup_error_no_channel")),
+    };
+
+    press
+        .create_response(
+            ctx.serenity_context(),
+            serenity::CreateInteractionResponse::Message(
+                serenity::CreateInteractionResponseMessage::new()
+                    .embed(result_embed)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Format an optional value, or a placeholder when it isn't set
+fn or_not_set(value: Option<String>) -> String {
+    value.unwrap_or_else(|| t!("setup_not_set").to_string())
+}
+
+/// Current settings as embed fields
+fn setting_fields(state: &SetupState) -> Vec<(String, String)> {
+    let config = &state.config;
+    let features = Feature::ALL
+        .iter()
+        .filter(|feature| state.features.contains(**feature))
+        .map(|feature| format!("`{feature}`"))
+        .collect::<Vec<_>>();
+
+    vec![
+        (
+            t!("setup_channel").to_string(),
+            or_not_set(config.notification_channel_id.map(|id| format!("<#{id}>"))),
+        ),
+        (
+            t!("setup_daily_time").to_string(),
+            or_not_set(config.daily_notification_time.clone()),
+        ),
+        (
+            t!("setup_weekly_time").to_string(),
+            or_not_set(config.weekly_notification_time.clone()),
+        ),
+        (
+            t!("setup_locale").to_string(),
+            or_not_set(config.locale.clone()),
+        ),
+        (
+            t!("setup_features").to_string(),
+            if features.is_empty() {
+                t!("setup_no_features").to_string()
+            } else {
+                features.join(", ")
+            },
+        ),
+    ]
+}
+
+/// Embed listing the current settings
+fn summary_embed(state: &SetupState) -> serenity::CreateEmbed {
+    setting_fields(state).into_iter().fold(
+        create_success_embed(&t!("setup_title"), &t!("setup_summary")),
+        |embed, (name, value)| embed.field(name, value, true),
+    )
+}
+
+/// Build the wizard message for the current step
+fn render(prefix: &str, state: &SetupState, notice: Option<&str>) -> poise::CreateReply {
+    if state.step == SetupStep::Summary {
+        let mut embed = summary_embed(state);
+        if let Some(notice) = notice {
+            embed = embed.field(t!("setup_notice"), notice, false);
+        }
+
+        let edit_buttons = SetupStep::EDITABLE
+            .iter()
+            .map(|step| {
+                serenity::CreateButton::new(format!("{prefix}:edit:{}", step.key()))
+                    .label(t!(format!("setup_edit_{}", step.key())))
+                    .style(serenity::ButtonStyle::Secondary)
+            })
+            .collect();
+        let actions = vec![
+            serenity::CreateButton::new(format!("{prefix}:test"))
+                .label(t!("setup_send_test"))
+                .style(serenity::ButtonStyle::Primary)
+                .disabled(state.config.notification_channel_id.is_none()),
+            serenity::CreateButton::new(format!("{prefix}:done"))
+                .label(t!("setup_done"))
+                .style(serenity::ButtonStyle::Success),
+        ];
+
+        return poise::CreateReply::default().embed(embed).components(vec![
+            serenity::CreateActionRow::Buttons(edit_buttons),
+            serenity::CreateActionRow::Buttons(actions),
+        ]);
+    }
+
+    let description = match notice {
+        Some(notice) => format!(
+            "⚠️ {notice}\n\n{}",
+            t!(format!("setup_step_{}", state.step.key()))
+        ),
+        None => t!(format!("setup_step_{}", state.step.key())).to_string(),
+    };
+    let embed = setting_fields(state).into_iter().fold(
+        create_info_embed(&t!("setup_title"), &description),
+        |embed, (name, value)| embed.field(name, value, true),
+    );
+
+    let input = match state.step {
+        SetupStep::Channel => serenity::CreateActionRow::SelectMenu(
+            serenity::CreateSelectMenu::new(
+                format!("{prefix}:channel"),
+                serenity::CreateSelectMenuKind::Channel {
+                    channel_types: Some(vec![serenity::ChannelType::Text]),
+                    default_channels: state
+                        .config
+                        .notification_channel_id
+                        .map(|id| vec![serenity::ChannelId::new(id)]),
+                },
+            )
+            .placeholder(t!("setup_channel")),
+        ),
+        SetupStep::Times => serenity::CreateActionRow::Buttons(vec![serenity::CreateButton::new(
+            format!("{prefix}:times"),
+        )
+        .label(t!("setup_set_times"))
+        .style(serenity::ButtonStyle::Primary)]),
+        SetupStep::Locale => {
+            let options = available_locales()
+                .into_iter()
+                .map(|locale| {
+                    serenity::CreateSelectMenuOption::new(locale, locale)
+                        .default_selection(state.config.locale.as_deref() == Some(locale))
+                })
+                .collect();
+            serenity::CreateActionRow::SelectMenu(
+                serenity::CreateSelectMenu::new(
+                    format!("{prefix}:locale"),
+                    serenity::CreateSelectMenuKind::String { options },
+                )
+                .placeholder(t!("setup_locale")),
+            )
+        }
+        SetupStep::Features | SetupStep::Summary => {
+            let options = Feature::ALL
+                .iter()
+                .map(|feature| {
+                    serenity::CreateSelectMenuOption::new(feature.key(), feature.key())
+                        .default_selection(state.features.contains(*feature))
+                })
+                .collect();
+            serenity::CreateActionRow::SelectMenu(
+                serenity::CreateSelectMenu::new(
+                    format!("{prefix}:features"),
+                    serenity::CreateSelectMenuKind::String { options },
+                )
+                .min_values(0)
+                .max_values(Feature::ALL.len() as u8)
+                .placeholder(t!("setup_features")),
+            )
+        }
+    };
+    let skip = serenity::CreateActionRow::Buttons(vec![serenity::CreateButton::new(format!(
+        "{prefix}:skip"
+    ))
+    .label(t!("setup_skip"))
+    .style(serenity::ButtonStyle::Secondary)]);
+
+    poise::CreateReply::default()
+        .embed(embed)
+        .components(vec![input, skip])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configured() -> GuildConfig {
+        GuildConfig {
+            notification_channel_id: Some(42),
+            daily_notification_time: Some("08:00".to_string()),
+            weekly_notification_time: Some("09:00".to_string()),
+            locale: Some("en".to_string()),
+            timezone: None,
+            prefix: None,
+            theme: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_first_run_walks_through_every_step() {
+        let mut state = SetupState::new(GuildConfig::default(), FeatureFlags::default());
+        assert_eq!(state.step, SetupStep::Channel);
+
+        state.apply(SetupInput::Channel(42)).unwrap();
+        assert_eq!(state.step, SetupStep::Times);
+        state
+            .apply(SetupInput::Times {
+                daily: "8:00".to_string(),
+                weekly: " 09:30 ".to_string(),
+            })
+            .unwrap();
+        assert_eq!(state.step, SetupStep::Locale);
+        state
+            .apply(SetupInput::Locale("fi-FI".to_string()))
+            .unwrap();
+        assert_eq!(state.step, SetupStep::Features);
+        state
+            .apply(SetupInput::Features(vec!["shift_swap".to_string()]))
+            .unwrap();
+        assert_eq!(state.step, SetupStep::Summary);
+
+        assert_eq!(
+            state.config,
+            GuildConfig {
+                notification_channel_id: Some(42),
+                daily_notification_time: Some("08:00".to_string()),
+                weekly_notification_time: Some("09:30".to_string()),
+                locale: Some("fi-FI".to_string()),
+                timezone: None,
+                prefix: None,
+                theme: Default::default(),
+            }
+        );
+        assert!(state.features.contains(Feature::ShiftSwap));
+        assert!(!state.features.contains(Feature::AiQuestions));
+    }
+
+    #[test]
+    fn test_rerun_resumes_and_edits_single_step() {
+        // Fully configured guilds start from the summary
+        let mut state = SetupState::new(configured(), FeatureFlags::default());
+        assert_eq!(state.step, SetupStep::Summary);
+
+        state.apply(SetupInput::Edit(SetupStep::Locale)).unwrap();
+        assert_eq!(state.step, SetupStep::Locale);
+        state
+            .apply(SetupInput::Locale("fi-FI".to_string()))
+            .unwrap();
+        assert_eq!(state.step, SetupStep::Summary);
+        assert_eq!(state.config.locale.as_deref(), Some("fi-FI"));
+        assert_eq!(state.config.notification_channel_id, Some(42));
+
+        // Partially configured guilds continue from the first missing value
+        let mut partial = configured();
+        partial.weekly_notification_time = None;
+        partial.locale = None;
+        let mut state = SetupState::new(partial, FeatureFlags::default());
+        assert_eq!(state.step, SetupStep::Times);
+        state.apply(SetupInput::Skip).unwrap();
+        assert_eq!(state.step, SetupStep::Locale);
+    }
+
+    #[test]
+    fn test_invalid_input_keeps_step() {
+        let mut state = SetupState::new(configured(), FeatureFlags::default());
+        state.apply(SetupInput::Edit(SetupStep::Times)).unwrap();
+
+        for (daily, weekly) in [("24:00", "09:00"), ("08:00", "9"), ("", "09:00")] {
+            assert_eq!(
+                state.apply(SetupInput::Times {
+                    daily: daily.to_string(),
+                    weekly: weekly.to_string(),
+                }),
+                Err("setup_error_invalid_time")
+            );
+        }
+        assert_eq!(state.step, SetupStep::Times);
+        assert_eq!(state.config, configured());
+
+        assert_eq!(
+            state.apply(SetupInput::Channel(7)),
+            Err("setup_error_unexpected_input")
+        );
+
+        state.apply(SetupInput::Edit(SetupStep::Locale)).unwrap();
+        assert_eq!(
+            state.apply(SetupInput::Locale("xx".to_string())),
+            Err("setup_error_unknown_locale")
+        );
+
+        state.apply(SetupInput::Edit(SetupStep::Features)).unwrap();
+        assert_eq!(
+            state.apply(SetupInput::Features(vec!["bogus".to_string()])),
+            Err("setup_error_unknown_feature")
+        );
+        assert_eq!(state.step, SetupStep::Features);
+
+        state.apply(SetupInput::Skip).unwrap();
+        assert_eq!(
+            state.apply(SetupInput::Skip),
+            Err("setup_error_unexpected_input")
+        );
+    }
+}
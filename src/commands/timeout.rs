@@ -0,0 +1,214 @@
+//! Giving up on commands that take too long.
+//!
+//! Every command's actions are wrapped when the commands are collected: the original actions
+//! are kept in the command's `custom_data` and run under `command_timeout_seconds`. On timeout
+//! the action's future is dropped, cancelling whatever it was waiting on, and the invoker is
+//! told to try again shortly.
+
+use super::{CommandContext, Context};
+use crate::error::{BotResult, Error};
+use crate::utils::render::View;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use poise::serenity_prelude as serenity;
+use rust_i18n::t;
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tracing::warn;
+
+/// Commands that wait for button presses for minutes, so they run without a timeout
+const INTERACTIVE_COMMANDS: [&str; 2] = ["duplikaatit", "setup"];
+
+type Command = poise::Command<CommandContext, Error>;
+type ActionResult<'a> = Result<(), poise::FrameworkError<'a, CommandContext, Error>>;
+type SlashAction = for<'a> fn(
+    poise::ApplicationContext<'a, CommandContext, Error>,
+) -> BoxFuture<'a, ActionResult<'a>>;
+type PrefixAction =
+    for<'a> fn(poise::PrefixContext<'a, CommandContext, Error>) -> BoxFuture<'a, ActionResult<'a>>;
+
+/// The actions a wrapped command runs under the timeout
+struct TimedActions {
+    slash: Option<SlashAction>,
+    prefix: Option<PrefixAction>,
+}
+
+/// Where a command that gave up tells its invoker so
+#[async_trait]
+pub trait TimeoutReply: Sync {
+    /// Show the notice, in place of what the command has replied so far where possible
+    async fn timed_out(&self, notice: View) -> BotResult<()>;
+}
+
+#[async_trait]
+impl TimeoutReply for Context<'_> {
+    async fn timed_out(&self, notice: View) -> BotResult<()> {
+        let theme = self.data().theme(self.guild_id()).await;
+        let embed = notice.to_themed_embed(&theme);
+        match self {
+            // A processing message or deferred acknowledgement is the interaction's response
+            poise::Context::Application(ctx)
+                if ctx.has_sent_initial_response.load(Ordering::SeqCst) =>
+            {
+                ctx.interaction
+                    .edit_response(
+                        ctx.serenity_context,
+                        serenity::EditInteractionResponse::new()
+                            .content("")
+                            .embed(embed),
+                    )
+                    .await?;
+            }
+            _ => {
+                self.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run a command's body, giving up after `limit`. Returns None when it timed out, after
+/// telling the invoker through `reply`.
+pub async fn run_with_timeout<T>(
+    limit: Duration,
+    reply: &impl TimeoutReply,
+    body: impl Future<Output = T>,
+) -> Option<T> {
+    match tokio::time::timeout(limit, body).await {
+        Ok(output) => Some(output),
+        Err(_) => {
+            let notice = View::warning(
+                &t!("command_timeout_title"),
+                &t!("command_timeout_description"),
+            );
+            if let Err(e) = reply.timed_out(notice).await {
+                warn!("Failed to tell the invoker a command timed out: {}", e);
+            }
+            None
+        }
+    }
+}
+
+/// Wrap a command's actions, and those of its subcommands, in the timeout
+pub fn with_timeout(mut command: Command) -> Command {
+    if INTERACTIVE_COMMANDS.contains(&command.name.as_str()) {
+        return command;
+    }
+
+    command.subcommands = command.subcommands.into_iter().map(with_timeout).collect();
+    command.custom_data = Box::new(TimedActions {
+        slash: command.slash_action,
+        prefix: command.prefix_action,
+    });
+    if command.slash_action.is_some() {
+        command.slash_action = Some(timed_slash);
+    }
+    if command.prefix_action.is_some() {
+        command.prefix_action = Some(timed_prefix);
+    }
+    command
+}
+
+/// How long the invoked command may run
+async fn limit(data: &CommandContext) -> Duration {
+    Duration::from_secs(data.config.read().await.command_timeout_seconds)
+}
+
+fn timed_slash(
+    ctx: poise::ApplicationContext<'_, CommandContext, Error>,
+) -> BoxFuture<'_, ActionResult<'_>> {
+    Box::pin(async move {
+        let Some(action) = ctx
+            .command
+            .custom_data
+            .downcast_ref::<TimedActions>()
+            .and_then(|actions| actions.slash)
+        else {
+            return Ok(());
+        };
+        let limit = limit(ctx.data).await;
+        run_with_timeout(limit, &poise::Context::Application(ctx), action(ctx))
+            .await
+            .unwrap_or(Ok(()))
+    })
+}
+
+fn timed_prefix(
+    ctx: poise::PrefixContext<'_, CommandContext, Error>,
+) -> BoxFuture<'_, ActionResult<'_>> {
+    Box::pin(async move {
+        let Some(action) = ctx
+            .command
+            .custom_data
+            .downcast_ref::<TimedActions>()
+            .and_then(|actions| actions.prefix)
+        else {
+            return Ok(());
+        };
+        let limit = limit(ctx.data).await;
+        run_with_timeout(limit, &poise::Context::Prefix(ctx), action(ctx))
+            .await
+            .unwrap_or(Ok(()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Stands in for a command's context, keeping the notices it was given
+    #[derive(Default)]
+    struct StubReply {
+        notices: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl TimeoutReply for StubReply {
+        async fn timed_out(&self, notice: View) -> BotResult<()> {
+            self.notices
+                .lock()
+                .unwrap()
+                .push(notice.to_text().join("\n"));
+            Ok(())
+        }
+    }
+
+    /// A command handler waiting on something for `wait`
+    async fn handler(wait: Duration) -> BotResult<&'static str> {
+        tokio::time::sleep(wait).await;
+        Ok("done")
+    }
+
+    #[tokio::test]
+    async fn test_slow_command_is_cancelled_with_a_notice() {
+        let reply = StubReply::default();
+        let output = run_with_timeout(
+            Duration::from_millis(20),
+            &reply,
+            handler(Duration::from_secs(5)),
+        )
+        .await;
+
+        assert!(output.is_none());
+        let notices = reply.notices.lock().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].contains("taking too long"), "{}", notices[0]);
+    }
+
+    #[tokio::test]
+    async fn test_quick_command_finishes() {
+        let reply = StubReply::default();
+        let output = run_with_timeout(
+            Duration::from_secs(5),
+            &reply,
+            handler(Duration::from_millis(1)),
+        )
+        .await;
+
+        assert_eq!(output.unwrap().unwrap(), "done");
+        assert!(reply.notices.lock().unwrap().is_empty());
+    }
+}
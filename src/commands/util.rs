@@ -1,4 +1,14 @@
-use crate::commands::{create_success_embed, CommandResult, Context};
+use crate::commands::{create_info_embed, create_success_embed, CommandResult, Context};
+use crate::components::google_calendar::quota::{api_calls_on, quota_date};
+use crate::components::google_calendar::response::skipped_events;
+use crate::components::supervisor::restart_counts;
+use crate::components::work_schedule::credentials::{load_credential_statuses, CredentialState};
+use crate::leader::{current_leader, instance_id, leadership_metrics};
+use crate::maintenance::get_maintenance;
+use crate::utils::scheduler::notification_panics;
+use crate::watchdog::{inspect, load_heartbeats, status_line};
+use chrono::Utc;
+use poise::serenity_prelude::{Mentionable, UserId};
 use rust_i18n::t;
 
 /// Simple ping command to check if the bot is responsive
@@ -11,3 +21,143 @@ pub async fn ping(ctx: Context<'_>) -> CommandResult {
     .await?;
     Ok(())
 }
+
+/// Show the health of the bot's internal actors
+#[poise::command(slash_command, prefix_command)]
+pub async fn status(ctx: Context<'_>) -> CommandResult {
+    let lines: Vec<String> = restart_counts()
+        .into_iter()
+        .map(|(actor, restarts)| {
+            t!("status_actor_line", actor = actor, restarts = restarts).to_string()
+        })
+        .collect();
+
+    let mut description = if lines.is_empty() {
+        t!("status_no_actors").to_string()
+    } else {
+        lines.join("\n")
+    };
+
+    if let Some(component_manager) = &ctx.data().component_manager {
+        let states: Vec<String> = component_manager
+            .component_states()
+            .into_iter()
+            .map(|(component, enabled)| {
+                if enabled {
+                    t!("status_component_enabled", component = component).to_string()
+                } else {
+                    t!("status_component_disabled", component = component).to_string()
+                }
+            })
+            .collect();
+        description.push_str(&format!("\n\n{}", states.join("\n")));
+    }
+
+    if let Some(leadership) = &ctx.data().leadership {
+        let (_, changes) = leadership_metrics();
+        let line = if leadership.is_leader() {
+            t!("status_leader", instance = instance_id(), changes = changes)
+        } else {
+            let leader = current_leader(&ctx.data().redis())
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| "-".to_string());
+            t!(
+                "status_follower",
+                instance = instance_id(),
+                leader = leader,
+                changes = changes
+            )
+        };
+        description.push_str(&format!("\n\n{line}"));
+    }
+
+    let line = match get_maintenance(&ctx.data().redis()).await {
+        Some(maintenance) => t!(
+            "status_maintenance_on",
+            user = UserId::new(maintenance.enabled_by).mention(),
+            since = format!("<t:{}:R>", maintenance.enabled_at),
+            message = maintenance.message()
+        ),
+        None => t!("status_maintenance_off"),
+    };
+    description.push_str(&format!("\n\n{line}"));
+
+    // Leave the usage out when Redis can't be reached
+    let (timezone, budget) = {
+        let config = ctx.data().config.read().await;
+        (config.timezone.clone(), config.calendar_api_daily_budget)
+    };
+    let today = quota_date(&timezone, Utc::now());
+    if let Ok(calls) = api_calls_on(&ctx.data().redis(), today).await {
+        let line = if budget == 0 {
+            t!("status_calendar_api_calls", calls = calls)
+        } else {
+            t!(
+                "status_calendar_api_calls_budget",
+                calls = calls,
+                budget = budget
+            )
+        };
+        description.push_str(&format!("\n\n{line}"));
+    }
+    let skipped = skipped_events();
+    if skipped > 0 {
+        description.push_str(&format!(
+            "\n{}",
+            t!("status_calendar_skipped_events", count = skipped)
+        ));
+    }
+    let panics = notification_panics();
+    if panics > 0 {
+        description.push_str(&format!(
+            "\n{}",
+            t!("status_notification_panics", count = panics)
+        ));
+    }
+
+    // Heartbeats of the schedulers running on the leader, and what the watchdog finds wrong
+    let now = Utc::now().timestamp();
+    if let Ok(heartbeats) = load_heartbeats(&ctx.data().redis()).await {
+        for (scheduler, heartbeat) in heartbeats.iter().filter(|(_, h)| !h.is_stale(now)) {
+            let line = t!(
+                "status_scheduler_ok",
+                scheduler = scheduler,
+                since = format!("<t:{}:R>", heartbeat.at),
+                wake = format!("<t:{}:R>", heartbeat.wake)
+            );
+            description.push_str(&format!("\n{line}"));
+        }
+    }
+    if let Ok(findings) = inspect(&ctx.data().redis(), now).await {
+        for finding in &findings {
+            description.push_str(&format!("\n{}", status_line(finding)));
+        }
+    }
+
+    // Checked by work_hours, so nothing is shown before it has run
+    if let Ok(statuses) = load_credential_statuses(&ctx.data().redis()).await {
+        for status in statuses {
+            let line = t!(
+                "status_credential",
+                icon = match status.state {
+                    CredentialState::Ok => "🟢",
+                    CredentialState::AuthFailed => "🔴",
+                    CredentialState::Unreachable => "🟡",
+                },
+                provider = status.provider,
+                state = t!(format!("credential_state_{}", status.state.as_str())),
+                checked = format!("<t:{}:R>", status.checked_at),
+                since = format!("<t:{}:R>", status.since)
+            );
+            description.push_str(&format!("\n{line}"));
+        }
+    }
+
+    ctx.send(
+        poise::CreateReply::default().embed(create_info_embed(&t!("status_title"), &description)),
+    )
+    .await?;
+    Ok(())
+}
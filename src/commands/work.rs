@@ -1,254 +1,383 @@
 use crate::commands::{
-    create_error_embed, create_info_embed, create_success_embed, create_warning_embed,
-    CommandResult, Context,
+    create_error_embed, create_info_embed, create_success_embed, create_warning_embed, is_admin,
+    not_in_maintenance, schedule_rate_limit, send_view, with_maintenance_notice,
+    work_schedule_enabled, CommandContext, CommandResult, Context,
+};
+use crate::components::work_schedule::actuals::{
+    check_actuals_date, parse_actual_range, ActualsRefusal, MAX_ACTUALS_AGE_DAYS,
+};
+use crate::components::work_schedule::corrections::{
+    button_id, check_requester, parse_correction_value, store_correction, Correction,
+    CorrectionAction, CorrectionRefusal,
+};
+use crate::components::work_schedule::groups::{load_employee_groups, EmployeeFilter};
+use crate::components::work_schedule::models::{parse_minutes, ContextLink};
+use crate::components::work_schedule::overlap::{DuplicateShift, KeepChoice};
+use crate::components::work_schedule::parse_failures::load_parse_failures;
+use crate::components::work_schedule::quality::{
+    load_parse_records, quality_trend, weekly_quality, DEFAULT_QUALITY_WEEKS, MAX_QUALITY_WEEKS,
+};
+use crate::components::work_schedule::render::{
+    actuals_variance_view, compact_day_schedules, compact_employee_days, compact_week_overview,
+    day_schedules, employee_days, week_overview, weekend_fairness_view, weekend_overview,
+    ScheduleFormatter,
+};
+use crate::components::work_schedule::stats::{
+    busiest_week, compress_dates, variance, weekend_fairness, DayRange, Variance,
+};
+use crate::components::work_schedule::week_nav::{
+    can_step, WeekNav, WeekTarget, BUTTON_PREFIX as WEEK_NAV_PREFIX,
 };
 use crate::components::work_schedule::{WorkSchedule, WorkScheduleHandle};
+use crate::components::EventBus;
 use crate::config::Config;
-use chrono::{Duration, Local, NaiveDate};
+use crate::error::Error;
+use crate::user_preferences::{get_user_preferences, OutputFormat, ScheduleStyle};
+use crate::utils::discord::parse_message_link;
+use crate::utils::embed::{limit_fields, truncate};
+use crate::utils::i18n::{humanize_duration, weekday_name};
+use crate::utils::render::{View, ViewLine};
+use crate::utils::time::{parse_user_date, week_bounds, week_label, week_range_label};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Weekday};
 use poise::serenity_prelude as serenity;
 use rust_i18n::t;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::debug;
 
+/// How long week navigation buttons stay usable after their last press
+const WEEK_BUTTON_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Reply for a schedule that couldn't be fetched
+fn fetch_error(context: &str, resource: &str, error: &Error) -> View {
+    View::error(
+        &t!("error_title", context = context),
+        &t!(
+            "work_schedule_error_fetching",
+            resource = resource,
+            error = error.to_string()
+        ),
+    )
+}
+
+/// Filter for the employee group given to a command, or a notice for the invoker if there's no
+/// such group
+async fn group_filter(data: &CommandContext, group: Option<&str>) -> Result<EmployeeFilter, View> {
+    let Some(group) = group else {
+        return Ok(EmployeeFilter::All);
+    };
+    let groups = load_employee_groups(&data.redis())
+        .await
+        .map_err(|e| fetch_error("groups", "employee groups", &e))?;
+    groups.filter(group).ok_or_else(|| {
+        View::warning(
+            &t!("employee_groups_title"),
+            &t!("employee_groups_unknown", group = group),
+        )
+    })
+}
+
+/// Schedule of every employee passing `filter` over a range, or a notice for the invoker on
+/// why it can't be shown
+async fn week_overview_view(
+    handle: &WorkScheduleHandle,
+    title: String,
+    start_date: &str,
+    end_date: &str,
+    filter: &EmployeeFilter,
+    formatter: &ScheduleFormatter,
+    style: ScheduleStyle,
+) -> Result<View, View> {
+    let employees: Vec<String> = match handle.get_employees().await {
+        Ok(employees) => employees
+            .into_iter()
+            .filter(|employee| filter.allows(employee))
+            .collect(),
+        Err(e) => return Err(fetch_error("employees", "employees", &e)),
+    };
+    if employees.is_empty() {
+        return Err(View::info(&title, &t!("work_schedule_no_employees")));
+    }
+
+    let mut schedules = Vec::with_capacity(employees.len());
+    for employee in employees {
+        let entries = handle
+            .get_schedule_for_date_range(
+                employee.clone(),
+                start_date.to_string(),
+                end_date.to_string(),
+            )
+            .await
+            .map(|schedule| schedule.schedule);
+        schedules.push((employee, entries));
+    }
+    Ok(match style {
+        ScheduleStyle::Normal => week_overview(title, schedules, formatter),
+        ScheduleStyle::Compact => compact_week_overview(title, schedules),
+    })
+}
+
 /// Get work schedule for this week
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
 pub async fn tyovuorot(
     ctx: Context<'_>,
     #[description = "Employee name (leave empty for all employees)"] employee: Option<String>,
+    #[description = "Employee group to show (leave empty for all employees)"] group: Option<String>,
+    #[description = "Layout (default: your /preferences style)"] style: Option<ScheduleStyle>,
 ) -> CommandResult {
-    // Start response with waiting message
-    let response = ctx
-        .say(t!(
-            "fetch_processing",
-            resource = "work schedules for this week"
-        ))
-        .await?;
-
-    // Get the handle to work schedule
-    let handle = get_work_schedule_handle(
-        ctx.data().component_manager.as_ref(),
-        ctx.data().config.clone(),
+    send_week(
+        ctx,
+        WeekTarget::from_options(employee, group),
+        style,
+        0,
+        "work schedules for this week",
     )
-    .await;
+    .await
+}
 
-    // Calculate the date range for this week (Monday to Sunday)
-    let now = Local::now();
-    let today = now.date_naive();
-    let weekday_num = today.format("%u").to_string().parse::<u32>().unwrap_or(1); // 1=Monday, 7=Sunday
-    let days_since_monday = weekday_num - 1;
-    let monday = today
-        .checked_sub_signed(Duration::days(days_since_monday as i64))
-        .unwrap_or(today);
-    let sunday = monday
-        .checked_add_signed(Duration::days(6))
-        .unwrap_or(monday);
-
-    let start_date = monday.format("%Y-%m-%d").to_string();
-    let end_date = sunday.format("%Y-%m-%d").to_string();
-
-    if let Some(emp) = employee {
+/// The week a navigation state points at, or a notice for the invoker on why it can't be shown. Shared by the week commands and their navigation buttons.
+pub async fn render_week(data: &CommandContext, nav: &WeekNav) -> Result<View, View> {
+    let filter = match &nav.target {
+        WeekTarget::Group(group) => group_filter(data, Some(group)).await?,
+        _ => EmployeeFilter::All,
+    };
+
+    let handle =
+        get_work_schedule_handle(data.component_manager.as_ref(), data.config.clone()).await;
+    let formatter = handle.formatter().await;
+
+    let week_start = data.config.read().await.week_starts_on;
+    let (current, _) = week_bounds(Local::now().date_naive(), week_start);
+    let (first, last) = week_bounds(nav.week_start, week_start);
+
+    let start_date = first.format("%Y-%m-%d").to_string();
+    let end_date = last.format("%Y-%m-%d").to_string();
+    let mut week = week_label(last, &rust_i18n::locale());
+    if first == current + Duration::weeks(1) {
+        week = format!("{} · {week}", t!("calendar_next_week"));
+    }
+
+    let view = if let WeekTarget::Employee(emp) = &nav.target {
         // Get schedule for specific employee
         match handle
             .get_schedule_for_date_range(emp.clone(), start_date.clone(), end_date.clone())
             .await
         {
             Ok(schedule) => {
-                let title = t!("work_schedule_employee_title", employee = emp);
-                let mut embed = serenity::CreateEmbed::new()
-                    .title(title)
-                    .description(format!("{start_date} - {end_date}"))
-                    .color(0x00_99_FF); // Blue color
-
-                if schedule.schedule.is_empty() {
-                    embed = embed
-                        .description(t!("work_schedule_no_entries_for_employee", employee = emp));
-                } else {
-                    let mut field_content;
-
-                    for entry in schedule.schedule {
-                        // Parse date to get day of week
-                        if let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
-                            let weekday_num =
-                                date.format("%u").to_string().parse::<u32>().unwrap_or(0);
-                            let day_name = match weekday_num {
-                                1 => t!("day_monday"),
-                                2 => t!("day_tuesday"),
-                                3 => t!("day_wednesday"),
-                                4 => t!("day_thursday"),
-                                5 => t!("day_friday"),
-                                6 => t!("day_saturday"),
-                                7 => t!("day_sunday"),
-                                _ => t!("day_unknown"),
-                            };
-
-                            // Format as field per day
-                            field_content = entry.format();
-                            embed = embed.field(
-                                format!("{} ({})", day_name, entry.date),
-                                field_content,
-                                false,
-                            );
-                        } else {
-                            // Fallback if we can't parse the date
-                            field_content = entry.format();
-                            embed = embed.field(entry.date, field_content, false);
-                        }
-                    }
-                }
-
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "schedule"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "schedule",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
+                let title = format!(
+                    "{} · {week}",
+                    t!(
+                        "work_schedule_employee_title",
+                        employee = formatter.employee(emp)
+                    )
+                );
+                Ok(match nav.style {
+                    ScheduleStyle::Normal => employee_days(
+                        title,
+                        Some((&start_date, &end_date)),
+                        emp,
+                        &schedule.schedule,
+                        &formatter,
+                    ),
+                    ScheduleStyle::Compact => compact_employee_days(title, emp, &schedule.schedule),
+                })
             }
+            Err(e) => Err(fetch_error("schedule", "schedule", &e)),
         }
     } else {
-        // Get all employees
-        match handle.get_employees().await {
-            Ok(employees) => {
-                if employees.is_empty() {
-                    let _ = response.delete(ctx).await;
-                    ctx.send(
-                        poise::CreateReply::default()
-                            .embed(create_info_embed(
-                                &t!(
-                                    "work_schedule_weekly_title",
-                                    start_date = start_date,
-                                    end_date = end_date
-                                ),
-                                &t!("work_schedule_no_employees"),
-                            ))
-                            .ephemeral(true),
-                    )
-                    .await?;
-                    return Ok(());
-                }
+        let title = format!(
+            "{} · {week}",
+            t!(
+                "work_schedule_weekly_title",
+                start_date = start_date,
+                end_date = end_date
+            )
+        );
+        week_overview_view(
+            &handle,
+            title,
+            &start_date,
+            &end_date,
+            &filter,
+            &formatter,
+            nav.style,
+        )
+        .await
+    };
 
-                let mut embed = serenity::CreateEmbed::new()
-                    .title(t!(
-                        "work_schedule_weekly_title",
-                        start_date = start_date,
-                        end_date = end_date
-                    ))
-                    .color(0x00_99_FF); // Blue color
-
-                for emp in employees {
-                    match handle
-                        .get_schedule_for_date_range(
-                            emp.clone(),
-                            start_date.clone(),
-                            end_date.clone(),
-                        )
-                        .await
-                    {
-                        Ok(schedule) => {
-                            if schedule.schedule.is_empty() {
-                                embed =
-                                    embed.field(emp, t!("work_schedule_no_entries_found"), false);
-                            } else {
-                                let mut field_value = String::new();
-                                for entry in schedule.schedule {
-                                    // Parse date to get day of week
-                                    if let Ok(date) =
-                                        NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d")
-                                    {
-                                        let weekday_num = date
-                                            .format("%u")
-                                            .to_string()
-                                            .parse::<u32>()
-                                            .unwrap_or(0);
-                                        let day_name = match weekday_num {
-                                            1 => t!("day_short_monday"),
-                                            2 => t!("day_short_tuesday"),
-                                            3 => t!("day_short_wednesday"),
-                                            4 => t!("day_short_thursday"),
-                                            5 => t!("day_short_friday"),
-                                            6 => t!("day_short_saturday"),
-                                            7 => t!("day_short_sunday"),
-                                            _ => t!("day_short_unknown"),
-                                        };
-
-                                        field_value.push_str(&format!(
-                                            "• **{}**: {}\n",
-                                            day_name,
-                                            entry.format()
-                                        ));
-                                    } else {
-                                        field_value.push_str(&format!(
-                                            "• {}: {}\n",
-                                            entry.date,
-                                            entry.format()
-                                        ));
-                                    }
-                                }
-                                embed = embed.field(emp, field_value, false);
-                            }
-                        }
-                        Err(e) => {
-                            embed = embed.field(
-                                emp.clone(),
-                                t!(
-                                    "work_schedule_error_fetching",
-                                    resource = emp.clone(),
-                                    error = e.to_string()
-                                ),
-                                false,
-                            );
-                        }
-                    }
-                }
+    handle.record_missing_notes(&formatter).await;
+    view
+}
 
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "employees"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "employees",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
-            }
+/// ◀️ / ▶️ buttons stepping a shown week, disabled at the edges of their reach or once expired.
+/// None when the target's name doesn't fit a button.
+pub fn week_buttons(
+    current: NaiveDate,
+    shown: &WeekNav,
+    active: bool,
+) -> Option<Vec<serenity::CreateActionRow>> {
+    let (back, forward) = can_step(current, shown.week_start);
+    let button = |weeks: i64, emoji: char, enabled: bool| {
+        shown.shifted(weeks).button_id().map(|id| {
+            serenity::CreateButton::new(id)
+                .emoji(emoji)
+                .style(serenity::ButtonStyle::Secondary)
+                .disabled(!(active && enabled))
+        })
+    };
+    Some(vec![serenity::CreateActionRow::Buttons(vec![
+        button(-1, '◀', back)?,
+        button(1, '▶', forward)?,
+    ])])
+}
+
+/// The week a message's navigation buttons step from
+fn shown_week(message: &serenity::Message) -> Option<WeekNav> {
+    message
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            serenity::ActionRowComponent::Button(serenity::Button {
+                data: serenity::ButtonKind::NonLink { custom_id, .. },
+                ..
+            }) => WeekNav::parse(custom_id),
+            _ => None,
+        })
+        .map(|back| back.shifted(1))
+}
+
+/// Reply with a target's week and buttons stepping it; presses are answered by the global
+/// handler, and a background task disables the buttons once they've gone unused for a while
+async fn send_week(
+    ctx: Context<'_>,
+    target: WeekTarget,
+    style: Option<ScheduleStyle>,
+    offset: i64,
+    resource: &str,
+) -> CommandResult {
+    if let WeekTarget::Group(group) = &target {
+        if let Err(notice) = group_filter(ctx.data(), Some(group)).await {
+            return send_view(ctx, notice, true).await;
         }
     }
 
+    // Start response with waiting message
+    let response = ctx.say(t!("fetch_processing", resource = resource)).await?;
+
+    let preferences = get_user_preferences(&ctx.data().redis(), ctx.author().id.get()).await;
+    let week_start = ctx.data().config.read().await.week_starts_on;
+    let (current, _) = week_bounds(Local::now().date_naive(), week_start);
+    let nav = WeekNav {
+        week_start: current + Duration::weeks(offset),
+        target,
+        style: style.unwrap_or(preferences.schedule_style),
+    };
+    let view = render_week(ctx.data(), &nav).await;
+
+    // Delete the waiting message and send the schedule
+    let _ = response.delete(ctx).await;
+    let view = match view {
+        Ok(view) => view,
+        Err(notice) => return send_view(ctx, notice, true).await,
+    };
+
+    // Text replies may span several messages, so only embeds are stepped in place
+    let buttons = week_buttons(current, &nav, true);
+    let (OutputFormat::Embed, Some(buttons)) = (preferences.output_format, buttons) else {
+        return send_view(ctx, view, false).await;
+    };
+
+    let view = with_maintenance_notice(ctx.data(), view).await;
+    let theme = ctx.data().theme(ctx.guild_id()).await;
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(view.to_themed_embed(&theme))
+                .components(buttons),
+        )
+        .await?;
+    let message = reply.message().await?;
+
+    // The wait outlives the command, which is cancelled once it runs past its timeout
+    tokio::spawn(expire_week_buttons(
+        ctx.serenity_context().clone(),
+        message.channel_id,
+        message.id,
+        current,
+    ));
+
     Ok(())
 }
 
+/// Disable a week reply's buttons once they've gone unused for a while
+async fn expire_week_buttons(
+    ctx: serenity::Context,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+    current: NaiveDate,
+) {
+    // Every press restarts the wait
+    while serenity::ComponentInteractionCollector::new(&ctx)
+        .message_id(message_id)
+        .filter(|press| press.data.custom_id.starts_with(WEEK_NAV_PREFIX))
+        .timeout(WEEK_BUTTON_TIMEOUT)
+        .await
+        .is_some()
+    {}
+
+    // Disable the buttons on whichever week is shown by now
+    let message = match channel_id.message(&ctx, message_id).await {
+        Ok(message) => message,
+        Err(e) => {
+            debug!("Failed to fetch week reply to disable its buttons: {}", e);
+            return;
+        }
+    };
+    let Some(shown) = shown_week(&message) else {
+        return;
+    };
+    let edit = serenity::EditMessage::new()
+        .components(week_buttons(current, &shown, false).unwrap_or_default());
+    if let Err(e) = channel_id.edit_message(&ctx, message_id, edit).await {
+        debug!("Failed to disable week buttons: {}", e);
+    }
+}
+
 /// Get work schedule for a specific date
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
 pub async fn day(
     ctx: Context<'_>,
-    #[description = "Date (YYYY-MM-DD)"] date: String,
+    #[description = "Date: YYYY-MM-DD, d.m., vko27, today, tomorrow or a weekday"] date: String,
     #[description = "Employee name (leave empty for all employees)"] employee: Option<String>,
+    #[description = "Employee group to show (leave empty for all employees)"] group: Option<String>,
+    #[description = "Layout (default: your /preferences style)"] style: Option<ScheduleStyle>,
 ) -> CommandResult {
+    let filter = match group_filter(ctx.data(), group.as_deref()).await {
+        Ok(filter) => filter,
+        Err(notice) => return send_view(ctx, notice, true).await,
+    };
+
+    // Resolve shortcuts to an ISO date, which the titles show back
+    let Some(date) = parse_user_date(&date, Local::now().date_naive(), &rust_i18n::locale()) else {
+        let view = View::warning(
+            &t!("work_schedule_invalid_date"),
+            &t!("work_schedule_invalid_date"),
+        );
+        return send_view(ctx, view, true).await;
+    };
+    let date = date.format("%Y-%m-%d").to_string();
+
     // Start response with waiting message
     let response = ctx
         .say(t!(
@@ -263,24 +392,17 @@ pub async fn day(
         ctx.data().config.clone(),
     )
     .await;
+    let formatter = handle.formatter().await;
+    let style = match style {
+        Some(style) => style,
+        None => {
+            get_user_preferences(&ctx.data().redis(), ctx.author().id.get())
+                .await
+                .schedule_style
+        }
+    };
 
-    // Validate date format
-    if NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
-        // Delete the waiting message and send the error
-        let _ = response.delete(ctx).await;
-        ctx.send(
-            poise::CreateReply::default()
-                .embed(create_warning_embed(
-                    &t!("work_schedule_invalid_date"),
-                    &t!("work_schedule_invalid_date"),
-                ))
-                .ephemeral(true),
-        )
-        .await?;
-        return Ok(());
-    }
-
-    if let Some(emp) = employee {
+    let (view, ephemeral) = if let Some(emp) = employee {
         // Get schedule for specific employee on specific date
         match handle.get_entry_for_employee_date(&emp, &date).await {
             Ok(entry) => {
@@ -289,123 +411,58 @@ pub async fn day(
                     employee = emp,
                     date = date
                 );
-
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_success_embed(&title, &entry.format())),
-                )
-                .await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "schedule"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "schedule",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
+                let view = match style {
+                    ScheduleStyle::Normal => View::success(&title, &formatter.format_full(&entry)),
+                    ScheduleStyle::Compact => {
+                        compact_employee_days(title.to_string(), &emp, &[entry])
+                    }
+                };
+                (view, false)
             }
+            Err(e) => (fetch_error("schedule", "schedule", &e), true),
         }
     } else {
         // Get schedule for all employees on specific date
-        match handle.get_schedule_for_date(&date).await {
+        let schedules = handle
+            .get_schedule_for_date(&date)
+            .await
+            .map(|mut schedules| {
+                schedules.retain(|employee| filter.allows(employee));
+                schedules
+            });
+        match schedules {
+            Ok(schedules) if schedules.is_empty() && schedules.missing().is_empty() => (
+                View::info(
+                    &t!("work_schedule_date_title", date = date),
+                    &t!("work_schedule_no_schedules_found", date = date),
+                ),
+                false,
+            ),
             Ok(schedules) => {
-                if schedules.is_empty() {
-                    // Delete the waiting message and send the info
-                    let _ = response.delete(ctx).await;
-                    ctx.send(poise::CreateReply::default().embed(create_info_embed(
-                        &t!("work_schedule_date_title", date = date),
-                        &t!("work_schedule_no_schedules_found", date = date),
-                    )))
-                    .await?;
-                    return Ok(());
-                }
-
-                // Try to parse the date to get day of week
-                let day_header =
-                    if let Ok(parsed_date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
-                        let weekday_num = parsed_date
-                            .format("%u")
-                            .to_string()
-                            .parse::<u32>()
-                            .unwrap_or(0);
-                        let day_name = match weekday_num {
-                            1 => t!("day_monday"),
-                            2 => t!("day_tuesday"),
-                            3 => t!("day_wednesday"),
-                            4 => t!("day_thursday"),
-                            5 => t!("day_friday"),
-                            6 => t!("day_saturday"),
-                            7 => t!("day_sunday"),
-                            _ => t!("day_unknown"),
-                        };
-
-                        format!("{day_name} ({date})")
-                    } else {
-                        date.clone()
-                    };
-
-                let title = t!("work_schedule_date_title", date = day_header);
-                let mut embed = serenity::CreateEmbed::new().title(title).color(0x00_99_FF); // Blue color
-
-                // Check if everyone has a day off
-                let all_day_off = schedules.values().all(|entry| entry.is_day_off);
-                if all_day_off {
-                    embed = embed
-                        .description(t!("work_schedule_all_day_off"))
-                        .image("https://media.giphy.com/media/v1.Y2lkPTc5MGI3NjExdG9nM3J1YnA1NHcxc2cwcmE5bjNqOWF1eHZsY3h3MDBxbDl5aGdldiZlcD12MV9pbnRlcm5hbF9naWZfYnlfaWQmY3Q9Zw/DKnMqdm9i980E/giphy.gif");
-                } else {
-                    // Add fields for each employee sorted alphabetically
-                    let mut employees: Vec<(
-                        &String,
-                        &crate::components::work_schedule::models::WorkScheduleEntry,
-                    )> = schedules.iter().collect();
-                    employees.sort_by(|a, b| a.0.cmp(b.0));
-
-                    for (emp, entry) in employees {
-                        embed = embed.field(emp, entry.format(), false);
-                    }
-                }
-
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "schedule"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "schedules",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
+                let view = match style {
+                    ScheduleStyle::Normal => day_schedules(&date, &schedules, &formatter),
+                    ScheduleStyle::Compact => compact_day_schedules(&date, &schedules),
+                };
+                (view, false)
             }
+            Err(e) => (fetch_error("schedule", "schedules", &e), true),
         }
-    }
+    };
 
-    Ok(())
+    handle.record_missing_notes(&formatter).await;
+
+    // Delete the waiting message and send the schedule
+    let _ = response.delete(ctx).await;
+    send_view(ctx, view, ephemeral).await
 }
 
 /// Get an employee's work schedule
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
 pub async fn employee(
     ctx: Context<'_>,
     #[description = "Employee name"] employee: String,
@@ -424,379 +481,1078 @@ pub async fn employee(
         ctx.data().config.clone(),
     )
     .await;
+    let formatter = handle.formatter().await;
 
     // Get schedule for employee
-    match handle.get_schedule_for_employee(employee.clone()).await {
-        Ok(schedule) => {
-            let title = t!("work_schedule_employee_title", employee = employee);
-            let mut embed = serenity::CreateEmbed::new().title(title).color(0x00_99_FF); // Blue color
-
-            if schedule.schedule.is_empty() {
-                embed = embed.description(t!(
-                    "work_schedule_no_entries_for_employee",
-                    employee = employee
-                ));
-            } else {
-                // Group entries by day
-                let mut day_entries: std::collections::HashMap<
-                    String,
-                    Vec<&crate::components::work_schedule::models::WorkScheduleEntry>,
-                > = std::collections::HashMap::new();
-
-                for entry in &schedule.schedule {
-                    if let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
-                        let weekday_num = date.format("%u").to_string().parse::<u32>().unwrap_or(0);
-                        let day_name = match weekday_num {
-                            1 => t!("day_monday"),
-                            2 => t!("day_tuesday"),
-                            3 => t!("day_wednesday"),
-                            4 => t!("day_thursday"),
-                            5 => t!("day_friday"),
-                            6 => t!("day_saturday"),
-                            7 => t!("day_sunday"),
-                            _ => t!("day_unknown"),
-                        };
-
-                        let day_key = format!("{} ({})", day_name, entry.date);
-                        day_entries.entry(day_key).or_default().push(entry);
-                    } else {
-                        // If we can't parse the date, just use the date string
-                        day_entries
-                            .entry(entry.date.clone())
-                            .or_default()
-                            .push(entry);
-                    }
-                }
+    let (view, ephemeral) = match handle.get_schedule_for_employee(employee.clone()).await {
+        Ok(schedule) => (
+            employee_days(
+                t!(
+                    "work_schedule_employee_title",
+                    employee = formatter.employee(&employee)
+                )
+                .to_string(),
+                None,
+                &employee,
+                &schedule.schedule,
+                &formatter,
+            ),
+            false,
+        ),
+        Err(e) => (fetch_error("schedule", "schedule", &e), true),
+    };
+
+    handle.record_missing_notes(&formatter).await;
+
+    // Delete the waiting message and send the schedule
+    let _ = response.delete(ctx).await;
+    send_view(ctx, view, ephemeral).await
+}
 
-                // Sort days by date
-                let mut days: Vec<(
-                    String,
-                    Vec<&crate::components::work_schedule::models::WorkScheduleEntry>,
-                )> = day_entries.into_iter().collect();
-                days.sort_by(|a, b| {
-                    let a_date = a.1.first().map(|e| e.date.clone()).unwrap_or_default();
-                    let b_date = b.1.first().map(|e| e.date.clone()).unwrap_or_default();
-                    a_date.cmp(&b_date)
-                });
-
-                // Add fields for each day
-                for (day, entries) in days {
-                    let entry_fmt = if entries.len() == 1 {
-                        entries[0].format()
-                    } else {
-                        let mut times = String::new();
-                        for (i, entry) in entries.iter().enumerate() {
-                            if i > 0 {
-                                times.push('\n');
-                            }
-                            times.push_str(&format!("• {}", entry.format()));
-                        }
-                        times
-                    };
-
-                    embed = embed.field(day, entry_fmt, false);
+/// Show when an employee works next
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn seuraava_vuoro(
+    ctx: Context<'_>,
+    #[description = "Employee name (leave empty for your linked employee)"] employee: Option<
+        String,
+    >,
+) -> CommandResult {
+    let employee = match employee {
+        Some(employee) => employee,
+        None => {
+            let preferences =
+                get_user_preferences(&ctx.data().redis(), ctx.author().id.get()).await;
+            match preferences.employee {
+                Some(employee) => employee,
+                None => {
+                    let view = View::warning(
+                        &t!("next_shift_title_generic"),
+                        &t!("next_shift_no_employee"),
+                    );
+                    return send_view(ctx, view, true).await;
                 }
             }
+        }
+    };
+
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let formatter = handle.formatter().await;
+
+    let schedule = match handle.get_schedule_for_employee(employee.clone()).await {
+        Ok(schedule) => schedule,
+        Err(e) => return send_view(ctx, fetch_error("schedule", "schedule", &e), true).await,
+    };
+
+    let now = Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let now_minutes = now.hour() * 60 + now.minute();
+    let title = t!("next_shift_title", employee = formatter.employee(&employee));
+
+    let next = schedule.next_shift(&today, now_minutes).and_then(|entry| {
+        let date = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").ok()?;
+        Some((entry, date))
+    });
+    let Some((entry, date)) = next else {
+        let message = match schedule.last_date() {
+            Some(last_date) => t!("next_shift_none", date = last_date),
+            None => t!("work_schedule_no_entries_for_employee", employee = employee),
+        };
+        return send_view(ctx, View::warning(&title, &message), false).await;
+    };
+
+    // Count down to the first shift that hasn't started yet
+    let start = entry
+        .shifts
+        .iter()
+        .filter_map(|shift| parse_minutes(shift.start.as_deref()?))
+        .find(|minutes| entry.date != today || *minutes > now_minutes)
+        .and_then(|minutes| {
+            let time = date.and_hms_opt(minutes / 60, minutes % 60, 0)?;
+            Local.from_local_datetime(&time).earliest()
+        });
+    let relative = match start {
+        Some(start) => humanize_duration(start - now),
+        None => t!("relative_now").to_string(),
+    };
+
+    let message = t!(
+        "next_shift_description",
+        weekday = weekday_name(date.weekday()),
+        date = date.format("%d.%m.%Y"),
+        hours = formatter.format(entry),
+        relative = relative
+    );
+    let mut view = View::success(&title, &message);
+    if let Some(avatar_url) = formatter.avatar_url(&employee) {
+        view = view.thumbnail(avatar_url);
+    }
+    handle.record_missing_notes(&formatter).await;
+    send_view(ctx, view, false).await
+}
+
+/// Get work schedule for next week
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn ensiviikko(
+    ctx: Context<'_>,
+    #[description = "Employee name (leave empty for all employees)"] employee: Option<String>,
+    #[description = "Employee group to show (leave empty for all employees)"] group: Option<String>,
+    #[description = "Layout (default: your /preferences style)"] style: Option<ScheduleStyle>,
+) -> CommandResult {
+    send_week(
+        ctx,
+        WeekTarget::from_options(employee, group),
+        style,
+        1,
+        "work schedules for next week",
+    )
+    .await
+}
+
+/// Show how far each employee's stored schedule reaches
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn kattavuus(ctx: Context<'_>) -> CommandResult {
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+
+    let coverage = match handle.get_employees_coverage().await {
+        Ok(coverage) => coverage,
+        Err(e) => return send_view(ctx, fetch_error("coverage", "coverage", &e), true).await,
+    };
+    let title = t!("coverage_title");
+    if coverage.is_empty() {
+        return send_view(
+            ctx,
+            View::info(&title, &t!("work_schedule_no_employees")),
+            true,
+        )
+        .await;
+    }
+
+    let lines = coverage
+        .iter()
+        .map(|info| {
+            let text = match (&info.first_date, &info.last_date) {
+                (Some(first), Some(last)) => t!(
+                    "coverage_line",
+                    first = first,
+                    last = last,
+                    weeks = coverage_weeks(first, last),
+                    days = info.day_count,
+                    working = info.working_day_count
+                ),
+                _ => t!("coverage_none"),
+            };
+            ViewLine::new(format!("**{}**: {text}", info.employee))
+        })
+        .collect();
+    send_view(
+        ctx,
+        View::info(&title, &t!("coverage_description")).field("\u{200B}", lines),
+        false,
+    )
+    .await
+}
+
+/// Week numbers covered by a stored schedule's `YYYY-MM-DD` date range
+fn coverage_weeks(first: &str, last: &str) -> String {
+    match (
+        NaiveDate::parse_from_str(first, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(last, "%Y-%m-%d"),
+    ) {
+        (Ok(first), Ok(last)) => week_range_label(first, last, &rust_i18n::locale()),
+        _ => String::new(),
+    }
+}
+
+/// Weeks /lomat shows unless asked otherwise
+const DEFAULT_VACATION_WEEKS: u32 = 6;
+/// Most weeks /lomat shows at once
+const MAX_VACATION_WEEKS: u32 = 12;
+
+/// Show everyone's vacation days over the coming weeks
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn lomat(
+    ctx: Context<'_>,
+    #[description = "Number of weeks to show (default 6, at most 12)"]
+    #[min = 1]
+    #[max = 12]
+    weeks: Option<u32>,
+) -> CommandResult {
+    let weeks = weeks
+        .unwrap_or(DEFAULT_VACATION_WEEKS)
+        .clamp(1, MAX_VACATION_WEEKS);
+    let week_start = ctx.data().config.read().await.week_starts_on;
+    let today = Local::now().date_naive();
+    let last = today + Duration::days(i64::from(weeks) * 7 - 1);
+    let start_date = today.format("%Y-%m-%d").to_string();
+    let end_date = last.format("%Y-%m-%d").to_string();
+
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let schedules = match handle
+        .get_stored_entries_for_range(&start_date, &end_date)
+        .await
+    {
+        Ok(schedules) => schedules,
+        Err(e) => return send_view(ctx, fetch_error("vacations", "vacations", &e), true).await,
+    };
+
+    let away: Vec<(String, Vec<NaiveDate>)> = schedules
+        .into_iter()
+        .map(|schedule| {
+            let dates: Vec<NaiveDate> = schedule
+                .schedule
+                .iter()
+                .filter(|entry| entry.is_vacation())
+                .filter_map(|entry| NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").ok())
+                .collect();
+            (schedule.employee, dates)
+        })
+        .filter(|(_, dates)| !dates.is_empty())
+        .collect();
+
+    let title = format!(
+        "{} · {}",
+        t!(
+            "vacations_title",
+            start_date = start_date,
+            end_date = end_date
+        ),
+        week_range_label(today, last, &rust_i18n::locale())
+    );
+    let dates: Vec<Vec<NaiveDate>> = away.iter().map(|(_, dates)| dates.clone()).collect();
+    let Some((busiest, count)) = busiest_week(&dates, week_start) else {
+        return send_view(ctx, View::info(&title, &t!("vacations_none")), false).await;
+    };
+
+    let view = away.iter().fold(
+        View::info(
+            &title,
+            &t!(
+                "vacations_busiest_week",
+                week = week_label(
+                    busiest
+                        .checked_add_signed(Duration::days(6))
+                        .unwrap_or(busiest),
+                    &rust_i18n::locale()
+                ),
+                start = busiest.format("%-d.%-m.").to_string(),
+                count = count
+            ),
+        ),
+        |view, (employee, dates)| {
+            let ranges: Vec<String> = compress_dates(dates).iter().map(DayRange::format).collect();
+            view.field(employee, vec![ViewLine::new(ranges.join(", "))])
+        },
+    );
+    send_view(ctx, view, false).await
+}
+
+/// Weekends /vuorot_viikonloppu shows unless asked otherwise
+const DEFAULT_WEEKENDS: u32 = 4;
+/// Most weekends /vuorot_viikonloppu shows at once
+const MAX_WEEKENDS: u32 = 12;
+/// Weeks the weekend fairness report looks back unless asked otherwise
+const DEFAULT_FAIRNESS_WEEKS: u32 = 12;
+/// Most weeks the weekend fairness report looks back
+const MAX_FAIRNESS_WEEKS: u32 = 52;
+
+/// Weekend shifts: who works the coming weekends and how evenly weekends are shared
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("weekend", "fairness"),
+    subcommand_required
+)]
+pub async fn vuorot_viikonloppu(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// The Saturday of the weekend under way, or of the next one on weekdays
+fn first_saturday(today: NaiveDate) -> NaiveDate {
+    match today.weekday().days_since(Weekday::Sat) {
+        days @ 0..=1 => today - Duration::days(i64::from(days)),
+        days => today + Duration::days(i64::from(7 - days)),
+    }
+}
+
+/// Show who works the coming weekends
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn weekend(
+    ctx: Context<'_>,
+    #[description = "Number of weekends to show (default 4, at most 12)"]
+    #[min = 1]
+    #[max = 12]
+    weekends: Option<u32>,
+) -> CommandResult {
+    let weekends = weekends.unwrap_or(DEFAULT_WEEKENDS).clamp(1, MAX_WEEKENDS);
+    let first = first_saturday(Local::now().date_naive());
+    let saturdays: Vec<NaiveDate> = (0..i64::from(weekends))
+        .map(|week| first + Duration::weeks(week))
+        .collect();
+    let last = first + Duration::weeks(i64::from(weekends) - 1) + Duration::days(1);
+
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let schedules = match handle
+        .get_stored_entries_for_range(
+            first.format("%Y-%m-%d").to_string(),
+            last.format("%Y-%m-%d").to_string(),
+        )
+        .await
+    {
+        Ok(schedules) => schedules,
+        Err(e) => return send_view(ctx, fetch_error("weekends", "weekends", &e), true).await,
+    };
+
+    let formatter = handle.formatter().await;
+    let title = format!(
+        "{} · {}",
+        t!("weekend_title", weekends = weekends),
+        week_range_label(first, last, &rust_i18n::locale())
+    );
+    let view = weekend_overview(title, &saturdays, &schedules, &formatter);
+    handle.record_missing_notes(&formatter).await;
+    send_view(ctx, view, false).await
+}
+
+/// Show how evenly weekend shifts have been shared over the past weeks
+#[poise::command(
+    slash_command,
+    prefix_command,
+    check = "work_schedule_enabled",
+    check = "schedule_rate_limit"
+)]
+pub async fn fairness(
+    ctx: Context<'_>,
+    #[description = "Number of weeks to look back (default 12, at most 52)"]
+    #[min = 1]
+    #[max = 52]
+    weeks: Option<u32>,
+) -> CommandResult {
+    let weeks = weeks
+        .unwrap_or(DEFAULT_FAIRNESS_WEEKS)
+        .clamp(1, MAX_FAIRNESS_WEEKS);
+    let week_start = ctx.data().config.read().await.week_starts_on;
+    // The window ends with the current week, whose weekend is already planned
+    let (current, end) = week_bounds(Local::now().date_naive(), week_start);
+    let start = current - Duration::weeks(i64::from(weeks) - 1);
+
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let schedules = match handle
+        .get_stored_entries_for_range(
+            start.format("%Y-%m-%d").to_string(),
+            end.format("%Y-%m-%d").to_string(),
+        )
+        .await
+    {
+        Ok(schedules) => schedules,
+        Err(e) => return send_view(ctx, fetch_error("fairness", "weekends", &e), true).await,
+    };
+
+    let shares = weekend_fairness(&schedules, start, end, week_start);
+    let title = format!(
+        "{} · {}",
+        t!("weekend_fairness_title", weeks = weeks),
+        week_range_label(start, end, &rust_i18n::locale())
+    );
+    let formatter = handle.formatter().await;
+    send_view(
+        ctx,
+        weekend_fairness_view(title, weeks, &shares, &formatter),
+        false,
+    )
+    .await
+}
+
+/// Show how schedule parsing has gone over the past weeks
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    check = "work_schedule_enabled"
+)]
+pub async fn laatu(
+    ctx: Context<'_>,
+    #[description = "Number of weeks to show (default 8, at most 52)"]
+    #[min = 1]
+    #[max = 52]
+    weeks: Option<u32>,
+) -> CommandResult {
+    let weeks = weeks
+        .unwrap_or(DEFAULT_QUALITY_WEEKS)
+        .clamp(1, MAX_QUALITY_WEEKS);
+    let week_start = ctx.data().config.read().await.week_starts_on;
+    let records = match load_parse_records(&ctx.data().redis()).await {
+        Ok(records) => records,
+        Err(e) => return send_view(ctx, fetch_error("quality", "parse records", &e), true).await,
+    };
+
+    let weekly = weekly_quality(&records, Local::now().date_naive(), weeks, week_start);
+    let title = t!("quality_title", weeks = weeks);
+    if weekly.iter().all(|week| week.uploads == 0) {
+        return send_view(ctx, View::info(&title, &t!("quality_none")), true).await;
+    }
+
+    let lines = quality_trend(&weekly)
+        .into_iter()
+        .map(ViewLine::new)
+        .collect();
+    send_view(
+        ctx,
+        View::info(&title, &t!("quality_description")).field("\u{200B}", lines),
+        true,
+    )
+    .await
+}
+
+/// Longest parse error shown in `/parse_failures`
+const PARSE_FAILURE_ERROR_LENGTH: usize = 200;
+
+/// List the latest failed schedule parses kept for debugging
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    check = "work_schedule_enabled"
+)]
+pub async fn parse_failures(ctx: Context<'_>) -> CommandResult {
+    let failures = match load_parse_failures(&ctx.data().redis()).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            return send_view(
+                ctx,
+                fetch_error("parse_failures", "parse failures", &e),
+                true,
+            )
+            .await
+        }
+    };
+
+    let title = t!("parse_failures_title");
+    if failures.is_empty() {
+        return send_view(ctx, View::info(&title, &t!("parse_failures_none")), true).await;
+    }
+
+    let view = failures.iter().fold(
+        View::info(&title, &t!("parse_failures_description")),
+        |view, failure| {
+            let failed_at = Local
+                .timestamp_opt(failure.failed_at, 0)
+                .single()
+                .map(|failed_at| failed_at.format("%-d.%-m. %H:%M").to_string())
+                .unwrap_or_default();
+            let exchange = &failure.exchange;
+            let error = failure.error.lines().next().unwrap_or_default();
+            view.field(
+                failure.id.clone(),
+                vec![
+                    ViewLine::new(format!(
+                        "{failed_at} · {} · {}/{}",
+                        failure.stage, exchange.provider, exchange.model
+                    )),
+                    ViewLine::new(truncate(error, PARSE_FAILURE_ERROR_LENGTH)),
+                ],
+            )
+        },
+    );
+    send_view(ctx, view, true).await
+}
+
+/// Longest excerpt of a linked message stored with an entry
+const CONTEXT_EXCERPT_LENGTH: usize = 100;
+
+/// Link a Discord message to an employee's entry for context, or remove the link
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "liitä_viesti",
+    required_permissions = "ADMINISTRATOR",
+    check = "work_schedule_enabled",
+    check = "not_in_maintenance"
+)]
+pub async fn liita_viesti(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "Date: YYYY-MM-DD, d.m., vko27, today, tomorrow or a weekday"] date: String,
+    #[description = "Link to the message (Copy Message Link)"] message_link: Option<String>,
+    #[description = "Remove the linked message instead"] remove: Option<bool>,
+) -> CommandResult {
+    let title = t!("attach_message_title");
+    let Some(date) = parse_user_date(&date, Local::now().date_naive(), &rust_i18n::locale()) else {
+        let view = View::warning(&title, &t!("work_schedule_invalid_date"));
+        return send_view(ctx, view, true).await;
+    };
+    let date = date.format("%Y-%m-%d").to_string();
+
+    let link = if remove.unwrap_or(false) {
+        None
+    } else {
+        let Some(message_link) = message_link else {
+            let view = View::warning(&title, &t!("attach_message_missing_link"));
+            return send_view(ctx, view, true).await;
+        };
+        let Some(parsed) = parse_message_link(&message_link) else {
+            let view = View::warning(&title, &t!("attach_message_invalid_link"));
+            return send_view(ctx, view, true).await;
+        };
+        // Fetching fails both for deleted messages and channels the bot can't see
+        let message = match serenity::ChannelId::new(parsed.channel_id)
+            .message(ctx, parsed.message_id)
+            .await
+        {
+            Ok(message) => message,
+            Err(e) => {
+                debug!("Failed to fetch linked message: {}", e);
+                let view = View::warning(&title, &t!("attach_message_unreadable"));
+                return send_view(ctx, view, true).await;
+            }
+        };
+        let content = message.content.split_whitespace().collect::<Vec<_>>();
+        Some(ContextLink {
+            url: message.link(),
+            excerpt: truncate(&content.join(" "), CONTEXT_EXCERPT_LENGTH),
+        })
+    };
 
-            // Delete the waiting message and send the embed
-            let _ = response.delete(ctx).await;
-            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    match handle
+        .set_context_link(&employee, &date, link.clone())
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let view = View::warning(
+                &title,
+                &t!("attach_message_no_entry", employee = employee, date = date),
+            );
+            return send_view(ctx, view, true).await;
         }
+        Err(e) => return send_view(ctx, fetch_error("schedule", "schedule", &e), true).await,
+    }
+    let description = match link {
+        Some(link) => t!(
+            "attach_message_attached",
+            url = link.url,
+            employee = employee,
+            date = date,
+            excerpt = link.excerpt
+        ),
+        None => t!("attach_message_removed", employee = employee, date = date),
+    };
+    send_view(ctx, View::success(&title, &description), true).await
+}
+
+/// Number of days ahead checked for duplicate shifts
+const DUPLICATE_LOOKAHEAD_DAYS: i64 = 30;
+/// Discord allows at most five rows of buttons on a message
+const MAX_DUPLICATE_BUTTON_ROWS: usize = 5;
+/// How long the keep buttons wait for the next press before they are removed
+const DUPLICATE_BUTTON_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// List duplicate shifts in the next 30 days and pick which entry to keep
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "ADMINISTRATOR",
+    check = "work_schedule_enabled",
+    check = "not_in_maintenance"
+)]
+pub async fn duplikaatit(ctx: Context<'_>) -> CommandResult {
+    ctx.defer_ephemeral().await?;
+
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+
+    let today = Local::now().date_naive();
+    let end = today + Duration::days(DUPLICATE_LOOKAHEAD_DAYS);
+    let duplicates = match handle
+        .get_duplicates(
+            today.format("%Y-%m-%d").to_string(),
+            end.format("%Y-%m-%d").to_string(),
+        )
+        .await
+    {
+        Ok(duplicates) => duplicates,
         Err(e) => {
-            // Delete the waiting message and send the error
-            let _ = response.delete(ctx).await;
             ctx.send(
                 poise::CreateReply::default()
                     .embed(create_error_embed(
                         &t!("error_title", context = "schedule"),
                         &t!(
                             "work_schedule_error_fetching",
-                            resource = "schedule",
+                            resource = "duplicates",
                             error = e.to_string()
                         ),
                     ))
                     .ephemeral(true),
             )
             .await?;
+            return Ok(());
         }
+    };
+
+    if duplicates.is_empty() {
+        ctx.send(
+            poise::CreateReply::default()
+                .embed(create_info_embed(
+                    &t!("duplicates_title"),
+                    &t!("duplicates_none", days = DUPLICATE_LOOKAHEAD_DAYS),
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let title = t!("duplicates_title").to_string();
+    let intro = t!("duplicates_intro", count = duplicates.len()).to_string();
+    let fields = duplicates
+        .iter()
+        .enumerate()
+        .map(|(i, duplicate)| {
+            (
+                format!("{}. {} ({})", i + 1, duplicate.employee, duplicate.date),
+                format_duplicate(duplicate),
+            )
+        })
+        .collect();
+    let mut embed = create_warning_embed(&title, &intro);
+    let used = title.chars().count() + intro.chars().count();
+    for (name, value) in limit_fields(fields, used) {
+        embed = embed.field(name, value, false);
+    }
+
+    let prefix = format!("{}:duplicate", ctx.id());
+    let rows = duplicates
+        .iter()
+        .take(MAX_DUPLICATE_BUTTON_ROWS)
+        .enumerate()
+        .map(|(i, _)| {
+            serenity::CreateActionRow::Buttons(vec![
+                serenity::CreateButton::new(format!("{prefix}:{i}:first"))
+                    .label(format!("{}. {}", i + 1, t!("duplicates_keep_first")))
+                    .style(serenity::ButtonStyle::Secondary),
+                serenity::CreateButton::new(format!("{prefix}:{i}:last"))
+                    .label(format!("{}. {}", i + 1, t!("duplicates_keep_last")))
+                    .style(serenity::ButtonStyle::Secondary),
+            ])
+        })
+        .collect();
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(embed.clone())
+                .components(rows)
+                .ephemeral(true),
+        )
+        .await?;
+
+    loop {
+        let filter_prefix = prefix.clone();
+        let Some(press) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+            .author_id(ctx.author().id)
+            .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+            .timeout(DUPLICATE_BUTTON_TIMEOUT)
+            .await
+        else {
+            break;
+        };
+
+        let Some((index, keep)) = parse_duplicate_button(&press.data.custom_id, &prefix) else {
+            continue;
+        };
+        let Some(duplicate) = duplicates.get(index) else {
+            continue;
+        };
+
+        let result_embed = match handle
+            .resolve_duplicate(
+                &duplicate.employee,
+                &duplicate.date,
+                keep,
+                Some(ctx.author().id.get()),
+            )
+            .await
+        {
+            Ok(entry) => create_success_embed(
+                &title,
+                &t!(
+                    "duplicates_resolved",
+                    employee = duplicate.employee,
+                    date = duplicate.date,
+                    entry = entry.format()
+                ),
+            ),
+            Err(e) => create_error_embed(&t!("error_title", context = "schedule"), &e.to_string()),
+        };
+
+        press
+            .create_response(
+                ctx.serenity_context(),
+                serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(result_embed)
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
     }
 
+    // Remove the buttons once they've expired
+    reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(embed)
+                .components(Vec::new()),
+        )
+        .await?;
+
     Ok(())
 }
 
-/// Get work schedule for next week
-#[poise::command(slash_command, prefix_command)]
-pub async fn ensiviikko(
+/// Describe a group of duplicate entries for the listing
+fn format_duplicate(duplicate: &DuplicateShift) -> String {
+    let mut lines = vec![format!("⚠️ {}", t!(duplicate.kind.locale_key()))];
+    lines.extend(
+        duplicate
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("`{}.` {}", i + 1, entry.format())),
+    );
+    lines.join("\n")
+}
+
+/// Parse a keep button id into the duplicate index and choice
+fn parse_duplicate_button(custom_id: &str, prefix: &str) -> Option<(usize, KeepChoice)> {
+    let (index, choice) = custom_id
+        .strip_prefix(prefix)?
+        .strip_prefix(':')?
+        .split_once(':')?;
+    let keep = match choice {
+        "first" => KeepChoice::First,
+        "last" => KeepChoice::Last,
+        _ => return None,
+    };
+    Some((index.parse().ok()?, keep))
+}
+
+/// Suggest a change to a day of your schedule for an admin to approve
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    check = "work_schedule_enabled",
+    check = "not_in_maintenance",
+    check = "schedule_rate_limit"
+)]
+pub async fn ehdota_korjausta(
     ctx: Context<'_>,
-    #[description = "Employee name (leave empty for all employees)"] employee: Option<String>,
+    #[description = "Date: YYYY-MM-DD, d.m., vko27, today, tomorrow or a weekday"] date: String,
+    #[description = "New value: hours like 9-17 or 8-12, 16-20, x for a day off, or a note"]
+    value: String,
+    #[description = "Employee name (leave empty for your linked employee)"] employee: Option<
+        String,
+    >,
 ) -> CommandResult {
-    // Start response with waiting message
-    let response = ctx
-        .say(t!(
-            "fetch_processing",
-            resource = "work schedules for next week"
-        ))
-        .await?;
+    let title = t!("correction_title");
+    let linked = get_user_preferences(&ctx.data().redis(), ctx.author().id.get())
+        .await
+        .employee;
+    let Some(employee) = employee.or_else(|| linked.clone()) else {
+        let view = View::warning(&title, &t!("correction_not_linked"));
+        return send_view(ctx, view, true).await;
+    };
+    if let Err(refusal) = check_requester(linked.as_deref(), &employee) {
+        let message = match refusal {
+            CorrectionRefusal::NotLinked => t!("correction_not_linked"),
+            CorrectionRefusal::OtherEmployee(linked) => {
+                t!("correction_other_employee", linked = linked)
+            }
+        };
+        return send_view(ctx, View::warning(&title, &message), true).await;
+    }
+
+    let Some(date) = parse_user_date(&date, Local::now().date_naive(), &rust_i18n::locale()) else {
+        let view = View::warning(&title, &t!("work_schedule_invalid_date"));
+        return send_view(ctx, view, true).await;
+    };
+    let date = date.format("%Y-%m-%d").to_string();
+    let entry = match parse_correction_value(&value, &date) {
+        Ok(entry) => entry,
+        Err(error) => {
+            let view = View::warning(&title, &t!("correction_invalid_value", error = error));
+            return send_view(ctx, view, true).await;
+        }
+    };
+    let Some(channel_id) = ctx.data().config.read().await.error_channel_id else {
+        return send_view(ctx, View::error(&title, &t!("correction_no_channel")), true).await;
+    };
 
-    // Get the handle to work schedule
     let handle = get_work_schedule_handle(
         ctx.data().component_manager.as_ref(),
         ctx.data().config.clone(),
     )
     .await;
+    let current = match handle.get_entry_for_employee_date(&employee, &date).await {
+        Ok(current) => current,
+        Err(e) => return send_view(ctx, fetch_error("schedule", "schedule", &e), true).await,
+    };
 
-    // Calculate the date range for next week (Monday to Sunday)
-    let now = Local::now();
-    let today = now.date_naive();
-    let weekday_num = today.format("%u").to_string().parse::<u32>().unwrap_or(1); // 1=Monday, 7=Sunday
-    let days_since_monday = weekday_num - 1;
-    let this_monday = today
-        .checked_sub_signed(Duration::days(days_since_monday as i64))
-        .unwrap_or(today);
-    let next_monday = this_monday
-        .checked_add_signed(Duration::days(7))
-        .unwrap_or(this_monday);
-    let next_sunday = next_monday
-        .checked_add_signed(Duration::days(6))
-        .unwrap_or(next_monday);
-
-    let start_date = next_monday.format("%Y-%m-%d").to_string();
-    let end_date = next_sunday.format("%Y-%m-%d").to_string();
-
-    if let Some(emp) = employee {
-        // Get schedule for specific employee
-        match handle
-            .get_schedule_for_date_range(emp.clone(), start_date.clone(), end_date.clone())
-            .await
-        {
-            Ok(schedule) => {
-                let title = t!("work_schedule_employee_title", employee = emp);
-                let mut embed = serenity::CreateEmbed::new()
-                    .title(format!("{}: {}", t!("calendar_next_week"), title))
-                    .description(format!("{start_date} - {end_date}"))
-                    .color(0x00_99_FF); // Blue color
-
-                if schedule.schedule.is_empty() {
-                    embed = embed
-                        .description(t!("work_schedule_no_entries_for_employee", employee = emp));
-                } else {
-                    // Group entries by day
-                    let mut day_entries: std::collections::HashMap<
-                        String,
-                        Vec<&crate::components::work_schedule::models::WorkScheduleEntry>,
-                    > = std::collections::HashMap::new();
-
-                    for entry in &schedule.schedule {
-                        if let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
-                            let weekday_num =
-                                date.format("%u").to_string().parse::<u32>().unwrap_or(0);
-                            let day_name = match weekday_num {
-                                1 => t!("day_monday"),
-                                2 => t!("day_tuesday"),
-                                3 => t!("day_wednesday"),
-                                4 => t!("day_thursday"),
-                                5 => t!("day_friday"),
-                                6 => t!("day_saturday"),
-                                7 => t!("day_sunday"),
-                                _ => t!("day_unknown"),
-                            };
-
-                            let day_key = format!("{} ({})", day_name, entry.date);
-                            day_entries.entry(day_key).or_default().push(entry);
-                        } else {
-                            // If we can't parse the date, just use the date string
-                            day_entries
-                                .entry(entry.date.clone())
-                                .or_default()
-                                .push(entry);
-                        }
-                    }
+    let correction = Correction {
+        id: ctx.id().to_string(),
+        employee,
+        date,
+        value: value.trim().to_string(),
+        entry,
+        requested_by: ctx.author().id.get(),
+        requested_at: chrono::Utc::now().timestamp(),
+    };
+    store_correction(&ctx.data().redis(), &correction).await?;
 
-                    // Sort days by date
-                    let mut days: Vec<(
-                        String,
-                        Vec<&crate::components::work_schedule::models::WorkScheduleEntry>,
-                    )> = day_entries.into_iter().collect();
-                    days.sort_by(|a, b| {
-                        let a_date = a.1.first().map(|e| e.date.clone()).unwrap_or_default();
-                        let b_date = b.1.first().map(|e| e.date.clone()).unwrap_or_default();
-                        a_date.cmp(&b_date)
-                    });
-
-                    // Add fields for each day
-                    for (day, entries) in days {
-                        let entry_fmt = if entries.len() == 1 {
-                            entries[0].format()
-                        } else {
-                            let mut times = String::new();
-                            for (i, entry) in entries.iter().enumerate() {
-                                if i > 0 {
-                                    times.push('\n');
-                                }
-                                times.push_str(&format!("• {}", entry.format()));
-                            }
-                            times
-                        };
-
-                        embed = embed.field(day, entry_fmt, false);
-                    }
+    let message = serenity::CreateMessage::new()
+        .embed(correction_embed(&correction, &current.format()))
+        .components(vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(button_id(CorrectionAction::Approve, &correction.id))
+                .label(t!("correction_approve_button"))
+                .style(serenity::ButtonStyle::Success),
+            serenity::CreateButton::new(button_id(CorrectionAction::Reject, &correction.id))
+                .label(t!("correction_reject_button"))
+                .style(serenity::ButtonStyle::Danger),
+        ])]);
+    serenity::ChannelId::new(channel_id)
+        .send_message(ctx, message)
+        .await?;
+
+    let view = View::success(&title, &t!("correction_sent", date = correction.date));
+    send_view(ctx, view, true).await
+}
+
+/// Embed describing a suggested correction for the admins, with the day's entry before it
+pub fn correction_embed(correction: &Correction, current: &str) -> serenity::CreateEmbed {
+    create_info_embed(
+        &t!("correction_title"),
+        &t!(
+            "correction_description",
+            requester = serenity::Mention::User(serenity::UserId::new(correction.requested_by)),
+            employee = correction.employee,
+            date = correction.date
+        ),
+    )
+    .field(t!("correction_current"), current, true)
+    .field(t!("correction_suggested"), correction.entry.format(), true)
+    .timestamp(
+        serenity::Timestamp::from_unix_timestamp(correction.requested_at)
+            .unwrap_or_else(|_| serenity::Timestamp::now()),
+    )
+}
+
+/// Record the hours an employee actually worked on a past day
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    check = "work_schedule_enabled",
+    check = "not_in_maintenance"
+)]
+pub async fn toteuma(
+    ctx: Context<'_>,
+    #[description = "Employee name"] employee: String,
+    #[description = "Date: YYYY-MM-DD, d.m. or a weekday, within the last 14 days"] date: String,
+    #[description = "Hours actually worked, like 9-17 or 08:00-16:30"] range: String,
+) -> CommandResult {
+    let title = t!("actuals_title");
+    // Members record their own hours, admins anyone's
+    let linked = get_user_preferences(&ctx.data().redis(), ctx.author().id.get())
+        .await
+        .employee;
+    if let Err(refusal) = check_requester(linked.as_deref(), &employee) {
+        if !is_admin(ctx).await {
+            let message = match refusal {
+                CorrectionRefusal::NotLinked => t!("actuals_not_linked"),
+                CorrectionRefusal::OtherEmployee(linked) => {
+                    t!("actuals_other_employee", linked = linked)
                 }
+            };
+            return send_view(ctx, View::warning(&title, &message), true).await;
+        }
+    }
 
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "schedule"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "schedule",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
-            }
+    let today = Local::now().date_naive();
+    let Some(date) = parse_user_date(&date, today, &rust_i18n::locale()) else {
+        let view = View::warning(&title, &t!("work_schedule_invalid_date"));
+        return send_view(ctx, view, true).await;
+    };
+    let actual = check_actuals_date(date, today).and_then(|_| parse_actual_range(&range));
+    let (start, end) = match actual {
+        Ok(actual) => actual,
+        Err(refusal) => {
+            let message = match refusal {
+                ActualsRefusal::NotPast => t!("actuals_not_past"),
+                ActualsRefusal::TooOld => t!("actuals_too_old", days = MAX_ACTUALS_AGE_DAYS),
+                ActualsRefusal::InvalidRange(range) => t!("actuals_invalid_range", range = range),
+            };
+            return send_view(ctx, View::warning(&title, &message), true).await;
         }
-    } else {
-        // Get all employees
-        match handle.get_employees().await {
-            Ok(employees) => {
-                if employees.is_empty() {
-                    let _ = response.delete(ctx).await;
-                    ctx.send(
-                        poise::CreateReply::default()
-                            .embed(create_info_embed(
-                                &format!(
-                                    "{}: {}",
-                                    t!("calendar_next_week"),
-                                    t!(
-                                        "work_schedule_week_title",
-                                        start_date = start_date,
-                                        end_date = end_date
-                                    )
-                                ),
-                                &t!("work_schedule_no_employees"),
-                            ))
-                            .ephemeral(true),
-                    )
-                    .await?;
-                    return Ok(());
-                }
+    };
+    let date = date.format("%Y-%m-%d").to_string();
 
-                let mut embed = serenity::CreateEmbed::new()
-                    .title(format!(
-                        "{}: {}",
-                        t!("calendar_next_week"),
-                        t!(
-                            "work_schedule_week_title",
-                            start_date = start_date,
-                            end_date = end_date
-                        )
-                    ))
-                    .color(0x00_99_FF); // Blue color
-
-                for emp in employees {
-                    match handle
-                        .get_schedule_for_date_range(
-                            emp.clone(),
-                            start_date.clone(),
-                            end_date.clone(),
-                        )
-                        .await
-                    {
-                        Ok(schedule) => {
-                            if schedule.schedule.is_empty() {
-                                embed =
-                                    embed.field(&emp, t!("work_schedule_no_entries_found"), false);
-                            } else {
-                                let mut field_value = String::new();
-                                for entry in schedule.schedule {
-                                    // Parse date to get day of week
-                                    if let Ok(date) =
-                                        NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d")
-                                    {
-                                        let weekday_num = date
-                                            .format("%u")
-                                            .to_string()
-                                            .parse::<u32>()
-                                            .unwrap_or(0);
-                                        let day_name = match weekday_num {
-                                            1 => t!("day_short_monday"),
-                                            2 => t!("day_short_tuesday"),
-                                            3 => t!("day_short_wednesday"),
-                                            4 => t!("day_short_thursday"),
-                                            5 => t!("day_short_friday"),
-                                            6 => t!("day_short_saturday"),
-                                            7 => t!("day_short_sunday"),
-                                            _ => t!("day_short_unknown"),
-                                        };
-
-                                        field_value.push_str(&format!(
-                                            "• **{}**: {}\n",
-                                            day_name,
-                                            entry.format()
-                                        ));
-                                    } else {
-                                        field_value.push_str(&format!(
-                                            "• {}: {}\n",
-                                            entry.date,
-                                            entry.format()
-                                        ));
-                                    }
-                                }
-                                embed = embed.field(emp, field_value, false);
-                            }
-                        }
-                        Err(e) => {
-                            embed = embed.field(
-                                emp.clone(),
-                                t!(
-                                    "work_schedule_error_fetching",
-                                    resource = emp.clone(),
-                                    error = e.to_string()
-                                ),
-                                false,
-                            );
-                        }
-                    }
-                }
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let actual = Some((start.clone(), end.clone()));
+    let entry = match handle
+        .set_actual_hours(&employee, &date, actual, ctx.author().name.clone())
+        .await
+    {
+        Ok(Some(entry)) => entry,
+        Ok(None) => {
+            let view = View::warning(
+                &title,
+                &t!("actuals_no_entry", employee = employee, date = date),
+            );
+            return send_view(ctx, view, true).await;
+        }
+        Err(e) => return send_view(ctx, fetch_error("schedule", "schedule", &e), true).await,
+    };
+    let description = t!(
+        "actuals_recorded",
+        start = start,
+        end = end,
+        employee = employee,
+        date = date,
+        planned = entry.format()
+    );
+    send_view(ctx, View::success(&title, &description), true).await
+}
 
-                // Delete the waiting message and send the embed
-                let _ = response.delete(ctx).await;
-                ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            }
-            Err(e) => {
-                // Delete the waiting message and send the error
-                let _ = response.delete(ctx).await;
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(create_error_embed(
-                            &t!("error_title", context = "employees"),
-                            &t!(
-                                "work_schedule_error_fetching",
-                                resource = "employees",
-                                error = e.to_string()
-                            ),
-                        ))
-                        .ephemeral(true),
-                )
-                .await?;
-            }
+/// Compare the planned hours of a month with the hours actually worked
+#[poise::command(
+    slash_command,
+    prefix_command,
+    required_permissions = "ADMINISTRATOR",
+    check = "work_schedule_enabled"
+)]
+pub async fn tilastot(
+    ctx: Context<'_>,
+    #[description = "Month as YYYY-MM (default this month)"] month: Option<String>,
+) -> CommandResult {
+    let today = Local::now().date_naive();
+    let first = match month {
+        Some(month) => {
+            let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+            else {
+                let view = View::warning(&t!("actuals_title"), &t!("actuals_stats_invalid_month"));
+                return send_view(ctx, view, true).await;
+            };
+            first
         }
-    }
+        None => today.with_day(1).unwrap_or(today),
+    };
+    let last = first
+        .checked_add_months(chrono::Months::new(1))
+        .map_or(first, |next| next - Duration::days(1));
+    // Only days that have passed can have actual hours
+    let end = last.min(today - Duration::days(1));
+    let title = t!("actuals_stats_title", month = first.format("%Y-%m"));
 
-    Ok(())
+    let handle = get_work_schedule_handle(
+        ctx.data().component_manager.as_ref(),
+        ctx.data().config.clone(),
+    )
+    .await;
+    let schedules = if end < first {
+        Vec::new()
+    } else {
+        match handle
+            .get_stored_entries_for_range(
+                first.format("%Y-%m-%d").to_string(),
+                end.format("%Y-%m-%d").to_string(),
+            )
+            .await
+        {
+            Ok(schedules) => schedules,
+            Err(e) => return send_view(ctx, fetch_error("stats", "schedule", &e), true).await,
+        }
+    };
+
+    let variances: Vec<(String, Variance)> = schedules
+        .iter()
+        .map(|schedule| {
+            (
+                schedule.employee.clone(),
+                variance(&schedule.schedule, first, end),
+            )
+        })
+        .filter(|(_, variance)| variance.recorded_days > 0 || variance.missing_days > 0)
+        .collect();
+    let formatter = handle.formatter().await;
+    send_view(
+        ctx,
+        actuals_variance_view(title.to_string(), &variances, &formatter),
+        false,
+    )
+    .await
 }
 
 /// Helper to get the work schedule handle
-async fn get_work_schedule_handle(
+pub async fn get_work_schedule_handle(
     component_manager: Option<&Arc<crate::components::ComponentManager>>,
     config: Arc<RwLock<Config>>,
 ) -> WorkScheduleHandle {
@@ -814,21 +1570,21 @@ async fn get_work_schedule_handle(
                     // Create a new handle if we couldn't get one
                     debug!("No handle in Work Schedule component, creating new one");
                     let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-                    WorkScheduleHandle::new(config.clone(), redis_handle)
+                    WorkScheduleHandle::new(config.clone(), redis_handle, component_manager.bus())
                 }
             } else {
                 debug!("Could not downcast Work Schedule component");
                 let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-                WorkScheduleHandle::new(config.clone(), redis_handle)
+                WorkScheduleHandle::new(config.clone(), redis_handle, component_manager.bus())
             }
         } else {
             debug!("Work Schedule component not found in ComponentManager");
             let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-            WorkScheduleHandle::new(config.clone(), redis_handle)
+            WorkScheduleHandle::new(config.clone(), redis_handle, component_manager.bus())
         }
     } else {
         debug!("ComponentManager not available, creating standalone handle");
         let redis_handle = crate::components::redis_service::RedisActorHandle::empty();
-        WorkScheduleHandle::new(config.clone(), redis_handle)
+        WorkScheduleHandle::new(config.clone(), redis_handle, EventBus::new())
     }
 }
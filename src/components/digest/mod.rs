@@ -0,0 +1,123 @@
+pub mod notifications;
+mod scheduler;
+
+use crate::components::google_calendar::GoogleCalendarHandle;
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::WorkScheduleHandle;
+use crate::components::EventBus;
+use crate::config::Config;
+use crate::error::BotResult;
+use crate::utils::scheduler::{clear_notification_state, Scheduler, SharedContext};
+use async_trait::async_trait;
+use poise::serenity_prelude as serenity;
+use scheduler::DigestScheduler;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info};
+
+/// Handles the digest reads today's events and schedules through
+#[derive(Clone)]
+pub struct DigestSources {
+    pub calendar: GoogleCalendarHandle,
+    pub work_schedule: WorkScheduleHandle,
+}
+
+/// Combined morning digest of calendar events and work schedules.
+///
+/// Only runs with `combined_daily_digest` enabled, in which case the Google Calendar and Work
+/// Schedule components leave their daily notifications to it.
+#[derive(Default)]
+pub struct Digest {
+    sources: RwLock<Option<DigestSources>>,
+    ctx: RwLock<Option<SharedContext>>,
+}
+
+impl Digest {
+    /// Create a new digest component
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl super::Component for Digest {
+    fn name(&self) -> &'static str {
+        "digest"
+    }
+
+    async fn create(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+        bus: EventBus,
+    ) -> BotResult<()> {
+        if !config.read().await.combined_daily_digest {
+            return Ok(());
+        }
+
+        let mut sources = self.sources.write().await;
+        if sources.is_none() {
+            *sources = Some(DigestSources {
+                calendar: GoogleCalendarHandle::new(
+                    config.clone(),
+                    redis_handle.clone(),
+                    bus.clone(),
+                ),
+                work_schedule: WorkScheduleHandle::new(config, redis_handle, bus),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn attach(&self, ctx: &serenity::Context) -> BotResult<()> {
+        // Without the digest enabled, create leaves the sources unset and nothing is posted
+        if self.sources.read().await.is_none() {
+            return Ok(());
+        }
+
+        // Hand the latest context to the scheduler; on reconnects the running loop picks it up
+        SharedContext::attach(&self.ctx, Arc::new(ctx.clone())).await;
+
+        Ok(())
+    }
+
+    async fn start_background(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+    ) -> BotResult<()> {
+        // Without the digest enabled, create leaves these unset
+        let (Some(shared_ctx), Some(sources)) = (
+            self.ctx.read().await.clone(),
+            self.sources.read().await.clone(),
+        ) else {
+            return Ok(());
+        };
+
+        info!("Starting daily digest scheduler");
+        if let Err(e) = DigestScheduler::start(shared_ctx, config, sources, redis_handle).await {
+            error!("Failed to start daily digest scheduler: {}", e);
+        }
+
+        Ok(())
+    }
+
+    async fn stop_background(&self) -> BotResult<()> {
+        DigestScheduler.stop().await
+    }
+
+    async fn shutdown(&self) -> BotResult<()> {
+        let stopped = self.stop_background().await;
+        clear_notification_state(&DigestScheduler::component_type()).await;
+        if let Some(sources) = self.sources.read().await.as_ref() {
+            sources.calendar.shutdown().await?;
+            sources.work_schedule.shutdown().await?;
+        }
+        stopped
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,242 @@
+use crate::components::google_calendar::models::{visible_events, CalendarEvent};
+use crate::components::google_calendar::oncall::{on_call_or_nobody, oncall_line, OnCall};
+use crate::components::google_calendar::{format_day_lines, GoogleCalendarHandle};
+use crate::components::work_schedule::models::DaySchedules;
+use crate::components::work_schedule::render::ScheduleFormatter;
+use crate::components::work_schedule::WorkScheduleHandle;
+use crate::error::BotResult;
+use crate::utils::embed::split_field;
+use crate::utils::notifier::{DailyReplace, Delivery, Notification, NotificationSink};
+use chrono::{Local, NaiveDate};
+use poise::serenity_prelude::CreateEmbed;
+use rust_i18n::t;
+use tracing::warn;
+
+/// Format the employees working on the day, sorted by name
+fn format_working_lines(schedules: &DaySchedules, formatter: &ScheduleFormatter) -> Vec<String> {
+    schedules
+        .iter()
+        .filter(|(_, entry)| entry.is_working())
+        .map(|(employee, entry)| {
+            format!(
+                "**{}** {}",
+                formatter.employee(employee),
+                formatter.format(entry)
+            )
+        })
+        .collect()
+}
+
+/// Build the digest embed with a calendar and a work schedule section, and who's on call above
+/// them
+pub fn digest_embed(
+    date: NaiveDate,
+    events: &[CalendarEvent],
+    on_call: &[OnCall],
+    schedules: &DaySchedules,
+    formatter: &ScheduleFormatter,
+) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(t!(
+            "digest_daily_title",
+            date = date.format("%d.%m.%Y").to_string()
+        ))
+        .color(0x4285F4); // Google Blue color
+
+    if let Some(line) = oncall_line(on_call) {
+        embed = embed.description(line);
+    }
+
+    let mut event_lines = format_day_lines(events, date);
+    if event_lines.is_empty() {
+        event_lines.push(t!("calendar_no_events_today").to_string());
+    }
+    for (name, value) in split_field(&t!("digest_calendar_section"), &event_lines) {
+        embed = embed.field(name, value, false);
+    }
+
+    let mut work_lines = format_working_lines(schedules, formatter);
+    if work_lines.is_empty() {
+        work_lines.push(t!("work_schedule_all_day_off").to_string());
+    }
+    for (name, value) in split_field(&t!("digest_work_section"), &work_lines) {
+        embed = embed.field(name, value, false);
+    }
+
+    embed
+}
+
+/// Send the combined daily digest, masking private calendar events unless `show_private`.
+///
+/// A source that can't be reached is left empty so the other one still gets posted.
+pub async fn send_digest_notification(
+    sink: &dyn NotificationSink,
+    channel_id: u64,
+    calendar: &GoogleCalendarHandle,
+    work_schedule: &WorkScheduleHandle,
+    mode: DailyReplace,
+    show_private: bool,
+) -> BotResult<()> {
+    let today = Local::now().date_naive();
+
+    let events = calendar.get_upcoming_events().await.unwrap_or_else(|e| {
+        warn!("Failed to get calendar events for the digest: {}", e);
+        Vec::new()
+    });
+    let events = visible_events(events, show_private);
+    let on_call = on_call_or_nobody(calendar, today).await;
+    let schedules = work_schedule
+        .get_schedule_for_date(today.format("%Y-%m-%d").to_string())
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to get work schedules for the digest: {}", e);
+            DaySchedules::default()
+        });
+
+    let formatter = work_schedule.formatter().await;
+    let notification = Notification {
+        content: None,
+        embed: digest_embed(today, &events, &on_call, &schedules, &formatter),
+    };
+    work_schedule.record_missing_notes(&formatter).await;
+    sink.deliver(&Delivery::daily("digest", channel_id, mode), &notification)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::{ShiftRange, WorkScheduleEntry};
+    use crate::utils::embed::render_embed;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+    }
+
+    fn events() -> Vec<CalendarEvent> {
+        vec![
+            CalendarEvent {
+                id: "standup".to_string(),
+                summary: Some("Standup".to_string()),
+                start_date_time: Some("2025-03-10T09:00:00+02:00".to_string()),
+                end_date_time: Some("2025-03-10T09:15:00+02:00".to_string()),
+                ..Default::default()
+            },
+            CalendarEvent {
+                id: "holiday".to_string(),
+                summary: Some("Holiday".to_string()),
+                start_date: Some("2025-03-10".to_string()),
+                end_date: Some("2025-03-11".to_string()),
+                ..Default::default()
+            },
+            // Another day
+            CalendarEvent {
+                id: "trip".to_string(),
+                summary: Some("Trip".to_string()),
+                start_date: Some("2025-03-12".to_string()),
+                end_date: Some("2025-03-13".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn schedules() -> DaySchedules {
+        let mut pekka = WorkScheduleEntry::new("2025-03-10".to_string());
+        pekka.shifts.push(ShiftRange::new("12:00", "20:00"));
+        let mut anna = WorkScheduleEntry::new("2025-03-10".to_string());
+        anna.shifts.push(ShiftRange::new("07:00", "15:00"));
+        let mut hanna = WorkScheduleEntry::new("2025-03-10".to_string());
+        hanna.is_day_off = true;
+
+        DaySchedules::from_iter([
+            ("Pekka".to_string(), pekka),
+            ("Anna".to_string(), anna),
+            ("Hanna".to_string(), hanna),
+        ])
+    }
+
+    #[test]
+    fn test_digest_snapshot() {
+        let expected = "\
+# Good morning! Today is 10.03.2025
+## 📅 Calendar
+⚪ **All day** Holiday
+⚪ **09:00–09:15** Standup
+## 👷 Working today
+**Anna** 07:00–15:00
+**Pekka** 12:00–20:00
+";
+        assert_eq!(
+            render_embed(&digest_embed(
+                date(),
+                &events(),
+                &[],
+                &schedules(),
+                &ScheduleFormatter::default()
+            )),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_digest_with_one_source_empty() {
+        let expected = "\
+# Good morning! Today is 10.03.2025
+## 📅 Calendar
+No calendar events today
+## 👷 Working today
+**Anna** 07:00–15:00
+**Pekka** 12:00–20:00
+";
+        assert_eq!(
+            render_embed(&digest_embed(
+                date(),
+                &[],
+                &[],
+                &schedules(),
+                &ScheduleFormatter::default()
+            )),
+            expected
+        );
+
+        let expected = "\
+# Good morning! Today is 10.03.2025
+## 📅 Calendar
+⚪ **All day** Holiday
+⚪ **09:00–09:15** Standup
+## 👷 Working today
+Everyone has a day off today! Time to celebrate! 🎉
+";
+        assert_eq!(
+            render_embed(&digest_embed(
+                date(),
+                &events(),
+                &[],
+                &DaySchedules::default(),
+                &ScheduleFormatter::default()
+            )),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_digest_with_both_sources_empty() {
+        let expected = "\
+# Good morning! Today is 10.03.2025
+## 📅 Calendar
+No calendar events today
+## 👷 Working today
+Everyone has a day off today! Time to celebrate! 🎉
+";
+        assert_eq!(
+            render_embed(&digest_embed(
+                date(),
+                &[],
+                &[],
+                &DaySchedules::default(),
+                &ScheduleFormatter::default()
+            )),
+            expected
+        );
+    }
+}
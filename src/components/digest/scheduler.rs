@@ -0,0 +1,229 @@
+use chrono::Local;
+use lazy_static::lazy_static;
+use poise::serenity_prelude as serenity;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{debug, error, info, warn};
+
+use super::notifications::send_digest_notification;
+use super::DigestSources;
+use crate::components::redis_service::RedisActorHandle;
+use crate::config::Config;
+use crate::error::BotResult;
+use crate::guild_config::{notification_settings, NotificationSettings};
+use crate::utils::notifier::{notification_sinks, DailyReplace, Sinks};
+use crate::utils::scheduler::{
+    deliver_notification, next_wake_time, reset_notification_flag, retry_pending_notifications,
+    sleep_until_target_time, try_claim_notification, update_last_sent_date,
+    update_notification_flags, NotificationHandler, NotificationType, Scheduler, SharedContext,
+};
+use crate::utils::time::{get_weekly_date_range, next_notification_time, WeekStart};
+use crate::watchdog::{SchedulerHeartbeat, Target};
+
+lazy_static! {
+    static ref DIGEST_TASK_RUNNING: AtomicBool = AtomicBool::new(false);
+    static ref DIGEST_TASK: RwLock<Option<JoinHandle<()>>> = RwLock::new(None);
+}
+
+/// Scheduler sending the combined daily digest
+#[derive(Default)]
+pub struct DigestScheduler;
+
+impl Scheduler for DigestScheduler {
+    type Handle = DigestSources;
+
+    fn component_type() -> String {
+        "digest".to_string()
+    }
+
+    fn start(
+        ctx: SharedContext,
+        config: Arc<RwLock<Config>>,
+        handle: Self::Handle,
+        redis_handle: RedisActorHandle,
+    ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send>> {
+        Box::pin(async move {
+            let week_start = config.read().await.week_starts_on;
+
+            let handler: Arc<dyn NotificationHandler> = Arc::new(DigestNotificationHandler {
+                sources: handle,
+                redis_handle: redis_handle.clone(),
+                config: Arc::clone(&config),
+            });
+
+            if !DIGEST_TASK_RUNNING.swap(true, Ordering::SeqCst) {
+                info!("Starting daily digest task");
+                let task = tokio::spawn(async move {
+                    run_digest_task(ctx, handler, config, redis_handle, week_start).await;
+                });
+                *DIGEST_TASK.write().await = Some(task);
+            } else {
+                warn!("Daily digest task is already running, skipping initialization");
+            }
+
+            Ok(())
+        })
+    }
+
+    fn stop(&self) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send>> {
+        Box::pin(async {
+            if let Some(task) = DIGEST_TASK.write().await.take() {
+                info!("Aborting daily digest task");
+                task.abort();
+                DIGEST_TASK_RUNNING.store(false, Ordering::SeqCst);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Sends the digest in place of the components' daily notifications
+pub struct DigestNotificationHandler {
+    sources: DigestSources,
+    redis_handle: RedisActorHandle,
+    config: Arc<RwLock<Config>>,
+}
+
+impl NotificationHandler for DigestNotificationHandler {
+    fn send_daily_notification<'a>(
+        &'a self,
+        http: &'a Arc<serenity::Http>,
+        channel_id: u64,
+        sinks: Sinks,
+    ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let config = self.config.read().await.clone();
+            let mode = DailyReplace::from_config(&config);
+            let sink = notification_sinks(http, &self.redis_handle, &config, &sinks);
+            send_digest_notification(
+                &sink,
+                channel_id,
+                &self.sources.calendar,
+                &self.sources.work_schedule,
+                mode,
+                config.notifications_show_private_event_details(channel_id),
+            )
+            .await
+        })
+    }
+
+    /// The digest is daily only; the components keep sending their own weekly notifications
+    fn send_weekly_notification<'a>(
+        &'a self,
+        _http: &'a Arc<serenity::Http>,
+        _channel_id: u64,
+        _sinks: Sinks,
+    ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// The main loop of the daily digest
+async fn run_digest_task(
+    ctx: SharedContext,
+    handler: Arc<dyn NotificationHandler>,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
+    week_start: WeekStart,
+) {
+    let component_type = DigestScheduler::component_type();
+    let component_type = component_type.as_str();
+    let liveness = SchedulerHeartbeat::register("digest", component_type, redis_handle.clone());
+
+    loop {
+        let now = Local::now();
+        let today = now.format("%Y-%m-%d").to_string();
+        let (week_start_date, _) = get_weekly_date_range(&now, week_start);
+        update_notification_flags(&today, &week_start_date, component_type).await;
+        let NotificationSettings {
+            channel_id,
+            daily_time,
+            ..
+        } = notification_settings(&redis_handle, &config).await;
+
+        // Retry digests that couldn't be delivered earlier
+        let has_pending = retry_pending_notifications(
+            &ctx,
+            handler.as_ref(),
+            &redis_handle,
+            component_type,
+            week_start,
+        )
+        .await;
+
+        let Some(next_time) = next_notification_time(now, daily_time, false, week_start) else {
+            error!("Failed to calculate next daily digest time");
+            sleep(TokioDuration::from_secs(3600)).await; // Retry in an hour
+            continue;
+        };
+        debug!(
+            "[{}] Next daily digest scheduled for {}",
+            component_type, next_time
+        );
+
+        // Sleep until the target time, waking up earlier to retry parked notifications
+        let wake_time = next_wake_time(next_time, has_pending);
+        let target = Target::new(NotificationType::Daily, next_time, week_start);
+        liveness.waiting_until(wake_time, Some(target)).await;
+        if let Err(e) = sleep_until_target_time(wake_time).await {
+            error!("Error while waiting for target time: {:?}", e);
+            sleep(TokioDuration::from_secs(60)).await; // Wait a minute before retrying
+            continue;
+        }
+        if wake_time < next_time {
+            continue;
+        }
+
+        let date = next_time.format("%Y-%m-%d").to_string();
+        if !try_claim_notification(
+            NotificationType::Daily,
+            component_type,
+            &redis_handle,
+            &date,
+        )
+        .await
+        {
+            info!(
+                "[{}] Daily digest already claimed by another instance",
+                component_type
+            );
+            sleep(TokioDuration::from_secs(10)).await; // Short wait before loop continues
+            continue;
+        }
+
+        if let Err(e) = deliver_notification(
+            &ctx,
+            handler.as_ref(),
+            &redis_handle,
+            component_type,
+            NotificationType::Daily,
+            &date,
+            channel_id,
+        )
+        .await
+        {
+            error!(
+                "[{}] Failed to send daily digest, retrying later: {}",
+                component_type, e
+            );
+            reset_notification_flag(
+                NotificationType::Daily,
+                component_type,
+                &redis_handle,
+                &date,
+            )
+            .await;
+        } else {
+            info!("[{}] Successfully sent daily digest", component_type);
+            update_last_sent_date(NotificationType::Daily, &date, component_type).await;
+        }
+
+        // Small pause after sending to prevent immediate recalculation
+        sleep(TokioDuration::from_secs(5)).await;
+    }
+}
@@ -0,0 +1,166 @@
+use crate::components::google_calendar::models::CalendarEvent;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Events buffered per event type before slow subscribers start lagging
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Published by the Google Calendar actor after every successful fetch
+#[derive(Debug, Clone)]
+pub struct EventsRefreshed(pub Vec<CalendarEvent>);
+
+/// Published by the Work Schedule actor after it changes an employee's entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleUpdated(pub String, pub Vec<String>);
+
+/// Published by the Work Schedule actor for each day it changes, for the change feed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleChanged {
+    pub employee: String,
+    pub date: String,
+    /// The day as it was shown before the change
+    pub before: String,
+    /// The day as it's shown after the change
+    pub after: String,
+    /// Discord user who made the change
+    pub changed_by: Option<u64>,
+}
+
+/// In-process event bus with one broadcast channel per event type.
+///
+/// Components publish what they produce and subscribe to what they need instead of holding
+/// each other's handles. Clones share the same channels.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    channels: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event_types = self.channels.lock().map(|c| c.len()).unwrap_or_default();
+        f.debug_struct("EventBus")
+            .field("event_types", &event_types)
+            .finish()
+    }
+}
+
+impl EventBus {
+    /// Create an empty bus
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the sender for an event type, creating its channel on first use
+    fn sender<E: Clone + Send + Sync + 'static>(&self) -> broadcast::Sender<E> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(broadcast::channel::<E>(CHANNEL_CAPACITY).0))
+            .downcast_ref::<broadcast::Sender<E>>()
+            .expect("event bus channel registered under the wrong type")
+            .clone()
+    }
+
+    /// Publish an event, returning how many subscribers will receive it
+    pub fn publish<E: Clone + Send + Sync + 'static>(&self, event: E) -> usize {
+        self.sender().send(event).unwrap_or(0)
+    }
+
+    /// Subscribe to events of a type published from now on
+    pub fn subscribe<E: Clone + Send + Sync + 'static>(&self) -> broadcast::Receiver<E> {
+        self.sender().subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for a component producing calendar data
+    struct DummyCalendar {
+        bus: EventBus,
+    }
+
+    impl DummyCalendar {
+        fn refresh(&self, summaries: &[&str]) -> usize {
+            let events = summaries
+                .iter()
+                .map(|summary| CalendarEvent {
+                    id: summary.to_string(),
+                    summary: Some(summary.to_string()),
+                    description: None,
+                    created: None,
+                    start_date_time: None,
+                    start_date: None,
+                    end_date_time: None,
+                    end_date: None,
+                    color_id: None,
+                    location: None,
+                    visibility: None,
+                })
+                .collect();
+            self.bus.publish(EventsRefreshed(events))
+        }
+    }
+
+    /// Stands in for a component reacting to calendar data and announcing schedule changes
+    struct DummySchedule {
+        bus: EventBus,
+        events: broadcast::Receiver<EventsRefreshed>,
+    }
+
+    impl DummySchedule {
+        fn init(bus: &EventBus) -> Self {
+            Self {
+                bus: bus.clone(),
+                events: bus.subscribe(),
+            }
+        }
+
+        async fn handle_next(&mut self) -> usize {
+            let EventsRefreshed(events) = self.events.recv().await.unwrap();
+            self.bus.publish(ScheduleUpdated(
+                "Anna".to_string(),
+                events.into_iter().map(|event| event.id).collect(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_components_exchange_events() {
+        let bus = EventBus::new();
+        let calendar = DummyCalendar { bus: bus.clone() };
+
+        // Nobody is listening yet
+        assert_eq!(calendar.refresh(&["early"]), 0);
+
+        let mut schedule = DummySchedule::init(&bus);
+        let mut updates = bus.subscribe::<ScheduleUpdated>();
+
+        assert_eq!(calendar.refresh(&["2025-01-06", "2025-01-07"]), 1);
+        assert_eq!(schedule.handle_next().await, 1);
+        assert_eq!(
+            updates.recv().await.unwrap(),
+            ScheduleUpdated(
+                "Anna".to_string(),
+                vec!["2025-01-06".to_string(), "2025-01-07".to_string()]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_types_use_separate_channels() {
+        let bus = EventBus::new();
+        let mut updates = bus.subscribe::<ScheduleUpdated>();
+
+        bus.publish(EventsRefreshed(Vec::new()));
+        bus.publish(ScheduleUpdated("Anna".to_string(), Vec::new()));
+
+        let ScheduleUpdated(employee, _) = updates.recv().await.unwrap();
+        assert_eq!(employee, "Anna");
+        assert!(updates.try_recv().is_err());
+    }
+}
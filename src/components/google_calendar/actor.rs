@@ -1,14 +1,20 @@
 use super::models::CalendarEvent;
+use super::quota::{quota_date, record_api_call};
+use super::response::parse_events;
+use super::time::{event_span, EventWindow};
 use super::token::TokenManager;
-use crate::components::redis_service::RedisActorHandle;
+use crate::components::event_bus::{EventBus, EventsRefreshed};
+use crate::components::redis_service::{keys, RedisActorHandle};
 use crate::config::Config;
-use crate::error::{google_calendar_error, BotResult};
+use crate::error::{
+    google_auth_error, google_calendar_error, network_error, other_error, BotResult,
+};
 use chrono::Utc;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
-use tracing::info;
+use tracing::{info, warn};
 use url::Url;
 
 /// The Google Calendar actor that processes messages
@@ -18,12 +24,15 @@ pub struct GoogleCalendarActor {
     client: Client,
     command_rx: mpsc::Receiver<GoogleCalendarCommand>,
     redis_handle: RedisActorHandle,
+    bus: EventBus,
 }
 
 /// Commands that can be sent to the Google Calendar actor
 pub enum GoogleCalendarCommand {
     GetUpcomingEvents(mpsc::Sender<BotResult<Vec<CalendarEvent>>>),
+    GetEventsInRange(EventWindow, mpsc::Sender<BotResult<Vec<CalendarEvent>>>),
     CheckNewEvents(mpsc::Sender<BotResult<Vec<CalendarEvent>>>),
+    GetOnCallEvents(EventWindow, mpsc::Sender<BotResult<Vec<CalendarEvent>>>),
     Shutdown,
 }
 
@@ -48,6 +57,20 @@ impl GoogleCalendarActorHandle {
             .ok_or_else(|| google_calendar_error("Response channel closed"))?
     }
 
+    /// Get the events in a time range, without touching the stored events
+    pub async fn get_events_in_range(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(GoogleCalendarCommand::GetEventsInRange(window, response_tx))
+            .await
+            .map_err(|e| google_calendar_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| google_calendar_error("Response channel closed"))?
+    }
+
     /// Check for new events since last check
     pub async fn check_new_events(&self) -> BotResult<Vec<CalendarEvent>> {
         let (response_tx, mut response_rx) = mpsc::channel(1);
@@ -62,6 +85,20 @@ impl GoogleCalendarActorHandle {
             .ok_or_else(|| google_calendar_error("Response channel closed"))?
     }
 
+    /// Get the on-call calendar's events in a time range, none when it isn't configured
+    pub async fn get_oncall_events(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(GoogleCalendarCommand::GetOnCallEvents(window, response_tx))
+            .await
+            .map_err(|e| google_calendar_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| google_calendar_error("Response channel closed"))?
+    }
+
     /// Shutdown the actor
     pub async fn shutdown(&self) -> BotResult<()> {
         let _ = self.command_tx.send(GoogleCalendarCommand::Shutdown).await;
@@ -74,6 +111,7 @@ impl GoogleCalendarActor {
     pub fn new(
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
+        bus: EventBus,
     ) -> (Self, GoogleCalendarActorHandle) {
         let (command_tx, command_rx) = mpsc::channel(32);
 
@@ -83,6 +121,7 @@ impl GoogleCalendarActor {
             client: Client::new(),
             command_rx,
             redis_handle,
+            bus,
         };
 
         let handle = GoogleCalendarActorHandle { command_tx };
@@ -98,24 +137,29 @@ impl GoogleCalendarActor {
         while let Some(cmd) = self.command_rx.recv().await {
             match cmd {
                 GoogleCalendarCommand::GetUpcomingEvents(response_tx) => {
-                    let result = Self::get_upcoming_events(
-                        Arc::clone(&self.config),
-                        self.token_manager.clone(),
-                        self.client.clone(),
-                    )
-                    .await;
-
-                    // Save events to Redis if successful
+                    let window = self.default_window().await;
+                    let result = self.fetch_events(window).await;
+
+                    // Save events to Redis and let other components know if successful
                     if let Ok(events) = &result {
-                        let _ = self.redis_handle.save_events(events.clone()).await;
+                        let _ = save_snapshot(&self.redis_handle, events, window).await;
+                        self.bus.publish(EventsRefreshed(events.clone()));
                     }
 
                     let _ = response_tx.send(result).await;
                 }
+                GoogleCalendarCommand::GetEventsInRange(window, response_tx) => {
+                    let result = self.fetch_events(window).await;
+                    let _ = response_tx.send(result).await;
+                }
                 GoogleCalendarCommand::CheckNewEvents(response_tx) => {
                     let result = self.check_new_events().await;
                     let _ = response_tx.send(result).await;
                 }
+                GoogleCalendarCommand::GetOnCallEvents(window, response_tx) => {
+                    let result = self.fetch_oncall_events(window).await;
+                    let _ = response_tx.send(result).await;
+                }
                 GoogleCalendarCommand::Shutdown => {
                     info!("Google Calendar actor shutting down");
                     break;
@@ -126,29 +170,68 @@ impl GoogleCalendarActor {
         info!("Google Calendar actor shut down");
     }
 
-    /// Get upcoming events from the calendar
-    pub async fn get_upcoming_events(
-        config: Arc<RwLock<Config>>,
+    /// Window the upcoming events are fetched over, around now
+    async fn default_window(&self) -> EventWindow {
+        let config = self.config.read().await;
+        EventWindow::around(
+            Utc::now(),
+            config.calendar_window_past_days,
+            config.calendar_window_future_days,
+        )
+    }
+
+    /// Fetch the events in a window from the monitored calendar
+    async fn fetch_events(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        let calendar_id = self.config.read().await.google_calendar_id.clone();
+        self.fetch_calendar(&calendar_id, window).await
+    }
+
+    /// Fetch the events in a window from the on-call calendar, none when it isn't configured
+    async fn fetch_oncall_events(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        let Some(calendar_id) = self.config.read().await.oncall_calendar_id.clone() else {
+            return Ok(Vec::new());
+        };
+        self.fetch_calendar(&calendar_id, window).await
+    }
+
+    /// Fetch the events in a window from a calendar, counting the call against the daily API
+    /// budget
+    async fn fetch_calendar(
+        &self,
+        calendar_id: &str,
+        window: EventWindow,
+    ) -> BotResult<Vec<CalendarEvent>> {
+        let timezone = self.config.read().await.timezone.clone();
+        let date = quota_date(&timezone, Utc::now());
+        if let Err(e) = record_api_call(&self.redis_handle, date).await {
+            warn!("Failed to count Google Calendar API call: {}", e);
+        }
+
+        Self::get_events_in_range(
+            self.token_manager.clone(),
+            self.client.clone(),
+            calendar_id,
+            window,
+        )
+        .await
+    }
+
+    /// Get the events in a window from a calendar
+    pub async fn get_events_in_range(
         token_manager: TokenManager,
         client: Client,
+        calendar_id: &str,
+        window: EventWindow,
     ) -> BotResult<Vec<CalendarEvent>> {
-        // Get calendar ID from config
-        let calendar_id = {
-            let config_read = config.read().await;
-            config_read.google_calendar_id.clone()
-        };
-
         // Get authentication token
         let token = token_manager.get_token().await?;
         let access_token = token
             .get("access_token")
             .and_then(|t| t.as_str())
-            .ok_or_else(|| google_calendar_error("No access token available"))?;
+            .ok_or_else(|| google_auth_error("No access token available"))?;
 
-        // Calculate time range (from now to 4 weeks in the future)
-        let now = Utc::now();
-        let time_min = now.to_rfc3339();
-        let time_max = (now + chrono::Duration::days(28)).to_rfc3339();
+        let time_min = window.start.to_rfc3339();
+        let time_max = window.end.to_rfc3339();
 
         // Build URL with query parameters
         let url_str =
@@ -173,7 +256,7 @@ impl GoogleCalendarActor {
             .header("Authorization", format!("Bearer {access_token}"))
             .send()
             .await
-            .map_err(|e| google_calendar_error(&format!("Failed to fetch events: {e}")))?;
+            .map_err(|e| network_error(&format!("Failed to fetch events: {e}")))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -181,114 +264,159 @@ impl GoogleCalendarActor {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Could not read error response".to_string());
-            return Err(google_calendar_error(&format!(
-                "Failed to fetch events: HTTP {status} - {error_body}"
-            )));
+            let message = format!("Failed to fetch events: HTTP {status} - {error_body}");
+            return Err(if matches!(status.as_u16(), 401 | 403) {
+                google_auth_error(&message)
+            } else {
+                google_calendar_error(&message)
+            });
         }
 
-        let response_data: serde_json::Value = response
-            .json()
+        let body = response
+            .text()
             .await
-            .map_err(|e| google_calendar_error(&format!("Failed to parse events response: {e}")))?;
+            .map_err(|e| google_calendar_error(&format!("Failed to read events response: {e}")))?;
 
-        // Parse events from response
-        let events = response_data
-            .get("items")
-            .and_then(|i| i.as_array())
-            .ok_or_else(|| google_calendar_error("No items in response"))?;
-
-        // Convert to CalendarEvent objects
-        let calendar_events = events
-            .iter()
-            .map(|event| {
-                let id = event
-                    .get("id")
-                    .and_then(|id| id.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let summary = event
-                    .get("summary")
-                    .and_then(|s| s.as_str())
-                    .map(|s| s.to_string());
-                let description = event
-                    .get("description")
-                    .and_then(|s| s.as_str())
-                    .map(|s| s.to_string());
-                let created = event
-                    .get("created")
-                    .and_then(|s| s.as_str())
-                    .map(|s| s.to_string());
-
-                let start_date_time = event
-                    .get("start")
-                    .and_then(|start| start.as_object())
-                    .and_then(|start| start.get("dateTime"))
-                    .and_then(|dt| dt.as_str())
-                    .map(|s| s.to_string());
-
-                let start_date = event
-                    .get("start")
-                    .and_then(|start| start.as_object())
-                    .and_then(|start| start.get("date"))
-                    .and_then(|d| d.as_str())
-                    .map(|s| s.to_string());
-
-                let end_date_time = event
-                    .get("end")
-                    .and_then(|end| end.as_object())
-                    .and_then(|end| end.get("dateTime"))
-                    .and_then(|dt| dt.as_str())
-                    .map(|s| s.to_string());
-
-                let end_date = event
-                    .get("end")
-                    .and_then(|end| end.as_object())
-                    .and_then(|end| end.get("date"))
-                    .and_then(|d| d.as_str())
-                    .map(|s| s.to_string());
-
-                CalendarEvent {
-                    id,
-                    summary,
-                    description,
-                    created,
-                    start_date_time,
-                    start_date,
-                    end_date_time,
-                    end_date,
-                }
-            })
-            .collect();
-
-        Ok(calendar_events)
+        parse_events(&body)
     }
 
     /// Check for new events since last check
     async fn check_new_events(&self) -> BotResult<Vec<CalendarEvent>> {
         // Get current events from Google Calendar
-        let current_events = Self::get_upcoming_events(
-            Arc::clone(&self.config),
-            self.token_manager.clone(),
-            self.client.clone(),
-        )
-        .await?;
+        let window = self.default_window().await;
+        let current_events = self.fetch_events(window).await?;
 
-        // Get last known events from Redis
-        let last_known_events = self.redis_handle.get_events().await?;
-        let mut new_events = Vec::new();
+        let new_events = remember_events(&self.redis_handle, &current_events, window).await?;
+        self.bus.publish(EventsRefreshed(current_events));
 
-        // Find new events by comparing with last known events
-        for event in &current_events {
-            if !last_known_events.iter().any(|e| e.id == event.id) {
-                new_events.push(event.clone());
-            }
-        }
+        Ok(new_events)
+    }
+}
+
+/// How long past its end an announced event is remembered
+const ANNOUNCED_GRACE_SECS: i64 = 24 * 60 * 60;
+
+/// Store the latest fetch in Redis, returning the events that weren't in the previous one and
+/// haven't been announced yet.
+///
+/// Only the part of the window both fetches covered is compared, so events that merely moved
+/// into the window as it slid forward aren't taken as new. Without a stored window, e.g. for
+/// events stored before windows were recorded, every event is compared.
+///
+/// The snapshot is updated right away, so the new events are also kept as pending until
+/// [`mark_announced`] records them; a notification that fails to send is retried on the next
+/// check, and one sent before a crash isn't sent again.
+pub async fn remember_events(
+    redis_handle: &RedisActorHandle,
+    current_events: &[CalendarEvent],
+    window: EventWindow,
+) -> BotResult<Vec<CalendarEvent>> {
+    // Get last known events from Redis
+    let last_known_events = redis_handle.get_events().await?;
+    let last_window = stored_window(redis_handle).await;
+    let compared = match last_window {
+        Some(last_window) => window.overlap(&last_window),
+        None => Some(window),
+    };
+    let pending: HashSet<String> = redis_handle
+        .smembers(&keys::GOOGLE_CALENDAR_PENDING_EVENT_IDS)
+        .await?;
+    let announced = announced_event_ids(redis_handle).await?;
 
-        // Update last known events in Redis
-        if !current_events.is_empty() {
-            let _ = self.redis_handle.save_events(current_events).await;
+    // Find new events by comparing with last known events
+    let new_events: Vec<CalendarEvent> = match compared {
+        Some(compared) => current_events
+            .iter()
+            .filter(|event| compared.contains(event))
+            .filter(|event| {
+                pending.contains(&event.id) || !last_known_events.iter().any(|e| e.id == event.id)
+            })
+            .filter(|event| !announced.contains(&event.id))
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    };
+
+    // Update last known events in Redis, keeping them when a fetch comes back empty
+    if !current_events.is_empty() {
+        let _ = save_snapshot(redis_handle, current_events, window).await;
+        redis_handle
+            .del(&keys::GOOGLE_CALENDAR_PENDING_EVENT_IDS)
+            .await?;
+        if !new_events.is_empty() {
+            let ids: Vec<&str> = new_events.iter().map(|event| event.id.as_str()).collect();
+            redis_handle
+                .sadd(&keys::GOOGLE_CALENDAR_PENDING_EVENT_IDS, ids)
+                .await?;
         }
+    }
 
-        Ok(new_events)
+    Ok(new_events)
+}
+
+/// Record events as announced once their notification is sent, so they aren't announced again
+/// until a day after they end
+pub async fn mark_announced(
+    redis_handle: &RedisActorHandle,
+    events: &[CalendarEvent],
+) -> BotResult<()> {
+    let now = Utc::now().timestamp();
+    let members: Vec<(i64, String)> = events
+        .iter()
+        .map(|event| {
+            // The wall clock end is off by at most the time zone, well within the grace
+            let end = event_span(event).map_or(now, |span| span.end.and_utc().timestamp().max(now));
+            (end + ANNOUNCED_GRACE_SECS, event.id.clone())
+        })
+        .collect();
+
+    // One ZADD merges them all at once; a crash after it leaves at most stale pending ids
+    redis_handle
+        .zadd_many(&keys::GOOGLE_CALENDAR_ANNOUNCED_EVENT_IDS, &members)
+        .await?;
+    let ids: Vec<&str> = events.iter().map(|event| event.id.as_str()).collect();
+    if !ids.is_empty() {
+        redis_handle
+            .srem(&keys::GOOGLE_CALENDAR_PENDING_EVENT_IDS, ids)
+            .await?;
     }
+    redis_handle
+        .zrem_up_to(&keys::GOOGLE_CALENDAR_ANNOUNCED_EVENT_IDS, now)
+        .await
+}
+
+/// Ids of the events announced and not yet forgotten
+async fn announced_event_ids(redis_handle: &RedisActorHandle) -> BotResult<HashSet<String>> {
+    let announced: Vec<(String, f64)> = redis_handle
+        .zrange_withscores(&keys::GOOGLE_CALENDAR_ANNOUNCED_EVENT_IDS)
+        .await?;
+    let now = Utc::now().timestamp() as f64;
+    Ok(announced
+        .into_iter()
+        .filter(|(_, until)| *until > now)
+        .map(|(id, _)| id)
+        .collect())
+}
+
+/// Store a fetch in Redis with the window it covers
+pub async fn save_snapshot(
+    redis_handle: &RedisActorHandle,
+    events: &[CalendarEvent],
+    window: EventWindow,
+) -> BotResult<()> {
+    redis_handle.save_events(events.to_vec()).await?;
+    let json = serde_json::to_string(&window)
+        .map_err(|e| other_error(&format!("Failed to serialize event window: {e}")))?;
+    redis_handle
+        .set(&keys::GOOGLE_CALENDAR_EVENTS_WINDOW, json)
+        .await
+}
+
+/// Window of the stored events, if one was recorded
+async fn stored_window(redis_handle: &RedisActorHandle) -> Option<EventWindow> {
+    let json: Option<String> = redis_handle
+        .get(&keys::GOOGLE_CALENDAR_EVENTS_WINDOW)
+        .await
+        .ok()?;
+    serde_json::from_str(&json?).ok()
 }
@@ -0,0 +1,60 @@
+/// Emoji and embed accent color for a Google Calendar event color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventColor {
+    pub emoji: &'static str,
+    pub color: u32,
+}
+
+/// Fallback used for events without a colorId or with an unknown one
+pub const NEUTRAL_EVENT_COLOR: EventColor = EventColor {
+    emoji: "⚪",
+    color: 0x4285F4, // Google Blue color
+};
+
+/// Google Calendar event colorIds ("1" - "11") mapped to emoji and accent color
+const EVENT_COLORS: [(&str, &str, u32); 11] = [
+    ("1", "🟪", 0x7986CB),  // Lavender
+    ("2", "🟩", 0x33B679),  // Sage
+    ("3", "🟣", 0x8E24AA),  // Grape
+    ("4", "🌸", 0xE67C73),  // Flamingo
+    ("5", "🟡", 0xF6BF26),  // Banana
+    ("6", "🟠", 0xF4511E),  // Tangerine
+    ("7", "🔵", 0x039BE5),  // Peacock
+    ("8", "⚫", 0x616161),  // Graphite
+    ("9", "🟦", 0x3F51B5),  // Blueberry
+    ("10", "🟢", 0x0B8043), // Basil
+    ("11", "🔴", 0xD50000), // Tomato
+];
+
+/// Look up the emoji and accent color for an event colorId
+pub fn event_color(color_id: Option<&str>) -> EventColor {
+    color_id
+        .and_then(|id| {
+            EVENT_COLORS
+                .iter()
+                .find(|(key, _, _)| *key == id.trim())
+                .map(|&(_, emoji, color)| EventColor { emoji, color })
+        })
+        .unwrap_or(NEUTRAL_EVENT_COLOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_color_ids() {
+        assert_eq!(event_color(Some("11")).emoji, "🔴");
+        assert_eq!(event_color(Some("11")).color, 0xD50000);
+        assert_eq!(event_color(Some("9")).emoji, "🟦");
+        assert_eq!(event_color(Some("1")).color, 0x7986CB);
+    }
+
+    #[test]
+    fn test_unknown_color_ids_fall_back_to_neutral() {
+        assert_eq!(event_color(None), NEUTRAL_EVENT_COLOR);
+        assert_eq!(event_color(Some("")), NEUTRAL_EVENT_COLOR);
+        assert_eq!(event_color(Some("12")), NEUTRAL_EVENT_COLOR);
+        assert_eq!(event_color(Some("red")), NEUTRAL_EVENT_COLOR);
+    }
+}
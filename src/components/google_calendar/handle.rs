@@ -1,6 +1,8 @@
 use super::actor::GoogleCalendarActorHandle;
 use super::models::CalendarEvent;
+use super::time::EventWindow;
 use crate::components::redis_service::RedisActorHandle;
+use crate::components::EventBus;
 use crate::config::Config;
 use crate::error::BotResult;
 use std::sync::Arc;
@@ -16,11 +18,11 @@ pub struct GoogleCalendarHandle {
 
 impl GoogleCalendarHandle {
     /// Create a new GoogleCalendarHandle and spawn the actor
-    pub fn new(config: Arc<RwLock<Config>>, redis_handle: RedisActorHandle) -> Self {
+    pub fn new(config: Arc<RwLock<Config>>, redis_handle: RedisActorHandle, bus: EventBus) -> Self {
         use super::actor::GoogleCalendarActor;
 
         // Create the actor and get its handle
-        let (mut actor, handle) = GoogleCalendarActor::new(config, redis_handle);
+        let (mut actor, handle) = GoogleCalendarActor::new(config, redis_handle, bus);
 
         // Spawn a task to run the actor
         let actor_task = tokio::spawn(async move {
@@ -38,11 +40,21 @@ impl GoogleCalendarHandle {
         self.actor_handle.get_upcoming_events().await
     }
 
+    /// Get the events in a time range
+    pub async fn get_events_in_range(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        self.actor_handle.get_events_in_range(window).await
+    }
+
     /// Check for new events since last check
     pub async fn check_new_events(&self) -> BotResult<Vec<CalendarEvent>> {
         self.actor_handle.check_new_events().await
     }
 
+    /// Get the on-call calendar's events in a time range, none when it isn't configured
+    pub async fn get_oncall_events(&self, window: EventWindow) -> BotResult<Vec<CalendarEvent>> {
+        self.actor_handle.get_oncall_events(window).await
+    }
+
     /// Shutdown the actor
     pub async fn shutdown(&self) -> BotResult<()> {
         self.actor_handle.shutdown().await
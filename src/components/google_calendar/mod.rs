@@ -1,17 +1,25 @@
 mod actor;
+pub mod colors;
 mod handle;
 pub mod models;
 mod notifications;
+pub mod oncall;
+pub mod quota;
+pub mod render;
+pub mod response;
 mod scheduler;
-mod time;
+pub mod time;
 pub mod token;
 
+// Shared with the integration tests
+pub use actor::{mark_announced, remember_events};
 pub use handle::GoogleCalendarHandle;
+pub use notifications::{build_daily_notification, build_weekly_notification, format_day_lines};
+pub use scheduler::notification_handler;
 
 use crate::config::Config;
 use crate::error::BotResult;
 use async_trait::async_trait;
-use lazy_static::lazy_static;
 use poise::serenity_prelude as serenity;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -20,17 +28,16 @@ use tracing::{error, info, warn};
 
 use super::google_calendar::scheduler::GoogleCalendarScheduler;
 use super::redis_service::RedisActorHandle;
-use crate::utils::scheduler::Scheduler;
-
-lazy_static! {
-    static ref SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
-}
+use super::EventBus;
+use crate::utils::scheduler::{clear_notification_state, Scheduler, SharedContext};
 
 /// Google Calendar component for integration with Discord
 #[derive(Default)]
 pub struct GoogleCalendar {
     handle: RwLock<Option<GoogleCalendarHandle>>,
-    ctx: RwLock<Option<Arc<serenity::Context>>>,
+    ctx: RwLock<Option<SharedContext>>,
+    /// Whether this instance started the notification scheduler
+    scheduler_started: AtomicBool,
 }
 
 impl GoogleCalendar {
@@ -39,6 +46,7 @@ impl GoogleCalendar {
         Self {
             handle: RwLock::new(None),
             ctx: RwLock::new(None),
+            scheduler_started: AtomicBool::new(false),
         }
     }
 
@@ -55,30 +63,48 @@ impl super::Component for GoogleCalendar {
         "google_calendar"
     }
 
-    async fn init(
+    async fn create(
         &self,
-        ctx: &serenity::Context,
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
+        bus: EventBus,
     ) -> BotResult<()> {
-        // Store context for scheduler
-        *self.ctx.write().await = Some(Arc::new(ctx.clone()));
-
         // Create a new handle if one doesn't exist
         let mut handle_lock = self.handle.write().await;
         if handle_lock.is_none() {
             // Pass the redis_handle to the GoogleCalendarHandle
-            *handle_lock = Some(GoogleCalendarHandle::new(config.clone(), redis_handle));
+            *handle_lock = Some(GoogleCalendarHandle::new(config, redis_handle, bus));
         }
 
-        // Get the handle and context for the scheduler
-        let handle = handle_lock.as_ref().unwrap().clone();
-        let ctx = Arc::new(ctx.clone());
+        Ok(())
+    }
+
+    async fn attach(&self, ctx: &serenity::Context) -> BotResult<()> {
+        // Hand the latest context to the scheduler; on reconnects the running loops pick it up
+        SharedContext::attach(&self.ctx, Arc::new(ctx.clone())).await;
+
+        Ok(())
+    }
+
+    async fn start_background(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+    ) -> BotResult<()> {
+        let (Some(shared_ctx), Some(handle)) = (
+            self.ctx.read().await.clone(),
+            self.handle.read().await.clone(),
+        ) else {
+            warn!("Google Calendar isn't initialized, not starting its scheduler");
+            return Ok(());
+        };
 
         // Start the notification scheduler only if it hasn't been started yet
-        if !SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        if !self.scheduler_started.swap(true, Ordering::SeqCst) {
             info!("Starting Google Calendar notification scheduler");
-            if let Err(e) = GoogleCalendarScheduler::start(ctx, config, handle).await {
+            if let Err(e) =
+                GoogleCalendarScheduler::start(shared_ctx, config, handle, redis_handle).await
+            {
                 error!("Failed to start Google Calendar scheduler: {}", e);
             }
         } else {
@@ -88,18 +114,26 @@ impl super::Component for GoogleCalendar {
         Ok(())
     }
 
+    async fn stop_background(&self) -> BotResult<()> {
+        let scheduler = GoogleCalendarScheduler;
+        scheduler.stop().await?;
+        self.scheduler_started.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
     async fn shutdown(&self) -> BotResult<()> {
+        // Stop the scheduler first, so it's stopped even if the handle fails to shut down and a
+        // restarted instance can start it again
+        let stopped = self.stop_background().await;
+        clear_notification_state(&GoogleCalendarScheduler::component_type()).await;
+
         // Shutdown the handle if it exists
         let handle_lock = self.handle.read().await;
         if let Some(handle) = &*handle_lock {
             handle.shutdown().await?;
         }
 
-        // Stop the scheduler
-        let scheduler = GoogleCalendarScheduler;
-        scheduler.stop().await?;
-
-        Ok(())
+        stopped
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
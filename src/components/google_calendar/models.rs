@@ -1,3 +1,6 @@
+use super::colors::{event_color, EventColor};
+use rust_i18n::t;
+
 /// Simplified calendar event representation
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
 pub struct CalendarEvent {
@@ -9,4 +12,55 @@ pub struct CalendarEvent {
     pub start_date: Option<String>,
     pub end_date_time: Option<String>,
     pub end_date: Option<String>,
+    #[serde(default)]
+    pub color_id: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Google's visibility of the event: "default", "public", "private" or "confidential"
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+impl CalendarEvent {
+    /// Get the category emoji and accent color for this event
+    pub fn color(&self) -> EventColor {
+        event_color(self.color_id.as_deref())
+    }
+
+    /// Whether the event is marked private or confidential, so only its time may be shown
+    /// outside the channels allowed to see its details
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self.visibility.as_deref(),
+            Some("private") | Some("confidential")
+        )
+    }
+
+    /// The event with its summary replaced by a placeholder and its description and location
+    /// left out, keeping its time and color
+    pub fn masked(&self) -> Self {
+        Self {
+            summary: Some(t!("calendar_private_event").to_string()),
+            description: None,
+            location: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Events as they may be shown in a channel: private ones are masked unless `show_private`
+pub fn visible_events(events: Vec<CalendarEvent>, show_private: bool) -> Vec<CalendarEvent> {
+    if show_private {
+        return events;
+    }
+    events
+        .into_iter()
+        .map(|event| {
+            if event.is_private() {
+                event.masked()
+            } else {
+                event
+            }
+        })
+        .collect()
 }
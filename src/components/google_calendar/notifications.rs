@@ -1,27 +1,50 @@
+use crate::components::google_calendar::actor::mark_announced;
 use crate::components::google_calendar::handle::GoogleCalendarHandle;
-use crate::components::google_calendar::models::CalendarEvent;
-use crate::components::google_calendar::time::get_event_start;
+use crate::components::google_calendar::models::{visible_events, CalendarEvent};
+use crate::components::google_calendar::oncall::{on_call_or_nobody, oncall_line, OnCall};
+use crate::components::google_calendar::time::{event_span, get_event_start, occurs_on};
+use crate::components::redis_service::RedisActorHandle;
 use crate::error::BotResult;
-use chrono::{Duration, Local};
-use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use crate::theme::{Theme, ThemeColor};
+use crate::utils::embed::{limit_fields, split_field};
+use crate::utils::i18n::weekday_name;
+use crate::utils::notifier::{DailyReplace, Delivery, Notification, NotificationSink, Notifier};
+use crate::utils::time::{week_bounds, week_label, WeekStart};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime};
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
 use rust_i18n::t;
 
 // Icon URLs for calendar notifications
 const CALENDAR_EMPTY_ICON: &str = "https://cdn-icons-png.flaticon.com/512/3652/3652191.png";
 const CALENDAR_WITH_EVENTS_ICON: &str = "https://cdn-icons-png.flaticon.com/512/2693/2693507.png";
 const NEW_EVENT_ICON: &str = "https://cdn-icons-png.flaticon.com/512/2965/2965879.png";
-/// Send daily notification of calendar events
-pub async fn send_daily_notification(
-    ctx: &serenity::Context,
-    channel_id: u64,
+
+/// Fetch the upcoming events and build the daily notification for a date. Private events are
+/// masked unless `show_private`.
+pub async fn build_daily_notification(
     handle: &GoogleCalendarHandle,
-) -> BotResult<()> {
-    let events = handle.get_upcoming_events().await?;
-    let today = Local::now().date_naive();
+    date: NaiveDate,
+    show_private: bool,
+    theme: &Theme,
+) -> BotResult<Notification> {
+    let events = visible_events(handle.get_upcoming_events().await?, show_private);
+    let on_call = on_call_or_nobody(handle, date).await;
+    Ok(Notification {
+        content: None,
+        embed: theme.brand(daily_embed(&events, &on_call, date, theme)),
+    })
+}
 
+/// Build the daily embed listing the events starting on a date, with who's on call first
+fn daily_embed(
+    events: &[CalendarEvent],
+    on_call: &[OnCall],
+    today: NaiveDate,
+    theme: &Theme,
+) -> CreateEmbed {
     let mut today_events = Vec::new();
     for event in events {
-        if let Ok(Some(start)) = get_event_start(&event) {
+        if let Ok(Some(start)) = get_event_start(event) {
             if start.date_naive() == today {
                 today_events.push((event, start));
             }
@@ -31,22 +54,27 @@ pub async fn send_daily_notification(
     // Create an embed for the notification
     let mut embed = CreateEmbed::new()
         .title(t!("calendar_daily_title"))
-        .color(0x4285F4) // Google Blue color
+        .color(theme.color_or(ThemeColor::Primary, 0x4285F4)) // Google Blue by default
         .timestamp(Local::now());
 
+    let on_call_text = oncall_line(on_call)
+        .map(|line| format!("{line}\n\n"))
+        .unwrap_or_default();
+
     if today_events.is_empty() {
         embed = embed
-            .description(t!("calendar_no_events_today"))
+            .description(format!("{on_call_text}{}", t!("calendar_no_events_today")))
             .thumbnail(CALENDAR_EMPTY_ICON);
     } else {
         // Sort events by time
         today_events.sort_by_key(|(_, start)| *start);
 
-        let mut events_text = String::new();
+        let mut events_text = on_call_text;
         for (event, start) in today_events {
             let summary = event.summary.as_deref().unwrap_or("calendar_unnamed_event");
             let time = start.format("%H:%M").to_string();
-            events_text.push_str(&format!("🕐 **{time}** - {summary}\n"));
+            let emoji = event.color().emoji;
+            events_text.push_str(&format!("{emoji} 🕐 **{time}** - {summary}\n"));
         }
 
         embed = embed
@@ -58,117 +86,540 @@ pub async fn send_daily_notification(
             )));
     }
 
-    ChannelId::new(channel_id)
-        .send_message(ctx, CreateMessage::new().embed(embed))
-        .await?;
-
-    Ok(())
+    embed
 }
 
-/// Send weekly notification of calendar events
-pub async fn send_weekly_notification(
-    ctx: &serenity::Context,
+/// Send daily notification of calendar events
+pub async fn send_daily_notification(
+    sink: &dyn NotificationSink,
     channel_id: u64,
     handle: &GoogleCalendarHandle,
+    mode: DailyReplace,
+    show_private: bool,
+    theme: &Theme,
 ) -> BotResult<()> {
-    let events = handle.get_upcoming_events().await?;
-    let today = Local::now().date_naive();
-    let week_end = today + Duration::days(7);
+    let notification =
+        build_daily_notification(handle, Local::now().date_naive(), show_private, theme).await?;
+    sink.deliver(
+        &Delivery::daily("google_calendar", channel_id, mode),
+        &notification,
+    )
+    .await
+}
 
-    let mut week_events = Vec::new();
-    for event in events {
-        if let Ok(Some(start)) = get_event_start(&event) {
-            let event_date = start.date_naive();
-            if event_date >= today && event_date < week_end {
-                week_events.push((event, start));
+/// Format an event as a line of the weekly overview for a given day
+fn format_week_line(event: &CalendarEvent, date: NaiveDate) -> Option<(bool, NaiveTime, String)> {
+    let span = event_span(event)?;
+    let summary = event
+        .summary
+        .clone()
+        .unwrap_or_else(|| t!("calendar_unnamed_event").to_string());
+    let location = event
+        .location
+        .as_deref()
+        .filter(|location| !location.trim().is_empty())
+        .map(|location| format!(" ({location})"))
+        .unwrap_or_default();
+    let emoji = event.color().emoji;
+
+    if span.all_day {
+        let line = format!("{emoji} **{}** {summary}{location}", t!("calendar_all_day"));
+        return Some((true, NaiveTime::MIN, line));
+    }
+
+    // Multi-day events are clipped to the part falling on this day
+    let day_start = date.and_time(NaiveTime::MIN);
+    let from = if span.start > day_start {
+        span.start.time()
+    } else {
+        NaiveTime::MIN
+    };
+    let to = if span.end < day_start + Duration::days(1) {
+        span.end.format("%H:%M").to_string()
+    } else {
+        "24:00".to_string()
+    };
+
+    let line = format!(
+        "{emoji} **{}–{to}** {summary}{location}",
+        from.format("%H:%M")
+    );
+    Some((false, from, line))
+}
+
+/// Format the events taking place on a date, all-day events first and the rest by start time
+pub fn format_day_lines(events: &[CalendarEvent], date: NaiveDate) -> Vec<String> {
+    let mut day_lines: Vec<(bool, NaiveTime, String)> = events
+        .iter()
+        .filter(|event| occurs_on(event, date))
+        .filter_map(|event| format_week_line(event, date))
+        .collect();
+    day_lines.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    day_lines.into_iter().map(|(_, _, line)| line).collect()
+}
+
+/// Build the per-day fields of the weekly overview, all-day events first on each day
+pub fn format_weekly_fields(
+    events: &[CalendarEvent],
+    start: NaiveDate,
+    days: i64,
+    show_empty_days: bool,
+) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+
+    for offset in 0..days {
+        let date = start + Duration::days(offset);
+        let mut lines = format_day_lines(events, date);
+
+        if lines.is_empty() {
+            if !show_empty_days {
+                continue;
             }
+            lines.push(t!("calendar_no_events_day").to_string());
         }
+
+        let name = format!(
+            "{} ({})",
+            weekday_name(date.weekday()),
+            date.format("%d.%m")
+        );
+
+        fields.extend(split_field(&name, &lines));
     }
 
+    fields
+}
+
+/// Fetch the upcoming events and build the weekly overview of the week containing a date.
+/// Private events are masked unless `show_private`.
+pub async fn build_weekly_notification(
+    handle: &GoogleCalendarHandle,
+    date: NaiveDate,
+    show_empty_days: bool,
+    week_start: WeekStart,
+    show_private: bool,
+    theme: &Theme,
+) -> BotResult<Notification> {
+    let events = visible_events(handle.get_upcoming_events().await?, show_private);
+    let (first, last) = week_bounds(date, week_start);
+    Ok(Notification {
+        content: None,
+        embed: theme.brand(weekly_embed(&events, first, last, show_empty_days, theme)),
+    })
+}
+
+/// Build the weekly overview embed for the week from `first` to `last`
+fn weekly_embed(
+    events: &[CalendarEvent],
+    first: NaiveDate,
+    last: NaiveDate,
+    show_empty_days: bool,
+    theme: &Theme,
+) -> CreateEmbed {
+    let title = format!(
+        "{} {}",
+        t!("calendar_weekly_title"),
+        week_label(last, &rust_i18n::locale())
+    );
+    let footer = format!(
+        "📅 {} - {}",
+        first.format("%d.%m.%Y"),
+        last.format("%d.%m.%Y")
+    );
+
     // Create an embed for the weekly notification
     let mut embed = CreateEmbed::new()
-        .title(t!("calendar_weekly_title"))
-        .color(0x34A853) // Google Green color
+        .title(&title)
+        .color(theme.color_or(ThemeColor::Success, 0x34A853)) // Google Green by default
         .timestamp(Local::now())
-        .footer(serenity::CreateEmbedFooter::new(format!(
-            "📅 {} - {}",
-            today.format("%d.%m.%Y"),
-            (week_end - Duration::days(1)).format("%d.%m.%Y")
-        )));
+        .footer(serenity::CreateEmbedFooter::new(&footer));
+
+    let has_events = (0..7).any(|offset| {
+        let date = first + Duration::days(offset);
+        events.iter().any(|event| occurs_on(event, date))
+    });
 
-    if week_events.is_empty() {
+    if !has_events {
         embed = embed
             .description(t!("calendar_no_events_week"))
             .thumbnail(CALENDAR_EMPTY_ICON);
     } else {
-        // Sort events by time
-        week_events.sort_by_key(|(_, start)| *start);
-
         embed = embed.thumbnail(CALENDAR_WITH_EVENTS_ICON);
 
-        let mut current_date = today;
-        while current_date < week_end {
-            let day_events: Vec<_> = week_events
-                .iter()
-                .filter(|(_, start)| start.date_naive() == current_date)
-                .collect();
-
-            if !day_events.is_empty() {
-                let day_name = current_date.format("%A").to_string();
-                let day_date = current_date.format("%d.%m").to_string();
-
-                let mut day_text = String::new();
-                for (event, start) in day_events {
-                    let summary = event.summary.as_deref().unwrap_or("calendar_unnamed_event");
-                    let time = start.format("%H:%M").to_string();
-                    day_text.push_str(&format!("🕐 **{time}** - {summary}\n"));
-                }
-
-                embed = embed.field(format!("{day_name} ({day_date})"), day_text, false);
-            }
-
-            current_date += Duration::days(1);
+        let fields = format_weekly_fields(events, first, 7, show_empty_days);
+        let used = title.chars().count() + footer.chars().count();
+        for (name, value) in limit_fields(fields, used) {
+            embed = embed.field(name, value, false);
         }
     }
 
-    ChannelId::new(channel_id)
-        .send_message(ctx, CreateMessage::new().embed(embed))
-        .await?;
+    embed
+}
 
-    Ok(())
+/// Send weekly notification of calendar events for the current week
+pub async fn send_weekly_notification(
+    sink: &dyn NotificationSink,
+    channel_id: u64,
+    handle: &GoogleCalendarHandle,
+    show_empty_days: bool,
+    week_start: WeekStart,
+    show_private: bool,
+    theme: &Theme,
+) -> BotResult<()> {
+    let notification = build_weekly_notification(
+        handle,
+        Local::now().date_naive(),
+        show_empty_days,
+        week_start,
+        show_private,
+        theme,
+    )
+    .await?;
+    sink.deliver(
+        &Delivery::once("google_calendar", channel_id),
+        &notification,
+    )
+    .await
+}
+
+/// Build the notification announcing new calendar events, masking private ones unless
+/// `show_private`
+pub fn new_events_notification(
+    events: &[CalendarEvent],
+    show_private: bool,
+    theme: &Theme,
+) -> Notification {
+    let events = visible_events(events.to_vec(), show_private);
+    let mut embed = CreateEmbed::new()
+        .title(t!("calendar_new_events_title"))
+        .color(theme.color_or(ThemeColor::Warning, 0xEA4335)) // Google Red by default
+        .timestamp(Local::now())
+        .thumbnail(NEW_EVENT_ICON);
+
+    let mut events_text = String::new();
+    for event in &events {
+        let summary = event.summary.as_deref().unwrap_or("calendar_unnamed_event");
+        let time = if let Ok(Some(start)) = get_event_start(event) {
+            format!("{}", start.format("%d.%m. %H:%M"))
+        } else {
+            t!("calendar_unknown_time").to_string()
+        };
+        let emoji = event.color().emoji;
+        events_text.push_str(&format!("🆕 {emoji} **{time}** - {summary}\n"));
+    }
+
+    // A lone event gets its own category color as the embed accent
+    if let [event] = events.as_slice() {
+        embed = embed.color(event.color().color);
+    }
+
+    Notification {
+        content: None,
+        embed: theme.brand(embed.description(events_text)),
+    }
 }
 
-/// Send notification for new calendar events
+/// Send notification for new calendar events, then record them as announced. Events whose
+/// notification fails stay pending and are announced on the next check.
 pub async fn send_new_events_notification(
-    ctx: &serenity::Context,
+    notifier: &dyn Notifier,
+    redis_handle: &RedisActorHandle,
     channel_id: u64,
     events: &[CalendarEvent],
+    show_private: bool,
+    theme: &Theme,
 ) -> BotResult<()> {
     if !events.is_empty() {
-        // Create an embed for new events notification
-        let mut embed = CreateEmbed::new()
-            .title(t!("calendar_new_events_title"))
-            .color(0xEA4335) // Google Red color
-            .timestamp(Local::now())
-            .thumbnail(NEW_EVENT_ICON);
-
-        let mut events_text = String::new();
-        for event in events {
-            let summary = event.summary.as_deref().unwrap_or("calendar_unnamed_event");
-            let time = if let Ok(Some(start)) = get_event_start(event) {
-                format!("{}", start.format("%d.%m. %H:%M"))
-            } else {
-                t!("calendar_unknown_time").to_string()
-            };
-            events_text.push_str(&format!("🆕 **{time}** - {summary}\n"));
+        notifier
+            .send(
+                channel_id,
+                new_events_notification(events, show_private, theme),
+            )
+            .await?;
+        mark_announced(redis_handle, events).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::google_calendar::actor::{remember_events, save_snapshot};
+    use crate::components::google_calendar::time::EventWindow;
+    use crate::utils::embed::render_embed;
+    use crate::utils::notifier::recording::{Call, RecordingNotifier};
+
+    fn event(summary: &str, start: (&str, bool), end: (&str, bool)) -> CalendarEvent {
+        let (start, start_is_date) = start;
+        let (end, end_is_date) = end;
+        CalendarEvent {
+            id: summary.to_string(),
+            summary: Some(summary.to_string()),
+            start_date_time: (!start_is_date).then(|| start.to_string()),
+            start_date: start_is_date.then(|| start.to_string()),
+            end_date_time: (!end_is_date).then(|| end.to_string()),
+            end_date: end_is_date.then(|| end.to_string()),
+            ..Default::default()
         }
+    }
 
-        embed = embed.description(events_text);
+    fn fixture_week() -> Vec<CalendarEvent> {
+        let mut standup = event(
+            "Standup",
+            ("2025-03-10T09:00:00+02:00", false),
+            ("2025-03-10T09:15:00+02:00", false),
+        );
+        standup.location = Some("Room 1".to_string());
+        standup.color_id = Some("11".to_string());
 
-        ChannelId::new(channel_id)
-            .send_message(ctx, CreateMessage::new().embed(embed))
-            .await?;
+        vec![
+            standup,
+            event("Holiday", ("2025-03-10", true), ("2025-03-11", true)),
+            event("Trip", ("2025-03-12", true), ("2025-03-15", true)),
+            event(
+                "Night shift",
+                ("2025-03-13T22:00:00+02:00", false),
+                ("2025-03-14T02:00:00+02:00", false),
+            ),
+        ]
     }
 
-    Ok(())
+    fn render(fields: &[(String, String)]) -> String {
+        fields
+            .iter()
+            .map(|(name, value)| format!("## {name}\n{value}\n"))
+            .collect()
+    }
+
+    #[test]
+    fn test_weekly_fields_snapshot() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let fields = format_weekly_fields(&fixture_week(), monday, 7, false);
+
+        let expected = "\
+## Monday (10.03)
+⚪ **All day** Holiday
+🔴 **09:00–09:15** Standup (Room 1)
+## Wednesday (12.03)
+⚪ **All day** Trip
+## Thursday (13.03)
+⚪ **All day** Trip
+⚪ **22:00–24:00** Night shift
+## Friday (14.03)
+⚪ **All day** Trip
+⚪ **00:00–02:00** Night shift
+";
+        assert_eq!(render(&fields), expected);
+    }
+
+    #[test]
+    fn test_daily_embed_snapshot() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+
+        let expected = "\
+# Today:
+⚪ 🕐 **00:00** - Holiday
+🔴 🕐 **09:00** - Standup
+
+-- 📅 Monday, March 10, 2025
+";
+        assert_eq!(
+            render_embed(&daily_embed(
+                &fixture_week(),
+                &[],
+                monday,
+                &Theme::default()
+            )),
+            expected
+        );
+
+        let tuesday = NaiveDate::from_ymd_opt(2025, 3, 11).unwrap();
+        let expected = "\
+# Today:
+No calendar events today
+";
+        assert_eq!(
+            render_embed(&daily_embed(
+                &fixture_week(),
+                &[],
+                tuesday,
+                &Theme::default()
+            )),
+            expected
+        );
+
+        // Who's on call goes above the events
+        let on_call = [OnCall {
+            name: "Anna".to_string(),
+            until: NaiveDate::from_ymd_opt(2025, 3, 13)
+                .unwrap()
+                .and_hms_opt(8, 0, 0)
+                .unwrap(),
+            all_day: false,
+        }];
+        let expected = "\
+# Today:
+🚨 On call: Anna (until Thu 08:00)
+
+⚪ 🕐 **00:00** - Holiday
+🔴 🕐 **09:00** - Standup
+
+-- 📅 Monday, March 10, 2025
+";
+        assert_eq!(
+            render_embed(&daily_embed(
+                &fixture_week(),
+                &on_call,
+                monday,
+                &Theme::default()
+            )),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_weekly_embed_snapshot() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2025, 3, 16).unwrap();
+
+        let expected = "\
+# This Week: Week 11
+## Monday (10.03)
+⚪ **All day** Holiday
+🔴 **09:00–09:15** Standup (Room 1)
+## Wednesday (12.03)
+⚪ **All day** Trip
+## Thursday (13.03)
+⚪ **All day** Trip
+⚪ **22:00–24:00** Night shift
+## Friday (14.03)
+⚪ **All day** Trip
+⚪ **00:00–02:00** Night shift
+-- 📅 10.03.2025 - 16.03.2025
+";
+        assert_eq!(
+            render_embed(&weekly_embed(
+                &fixture_week(),
+                monday,
+                sunday,
+                false,
+                &Theme::default()
+            )),
+            expected
+        );
+
+        let expected = "\
+# This Week: Week 11
+No events scheduled for this week!
+-- 📅 10.03.2025 - 16.03.2025
+";
+        assert_eq!(
+            render_embed(&weekly_embed(&[], monday, sunday, false, &Theme::default())),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_weekly_fields_show_empty_days() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let fields = format_weekly_fields(&fixture_week(), monday, 7, true);
+
+        assert_eq!(fields.len(), 7);
+        assert_eq!(fields[1].0, "Tuesday (11.03)");
+        assert_eq!(fields[1].1, "No events");
+    }
+
+    #[test]
+    fn test_new_private_events_are_masked() {
+        let mut review = event(
+            "Salary review: Anna",
+            ("2025-03-10T13:00:00+02:00", false),
+            ("2025-03-10T14:00:00+02:00", false),
+        );
+        review.visibility = Some("confidential".to_string());
+        review.description = Some("Bring the numbers".to_string());
+        let events = [review, fixture_week().remove(0)];
+
+        let masked =
+            render_embed(&new_events_notification(&events, false, &Theme::default()).embed);
+        assert!(masked.contains("**10.03. 13:00** - 🔒 Private event"));
+        assert!(!masked.contains("Salary review"));
+        assert!(masked.contains("Standup"));
+
+        let full = render_embed(&new_events_notification(&events, true, &Theme::default()).embed);
+        assert!(full.contains("**10.03. 13:00** - Salary review: Anna"));
+    }
+
+    fn ids(events: &[CalendarEvent]) -> Vec<&str> {
+        events.iter().map(|event| event.id.as_str()).collect()
+    }
+
+    fn window() -> EventWindow {
+        EventWindow::around("2025-03-10T00:00:00Z".parse().unwrap(), 0, 28)
+    }
+
+    /// One check of the new events task: diff the fetch and announce what's new
+    async fn check(
+        redis_handle: &RedisActorHandle,
+        notifier: &RecordingNotifier,
+        events: &[CalendarEvent],
+    ) -> Vec<CalendarEvent> {
+        let new_events = remember_events(redis_handle, events, window())
+            .await
+            .unwrap();
+        let _ = send_new_events_notification(
+            notifier,
+            redis_handle,
+            1,
+            &new_events,
+            false,
+            &Theme::default(),
+        )
+        .await;
+        new_events
+    }
+
+    #[tokio::test]
+    async fn test_failed_announcement_is_retried_next_check() {
+        let redis_handle = RedisActorHandle::fake();
+        let notifier = RecordingNotifier::failing_sends(1);
+        let mut events = fixture_week();
+        save_snapshot(&redis_handle, &events[..1], window())
+            .await
+            .unwrap();
+
+        // The send fails after the snapshot already holds the event
+        let new_events = check(&redis_handle, &notifier, &events[..2]).await;
+        assert_eq!(ids(&new_events), ["Holiday"]);
+        assert_eq!(notifier.calls(), [Call::FailedSend]);
+
+        events.truncate(3);
+        let new_events = check(&redis_handle, &notifier, &events).await;
+        assert_eq!(ids(&new_events), ["Holiday", "Trip"]);
+        assert_eq!(notifier.calls(), [Call::FailedSend, Call::Send(1)]);
+
+        assert!(check(&redis_handle, &notifier, &events).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_announced_events_survive_a_crash_before_the_snapshot() {
+        let redis_handle = RedisActorHandle::fake();
+        let notifier = RecordingNotifier::default();
+        let events = fixture_week();
+        save_snapshot(&redis_handle, &events[..1], window())
+            .await
+            .unwrap();
+
+        let new_events = check(&redis_handle, &notifier, &events).await;
+        assert_eq!(ids(&new_events), ["Holiday", "Trip", "Night shift"]);
+
+        // A restart finding the snapshot from before the send doesn't announce them again
+        redis_handle
+            .save_events(events[..1].to_vec())
+            .await
+            .unwrap();
+        assert!(check(&redis_handle, &notifier, &events).await.is_empty());
+        assert_eq!(notifier.calls(), [Call::Send(1)]);
+    }
 }
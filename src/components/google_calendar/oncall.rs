@@ -0,0 +1,247 @@
+//! Who's on call, read from a calendar whose events are named after the people on call.
+//!
+//! Rotations usually run over several days, e.g. Monday 08:00 to Thursday 08:00, so every
+//! event taking place on a day counts, not just the ones starting on it.
+
+use super::handle::GoogleCalendarHandle;
+use super::models::CalendarEvent;
+use super::time::{event_span, occurs_on, EventWindow};
+use crate::components::work_schedule::EmployeeId;
+use crate::error::{google_calendar_error, BotResult};
+use crate::utils::i18n::weekday_short_name;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use poise::serenity_prelude::{Mentionable, UserId};
+use rust_i18n::t;
+use tracing::warn;
+
+/// Someone on call on a day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnCall {
+    pub name: String,
+    /// Exclusive end of the rotation, in the wall clock time the event was given in
+    pub until: NaiveDateTime,
+    /// Whether the rotation is an all-day event, which ends at midnight after its last day
+    pub all_day: bool,
+}
+
+/// Window the on-call calendar is queried over for a local date
+pub fn day_window(date: NaiveDate) -> Option<EventWindow> {
+    let midnight = |date: NaiveDate| {
+        date.and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|dt| dt.to_utc())
+    };
+    Some(EventWindow {
+        start: midnight(date)?,
+        end: midnight(date + Duration::days(1))?,
+    })
+}
+
+/// Names in an on-call event's title. A label before a colon, like "Päivystys: Anna", is
+/// dropped, and several names can be separated with commas, "&" or "/".
+pub fn oncall_names(summary: &str) -> Vec<String> {
+    let names = summary.split_once(':').map_or(summary, |(_, names)| names);
+    names
+        .split([',', '&', '/'])
+        .map(|name| name.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Everyone on call on a date, in the order of their events
+pub fn on_call_on(events: &[CalendarEvent], date: NaiveDate) -> Vec<OnCall> {
+    events
+        .iter()
+        .filter(|event| occurs_on(event, date))
+        .filter_map(|event| Some((event, event_span(event)?)))
+        .flat_map(|(event, span)| {
+            oncall_names(event.summary.as_deref().unwrap_or_default())
+                .into_iter()
+                .map(move |name| OnCall {
+                    name,
+                    until: span.end,
+                    all_day: span.all_day,
+                })
+        })
+        .collect()
+}
+
+/// Fetch everyone on call on a local date. Without an on-call calendar configured nobody is.
+pub async fn get_on_call(handle: &GoogleCalendarHandle, date: NaiveDate) -> BotResult<Vec<OnCall>> {
+    let window =
+        day_window(date).ok_or_else(|| google_calendar_error("Failed to calculate the day"))?;
+    let events = handle.get_oncall_events(window).await?;
+    Ok(on_call_on(&events, date))
+}
+
+/// Like [`get_on_call`] for the daily notifications, which leave the line out rather than fail
+/// when the on-call calendar can't be read
+pub async fn on_call_or_nobody(handle: &GoogleCalendarHandle, date: NaiveDate) -> Vec<OnCall> {
+    get_on_call(handle, date).await.unwrap_or_else(|e| {
+        warn!("Failed to get the on-call calendar's events: {}", e);
+        Vec::new()
+    })
+}
+
+/// When a rotation ends, like "Thu 08:00". All-day rotations name their last day instead.
+pub fn format_until(on_call: &OnCall) -> String {
+    if on_call.all_day {
+        let last = on_call.until.date() - Duration::days(1);
+        return weekday_short_name(last.weekday());
+    }
+    format!(
+        "{} {}",
+        weekday_short_name(on_call.until.weekday()),
+        on_call.until.format("%H:%M")
+    )
+}
+
+/// The line shown at the top of the daily notifications, like "On call: Anna (until Thu
+/// 08:00)", or None when nobody is on call
+pub fn oncall_line(on_call: &[OnCall]) -> Option<String> {
+    if on_call.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = on_call
+        .iter()
+        .map(|on_call| {
+            format!(
+                "{} ({})",
+                on_call.name,
+                t!("oncall_until", time = format_until(on_call))
+            )
+        })
+        .collect();
+    Some(t!("oncall_line", names = names.join(", ")).to_string())
+}
+
+/// Users who linked themselves to the employee an on-call name resolves to, out of the
+/// `(user, employee)` links from `/preferences`
+pub fn linked_users(name: &str, linked: &[(u64, String)]) -> Vec<u64> {
+    let name = EmployeeId::new(name);
+    linked
+        .iter()
+        .filter(|(_, employee)| EmployeeId::new(employee) == name)
+        .map(|(user_id, _)| *user_id)
+        .collect()
+}
+
+/// A line of `/päivystys`: who's on call, until when and a mention of their linked users
+pub fn oncall_entry(on_call: &OnCall, users: &[u64]) -> String {
+    let mentions: String = users
+        .iter()
+        .map(|user_id| format!(" {}", UserId::new(*user_id).mention()))
+        .collect();
+    format!(
+        "**{}** – {}{mentions}",
+        on_call.name,
+        t!("oncall_until", time = format_until(on_call))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::redis_service::RedisActorHandle;
+    use crate::user_preferences::{linked_employees, set_user_preferences, UserPreferences};
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn rotation(summary: &str, start: &str, end: &str) -> CalendarEvent {
+        CalendarEvent {
+            id: summary.to_string(),
+            summary: Some(summary.to_string()),
+            start_date_time: Some(start.to_string()),
+            end_date_time: Some(end.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_oncall_names() {
+        assert_eq!(oncall_names("Anna"), ["Anna"]);
+        assert_eq!(oncall_names("Päivystys:  Anna  Mäkinen"), ["Anna Mäkinen"]);
+        assert_eq!(
+            oncall_names("On call: Anna & Pekka, Hanna"),
+            ["Anna", "Pekka", "Hanna"]
+        );
+        assert!(oncall_names("Päivystys:").is_empty());
+    }
+
+    #[test]
+    fn test_rotation_spanning_the_day() {
+        let events = [
+            rotation(
+                "Päivystys: Anna",
+                "2025-03-10T08:00:00+02:00",
+                "2025-03-13T08:00:00+02:00",
+            ),
+            rotation(
+                "Pekka",
+                "2025-03-13T08:00:00+02:00",
+                "2025-03-17T08:00:00+02:00",
+            ),
+            CalendarEvent {
+                id: "week".to_string(),
+                summary: Some("Hanna".to_string()),
+                start_date: Some("2025-03-10".to_string()),
+                end_date: Some("2025-03-17".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        // A day in the middle of Anna's rotation, which started before it
+        let on_call = on_call_on(&events, date("2025-03-11"));
+        assert_eq!(
+            oncall_line(&on_call).unwrap(),
+            "🚨 On call: Anna (until Thu 08:00), Hanna (until Sun)"
+        );
+
+        // The handover day has both rotations
+        let names: Vec<_> = on_call_on(&events, date("2025-03-13"))
+            .into_iter()
+            .map(|on_call| on_call.name)
+            .collect();
+        assert_eq!(names, ["Anna", "Pekka", "Hanna"]);
+    }
+
+    #[test]
+    fn test_empty_oncall_calendar() {
+        assert!(on_call_on(&[], date("2025-03-11")).is_empty());
+        assert_eq!(oncall_line(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_linked_user_is_mentioned() {
+        let redis_handle = RedisActorHandle::fake();
+        let anna = UserPreferences {
+            employee: Some("Anna Mäkinen".to_string()),
+            ..Default::default()
+        };
+        set_user_preferences(&redis_handle, 42, &anna)
+            .await
+            .unwrap();
+        set_user_preferences(&redis_handle, 7, &UserPreferences::default())
+            .await
+            .unwrap();
+
+        let linked = linked_employees(&redis_handle).await.unwrap();
+        assert_eq!(linked, [(42, "Anna Mäkinen".to_string())]);
+
+        let on_call = OnCall {
+            name: "anna makinen".to_string(),
+            until: date("2025-03-13").and_hms_opt(8, 0, 0).unwrap(),
+            all_day: false,
+        };
+        let users = linked_users(&on_call.name, &linked);
+        assert_eq!(users, [42]);
+        assert_eq!(
+            oncall_entry(&on_call, &users),
+            "**anna makinen** – until Thu 08:00 <@42>"
+        );
+        assert!(linked_users("Pekka", &linked).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::BotResult;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::time::Duration;
+
+/// How long a day's call counter is kept, so the previous day can still be read after midnight
+pub const USAGE_TTL_SECS: u64 = 48 * 60 * 60;
+
+/// Share of the daily budget, in percent, at which admins are first warned
+pub const WARNING_PERCENT: u64 = 80;
+
+/// Redis key counting the Google Calendar API calls made on a day
+pub fn usage_key(date: NaiveDate) -> BotResult<Key> {
+    Key::fixed("google_calendar:api_calls").segment(&date.format("%Y-%m-%d").to_string())
+}
+
+/// Redis key claimed when admins have been warned about a level on a day
+fn warned_key(date: NaiveDate, level: QuotaLevel) -> BotResult<Key> {
+    Ok(Key::fixed("google_calendar:api_quota_warned")
+        .segment(&date.format("%Y-%m-%d").to_string())?
+        .name(level.name()))
+}
+
+/// The day calls are counted under: the date in the configured timezone, UTC if it's invalid
+pub fn quota_date(timezone: &str, now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&timezone.parse().unwrap_or(Tz::UTC))
+        .date_naive()
+}
+
+/// Count one API call for the day, returning the day's total
+pub async fn record_api_call(redis_handle: &RedisActorHandle, date: NaiveDate) -> BotResult<u64> {
+    let key = usage_key(date)?;
+    let calls = redis_handle.incr(&key).await?;
+    redis_handle.expire(&key, USAGE_TTL_SECS).await?;
+    Ok(calls)
+}
+
+/// API calls made on a day so far
+pub async fn api_calls_on(redis_handle: &RedisActorHandle, date: NaiveDate) -> BotResult<u64> {
+    let calls: Option<u64> = redis_handle.get(&usage_key(date)?).await?;
+    Ok(calls.unwrap_or(0))
+}
+
+/// Claim the warning about a level for a day. Returns false if another run already warned.
+pub async fn claim_warning(
+    redis_handle: &RedisActorHandle,
+    date: NaiveDate,
+    level: QuotaLevel,
+) -> BotResult<bool> {
+    redis_handle
+        .set_nx_ex(&warned_key(date, level)?, 1, USAGE_TTL_SECS)
+        .await
+}
+
+/// How much of the daily budget has been used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaLevel {
+    #[default]
+    Normal,
+    /// At least [`WARNING_PERCENT`] of the budget is used
+    Warning,
+    /// The whole budget is used
+    Exhausted,
+}
+
+impl QuotaLevel {
+    /// Level of a call count against a budget. A budget of 0 means there's no budget.
+    pub fn of(calls: u64, budget: u64) -> Self {
+        if budget == 0 {
+            QuotaLevel::Normal
+        } else if calls >= budget {
+            QuotaLevel::Exhausted
+        } else if calls * 100 >= budget * WARNING_PERCENT {
+            QuotaLevel::Warning
+        } else {
+            QuotaLevel::Normal
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            QuotaLevel::Normal => "normal",
+            QuotaLevel::Warning => "warning",
+            QuotaLevel::Exhausted => "exhausted",
+        }
+    }
+}
+
+/// Follows the day's quota level for the new events poller.
+///
+/// Each threshold is reported once a day, and from the warning threshold on the poller waits
+/// twice as long between checks. Both reset when the day changes.
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    date: Option<NaiveDate>,
+    level: QuotaLevel,
+}
+
+impl QuotaTracker {
+    /// Record the day's call count, returning the level when a threshold was newly crossed
+    pub fn observe(&mut self, date: NaiveDate, calls: u64, budget: u64) -> Option<QuotaLevel> {
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.level = QuotaLevel::Normal;
+        }
+
+        let level = QuotaLevel::of(calls, budget);
+        if level > self.level {
+            self.level = level;
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    /// Interval between checks on a day, doubled once the day crossed the warning threshold
+    pub fn interval(&self, date: NaiveDate, base: Duration) -> Duration {
+        if self.date == Some(date) && self.level >= QuotaLevel::Warning {
+            base * 2
+        } else {
+            base
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_thresholds_are_reported_once() {
+        let base = Duration::from_secs(300);
+        let mut tracker = QuotaTracker::default();
+
+        assert_eq!(tracker.observe(day(10), 79, 100), None);
+        assert_eq!(tracker.interval(day(10), base), base);
+
+        assert_eq!(tracker.observe(day(10), 80, 100), Some(QuotaLevel::Warning));
+        assert_eq!(tracker.observe(day(10), 90, 100), None);
+        assert_eq!(tracker.interval(day(10), base), base * 2);
+
+        assert_eq!(
+            tracker.observe(day(10), 100, 100),
+            Some(QuotaLevel::Exhausted)
+        );
+        assert_eq!(tracker.observe(day(10), 150, 100), None);
+        assert_eq!(tracker.interval(day(10), base), base * 2);
+
+        // Without a budget nothing is tracked
+        let mut tracker = QuotaTracker::default();
+        assert_eq!(tracker.observe(day(10), 1_000_000, 0), None);
+        assert_eq!(tracker.interval(day(10), base), base);
+    }
+
+    #[test]
+    fn test_tracker_resets_at_midnight() {
+        let base = Duration::from_secs(300);
+        let mut tracker = QuotaTracker::default();
+        assert_eq!(
+            tracker.observe(day(10), 100, 100),
+            Some(QuotaLevel::Exhausted)
+        );
+
+        // The next day starts over with a fresh counter
+        assert_eq!(tracker.interval(day(11), base), base);
+        assert_eq!(tracker.observe(day(11), 1, 100), None);
+        assert_eq!(tracker.interval(day(11), base), base);
+        assert_eq!(tracker.observe(day(11), 85, 100), Some(QuotaLevel::Warning));
+
+        // Midnight is the configured timezone's: 23:30 UTC is already the next day in Helsinki
+        let late = Utc.with_ymd_and_hms(2025, 3, 10, 23, 30, 0).unwrap();
+        assert_eq!(quota_date("UTC", late), day(10));
+        assert_eq!(quota_date("Europe/Helsinki", late), day(11));
+        assert_eq!(quota_date("Not/AZone", late), day(10));
+    }
+}
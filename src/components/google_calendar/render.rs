@@ -0,0 +1,136 @@
+use crate::components::google_calendar::models::CalendarEvent;
+use crate::utils::render::{View, ViewLine};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use rust_i18n::t;
+
+/// Color of the calendar replies
+const CALENDAR_COLOR: u32 = 0x0099FF;
+
+/// Date an event starts on in a timezone, if its start can be parsed
+fn start_date(event: &CalendarEvent, timezone: &Tz) -> Option<NaiveDate> {
+    if let Some(date_time) = &event.start_date_time {
+        // date_time is a string in RFC3339 format
+        DateTime::parse_from_rfc3339(date_time)
+            .ok()
+            .map(|event_time| event_time.with_timezone(timezone).date_naive())
+    } else {
+        // date is a string in YYYY-MM-DD format
+        NaiveDate::parse_from_str(event.start_date.as_deref()?, "%Y-%m-%d").ok()
+    }
+}
+
+/// Date an event starts on in a timezone, today if its start can't be parsed
+pub fn event_date(event: &CalendarEvent, timezone: &Tz) -> NaiveDate {
+    start_date(event, timezone).unwrap_or_else(|| Utc::now().with_timezone(timezone).date_naive())
+}
+
+fn event_title(event: &CalendarEvent) -> String {
+    event
+        .summary
+        .clone()
+        .unwrap_or(t!("calendar_unnamed_event").to_string())
+}
+
+/// Events starting in the seven days from `from`, one field per day
+pub fn week_events(
+    title: String,
+    events: &[CalendarEvent],
+    timezone: &Tz,
+    from: NaiveDate,
+) -> View {
+    events_between(
+        title,
+        events,
+        timezone,
+        (from, from + Duration::days(7)),
+        t!("calendar_no_events").to_string(),
+    )
+}
+
+/// Events starting yesterday, for `/eilen`
+pub fn yesterday_events(
+    title: String,
+    events: &[CalendarEvent],
+    timezone: &Tz,
+    date: NaiveDate,
+) -> View {
+    events_between(
+        title,
+        events,
+        timezone,
+        (date, date + Duration::days(1)),
+        t!("calendar_no_events_yesterday").to_string(),
+    )
+}
+
+/// Events starting from the first date until before the second, one field per day
+fn events_between(
+    title: String,
+    events: &[CalendarEvent],
+    timezone: &Tz,
+    (from, until): (NaiveDate, NaiveDate),
+    empty: String,
+) -> View {
+    let mut weekly_events: Vec<(NaiveDate, &CalendarEvent)> = events
+        .iter()
+        .filter_map(|event| Some((start_date(event, timezone)?, event)))
+        .filter(|(date, _)| *date >= from && *date < until)
+        .collect();
+    // Stable, so events keep their start time order within a day
+    weekly_events.sort_by_key(|(date, _)| *date);
+
+    let view = View::new(title, CALENDAR_COLOR);
+    if weekly_events.is_empty() {
+        return view.description(empty);
+    }
+
+    let mut days: Vec<(NaiveDate, Vec<ViewLine>)> = Vec::new();
+    for (date, event) in weekly_events {
+        let start_time = match &event.start_date_time {
+            Some(date_time) => match DateTime::parse_from_rfc3339(date_time) {
+                Ok(dt) => dt.with_timezone(timezone).format("%H:%M").to_string(),
+                Err(_) => t!("calendar_unknown_time").to_string(),
+            },
+            None => t!("calendar_all_day").to_string(),
+        };
+        let line = ViewLine::new(format!(
+            "{} **{start_time}** – {}",
+            event.color().emoji,
+            event_title(event)
+        ));
+        match days.last_mut() {
+            Some((day, lines)) if *day == date => lines.push(line),
+            _ => days.push((date, vec![line])),
+        }
+    }
+
+    days.into_iter().fold(view, |view, (date, lines)| {
+        view.field(date.format("%A, %B %d").to_string(), lines)
+    })
+}
+
+/// An event with when it starts and its details, in the event's color
+pub fn next_event(event: &CalendarEvent, timezone: &Tz) -> View {
+    let color = event.color();
+    let when = match &event.start_date_time {
+        Some(date_time) => match DateTime::parse_from_rfc3339(date_time) {
+            Ok(dt) => dt
+                .with_timezone(timezone)
+                .format("%A, %B %d %H:%M")
+                .to_string(),
+            Err(_) => t!("calendar_unknown_time").to_string(),
+        },
+        None => format!(
+            "{} ({})",
+            event_date(event, timezone).format("%A, %B %d"),
+            t!("calendar_all_day")
+        ),
+    };
+
+    let mut description = format!("{} **{when}**", color.emoji);
+    if let Some(details) = &event.description {
+        description.push_str(&format!("\n\n{details}"));
+    }
+    View::new(event_title(event), color.color).description(description)
+}
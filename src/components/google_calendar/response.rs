@@ -0,0 +1,165 @@
+//! Typed Google Calendar API responses.
+
+use super::models::CalendarEvent;
+use crate::error::{google_calendar_error, BotResult};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+/// Number of events skipped as malformed since startup
+static SKIPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of events from the API skipped as malformed since startup
+pub fn skipped_events() -> u64 {
+    SKIPPED_EVENTS.load(Ordering::Relaxed)
+}
+
+/// Response of the events list endpoint.
+///
+/// The items are kept as raw JSON so each one is deserialized on its own, and a malformed
+/// event doesn't take the rest of the page with it.
+#[derive(Debug, Deserialize)]
+pub struct EventsListResponse {
+    #[serde(default)]
+    pub items: Option<Vec<serde_json::Value>>,
+}
+
+/// An event as the API returns it. Only the id is required.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiEvent {
+    pub id: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created: Option<String>,
+    #[serde(default)]
+    pub start: EventTime,
+    #[serde(default)]
+    pub end: EventTime,
+    #[serde(default)]
+    pub color_id: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub visibility: Option<String>,
+}
+
+/// Start or end of an event: a date and time for timed events, a date for all-day ones
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTime {
+    #[serde(default)]
+    pub date_time: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+impl From<ApiEvent> for CalendarEvent {
+    fn from(event: ApiEvent) -> Self {
+        Self {
+            id: event.id,
+            summary: event.summary,
+            description: event.description,
+            created: event.created,
+            start_date_time: event.start.date_time,
+            start_date: event.start.date,
+            end_date_time: event.end.date_time,
+            end_date: event.end.date,
+            color_id: event.color_id,
+            location: event.location,
+            visibility: event.visibility,
+        }
+    }
+}
+
+/// Parse an events list response body, skipping and logging the events that don't deserialize
+pub fn parse_events(body: &str) -> BotResult<Vec<CalendarEvent>> {
+    let response: EventsListResponse = serde_json::from_str(body)
+        .map_err(|e| google_calendar_error(&format!("Failed to parse events response: {e}")))?;
+    let items = response
+        .items
+        .ok_or_else(|| google_calendar_error("No items in response"))?;
+
+    let total = items.len();
+    let mut events = Vec::with_capacity(total);
+    for (index, item) in items.into_iter().enumerate() {
+        match serde_json::from_value::<ApiEvent>(item) {
+            Ok(event) => events.push(event.into()),
+            Err(e) => warn!("Skipping malformed calendar event {}: {}", index, e),
+        }
+    }
+
+    let skipped = total - events.len();
+    if skipped > 0 {
+        let count = SKIPPED_EVENTS.fetch_add(skipped as u64, Ordering::Relaxed) + skipped as u64;
+        warn!(
+            skipped_events = count,
+            "Skipped {} of {} calendar events as malformed", skipped, total
+        );
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captured_response_parses() {
+        let events =
+            parse_events(include_str!("../../../tests/fixtures/calendar_events.json")).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let timed = &events[0];
+        assert_eq!(timed.id, "4q2m9v7tk3h1s0bq8l5n6r2c1e");
+        assert_eq!(timed.summary.as_deref(), Some("Inventaario"));
+        assert_eq!(
+            timed.start_date_time.as_deref(),
+            Some("2025-03-12T08:00:00+02:00")
+        );
+        assert_eq!(
+            timed.end_date_time.as_deref(),
+            Some("2025-03-12T12:00:00+02:00")
+        );
+        assert_eq!(timed.color_id.as_deref(), Some("11"));
+        assert_eq!(timed.location.as_deref(), Some("Varasto"));
+
+        let all_day = &events[1];
+        assert_eq!(all_day.start_date.as_deref(), Some("2025-03-14"));
+        assert_eq!(all_day.end_date.as_deref(), Some("2025-03-15"));
+        assert_eq!(all_day.start_date_time, None);
+    }
+
+    #[test]
+    fn test_missing_optional_fields_are_none() {
+        let events = parse_events(include_str!(
+            "../../../tests/fixtures/calendar_events_minimal.json"
+        ))
+        .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, "minimal1");
+        assert_eq!(events[0].summary, None);
+        assert_eq!(events[0].start_date_time, None);
+        assert_eq!(events[0].start_date, None);
+        assert_eq!(events[0].color_id, None);
+    }
+
+    #[test]
+    fn test_malformed_events_are_skipped() {
+        let before = skipped_events();
+        let events = parse_events(include_str!(
+            "../../../tests/fixtures/calendar_events_malformed.json"
+        ))
+        .unwrap();
+
+        let ids: Vec<&str> = events.iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, ["good1", "good2"]);
+        assert!(skipped_events() >= before + 2);
+
+        assert!(parse_events("{}").is_err());
+        assert!(parse_events("not json").is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use chrono::Local;
+use chrono::{Local, NaiveDate, Timelike, Utc};
 use lazy_static::lazy_static;
-use poise::serenity_prelude as serenity;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use rust_i18n::t;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
@@ -14,15 +15,24 @@ use super::handle::GoogleCalendarHandle;
 use super::notifications::{
     send_daily_notification, send_new_events_notification, send_weekly_notification,
 };
+use super::quota::{api_calls_on, claim_warning, quota_date, QuotaLevel, QuotaTracker};
 use super::time::next_notification_time;
+use crate::components::redis_service::RedisActorHandle;
 use crate::config::Config;
 use crate::error::BotResult;
+use crate::features::{get_guild_features, Feature, FeatureFlags};
+use crate::guild_config::{notification_settings, NotificationSettings};
+use crate::theme::Theme;
+use crate::utils::backoff::{with_jitter, PollBackoff, PollOutcome, AUTH_ALERT_THRESHOLD};
+use crate::utils::notifier::{notification_sinks, DailyReplace, DiscordNotifier, Sinks};
 use crate::utils::scheduler::{
-    is_notification_sent, reset_notification_flag, sleep_until_target_time, try_claim_notification,
+    deliver_notification, is_notification_sent, next_wake_time, reset_notification_flag,
+    retry_pending_notifications, sleep_until_target_time, try_claim_notification,
     update_last_sent_date, update_notification_flags, NotificationHandler, NotificationType,
-    Scheduler,
+    Scheduler, SharedContext,
 };
-use crate::utils::time::get_weekly_date_range;
+use crate::utils::time::{get_weekly_date_range, is_within_time_range, WeekStart};
+use crate::watchdog::{SchedulerHeartbeat, Target};
 
 lazy_static! {
     static ref SCHEDULER_INSTANCES: AtomicU32 = AtomicU32::new(0);
@@ -52,9 +62,10 @@ impl Scheduler for GoogleCalendarScheduler {
 
     /// Start the notification scheduler
     fn start(
-        ctx: Arc<serenity::Context>,
+        ctx: SharedContext,
         config: Arc<RwLock<Config>>,
         handle: Self::Handle,
+        redis_handle: RedisActorHandle,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send>> {
         Box::pin(async move {
             // Increment instance counter and log
@@ -72,27 +83,32 @@ impl Scheduler for GoogleCalendarScheduler {
 
             // Read config values
             let config_read = config.read().await;
-            let daily_time = config_read.daily_notification_time.clone();
-            let weekly_time = config_read.weekly_notification_time.clone();
-            let channel_id = config_read.calendar_channel_id;
 
             // Get the new events check interval
             let new_events_check_interval = config_read.new_events_check_interval;
+            let show_empty_days = config_read.show_empty_days;
+            let week_start = config_read.week_starts_on;
+            // The combined digest sends the daily events instead
+            let daily_enabled = !config_read.combined_daily_digest;
             drop(config_read);
 
             // Create the notification handler
-            let notification_handler = GoogleCalendarNotificationHandler {
+            let notification_handler = Arc::new(GoogleCalendarNotificationHandler {
                 handle: handle.clone(),
-            };
-            let notification_handler = Arc::new(notification_handler);
+                show_empty_days,
+                redis_handle: redis_handle.clone(),
+                config: Arc::clone(&config),
+            });
 
             // Get the component type
             let component_type = Self::component_type();
 
             // Spawn task for daily/weekly notifications
-            let ctx_clone = Arc::clone(&ctx);
+            let ctx_clone = ctx.clone();
             let handler_clone = Arc::clone(&notification_handler);
             let component_type_clone = component_type.clone();
+            let redis_clone = redis_handle.clone();
+            let config_clone = Arc::clone(&config);
 
             // Only spawn the daily/weekly task if it's not already running
             if !DAILY_WEEKLY_TASK_RUNNING.swap(true, Ordering::SeqCst) {
@@ -100,11 +116,12 @@ impl Scheduler for GoogleCalendarScheduler {
                 let task = tokio::spawn(async move {
                     run_daily_weekly_task(
                         ctx_clone,
-                        &daily_time,
-                        &weekly_time,
-                        channel_id,
                         handler_clone,
                         &component_type_clone,
+                        config_clone,
+                        redis_clone,
+                        week_start,
+                        daily_enabled,
                     )
                     .await;
                 });
@@ -116,7 +133,7 @@ impl Scheduler for GoogleCalendarScheduler {
             }
 
             // Spawn task for checking new events
-            let ctx_clone = Arc::clone(&ctx);
+            let ctx_clone = ctx;
             let handle_clone = handle.clone();
 
             // Only spawn the new events task if it's not already running
@@ -125,8 +142,9 @@ impl Scheduler for GoogleCalendarScheduler {
                 let task = tokio::spawn(async move {
                     run_new_events_task(
                         ctx_clone,
-                        channel_id,
                         handle_clone,
+                        config,
+                        redis_handle,
                         new_events_check_interval,
                     )
                     .await;
@@ -159,6 +177,9 @@ impl Scheduler for GoogleCalendarScheduler {
                 NEW_EVENTS_TASK_RUNNING.store(false, Ordering::SeqCst);
             }
 
+            // Starting again, e.g. after regaining leadership, isn't a second instance
+            SCHEDULER_INSTANCES.store(0, Ordering::SeqCst);
+
             info!("Google Calendar scheduler stopped");
             Ok(())
         })
@@ -168,55 +189,111 @@ impl Scheduler for GoogleCalendarScheduler {
 /// Google Calendar notification handler implementation
 struct GoogleCalendarNotificationHandler {
     handle: GoogleCalendarHandle,
+    show_empty_days: bool,
+    redis_handle: RedisActorHandle,
+    config: Arc<RwLock<Config>>,
 }
 
 impl NotificationHandler for GoogleCalendarNotificationHandler {
     fn send_daily_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
         let handle = self.handle.clone();
 
         Box::pin(async move {
+            let config = self.config.read().await.clone();
+            let mode = DailyReplace::from_config(&config);
+            let sink = notification_sinks(http, &self.redis_handle, &config, &sinks);
             info!("Sending daily calendar notification");
-            send_daily_notification(ctx, channel_id, &handle).await
+            let theme = Theme::for_channel(http, &self.redis_handle, channel_id).await;
+            let show_private = config.notifications_show_private_event_details(channel_id);
+            send_daily_notification(&sink, channel_id, &handle, mode, show_private, &theme).await
         })
     }
 
     fn send_weekly_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
         let handle = self.handle.clone();
 
         Box::pin(async move {
             info!("Sending weekly calendar notification");
-            send_weekly_notification(ctx, channel_id, &handle).await
+            let config = self.config.read().await.clone();
+            let sink = notification_sinks(http, &self.redis_handle, &config, &sinks);
+            let theme = Theme::for_channel(http, &self.redis_handle, channel_id).await;
+            send_weekly_notification(
+                &sink,
+                channel_id,
+                &handle,
+                self.show_empty_days,
+                config.week_starts_on,
+                config.notifications_show_private_event_details(channel_id),
+                &theme,
+            )
+            .await
         })
     }
 }
 
+/// Notification handler for sending outside the scheduler, e.g. from a one-shot run
+pub async fn notification_handler(
+    handle: GoogleCalendarHandle,
+    redis_handle: RedisActorHandle,
+    config: Arc<RwLock<Config>>,
+) -> Arc<dyn NotificationHandler> {
+    let show_empty_days = config.read().await.show_empty_days;
+    Arc::new(GoogleCalendarNotificationHandler {
+        handle,
+        show_empty_days,
+        redis_handle,
+        config,
+    })
+}
+
 /// The main loop for daily and weekly notifications
+#[allow(clippy::too_many_arguments)]
 async fn run_daily_weekly_task(
-    ctx: Arc<serenity::Context>,
-    daily_time: &str,
-    weekly_time: &str,
-    channel_id: u64,
+    ctx: SharedContext,
     handler: Arc<dyn NotificationHandler>,
     component_type: &str,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
+    week_start: WeekStart,
+    daily_enabled: bool,
 ) {
+    let liveness =
+        SchedulerHeartbeat::register("google_calendar", component_type, redis_handle.clone());
     loop {
         let now = Local::now();
         let today = now.format("%Y-%m-%d").to_string();
-        let (week_start_date, _) = get_weekly_date_range(&now);
+        let (week_start_date, _) = get_weekly_date_range(&now, week_start);
+        let NotificationSettings {
+            channel_id,
+            daily_time,
+            weekly_time,
+        } = notification_settings(&redis_handle, &config).await;
 
         // Update notification flags
         update_notification_flags(&today, &week_start_date, component_type).await;
 
+        // Retry notifications that couldn't be delivered earlier
+        let has_pending = retry_pending_notifications(
+            &ctx,
+            handler.as_ref(),
+            &redis_handle,
+            component_type,
+            week_start,
+        )
+        .await;
+
         // Calculate next notification times
-        let next_daily = match next_notification_time(now, daily_time, false) {
+        let next_daily = match next_notification_time(now, daily_time, false, week_start) {
             Ok(time) => time,
             Err(e) => {
                 error!("Failed to calculate next daily notification time: {}", e);
@@ -225,7 +302,7 @@ async fn run_daily_weekly_task(
             }
         };
 
-        let next_weekly = match next_notification_time(now, weekly_time, true) {
+        let next_weekly = match next_notification_time(now, weekly_time, true, week_start) {
             Ok(time) => time,
             Err(e) => {
                 error!("Failed to calculate next weekly notification time: {}", e);
@@ -241,17 +318,17 @@ async fn run_daily_weekly_task(
         // Check if the current day/week needs notifications, or if we need to wait
         let daily_today = next_daily.date_naive().format("%Y-%m-%d").to_string() == today;
         let weekly_this_week = {
-            let (current_week_start, _) = get_weekly_date_range(&now);
-            let (next_week_start, _) = get_weekly_date_range(&next_weekly);
+            let (current_week_start, _) = get_weekly_date_range(&now, week_start);
+            let (next_week_start, _) = get_weekly_date_range(&next_weekly, week_start);
             current_week_start == next_week_start
         };
 
         // Determine which notification comes next and needs to be sent
-        let (next_type, next_time) = if daily_today && !daily_sent {
+        let (next_type, next_time) = if daily_enabled && daily_today && !daily_sent {
             (NotificationType::Daily, next_daily)
         } else if weekly_this_week && !weekly_sent {
             (NotificationType::Weekly, next_weekly)
-        } else if next_daily <= next_weekly {
+        } else if daily_enabled && next_daily <= next_weekly {
             (NotificationType::Daily, next_daily)
         } else {
             (NotificationType::Weekly, next_weekly)
@@ -262,33 +339,67 @@ async fn run_daily_weekly_task(
             component_type, next_type, next_time
         );
 
-        // Sleep until the target time
-        if let Err(e) = sleep_until_target_time(next_time).await {
+        // Sleep until the target time, waking up earlier to retry parked notifications
+        let wake_time = next_wake_time(next_time, has_pending);
+        liveness
+            .waiting_until(
+                wake_time,
+                Some(Target::new(next_type.clone(), next_time, week_start)),
+            )
+            .await;
+        if let Err(e) = sleep_until_target_time(wake_time).await {
             error!("Error while waiting for target time: {:?}", e);
             sleep(TokioDuration::from_secs(60)).await; // Wait a minute before retrying
             continue;
         }
+        if wake_time < next_time {
+            continue;
+        }
 
         // After waking, determine which notification(s) to send
         let now = Local::now();
 
         // Determine if we should send notifications
-        let send_daily = now >= next_daily
+        let send_daily = daily_enabled
+            && now >= next_daily
             && !is_notification_sent(NotificationType::Daily, component_type).await;
         let send_weekly = now >= next_weekly
             && !is_notification_sent(NotificationType::Weekly, component_type).await;
 
         // Handle daily notification
         if send_daily {
-            if try_claim_notification(NotificationType::Daily, component_type).await {
+            if try_claim_notification(
+                NotificationType::Daily,
+                component_type,
+                &redis_handle,
+                &today,
+            )
+            .await
+            {
                 info!("[{}] Sending daily calendar notification", component_type);
 
-                if let Err(e) = handler.send_daily_notification(&ctx, channel_id).await {
+                if let Err(e) = deliver_notification(
+                    &ctx,
+                    handler.as_ref(),
+                    &redis_handle,
+                    component_type,
+                    NotificationType::Daily,
+                    &today,
+                    channel_id,
+                )
+                .await
+                {
                     error!(
-                        "[{}] Failed to send daily notification: {}",
+                        "[{}] Failed to send daily notification, retrying later: {}",
                         component_type, e
                     );
-                    reset_notification_flag(NotificationType::Daily, component_type).await;
+                    reset_notification_flag(
+                        NotificationType::Daily,
+                        component_type,
+                        &redis_handle,
+                        &today,
+                    )
+                    .await;
                 } else {
                     info!(
                         "[{}] Successfully sent daily calendar notification",
@@ -306,15 +417,38 @@ async fn run_daily_weekly_task(
 
         // Handle weekly notification
         if send_weekly {
-            if try_claim_notification(NotificationType::Weekly, component_type).await {
+            if try_claim_notification(
+                NotificationType::Weekly,
+                component_type,
+                &redis_handle,
+                &week_start_date,
+            )
+            .await
+            {
                 info!("[{}] Sending weekly calendar notification", component_type);
 
-                if let Err(e) = handler.send_weekly_notification(&ctx, channel_id).await {
+                if let Err(e) = deliver_notification(
+                    &ctx,
+                    handler.as_ref(),
+                    &redis_handle,
+                    component_type,
+                    NotificationType::Weekly,
+                    &week_start_date,
+                    channel_id,
+                )
+                .await
+                {
                     error!(
-                        "[{}] Failed to send weekly notification: {}",
+                        "[{}] Failed to send weekly notification, retrying later: {}",
                         component_type, e
                     );
-                    reset_notification_flag(NotificationType::Weekly, component_type).await;
+                    reset_notification_flag(
+                        NotificationType::Weekly,
+                        component_type,
+                        &redis_handle,
+                        &week_start_date,
+                    )
+                    .await;
                 } else {
                     info!(
                         "[{}] Successfully sent weekly calendar notification",
@@ -340,14 +474,153 @@ async fn run_daily_weekly_task(
     }
 }
 
-/// The task for checking and notifying about new events
+/// Check whether polling should pause for the configured guild's quiet hours
+async fn in_quiet_hours(config: &Arc<RwLock<Config>>, redis_handle: &RedisActorHandle) -> bool {
+    let (range, guild_id, defaults) = {
+        let config = config.read().await;
+        (
+            config.quiet_hours.clone(),
+            config.guild_id,
+            FeatureFlags::from_names(&config.default_features),
+        )
+    };
+    let Some(range) = range else {
+        return false;
+    };
+
+    let now = Local::now();
+    if !is_within_time_range(&range, now.hour(), now.minute()).unwrap_or(false) {
+        return false;
+    }
+    get_guild_features(redis_handle, guild_id, defaults)
+        .await
+        .contains(Feature::QuietHours)
+}
+
+/// Tell admins the Google credentials need re-authorizing
+async fn send_auth_alert(ctx: &SharedContext, config: &Arc<RwLock<Config>>) {
+    error!(
+        "Google Calendar authorization failed {} times in a row, re-authorize the bot",
+        AUTH_ALERT_THRESHOLD
+    );
+    let Some(channel_id) = config.read().await.error_channel_id else {
+        return;
+    };
+
+    let embed = CreateEmbed::new()
+        .title(t!("calendar_auth_alert_title"))
+        .description(t!("calendar_auth_alert", failures = AUTH_ALERT_THRESHOLD))
+        .color(0xFF_00_00);
+    let ctx = ctx.current().await;
+    if let Err(e) = ChannelId::new(channel_id)
+        .send_message(&ctx, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Failed to send authorization alert: {}", e);
+    }
+}
+
+/// Warn admins that a threshold of the daily API budget was crossed, once a day per threshold
+async fn send_quota_warning(
+    ctx: &SharedContext,
+    config: &Arc<RwLock<Config>>,
+    redis_handle: &RedisActorHandle,
+    date: NaiveDate,
+    level: QuotaLevel,
+    calls: u64,
+    budget: u64,
+) {
+    warn!(
+        "Google Calendar API calls at {} of the daily budget of {}, checking new events less often until midnight",
+        calls, budget
+    );
+    let Some(channel_id) = config.read().await.error_channel_id else {
+        return;
+    };
+    match claim_warning(redis_handle, date, level).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            warn!("Failed to claim the quota warning: {}", e);
+            return;
+        }
+    }
+
+    let description = match level {
+        QuotaLevel::Exhausted => t!("calendar_quota_exhausted", calls = calls, budget = budget),
+        _ => t!("calendar_quota_warning", calls = calls, budget = budget),
+    };
+    let embed = CreateEmbed::new()
+        .title(t!("calendar_quota_title"))
+        .description(description)
+        .color(0xFF_A5_00);
+    let ctx = ctx.current().await;
+    if let Err(e) = ChannelId::new(channel_id)
+        .send_message(&ctx, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Failed to send quota warning: {}", e);
+    }
+}
+
+/// Compare the day's API calls against the budget, warning admins about newly crossed
+/// thresholds. Returns the day the calls were counted under.
+async fn check_quota(
+    ctx: &SharedContext,
+    config: &Arc<RwLock<Config>>,
+    redis_handle: &RedisActorHandle,
+    quota: &mut QuotaTracker,
+) -> NaiveDate {
+    let (timezone, budget) = {
+        let config = config.read().await;
+        (config.timezone.clone(), config.calendar_api_daily_budget)
+    };
+    let today = quota_date(&timezone, Utc::now());
+
+    match api_calls_on(redis_handle, today).await {
+        Ok(calls) => {
+            if let Some(level) = quota.observe(today, calls, budget) {
+                send_quota_warning(ctx, config, redis_handle, today, level, calls, budget).await;
+            }
+        }
+        Err(e) => warn!("Failed to read Google Calendar API usage: {}", e),
+    }
+    today
+}
+
+/// The task for checking and notifying about new events.
+///
+/// Failures back off exponentially up to an hour, and a long streak of authorization failures
+/// alerts the admins once. Once the day's API calls reach the warning threshold of the budget,
+/// checks happen half as often until midnight.
 async fn run_new_events_task(
-    ctx: Arc<serenity::Context>,
-    channel_id: u64,
+    ctx: SharedContext,
     handle: GoogleCalendarHandle,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
     check_interval: u64,
 ) {
+    let mut backoff = PollBackoff::new(TokioDuration::from_secs(check_interval));
+    let mut quota = QuotaTracker::default();
+    let liveness = SchedulerHeartbeat::register(
+        "google_calendar_new_events",
+        "google_calendar",
+        redis_handle.clone(),
+    );
+
     loop {
+        if in_quiet_hours(&config, &redis_handle).await {
+            debug!("Quiet hours, skipping new events check");
+            liveness
+                .waiting_until(
+                    Local::now() + TokioDuration::from_secs(check_interval),
+                    None,
+                )
+                .await;
+            sleep(TokioDuration::from_secs(check_interval)).await;
+            continue;
+        }
+
         // Get current timestamp in seconds
         let now = chrono::Utc::now().timestamp();
         let last_check = *LAST_NEW_EVENTS_CHECK.read().await;
@@ -363,12 +636,27 @@ async fn run_new_events_task(
         *LAST_NEW_EVENTS_CHECK.write().await = now;
 
         debug!("Checking for new calendar events");
-        match handle.check_new_events().await {
+        let result = handle.check_new_events().await;
+        match &result {
             Ok(new_events) => {
                 if !new_events.is_empty() {
                     info!("Found {} new calendar events", new_events.len());
-                    if let Err(e) =
-                        send_new_events_notification(&ctx, channel_id, &new_events).await
+                    let channel_id = notification_settings(&redis_handle, &config)
+                        .await
+                        .channel_id;
+                    let ctx = ctx.current().await;
+                    let theme = Theme::for_channel(&ctx.http, &redis_handle, channel_id).await;
+                    let notifier = DiscordNotifier::new(&ctx);
+                    let show_private = config.read().await.shows_private_event_details(channel_id);
+                    if let Err(e) = send_new_events_notification(
+                        &notifier,
+                        &redis_handle,
+                        channel_id,
+                        new_events,
+                        show_private,
+                        &theme,
+                    )
+                    .await
                     {
                         error!("Failed to send new events notification: {}", e);
                     } else {
@@ -382,12 +670,28 @@ async fn run_new_events_task(
                 }
             }
             Err(e) => {
-                error!("Failed to check for new events: {}", e);
+                error!(
+                    "Failed to check for new events ({} in a row): {}",
+                    backoff.failures() + 1,
+                    e
+                );
             }
         }
 
-        // Wait for the configured interval before checking again
-        debug!("Waiting {}s before next new events check", check_interval);
-        sleep(TokioDuration::from_secs(check_interval)).await;
+        let decision = backoff.record(PollOutcome::of(&result));
+        if decision.alert {
+            send_auth_alert(&ctx, &config).await;
+        }
+        let today = check_quota(&ctx, &config, &redis_handle, &mut quota).await;
+
+        // Wait for the configured interval, or longer while the checks keep failing
+        let delay = if backoff.failures() == 0 {
+            quota.interval(today, decision.delay)
+        } else {
+            with_jitter(decision.delay)
+        };
+        debug!("Waiting {}s before next new events check", delay.as_secs());
+        liveness.waiting_until(Local::now() + delay, None).await;
+        sleep(delay).await;
     }
 }
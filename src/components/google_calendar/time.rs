@@ -1,15 +1,17 @@
 use super::models::CalendarEvent;
 use crate::error::{google_calendar_error, BotResult};
-use crate::utils::time;
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use crate::utils::time::{self, TimeOfDay, WeekStart};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Calculate next notification time
 pub fn next_notification_time(
     current_time: DateTime<Local>,
-    target_time: &str,
+    target_time: TimeOfDay,
     is_weekly: bool,
+    week_start: WeekStart,
 ) -> BotResult<DateTime<Local>> {
-    let result = time::next_notification_time(current_time, target_time, is_weekly)
+    let result = time::next_notification_time(current_time, target_time, is_weekly, week_start)
         .ok_or_else(|| google_calendar_error("Failed to calculate next notification time"))?;
 
     Ok(result)
@@ -50,3 +52,205 @@ pub fn get_event_start(event: &CalendarEvent) -> BotResult<Option<DateTime<Local
         Ok(None)
     }
 }
+
+/// Start and end of an event in the wall clock time it was given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventSpan {
+    pub start: NaiveDateTime,
+    /// Exclusive end; for all-day events this is midnight after the last day
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+}
+
+/// Parse a Google Calendar dateTime value
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.naive_local())
+        })
+}
+
+/// Parse a Google Calendar all-day date value as midnight
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+}
+
+/// Get the span of an event, or None if its start can't be parsed
+pub fn event_span(event: &CalendarEvent) -> Option<EventSpan> {
+    if let Some(start) = event.start_date_time.as_deref().and_then(parse_date_time) {
+        let end = event
+            .end_date_time
+            .as_deref()
+            .and_then(parse_date_time)
+            .filter(|end| *end >= start)
+            .unwrap_or(start);
+        return Some(EventSpan {
+            start,
+            end,
+            all_day: false,
+        });
+    }
+
+    let start = event.start_date.as_deref().and_then(parse_date)?;
+    let end = event
+        .end_date
+        .as_deref()
+        .and_then(parse_date)
+        .filter(|end| *end > start)
+        .unwrap_or(start + Duration::days(1));
+    Some(EventSpan {
+        start,
+        end,
+        all_day: true,
+    })
+}
+
+/// Check whether an event takes place on a date, including every day of multi-day events
+pub fn occurs_on(event: &CalendarEvent, date: NaiveDate) -> bool {
+    let Some(span) = event_span(event) else {
+        return false;
+    };
+    let Some(day_start) = date.and_hms_opt(0, 0, 0) else {
+        return false;
+    };
+    let day_end = day_start + Duration::days(1);
+
+    // Zero-length events still count on the day they start
+    span.start < day_end && (span.end > day_start || span.start >= day_start)
+}
+
+/// Time range calendar events are fetched over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventWindow {
+    pub start: DateTime<Utc>,
+    /// Exclusive end
+    pub end: DateTime<Utc>,
+}
+
+impl EventWindow {
+    /// Window from `past_days` before `now` until `future_days` after it
+    pub fn around(now: DateTime<Utc>, past_days: u32, future_days: u32) -> Self {
+        Self {
+            start: now - Duration::days(past_days.into()),
+            end: now + Duration::days(future_days.into()),
+        }
+    }
+
+    /// Part of the window also covered by `other`, if any
+    pub fn overlap(&self, other: &EventWindow) -> Option<EventWindow> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(EventWindow { start, end })
+    }
+
+    /// Whether an event takes place in the window. Like Google's own range query, an event
+    /// counts when it ends after the start and starts before the end. All-day events are taken
+    /// as UTC days, and events whose times can't be parsed always count.
+    pub fn contains(&self, event: &CalendarEvent) -> bool {
+        let instant = |date_time: Option<&str>, date: Option<&str>| match date_time
+            .map(DateTime::parse_from_rfc3339)
+        {
+            Some(parsed) => parsed.ok().map(|dt| dt.with_timezone(&Utc)),
+            None => parse_date(date?).map(|dt| dt.and_utc()),
+        };
+        let Some(start) = instant(
+            event.start_date_time.as_deref(),
+            event.start_date.as_deref(),
+        ) else {
+            return true;
+        };
+        let end = instant(event.end_date_time.as_deref(), event.end_date.as_deref())
+            .filter(|end| *end > start)
+            .unwrap_or(start);
+
+        // Zero-length events count at their start
+        start < self.end && (end > self.start || start >= self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_occurs_on_timed_and_all_day_events() {
+        let timed = CalendarEvent {
+            start_date_time: Some("2025-03-13T22:00:00+02:00".to_string()),
+            end_date_time: Some("2025-03-14T02:00:00+02:00".to_string()),
+            ..Default::default()
+        };
+        assert!(!occurs_on(&timed, date("2025-03-12")));
+        assert!(occurs_on(&timed, date("2025-03-13")));
+        assert!(occurs_on(&timed, date("2025-03-14")));
+        assert!(!occurs_on(&timed, date("2025-03-15")));
+
+        // All-day end dates are exclusive
+        let all_day = CalendarEvent {
+            start_date: Some("2025-03-12".to_string()),
+            end_date: Some("2025-03-15".to_string()),
+            ..Default::default()
+        };
+        assert!(occurs_on(&all_day, date("2025-03-12")));
+        assert!(occurs_on(&all_day, date("2025-03-14")));
+        assert!(!occurs_on(&all_day, date("2025-03-15")));
+
+        let instant = CalendarEvent {
+            start_date_time: Some("2025-03-10T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        assert!(occurs_on(&instant, date("2025-03-10")));
+        assert!(!occurs_on(&instant, date("2025-03-09")));
+    }
+
+    #[test]
+    fn test_event_window_overlap_and_contains() {
+        let at = |value: &str| DateTime::parse_from_rfc3339(value).unwrap().to_utc();
+        let now = at("2025-03-10T12:00:00Z");
+        let window = EventWindow::around(now, 1, 28);
+        assert_eq!(window.start, at("2025-03-09T12:00:00Z"));
+        assert_eq!(window.end, at("2025-04-07T12:00:00Z"));
+
+        // A day later the window has moved on by a day at both ends
+        let later = EventWindow::around(now + Duration::days(1), 1, 28);
+        assert_eq!(
+            window.overlap(&later),
+            Some(EventWindow {
+                start: later.start,
+                end: window.end
+            })
+        );
+        let far = EventWindow::around(now + Duration::days(60), 1, 28);
+        assert_eq!(window.overlap(&far), None);
+
+        let timed = |start: &str, end: &str| CalendarEvent {
+            start_date_time: Some(start.to_string()),
+            end_date_time: Some(end.to_string()),
+            ..Default::default()
+        };
+        assert!(window.contains(&timed("2025-03-09T10:00:00Z", "2025-03-09T13:00:00Z")));
+        assert!(!window.contains(&timed("2025-03-09T10:00:00Z", "2025-03-09T12:00:00Z")));
+        assert!(window.contains(&timed(
+            "2025-04-07T14:00:00+03:00",
+            "2025-04-07T15:00:00+03:00"
+        )));
+        assert!(!window.contains(&timed("2025-04-07T12:00:00Z", "2025-04-07T13:00:00Z")));
+
+        let all_day = CalendarEvent {
+            start_date: Some("2025-04-07".to_string()),
+            end_date: Some("2025-04-08".to_string()),
+            ..Default::default()
+        };
+        assert!(window.contains(&all_day));
+        assert!(!EventWindow::around(now, 0, 20).contains(&all_day));
+        assert!(window.contains(&CalendarEvent::default()));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::components::redis_service::RedisActorHandle;
 use crate::config::Config;
-use crate::error::{google_calendar_error, BotResult};
+use crate::error::{google_auth_error, google_calendar_error, network_error, BotResult};
 use chrono::Utc;
 use reqwest::Client;
 use serde_json::{json, Value};
@@ -46,7 +46,7 @@ impl TokenManager {
         }
 
         // No token in Redis or no expiry, return error - manual setup required
-        Err(google_calendar_error(
+        Err(google_auth_error(
             "No valid token found. Please set up token manually.",
         ))
     }
@@ -56,7 +56,7 @@ impl TokenManager {
         let refresh_token = token
             .get("refresh_token")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| google_calendar_error("No refresh token in token data"))?;
+            .ok_or_else(|| google_auth_error("No refresh token in token data"))?;
 
         let client_id = {
             let config_read = self.config.read().await;
@@ -81,7 +81,7 @@ impl TokenManager {
             .form(&params)
             .send()
             .await
-            .map_err(|e| google_calendar_error(&format!("Failed to refresh token: {e}")))?;
+            .map_err(|e| network_error(&format!("Failed to refresh token: {e}")))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -89,9 +89,13 @@ impl TokenManager {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Could not read error response".to_string());
-            return Err(google_calendar_error(&format!(
-                "Failed to refresh token: HTTP {status} - {error_body}"
-            )));
+            let message = format!("Failed to refresh token: HTTP {status} - {error_body}");
+            // Google answers a revoked or expired refresh token with 400 invalid_grant
+            return Err(if status.is_client_error() {
+                google_auth_error(&message)
+            } else {
+                google_calendar_error(&message)
+            });
         }
 
         let mut new_token: Value = response
@@ -1,19 +1,26 @@
 use crate::components::redis_service::RedisActorHandle;
 use crate::config::Config;
 use crate::error::BotResult;
+use crate::utils::scheduler::SharedContext;
 use async_trait::async_trait;
 use poise::serenity_prelude as serenity;
 use std::any::Any;
 use std::fmt;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, RwLock as SyncRwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
 use tracing::info;
 
 // Export components
+pub mod digest;
+pub mod event_bus;
 pub mod google_calendar;
 pub mod redis_service;
+pub mod supervisor;
+pub mod warmup;
 pub mod work_schedule;
 
+pub use event_bus::EventBus;
 // Re-export Google Calendar handle
 pub use google_calendar::GoogleCalendarHandle;
 // Re-export Work Schedule handle
@@ -24,13 +31,47 @@ pub trait Component: Send + Sync + Any {
     /// Get the name of the component
     fn name(&self) -> &'static str;
 
-    /// Initialize the component
+    /// Create the component's handles and actors, subscribing to any bus events it needs.
+    /// Needs no Discord connection, so binaries without one can use the component too.
+    async fn create(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+        bus: EventBus,
+    ) -> BotResult<()>;
+
+    /// Hand the component the Discord context its schedulers post through. Called after
+    /// `create`, and again with the fresh context after a reconnect.
+    async fn attach(&self, _ctx: &serenity::Context) -> BotResult<()> {
+        Ok(())
+    }
+
+    /// Initialize the component for the bot: create it and attach the Discord context
     async fn init(
         &self,
         ctx: &serenity::Context,
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
-    ) -> BotResult<()>;
+        bus: EventBus,
+    ) -> BotResult<()> {
+        self.create(config, redis_handle, bus).await?;
+        self.attach(ctx).await
+    }
+
+    /// Start the schedulers and background tasks. Only the leader replica runs them, so this
+    /// is called after `init` whenever the instance becomes the leader.
+    async fn start_background(
+        &self,
+        _config: Arc<RwLock<Config>>,
+        _redis_handle: RedisActorHandle,
+    ) -> BotResult<()> {
+        Ok(())
+    }
+
+    /// Stop the schedulers and background tasks, keeping what commands use
+    async fn stop_background(&self) -> BotResult<()> {
+        Ok(())
+    }
 
     /// Shutdown the component
     async fn shutdown(&self) -> BotResult<()>;
@@ -39,17 +80,53 @@ pub trait Component: Send + Sync + Any {
     fn as_any(&self) -> &dyn Any;
 }
 
+/// Creates a fresh instance of a component, so it can be restarted
+pub type ComponentFactory = Box<dyn Fn() -> Box<dyn Component> + Send + Sync>;
+
+/// A registered component with the factory it was created by
+struct Registered {
+    component: Arc<dyn Component>,
+    factory: ComponentFactory,
+}
+
+/// How restarting a component went
+#[derive(Debug)]
+pub struct RestartReport {
+    /// Time from shutting the old instance down to the new one being ready
+    pub elapsed: Duration,
+    /// Error from shutting the old instance down, which doesn't stop the restart
+    pub shutdown_error: Option<String>,
+    /// Error from initializing or starting the new instance
+    pub init_error: Option<String>,
+}
+
+impl RestartReport {
+    /// Whether the new instance came up
+    pub fn succeeded(&self) -> bool {
+        self.init_error.is_none()
+    }
+}
+
 /// Manager for all components
 pub struct ComponentManager {
-    components: Vec<Box<dyn Component>>,
+    components: SyncRwLock<Vec<Registered>>,
+    /// Components left out because the config disables them
+    disabled: Vec<&'static str>,
     config: Arc<RwLock<Config>>,
+    bus: EventBus,
+    /// Held while a component restarts, so two restarts can't interleave
+    restart_lock: Mutex<()>,
+    /// Context of the latest gateway session, for tasks that outlive a reconnect
+    session: RwLock<Option<SharedContext>>,
 }
 
 impl fmt::Debug for ComponentManager {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ComponentManager")
-            .field("component_count", &self.components.len())
+            .field("component_count", &self.components().len())
+            .field("disabled", &self.disabled)
             .field("config", &self.config)
+            .field("bus", &self.bus)
             .finish()
     }
 }
@@ -58,21 +135,80 @@ impl ComponentManager {
     /// Create a new component manager
     pub fn new(config: Arc<RwLock<Config>>) -> Self {
         Self {
-            components: Vec::new(),
+            components: SyncRwLock::new(Vec::new()),
+            disabled: Vec::new(),
             config,
+            bus: EventBus::new(),
+            restart_lock: Mutex::new(()),
+            session: RwLock::new(None),
         }
     }
 
+    /// The current instances of the registered components, in registration order
+    fn components(&self) -> Vec<Arc<dyn Component>> {
+        self.components
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|registered| Arc::clone(&registered.component))
+            .collect()
+    }
+
+    /// Get the event bus shared by the components
+    pub fn bus(&self) -> EventBus {
+        self.bus.clone()
+    }
+
     /// Get the configuration
     #[allow(dead_code)]
     pub fn get_config(&self) -> Arc<RwLock<Config>> {
         Arc::clone(&self.config)
     }
 
-    /// Register a component
-    pub fn register<T: Component + 'static>(&mut self, component: T) {
+    /// Register a component created by `factory`, unless the config disables it. The factory
+    /// creates a fresh instance whenever the component is restarted.
+    pub fn register<T, F>(&mut self, factory: F)
+    where
+        T: Component + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let component = factory();
+        // Components are registered at startup, before anything can hold the config for writing
+        let enabled = self
+            .config
+            .try_read()
+            .map_or(true, |config| config.is_component_enabled(component.name()));
+        if !enabled {
+            info!(
+                "Component {} is disabled, not registering it",
+                component.name()
+            );
+            self.disabled.push(component.name());
+            return;
+        }
+
         info!("Registering component: {}", component.name());
-        self.components.push(Box::new(component));
+        self.components
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Registered {
+                component: Arc::new(component),
+                factory: Box::new(move || Box::new(factory())),
+            });
+    }
+
+    /// Check whether a component was left out because the config disables it
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.disabled.contains(&name)
+    }
+
+    /// Every component with whether it is enabled, registered ones first
+    pub fn component_states(&self) -> Vec<(&'static str, bool)> {
+        self.components()
+            .iter()
+            .map(|c| (c.name(), true))
+            .chain(self.disabled.iter().map(|name| (*name, false)))
+            .collect()
     }
 
     /// Initialize all registered components
@@ -82,11 +218,12 @@ impl ComponentManager {
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
     ) -> BotResult<()> {
-        for component in &self.components {
+        SharedContext::attach(&self.session, Arc::new(ctx.clone())).await;
+        for component in self.components() {
             info!("Initializing component: {}", component.name());
 
             if let Err(e) = component
-                .init(ctx, config.clone(), redis_handle.clone())
+                .init(ctx, config.clone(), redis_handle.clone(), self.bus.clone())
                 .await
             {
                 // Log error but continue with other components
@@ -97,11 +234,86 @@ impl ComponentManager {
         Ok(())
     }
 
+    /// Hand every registered component the context of a new gateway session
+    pub async fn attach_all(&self, ctx: &serenity::Context) {
+        SharedContext::attach(&self.session, Arc::new(ctx.clone())).await;
+        for component in self.components() {
+            if let Err(e) = component.attach(ctx).await {
+                tracing::error!("Error attaching component {}: {:?}", component.name(), e);
+            }
+        }
+    }
+
+    /// Context of the latest gateway session, once the components have been initialized
+    pub async fn session(&self) -> Option<SharedContext> {
+        self.session.read().await.clone()
+    }
+
+    /// Create all registered components without a Discord connection, for binaries that only
+    /// use their handles
+    pub async fn create_all(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+    ) -> BotResult<()> {
+        for component in self.components() {
+            info!("Creating component: {}", component.name());
+            component
+                .create(config.clone(), redis_handle.clone(), self.bus.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Start the schedulers and background tasks of all registered components
+    pub async fn start_background_all(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+    ) {
+        for component in self.components() {
+            info!(
+                "Starting background tasks of component: {}",
+                component.name()
+            );
+
+            if let Err(e) = component
+                .start_background(config.clone(), redis_handle.clone())
+                .await
+            {
+                tracing::error!(
+                    "Error starting background tasks of component {}: {:?}",
+                    component.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Stop the schedulers and background tasks of all registered components
+    pub async fn stop_background_all(&self) {
+        for component in self.components() {
+            info!(
+                "Stopping background tasks of component: {}",
+                component.name()
+            );
+
+            if let Err(e) = component.stop_background().await {
+                tracing::error!(
+                    "Error stopping background tasks of component {}: {:?}",
+                    component.name(),
+                    e
+                );
+            }
+        }
+    }
+
     /// Shutdown all components
     pub async fn shutdown_all(&self) -> BotResult<()> {
         info!("Shutting down all components");
 
-        for component in &self.components {
+        for component in self.components() {
             info!("Shutting down component: {}", component.name());
 
             if let Err(e) = component.shutdown().await {
@@ -118,10 +330,77 @@ impl ComponentManager {
     }
 
     /// Get a component by name
-    pub fn get_component_by_name(&self, name: &str) -> Option<&dyn Component> {
-        self.components
-            .iter()
-            .find(|c| c.name() == name)
-            .map(|c| c.as_ref())
+    pub fn get_component_by_name(&self, name: &str) -> Option<Arc<dyn Component>> {
+        self.components().into_iter().find(|c| c.name() == name)
+    }
+
+    /// Restart a component: shut the running instance down, create a new one with its factory
+    /// and attach it to the latest gateway session, if there is one yet. Background tasks are
+    /// started again when `leading`, since only the leader runs them. Returns `None` for a
+    /// component that isn't registered.
+    pub async fn restart(
+        &self,
+        name: &str,
+        redis_handle: RedisActorHandle,
+        leading: bool,
+    ) -> Option<RestartReport> {
+        let _guard = self.restart_lock.lock().await;
+        let old = self.get_component_by_name(name)?;
+        let started = Instant::now();
+        info!("Restarting component: {}", name);
+
+        // A failed shutdown may leave the old instance half-running, but it's replaced anyway
+        let shutdown_error = old.shutdown().await.err().map(|e| {
+            tracing::error!("Error shutting down component {}: {:?}", name, e);
+            e.to_string()
+        });
+
+        let new: Arc<dyn Component> = {
+            let components = self.components.read().unwrap_or_else(|e| e.into_inner());
+            let registered = components.iter().find(|r| r.component.name() == name)?;
+            Arc::from((registered.factory)())
+        };
+        let mut init_error = new
+            .create(self.config.clone(), redis_handle.clone(), self.bus.clone())
+            .await
+            .err();
+        if init_error.is_none() {
+            if let Some(session) = self.session().await {
+                init_error = new.attach(&*session.current().await).await.err();
+            }
+        }
+        if init_error.is_none() && leading {
+            init_error = new
+                .start_background(self.config.clone(), redis_handle)
+                .await
+                .err();
+        }
+
+        // Swap even after a failed init so the next restart shuts the new instance down
+        if let Some(registered) = self
+            .components
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter_mut()
+            .find(|r| r.component.name() == name)
+        {
+            registered.component = new;
+        }
+
+        let report = RestartReport {
+            elapsed: started.elapsed(),
+            shutdown_error,
+            init_error: init_error.map(|e| {
+                tracing::error!("Error restarting component {}: {:?}", name, e);
+                e.to_string()
+            }),
+        };
+        info!(
+            "Restarted component {} in {:?} (ok: {})",
+            name,
+            report.elapsed,
+            report.succeeded()
+        );
+        Some(report)
     }
 }
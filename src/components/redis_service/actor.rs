@@ -1,7 +1,10 @@
+use super::keyspace::Key;
 use crate::components::google_calendar::models::CalendarEvent;
+use crate::components::supervisor::{actor_channel, supervise, SharedReceiver};
 use crate::config::Config;
 use crate::error::{google_calendar_error, BotResult};
-use redis::{aio::MultiplexedConnection, AsyncCommands, Client as RedisClient};
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{AsyncCommands, Client as RedisClient};
 use serde_json::Value;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
@@ -9,15 +12,25 @@ use tracing::info;
 
 // Redis key constants
 pub mod keys {
-    pub const GOOGLE_CALENDAR_EVENTS: &str = "google_calendar_events";
-    pub const GOOGLE_CALENDAR_TOKEN: &str = "google_calendar_token";
+    use super::Key;
+
+    pub const GOOGLE_CALENDAR_EVENTS: Key = Key::fixed("google_calendar_events");
+    /// Window the stored calendar events were fetched over
+    pub const GOOGLE_CALENDAR_EVENTS_WINDOW: Key = Key::fixed("google_calendar_events_window");
+    /// Ids of the new events already announced, scored by when they can be forgotten
+    pub const GOOGLE_CALENDAR_ANNOUNCED_EVENT_IDS: Key =
+        Key::fixed("google_calendar_announced_event_ids");
+    /// Ids of the new events found but not announced yet
+    pub const GOOGLE_CALENDAR_PENDING_EVENT_IDS: Key =
+        Key::fixed("google_calendar_pending_event_ids");
+    pub const GOOGLE_CALENDAR_TOKEN: Key = Key::fixed("google_calendar_token");
 }
 
 /// The Redis actor that processes messages
 pub struct RedisActor {
     config: Arc<RwLock<Config>>,
     client: RedisClient,
-    command_rx: mpsc::Receiver<RedisCommand>,
+    command_rx: SharedReceiver<RedisCommand>,
 }
 
 /// Commands that can be sent to the Redis actor
@@ -27,13 +40,16 @@ pub enum RedisCommand {
     GetToken(mpsc::Sender<BotResult<Option<Value>>>),
     SaveToken(Value, mpsc::Sender<BotResult<()>>),
     RunCommand(redis::Cmd, mpsc::Sender<BotResult<redis::Value>>),
+    /// Run a pipeline, answering with every reply Redis sends for it, transaction framing
+    /// included
+    RunPipeline(redis::Pipeline, mpsc::Sender<BotResult<Vec<redis::Value>>>),
     Shutdown,
 }
 
 /// Handle for communicating with the Redis actor
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct RedisActorHandle {
-    command_tx: mpsc::Sender<RedisCommand>,
+    pub(super) command_tx: mpsc::Sender<RedisCommand>,
 }
 
 impl RedisActorHandle {
@@ -99,8 +115,8 @@ impl RedisActorHandle {
             .ok_or_else(|| google_calendar_error("Response channel closed"))?
     }
 
-    /// Execute a custom Redis command
-    pub async fn run_command<T: redis::FromRedisValue>(&self, cmd: redis::Cmd) -> BotResult<T> {
+    /// Execute a command built by the typed operations in the `kv` module
+    pub(super) async fn query<T: redis::FromRedisValue>(&self, cmd: redis::Cmd) -> BotResult<T> {
         // Create a channel for the result
         let (response_tx, mut response_rx) = mpsc::channel(1);
 
@@ -124,6 +140,22 @@ impl RedisActorHandle {
         }
     }
 
+    /// Execute a pipeline, returning every reply Redis sends for it
+    #[cfg(any(test, feature = "web-interface"))]
+    pub(super) async fn pipeline(&self, pipe: redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+
+        self.command_tx
+            .send(RedisCommand::RunPipeline(pipe, response_tx))
+            .await
+            .map_err(|e| google_calendar_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| google_calendar_error("Response channel closed"))?
+    }
+
     /// Shutdown the actor
     pub async fn shutdown(&self) -> BotResult<()> {
         let _ = self.command_tx.send(RedisCommand::Shutdown).await;
@@ -134,21 +166,38 @@ impl RedisActorHandle {
 impl RedisActor {
     /// Create a new actor and return its handle
     pub fn new(config: Arc<RwLock<Config>>) -> (Self, RedisActorHandle) {
-        let (command_tx, command_rx) = mpsc::channel(32);
+        let (command_tx, command_rx) = actor_channel(32);
+        let handle = RedisActorHandle { command_tx };
 
+        (Self::with_receiver(config, command_rx), handle)
+    }
+
+    /// Create an actor serving an existing command channel, e.g. when restarting after a crash
+    pub fn with_receiver(
+        config: Arc<RwLock<Config>>,
+        command_rx: SharedReceiver<RedisCommand>,
+    ) -> Self {
         // Get the default Redis URL - we'll connect to Redis properly in the async methods
         let redis_url = "redis://127.0.0.1:6379".to_string();
         let redis = RedisClient::open(redis_url).expect("Failed to create Redis client");
 
-        let actor = Self {
+        Self {
             config,
             client: redis,
             command_rx,
-        };
+        }
+    }
 
-        let handle = RedisActorHandle { command_tx };
+    /// Spawn a supervised actor that is restarted if it panics
+    pub fn spawn_supervised(config: Arc<RwLock<Config>>) -> RedisActorHandle {
+        let (command_tx, command_rx) = actor_channel(32);
+
+        supervise("redis", move || {
+            let mut actor = Self::with_receiver(config.clone(), command_rx.clone());
+            async move { actor.run().await }
+        });
 
-        (actor, handle)
+        RedisActorHandle { command_tx }
     }
 
     /// Start the actor's processing loop
@@ -156,7 +205,10 @@ impl RedisActor {
         info!("Redis actor started");
 
         // Process commands
-        while let Some(cmd) = self.command_rx.recv().await {
+        loop {
+            let Some(cmd) = self.command_rx.lock().await.recv().await else {
+                break;
+            };
             match cmd {
                 RedisCommand::SaveEvents(events, response_tx) => {
                     let result = self.save_events_to_redis(events).await;
@@ -178,6 +230,10 @@ impl RedisActor {
                     let result = self.run_command(cmd).await;
                     let _ = response_tx.send(result).await;
                 }
+                RedisCommand::RunPipeline(pipe, response_tx) => {
+                    let result = self.run_pipeline(pipe).await;
+                    let _ = response_tx.send(result).await;
+                }
                 RedisCommand::Shutdown => {
                     info!("Redis actor shutting down");
                     break;
@@ -328,4 +384,15 @@ impl RedisActor {
             .await
             .map_err(|e| google_calendar_error(&format!("Failed to execute Redis command: {e}")))
     }
+
+    /// Execute a pipeline, keeping the replies to MULTI and the queued commands of a transaction
+    async fn run_pipeline(&self, pipe: redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        let mut redis_conn = self.get_redis_connection().await?;
+
+        let framing = if pipe.is_transaction() { 2 } else { 0 };
+        redis_conn
+            .req_packed_commands(&pipe, 0, pipe.len() + framing)
+            .await
+            .map_err(|e| google_calendar_error(&format!("Failed to execute Redis pipeline: {e}")))
+    }
 }
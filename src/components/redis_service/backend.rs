@@ -0,0 +1,71 @@
+use super::{RedisActor, RedisActorHandle};
+use crate::config::Config;
+use crate::error::BotResult;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Where the bot and work_hours keep their data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A Redis server at `REDIS_URL`
+    #[default]
+    Redis,
+    /// A SQLite file at `SQLITE_PATH`, for running locally without Redis. Needs the sqlite
+    /// feature.
+    Sqlite,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "redis" => Ok(StorageBackend::Redis),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(format!("Unknown storage backend: {s}")),
+        }
+    }
+}
+
+/// Start the store the config selects and return the handle fronting it
+pub async fn spawn_store(config: Arc<RwLock<Config>>) -> BotResult<RedisActorHandle> {
+    let (backend, sqlite_path) = {
+        let config = config.read().await;
+        (config.storage_backend, config.sqlite_path.clone())
+    };
+    match backend {
+        // Restart the actor if it crashes
+        StorageBackend::Redis => Ok(RedisActor::spawn_supervised(config)),
+        StorageBackend::Sqlite => open_sqlite(&sqlite_path),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(path: &str) -> BotResult<RedisActorHandle> {
+    info!("Storing data in SQLite at {}", path);
+    RedisActorHandle::sqlite(path)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(path: &str) -> BotResult<RedisActorHandle> {
+    info!("SQLite storage at {} requested", path);
+    Err(crate::error::config_error(
+        "STORAGE_BACKEND=sqlite needs a build with the sqlite feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backends_parse() {
+        assert_eq!(" Redis ".parse(), Ok(StorageBackend::Redis));
+        assert_eq!("sqlite".parse(), Ok(StorageBackend::Sqlite));
+        assert!("postgres".parse::<StorageBackend>().is_err());
+    }
+}
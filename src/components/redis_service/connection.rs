@@ -0,0 +1,124 @@
+//! The Redis actor as a `redis` connection, for the web app's `redis` crate code that should
+//! share the bot's store instead of opening its own. Kept inside the crate, so commands from
+//! outside still go through the typed methods.
+
+#[cfg(any(test, feature = "web-interface"))]
+use super::RedisActorHandle;
+
+/// Replies Redis sends for a pipeline whose commands answered `results`: a transaction gets
+/// OK to MULTI, QUEUED to each command and the results in the reply to EXEC
+#[cfg(any(test, feature = "test-util", feature = "sqlite"))]
+pub(super) fn pipeline_replies(
+    pipe: &redis::Pipeline,
+    results: Vec<redis::Value>,
+) -> Vec<redis::Value> {
+    if !pipe.is_transaction() {
+        return results;
+    }
+    std::iter::once(redis::Value::Okay)
+        .chain((0..results.len()).map(|_| redis::Value::SimpleString("QUEUED".to_string())))
+        .chain([redis::Value::Array(results)])
+        .collect()
+}
+
+#[cfg(any(test, feature = "web-interface"))]
+fn actor_error(detail: &str) -> redis::RedisError {
+    redis::RedisError::from((
+        redis::ErrorKind::IoError,
+        "Redis actor error",
+        detail.to_string(),
+    ))
+}
+
+/// A handle that takes raw commands, for the web app's store
+#[cfg(any(test, feature = "web-interface"))]
+#[derive(Clone)]
+pub(crate) struct RawConnection(RedisActorHandle);
+
+#[cfg(any(test, feature = "web-interface"))]
+impl RedisActorHandle {
+    /// This handle as a `redis` connection
+    pub(crate) fn raw_connection(&self) -> RawConnection {
+        RawConnection(self.clone())
+    }
+}
+
+#[cfg(any(test, feature = "web-interface"))]
+impl redis::aio::ConnectionLike for RawConnection {
+    fn req_packed_command<'a>(
+        &'a mut self,
+        cmd: &'a redis::Cmd,
+    ) -> redis::RedisFuture<'a, redis::Value> {
+        Box::pin(async move {
+            self.0
+                .query(cmd.clone())
+                .await
+                .map_err(|e| actor_error(&e.to_string()))
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipe: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+        Box::pin(async move {
+            let replies = self
+                .0
+                .pipeline(pipe.clone())
+                .await
+                .map_err(|e| actor_error(&e.to_string()))?;
+            Ok(replies.into_iter().skip(offset).take(count).collect())
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handle_answers_commands_and_transactions() {
+        let mut conn = RedisActorHandle::fake().raw_connection();
+
+        let (): () = redis::cmd("SET")
+            .arg("name")
+            .arg("Anna")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        let name: String = redis::cmd("GET")
+            .arg("name")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(name, "Anna");
+
+        let (member, members): (bool, Vec<String>) = redis::pipe()
+            .atomic()
+            .sadd("employees", "anna")
+            .ignore()
+            .sadd("employees", "pekka")
+            .ignore()
+            .sismember("employees", "anna")
+            .smembers("employees")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert!(member);
+        assert_eq!(members.len(), 2);
+
+        let (name, missing): (String, Option<String>) = redis::pipe()
+            .get("name")
+            .get("missing")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!((name.as_str(), missing), ("Anna", None));
+    }
+}
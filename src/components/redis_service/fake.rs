@@ -0,0 +1,967 @@
+//! In-memory Redis substitute for tests.
+//!
+//! It serves the same [`RedisActorHandle`] interface as the real actor and understands the
+//! subset of commands the crate uses, including key expiry driven by a [`FakeClock`]. The
+//! SQLite backend runs its commands through the same interpreter.
+
+use super::actor::{keys, RedisCommand};
+use super::connection::pipeline_replies;
+use super::kv::{DEL_IF_EQ_SCRIPT, EXPIRE_IF_EQ_SCRIPT, ZADD_WITHIN_LIMITS_SCRIPT};
+use super::RedisActorHandle;
+use crate::components::google_calendar::models::CalendarEvent;
+use crate::error::{other_error, BotResult};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Clock deciding when keys expire, moved forward by hand
+#[derive(Debug, Clone, Default)]
+pub struct FakeClock {
+    now_ms: Arc<AtomicU64>,
+}
+
+impl FakeClock {
+    /// Create a clock at time zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a clock at a time in milliseconds
+    pub fn at(now_ms: u64) -> Self {
+        Self {
+            now_ms: Arc::new(AtomicU64::new(now_ms)),
+        }
+    }
+
+    /// Current time in milliseconds
+    pub fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        self.now_ms
+            .fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+/// A value stored under a key
+#[derive(Debug, Clone)]
+pub(super) enum Entry {
+    String(Vec<u8>),
+    Set(BTreeSet<Vec<u8>>),
+    Hash(BTreeMap<Vec<u8>, Vec<u8>>),
+    List(VecDeque<Vec<u8>>),
+    SortedSet(Vec<(f64, Vec<u8>)>),
+}
+
+/// Keys, their values and expiry times
+#[derive(Debug, Default)]
+pub struct FakeRedis {
+    entries: HashMap<Vec<u8>, Entry>,
+    expires_at_ms: HashMap<Vec<u8>, u64>,
+    clock: FakeClock,
+}
+
+impl RedisActorHandle {
+    /// Handle served by a fresh [`FakeRedis`] whose keys never expire on their own
+    pub fn fake() -> Self {
+        Self::fake_with_clock(FakeClock::new())
+    }
+
+    /// Handle served by a fresh [`FakeRedis`] expiring keys according to `clock`
+    pub fn fake_with_clock(clock: FakeClock) -> Self {
+        Self::fake_serving(FakeRedis::with_clock(clock))
+    }
+
+    /// Handle served by `redis`, e.g. one already holding what another writer stored
+    pub fn fake_serving(mut redis: FakeRedis) -> Self {
+        let (command_tx, mut command_rx) = mpsc::channel(32);
+
+        tokio::spawn(async move {
+            while let Some(command) = command_rx.recv().await {
+                if !answer(&mut redis, command) {
+                    break;
+                }
+            }
+        });
+
+        Self { command_tx }
+    }
+}
+
+/// A store answering the actor's commands without a Redis server
+pub(super) trait CommandStore {
+    fn execute(&mut self, cmd: &redis::Cmd) -> BotResult<redis::Value>;
+    fn execute_pipeline(&mut self, pipe: &redis::Pipeline) -> BotResult<Vec<redis::Value>>;
+    fn save_events(&mut self, events: &[CalendarEvent]) -> BotResult<()>;
+    fn get_events(&mut self) -> BotResult<Vec<CalendarEvent>>;
+    fn get_token(&mut self) -> BotResult<Option<serde_json::Value>>;
+    fn save_token(&mut self, token: &serde_json::Value) -> BotResult<()>;
+}
+
+impl CommandStore for FakeRedis {
+    fn execute(&mut self, cmd: &redis::Cmd) -> BotResult<redis::Value> {
+        FakeRedis::execute(self, cmd)
+    }
+
+    fn execute_pipeline(&mut self, pipe: &redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        FakeRedis::execute_pipeline(self, pipe)
+    }
+
+    fn save_events(&mut self, events: &[CalendarEvent]) -> BotResult<()> {
+        FakeRedis::save_events(self, events)
+    }
+
+    fn get_events(&mut self) -> BotResult<Vec<CalendarEvent>> {
+        FakeRedis::get_events(self)
+    }
+
+    fn get_token(&mut self) -> BotResult<Option<serde_json::Value>> {
+        FakeRedis::get_token(self)
+    }
+
+    fn save_token(&mut self, token: &serde_json::Value) -> BotResult<()> {
+        FakeRedis::save_token(self, token)
+    }
+}
+
+/// Answer one of the actor's commands, returning false once asked to shut down. The reply
+/// channels are fresh ones with room for the reply, so sending never waits.
+pub(super) fn answer(store: &mut impl CommandStore, command: RedisCommand) -> bool {
+    match command {
+        RedisCommand::RunCommand(cmd, response_tx) => {
+            let _ = response_tx.try_send(store.execute(&cmd));
+        }
+        RedisCommand::RunPipeline(pipe, response_tx) => {
+            let replies = store
+                .execute_pipeline(&pipe)
+                .map(|results| pipeline_replies(&pipe, results));
+            let _ = response_tx.try_send(replies);
+        }
+        RedisCommand::SaveEvents(events, response_tx) => {
+            let _ = response_tx.try_send(store.save_events(&events));
+        }
+        RedisCommand::GetEvents(response_tx) => {
+            let _ = response_tx.try_send(store.get_events());
+        }
+        RedisCommand::GetToken(response_tx) => {
+            let _ = response_tx.try_send(store.get_token());
+        }
+        RedisCommand::SaveToken(token, response_tx) => {
+            let _ = response_tx.try_send(store.save_token(&token));
+        }
+        RedisCommand::Shutdown => return false,
+    }
+    true
+}
+
+fn wrong_type() -> crate::error::Error {
+    other_error("WRONGTYPE Operation against a key holding the wrong kind of value")
+}
+
+fn bulk(bytes: &[u8]) -> redis::Value {
+    redis::Value::BulkString(bytes.to_vec())
+}
+
+fn parse<T: std::str::FromStr>(arg: Option<&Vec<u8>>) -> BotResult<T> {
+    arg.and_then(|arg| std::str::from_utf8(arg).ok()?.parse().ok())
+        .ok_or_else(|| other_error("ERR value is not a valid number"))
+}
+
+/// Parse a sorted set score bound, ignoring exclusive bounds
+fn parse_bound(arg: Option<&Vec<u8>>) -> BotResult<f64> {
+    match arg.map(Vec::as_slice) {
+        Some(b"-inf") => Ok(f64::NEG_INFINITY),
+        Some(b"+inf") | Some(b"inf") => Ok(f64::INFINITY),
+        Some([b'(', rest @ ..]) => parse(Some(&rest.to_vec())),
+        _ => parse(arg),
+    }
+}
+
+/// Match a key against a SCAN pattern, supporting a trailing `*` only
+fn matches_pattern(key: &[u8], pattern: &[u8]) -> bool {
+    match pattern.strip_suffix(b"*") {
+        Some(prefix) => key.starts_with(prefix),
+        None => key == pattern,
+    }
+}
+
+/// Resolve a Redis start/stop index pair, which may count from the end, to a range
+fn index_range(len: usize, start: isize, stop: isize) -> std::ops::Range<usize> {
+    let resolve = |index: isize| {
+        if index < 0 {
+            (len as isize + index).max(0) as usize
+        } else {
+            index as usize
+        }
+    };
+    let (start, stop) = (resolve(start), resolve(stop).saturating_add(1).min(len));
+    if start >= stop {
+        0..0
+    } else {
+        start..stop
+    }
+}
+
+impl FakeRedis {
+    /// Create an empty store expiring keys according to `clock`
+    pub fn with_clock(clock: FakeClock) -> Self {
+        Self {
+            clock,
+            ..Default::default()
+        }
+    }
+
+    /// Put a key loaded from elsewhere in place, with its expiry time in milliseconds
+    #[cfg(feature = "sqlite")]
+    pub(super) fn load_entry(&mut self, key: Vec<u8>, entry: Entry, expires_at_ms: Option<u64>) {
+        if let Some(at) = expires_at_ms {
+            self.expires_at_ms.insert(key.clone(), at);
+        }
+        self.entries.insert(key, entry);
+    }
+
+    /// Take a key's value and expiry time out, or `None` if it doesn't exist or has expired
+    #[cfg(feature = "sqlite")]
+    pub(super) fn take_entry(&mut self, key: &[u8]) -> Option<(Entry, Option<u64>)> {
+        self.purge(key);
+        let expires_at_ms = self.expires_at_ms.remove(key);
+        self.entries.remove(key).map(|entry| (entry, expires_at_ms))
+    }
+
+    /// Drop a key if it has expired
+    fn purge(&mut self, key: &[u8]) {
+        if self
+            .expires_at_ms
+            .get(key)
+            .is_some_and(|at| *at <= self.clock.now_ms())
+        {
+            self.expires_at_ms.remove(key);
+            self.entries.remove(key);
+        }
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<&mut Entry> {
+        self.purge(key);
+        self.entries.get_mut(key)
+    }
+
+    /// Get a key's value, creating it with `empty` if it doesn't exist
+    fn get_or_insert(&mut self, key: &[u8], empty: Entry) -> &mut Entry {
+        self.purge(key);
+        self.entries.entry(key.to_vec()).or_insert(empty)
+    }
+
+    /// Store a value, clearing any expiry
+    fn insert(&mut self, key: Vec<u8>, entry: Entry) {
+        self.expires_at_ms.remove(&key);
+        self.entries.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &[u8]) -> bool {
+        self.purge(key);
+        self.expires_at_ms.remove(key);
+        self.entries.remove(key).is_some()
+    }
+
+    /// Drop collections left empty, as Redis does
+    fn remove_if_empty(&mut self, key: &[u8]) {
+        let empty = match self.entries.get(key) {
+            Some(Entry::Set(set)) => set.is_empty(),
+            Some(Entry::Hash(hash)) => hash.is_empty(),
+            Some(Entry::List(list)) => list.is_empty(),
+            Some(Entry::SortedSet(set)) => set.is_empty(),
+            _ => false,
+        };
+        if empty {
+            self.remove(key);
+        }
+    }
+
+    fn expire_in(&mut self, key: &[u8], ttl: Duration) {
+        self.expires_at_ms
+            .insert(key.to_vec(), self.clock.now_ms() + ttl.as_millis() as u64);
+    }
+
+    /// Execute every command of a pipeline in order, returning their replies. Commands run
+    /// one after another either way, so a transaction needs no extra handling.
+    pub fn execute_pipeline(&mut self, pipe: &redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        pipe.cmd_iter().map(|cmd| self.execute(cmd)).collect()
+    }
+
+    /// Run one of the scripts the handle sends, as Redis would run its Lua. Other scripts are
+    /// refused, since the fake can't run Lua.
+    fn eval(&mut self, args: &[Vec<u8>]) -> BotResult<redis::Value> {
+        let [script, numkeys, rest @ ..] = args else {
+            return Err(other_error("ERR wrong number of arguments for 'eval'"));
+        };
+        let numkeys: usize = parse(Some(numkeys))?;
+        if numkeys > rest.len() {
+            return Err(other_error(
+                "ERR Number of keys can't be greater than number of args",
+            ));
+        }
+        let (keys, argv) = rest.split_at(numkeys);
+        let mut cmd = match std::str::from_utf8(script).unwrap_or_default() {
+            EXPIRE_IF_EQ_SCRIPT => redis::cmd("EXPIRE"),
+            DEL_IF_EQ_SCRIPT => redis::cmd("DEL"),
+            ZADD_WITHIN_LIMITS_SCRIPT => return self.zadd_within_limits(keys, argv),
+            _ => return Err(other_error("NOSCRIPT the fake can't run this script")),
+        };
+        let ([key], [expected, argv @ ..]) = (keys, argv) else {
+            return Err(other_error("ERR scripts take a single key and a value"));
+        };
+        if !matches!(self.get(key), Some(Entry::String(value)) if value == expected) {
+            return Ok(redis::Value::Int(0));
+        }
+        cmd.arg(key).arg(argv);
+        self.execute(&cmd)
+    }
+
+    /// Run [`ZADD_WITHIN_LIMITS_SCRIPT`] over its sorted sets
+    fn zadd_within_limits(
+        &mut self,
+        keys: &[Vec<u8>],
+        argv: &[Vec<u8>],
+    ) -> BotResult<redis::Value> {
+        let [now_ms, member, limits @ ..] = argv else {
+            return Err(other_error("ERR wrong number of arguments for the script"));
+        };
+        if limits.len() != keys.len() * 2 {
+            return Err(other_error("ERR every key needs a window and a limit"));
+        }
+        let now_ms: i64 = parse(Some(now_ms))?;
+        for (key, limit) in keys.iter().zip(limits.chunks(2)) {
+            let window_ms = parse::<i64>(limit.first())? * 1000;
+            let max: usize = parse(limit.get(1))?;
+            let scores: Vec<f64> = match self.get(key) {
+                None => Vec::new(),
+                Some(Entry::SortedSet(set)) => {
+                    set.retain(|(score, _)| *score > (now_ms - window_ms) as f64);
+                    set.iter().map(|(score, _)| *score).collect()
+                }
+                Some(_) => return Err(wrong_type()),
+            };
+            self.remove_if_empty(key);
+            if scores.len() >= max {
+                let freeing = scores
+                    .get(scores.len() - max)
+                    .map_or(now_ms, |at| *at as i64);
+                return Ok(redis::Value::Int((freeing + window_ms - now_ms).max(1)));
+            }
+        }
+        for (key, limit) in keys.iter().zip(limits.chunks(2)) {
+            self.execute(redis::cmd("ZADD").arg(key).arg(now_ms).arg(member))?;
+            self.execute(redis::cmd("EXPIRE").arg(key).arg(&limit[0]))?;
+        }
+        Ok(redis::Value::Int(0))
+    }
+
+    /// Execute a command, returning the reply Redis would send
+    pub fn execute(&mut self, cmd: &redis::Cmd) -> BotResult<redis::Value> {
+        let args: Vec<Vec<u8>> = cmd
+            .args_iter()
+            .filter_map(|arg| match arg {
+                redis::Arg::Simple(bytes) => Some(bytes.to_vec()),
+                redis::Arg::Cursor => None,
+            })
+            .collect();
+        let name = String::from_utf8_lossy(args.first().map(Vec::as_slice).unwrap_or_default())
+            .to_uppercase();
+        if name == "PING" {
+            return Ok(redis::Value::SimpleString("PONG".to_string()));
+        }
+        if name == "EVAL" {
+            return self.eval(&args[1..]);
+        }
+        let Some(key) = args.get(1).cloned() else {
+            return Err(other_error(&format!("ERR {name} without a key")));
+        };
+        let rest = &args[2..];
+
+        match name.as_str() {
+            "GET" => match self.get(&key) {
+                None => Ok(redis::Value::Nil),
+                Some(Entry::String(value)) => Ok(bulk(value)),
+                Some(_) => Err(wrong_type()),
+            },
+            "MGET" => Ok(redis::Value::Array(
+                args[1..]
+                    .iter()
+                    .map(|key| match self.get(key) {
+                        Some(Entry::String(value)) => bulk(value),
+                        _ => redis::Value::Nil,
+                    })
+                    .collect(),
+            )),
+            "SET" => self.set(key, rest),
+            "SETNX" => {
+                if self.get(&key).is_some() {
+                    return Ok(redis::Value::Int(0));
+                }
+                let value = rest.first().cloned().unwrap_or_default();
+                self.insert(key, Entry::String(value));
+                Ok(redis::Value::Int(1))
+            }
+            "SETEX" => {
+                let secs: u64 = parse(rest.first())?;
+                let value = rest.get(1).cloned().unwrap_or_default();
+                self.insert(key.clone(), Entry::String(value));
+                self.expire_in(&key, Duration::from_secs(secs));
+                Ok(redis::Value::Okay)
+            }
+            "INCR" | "INCRBY" => {
+                let by: i64 = if name == "INCRBY" {
+                    parse(rest.first())?
+                } else {
+                    1
+                };
+                let current: i64 = match self.get(&key) {
+                    None => 0,
+                    Some(Entry::String(value)) => parse(Some(&value.clone()))
+                        .map_err(|_| other_error("ERR value is not an integer or out of range"))?,
+                    Some(_) => return Err(wrong_type()),
+                };
+                let next = current + by;
+                // Unlike SET, INCR keeps the expiry
+                self.entries
+                    .insert(key, Entry::String(next.to_string().into_bytes()));
+                Ok(redis::Value::Int(next))
+            }
+            "EXPIRE" => {
+                let secs: u64 = parse(rest.first())?;
+                if self.get(&key).is_none() {
+                    return Ok(redis::Value::Int(0));
+                }
+                self.expire_in(&key, Duration::from_secs(secs));
+                Ok(redis::Value::Int(1))
+            }
+            "TTL" => {
+                if self.get(&key).is_none() {
+                    return Ok(redis::Value::Int(-2));
+                }
+                Ok(redis::Value::Int(
+                    self.expires_at_ms.get(&key).map_or(-1, |at| {
+                        at.saturating_sub(self.clock.now_ms()).div_ceil(1000) as i64
+                    }),
+                ))
+            }
+            "EXISTS" => Ok(redis::Value::Int(
+                args[1..]
+                    .iter()
+                    .filter(|key| self.get(key).is_some())
+                    .count() as i64,
+            )),
+            "DEL" => Ok(redis::Value::Int(
+                args[1..].iter().filter(|key| self.remove(key)).count() as i64,
+            )),
+            "SADD" => match self.get_or_insert(&key, Entry::Set(BTreeSet::new())) {
+                Entry::Set(set) => Ok(redis::Value::Int(
+                    rest.iter()
+                        .filter(|member| set.insert(member.to_vec()))
+                        .count() as i64,
+                )),
+                _ => Err(wrong_type()),
+            },
+            "SREM" => {
+                let removed = match self.get(&key) {
+                    None => 0,
+                    Some(Entry::Set(set)) => {
+                        rest.iter().filter(|member| set.remove(*member)).count()
+                    }
+                    Some(_) => return Err(wrong_type()),
+                };
+                self.remove_if_empty(&key);
+                Ok(redis::Value::Int(removed as i64))
+            }
+            "SMEMBERS" => match self.get(&key) {
+                None => Ok(redis::Value::Array(Vec::new())),
+                Some(Entry::Set(set)) => Ok(redis::Value::Array(
+                    set.iter().map(|member| bulk(member)).collect(),
+                )),
+                Some(_) => Err(wrong_type()),
+            },
+            "SISMEMBER" => match self.get(&key) {
+                None => Ok(redis::Value::Int(0)),
+                Some(Entry::Set(set)) => Ok(redis::Value::Int(
+                    rest.first().is_some_and(|member| set.contains(member)) as i64,
+                )),
+                Some(_) => Err(wrong_type()),
+            },
+            "HGET" => match self.get(&key) {
+                None => Ok(redis::Value::Nil),
+                Some(Entry::Hash(hash)) => Ok(rest
+                    .first()
+                    .and_then(|field| hash.get(field))
+                    .map_or(redis::Value::Nil, |value| bulk(value))),
+                Some(_) => Err(wrong_type()),
+            },
+            "HSET" => match self.get_or_insert(&key, Entry::Hash(BTreeMap::new())) {
+                Entry::Hash(hash) => Ok(redis::Value::Int(
+                    rest.chunks_exact(2)
+                        .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+                        .count() as i64,
+                )),
+                _ => Err(wrong_type()),
+            },
+            "HINCRBY" => {
+                let by: i64 = parse(rest.get(1))?;
+                match self.get_or_insert(&key, Entry::Hash(BTreeMap::new())) {
+                    Entry::Hash(hash) => {
+                        let field = rest.first().cloned().unwrap_or_default();
+                        let current: i64 = match hash.get(&field) {
+                            None => 0,
+                            Some(value) => parse(Some(value))
+                                .map_err(|_| other_error("ERR hash value is not an integer"))?,
+                        };
+                        let next = current + by;
+                        hash.insert(field, next.to_string().into_bytes());
+                        Ok(redis::Value::Int(next))
+                    }
+                    _ => Err(wrong_type()),
+                }
+            }
+            "HDEL" => {
+                let removed = match self.get(&key) {
+                    None => 0,
+                    Some(Entry::Hash(hash)) => rest
+                        .iter()
+                        .filter(|field| hash.remove(*field).is_some())
+                        .count(),
+                    Some(_) => return Err(wrong_type()),
+                };
+                self.remove_if_empty(&key);
+                Ok(redis::Value::Int(removed as i64))
+            }
+            "HVALS" | "HGETALL" => match self.get(&key) {
+                None => Ok(redis::Value::Array(Vec::new())),
+                Some(Entry::Hash(hash)) => Ok(redis::Value::Array(
+                    hash.iter()
+                        .flat_map(|(field, value)| {
+                            (name == "HGETALL")
+                                .then(|| bulk(field))
+                                .into_iter()
+                                .chain([bulk(value)])
+                        })
+                        .collect(),
+                )),
+                Some(_) => Err(wrong_type()),
+            },
+            "LPUSH" => match self.get_or_insert(&key, Entry::List(VecDeque::new())) {
+                Entry::List(list) => {
+                    for value in rest {
+                        list.push_front(value.clone());
+                    }
+                    Ok(redis::Value::Int(list.len() as i64))
+                }
+                _ => Err(wrong_type()),
+            },
+            "LTRIM" => {
+                let (start, stop): (isize, isize) = (parse(rest.first())?, parse(rest.get(1))?);
+                match self.get(&key) {
+                    None => {}
+                    Some(Entry::List(list)) => {
+                        let range = index_range(list.len(), start, stop);
+                        *list = list.drain(range).collect();
+                    }
+                    Some(_) => return Err(wrong_type()),
+                }
+                self.remove_if_empty(&key);
+                Ok(redis::Value::Okay)
+            }
+            "LRANGE" => {
+                let (start, stop): (isize, isize) = (parse(rest.first())?, parse(rest.get(1))?);
+                match self.get(&key) {
+                    None => Ok(redis::Value::Array(Vec::new())),
+                    Some(Entry::List(list)) => {
+                        let range = index_range(list.len(), start, stop);
+                        Ok(redis::Value::Array(
+                            list.range(range).map(|value| bulk(value)).collect(),
+                        ))
+                    }
+                    Some(_) => Err(wrong_type()),
+                }
+            }
+            "ZADD" => {
+                if rest.is_empty() || !rest.len().is_multiple_of(2) {
+                    return Err(other_error("ERR ZADD without a member"));
+                }
+                let members = rest
+                    .chunks(2)
+                    .map(|pair| Ok((parse::<f64>(pair.first())?, pair[1].clone())))
+                    .collect::<BotResult<Vec<_>>>()?;
+                match self.get_or_insert(&key, Entry::SortedSet(Vec::new())) {
+                    Entry::SortedSet(set) => {
+                        let mut added = 0;
+                        for (score, member) in members {
+                            match set.iter_mut().find(|(_, m)| *m == member) {
+                                Some(existing) => existing.0 = score,
+                                None => {
+                                    set.push((score, member));
+                                    added += 1;
+                                }
+                            }
+                        }
+                        set.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+                        Ok(redis::Value::Int(added))
+                    }
+                    _ => Err(wrong_type()),
+                }
+            }
+            "ZRANGE" => {
+                let (start, stop): (isize, isize) = (parse(rest.first())?, parse(rest.get(1))?);
+                let with_scores = rest
+                    .iter()
+                    .any(|arg| arg.eq_ignore_ascii_case(b"WITHSCORES"));
+                match self.get(&key) {
+                    None => Ok(redis::Value::Array(Vec::new())),
+                    Some(Entry::SortedSet(set)) => {
+                        let range = index_range(set.len(), start, stop);
+                        Ok(redis::Value::Array(
+                            set[range]
+                                .iter()
+                                .flat_map(|(score, member)| {
+                                    [
+                                        Some(bulk(member)),
+                                        with_scores.then(|| bulk(score.to_string().as_bytes())),
+                                    ]
+                                    .into_iter()
+                                    .flatten()
+                                })
+                                .collect(),
+                        ))
+                    }
+                    Some(_) => Err(wrong_type()),
+                }
+            }
+            "ZREMRANGEBYSCORE" => {
+                let (min, max) = (parse_bound(rest.first())?, parse_bound(rest.get(1))?);
+                let removed = match self.get(&key) {
+                    None => 0,
+                    Some(Entry::SortedSet(set)) => {
+                        let before = set.len();
+                        set.retain(|(score, _)| *score < min || *score > max);
+                        before - set.len()
+                    }
+                    Some(_) => return Err(wrong_type()),
+                };
+                self.remove_if_empty(&key);
+                Ok(redis::Value::Int(removed as i64))
+            }
+            "SCAN" => {
+                let pattern = rest
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(b"MATCH"))
+                    .and_then(|at| rest.get(at + 1))
+                    .cloned()
+                    .unwrap_or_else(|| b"*".to_vec());
+                let mut keys: Vec<Vec<u8>> = self
+                    .entries
+                    .keys()
+                    .filter(|key| matches_pattern(key, &pattern))
+                    .cloned()
+                    .collect();
+                keys.retain(|key| self.get(key).is_some());
+                keys.sort();
+                // Everything fits in one page, so the cursor is done right away
+                Ok(redis::Value::Array(vec![
+                    bulk(b"0"),
+                    redis::Value::Array(keys.iter().map(|key| bulk(key)).collect()),
+                ]))
+            }
+            _ => Err(other_error(&format!("ERR unsupported command {name}"))),
+        }
+    }
+
+    /// SET with the NX, XX, EX, PX and KEEPTTL options
+    fn set(&mut self, key: Vec<u8>, rest: &[Vec<u8>]) -> BotResult<redis::Value> {
+        let value = rest.first().cloned().unwrap_or_default();
+        let (mut nx, mut xx, mut keep_ttl, mut ttl) = (false, false, false, None);
+        let mut options = rest.iter().skip(1);
+        while let Some(option) = options.next() {
+            match option.to_ascii_uppercase().as_slice() {
+                b"NX" => nx = true,
+                b"XX" => xx = true,
+                b"KEEPTTL" => keep_ttl = true,
+                b"EX" => ttl = Some(Duration::from_secs(parse(options.next())?)),
+                b"PX" => ttl = Some(Duration::from_millis(parse(options.next())?)),
+                _ => return Err(other_error("ERR syntax error")),
+            }
+        }
+
+        let exists = self.get(&key).is_some();
+        if (nx && exists) || (xx && !exists) {
+            return Ok(redis::Value::Nil);
+        }
+        let expiry = self.expires_at_ms.get(&key).copied();
+        self.insert(key.clone(), Entry::String(value));
+        match (ttl, expiry) {
+            (Some(ttl), _) => self.expire_in(&key, ttl),
+            (None, Some(expiry)) if keep_ttl => {
+                self.expires_at_ms.insert(key, expiry);
+            }
+            _ => {}
+        }
+        Ok(redis::Value::Okay)
+    }
+
+    fn save_events(&mut self, events: &[CalendarEvent]) -> BotResult<()> {
+        let json = serde_json::to_string(events)
+            .map_err(|e| other_error(&format!("Failed to serialize events: {e}")))?;
+        let key = keys::GOOGLE_CALENDAR_EVENTS.as_str().as_bytes().to_vec();
+        self.insert(key, Entry::String(json.into_bytes()));
+        Ok(())
+    }
+
+    fn get_events(&mut self) -> BotResult<Vec<CalendarEvent>> {
+        match self.get(keys::GOOGLE_CALENDAR_EVENTS.as_str().as_bytes()) {
+            Some(Entry::String(json)) => serde_json::from_slice(json)
+                .map_err(|e| other_error(&format!("Failed to deserialize events: {e}"))),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn save_token(&mut self, token: &serde_json::Value) -> BotResult<()> {
+        let key = keys::GOOGLE_CALENDAR_TOKEN.as_str().as_bytes().to_vec();
+        self.insert(key, Entry::String(token.to_string().into_bytes()));
+        Ok(())
+    }
+
+    fn get_token(&mut self) -> BotResult<Option<serde_json::Value>> {
+        match self.get(keys::GOOGLE_CALENDAR_TOKEN.as_str().as_bytes()) {
+            Some(Entry::String(json)) => serde_json::from_slice(json)
+                .map(Some)
+                .map_err(|e| other_error(&format!("Failed to deserialize token: {e}"))),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(redis: &mut FakeRedis, args: &[&str]) -> redis::Value {
+        let mut cmd = redis::cmd(args[0]);
+        for arg in &args[1..] {
+            cmd.arg(*arg);
+        }
+        redis.execute(&cmd).unwrap()
+    }
+
+    fn strings(value: redis::Value) -> Vec<String> {
+        redis::FromRedisValue::from_redis_value(&value).unwrap()
+    }
+
+    #[test]
+    fn test_keys_expire_with_the_clock() {
+        let clock = FakeClock::new();
+        let mut redis = FakeRedis::with_clock(clock.clone());
+
+        assert_eq!(
+            run(&mut redis, &["SET", "claim", "1", "NX", "EX", "60"]),
+            redis::Value::Okay
+        );
+        assert_eq!(
+            run(&mut redis, &["SET", "claim", "2", "NX", "EX", "60"]),
+            redis::Value::Nil
+        );
+        assert_eq!(
+            run(&mut redis, &["SETNX", "claim", "3"]),
+            redis::Value::Int(0)
+        );
+
+        // KEEPTTL keeps the original deadline
+        clock.advance(Duration::from_secs(30));
+        run(&mut redis, &["SET", "claim", "4", "KEEPTTL"]);
+        assert_eq!(run(&mut redis, &["TTL", "claim"]), redis::Value::Int(30));
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(run(&mut redis, &["GET", "claim"]), bulk(b"4"));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(run(&mut redis, &["EXISTS", "claim"]), redis::Value::Int(0));
+        assert_eq!(run(&mut redis, &["TTL", "claim"]), redis::Value::Int(-2));
+        assert_eq!(
+            run(&mut redis, &["SETNX", "claim", "5"]),
+            redis::Value::Int(1)
+        );
+        assert_eq!(run(&mut redis, &["TTL", "claim"]), redis::Value::Int(-1));
+
+        // INCR counts from zero and keeps the expiry
+        assert_eq!(run(&mut redis, &["INCR", "calls"]), redis::Value::Int(1));
+        run(&mut redis, &["EXPIRE", "calls", "60"]);
+        assert_eq!(run(&mut redis, &["INCR", "calls"]), redis::Value::Int(2));
+        assert_eq!(run(&mut redis, &["TTL", "calls"]), redis::Value::Int(60));
+
+        // Plain SET clears an expiry set with EXPIRE
+        run(&mut redis, &["SADD", "dates", "2025-01-06"]);
+        assert_eq!(
+            run(&mut redis, &["EXPIRE", "dates", "10"]),
+            redis::Value::Int(1)
+        );
+        assert_eq!(
+            run(&mut redis, &["EXPIRE", "missing", "10"]),
+            redis::Value::Int(0)
+        );
+        clock.advance(Duration::from_secs(10));
+        assert!(strings(run(&mut redis, &["SMEMBERS", "dates"])).is_empty());
+        assert_eq!(run(&mut redis, &["GET", "claim"]), bulk(b"5"));
+    }
+
+    #[test]
+    fn test_scripts_compare_before_acting() {
+        let mut redis = FakeRedis::default();
+        run(&mut redis, &["SET", "lease", "a"]);
+
+        let expire = |holder| ["EVAL", EXPIRE_IF_EQ_SCRIPT, "1", "lease", holder, "30"];
+        assert_eq!(run(&mut redis, &expire("b")), redis::Value::Int(0));
+        assert_eq!(run(&mut redis, &["TTL", "lease"]), redis::Value::Int(-1));
+        assert_eq!(run(&mut redis, &expire("a")), redis::Value::Int(1));
+        assert_eq!(run(&mut redis, &["TTL", "lease"]), redis::Value::Int(30));
+
+        assert_eq!(
+            run(&mut redis, &["EVAL", DEL_IF_EQ_SCRIPT, "1", "lease", "b"]),
+            redis::Value::Int(0)
+        );
+        assert_eq!(
+            run(&mut redis, &["EVAL", DEL_IF_EQ_SCRIPT, "1", "lease", "a"]),
+            redis::Value::Int(1)
+        );
+
+        // Scripts the fake doesn't know are refused, not skipped
+        let unknown = redis::cmd("EVAL")
+            .arg("return 1")
+            .arg(1)
+            .arg("lease")
+            .arg("a")
+            .to_owned();
+        assert!(redis.execute(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_windowed_zadd_needs_room_in_every_set() {
+        let mut redis = FakeRedis::default();
+        let add = |now| {
+            [
+                "EVAL",
+                ZADD_WITHIN_LIMITS_SCRIPT,
+                "2",
+                "user",
+                "guild",
+                now,
+                now,
+                "60",
+                "2",
+                "60",
+                "1",
+            ]
+        };
+
+        assert_eq!(run(&mut redis, &add("1000")), redis::Value::Int(0));
+        assert_eq!(run(&mut redis, &["TTL", "user"]), redis::Value::Int(60));
+        // The guild set is full until its call slides out, and the user set stays untouched
+        assert_eq!(run(&mut redis, &add("2000")), redis::Value::Int(59_000));
+        assert_eq!(
+            strings(run(&mut redis, &["ZRANGE", "user", "0", "-1"])),
+            ["1000"]
+        );
+        assert_eq!(run(&mut redis, &add("61000")), redis::Value::Int(0));
+        assert_eq!(
+            strings(run(&mut redis, &["ZRANGE", "user", "0", "-1"])),
+            ["61000"]
+        );
+    }
+
+    #[test]
+    fn test_collections() {
+        let mut redis = FakeRedis::default();
+
+        run(&mut redis, &["SADD", "employees", "anna", "bertil", "anna"]);
+        run(&mut redis, &["SREM", "employees", "bertil"]);
+        assert_eq!(
+            run(&mut redis, &["SISMEMBER", "employees", "anna"]),
+            redis::Value::Int(1)
+        );
+        assert_eq!(
+            run(&mut redis, &["SISMEMBER", "employees", "bertil"]),
+            redis::Value::Int(0)
+        );
+        assert_eq!(
+            strings(run(&mut redis, &["SMEMBERS", "employees"])),
+            ["anna"]
+        );
+
+        run(&mut redis, &["SET", "a", "1"]);
+        run(&mut redis, &["SET", "b", "2"]);
+        let values: Vec<Option<String>> =
+            redis::FromRedisValue::from_redis_value(&run(&mut redis, &["MGET", "a", "x", "b"]))
+                .unwrap();
+        assert_eq!(values, [Some("1".into()), None, Some("2".into())]);
+        assert_eq!(
+            run(&mut redis, &["DEL", "a", "b", "x"]),
+            redis::Value::Int(2)
+        );
+
+        for upload in ["1", "2", "3", "4"] {
+            run(&mut redis, &["LPUSH", "uploads", upload]);
+        }
+        run(&mut redis, &["LTRIM", "uploads", "0", "2"]);
+        assert_eq!(
+            strings(run(&mut redis, &["LRANGE", "uploads", "0", "-1"])),
+            ["4", "3", "2"]
+        );
+
+        run(
+            &mut redis,
+            &["HSET", "names", "anna", "Anna", "bertil", "Bertil"],
+        );
+        run(&mut redis, &["HDEL", "names", "bertil"]);
+        assert_eq!(
+            run(&mut redis, &["HINCRBY", "edits", "upload", "1"]),
+            redis::Value::Int(1)
+        );
+        assert_eq!(
+            run(&mut redis, &["HINCRBY", "edits", "upload", "2"]),
+            redis::Value::Int(3)
+        );
+        let names: HashMap<String, String> =
+            redis::FromRedisValue::from_redis_value(&run(&mut redis, &["HGETALL", "names"]))
+                .unwrap();
+        assert_eq!(names, HashMap::from([("anna".into(), "Anna".into())]));
+
+        run(&mut redis, &["ZADD", "bucket", "2000", "b"]);
+        assert_eq!(
+            run(&mut redis, &["ZADD", "bucket", "1000", "a", "1500", "c"]),
+            redis::Value::Int(2)
+        );
+        run(&mut redis, &["ZREMRANGEBYSCORE", "bucket", "-inf", "1500"]);
+        let scores: Vec<(String, i64)> = redis::FromRedisValue::from_redis_value(&run(
+            &mut redis,
+            &["ZRANGE", "bucket", "0", "-1", "WITHSCORES"],
+        ))
+        .unwrap();
+        assert_eq!(scores, [("b".to_string(), 2000)]);
+
+        run(&mut redis, &["SET", "day:anna:1", "x"]);
+        run(&mut redis, &["SET", "day:bertil:1", "x"]);
+        let scan: (u64, Vec<String>) = redis::FromRedisValue::from_redis_value(&run(
+            &mut redis,
+            &["SCAN", "0", "MATCH", "day:*", "COUNT", "100"],
+        ))
+        .unwrap();
+        assert_eq!(
+            scan,
+            (
+                0,
+                vec!["day:anna:1".to_string(), "day:bertil:1".to_string()]
+            )
+        );
+
+        // Commands against the wrong type fail like in Redis
+        let mut cmd = redis::cmd("SADD");
+        cmd.arg("names").arg("x");
+        assert!(redis.execute(&cmd).is_err());
+    }
+}
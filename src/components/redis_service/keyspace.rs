@@ -0,0 +1,122 @@
+use crate::error::{invalid_key_error, BotResult};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Separator between the segments of a key
+pub const SEPARATOR: char = ':';
+
+/// Check that a dynamic key segment can only ever address its own key.
+///
+/// Separators would let a value reach into another part of the keyspace, and glob characters
+/// would turn it into a pattern for commands that take one, so both are rejected along with
+/// empty segments and control characters such as newlines.
+pub fn validate_segment(segment: &str) -> BotResult<&str> {
+    if segment.is_empty() {
+        return Err(invalid_key_error("Key segment is empty"));
+    }
+    let forbidden = segment
+        .chars()
+        .find(|c| *c == SEPARATOR || matches!(c, '*' | '?' | '[' | ']') || c.is_control());
+    match forbidden {
+        Some(c) => Err(invalid_key_error(&format!(
+            "Key segment {segment:?} contains {c:?}"
+        ))),
+        None => Ok(segment),
+    }
+}
+
+/// A Redis key whose dynamic segments have been validated.
+///
+/// Keys start from a fixed name in the code and only grow through [`Key::name`],
+/// [`Key::segment`] and [`Key::id`], so values from users or stored data can't address other keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key(Cow<'static, str>);
+
+impl Key {
+    /// Key with a name fixed in the code, e.g. "work_hours:employees"
+    pub const fn fixed(name: &'static str) -> Self {
+        Self(Cow::Borrowed(name))
+    }
+
+    /// Append a segment fixed in the code
+    pub fn name(self, name: &'static str) -> Self {
+        self.push(name)
+    }
+
+    /// Append a segment taken from user input or stored data
+    pub fn segment(self, segment: &str) -> BotResult<Self> {
+        let segment = validate_segment(segment)?;
+        Ok(self.push(segment))
+    }
+
+    /// Append a numeric id, which is always a valid segment
+    pub fn id(self, id: u64) -> Self {
+        self.push(&id.to_string())
+    }
+
+    fn push(self, segment: &str) -> Self {
+        let mut key = self.0.into_owned();
+        key.push(SEPARATOR);
+        key.push_str(segment);
+        Self(Cow::Owned(key))
+    }
+
+    /// The key as sent to Redis
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl redis::ToRedisArgs for Key {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        out.write_arg(self.0.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_are_built_from_segments() {
+        let key = Key::fixed("work_hours:day")
+            .segment("anna makinen")
+            .unwrap()
+            .segment("2025-01-06")
+            .unwrap();
+        assert_eq!(key.as_str(), "work_hours:day:anna makinen:2025-01-06");
+        assert_eq!(
+            Key::fixed("guild_config").id(42).to_string(),
+            "guild_config:42"
+        );
+    }
+
+    #[test]
+    fn test_segments_cannot_escape_their_key() {
+        for segment in [
+            "x:*",
+            "anna:2025-01-06",
+            "*",
+            "a?",
+            "[ab]",
+            "anna\nmakinen",
+            "\u{7}",
+            "",
+        ] {
+            assert!(
+                Key::fixed("work_hours:dates").segment(segment).is_err(),
+                "{segment:?}"
+            );
+        }
+        assert!(validate_segment("Åsa Öberg-Ström").is_ok());
+    }
+}
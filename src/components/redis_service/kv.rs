@@ -0,0 +1,359 @@
+use super::keyspace::Key;
+use super::RedisActorHandle;
+use crate::error::BotResult;
+use redis::{FromRedisValue, ToRedisArgs};
+
+/// Keys SCAN is asked to look at per round trip
+const SCAN_COUNT: usize = 500;
+
+/// Reset the expiry of KEYS[1] to ARGV[2] seconds if it holds ARGV[1]
+pub(super) const EXPIRE_IF_EQ_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('EXPIRE', KEYS[1], ARGV[2]) end return 0";
+
+/// Delete KEYS[1] if it holds ARGV[1]
+pub(super) const DEL_IF_EQ_SCRIPT: &str =
+    "if redis.call('GET', KEYS[1]) == ARGV[1] then return redis.call('DEL', KEYS[1]) end return 0";
+
+/// Trim each sorted set KEYS[i] to the ARGV[1 + 2i] seconds before the time ARGV[1] (ms) and,
+/// when every one has fewer than ARGV[2 + 2i] members left, add member ARGV[2] scored ARGV[1] to
+/// all of them. Returns 0 when added, otherwise the ms until the first full set has room.
+pub(super) const ZADD_WITHIN_LIMITS_SCRIPT: &str = "local now = tonumber(ARGV[1]) \
+     for i, key in ipairs(KEYS) do \
+     local window, max = tonumber(ARGV[1 + 2 * i]) * 1000, tonumber(ARGV[2 + 2 * i]) \
+     redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window) \
+     local count = redis.call('ZCARD', key) \
+     if count >= max then \
+     local freeing = redis.call('ZRANGE', key, count - max, count - max, 'WITHSCORES')[2] \
+     return math.max((tonumber(freeing) or now) + window - now, 1) end end \
+     for i, key in ipairs(KEYS) do \
+     redis.call('ZADD', key, now, ARGV[2]) \
+     redis.call('EXPIRE', key, ARGV[1 + 2 * i]) end return 0";
+
+/// Typed key-value operations. Every key is a [`Key`], so callers can't build one from raw
+/// strings and a stored or user-supplied value can't address a key it doesn't own.
+impl RedisActorHandle {
+    /// Check that the store answers
+    pub async fn ping(&self) -> BotResult<()> {
+        let _: String = self.query(redis::cmd("PING")).await?;
+        Ok(())
+    }
+
+    /// Get a string value
+    pub async fn get<T: FromRedisValue>(&self, key: &Key) -> BotResult<T> {
+        let mut cmd = redis::cmd("GET");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Get several string values in one round trip, in the order of the keys
+    pub async fn mget<T: FromRedisValue>(&self, keys: &[Key]) -> BotResult<Vec<T>> {
+        // MGET needs at least one key
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut cmd = redis::cmd("MGET");
+        cmd.arg(keys);
+        self.query(cmd).await
+    }
+
+    /// Set a string value
+    pub async fn set(&self, key: &Key, value: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value);
+        self.query(cmd).await
+    }
+
+    /// Set a string value without resetting its expiry
+    pub async fn set_keep_ttl(&self, key: &Key, value: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value).arg("KEEPTTL");
+        self.query(cmd).await
+    }
+
+    /// Set a string value that expires
+    pub async fn set_ex(&self, key: &Key, value: impl ToRedisArgs, ttl_secs: u64) -> BotResult<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value).arg("EX").arg(ttl_secs);
+        self.query(cmd).await
+    }
+
+    /// Set a string value that expires, unless the key exists. Returns whether it was set.
+    pub async fn set_nx_ex(
+        &self,
+        key: &Key,
+        value: impl ToRedisArgs,
+        ttl_secs: u64,
+    ) -> BotResult<bool> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value).arg("NX").arg("EX").arg(ttl_secs);
+        let reply: redis::Value = self.query(cmd).await?;
+        Ok(!matches!(reply, redis::Value::Nil))
+    }
+
+    /// Reset the expiry of a key if it holds `expected`, checked and set in one step so a value
+    /// another writer put there in between is left alone. Returns whether it was reset.
+    pub async fn expire_if_eq(&self, key: &Key, expected: &str, ttl_secs: u64) -> BotResult<bool> {
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(EXPIRE_IF_EQ_SCRIPT)
+            .arg(1)
+            .arg(key)
+            .arg(expected)
+            .arg(ttl_secs);
+        let reset: i64 = self.query(cmd).await?;
+        Ok(reset == 1)
+    }
+
+    /// Delete a key if it holds `expected`, checked and deleted in one step. Returns whether it
+    /// was deleted.
+    pub async fn del_if_eq(&self, key: &Key, expected: &str) -> BotResult<bool> {
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(DEL_IF_EQ_SCRIPT).arg(1).arg(key).arg(expected);
+        let deleted: i64 = self.query(cmd).await?;
+        Ok(deleted == 1)
+    }
+
+    /// Increment a counter, starting from zero, and return its new value. The expiry is kept.
+    pub async fn incr(&self, key: &Key) -> BotResult<u64> {
+        let mut cmd = redis::cmd("INCR");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Delete a key
+    pub async fn del(&self, key: &Key) -> BotResult<()> {
+        let mut cmd = redis::cmd("DEL");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Delete several keys in one round trip
+    pub async fn del_many(&self, keys: &[Key]) -> BotResult<()> {
+        // DEL needs at least one key
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mut cmd = redis::cmd("DEL");
+        cmd.arg(keys);
+        self.query(cmd).await
+    }
+
+    /// Every key below a prefix, e.g. `work_hours:day` for all day entries. Uses SCAN, so the
+    /// keyspace isn't blocked while it's walked.
+    pub async fn scan_prefix(&self, prefix: &Key) -> BotResult<Vec<String>> {
+        let pattern = format!("{prefix}:*");
+        let mut keys = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let mut cmd = redis::cmd("SCAN");
+            cmd.arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT);
+            let (next, page): (u64, Vec<String>) = self.query(cmd).await?;
+            keys.extend(page);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        // A key can be returned more than once while the keyspace changes
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// Expire a key after the given number of seconds
+    pub async fn expire(&self, key: &Key, ttl_secs: u64) -> BotResult<()> {
+        let mut cmd = redis::cmd("EXPIRE");
+        cmd.arg(key).arg(ttl_secs);
+        self.query(cmd).await
+    }
+
+    /// Remaining time to live of a key in seconds, or None if it doesn't expire or doesn't exist
+    pub async fn ttl(&self, key: &Key) -> BotResult<Option<u64>> {
+        let mut cmd = redis::cmd("TTL");
+        cmd.arg(key);
+        let ttl: i64 = self.query(cmd).await?;
+        Ok(u64::try_from(ttl).ok())
+    }
+
+    /// Get a hash field
+    pub async fn hget<T: FromRedisValue>(&self, key: &Key, field: &str) -> BotResult<T> {
+        let mut cmd = redis::cmd("HGET");
+        cmd.arg(key).arg(field);
+        self.query(cmd).await
+    }
+
+    /// Set a hash field
+    pub async fn hset(&self, key: &Key, field: &str, value: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("HSET");
+        cmd.arg(key).arg(field).arg(value);
+        self.query(cmd).await
+    }
+
+    /// Add to the integer in a hash field, which counts from zero, returning the new value
+    pub async fn hincrby(&self, key: &Key, field: &str, by: i64) -> BotResult<i64> {
+        let mut cmd = redis::cmd("HINCRBY");
+        cmd.arg(key).arg(field).arg(by);
+        self.query(cmd).await
+    }
+
+    /// Delete a hash field
+    pub async fn hdel(&self, key: &Key, field: &str) -> BotResult<()> {
+        let mut cmd = redis::cmd("HDEL");
+        cmd.arg(key).arg(field);
+        self.query(cmd).await
+    }
+
+    /// Get all values of a hash
+    pub async fn hvals<T: FromRedisValue>(&self, key: &Key) -> BotResult<T> {
+        let mut cmd = redis::cmd("HVALS");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Get all fields and values of a hash
+    pub async fn hgetall<T: FromRedisValue>(&self, key: &Key) -> BotResult<T> {
+        let mut cmd = redis::cmd("HGETALL");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Add a member, or a list of members, to a set
+    pub async fn sadd(&self, key: &Key, member: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("SADD");
+        cmd.arg(key).arg(member);
+        self.query(cmd).await
+    }
+
+    /// Remove a member, or a list of members, from a set
+    pub async fn srem(&self, key: &Key, members: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("SREM");
+        cmd.arg(key).arg(members);
+        self.query(cmd).await
+    }
+
+    /// Get all members of a set
+    pub async fn smembers<T: FromRedisValue>(&self, key: &Key) -> BotResult<T> {
+        let mut cmd = redis::cmd("SMEMBERS");
+        cmd.arg(key);
+        self.query(cmd).await
+    }
+
+    /// Whether a set contains a member
+    pub async fn sismember(&self, key: &Key, member: impl ToRedisArgs) -> BotResult<bool> {
+        let mut cmd = redis::cmd("SISMEMBER");
+        cmd.arg(key).arg(member);
+        self.query(cmd).await
+    }
+
+    /// Push a value to the head of a list
+    pub async fn lpush(&self, key: &Key, value: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("LPUSH");
+        cmd.arg(key).arg(value);
+        self.query(cmd).await
+    }
+
+    /// Trim a list to the given range
+    pub async fn ltrim(&self, key: &Key, start: isize, stop: isize) -> BotResult<()> {
+        let mut cmd = redis::cmd("LTRIM");
+        cmd.arg(key).arg(start).arg(stop);
+        self.query(cmd).await
+    }
+
+    /// Get a range of a list
+    pub async fn lrange<T: FromRedisValue>(
+        &self,
+        key: &Key,
+        start: isize,
+        stop: isize,
+    ) -> BotResult<T> {
+        let mut cmd = redis::cmd("LRANGE");
+        cmd.arg(key).arg(start).arg(stop);
+        self.query(cmd).await
+    }
+
+    /// Add a member to a sorted set
+    pub async fn zadd(&self, key: &Key, score: i64, member: impl ToRedisArgs) -> BotResult<()> {
+        let mut cmd = redis::cmd("ZADD");
+        cmd.arg(key).arg(score).arg(member);
+        self.query(cmd).await
+    }
+
+    /// Add several scored members to a sorted set in one atomic command
+    pub async fn zadd_many(&self, key: &Key, members: &[(i64, String)]) -> BotResult<()> {
+        // ZADD needs at least one member
+        if members.is_empty() {
+            return Ok(());
+        }
+        let mut cmd = redis::cmd("ZADD");
+        cmd.arg(key).arg(members);
+        self.query(cmd).await
+    }
+
+    /// Get all members of a sorted set with their scores
+    pub async fn zrange_withscores<T: FromRedisValue>(&self, key: &Key) -> BotResult<T> {
+        let mut cmd = redis::cmd("ZRANGE");
+        cmd.arg(key).arg(0).arg(-1).arg("WITHSCORES");
+        self.query(cmd).await
+    }
+
+    /// Add a member scored `now_ms` to sorted sets of timestamps, each first trimmed to its
+    /// window of `(key, window_secs, max_members)`, but only when every one has room left.
+    /// Checked and added in one step, so parallel callers can't both take the last place.
+    /// Returns `None` when added, otherwise the milliseconds until a place frees up.
+    pub async fn zadd_within_limits(
+        &self,
+        windows: &[(Key, u64, u32)],
+        now_ms: i64,
+        member: &str,
+    ) -> BotResult<Option<u64>> {
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(ZADD_WITHIN_LIMITS_SCRIPT)
+            .arg(windows.len())
+            .arg(windows.iter().map(|(key, _, _)| key).collect::<Vec<_>>())
+            .arg(now_ms)
+            .arg(member);
+        for (_, window_secs, max_members) in windows {
+            cmd.arg(window_secs).arg(max_members);
+        }
+        let retry_after_ms: u64 = self.query(cmd).await?;
+        Ok((retry_after_ms > 0).then_some(retry_after_ms))
+    }
+
+    /// Remove the members of a sorted set scored at most `max`
+    pub async fn zrem_up_to(&self, key: &Key, max: i64) -> BotResult<()> {
+        let mut cmd = redis::cmd("ZREMRANGEBYSCORE");
+        cmd.arg(key).arg("-inf").arg(max);
+        self.query(cmd).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::keys::{dates_key, day_key};
+    use crate::components::work_schedule::EmployeeId;
+
+    #[tokio::test]
+    async fn test_employee_names_cannot_reach_other_keys() {
+        let redis_handle = RedisActorHandle::fake();
+        let anna = EmployeeId::new("Anna");
+        let anna_day = day_key(&anna, "2025-01-06").unwrap();
+        redis_handle.set(&anna_day, "anna's shift").await.unwrap();
+
+        // Names and dates that would otherwise address Anna's keys or match them as a pattern
+        for name in ["x:*", "*", "anna:2025-01-06", "anna\u{0}"] {
+            let employee = EmployeeId::new(name);
+            assert!(dates_key(&employee).is_err(), "{name:?}");
+            assert!(day_key(&employee, "2025-01-06").is_err(), "{name:?}");
+        }
+        assert!(day_key(&anna, "*").is_err());
+        assert!(day_key(&anna, "2025-01-06:x").is_err());
+
+        let stored: Option<String> = redis_handle.get(&anna_day).await.unwrap();
+        assert_eq!(stored.as_deref(), Some("anna's shift"));
+    }
+}
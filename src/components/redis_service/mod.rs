@@ -1,3 +1,20 @@
 mod actor;
+mod backend;
+mod connection;
+#[cfg(any(test, feature = "test-util", feature = "sqlite"))]
+mod fake;
+mod keyspace;
+mod kv;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 
-pub use actor::{RedisActor, RedisActorHandle};
+pub use actor::{keys, RedisActor, RedisActorHandle};
+pub use backend::{spawn_store, StorageBackend};
+#[cfg(feature = "web-interface")]
+pub(crate) use connection::RawConnection;
+#[cfg(any(test, feature = "test-util", feature = "sqlite"))]
+pub use fake::{FakeClock, FakeRedis};
+pub use keyspace::validate_segment;
+pub use keyspace::Key;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteConnection, SqliteRedis};
@@ -0,0 +1,533 @@
+//! SQLite backend for running without a Redis server.
+//!
+//! Strings are rows of a key/value table and collections keep their members in a second
+//! table, both with the key's expiry time. Each command loads the keys it touches into a
+//! [`FakeRedis`] clocked at the current time, runs there and writes the keys back in one
+//! SQLite transaction, so the backend answers exactly like the test substitute. Expired keys
+//! are skipped on read and swept out every few minutes.
+
+use super::connection::pipeline_replies;
+use super::fake::{answer, CommandStore, Entry, FakeClock, FakeRedis};
+use super::RedisActorHandle;
+use crate::components::google_calendar::models::CalendarEvent;
+use crate::error::{other_error, BotResult};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// Time between sweeps of expired keys
+const SWEEP_INTERVAL_MS: u64 = 5 * 60 * 1000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS kv (
+        key BLOB PRIMARY KEY,
+        kind TEXT NOT NULL,
+        value BLOB,
+        expires_at_ms INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS members (
+        key BLOB NOT NULL,
+        position INTEGER NOT NULL,
+        member BLOB NOT NULL,
+        value BLOB,
+        score REAL,
+        PRIMARY KEY (key, position)
+    );
+    CREATE INDEX IF NOT EXISTS kv_expires_at ON kv (expires_at_ms);
+";
+
+fn sqlite_error(e: rusqlite::Error) -> crate::error::Error {
+    other_error(&format!("SQLite error: {e}"))
+}
+
+/// A member row: the member, its value in hashes and its score in sorted sets
+type MemberRow = (Vec<u8>, Option<Vec<u8>>, Option<f64>);
+
+/// Redis commands served from a SQLite database
+pub struct SqliteRedis {
+    conn: Connection,
+    /// Clock overriding the system time, for tests
+    clock: Option<FakeClock>,
+    last_sweep_ms: u64,
+}
+
+impl SqliteRedis {
+    /// Open or create the database file at `path`
+    pub fn open(path: impl AsRef<Path>) -> BotResult<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| other_error(&format!("Failed to create {}: {e}", dir.display())))?;
+        }
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Create a database living in memory only
+    pub fn in_memory() -> BotResult<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> BotResult<Self> {
+        // The bot and work_hours may share the file, so writers wait for each other a while
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .map_err(sqlite_error)?;
+        conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        Ok(Self {
+            conn,
+            clock: None,
+            last_sweep_ms: 0,
+        })
+    }
+
+    /// Expire keys according to `clock` instead of the system time
+    pub fn with_clock(mut self, clock: FakeClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    fn now_ms(&self) -> u64 {
+        match &self.clock {
+            Some(clock) => clock.now_ms(),
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_millis() as u64),
+        }
+    }
+
+    /// Execute every command of a pipeline in one SQLite transaction, returning their replies
+    pub fn execute_pipeline(&mut self, pipe: &redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        let mut keys = Vec::new();
+        for cmd in pipe.cmd_iter() {
+            keys.extend(self.touched_keys(cmd)?);
+        }
+        self.run(keys, |redis| redis.execute_pipeline(pipe))
+    }
+
+    /// Keys a command reads or writes. SCAN touches every stored key matching its pattern.
+    fn touched_keys(&self, cmd: &redis::Cmd) -> BotResult<Vec<Vec<u8>>> {
+        let args: Vec<&[u8]> = cmd
+            .args_iter()
+            .filter_map(|arg| match arg {
+                redis::Arg::Simple(bytes) => Some(bytes),
+                redis::Arg::Cursor => None,
+            })
+            .collect();
+        let name = args.first().map(|name| name.to_ascii_uppercase());
+        match name.as_deref() {
+            Some(b"PING") | None => Ok(Vec::new()),
+            Some(b"MGET") | Some(b"EXISTS") | Some(b"DEL") => {
+                Ok(args[1..].iter().map(|key| key.to_vec()).collect())
+            }
+            // EVAL script numkeys key...
+            Some(b"EVAL") => {
+                let numkeys: usize = args
+                    .get(2)
+                    .and_then(|n| std::str::from_utf8(n).ok()?.parse().ok())
+                    .ok_or_else(|| other_error("ERR EVAL without a key count"))?;
+                Ok(args
+                    .iter()
+                    .skip(3)
+                    .take(numkeys)
+                    .map(|key| key.to_vec())
+                    .collect())
+            }
+            Some(b"SCAN") => {
+                let pattern = args
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(b"MATCH"))
+                    .and_then(|at| args.get(at + 1))
+                    .copied()
+                    .unwrap_or(b"*");
+                let prefix = pattern.strip_suffix(b"*").unwrap_or(pattern);
+                let mut statement = self
+                    .conn
+                    .prepare_cached("SELECT key FROM kv WHERE substr(key, 1, ?1) = ?2")
+                    .map_err(sqlite_error)?;
+                let keys = statement
+                    .query_map(params![prefix.len() as i64, prefix], |row| row.get(0))
+                    .map_err(sqlite_error)?
+                    .collect::<Result<Vec<Vec<u8>>, _>>()
+                    .map_err(sqlite_error)?;
+                Ok(keys)
+            }
+            Some(_) => Ok(args.get(1).map(|key| key.to_vec()).into_iter().collect()),
+        }
+    }
+
+    /// Run `f` against the stored `keys` and write back what it changed. Nothing is written
+    /// when it fails.
+    fn run<T>(
+        &mut self,
+        mut keys: Vec<Vec<u8>>,
+        f: impl FnOnce(&mut FakeRedis) -> BotResult<T>,
+    ) -> BotResult<T> {
+        let now_ms = self.now_ms();
+        if now_ms.saturating_sub(self.last_sweep_ms) >= SWEEP_INTERVAL_MS {
+            self.sweep(now_ms)?;
+        }
+        keys.sort();
+        keys.dedup();
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(sqlite_error)?;
+        let mut redis = FakeRedis::with_clock(FakeClock::at(now_ms));
+        for key in &keys {
+            if let Some((entry, expires_at_ms)) = load(&tx, key)? {
+                redis.load_entry(key.clone(), entry, expires_at_ms);
+            }
+        }
+
+        let result = f(&mut redis)?;
+        for key in &keys {
+            store(&tx, key, redis.take_entry(key))?;
+        }
+        tx.commit().map_err(sqlite_error)?;
+        Ok(result)
+    }
+
+    /// Delete the keys that have expired
+    fn sweep(&mut self, now_ms: u64) -> BotResult<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(sqlite_error)?;
+        tx.execute(
+            "DELETE FROM members WHERE key IN
+                (SELECT key FROM kv WHERE expires_at_ms IS NOT NULL AND expires_at_ms <= ?1)",
+            params![now_ms as i64],
+        )
+        .map_err(sqlite_error)?;
+        let swept = tx
+            .execute(
+                "DELETE FROM kv WHERE expires_at_ms IS NOT NULL AND expires_at_ms <= ?1",
+                params![now_ms as i64],
+            )
+            .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+
+        if swept > 0 {
+            debug!("Swept {} expired keys from SQLite", swept);
+        }
+        self.last_sweep_ms = now_ms;
+        Ok(())
+    }
+}
+
+/// Read a key's value and expiry time, expired or not
+fn load(tx: &Transaction, key: &[u8]) -> BotResult<Option<(Entry, Option<u64>)>> {
+    let row: Option<(String, Option<Vec<u8>>, Option<i64>)> = tx
+        .query_row(
+            "SELECT kind, value, expires_at_ms FROM kv WHERE key = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(sqlite_error)?;
+    let Some((kind, value, expires_at_ms)) = row else {
+        return Ok(None);
+    };
+    let expires_at_ms = expires_at_ms.map(|at| at as u64);
+    if kind == "string" {
+        return Ok(Some((
+            Entry::String(value.unwrap_or_default()),
+            expires_at_ms,
+        )));
+    }
+
+    let mut statement = tx
+        .prepare_cached("SELECT member, value, score FROM members WHERE key = ?1 ORDER BY position")
+        .map_err(sqlite_error)?;
+    let members = statement
+        .query_map(params![key], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(sqlite_error)?
+        .collect::<Result<Vec<MemberRow>, _>>()
+        .map_err(sqlite_error)?;
+
+    let entry = match kind.as_str() {
+        "set" => Entry::Set(members.into_iter().map(|(member, _, _)| member).collect()),
+        "hash" => Entry::Hash(
+            members
+                .into_iter()
+                .map(|(field, value, _)| (field, value.unwrap_or_default()))
+                .collect(),
+        ),
+        "list" => Entry::List(members.into_iter().map(|(value, _, _)| value).collect()),
+        "zset" => Entry::SortedSet(
+            members
+                .into_iter()
+                .map(|(member, _, score)| (score.unwrap_or_default(), member))
+                .collect(),
+        ),
+        other => return Err(other_error(&format!("Unknown SQLite entry kind {other}"))),
+    };
+    Ok(Some((entry, expires_at_ms)))
+}
+
+/// Replace a key's stored value, deleting it when `entry` is `None`
+fn store(tx: &Transaction, key: &[u8], entry: Option<(Entry, Option<u64>)>) -> BotResult<()> {
+    tx.execute("DELETE FROM members WHERE key = ?1", params![key])
+        .map_err(sqlite_error)?;
+    let Some((entry, expires_at_ms)) = entry else {
+        tx.execute("DELETE FROM kv WHERE key = ?1", params![key])
+            .map_err(sqlite_error)?;
+        return Ok(());
+    };
+
+    let (kind, value, members): (&str, Option<Vec<u8>>, Vec<MemberRow>) = match entry {
+        Entry::String(value) => ("string", Some(value), Vec::new()),
+        Entry::Set(set) => (
+            "set",
+            None,
+            set.into_iter().map(|member| (member, None, None)).collect(),
+        ),
+        Entry::Hash(hash) => (
+            "hash",
+            None,
+            hash.into_iter()
+                .map(|(field, value)| (field, Some(value), None))
+                .collect(),
+        ),
+        Entry::List(list) => (
+            "list",
+            None,
+            list.into_iter().map(|value| (value, None, None)).collect(),
+        ),
+        Entry::SortedSet(set) => (
+            "zset",
+            None,
+            set.into_iter()
+                .map(|(score, member)| (member, None, Some(score)))
+                .collect(),
+        ),
+    };
+    tx.execute(
+        "INSERT OR REPLACE INTO kv (key, kind, value, expires_at_ms) VALUES (?1, ?2, ?3, ?4)",
+        params![key, kind, value, expires_at_ms.map(|at| at as i64)],
+    )
+    .map_err(sqlite_error)?;
+
+    let mut statement = tx
+        .prepare_cached(
+            "INSERT INTO members (key, position, member, value, score) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .map_err(sqlite_error)?;
+    for (position, (member, value, score)) in members.into_iter().enumerate() {
+        statement
+            .execute(params![key, position as i64, member, value, score])
+            .map_err(sqlite_error)?;
+    }
+    Ok(())
+}
+
+impl CommandStore for SqliteRedis {
+    fn execute(&mut self, cmd: &redis::Cmd) -> BotResult<redis::Value> {
+        let keys = self.touched_keys(cmd)?;
+        self.run(keys, |redis| redis.execute(cmd))
+    }
+
+    fn execute_pipeline(&mut self, pipe: &redis::Pipeline) -> BotResult<Vec<redis::Value>> {
+        SqliteRedis::execute_pipeline(self, pipe)
+    }
+
+    fn save_events(&mut self, events: &[CalendarEvent]) -> BotResult<()> {
+        let keys = vec![super::keys::GOOGLE_CALENDAR_EVENTS
+            .as_str()
+            .as_bytes()
+            .to_vec()];
+        self.run(keys, |redis| CommandStore::save_events(redis, events))
+    }
+
+    fn get_events(&mut self) -> BotResult<Vec<CalendarEvent>> {
+        let keys = vec![super::keys::GOOGLE_CALENDAR_EVENTS
+            .as_str()
+            .as_bytes()
+            .to_vec()];
+        self.run(keys, CommandStore::get_events)
+    }
+
+    fn get_token(&mut self) -> BotResult<Option<serde_json::Value>> {
+        let keys = vec![super::keys::GOOGLE_CALENDAR_TOKEN
+            .as_str()
+            .as_bytes()
+            .to_vec()];
+        self.run(keys, CommandStore::get_token)
+    }
+
+    fn save_token(&mut self, token: &serde_json::Value) -> BotResult<()> {
+        let keys = vec![super::keys::GOOGLE_CALENDAR_TOKEN
+            .as_str()
+            .as_bytes()
+            .to_vec()];
+        self.run(keys, |redis| CommandStore::save_token(redis, token))
+    }
+}
+
+impl RedisActorHandle {
+    /// Handle served by the SQLite database at `path`
+    pub fn sqlite(path: impl AsRef<Path>) -> BotResult<Self> {
+        Ok(Self::sqlite_serving(SqliteRedis::open(path)?))
+    }
+
+    /// Handle served by `store` on a blocking thread, as SQLite calls block
+    pub fn sqlite_serving(mut store: SqliteRedis) -> Self {
+        let (command_tx, mut command_rx) = mpsc::channel(32);
+
+        tokio::task::spawn_blocking(move || {
+            while let Some(command) = command_rx.blocking_recv() {
+                if !answer(&mut store, command) {
+                    break;
+                }
+            }
+        });
+
+        Self { command_tx }
+    }
+}
+
+/// A `redis` connection answered by a shared [`SqliteRedis`], for code talking to Redis
+/// through the `redis` crate directly
+#[derive(Clone)]
+pub struct SqliteConnection {
+    store: Arc<Mutex<SqliteRedis>>,
+}
+
+impl SqliteConnection {
+    pub fn new(store: SqliteRedis) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(store)),
+        }
+    }
+
+    /// Run `f` against the store on a blocking thread
+    async fn with_store<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut SqliteRedis) -> BotResult<T> + Send + 'static,
+    ) -> redis::RedisResult<T> {
+        let store = Arc::clone(&self.store);
+        let result = tokio::task::spawn_blocking(move || {
+            let mut store = store.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut store)
+        })
+        .await
+        .map_err(|e| {
+            error!("SQLite task failed: {}", e);
+            redis_error(&e.to_string())
+        })?;
+        result.map_err(|e| redis_error(&e.to_string()))
+    }
+}
+
+fn redis_error(detail: &str) -> redis::RedisError {
+    redis::RedisError::from((
+        redis::ErrorKind::ResponseError,
+        "SQLite store error",
+        detail.to_string(),
+    ))
+}
+
+impl redis::aio::ConnectionLike for SqliteConnection {
+    fn req_packed_command<'a>(
+        &'a mut self,
+        cmd: &'a redis::Cmd,
+    ) -> redis::RedisFuture<'a, redis::Value> {
+        let cmd = cmd.clone();
+        Box::pin(self.with_store(move |store| store.execute(&cmd)))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipe: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+        let owned = pipe.clone();
+        Box::pin(async move {
+            let results = self
+                .with_store(move |store| store.execute_pipeline(&owned))
+                .await?;
+            let replies = pipeline_replies(pipe, results);
+            Ok(replies.into_iter().skip(offset).take(count).collect())
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    #[test]
+    fn test_keys_survive_reopening_and_expire() {
+        let path = std::env::temp_dir().join(format!("mussubotti-{}.db", uuid::Uuid::new_v4()));
+        let clock = FakeClock::at(1_000);
+        {
+            let mut store = SqliteRedis::open(&path).unwrap().with_clock(clock.clone());
+            store
+                .execute(redis::cmd("SET").arg("kept").arg("1"))
+                .unwrap();
+            store
+                .execute(redis::cmd("SET").arg("brief").arg("1").arg("EX").arg(60))
+                .unwrap();
+            store
+                .execute(redis::cmd("SADD").arg("set").arg("a").arg("b"))
+                .unwrap();
+        }
+
+        let mut store = SqliteRedis::open(&path).unwrap().with_clock(clock.clone());
+        let members = store.execute(redis::cmd("SMEMBERS").arg("set")).unwrap();
+        assert_eq!(
+            redis::from_redis_value::<BTreeSet<String>>(&members).unwrap(),
+            BTreeSet::from(["a".to_string(), "b".to_string()])
+        );
+
+        clock.advance(Duration::from_millis(SWEEP_INTERVAL_MS));
+        let exists = store
+            .execute(redis::cmd("EXISTS").arg("kept").arg("brief"))
+            .unwrap();
+        assert_eq!(exists, redis::Value::Int(1));
+        let rows: i64 = store
+            .conn
+            .query_row("SELECT COUNT(*) FROM kv", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 2, "the expired key is swept out");
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_transactions_reply_like_redis() {
+        let mut conn = SqliteConnection::new(SqliteRedis::in_memory().unwrap());
+        let (count, members): (i64, Vec<String>) = redis::pipe()
+            .atomic()
+            .sadd("set", "a")
+            .ignore()
+            .incr("counter", 2)
+            .smembers("set")
+            .query_async(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!((count, members), (2, vec!["a".to_string()]));
+
+        let pong: String = redis::cmd("PING").query_async(&mut conn).await.unwrap();
+        assert_eq!(pong, "PONG");
+        assert!(redis::cmd("SADD")
+            .arg("counter")
+            .arg("x")
+            .query_async::<i64>(&mut conn)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use lazy_static::lazy_static;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex as AsyncMutex};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Delay before the first restart, doubled after every consecutive crash
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Upper bound for the restart delay
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref RESTART_COUNTS: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+}
+
+/// Command receiver shared between incarnations of an actor.
+///
+/// The channel lives outside the actor, so a restarted actor keeps serving the handles that
+/// were given out before the crash.
+pub type SharedReceiver<C> = Arc<AsyncMutex<mpsc::Receiver<C>>>;
+
+/// Create a command channel whose receiver can be handed to successive actor instances
+pub fn actor_channel<C>(buffer: usize) -> (mpsc::Sender<C>, SharedReceiver<C>) {
+    let (command_tx, command_rx) = mpsc::channel(buffer);
+    (command_tx, Arc::new(AsyncMutex::new(command_rx)))
+}
+
+/// Number of times each supervised actor has been restarted
+pub fn restart_counts() -> Vec<(&'static str, u64)> {
+    RESTART_COUNTS
+        .lock()
+        .map(|counts| counts.iter().map(|(name, count)| (*name, *count)).collect())
+        .unwrap_or_default()
+}
+
+fn record_restart(name: &'static str) -> u64 {
+    let mut counts = RESTART_COUNTS.lock().unwrap_or_else(|e| e.into_inner());
+    let count = counts.entry(name).or_insert(0);
+    *count += 1;
+    *count
+}
+
+/// Extract a readable message from a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Run an actor under supervision.
+///
+/// The factory builds and runs a fresh actor instance. When the instance panics the panic is
+/// logged and a new one is started after an increasing delay; a normal return (e.g. after a
+/// shutdown command) ends supervision.
+pub fn supervise<F, Fut>(name: &'static str, factory: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    RESTART_COUNTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(name)
+        .or_insert(0);
+
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            match tokio::spawn(factory()).await {
+                Ok(()) => {
+                    info!("Supervised actor {} stopped", name);
+                    break;
+                }
+                Err(e) if e.is_panic() => {
+                    let payload = e.into_panic();
+                    let restarts = record_restart(name);
+                    error!(
+                        "Actor {} panicked: {}. Restarting in {:?} (restart #{})",
+                        name,
+                        panic_message(payload.as_ref()),
+                        backoff,
+                        restarts
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(e) => {
+                    warn!("Actor {} task was cancelled: {}", name, e);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum TestCommand {
+        Ping(mpsc::Sender<&'static str>),
+        Poison,
+        Shutdown,
+    }
+
+    async fn run_test_actor(command_rx: SharedReceiver<TestCommand>) {
+        loop {
+            let Some(cmd) = command_rx.lock().await.recv().await else {
+                break;
+            };
+            match cmd {
+                TestCommand::Ping(response_tx) => {
+                    let _ = response_tx.send("pong").await;
+                }
+                TestCommand::Poison => panic!("poisoned"),
+                TestCommand::Shutdown => break,
+            }
+        }
+    }
+
+    async fn ping(command_tx: &mpsc::Sender<TestCommand>) -> Option<&'static str> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        command_tx.send(TestCommand::Ping(response_tx)).await.ok()?;
+        response_rx.recv().await
+    }
+
+    fn restarts_of(name: &str) -> u64 {
+        restart_counts()
+            .into_iter()
+            .find(|(actor, _)| *actor == name)
+            .map(|(_, count)| count)
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_actor_is_restarted_after_panic() {
+        let (command_tx, command_rx) = actor_channel(8);
+        let supervisor = supervise("test_poison", move || run_test_actor(command_rx.clone()));
+
+        assert_eq!(ping(&command_tx).await, Some("pong"));
+
+        command_tx.send(TestCommand::Poison).await.unwrap();
+        // The restarted actor picks up the same channel
+        assert_eq!(ping(&command_tx).await, Some("pong"));
+        assert_eq!(restarts_of("test_poison"), 1);
+
+        command_tx.send(TestCommand::Shutdown).await.unwrap();
+        supervisor.await.unwrap();
+        assert_eq!(restarts_of("test_poison"), 1);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::components::google_calendar::GoogleCalendarHandle;
+use crate::components::work_schedule::WorkScheduleHandle;
+use crate::error::BotResult;
+use crate::utils::time::{week_bounds, WeekStart};
+use async_trait::async_trait;
+use chrono::Local;
+use futures::future::join_all;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long the startup warm-up may take in total
+pub const WARM_UP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Data fetched ahead of the first commands that need it
+#[async_trait]
+pub trait WarmUp: Send + Sync {
+    /// What is warmed, for the log
+    fn name(&self) -> &'static str;
+
+    /// Fetch the data, returning how many items were loaded
+    async fn warm(&self) -> BotResult<usize>;
+}
+
+/// The employees and this week's schedule of each of them
+pub struct WorkScheduleWarmUp {
+    pub handle: WorkScheduleHandle,
+    pub week_start: WeekStart,
+}
+
+#[async_trait]
+impl WarmUp for WorkScheduleWarmUp {
+    fn name(&self) -> &'static str {
+        "work schedules"
+    }
+
+    async fn warm(&self) -> BotResult<usize> {
+        let employees = self.handle.get_employees().await?;
+        let (first, last) = week_bounds(Local::now().date_naive(), self.week_start);
+        let start_date = first.format("%Y-%m-%d").to_string();
+        let end_date = last.format("%Y-%m-%d").to_string();
+
+        let schedules = join_all(employees.iter().map(|employee| {
+            self.handle
+                .get_schedule_for_date_range(employee.clone(), &start_date, &end_date)
+        }))
+        .await;
+        Ok(schedules.into_iter().filter(Result::is_ok).count())
+    }
+}
+
+/// The upcoming calendar events, which are saved to Redis as they are fetched
+pub struct CalendarWarmUp(pub GoogleCalendarHandle);
+
+#[async_trait]
+impl WarmUp for CalendarWarmUp {
+    fn name(&self) -> &'static str {
+        "calendar events"
+    }
+
+    async fn warm(&self) -> BotResult<usize> {
+        Ok(self.0.get_upcoming_events().await?.len())
+    }
+}
+
+/// How one warm-up went
+#[derive(Debug)]
+pub enum WarmUpOutcome {
+    Warmed(usize),
+    Failed(String),
+    TimedOut,
+}
+
+/// Run the warm-ups concurrently, giving up on any still running after `limit`. Failures are
+/// logged and otherwise ignored.
+pub async fn warm_up(
+    sources: &[Box<dyn WarmUp>],
+    limit: Duration,
+) -> Vec<(&'static str, WarmUpOutcome)> {
+    let started = Instant::now();
+    let outcomes = join_all(sources.iter().map(|source| async move {
+        let outcome = match tokio::time::timeout(limit, source.warm()).await {
+            Ok(Ok(count)) => WarmUpOutcome::Warmed(count),
+            Ok(Err(e)) => WarmUpOutcome::Failed(e.to_string()),
+            Err(_) => WarmUpOutcome::TimedOut,
+        };
+        (source.name(), outcome)
+    }))
+    .await;
+
+    for (name, outcome) in &outcomes {
+        match outcome {
+            WarmUpOutcome::Warmed(count) => info!("Warmed {} ({} items)", name, count),
+            WarmUpOutcome::Failed(e) => warn!("Failed to warm {}: {}", name, e),
+            WarmUpOutcome::TimedOut => warn!("Warming {} timed out after {:?}", name, limit),
+        }
+    }
+    info!("Cache warm-up finished in {:?}", started.elapsed());
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::other_error;
+
+    /// A source that takes `delay` to load `count` items, or fails without any
+    struct FakeWarmUp {
+        delay: Duration,
+        count: Option<usize>,
+    }
+
+    #[async_trait]
+    impl WarmUp for FakeWarmUp {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn warm(&self) -> BotResult<usize> {
+            tokio::time::sleep(self.delay).await;
+            self.count.ok_or_else(|| other_error("unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_respects_the_timeout() {
+        let sources: Vec<Box<dyn WarmUp>> = vec![
+            Box::new(FakeWarmUp {
+                delay: Duration::from_secs(60),
+                count: Some(1),
+            }),
+            Box::new(FakeWarmUp {
+                delay: Duration::ZERO,
+                count: Some(3),
+            }),
+            Box::new(FakeWarmUp {
+                delay: Duration::ZERO,
+                count: None,
+            }),
+        ];
+
+        let started = Instant::now();
+        let outcomes = warm_up(&sources, Duration::from_millis(50)).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert!(matches!(outcomes[0].1, WarmUpOutcome::TimedOut));
+        assert!(matches!(outcomes[1].1, WarmUpOutcome::Warmed(3)));
+        assert!(matches!(outcomes[2].1, WarmUpOutcome::Failed(_)));
+    }
+}
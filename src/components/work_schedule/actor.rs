@@ -1,41 +1,170 @@
-use crate::components::redis_service::RedisActorHandle;
-use crate::components::work_schedule::models::{EmployeeSchedule, WorkScheduleEntry};
+use crate::components::event_bus::{EventBus, ScheduleChanged, ScheduleUpdated};
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::components::supervisor::{actor_channel, supervise, SharedReceiver};
+use crate::components::work_schedule::actuals::ACTUALS_AUDIT_SOURCE;
+use crate::components::work_schedule::audit::{record_audit, AuditRecord};
+use crate::components::work_schedule::employee::{compare_names, EmployeeId};
+use crate::components::work_schedule::models::{
+    parse_stored_entry, ContextLink, CoverageInfo, DaySchedules, EmployeeSchedule,
+    WorkScheduleEntry,
+};
+use crate::components::work_schedule::overlap::{
+    duplicate_kind, merge_entries, pick_entry, DuplicateShift, KeepChoice,
+};
+use crate::components::work_schedule::quality::record_manual_edit;
+use crate::components::work_schedule::reconcile::{ReconcileMode, ReconcileReport};
 use crate::config::Config;
 use crate::error::{work_schedule_error, BotResult};
+use crate::utils::redact::Redacted;
 use chrono::NaiveDate;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
-use tracing::{error, info};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
 
 // Redis key constants
 pub mod keys {
-    pub const WORK_HOURS_EMPLOYEES: &str = "work_hours:employees";
-    pub const WORK_HOURS_DAY_PREFIX: &str = "work_hours:day:";
-    pub const WORK_HOURS_DATES_PREFIX: &str = "work_hours:dates:";
+    use super::{EmployeeId, Key};
+    use crate::error::BotResult;
+
+    pub const WORK_HOURS_EMPLOYEES: Key = Key::fixed("work_hours:employees");
+    pub const WORK_HOURS_EMPLOYEE_NAMES: Key = Key::fixed("work_hours:employee_names");
+    /// Day entries, followed by the employee slug and date
+    pub const WORK_HOURS_DAY: Key = Key::fixed("work_hours:day");
+    /// Sets of dates with entries, followed by the employee slug
+    pub const WORK_HOURS_DATES: Key = Key::fixed("work_hours:dates");
+    /// Hash of duplicate entries, `slug|date` -> JSON array of the entries
+    pub const WORK_HOURS_DUPLICATES: Key = Key::fixed("work_hours:duplicates");
+    /// List of stored schedule uploads as JSON, newest first
+    pub const WORK_HOURS_UPLOADS: Key = Key::fixed("work_hours:uploads");
+    /// Hash of contract hours, slug -> JSON record
+    pub const WORK_HOURS_CONTRACT_HOURS: Key = Key::fixed("work_hours:contract_hours");
+    /// Hash of employee groups, group name -> JSON array of employee names
+    pub const WORK_HOURS_EMPLOYEE_GROUPS: Key = Key::fixed("work_hours:employee_groups");
+    /// Hash of employee display metadata, slug -> JSON record of their emoji and avatar
+    pub const WORK_HOURS_EMPLOYEE_PROFILES: Key = Key::fixed("work_hours:employee_profiles");
+    /// Hash of parse quality records, upload id -> JSON record
+    pub const WORK_HOURS_PARSE_RECORDS: Key = Key::fixed("work_hours:parse_records");
+    /// Hash counting manual edits of parsed entries, upload id -> count
+    pub const WORK_HOURS_PARSE_EDITS: Key = Key::fixed("work_hours:parse_edits");
+    /// Hash of note display strings, term -> JSON object of locale -> display string
+    pub const WORK_HOURS_GLOSSARY: Key = Key::fixed("work_hours:glossary");
+    /// Hash counting how often untranslated notes were shown, term -> count
+    pub const WORK_HOURS_GLOSSARY_MISSING: Key = Key::fixed("work_hours:glossary_missing");
+    /// List of parse failure ids, newest first
+    pub const WORK_HOURS_PARSE_FAILURES: Key = Key::fixed("work_hours:parse_failures");
+    /// Parse failures as JSON, followed by the failure id
+    pub const PARSE_FAILURES: Key = Key::fixed("parse_failures");
+    /// Pending schedule corrections as JSON, followed by the correction id
+    pub const WORK_HOURS_CORRECTIONS: Key = Key::fixed("work_hours:corrections");
+    /// Markers of decided corrections, followed by the correction id
+    pub const WORK_HOURS_CORRECTIONS_DECIDED: Key = Key::fixed("work_hours:corrections_decided");
+    /// List of audit records of schedule changes made in work_hours as JSON, newest first
+    pub const WORK_HOURS_AUDIT: Key = Key::fixed("work_hours:audit");
+    /// Sequence number of the latest audit record
+    pub const WORK_HOURS_AUDIT_SEQ: Key = Key::fixed("work_hours:audit_seq");
+    /// Hash of API key check results, provider -> JSON status
+    pub const WORK_HOURS_CREDENTIALS: Key = Key::fixed("work_hours:credentials");
+    /// How long day entries are kept, matching what uploads store
+    pub const DAY_ENTRY_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+    /// Key of the set of dates an employee has entries for
+    pub fn dates_key(employee: &EmployeeId) -> BotResult<Key> {
+        WORK_HOURS_DATES.segment(employee.slug())
+    }
+
+    /// Key of a single day entry
+    pub fn day_key(employee: &EmployeeId, date: &str) -> BotResult<Key> {
+        WORK_HOURS_DAY.segment(employee.slug())?.segment(date)
+    }
+
+    /// Key of a stored parse failure
+    pub fn parse_failure_key(id: &str) -> BotResult<Key> {
+        PARSE_FAILURES.segment(id)
+    }
+
+    /// Key of a pending schedule correction
+    pub fn correction_key(id: &str) -> BotResult<Key> {
+        WORK_HOURS_CORRECTIONS.segment(id)
+    }
+
+    /// Key marking a schedule correction as decided
+    pub fn correction_decided_key(id: &str) -> BotResult<Key> {
+        WORK_HOURS_CORRECTIONS_DECIDED.segment(id)
+    }
+
+    /// Field of an employee's date in the duplicates hash
+    pub fn duplicate_field(employee: &EmployeeId, date: &str) -> String {
+        format!("{}|{date}", employee.slug())
+    }
+
+    /// Split a duplicates hash field into the employee slug and date
+    pub fn parse_duplicate_field(field: &str) -> Option<(&str, &str)> {
+        field.rsplit_once('|')
+    }
+
+    /// Split a day key into the employees set member and date
+    pub fn parse_day_key(key: &str) -> Option<(&str, &str)> {
+        key.strip_prefix(WORK_HOURS_DAY.as_str())?
+            .strip_prefix(':')?
+            .split_once(':')
+    }
 }
 
 /// The Work Schedule actor that processes messages
 pub struct WorkScheduleActor {
     _config: Arc<RwLock<Config>>,
     redis_handle: RedisActorHandle,
-    command_rx: mpsc::Receiver<WorkScheduleCommand>,
+    bus: EventBus,
+    command_rx: SharedReceiver<WorkScheduleCommand>,
 }
 
 /// Commands that can be sent to the Work Schedule actor
 pub enum WorkScheduleCommand {
     GetEmployees(mpsc::Sender<BotResult<Vec<String>>>),
-    GetScheduleForEmployee(String, mpsc::Sender<BotResult<EmployeeSchedule>>),
-    GetScheduleForDate(
+    GetEmployeesCoverage(mpsc::Sender<BotResult<Vec<CoverageInfo>>>),
+    GetStoredEntriesForRange(
         String,
-        mpsc::Sender<BotResult<HashMap<String, WorkScheduleEntry>>>,
+        String,
+        mpsc::Sender<BotResult<Vec<EmployeeSchedule>>>,
     ),
+    GetScheduleForEmployee(String, mpsc::Sender<BotResult<EmployeeSchedule>>),
+    GetScheduleForDate(String, mpsc::Sender<BotResult<DaySchedules>>),
     GetScheduleForDateRange(
         String,
         String,
         String,
         mpsc::Sender<BotResult<EmployeeSchedule>>,
     ),
+    GetDuplicates(String, String, mpsc::Sender<BotResult<Vec<DuplicateShift>>>),
+    ResolveDuplicate(
+        String,
+        String,
+        KeepChoice,
+        Option<u64>,
+        mpsc::Sender<BotResult<WorkScheduleEntry>>,
+    ),
+    CorrectEntry(
+        String,
+        WorkScheduleEntry,
+        Option<u64>,
+        mpsc::Sender<BotResult<WorkScheduleEntry>>,
+    ),
+    SetContextLink(
+        String,
+        String,
+        Option<ContextLink>,
+        mpsc::Sender<BotResult<Option<WorkScheduleEntry>>>,
+    ),
+    SetActualHours(
+        String,
+        String,
+        Option<(String, String)>,
+        String,
+        mpsc::Sender<BotResult<Option<WorkScheduleEntry>>>,
+    ),
+    Reconcile(ReconcileMode, mpsc::Sender<BotResult<ReconcileReport>>),
     Shutdown,
 }
 
@@ -60,6 +189,42 @@ impl WorkScheduleActorHandle {
             .ok_or_else(|| work_schedule_error("Response channel closed"))?
     }
 
+    /// Get how far each employee's stored schedule reaches, sorted by employee
+    pub async fn get_employees_coverage(&self) -> BotResult<Vec<CoverageInfo>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::GetEmployeesCoverage(response_tx))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Get the entries every employee has stored in a date range, sorted by employee
+    pub async fn get_stored_entries_for_range(
+        &self,
+        start_date: impl Into<String>,
+        end_date: impl Into<String>,
+    ) -> BotResult<Vec<EmployeeSchedule>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::GetStoredEntriesForRange(
+                start_date.into(),
+                end_date.into(),
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
     /// Get schedule for a specific employee
     pub async fn get_schedule_for_employee(
         &self,
@@ -81,10 +246,7 @@ impl WorkScheduleActorHandle {
     }
 
     /// Get schedule for all employees on a specific date
-    pub async fn get_schedule_for_date(
-        &self,
-        date: impl Into<String>,
-    ) -> BotResult<HashMap<String, WorkScheduleEntry>> {
+    pub async fn get_schedule_for_date(&self, date: impl Into<String>) -> BotResult<DaySchedules> {
         let (response_tx, mut response_rx) = mpsc::channel(1);
         self.command_tx
             .send(WorkScheduleCommand::GetScheduleForDate(
@@ -124,6 +286,145 @@ impl WorkScheduleActorHandle {
             .ok_or_else(|| work_schedule_error("Response channel closed"))?
     }
 
+    /// Get duplicate entries stored for dates in a range
+    pub async fn get_duplicates(
+        &self,
+        start_date: impl Into<String>,
+        end_date: impl Into<String>,
+    ) -> BotResult<Vec<DuplicateShift>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::GetDuplicates(
+                start_date.into(),
+                end_date.into(),
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Resolve duplicates for an employee and date by keeping one of the entries.
+    /// `changed_by` is the Discord user shown in the change feed.
+    pub async fn resolve_duplicate(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        keep: KeepChoice,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::ResolveDuplicate(
+                employee.into(),
+                date.into(),
+                keep,
+                changed_by,
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Replace an employee's entry for the entry's date, e.g. with an approved correction.
+    /// `changed_by` is the Discord user shown in the change feed.
+    pub async fn correct_entry(
+        &self,
+        employee: impl Into<String>,
+        entry: WorkScheduleEntry,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::CorrectEntry(
+                employee.into(),
+                entry,
+                changed_by,
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Record the hours an employee actually worked on a stored day, or clear them with None.
+    /// `actor` is who recorded them, for the audit trail.
+    pub async fn set_actual_hours(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        actual: Option<(String, String)>,
+        actor: impl Into<String>,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::SetActualHours(
+                employee.into(),
+                date.into(),
+                actual,
+                actor.into(),
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Link a message to an employee's stored entry for a date, or remove the link with None
+    pub async fn set_context_link(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        link: Option<ContextLink>,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::SetContextLink(
+                employee.into(),
+                date.into(),
+                link,
+                response_tx,
+            ))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
+    /// Find day entries and dates sets that disagree, repairing them in repair mode
+    pub async fn reconcile(&self, mode: ReconcileMode) -> BotResult<ReconcileReport> {
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+        self.command_tx
+            .send(WorkScheduleCommand::Reconcile(mode, response_tx))
+            .await
+            .map_err(|e| work_schedule_error(&format!("Actor mailbox error: {e}")))?;
+
+        response_rx
+            .recv()
+            .await
+            .ok_or_else(|| work_schedule_error("Response channel closed"))?
+    }
+
     /// Shutdown the actor
     pub async fn shutdown(&self) -> BotResult<()> {
         let _ = self.command_tx.send(WorkScheduleCommand::Shutdown).await;
@@ -132,22 +433,40 @@ impl WorkScheduleActorHandle {
 }
 
 impl WorkScheduleActor {
-    /// Create a new actor and return its handle
-    pub fn new(
+    /// Spawn a supervised actor that is restarted if it panics, and return its handle
+    pub fn spawn_supervised(
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
-    ) -> (Self, WorkScheduleActorHandle) {
-        let (command_tx, command_rx) = mpsc::channel(32);
+        bus: EventBus,
+    ) -> (WorkScheduleActorHandle, JoinHandle<()>) {
+        let (command_tx, command_rx) = actor_channel(32);
+
+        let task = supervise("work_schedule", move || {
+            let mut actor = Self::with_receiver(
+                config.clone(),
+                redis_handle.clone(),
+                bus.clone(),
+                command_rx.clone(),
+            );
+            async move { actor.run().await }
+        });
 
-        let actor = Self {
+        (WorkScheduleActorHandle { command_tx }, task)
+    }
+
+    /// Create an actor serving an existing command channel, e.g. when restarting after a crash
+    pub fn with_receiver(
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+        bus: EventBus,
+        command_rx: SharedReceiver<WorkScheduleCommand>,
+    ) -> Self {
+        Self {
             _config: config,
             redis_handle,
+            bus,
             command_rx,
-        };
-
-        let handle = WorkScheduleActorHandle { command_tx };
-
-        (actor, handle)
+        }
     }
 
     /// Start the actor's processing loop
@@ -155,12 +474,29 @@ impl WorkScheduleActor {
         info!("Work Schedule actor started");
 
         // Process commands
-        while let Some(cmd) = self.command_rx.recv().await {
+        loop {
+            let Some(cmd) = self.command_rx.lock().await.recv().await else {
+                break;
+            };
             match cmd {
                 WorkScheduleCommand::GetEmployees(response_tx) => {
                     let result = self.get_employees_from_redis().await;
                     let _ = response_tx.send(result).await;
                 }
+                WorkScheduleCommand::GetEmployeesCoverage(response_tx) => {
+                    let result = self.get_employees_coverage().await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::GetStoredEntriesForRange(
+                    start_date,
+                    end_date,
+                    response_tx,
+                ) => {
+                    let result = self
+                        .get_stored_entries_for_range(&start_date, &end_date)
+                        .await;
+                    let _ = response_tx.send(result).await;
+                }
                 WorkScheduleCommand::GetScheduleForEmployee(employee, response_tx) => {
                     let result = self.get_schedule_for_employee(&employee).await;
                     let _ = response_tx.send(result).await;
@@ -180,6 +516,38 @@ impl WorkScheduleActor {
                         .await;
                     let _ = response_tx.send(result).await;
                 }
+                WorkScheduleCommand::GetDuplicates(start_date, end_date, response_tx) => {
+                    let result = self.get_duplicates(&start_date, &end_date).await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::ResolveDuplicate(
+                    employee,
+                    date,
+                    keep,
+                    changed_by,
+                    response_tx,
+                ) => {
+                    let result = self
+                        .resolve_duplicate(&employee, &date, keep, changed_by)
+                        .await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::CorrectEntry(employee, entry, changed_by, response_tx) => {
+                    let result = self.correct_entry(&employee, entry, changed_by).await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::SetContextLink(employee, date, link, response_tx) => {
+                    let result = self.set_context_link(&employee, &date, link).await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::SetActualHours(employee, date, actual, actor, response_tx) => {
+                    let result = self.set_actual_hours(&employee, &date, actual, actor).await;
+                    let _ = response_tx.send(result).await;
+                }
+                WorkScheduleCommand::Reconcile(mode, response_tx) => {
+                    let result = self.reconcile(mode).await;
+                    let _ = response_tx.send(result).await;
+                }
                 WorkScheduleCommand::Shutdown => {
                     info!("Work Schedule actor shutting down");
                     break;
@@ -192,37 +560,140 @@ impl WorkScheduleActor {
 
     /// Get all employees from Redis
     async fn get_employees_from_redis(&self) -> BotResult<Vec<String>> {
-        let mut custom_cmd = redis::cmd("SMEMBERS");
-        custom_cmd.arg(keys::WORK_HOURS_EMPLOYEES);
+        let ids = self.get_employee_ids().await?;
+        Ok(ids.into_iter().map(|id| id.display().to_string()).collect())
+    }
+
+    /// Get all employees as canonical ids, resolving slugs to their stored display names, in
+    /// name order
+    async fn get_employee_ids(&self) -> BotResult<Vec<EmployeeId>> {
+        let slugs: Vec<String> = self
+            .redis_handle
+            .smembers(&keys::WORK_HOURS_EMPLOYEES)
+            .await
+            .map_err(|e| work_schedule_error(&format!("Failed to get employees: {e}")))?;
 
-        let result: BotResult<Vec<String>> = self
+        let names: HashMap<String, String> = self
             .redis_handle
-            .run_command(custom_cmd)
+            .hgetall(&keys::WORK_HOURS_EMPLOYEE_NAMES)
             .await
-            .map_err(|e| work_schedule_error(&format!("Failed to get employees: {e}")));
+            .map_err(|e| work_schedule_error(&format!("Failed to get employee names: {e}")))?;
+
+        let mut ids: Vec<EmployeeId> = Vec::new();
+        for slug in slugs {
+            let id = EmployeeId::new(names.get(&slug).unwrap_or(&slug));
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
 
-        result
+        ids.sort_by(|a, b| compare_names(a.display(), b.display()));
+        Ok(ids)
     }
 
-    /// Get schedule for a specific employee
-    async fn get_schedule_for_employee(&self, employee: &str) -> BotResult<EmployeeSchedule> {
-        // First get all dates for this employee
-        let dates_key = format!("{}{}", keys::WORK_HOURS_DATES_PREFIX, employee);
+    /// Get the dates an employee has entries stored for
+    async fn get_stored_dates(&self, employee: &EmployeeId) -> BotResult<Vec<String>> {
+        self.redis_handle
+            .smembers(&keys::dates_key(employee)?)
+            .await
+            .map_err(|e| work_schedule_error(&format!("Failed to get dates: {e}")))
+    }
 
-        let mut custom_cmd = redis::cmd("SMEMBERS");
-        custom_cmd.arg(dates_key);
+    /// Read an employee's stored entries for the dates in one batch, skipping unreadable ones
+    async fn get_stored_entries(
+        &self,
+        employee: &EmployeeId,
+        dates: &[String],
+    ) -> BotResult<Vec<WorkScheduleEntry>> {
+        let day_keys = dates
+            .iter()
+            .map(|date| keys::day_key(employee, date))
+            .collect::<BotResult<Vec<Key>>>()?;
+        let stored: Vec<Option<String>> = self.redis_handle.mget(&day_keys).await.map_err(|e| {
+            work_schedule_error(&format!(
+                "Failed to get entries for {}: {e}",
+                Redacted(employee)
+            ))
+        })?;
+
+        Ok(stored
+            .iter()
+            .flatten()
+            .filter_map(|json| match parse_stored_entry(json) {
+                Ok((entry, _)) => Some(entry),
+                Err(e) => {
+                    warn!("Skipping unreadable entry of {}: {}", Redacted(employee), e);
+                    None
+                }
+            })
+            .collect())
+    }
 
-        let _dates: Vec<String> = self
+    /// Get every employee's coverage, reading each employee's entries in one batch
+    async fn get_employees_coverage(&self) -> BotResult<Vec<CoverageInfo>> {
+        let mut coverage = Vec::new();
+        for employee in self.get_employee_ids().await? {
+            let dates = self.get_stored_dates(&employee).await?;
+            let entries = self.get_stored_entries(&employee, &dates).await?;
+            coverage.push(CoverageInfo::new(
+                employee.display().to_string(),
+                &dates,
+                &entries,
+            ));
+        }
+
+        coverage.sort_by(|a, b| compare_names(&a.employee, &b.employee));
+        Ok(coverage)
+    }
+
+    /// Get the entries every employee has stored in a date range, reading each employee's
+    /// entries in one batch. Dates without an entry are left out.
+    async fn get_stored_entries_for_range(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> BotResult<Vec<EmployeeSchedule>> {
+        for date in [start_date, end_date] {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|e| work_schedule_error(&format!("Failed to parse date {date}: {e}")))?;
+        }
+
+        let mut schedules = Vec::new();
+        for employee in self.get_employee_ids().await? {
+            // Dates sort chronologically as strings
+            let mut dates: Vec<String> = self
+                .get_stored_dates(&employee)
+                .await?
+                .into_iter()
+                .filter(|date| date.as_str() >= start_date && date.as_str() <= end_date)
+                .collect();
+            dates.sort();
+            schedules.push(EmployeeSchedule {
+                employee: employee.display().to_string(),
+                schedule: self.get_stored_entries(&employee, &dates).await?,
+            });
+        }
+
+        schedules.sort_by(|a, b| compare_names(&a.employee, &b.employee));
+        Ok(schedules)
+    }
+
+    /// Resolve a user-provided name to the canonical id, using the stored display name if known
+    async fn resolve_employee(&self, employee: &str) -> EmployeeId {
+        let id = EmployeeId::new(employee);
+
+        match self
             .redis_handle
-            .run_command(custom_cmd)
+            .hget::<Option<String>>(&keys::WORK_HOURS_EMPLOYEE_NAMES, id.slug())
             .await
-            .map_err(|e| work_schedule_error(&format!("Failed to get dates: {e}")))?;
-
-        let _schedule = EmployeeSchedule {
-            employee: employee.to_string(),
-            schedule: Vec::new(),
-        };
+        {
+            Ok(Some(display)) => EmployeeId::new(&display),
+            _ => id,
+        }
+    }
 
+    /// Get schedule for a specific employee
+    async fn get_schedule_for_employee(&self, employee: &str) -> BotResult<EmployeeSchedule> {
         // Calculate the date range for this week (Monday to Sunday)
         let now = chrono::Local::now();
         let today = now.date_naive();
@@ -244,61 +715,390 @@ impl WorkScheduleActor {
             .await;
     }
 
-    /// Get a single schedule entry for employee and date
+    /// Get a single schedule entry for employee and date, if one is stored
     async fn get_entry_for_employee_date(
         &self,
-        employee: &str,
+        employee: &EmployeeId,
         date: &str,
-    ) -> BotResult<WorkScheduleEntry> {
-        let key = format!("{}{}{}{}", keys::WORK_HOURS_DAY_PREFIX, employee, ":", date);
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        let key = keys::day_key(employee, date)?;
 
-        let mut custom_cmd = redis::cmd("GET");
-        custom_cmd.arg(key);
-
-        let entry_json: Option<String> =
-            self.redis_handle
-                .run_command(custom_cmd)
-                .await
-                .map_err(|e| {
-                    work_schedule_error(&format!(
-                        "Failed to get entry for {employee} on {date}: {e}"
-                    ))
-                })?;
+        let entry_json: Option<String> = self.redis_handle.get(&key).await.map_err(|e| {
+            work_schedule_error(&format!(
+                "Failed to get entry for {} on {date}: {e}",
+                Redacted(employee)
+            ))
+        })?;
 
-        if let Some(json) = entry_json {
-            let entry: WorkScheduleEntry = serde_json::from_str(&json).map_err(|e| {
+        let entry = if let Some(json) = entry_json {
+            let (entry, outdated) = parse_stored_entry(&json).map_err(|e| {
                 work_schedule_error(&format!(
-                    "Failed to deserialize entry for {employee} on {date}: {e}"
+                    "Failed to deserialize entry for {} on {date}: {e}",
+                    Redacted(employee)
                 ))
             })?;
-            Ok(entry)
+            if outdated {
+                self.migrate_entry(&key, &entry).await;
+            }
+            entry
         } else {
-            // If no entry is found, create a default one
-            Ok(WorkScheduleEntry::new(date.to_string()))
+            return Ok(None);
+        };
+
+        // Merge duplicates the parser stored for this date, flagging anything suspicious
+        let entries = match self.get_duplicate_entries(employee, date).await {
+            Ok(Some(entries)) => entries,
+            Ok(None) => vec![entry],
+            Err(e) => {
+                warn!(
+                    "Failed to check duplicates for {} on {}: {}",
+                    Redacted(employee),
+                    date,
+                    e
+                );
+                vec![entry]
+            }
+        };
+
+        merge_entries(&entries).map(Some).ok_or_else(|| {
+            work_schedule_error(&format!("No entries for {} on {date}", Redacted(employee)))
+        })
+    }
+
+    /// Rewrite an entry read in an older format, keeping its expiry
+    async fn migrate_entry(&self, key: &Key, entry: &WorkScheduleEntry) {
+        let json = match serde_json::to_string(entry) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!(
+                    "Failed to serialize migrated entry for {}: {}",
+                    entry.date, e
+                );
+                return;
+            }
+        };
+
+        if let Err(e) = self.redis_handle.set_keep_ttl(key, json).await {
+            warn!("Failed to migrate entry for {}: {}", entry.date, e);
         }
     }
 
-    /// Get schedule for all employees on a specific date
-    async fn get_schedule_for_date(
+    /// Get the duplicate entries stored for an employee and date, if any
+    async fn get_duplicate_entries(
+        &self,
+        employee: &EmployeeId,
+        date: &str,
+    ) -> BotResult<Option<Vec<WorkScheduleEntry>>> {
+        let json: Option<String> = self
+            .redis_handle
+            .hget(
+                &keys::WORK_HOURS_DUPLICATES,
+                &keys::duplicate_field(employee, date),
+            )
+            .await?;
+        json.map(|json| {
+            serde_json::from_str(&json).map_err(|e| {
+                work_schedule_error(&format!(
+                    "Failed to deserialize duplicates for {} on {date}: {e}",
+                    Redacted(employee)
+                ))
+            })
+        })
+        .transpose()
+    }
+
+    /// Get all duplicate entries stored for dates in a range
+    async fn get_duplicates(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> BotResult<Vec<DuplicateShift>> {
+        let stored: HashMap<String, String> = self
+            .redis_handle
+            .hgetall(&keys::WORK_HOURS_DUPLICATES)
+            .await
+            .map_err(|e| work_schedule_error(&format!("Failed to get duplicates: {e}")))?;
+
+        let names: HashMap<String, String> = self
+            .redis_handle
+            .hgetall(&keys::WORK_HOURS_EMPLOYEE_NAMES)
+            .await
+            .map_err(|e| work_schedule_error(&format!("Failed to get employee names: {e}")))?;
+
+        let mut duplicates = Vec::new();
+        for (field, json) in stored {
+            let Some((slug, date)) = keys::parse_duplicate_field(&field) else {
+                continue;
+            };
+            // Dates are YYYY-MM-DD so they compare correctly as strings
+            if date < start_date || date > end_date {
+                continue;
+            }
+
+            let entries: Vec<WorkScheduleEntry> = match serde_json::from_str(&json) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!("Skipping unreadable duplicates for {}: {}", field, e);
+                    continue;
+                }
+            };
+
+            duplicates.push(DuplicateShift {
+                employee: names.get(slug).cloned().unwrap_or_else(|| slug.to_string()),
+                date: date.to_string(),
+                kind: duplicate_kind(&entries),
+                entries,
+            });
+        }
+
+        duplicates.sort_by(|a, b| (&a.date, &a.employee).cmp(&(&b.date, &b.employee)));
+        Ok(duplicates)
+    }
+
+    /// Replace duplicates for an employee and date with the chosen entry
+    async fn resolve_duplicate(
         &self,
+        employee: &str,
         date: &str,
-    ) -> BotResult<HashMap<String, WorkScheduleEntry>> {
-        let employees = self.get_employees_from_redis().await?;
-        let mut result = HashMap::new();
+        keep: KeepChoice,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        let employee = self.resolve_employee(employee).await;
+
+        let entries = self
+            .get_duplicate_entries(&employee, date)
+            .await?
+            .unwrap_or_default();
+        let mut entry = pick_entry(&entries, keep).ok_or_else(|| {
+            work_schedule_error(&format!(
+                "No duplicates found for {} on {date}",
+                Redacted(&employee)
+            ))
+        })?;
+
+        // The kept entry still comes from the upload the stored one was parsed from
+        let day_key = keys::day_key(&employee, date)?;
+        let stored: Option<String> = self.redis_handle.get(&day_key).await?;
+        entry.upload_id = stored
+            .and_then(|json| parse_stored_entry(&json).ok())
+            .and_then(|(stored, _)| stored.upload_id);
+
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| work_schedule_error(&format!("Failed to serialize entry: {e}")))?;
+
+        self.redis_handle.set_keep_ttl(&day_key, json).await?;
+        if let Some(upload_id) = &entry.upload_id {
+            // Only feeds the parse quality trend, so don't fail the edit over it
+            if let Err(e) = record_manual_edit(&self.redis_handle, upload_id).await {
+                warn!("Failed to count manual edit of upload {}: {}", upload_id, e);
+            }
+        }
+        self.redis_handle
+            .hdel(
+                &keys::WORK_HOURS_DUPLICATES,
+                &keys::duplicate_field(&employee, date),
+            )
+            .await?;
+
+        info!(
+            "Resolved duplicate entries for {} on {}",
+            Redacted(&employee),
+            date
+        );
+        self.bus.publish(ScheduleUpdated(
+            employee.display().to_string(),
+            vec![date.to_string()],
+        ));
+        self.bus.publish(ScheduleChanged {
+            employee: employee.display().to_string(),
+            date: date.to_string(),
+            before: entries
+                .iter()
+                .map(WorkScheduleEntry::format)
+                .collect::<Vec<_>>()
+                .join(" / "),
+            after: entry.format(),
+            changed_by,
+        });
+        Ok(entry)
+    }
+
+    /// Replace an employee's entry for the entry's date, keeping the upload it came from
+    async fn correct_entry(
+        &self,
+        employee: &str,
+        mut entry: WorkScheduleEntry,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        let employee = self.resolve_employee(employee).await;
+        let known = self
+            .redis_handle
+            .sismember(&keys::WORK_HOURS_EMPLOYEES, employee.slug())
+            .await?;
+        if !known {
+            return Err(work_schedule_error(&format!(
+                "No schedule stored for {}",
+                Redacted(&employee)
+            )));
+        }
+
+        let date = entry.date.clone();
+        let day_key = keys::day_key(&employee, &date)?;
+        let stored = self
+            .redis_handle
+            .get::<Option<String>>(&day_key)
+            .await?
+            .and_then(|json| parse_stored_entry(&json).ok())
+            .map(|(stored, _)| stored);
+        entry.upload_id = stored.as_ref().and_then(|stored| stored.upload_id.clone());
+
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| work_schedule_error(&format!("Failed to serialize entry: {e}")))?;
+        if stored.is_some() {
+            self.redis_handle.set_keep_ttl(&day_key, json).await?;
+        } else {
+            self.redis_handle.set(&day_key, json).await?;
+            self.redis_handle
+                .expire(&day_key, keys::DAY_ENTRY_TTL_SECS)
+                .await?;
+            self.redis_handle
+                .sadd(&keys::dates_key(&employee)?, &date)
+                .await?;
+        }
+        if let Some(upload_id) = &entry.upload_id {
+            // Only feeds the parse quality trend, so don't fail the edit over it
+            if let Err(e) = record_manual_edit(&self.redis_handle, upload_id).await {
+                warn!("Failed to count manual edit of upload {}: {}", upload_id, e);
+            }
+        }
+
+        info!("Corrected entry for {} on {}", Redacted(&employee), date);
+        self.bus.publish(ScheduleUpdated(
+            employee.display().to_string(),
+            vec![date.clone()],
+        ));
+        self.bus.publish(ScheduleChanged {
+            employee: employee.display().to_string(),
+            before: stored
+                .unwrap_or_else(|| WorkScheduleEntry::new(date.clone()))
+                .format(),
+            date,
+            after: entry.format(),
+            changed_by,
+        });
+        Ok(entry)
+    }
+
+    /// Set or clear the context link of a stored entry, returning None if there's no entry.
+    /// Only the link changes, so the change feed and the parse quality counts are left alone.
+    async fn set_context_link(
+        &self,
+        employee: &str,
+        date: &str,
+        link: Option<ContextLink>,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        let employee = self.resolve_employee(employee).await;
+        let day_key = keys::day_key(&employee, date)?;
+        let Some(json) = self.redis_handle.get::<Option<String>>(&day_key).await? else {
+            return Ok(None);
+        };
+        let (mut entry, _) = parse_stored_entry(&json)
+            .map_err(|e| work_schedule_error(&format!("Failed to parse entry: {e}")))?;
+        entry.context_link = link;
+
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| work_schedule_error(&format!("Failed to serialize entry: {e}")))?;
+        self.redis_handle.set_keep_ttl(&day_key, json).await?;
+
+        info!(
+            "Set context link of {} on {}: {}",
+            Redacted(&employee),
+            date,
+            entry.context_link.is_some()
+        );
+        self.bus.publish(ScheduleUpdated(
+            employee.display().to_string(),
+            vec![date.to_string()],
+        ));
+        Ok(Some(entry))
+    }
+
+    /// Set or clear the actual hours of a stored entry, returning None if there's no entry.
+    /// The change goes to the audit trail; the schedule itself looks the same, so it doesn't
+    /// show in the change feed.
+    async fn set_actual_hours(
+        &self,
+        employee: &str,
+        date: &str,
+        actual: Option<(String, String)>,
+        actor: String,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        let employee = self.resolve_employee(employee).await;
+        let day_key = keys::day_key(&employee, date)?;
+        let Some(json) = self.redis_handle.get::<Option<String>>(&day_key).await? else {
+            return Ok(None);
+        };
+        let (before, _) = parse_stored_entry(&json)
+            .map_err(|e| work_schedule_error(&format!("Failed to parse entry: {e}")))?;
+        let mut entry = before.clone();
+        (entry.actual_start, entry.actual_end) = actual.unzip();
+
+        let json = serde_json::to_string(&entry)
+            .map_err(|e| work_schedule_error(&format!("Failed to serialize entry: {e}")))?;
+        self.redis_handle.set_keep_ttl(&day_key, json).await?;
+        record_audit(
+            &self.redis_handle,
+            AuditRecord {
+                seq: 0,
+                at: chrono::Utc::now().timestamp(),
+                employee: employee.display().to_string(),
+                date: date.to_string(),
+                before: Some(before),
+                after: Some(entry.clone()),
+                source: ACTUALS_AUDIT_SOURCE.to_string(),
+                actor: Some(actor),
+            },
+        )
+        .await?;
+
+        info!(
+            "Set actual hours of {} on {}: {}",
+            Redacted(&employee),
+            date,
+            entry.actual_start.is_some()
+        );
+        self.bus.publish(ScheduleUpdated(
+            employee.display().to_string(),
+            vec![date.to_string()],
+        ));
+        Ok(Some(entry))
+    }
+
+    /// Get schedule for all employees on a specific date
+    async fn get_schedule_for_date(&self, date: &str) -> BotResult<DaySchedules> {
+        let employees = self.get_employee_ids().await?;
+        let mut result = Vec::new();
+        let mut missing = Vec::new();
 
         for employee in employees {
             match self.get_entry_for_employee_date(&employee, date).await {
-                Ok(entry) => {
-                    result.insert(employee, entry);
+                Ok(Some(entry)) => {
+                    result.push((employee.display().to_string(), entry));
                 }
+                Ok(None) => missing.push(employee.display().to_string()),
                 Err(e) => {
-                    error!("Failed to get entry for {} on {}: {}", employee, date, e);
+                    error!(
+                        "Failed to get entry for {} on {}: {}",
+                        Redacted(&employee),
+                        date,
+                        e
+                    );
                     // Continue with the next employee
                 }
             }
         }
 
-        Ok(result)
+        Ok(DaySchedules::from_iter(result).with_missing(missing))
     }
 
     /// Get schedule for an employee in a date range
@@ -317,22 +1117,19 @@ impl WorkScheduleActor {
             work_schedule_error(&format!("Failed to parse end date {end_date}: {e}"))
         })?;
 
-        // Get all dates for this employee
-        let dates_key = format!("{}{}", keys::WORK_HOURS_DATES_PREFIX, employee);
-
-        let mut custom_cmd = redis::cmd("SMEMBERS");
-        custom_cmd.arg(dates_key);
+        let employee = self.resolve_employee(employee).await;
 
+        // Get all dates for this employee
         let all_dates: HashSet<String> = self
             .redis_handle
-            .run_command::<Vec<String>>(custom_cmd)
+            .smembers::<Vec<String>>(&keys::dates_key(&employee)?)
             .await
             .map_err(|e| work_schedule_error(&format!("Failed to get dates: {e}")))?
             .into_iter()
             .collect();
 
         let mut schedule = EmployeeSchedule {
-            employee: employee.to_string(),
+            employee: employee.display().to_string(),
             schedule: Vec::new(),
         };
 
@@ -343,14 +1140,18 @@ impl WorkScheduleActor {
 
             // If the date exists in Redis, get the entry
             if all_dates.contains(&date_str) {
-                match self.get_entry_for_employee_date(employee, &date_str).await {
+                match self.get_entry_for_employee_date(&employee, &date_str).await {
                     Ok(entry) => {
-                        schedule.schedule.push(entry);
+                        schedule
+                            .schedule
+                            .push(entry.unwrap_or_else(|| WorkScheduleEntry::new(date_str)));
                     }
                     Err(e) => {
                         error!(
                             "Failed to get entry for {} on {}: {}",
-                            employee, date_str, e
+                            Redacted(&employee),
+                            date_str,
+                            e
                         );
                     }
                 }
@@ -365,4 +1166,144 @@ impl WorkScheduleActor {
 
         Ok(schedule)
     }
+
+    /// Compare every dates set with the day keys that exist, reading each employee's entries
+    /// in one batch and walking the day keys with SCAN. Repairs in repair mode.
+    async fn reconcile(&self, mode: ReconcileMode) -> BotResult<ReconcileReport> {
+        let members: Vec<String> = self
+            .redis_handle
+            .smembers(&keys::WORK_HOURS_EMPLOYEES)
+            .await
+            .map_err(|e| work_schedule_error(&format!("Failed to get employees: {e}")))?;
+
+        let mut report = ReconcileReport {
+            mode,
+            ..Default::default()
+        };
+
+        // Dates listed for each employees set member, and the members with a stored entry
+        let mut listed: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut stored: HashSet<String> = HashSet::new();
+        for member in members {
+            let (Ok(dates_key), Ok(day_prefix)) = (
+                keys::WORK_HOURS_DATES.segment(&member),
+                keys::WORK_HOURS_DAY.segment(&member),
+            ) else {
+                warn!("Skipping unusable employee {}", Redacted(&member));
+                continue;
+            };
+            let dates: Vec<String> = self
+                .redis_handle
+                .smembers(&dates_key)
+                .await
+                .map_err(|e| work_schedule_error(&format!("Failed to get dates: {e}")))?;
+            let (dates, day_keys): (Vec<&String>, Vec<Key>) = dates
+                .iter()
+                .filter_map(|date| Some((date, day_prefix.clone().segment(date).ok()?)))
+                .unzip();
+            let entries: Vec<Option<String>> =
+                self.redis_handle.mget(&day_keys).await.map_err(|e| {
+                    work_schedule_error(&format!(
+                        "Failed to get entries for {}: {e}",
+                        Redacted(&member)
+                    ))
+                })?;
+
+            for (date, entry) in dates.iter().zip(entries) {
+                if entry.is_some() {
+                    stored.insert(member.clone());
+                } else {
+                    report
+                        .missing_entries
+                        .push((member.clone(), date.to_string()));
+                }
+            }
+            listed.insert(member, dates.into_iter().cloned().collect());
+        }
+
+        for key in self.redis_handle.scan_prefix(&keys::WORK_HOURS_DAY).await? {
+            let Some((member, date)) = keys::parse_day_key(&key) else {
+                continue;
+            };
+            let entry = (member.to_string(), date.to_string());
+            match listed.get(member) {
+                Some(dates) if dates.contains(date) => {}
+                Some(_) => {
+                    stored.insert(entry.0.clone());
+                    report.unindexed_entries.push(entry);
+                }
+                None => report.orphaned_entries.push(entry),
+            }
+        }
+
+        report.empty_employees = listed
+            .into_keys()
+            .filter(|member| !stored.contains(member))
+            .collect();
+        report.sort();
+
+        if mode == ReconcileMode::Repair {
+            self.repair(&report).await?;
+        }
+        Ok(report)
+    }
+
+    /// Fix what a reconciliation found: list unindexed entries, unlist missing ones, delete
+    /// orphaned entries and forget employees without entries. One command per kind and
+    /// employee.
+    async fn repair(&self, report: &ReconcileReport) -> BotResult<()> {
+        let by_member = |entries: &[(String, String)]| {
+            let mut dates: HashMap<String, Vec<String>> = HashMap::new();
+            for (member, date) in entries {
+                dates.entry(member.clone()).or_default().push(date.clone());
+            }
+            dates
+        };
+
+        let names: HashMap<String, String> = self
+            .redis_handle
+            .hgetall(&keys::WORK_HOURS_EMPLOYEE_NAMES)
+            .await?;
+        for (member, dates) in by_member(&report.unindexed_entries) {
+            self.redis_handle
+                .sadd(&keys::WORK_HOURS_DATES.segment(&member)?, &dates)
+                .await?;
+            // Range reads see these entries again
+            let employee = EmployeeId::new(names.get(&member).unwrap_or(&member));
+            self.bus
+                .publish(ScheduleUpdated(employee.display().to_string(), dates));
+        }
+        for (member, dates) in by_member(&report.missing_entries) {
+            self.redis_handle
+                .srem(&keys::WORK_HOURS_DATES.segment(&member)?, &dates)
+                .await?;
+        }
+
+        let orphaned = report
+            .orphaned_entries
+            .iter()
+            .map(|(member, date)| keys::WORK_HOURS_DAY.segment(member)?.segment(date))
+            .collect::<BotResult<Vec<Key>>>()?;
+        self.redis_handle.del_many(&orphaned).await?;
+
+        if !report.empty_employees.is_empty() {
+            self.redis_handle
+                .srem(&keys::WORK_HOURS_EMPLOYEES, &report.empty_employees)
+                .await?;
+            for member in &report.empty_employees {
+                self.redis_handle
+                    .hdel(&keys::WORK_HOURS_EMPLOYEE_NAMES, member)
+                    .await?;
+            }
+        }
+
+        info!(
+            "Repaired {} unindexed, {} missing and {} orphaned entries and {} empty employees",
+            report.unindexed_entries.len(),
+            report.missing_entries.len(),
+            report.orphaned_entries.len(),
+            report.empty_employees.len()
+        );
+        Ok(())
+    }
 }
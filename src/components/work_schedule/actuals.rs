@@ -0,0 +1,123 @@
+//! Hours actually worked, recorded next to the published schedule once a day has passed.
+//!
+//! Payroll compares them with the planned shifts, so they can only be set for recent past
+//! days: today's hours aren't known yet, and older ones should already have been paid.
+
+use crate::components::work_schedule::corrections::correction_time;
+use chrono::NaiveDate;
+use std::fmt;
+
+/// Audit record source of recorded actual hours
+pub const ACTUALS_AUDIT_SOURCE: &str = "actual_hours";
+
+/// How many days back actual hours can still be recorded
+pub const MAX_ACTUALS_AGE_DAYS: i64 = 14;
+
+/// Why actual hours can't be recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActualsRefusal {
+    /// The day is today or still ahead
+    NotPast,
+    /// The day is more than [`MAX_ACTUALS_AGE_DAYS`] back
+    TooOld,
+    /// The range couldn't be read, or it ends before it starts
+    InvalidRange(String),
+}
+
+impl fmt::Display for ActualsRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotPast => write!(f, "actual hours can only be recorded for past days"),
+            Self::TooOld => write!(
+                f,
+                "actual hours can only be recorded for the last {MAX_ACTUALS_AGE_DAYS} days"
+            ),
+            Self::InvalidRange(range) => {
+                write!(
+                    f,
+                    "invalid range \"{range}\", expected e.g. 9-17 or 08:00-16:30"
+                )
+            }
+        }
+    }
+}
+
+/// Check that actual hours can be recorded for `date` on `today`
+pub fn check_actuals_date(date: NaiveDate, today: NaiveDate) -> Result<(), ActualsRefusal> {
+    let age = (today - date).num_days();
+    if age < 1 {
+        Err(ActualsRefusal::NotPast)
+    } else if age > MAX_ACTUALS_AGE_DAYS {
+        Err(ActualsRefusal::TooOld)
+    } else {
+        Ok(())
+    }
+}
+
+/// Read the actual start and end as HH:MM, accepting times like "9", "9.30" or "09:30"
+pub fn parse_actual_times(start: &str, end: &str) -> Result<(String, String), ActualsRefusal> {
+    let invalid = || ActualsRefusal::InvalidRange(format!("{}-{}", start.trim(), end.trim()));
+    let (start, end) = (
+        correction_time(start).ok_or_else(invalid)?,
+        correction_time(end).ok_or_else(invalid)?,
+    );
+    // HH:MM compares in time order
+    if end <= start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+/// Read a typed range like "9-17" or "08:00-16:30"
+pub fn parse_actual_range(range: &str) -> Result<(String, String), ActualsRefusal> {
+    let (start, end) = range
+        .split_once('-')
+        .ok_or_else(|| ActualsRefusal::InvalidRange(range.trim().to_string()))?;
+    parse_actual_times(start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actuals_only_for_recent_past_days() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 20).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        assert_eq!(
+            check_actuals_date(day(21), today),
+            Err(ActualsRefusal::NotPast)
+        );
+        assert_eq!(
+            check_actuals_date(today, today),
+            Err(ActualsRefusal::NotPast)
+        );
+        assert_eq!(check_actuals_date(day(19), today), Ok(()));
+        assert_eq!(check_actuals_date(day(6), today), Ok(()));
+        assert_eq!(
+            check_actuals_date(day(5), today),
+            Err(ActualsRefusal::TooOld)
+        );
+    }
+
+    #[test]
+    fn test_actual_ranges() {
+        assert_eq!(
+            parse_actual_range("9-17.30"),
+            Ok(("09:00".to_string(), "17:30".to_string()))
+        );
+        assert_eq!(
+            parse_actual_range(" 08:00 - 16:15 "),
+            Ok(("08:00".to_string(), "16:15".to_string()))
+        );
+        for invalid in ["9", "17-9", "9-9", "aamu-ilta", "9-25"] {
+            assert!(
+                matches!(
+                    parse_actual_range(invalid),
+                    Err(ActualsRefusal::InvalidRange(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+}
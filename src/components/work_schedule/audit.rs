@@ -0,0 +1,235 @@
+//! Audit trail of schedule changes made through work_hours, relayed to the bot's change feed.
+//!
+//! work_hours runs in its own process, so it can't publish on the bot's event bus. It pushes
+//! numbered records to a capped Redis list instead, and the bot polls the list for records
+//! newer than the last one it saw. The bot writes records of its own for changes payroll
+//! needs a trail of, such as recorded actual hours.
+
+use crate::components::event_bus::{EventBus, ScheduleChanged, ScheduleUpdated};
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::{WORK_HOURS_AUDIT, WORK_HOURS_AUDIT_SEQ};
+use crate::components::work_schedule::models::WorkScheduleEntry;
+use crate::error::{other_error, BotResult};
+use crate::utils::redact::Redacted;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Audit records kept; older ones are dropped
+pub const MAX_AUDIT_RECORDS: usize = 1000;
+
+/// How often the bot checks for new audit records
+const AUDIT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A change to one of an employee's stored days
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Number of the record, increasing with every record written
+    pub seq: u64,
+    /// Unix timestamp of the change
+    pub at: i64,
+    /// Display name of the employee
+    pub employee: String,
+    /// Date of the changed day (YYYY-MM-DD)
+    pub date: String,
+    /// The day before the change, None if it was added
+    pub before: Option<WorkScheduleEntry>,
+    /// The day after the change, None if it was removed
+    pub after: Option<WorkScheduleEntry>,
+    /// What made the change, e.g. "json_import"
+    pub source: String,
+    /// Who made the change, e.g. the admin's username
+    pub actor: Option<String>,
+}
+
+impl AuditRecord {
+    /// The change as the change feed shows it, None when the day looks the same as before,
+    /// e.g. when only its actual hours were recorded
+    pub fn change(&self) -> Option<ScheduleChanged> {
+        let format = |entry: &Option<WorkScheduleEntry>| {
+            entry
+                .clone()
+                .unwrap_or_else(|| WorkScheduleEntry::new(self.date.clone()))
+                .format()
+        };
+        let (before, after) = (format(&self.before), format(&self.after));
+        (before != after).then(|| ScheduleChanged {
+            employee: self.employee.clone(),
+            date: self.date.clone(),
+            before,
+            after,
+            changed_by: None,
+        })
+    }
+}
+
+/// Number a record and push it to the audit list, dropping the oldest records over the cap
+pub async fn record_audit(
+    redis_handle: &RedisActorHandle,
+    mut record: AuditRecord,
+) -> BotResult<()> {
+    record.seq = redis_handle.incr(&WORK_HOURS_AUDIT_SEQ).await?;
+    let json = serde_json::to_string(&record)
+        .map_err(|e| other_error(&format!("Failed to serialize audit record: {e}")))?;
+    redis_handle.lpush(&WORK_HOURS_AUDIT, json).await?;
+    redis_handle
+        .ltrim(&WORK_HOURS_AUDIT, 0, MAX_AUDIT_RECORDS as isize - 1)
+        .await
+}
+
+/// Load the audit records numbered after `after_seq`, oldest first, skipping unreadable ones
+pub async fn load_audit_records(
+    redis_handle: &RedisActorHandle,
+    after_seq: u64,
+) -> BotResult<Vec<AuditRecord>> {
+    let stored: Vec<String> = redis_handle
+        .lrange(&WORK_HOURS_AUDIT, 0, MAX_AUDIT_RECORDS as isize - 1)
+        .await?;
+    let mut records: Vec<AuditRecord> = stored
+        .iter()
+        .filter_map(|json| match serde_json::from_str::<AuditRecord>(json) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn!("Ignoring invalid audit record: {}", e);
+                None
+            }
+        })
+        .filter(|record| record.seq > after_seq)
+        .collect();
+    records.sort_by_key(|record| record.seq);
+    Ok(records)
+}
+
+/// Publish audit records on the bus as the schedule changes they are
+pub fn relay_audit_records(bus: &EventBus, records: &[AuditRecord]) {
+    for record in records {
+        info!(
+            "Relaying {} change of {} on {}",
+            record.source,
+            Redacted(&record.employee),
+            record.date
+        );
+        bus.publish(ScheduleUpdated(
+            record.employee.clone(),
+            vec![record.date.clone()],
+        ));
+        if let Some(change) = record.change() {
+            bus.publish(change);
+        }
+    }
+}
+
+/// Start the task relaying new audit records to the bus. Records written before the task
+/// started aren't relayed.
+pub fn spawn_audit_relay(redis_handle: RedisActorHandle, bus: EventBus) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_seq: Option<u64> = None;
+        let mut interval = tokio::time::interval(AUDIT_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(after_seq) = last_seq else {
+                match redis_handle.get::<Option<u64>>(&WORK_HOURS_AUDIT_SEQ).await {
+                    Ok(seq) => last_seq = Some(seq.unwrap_or(0)),
+                    Err(e) => warn!("Failed to read the audit sequence: {}", e),
+                }
+                continue;
+            };
+
+            match load_audit_records(&redis_handle, after_seq).await {
+                Ok(records) => {
+                    if let Some(last) = records.last() {
+                        last_seq = Some(last.seq);
+                    }
+                    relay_audit_records(&bus, &records);
+                }
+                Err(e) => warn!("Failed to load audit records: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::ShiftRange;
+
+    fn record(seq: u64, after: Option<WorkScheduleEntry>) -> AuditRecord {
+        AuditRecord {
+            seq,
+            at: 1_741_600_000,
+            employee: "Anna".to_string(),
+            date: "2025-03-10".to_string(),
+            before: Some(WorkScheduleEntry {
+                shifts: vec![ShiftRange::new("08:00", "16:00")],
+                ..WorkScheduleEntry::new("2025-03-10".to_string())
+            }),
+            after,
+            source: "json_import".to_string(),
+            actor: Some("admin".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_records_are_relayed_in_order() {
+        let redis_handle = RedisActorHandle::fake();
+        for seq in 1..=3 {
+            let after = (seq != 3).then(|| WorkScheduleEntry {
+                is_day_off: true,
+                ..WorkScheduleEntry::new("2025-03-10".to_string())
+            });
+            let json = serde_json::to_string(&record(seq, after)).unwrap();
+            redis_handle.lpush(&WORK_HOURS_AUDIT, json).await.unwrap();
+        }
+
+        let records = load_audit_records(&redis_handle, 1).await.unwrap();
+        assert_eq!(
+            records.iter().map(|record| record.seq).collect::<Vec<_>>(),
+            [2, 3]
+        );
+
+        let bus = EventBus::new();
+        let mut changes = bus.subscribe::<ScheduleChanged>();
+        relay_audit_records(&bus, &records);
+        let change = changes.recv().await.unwrap();
+        assert_eq!(change.employee, "Anna");
+        assert_eq!(change.before, "08:00–16:00");
+        assert_eq!(change.after, "Day off");
+        // A removed day shows as having nothing stored
+        let removed = changes.recv().await.unwrap();
+        assert_eq!(
+            removed.after,
+            WorkScheduleEntry::new(String::new()).format()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorded_actuals_are_numbered_but_not_in_the_feed() {
+        let redis_handle = RedisActorHandle::fake();
+        redis_handle.set(&WORK_HOURS_AUDIT_SEQ, 4).await.unwrap();
+        let before = record(0, None).before;
+        let after = before.clone().map(|entry| WorkScheduleEntry {
+            actual_start: Some("08:00".to_string()),
+            actual_end: Some("16:30".to_string()),
+            ..entry
+        });
+        let actuals = AuditRecord {
+            after,
+            source: "toteuma".to_string(),
+            ..record(0, None)
+        };
+        record_audit(&redis_handle, actuals).await.unwrap();
+
+        let records = load_audit_records(&redis_handle, 4).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].seq, 5);
+        assert_eq!(records[0].change(), None);
+
+        let bus = EventBus::new();
+        let mut updates = bus.subscribe::<ScheduleUpdated>();
+        let mut changes = bus.subscribe::<ScheduleChanged>();
+        relay_audit_records(&bus, &records);
+        assert!(updates.try_recv().is_ok());
+        assert!(changes.try_recv().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::components::event_bus::{EventBus, ScheduleChanged};
+use crate::config::Config;
+use crate::utils::discord::{Batched, LineBatcher};
+use crate::utils::embed::{truncate, DESCRIPTION_LIMIT};
+use crate::utils::notifier::{DiscordNotifier, Notification, Notifier};
+use crate::utils::redact::Redacted;
+use crate::utils::scheduler::SharedContext;
+use poise::serenity_prelude::CreateEmbed;
+use rust_i18n::t;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Color of the change feed messages
+const CHANGE_COLOR: u32 = 0xFF_A5_00;
+
+/// One line describing a change, e.g. "✏️ Anna 2025-07-03: 08:00–16:00 → Day off (by @user)"
+pub fn change_line(change: &ScheduleChanged) -> String {
+    let line = t!(
+        "schedule_change_line",
+        employee = change.employee,
+        date = change.date,
+        before = change.before,
+        after = change.after
+    );
+    match change.changed_by {
+        Some(user_id) => format!(
+            "{line} {}",
+            t!("schedule_change_by", user = format!("<@{user_id}>"))
+        ),
+        None => line.to_string(),
+    }
+}
+
+/// The message posting a line or a summary of collapsed lines
+pub fn batch_notification(batched: Batched) -> Notification {
+    let embed = match batched {
+        Batched::Line(line) => CreateEmbed::new().description(line),
+        Batched::Summary(lines) => CreateEmbed::new()
+            .title(t!("schedule_changes_summary_title", count = lines.len()))
+            .description(truncate(&lines.join("\n"), DESCRIPTION_LIMIT)),
+    };
+
+    Notification {
+        content: None,
+        embed: embed.color(CHANGE_COLOR),
+    }
+}
+
+/// Post a message to the change feed channel if one is configured
+async fn post(ctx: &SharedContext, config: &RwLock<Config>, batched: Batched) {
+    let Some(channel_id) = config.read().await.schedule_changes_channel_id else {
+        return;
+    };
+
+    let notifier = DiscordNotifier::from_http(Arc::clone(&ctx.current().await.http));
+    if let Err(e) = notifier.send(channel_id, batch_notification(batched)).await {
+        warn!("Failed to post schedule change: {}", e);
+    }
+}
+
+/// Start the task posting schedule changes to the change feed channel.
+///
+/// Changes are dropped while no channel is configured.
+pub fn spawn_change_feed(
+    ctx: SharedContext,
+    config: Arc<RwLock<Config>>,
+    bus: &EventBus,
+) -> JoinHandle<()> {
+    let mut changes = bus.subscribe::<ScheduleChanged>();
+
+    tokio::spawn(async move {
+        info!("Schedule change feed started");
+        let mut batcher = LineBatcher::new();
+        loop {
+            let due_at = batcher.due_at();
+            let flush = async move {
+                match due_at {
+                    Some(at) => tokio::time::sleep_until(at.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                change = changes.recv() => match change {
+                    Ok(change) => {
+                        if config.read().await.schedule_changes_channel_id.is_none() {
+                            continue;
+                        }
+                        info!(
+                            "Posting change of {} on {}",
+                            Redacted(&change.employee),
+                            change.date
+                        );
+                        if let Some(batched) = batcher.push(change_line(&change), Instant::now()) {
+                            post(&ctx, &config, batched).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Schedule change feed missed {} changes", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = flush => {
+                    if let Some(batched) = batcher.flush(Instant::now()) {
+                        post(&ctx, &config, batched).await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::embed::render_embed;
+
+    fn change(changed_by: Option<u64>) -> ScheduleChanged {
+        ScheduleChanged {
+            employee: "Anna".to_string(),
+            date: "2025-07-03".to_string(),
+            before: "08:00–16:00 / Day off".to_string(),
+            after: "Day off".to_string(),
+            changed_by,
+        }
+    }
+
+    #[test]
+    fn test_change_line() {
+        assert_eq!(
+            change_line(&change(Some(42))),
+            "✏️ Anna 2025-07-03: 08:00–16:00 / Day off → Day off (by <@42>)"
+        );
+        assert_eq!(
+            change_line(&change(None)),
+            "✏️ Anna 2025-07-03: 08:00–16:00 / Day off → Day off"
+        );
+    }
+
+    #[test]
+    fn test_summary_snapshot() {
+        let lines = vec![change_line(&change(None)), change_line(&change(Some(42)))];
+        let expected = "\
+# 2 schedule changes
+✏️ Anna 2025-07-03: 08:00–16:00 / Day off → Day off
+✏️ Anna 2025-07-03: 08:00–16:00 / Day off → Day off (by <@42>)
+";
+        assert_eq!(
+            render_embed(&batch_notification(Batched::Summary(lines)).embed),
+            expected
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Schedule corrections suggested by employees and approved by admins.
+//!
+//! An employee linked to a schedule suggests a new value for one of its days. The suggestion
+//! waits in Redis for a week while admins approve or reject it from the error channel; the
+//! first decision claims it, so two admins pressing at once don't both apply it.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::actor::keys;
+use crate::components::work_schedule::employee::EmployeeId;
+use crate::components::work_schedule::models::{ShiftRange, WorkScheduleEntry, VACATION_CODES};
+use crate::error::{other_error, BotResult};
+use crate::utils::time::parse_time;
+use serde::{Deserialize, Serialize};
+
+/// How long a suggested correction waits for an admin
+pub const CORRECTION_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Longest value a correction may set, as typed
+pub const MAX_CORRECTION_VALUE_LENGTH: usize = 100;
+
+/// Prefix of the decision button ids, followed by the action and correction id
+pub const BUTTON_PREFIX: &str = "correction";
+
+/// Cell values meaning a day off
+const DAY_OFF_VALUES: [&str; 4] = ["x", "vapaa", "off", "day off"];
+
+/// A correction an employee suggested for a day of their schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Correction {
+    pub id: String,
+    pub employee: String,
+    /// Date corrected, YYYY-MM-DD
+    pub date: String,
+    /// The new value as the employee typed it
+    pub value: String,
+    /// The entry the value becomes
+    pub entry: WorkScheduleEntry,
+    /// Discord user who suggested the correction
+    pub requested_by: u64,
+    /// Unix timestamp of the suggestion
+    pub requested_at: i64,
+}
+
+/// Why a user can't suggest a correction for an employee
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorrectionRefusal {
+    /// The user hasn't linked themselves to an employee
+    NotLinked,
+    /// The user is linked to someone else, named here
+    OtherEmployee(String),
+}
+
+/// What an admin decided to do with a correction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorrectionAction {
+    Approve,
+    Reject,
+}
+
+impl CorrectionAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+/// What claiming a correction for a decision found
+#[derive(Debug, Clone, PartialEq)]
+pub enum CorrectionClaim {
+    /// The correction is now this caller's to decide
+    Claimed(Box<Correction>),
+    /// Another admin already decided it
+    Decided,
+    /// It expired, or never existed
+    Expired,
+}
+
+/// Check that a user linked to `linked` may suggest corrections to `employee`'s schedule.
+/// Names are compared the way storage keys are, ignoring case and diacritics.
+pub fn check_requester(linked: Option<&str>, employee: &str) -> Result<(), CorrectionRefusal> {
+    let Some(linked) = linked else {
+        return Err(CorrectionRefusal::NotLinked);
+    };
+    if EmployeeId::new(linked) == EmployeeId::new(employee) {
+        Ok(())
+    } else {
+        Err(CorrectionRefusal::OtherEmployee(linked.to_string()))
+    }
+}
+
+/// Read a typed time like "9", "9.30" or "09:30" as HH:MM
+pub(crate) fn correction_time(time: &str) -> Option<String> {
+    let time = time.trim().replace('.', ":");
+    let time = if time.contains(':') {
+        time
+    } else {
+        format!("{time}:00")
+    };
+    parse_time(&time)
+        .ok()
+        .map(|time| time.time().format("%H:%M").to_string())
+}
+
+/// Turn the value a user typed into the entry for `date`: "x" or "vapaa" for a day off,
+/// shifts like "9-17" or "8-12, 16-20", or anything else as a note such as "vv".
+pub fn parse_correction_value(value: &str, date: &str) -> Result<WorkScheduleEntry, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("The new value is empty".to_string());
+    }
+    if value.chars().count() > MAX_CORRECTION_VALUE_LENGTH {
+        return Err(format!(
+            "The new value is over {MAX_CORRECTION_VALUE_LENGTH} characters"
+        ));
+    }
+
+    let mut entry = WorkScheduleEntry::new(date.to_string());
+    if DAY_OFF_VALUES
+        .iter()
+        .any(|day_off| value.eq_ignore_ascii_case(day_off))
+    {
+        entry.is_day_off = true;
+        return Ok(entry);
+    }
+
+    let shifts: Option<Vec<ShiftRange>> = value
+        .split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-')?;
+            Some(ShiftRange::new(
+                correction_time(start)?,
+                correction_time(end)?,
+            ))
+        })
+        .collect();
+    match shifts {
+        Some(shifts) => entry.shifts = shifts,
+        // A note like a vacation code; something that looks like hours is most likely a typo
+        None if value.contains('-') && value.chars().any(|c| c.is_ascii_digit()) => {
+            return Err(format!("\"{value}\" isn't a valid shift like 9-17"));
+        }
+        None => {
+            let is_code = VACATION_CODES
+                .iter()
+                .any(|code| value.eq_ignore_ascii_case(code));
+            entry.notes = Some(if is_code {
+                value.to_lowercase()
+            } else {
+                value.to_string()
+            });
+        }
+    }
+    Ok(entry)
+}
+
+/// Store a suggested correction until an admin decides it or it expires
+pub async fn store_correction(
+    redis_handle: &RedisActorHandle,
+    correction: &Correction,
+) -> BotResult<()> {
+    let json = serde_json::to_string(correction)
+        .map_err(|e| other_error(&format!("Failed to serialize correction: {e}")))?;
+    let stored = redis_handle
+        .set_nx_ex(
+            &keys::correction_key(&correction.id)?,
+            json,
+            CORRECTION_TTL_SECS,
+        )
+        .await?;
+    if !stored {
+        return Err(other_error(&format!(
+            "Correction {} already exists",
+            correction.id
+        )));
+    }
+    Ok(())
+}
+
+/// Load a pending correction, or None if it expired or was decided
+pub async fn load_correction(
+    redis_handle: &RedisActorHandle,
+    id: &str,
+) -> BotResult<Option<Correction>> {
+    let stored: Option<String> = redis_handle.get(&keys::correction_key(id)?).await?;
+    Ok(stored.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// Claim a pending correction for a decision. Once claimed, nobody else can decide it; call
+/// [`finish_correction`] after acting on it, or [`release_correction`] if that failed.
+pub async fn claim_correction(
+    redis_handle: &RedisActorHandle,
+    id: &str,
+) -> BotResult<CorrectionClaim> {
+    let Some(correction) = load_correction(redis_handle, id).await? else {
+        return Ok(CorrectionClaim::Expired);
+    };
+    let claimed = redis_handle
+        .set_nx_ex(
+            &keys::correction_decided_key(id)?,
+            chrono::Utc::now().timestamp(),
+            CORRECTION_TTL_SECS,
+        )
+        .await?;
+    Ok(if claimed {
+        CorrectionClaim::Claimed(Box::new(correction))
+    } else {
+        CorrectionClaim::Decided
+    })
+}
+
+/// Remove a decided correction
+pub async fn finish_correction(redis_handle: &RedisActorHandle, id: &str) -> BotResult<()> {
+    redis_handle.del(&keys::correction_key(id)?).await
+}
+
+/// Give back a claim whose decision couldn't be carried out, so it can be tried again
+pub async fn release_correction(redis_handle: &RedisActorHandle, id: &str) -> BotResult<()> {
+    redis_handle.del(&keys::correction_decided_key(id)?).await
+}
+
+/// Id of the button deciding a correction
+pub fn button_id(action: CorrectionAction, id: &str) -> String {
+    format!("{BUTTON_PREFIX}:{}:{id}", action.as_str())
+}
+
+/// Read the action and correction id of a decision button
+pub fn parse_button_id(custom_id: &str) -> Option<(CorrectionAction, &str)> {
+    let (action, id) = custom_id
+        .strip_prefix(BUTTON_PREFIX)?
+        .strip_prefix(':')?
+        .split_once(':')?;
+    let action = match action {
+        "approve" => CorrectionAction::Approve,
+        "reject" => CorrectionAction::Reject,
+        _ => return None,
+    };
+    (!id.is_empty()).then_some((action, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::redis_service::FakeClock;
+    use std::time::Duration;
+
+    fn correction(id: &str) -> Correction {
+        Correction {
+            id: id.to_string(),
+            employee: "Anna".to_string(),
+            date: "2025-01-06".to_string(),
+            value: "9-17".to_string(),
+            entry: parse_correction_value("9-17", "2025-01-06").unwrap(),
+            requested_by: 42,
+            requested_at: 1_736_150_400,
+        }
+    }
+
+    #[test]
+    fn test_only_the_linked_employee_may_suggest() {
+        assert_eq!(
+            check_requester(Some("Anna Mäkinen"), "anna makinen"),
+            Ok(())
+        );
+        assert_eq!(
+            check_requester(Some("Pekka"), "Anna"),
+            Err(CorrectionRefusal::OtherEmployee("Pekka".to_string()))
+        );
+        assert_eq!(
+            check_requester(None, "Anna"),
+            Err(CorrectionRefusal::NotLinked)
+        );
+    }
+
+    #[test]
+    fn test_correction_values() {
+        let entry = parse_correction_value("9-17", "2025-01-06").unwrap();
+        assert_eq!(entry.shifts, [ShiftRange::new("09:00", "17:00")]);
+        assert_eq!(entry.date, "2025-01-06");
+
+        let entry = parse_correction_value("8.30-12, 16:00-20.15", "2025-01-06").unwrap();
+        assert_eq!(
+            entry.shifts,
+            [
+                ShiftRange::new("08:30", "12:00"),
+                ShiftRange::new("16:00", "20:15")
+            ]
+        );
+
+        assert!(
+            parse_correction_value("Vapaa", "2025-01-06")
+                .unwrap()
+                .is_day_off
+        );
+        let vacation = parse_correction_value("VV", "2025-01-06").unwrap();
+        assert!(vacation.is_vacation());
+        assert_eq!(
+            parse_correction_value("koulutus", "2025-01-06")
+                .unwrap()
+                .notes
+                .as_deref(),
+            Some("koulutus")
+        );
+
+        assert!(parse_correction_value(" ", "2025-01-06").is_err());
+        assert!(parse_correction_value("9-25", "2025-01-06").is_err());
+        assert!(parse_correction_value(&"a".repeat(101), "2025-01-06").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_corrections_are_decided_once() {
+        let redis_handle = RedisActorHandle::fake();
+        store_correction(&redis_handle, &correction("1"))
+            .await
+            .unwrap();
+        assert!(store_correction(&redis_handle, &correction("1"))
+            .await
+            .is_err());
+
+        assert_eq!(
+            claim_correction(&redis_handle, "1").await.unwrap(),
+            CorrectionClaim::Claimed(Box::new(correction("1")))
+        );
+        // A second admin pressing at the same time
+        assert_eq!(
+            claim_correction(&redis_handle, "1").await.unwrap(),
+            CorrectionClaim::Decided
+        );
+
+        // A failed decision can be made again
+        release_correction(&redis_handle, "1").await.unwrap();
+        assert!(matches!(
+            claim_correction(&redis_handle, "1").await.unwrap(),
+            CorrectionClaim::Claimed(_)
+        ));
+        finish_correction(&redis_handle, "1").await.unwrap();
+        assert_eq!(
+            claim_correction(&redis_handle, "1").await.unwrap(),
+            CorrectionClaim::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn test_corrections_expire_after_a_week() {
+        let clock = FakeClock::new();
+        let redis_handle = RedisActorHandle::fake_with_clock(clock.clone());
+        store_correction(&redis_handle, &correction("1"))
+            .await
+            .unwrap();
+
+        clock.advance(Duration::from_secs(CORRECTION_TTL_SECS - 1));
+        assert!(load_correction(&redis_handle, "1").await.unwrap().is_some());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(load_correction(&redis_handle, "1").await.unwrap(), None);
+        assert_eq!(
+            claim_correction(&redis_handle, "1").await.unwrap(),
+            CorrectionClaim::Expired
+        );
+    }
+
+    #[test]
+    fn test_button_ids() {
+        for action in [CorrectionAction::Approve, CorrectionAction::Reject] {
+            assert_eq!(
+                parse_button_id(&button_id(action, "123")),
+                Some((action, "123"))
+            );
+        }
+        assert_eq!(parse_button_id("correction:approve:"), None);
+        assert_eq!(parse_button_id("correction:maybe:123"), None);
+        assert_eq!(parse_button_id("welcome:link"), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Health of the API keys work_hours parses schedules with.
+//!
+//! work_hours checks each configured key once a day with a cheap authenticated request and
+//! stores the result in Redis, where `/status` reads it. A key that stops working is reported
+//! once, when it starts failing, instead of at the next upload.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::WORK_HOURS_CREDENTIALS;
+use crate::error::BotResult;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// How long a check result stays fresh before the key is checked again
+pub const CREDENTIAL_CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// What checking a key found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialState {
+    /// The provider accepted the key
+    Ok,
+    /// The provider refused the key, e.g. because it was revoked
+    AuthFailed,
+    /// The provider couldn't be reached or answered with an error unrelated to the key
+    Unreachable,
+}
+
+impl CredentialState {
+    /// Name of the state as stored and logged
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::AuthFailed => "auth_failed",
+            Self::Unreachable => "unreachable",
+        }
+    }
+}
+
+/// Latest check of a provider's key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    /// Provider checked, e.g. "gemini"
+    pub provider: String,
+    pub state: CredentialState,
+    /// Unix timestamp of the check
+    pub checked_at: i64,
+    /// Unix timestamp of the first check that found the current state
+    pub since: i64,
+}
+
+// Checking is done by the work hours binary, the bot only shows the results
+impl CredentialStatus {
+    /// Whether the result is old enough for the key to be checked again
+    pub fn is_due(&self, now: i64) -> bool {
+        now - self.checked_at >= CREDENTIAL_CHECK_INTERVAL_SECS
+    }
+
+    /// Status after a check found `state`, and whether the change calls for an alert. Only a
+    /// key that starts being refused is alerted on, so a failing key is reported once rather
+    /// than every day, and an outage of the provider isn't mistaken for a revoked key.
+    pub fn next(
+        previous: Option<&CredentialStatus>,
+        provider: &str,
+        state: CredentialState,
+        now: i64,
+    ) -> (CredentialStatus, bool) {
+        let unchanged = previous.filter(|previous| previous.state == state);
+        let status = CredentialStatus {
+            provider: provider.to_string(),
+            state,
+            checked_at: now,
+            since: unchanged.map_or(now, |previous| previous.since),
+        };
+        let alert = state == CredentialState::AuthFailed && unchanged.is_none();
+        (status, alert)
+    }
+}
+
+/// Load the latest check of every provider, by provider name, skipping unreadable records
+pub async fn load_credential_statuses(
+    redis_handle: &RedisActorHandle,
+) -> BotResult<Vec<CredentialStatus>> {
+    let stored: Vec<String> = redis_handle.hvals(&WORK_HOURS_CREDENTIALS).await?;
+    let mut statuses: Vec<CredentialStatus> = stored
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| warn!("Ignoring invalid credential status: {}", e))
+                .ok()
+        })
+        .collect();
+    statuses.sort_by(|a, b| a.provider.cmp(&b.provider));
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = CREDENTIAL_CHECK_INTERVAL_SECS;
+
+    #[test]
+    fn test_only_newly_refused_keys_are_alerted() {
+        use CredentialState::*;
+
+        let (ok, alert) = CredentialStatus::next(None, "gemini", Ok, 0);
+        assert!(!alert);
+        assert!(!ok.is_due(DAY - 1));
+        assert!(ok.is_due(DAY));
+
+        // An outage isn't a revoked key
+        let (unreachable, alert) = CredentialStatus::next(Some(&ok), "gemini", Unreachable, DAY);
+        assert!(!alert);
+        assert_eq!(unreachable.since, DAY);
+
+        let (failed, alert) =
+            CredentialStatus::next(Some(&unreachable), "gemini", AuthFailed, 2 * DAY);
+        assert!(alert);
+        // Still failing the next day is the same state, reported once
+        let (still, alert) = CredentialStatus::next(Some(&failed), "gemini", AuthFailed, 3 * DAY);
+        assert!(!alert);
+        assert_eq!(still.since, 2 * DAY);
+        assert_eq!(still.checked_at, 3 * DAY);
+
+        // Fixed and revoked again is a new alert
+        let (fixed, _) = CredentialStatus::next(Some(&still), "gemini", Ok, 4 * DAY);
+        let (_, alert) = CredentialStatus::next(Some(&fixed), "gemini", AuthFailed, 5 * DAY);
+        assert!(alert);
+
+        // A key refused from its first check is reported too
+        assert!(CredentialStatus::next(None, "llamaindex", AuthFailed, 0).1);
+    }
+}
@@ -0,0 +1,158 @@
+//! Differences between two versions of employees' schedules.
+
+use crate::components::work_schedule::models::WorkScheduleEntry;
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+
+/// A day whose times or day type differ from the day it's compared against
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayChange {
+    pub employee: String,
+    /// The entry compared against
+    pub before: WorkScheduleEntry,
+    /// The entry as it is now
+    pub after: WorkScheduleEntry,
+}
+
+/// Whether two entries have the same shifts and day type. Breaks, duplicate flags and the
+/// upload an entry came from don't count.
+pub fn same_day(a: &WorkScheduleEntry, b: &WorkScheduleEntry) -> bool {
+    a.shifts == b.shifts && a.is_day_off == b.is_day_off && a.notes == b.notes
+}
+
+/// Whether an entry has nothing stored for the day
+fn is_blank(entry: &WorkScheduleEntry) -> bool {
+    entry.shifts.is_empty() && !entry.is_day_off && entry.notes.is_none()
+}
+
+/// Compare each employee's week against the same weekdays of the week before, in the order of
+/// `this_week`.
+///
+/// Only days with an entry in both weeks are compared, so an employee absent last week or a
+/// week that was only partly uploaded doesn't show up as a wall of changes. Blank entries, like
+/// the ones range queries fill missing days with, count as absent.
+pub fn week_over_week(
+    this_week: &[(String, Vec<WorkScheduleEntry>)],
+    last_week: &[(String, Vec<WorkScheduleEntry>)],
+) -> Vec<DayChange> {
+    let last_week: HashMap<(&str, NaiveDate), &WorkScheduleEntry> = last_week
+        .iter()
+        .flat_map(|(employee, entries)| {
+            entries
+                .iter()
+                .filter(|entry| !is_blank(entry))
+                .filter_map(move |entry| {
+                    let date = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").ok()?;
+                    Some(((employee.as_str(), date), entry))
+                })
+        })
+        .collect();
+
+    let mut changes = Vec::new();
+    for (employee, entries) in this_week {
+        for entry in entries.iter().filter(|entry| !is_blank(entry)) {
+            let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") else {
+                continue;
+            };
+            let Some(before) = last_week.get(&(employee.as_str(), date - Duration::days(7))) else {
+                continue;
+            };
+            if !same_day(before, entry) {
+                changes.push(DayChange {
+                    employee: employee.clone(),
+                    before: (*before).clone(),
+                    after: entry.clone(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shifted_times_are_changes() {
+        let last_week = [(
+            "Anna".to_string(),
+            vec![
+                WorkScheduleEntry::working("2025-03-03", "08:00", "16:00"),
+                WorkScheduleEntry::working("2025-03-04", "08:00", "16:00"),
+            ],
+        )];
+        let mut moved_break = WorkScheduleEntry::working("2025-03-11", "08:00", "16:00");
+        moved_break.break_minutes = Some(30);
+        let this_week = [(
+            "Anna".to_string(),
+            vec![
+                WorkScheduleEntry::working("2025-03-10", "10:00", "18:00"),
+                moved_break,
+            ],
+        )];
+
+        let changes = week_over_week(&this_week, &last_week);
+        // Only the times count, not the break
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].employee, "Anna");
+        assert_eq!(changes[0].before.date, "2025-03-03");
+        assert_eq!(
+            changes[0].after,
+            WorkScheduleEntry::working("2025-03-10", "10:00", "18:00")
+        );
+    }
+
+    #[test]
+    fn test_new_days_off_are_changes() {
+        let last_week = [(
+            "Pekka".to_string(),
+            vec![
+                WorkScheduleEntry::working("2025-03-05", "12:00", "20:00"),
+                WorkScheduleEntry::day_off("2025-03-06"),
+            ],
+        )];
+        let mut vacation = WorkScheduleEntry::new("2025-03-13".to_string());
+        vacation.notes = Some("vv".to_string());
+        let this_week = [(
+            "Pekka".to_string(),
+            vec![WorkScheduleEntry::day_off("2025-03-12"), vacation],
+        )];
+
+        let changes = week_over_week(&this_week, &last_week);
+        let dates: Vec<&str> = changes.iter().map(|c| c.after.date.as_str()).collect();
+        assert_eq!(dates, ["2025-03-12", "2025-03-13"]);
+        assert!(changes[0].after.is_day_off && !changes[0].before.is_day_off);
+    }
+
+    #[test]
+    fn test_employees_absent_last_week_have_no_changes() {
+        let last_week = [
+            (
+                "Anna".to_string(),
+                vec![WorkScheduleEntry::working("2025-03-03", "08:00", "16:00")],
+            ),
+            // What a range query returns for an employee without entries that week
+            (
+                "Liisa".to_string(),
+                vec![WorkScheduleEntry::new("2025-03-03".to_string())],
+            ),
+        ];
+        let this_week = [
+            (
+                "Anna".to_string(),
+                vec![
+                    WorkScheduleEntry::working("2025-03-10", "08:00", "16:00"),
+                    // Nothing to compare against on Tuesday
+                    WorkScheduleEntry::day_off("2025-03-11"),
+                ],
+            ),
+            (
+                "Liisa".to_string(),
+                vec![WorkScheduleEntry::working("2025-03-10", "07:00", "15:00")],
+            ),
+        ];
+
+        assert!(week_over_week(&this_week, &last_week).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Canonical employee identity.
+///
+/// The display name is trimmed, whitespace-collapsed and NFC-normalized but keeps its case.
+/// The slug additionally lowercases and strips diacritics, and is what every Redis key uses so
+/// "Anna Mäkinen", "anna mäkinen" and "Anna  Mäkinen" all end up in the same place.
+#[derive(Debug, Clone, Eq)]
+pub struct EmployeeId {
+    display: String,
+    slug: String,
+}
+
+impl EmployeeId {
+    /// Normalize a raw employee name
+    pub fn new(raw: &str) -> Self {
+        let display = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        let display: String = display.nfc().collect();
+        let slug = display
+            .nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .flat_map(char::to_lowercase)
+            .nfc()
+            .collect();
+
+        Self { display, slug }
+    }
+
+    /// Name for showing to users
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// Case and diacritic insensitive form used in storage keys
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    /// Whether the name was blank
+    pub fn is_empty(&self) -> bool {
+        self.slug.is_empty()
+    }
+}
+
+impl PartialEq for EmployeeId {
+    fn eq(&self, other: &Self) -> bool {
+        self.slug == other.slug
+    }
+}
+
+impl Hash for EmployeeId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.slug.hash(state);
+    }
+}
+
+impl fmt::Display for EmployeeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+impl From<&str> for EmployeeId {
+    fn from(raw: &str) -> Self {
+        Self::new(raw)
+    }
+}
+
+/// Sort key of a letter in the Finnish alphabet, where å, ä and ö come after z and other
+/// accented letters sort with their base letter
+fn collation_weight(c: char) -> u32 {
+    let after_z = |offset: u32| 'z' as u32 + offset;
+    match c {
+        'å' => after_z(1),
+        'ä' | 'æ' => after_z(2),
+        'ö' | 'ø' => after_z(3),
+        'ü' => 'y' as u32,
+        c => std::iter::once(c)
+            .nfd()
+            .next()
+            .map_or(c as u32, |base| base as u32),
+    }
+}
+
+/// Compare names in Finnish alphabetical order, ignoring case. Names differing only in case
+/// are ordered by their exact spelling so the order never depends on where the names came from.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    let key = |name: &str| -> Vec<u32> {
+        name.nfc()
+            .flat_map(char::to_lowercase)
+            .map(collation_weight)
+            .collect()
+    };
+    key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variants_share_a_slug() {
+        let canonical = EmployeeId::new("Anna Mäkinen");
+        assert_eq!(canonical.slug(), "anna makinen");
+
+        for variant in [
+            "anna mäkinen",
+            "Anna  Mäkinen",
+            "  Anna\tMäkinen ",
+            "ANNA MÄKINEN",
+        ] {
+            assert_eq!(EmployeeId::new(variant), canonical, "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn test_display_keeps_case_and_is_nfc() {
+        // "a" followed by a combining diaeresis
+        let id = EmployeeId::new("  Anna   Ma\u{0308}kinen ");
+        assert_eq!(id.display(), "Anna Mäkinen");
+        assert_eq!(id.display().chars().count(), 12);
+        assert_eq!(id, EmployeeId::new("Anna Mäkinen"));
+    }
+
+    #[test]
+    fn test_slug_is_idempotent() {
+        let id = EmployeeId::new("Åsa Öberg-Ström");
+        assert_eq!(id.slug(), "asa oberg-strom");
+        assert_eq!(EmployeeId::new(id.slug()).slug(), id.slug());
+        assert!(EmployeeId::new(" \t ").is_empty());
+    }
+
+    #[test]
+    fn test_names_sort_in_finnish_order() {
+        let mut names = vec![
+            "Öhman",
+            "anna",
+            "Åsa",
+            "Ärla",
+            "Zacharias",
+            "Anna",
+            "Élise",
+            "ärla",
+            "Bertta",
+            "Ella",
+        ];
+        names.sort_by(|a, b| compare_names(a, b));
+        assert_eq!(
+            names,
+            [
+                "Anna",
+                "anna",
+                "Bertta",
+                "Élise",
+                "Ella",
+                "Zacharias",
+                "Åsa",
+                "Ärla",
+                "ärla",
+                "Öhman"
+            ]
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Weekly digest of schedule anomalies for managers, who don't need the full grid.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::models::{CoverageInfo, EmployeeSchedule};
+use crate::components::work_schedule::quality::{load_parse_records, ParseRecord};
+use crate::components::work_schedule::stats::{
+    load_contract_hours, weekly_totals, Deviation, HoursBudget,
+};
+use crate::components::work_schedule::WorkScheduleHandle;
+use crate::config::Config;
+use crate::utils::embed::split_field;
+use crate::utils::i18n::weekday_short_name;
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use poise::serenity_prelude::{self as serenity, CreateEmbed, CreateEmbedFooter};
+use rust_i18n::t;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Color of the exceptions digest
+const EXCEPTIONS_COLOR: u32 = 0xE6_7E_22;
+
+/// Days ahead a schedule may end before it's reported as running out
+const COVERAGE_WARNING_DAYS: i64 = 7;
+
+/// Days back uploads are checked for uncertain parses
+const RECENT_UPLOAD_DAYS: i64 = 7;
+
+/// What the digest is built from. A source that couldn't be read, or a feature that isn't set
+/// up, is None and its section is left out.
+#[derive(Debug, Clone, Default)]
+pub struct ExceptionSources {
+    /// Every employee's entries for the week
+    pub schedules: Option<Vec<EmployeeSchedule>>,
+    /// Contract hours, None when no one has any
+    pub budget: Option<HoursBudget>,
+    /// Parse records of the uploads, None when there are none
+    pub parse_records: Option<Vec<ParseRecord>>,
+    /// How far each employee's stored schedule reaches
+    pub coverage: Option<Vec<CoverageInfo>>,
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// A date with its short weekday, e.g. "Tue 11.03."
+fn short_day(date: NaiveDate) -> String {
+    format!(
+        "{} {}",
+        weekday_short_name(date.weekday()),
+        date.format("%d.%m.")
+    )
+}
+
+/// Days of the week nobody works on
+fn uncovered_lines(
+    schedules: &[EmployeeSchedule],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<String> {
+    start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .filter(|date| {
+            let date = date.format("%Y-%m-%d").to_string();
+            !schedules
+                .iter()
+                .flat_map(|schedule| &schedule.schedule)
+                .any(|entry| entry.date == date && entry.is_working())
+        })
+        .map(short_day)
+        .collect()
+}
+
+/// Employees whose week is over or under their contract beyond the tolerance
+fn contract_lines(
+    schedules: &[EmployeeSchedule],
+    budget: &HoursBudget,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<String> {
+    schedules
+        .iter()
+        .filter_map(|schedule| {
+            let contract_hours = budget.contract_for(&schedule.employee)?;
+            let summaries: Vec<String> =
+                weekly_totals(&schedule.schedule, start, end, budget.week_start)
+                    .iter()
+                    .filter(|week| {
+                        week.deviation(contract_hours, budget.tolerance_hours) != Deviation::Within
+                    })
+                    .map(|week| week.summary(contract_hours, budget.tolerance_hours))
+                    .collect();
+            (!summaries.is_empty())
+                .then(|| format!("**{}**: {}", schedule.employee, summaries.join(", ")))
+        })
+        .collect()
+}
+
+/// Recent uploads with cells the parser wasn't sure about or validation warnings
+fn parse_lines(records: &[ParseRecord], today: NaiveDate) -> Vec<String> {
+    let since = today - Duration::days(RECENT_UPLOAD_DAYS);
+    records
+        .iter()
+        .filter_map(|record| {
+            let parsed_on = Local
+                .timestamp_opt(record.parsed_at, 0)
+                .single()?
+                .date_naive();
+            if parsed_on < since || (record.low_confidence == 0 && record.warnings.is_empty()) {
+                return None;
+            }
+            Some(format!(
+                "**{}** · {}: {}",
+                record.employee,
+                parsed_on.format("%d.%m."),
+                t!(
+                    "exceptions_parse_line",
+                    low_confidence = record.low_confidence,
+                    warnings = record.warnings.len()
+                )
+            ))
+        })
+        .collect()
+}
+
+/// Employees whose stored schedule ends within the warning period, or who have none
+fn coverage_lines(coverage: &[CoverageInfo], today: NaiveDate) -> Vec<String> {
+    let horizon = today + Duration::days(COVERAGE_WARNING_DAYS);
+    coverage
+        .iter()
+        .filter_map(|info| {
+            let text = match info.last_date.as_deref().and_then(parse_date) {
+                Some(last) if last > horizon => return None,
+                Some(last) => t!("exceptions_coverage_ends", date = short_day(last)),
+                None => t!("exceptions_coverage_none"),
+            };
+            Some(format!("**{}**: {text}", info.employee))
+        })
+        .collect()
+}
+
+/// Build the exceptions digest of the week from `start` to `end`, or None when there's nothing
+/// to report. Sections whose source is missing are left out and named in the footer.
+pub fn exceptions_digest(
+    start: NaiveDate,
+    end: NaiveDate,
+    today: NaiveDate,
+    sources: &ExceptionSources,
+) -> Option<CreateEmbed> {
+    let schedules = sources.schedules.as_deref();
+    let sections = [
+        (
+            t!("exceptions_uncovered_section"),
+            schedules.map(|schedules| uncovered_lines(schedules, start, end)),
+        ),
+        (
+            t!("exceptions_contract_section"),
+            schedules
+                .zip(sources.budget.as_ref())
+                .map(|(schedules, budget)| contract_lines(schedules, budget, start, end)),
+        ),
+        (
+            t!("exceptions_parse_section"),
+            sources
+                .parse_records
+                .as_deref()
+                .map(|records| parse_lines(records, today)),
+        ),
+        (
+            t!("exceptions_coverage_section"),
+            sources
+                .coverage
+                .as_deref()
+                .map(|coverage| coverage_lines(coverage, today)),
+        ),
+    ];
+
+    if sections
+        .iter()
+        .all(|(_, lines)| lines.as_ref().is_none_or(Vec::is_empty))
+    {
+        return None;
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(t!(
+            "exceptions_title",
+            start_date = start.format("%d.%m.").to_string(),
+            end_date = end.format("%d.%m.%Y").to_string()
+        ))
+        .color(EXCEPTIONS_COLOR);
+    let mut missing = Vec::new();
+    for (name, lines) in sections {
+        match lines {
+            Some(lines) if !lines.is_empty() => {
+                for (name, value) in split_field(&name, &lines) {
+                    embed = embed.field(name, value, false);
+                }
+            }
+            Some(_) => {}
+            None => missing.push(name.to_string()),
+        }
+    }
+    if !missing.is_empty() {
+        embed = embed.footer(CreateEmbedFooter::new(t!(
+            "exceptions_not_checked",
+            sections = missing.join(", ")
+        )));
+    }
+    Some(embed)
+}
+
+/// Read the digest's sources, leaving out the ones that fail or aren't set up
+async fn collect_sources(
+    handle: &WorkScheduleHandle,
+    redis_handle: &RedisActorHandle,
+    config: &Config,
+    start_date: &str,
+    end_date: &str,
+) -> ExceptionSources {
+    let schedules = handle
+        .get_stored_entries_for_range(start_date, end_date)
+        .await
+        .map_err(|e| {
+            warn!(
+                "Failed to get the week's schedules for the exceptions digest: {}",
+                e
+            )
+        })
+        .ok();
+    let budget = match load_contract_hours(redis_handle).await {
+        Ok(contracts) if contracts.is_empty() => None,
+        Ok(contracts) => Some(
+            HoursBudget::new(contracts, config.contract_hours_tolerance)
+                .with_week_start(config.week_starts_on),
+        ),
+        Err(e) => {
+            warn!(
+                "Failed to load contract hours for the exceptions digest: {}",
+                e
+            );
+            None
+        }
+    };
+    let parse_records = match load_parse_records(redis_handle).await {
+        Ok(records) if records.is_empty() => None,
+        Ok(records) => Some(records),
+        Err(e) => {
+            warn!(
+                "Failed to load parse records for the exceptions digest: {}",
+                e
+            );
+            None
+        }
+    };
+    let coverage = handle
+        .get_employees_coverage()
+        .await
+        .map_err(|e| warn!("Failed to get coverage for the exceptions digest: {}", e))
+        .ok();
+
+    ExceptionSources {
+        schedules,
+        budget,
+        parse_records,
+        coverage,
+    }
+}
+
+/// DM the exceptions digest of the week to every manager. Nothing is sent when there are no
+/// managers or nothing to report, and a manager who can't be reached doesn't stop the others.
+pub async fn send_exceptions_digest(
+    http: &Arc<serenity::Http>,
+    handle: &WorkScheduleHandle,
+    redis_handle: &RedisActorHandle,
+    config: &Config,
+    start_date: &str,
+    end_date: &str,
+) {
+    if config.manager_user_ids.is_empty() {
+        return;
+    }
+    let (Some(start), Some(end)) = (parse_date(start_date), parse_date(end_date)) else {
+        warn!(
+            "Invalid week {} – {} for the exceptions digest",
+            start_date, end_date
+        );
+        return;
+    };
+
+    let sources = collect_sources(handle, redis_handle, config, start_date, end_date).await;
+    let Some(embed) = exceptions_digest(start, end, Local::now().date_naive(), &sources) else {
+        info!("No schedule exceptions this week, skipping the managers' digest");
+        return;
+    };
+
+    for user_id in &config.manager_user_ids {
+        let message = serenity::CreateMessage::new().embed(embed.clone());
+        if let Err(e) = serenity::UserId::new(*user_id)
+            .direct_message(http.as_ref(), message)
+            .await
+        {
+            warn!("Failed to send the exceptions digest to {}: {}", user_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::WorkScheduleEntry;
+    use crate::components::work_schedule::stats::ContractHours;
+    use crate::utils::embed::render_embed;
+    use chrono::NaiveTime;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// Anna works long days through the week, Pekka only on Monday, and nobody on Sunday
+    fn schedules() -> Vec<EmployeeSchedule> {
+        vec![
+            EmployeeSchedule {
+                employee: "Anna".to_string(),
+                schedule: [
+                    "2025-03-10",
+                    "2025-03-11",
+                    "2025-03-12",
+                    "2025-03-13",
+                    "2025-03-14",
+                    "2025-03-15",
+                ]
+                .iter()
+                .map(|date| WorkScheduleEntry::working(date, "07:00", "17:00"))
+                .collect(),
+            },
+            EmployeeSchedule {
+                employee: "Pekka".to_string(),
+                schedule: vec![WorkScheduleEntry::working("2025-03-10", "12:00", "20:00")],
+            },
+        ]
+    }
+
+    fn record(employee: &str, parsed_on: &str, low_confidence: usize) -> ParseRecord {
+        // Midday, so the date is the same in every timezone the tests run in
+        let parsed_at = Local
+            .from_local_datetime(
+                &date(parsed_on).and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+            )
+            .unwrap()
+            .timestamp();
+        ParseRecord {
+            upload_id: format!("{employee}-{parsed_at}"),
+            parsed_at,
+            employee: employee.to_string(),
+            provider: "llama".to_string(),
+            days: 7,
+            empty_cells: 0,
+            low_confidence,
+            warnings: Vec::new(),
+            manual_edits: 0,
+        }
+    }
+
+    fn coverage(employee: &str, last_date: Option<&str>) -> CoverageInfo {
+        CoverageInfo {
+            employee: employee.to_string(),
+            first_date: last_date.map(|_| "2025-03-01".to_string()),
+            last_date: last_date.map(str::to_string),
+            day_count: 10,
+            working_day_count: 8,
+        }
+    }
+
+    fn sources() -> ExceptionSources {
+        ExceptionSources {
+            schedules: Some(schedules()),
+            budget: Some(HoursBudget::new(
+                [
+                    ContractHours {
+                        employee: "Anna".to_string(),
+                        hours_per_week: 37.5,
+                    },
+                    ContractHours {
+                        employee: "Pekka".to_string(),
+                        hours_per_week: 8.0,
+                    },
+                ],
+                2.0,
+            )),
+            parse_records: Some(vec![
+                record("Anna", "2025-03-09", 2),
+                record("Pekka", "2025-03-08", 0),
+                // Older than a week
+                record("Pekka", "2025-02-20", 3),
+            ]),
+            coverage: Some(vec![
+                coverage("Anna", Some("2025-04-30")),
+                coverage("Pekka", Some("2025-03-16")),
+                coverage("Liisa", None),
+            ]),
+        }
+    }
+
+    fn digest(sources: &ExceptionSources) -> Option<String> {
+        exceptions_digest(
+            date("2025-03-10"),
+            date("2025-03-16"),
+            date("2025-03-10"),
+            sources,
+        )
+        .map(|embed| render_embed(&embed))
+    }
+
+    #[test]
+    fn test_exceptions_digest_snapshot() {
+        let expected = "\
+# Schedule exceptions 10.03. – 16.03.2025
+## 🕳️ Days nobody works
+Sun 16.03.
+## ⏱️ Contract hours
+**Anna**: Σ 60 h / 37.5 h · 🔴 +22.5 h over
+## 🔍 Uncertain parses
+**Anna** · 09.03.: 2 uncertain cells, 0 warnings
+## 📅 Schedules running out
+**Pekka**: ends Sun 16.03.
+**Liisa**: no stored schedule
+";
+        assert_eq!(digest(&sources()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_missing_sources_are_left_out() {
+        let sources = ExceptionSources {
+            budget: None,
+            parse_records: None,
+            ..sources()
+        };
+        let expected = "\
+# Schedule exceptions 10.03. – 16.03.2025
+## 🕳️ Days nobody works
+Sun 16.03.
+## 📅 Schedules running out
+**Pekka**: ends Sun 16.03.
+**Liisa**: no stored schedule
+-- Not checked: ⏱️ Contract hours, 🔍 Uncertain parses
+";
+        assert_eq!(digest(&sources).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_quiet_week_sends_nothing() {
+        let mut schedules = schedules();
+        schedules[1]
+            .schedule
+            .push(WorkScheduleEntry::working("2025-03-16", "10:00", "14:00"));
+        let sources = ExceptionSources {
+            schedules: Some(schedules),
+            budget: None,
+            parse_records: Some(vec![record("Pekka", "2025-03-08", 0)]),
+            coverage: Some(vec![coverage("Anna", Some("2025-04-30"))]),
+        };
+        assert_eq!(digest(&sources), None);
+        assert_eq!(digest(&ExceptionSources::default()), None);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::{WORK_HOURS_GLOSSARY, WORK_HOURS_GLOSSARY_MISSING};
+use crate::error::{work_schedule_error, BotResult};
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Untranslated terms `/sanasto missing` lists
+pub const MISSING_TERMS_SHOWN: usize = 15;
+
+/// Canonical form of a note, so "Toive vp" and "toive  VP" are the same term
+pub fn term_name(note: &str) -> String {
+    note.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Display strings for the notes parsed from schedule cells, e.g. "Toive vp", by term and
+/// locale
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoteGlossary {
+    terms: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl NoteGlossary {
+    /// Create a glossary from terms and their display strings by locale
+    pub fn new(terms: impl IntoIterator<Item = (String, BTreeMap<String, String>)>) -> Self {
+        Self {
+            terms: terms
+                .into_iter()
+                .map(|(term, translations)| (term_name(&term), translations))
+                .collect(),
+        }
+    }
+
+    /// Terms and their display strings by locale, sorted by term
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BTreeMap<String, String>)> {
+        self.terms
+            .iter()
+            .map(|(term, translations)| (term.as_str(), translations))
+    }
+
+    /// Display string of a note in a locale, falling back to another region of the same
+    /// language, e.g. "en" for "en-US"
+    pub fn translate(&self, note: &str, locale: &str) -> Option<&str> {
+        let translations = self.terms.get(&term_name(note))?;
+        let language = |locale: &str| locale.split('-').next().unwrap_or_default().to_string();
+        translations
+            .get(locale)
+            .or_else(|| {
+                translations
+                    .iter()
+                    .find(|(candidate, _)| language(candidate) == language(locale))
+                    .map(|(_, display)| display)
+            })
+            .map(String::as_str)
+    }
+}
+
+/// Load the glossary, skipping unreadable terms
+pub async fn load_glossary(redis_handle: &RedisActorHandle) -> BotResult<NoteGlossary> {
+    let stored: HashMap<String, String> = redis_handle.hgetall(&WORK_HOURS_GLOSSARY).await?;
+
+    Ok(NoteGlossary::new(stored.into_iter().filter_map(
+        |(term, json)| match serde_json::from_str(&json) {
+            Ok(translations) => Some((term, translations)),
+            Err(e) => {
+                warn!("Ignoring invalid glossary term {}: {}", term, e);
+                None
+            }
+        },
+    )))
+}
+
+/// Store a term's display strings, removing the term when it has none
+async fn save_term(
+    redis_handle: &RedisActorHandle,
+    term: &str,
+    translations: &BTreeMap<String, String>,
+) -> BotResult<()> {
+    if translations.is_empty() {
+        return redis_handle.hdel(&WORK_HOURS_GLOSSARY, term).await;
+    }
+
+    let json = serde_json::to_string(translations)
+        .map_err(|e| work_schedule_error(&format!("Failed to serialize glossary term: {e}")))?;
+    redis_handle.hset(&WORK_HOURS_GLOSSARY, term, json).await
+}
+
+/// Set the display string of a term in a locale. The term no longer counts as missing.
+pub async fn add_term(
+    redis_handle: &RedisActorHandle,
+    term: &str,
+    locale: &str,
+    display: &str,
+) -> BotResult<()> {
+    let term = term_name(term);
+    let glossary = load_glossary(redis_handle).await?;
+    let mut translations = glossary.terms.get(&term).cloned().unwrap_or_default();
+    translations.insert(locale.to_string(), display.trim().to_string());
+    save_term(redis_handle, &term, &translations).await?;
+    redis_handle.hdel(&WORK_HOURS_GLOSSARY_MISSING, &term).await
+}
+
+/// Remove a term's display string in one locale, or in every locale when none is given.
+/// Returns false if there was nothing to remove.
+pub async fn remove_term(
+    redis_handle: &RedisActorHandle,
+    term: &str,
+    locale: Option<&str>,
+) -> BotResult<bool> {
+    let term = term_name(term);
+    let glossary = load_glossary(redis_handle).await?;
+    let Some(mut translations) = glossary.terms.get(&term).cloned() else {
+        return Ok(false);
+    };
+
+    match locale {
+        Some(locale) => {
+            if translations.remove(locale).is_none() {
+                return Ok(false);
+            }
+        }
+        None => translations.clear(),
+    }
+    save_term(redis_handle, &term, &translations).await?;
+    Ok(true)
+}
+
+/// Count how many times each untranslated term was shown
+pub async fn record_missing_terms(
+    redis_handle: &RedisActorHandle,
+    counts: &BTreeMap<String, u64>,
+) -> BotResult<()> {
+    for (term, count) in counts {
+        redis_handle
+            .hincrby(&WORK_HOURS_GLOSSARY_MISSING, term, *count as i64)
+            .await?;
+    }
+    Ok(())
+}
+
+/// The most often shown untranslated terms with their counts, most common first
+pub async fn missing_terms(
+    redis_handle: &RedisActorHandle,
+    limit: usize,
+) -> BotResult<Vec<(String, u64)>> {
+    let stored: HashMap<String, u64> = redis_handle.hgetall(&WORK_HOURS_GLOSSARY_MISSING).await?;
+    let mut terms: Vec<(String, u64)> = stored.into_iter().collect();
+    terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    terms.truncate(limit);
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_terms_are_stored_per_locale() {
+        let redis_handle = RedisActorHandle::fake();
+        add_term(&redis_handle, "Toive  VP", "en", "Day off request")
+            .await
+            .unwrap();
+        add_term(&redis_handle, "toive vp", "sv", "Önskad ledighet")
+            .await
+            .unwrap();
+
+        let glossary = load_glossary(&redis_handle).await.unwrap();
+        assert_eq!(
+            glossary.translate("Toive vp", "en-US"),
+            Some("Day off request")
+        );
+        assert_eq!(
+            glossary.translate("TOIVE VP", "sv"),
+            Some("Önskad ledighet")
+        );
+        assert_eq!(glossary.translate("toive vp", "fi-FI"), None);
+        assert_eq!(glossary.translate("Palkat", "en"), None);
+
+        assert!(remove_term(&redis_handle, "toive vp", Some("sv"))
+            .await
+            .unwrap());
+        assert!(!remove_term(&redis_handle, "toive vp", Some("sv"))
+            .await
+            .unwrap());
+        assert!(remove_term(&redis_handle, "toive vp", None).await.unwrap());
+        assert_eq!(
+            load_glossary(&redis_handle).await.unwrap(),
+            NoteGlossary::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_terms_are_counted_until_added() {
+        let redis_handle = RedisActorHandle::fake();
+        let counts = |pairs: &[(&str, u64)]| {
+            pairs
+                .iter()
+                .map(|(term, count)| (term.to_string(), *count))
+                .collect::<BTreeMap<_, _>>()
+        };
+        record_missing_terms(&redis_handle, &counts(&[("palkat", 2), ("koulutus", 1)]))
+            .await
+            .unwrap();
+        record_missing_terms(&redis_handle, &counts(&[("koulutus", 3)]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            missing_terms(&redis_handle, 10).await.unwrap(),
+            vec![("koulutus".to_string(), 4), ("palkat".to_string(), 2)]
+        );
+        assert_eq!(missing_terms(&redis_handle, 1).await.unwrap().len(), 1);
+
+        add_term(&redis_handle, "Koulutus", "en", "Training")
+            .await
+            .unwrap();
+        assert_eq!(
+            missing_terms(&redis_handle, 10).await.unwrap(),
+            vec![("palkat".to_string(), 2)]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::employee::{compare_names, EmployeeId};
+use crate::components::work_schedule::keys::WORK_HOURS_EMPLOYEE_GROUPS;
+use crate::error::{work_schedule_error, BotResult};
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Canonical form of a group name, so "Tiimi A" and "tiimi  a" are the same group
+pub fn group_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Parse notification routes in `group=channel_id` format separated by commas, e.g.
+/// `tiimi a=123,tiimi b=456`
+pub fn parse_notification_routes(value: &str) -> Result<HashMap<String, u64>, String> {
+    value
+        .split(',')
+        .filter(|route| !route.trim().is_empty())
+        .map(|route| {
+            let (group, channel_id) = route
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid notification route: {route}"))?;
+            let group = group_name(group);
+            let channel_id = channel_id
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid channel id in notification route: {route}"))?;
+            if group.is_empty() {
+                return Err(format!("Missing group in notification route: {route}"));
+            }
+            Ok((group, channel_id))
+        })
+        .collect()
+}
+
+/// Employees a notification or command is limited to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmployeeFilter {
+    /// Every employee
+    All,
+    /// Only these employees, matched by their canonical name
+    Only(Vec<String>),
+}
+
+impl EmployeeFilter {
+    /// Check whether the employee passes the filter
+    pub fn allows(&self, employee: &str) -> bool {
+        match self {
+            EmployeeFilter::All => true,
+            EmployeeFilter::Only(employees) => {
+                let id = EmployeeId::new(employee);
+                employees.iter().any(|name| EmployeeId::new(name) == id)
+            }
+        }
+    }
+}
+
+/// Named groups of employees, e.g. the teams sharing a guild
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmployeeGroups {
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+impl EmployeeGroups {
+    /// Create groups from names and their employees
+    pub fn new(groups: impl IntoIterator<Item = (String, Vec<String>)>) -> Self {
+        Self {
+            groups: groups
+                .into_iter()
+                .map(|(name, employees)| (group_name(&name), employees))
+                .collect(),
+        }
+    }
+
+    /// Groups and their employees, sorted by group name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.groups
+            .iter()
+            .map(|(name, employees)| (name.as_str(), employees.as_slice()))
+    }
+
+    /// Employees of a group, or None if there's no such group
+    pub fn members(&self, group: &str) -> Option<&[String]> {
+        self.groups.get(&group_name(group)).map(Vec::as_slice)
+    }
+
+    /// Filter keeping the employees of a group, or None if there's no such group
+    pub fn filter(&self, group: &str) -> Option<EmployeeFilter> {
+        self.members(group)
+            .map(|employees| EmployeeFilter::Only(employees.to_vec()))
+    }
+}
+
+/// Channels notifications go to, with the employees each one shows.
+///
+/// Without routes everything goes to `default_channel`. Otherwise each routed group goes to its
+/// channel and `employees` outside the routed groups go to `default_channel`, which is left out
+/// when there are none.
+pub fn route_notifications(
+    routes: &HashMap<String, u64>,
+    groups: &EmployeeGroups,
+    employees: &[String],
+    default_channel: u64,
+) -> Vec<(u64, EmployeeFilter)> {
+    if routes.is_empty() {
+        return vec![(default_channel, EmployeeFilter::All)];
+    }
+
+    let mut routes: Vec<_> = routes.iter().collect();
+    routes.sort();
+
+    let mut channels: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    let mut routed = Vec::new();
+    for (group, channel_id) in routes {
+        let Some(members) = groups.members(group) else {
+            warn!("Notification route for unknown group {}", group);
+            continue;
+        };
+        channels
+            .entry(*channel_id)
+            .or_default()
+            .extend_from_slice(members);
+        routed.extend_from_slice(members);
+    }
+
+    let routed = EmployeeFilter::Only(routed);
+    let unrouted: Vec<String> = employees
+        .iter()
+        .filter(|employee| !routed.allows(employee))
+        .cloned()
+        .collect();
+    if !unrouted.is_empty() {
+        channels
+            .entry(default_channel)
+            .or_default()
+            .extend(unrouted);
+    }
+
+    channels
+        .into_iter()
+        .map(|(channel_id, employees)| (channel_id, EmployeeFilter::Only(employees)))
+        .collect()
+}
+
+/// Load the employee groups, skipping unreadable ones
+pub async fn load_employee_groups(redis_handle: &RedisActorHandle) -> BotResult<EmployeeGroups> {
+    let stored: HashMap<String, String> = redis_handle.hgetall(&WORK_HOURS_EMPLOYEE_GROUPS).await?;
+
+    Ok(EmployeeGroups::new(stored.into_iter().filter_map(
+        |(name, json)| match serde_json::from_str(&json) {
+            Ok(employees) => Some((name, employees)),
+            Err(e) => {
+                warn!("Ignoring invalid employee group {}: {}", name, e);
+                None
+            }
+        },
+    )))
+}
+
+/// Store a group's employees, removing the group when it has none
+async fn save_group(
+    redis_handle: &RedisActorHandle,
+    group: &str,
+    employees: &[String],
+) -> BotResult<()> {
+    if employees.is_empty() {
+        return redis_handle.hdel(&WORK_HOURS_EMPLOYEE_GROUPS, group).await;
+    }
+
+    let json = serde_json::to_string(employees)
+        .map_err(|e| work_schedule_error(&format!("Failed to serialize employee group: {e}")))?;
+    redis_handle
+        .hset(&WORK_HOURS_EMPLOYEE_GROUPS, group, json)
+        .await
+}
+
+/// Add an employee to a group, creating the group if needed. Returns false if the employee
+/// was already in it.
+pub async fn add_group_member(
+    redis_handle: &RedisActorHandle,
+    group: &str,
+    employee: &str,
+) -> BotResult<bool> {
+    let group = group_name(group);
+    let employee = EmployeeId::new(employee);
+    let groups = load_employee_groups(redis_handle).await?;
+    let mut employees = groups.members(&group).unwrap_or_default().to_vec();
+    if EmployeeFilter::Only(employees.clone()).allows(employee.display()) {
+        return Ok(false);
+    }
+
+    employees.push(employee.display().to_string());
+    employees.sort_by(|a, b| compare_names(a, b));
+    save_group(redis_handle, &group, &employees).await?;
+    Ok(true)
+}
+
+/// Remove an employee from a group, removing the group once it's empty. Returns false if the
+/// employee wasn't in it.
+pub async fn remove_group_member(
+    redis_handle: &RedisActorHandle,
+    group: &str,
+    employee: &str,
+) -> BotResult<bool> {
+    let group = group_name(group);
+    let employee = EmployeeId::new(employee);
+    let groups = load_employee_groups(redis_handle).await?;
+    let Some(members) = groups.members(&group) else {
+        return Ok(false);
+    };
+
+    let employees: Vec<String> = members
+        .iter()
+        .filter(|name| EmployeeId::new(name) != employee)
+        .cloned()
+        .collect();
+    if employees.len() == members.len() {
+        return Ok(false);
+    }
+    save_group(redis_handle, &group, &employees).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn teams() -> EmployeeGroups {
+        EmployeeGroups::new([
+            ("Tiimi A".to_string(), names(&["Anna", "Pekka"])),
+            ("tiimi b".to_string(), names(&["Liisa"])),
+        ])
+    }
+
+    #[test]
+    fn test_filter_matches_canonical_names() {
+        let filter = teams().filter("TIIMI  a").unwrap();
+        assert!(filter.allows("anna"));
+        assert!(filter.allows("Pekka"));
+        assert!(!filter.allows("Liisa"));
+        assert!(EmployeeFilter::All.allows("Liisa"));
+        assert_eq!(teams().filter("tiimi c"), None);
+    }
+
+    #[test]
+    fn test_without_routes_everything_goes_to_the_default_channel() {
+        assert_eq!(
+            route_notifications(&HashMap::new(), &teams(), &names(&["Anna", "Liisa"]), 1),
+            [(1, EmployeeFilter::All)]
+        );
+    }
+
+    #[test]
+    fn test_routed_groups_get_their_own_channels() {
+        let routes = parse_notification_routes("Tiimi A=10, tiimi b=20").unwrap();
+        let employees = names(&["Anna", "Liisa", "Mikko", "Pekka"]);
+
+        // Mikko isn't in any group, so they stay in the default channel
+        assert_eq!(
+            route_notifications(&routes, &teams(), &employees, 1),
+            [
+                (1, EmployeeFilter::Only(names(&["Mikko"]))),
+                (10, EmployeeFilter::Only(names(&["Anna", "Pekka"]))),
+                (20, EmployeeFilter::Only(names(&["Liisa"]))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_channel_is_left_out_when_everyone_is_routed() {
+        let routes = parse_notification_routes("tiimi a=10,tiimi b=10,tiimi c=30").unwrap();
+
+        // Both teams share a channel and the unknown group is skipped
+        assert_eq!(
+            route_notifications(&routes, &teams(), &names(&["Anna", "Liisa"]), 1),
+            [(10, EmployeeFilter::Only(names(&["Anna", "Pekka", "Liisa"])))]
+        );
+    }
+
+    #[test]
+    fn test_parse_notification_routes() {
+        assert_eq!(parse_notification_routes("").unwrap(), HashMap::new());
+        assert!(parse_notification_routes("tiimi a").is_err());
+        assert!(parse_notification_routes("tiimi a=abc").is_err());
+        assert!(parse_notification_routes("=10").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_group_members_are_added_and_removed() {
+        let redis_handle = RedisActorHandle::fake();
+
+        assert!(add_group_member(&redis_handle, "Tiimi A", "Pekka")
+            .await
+            .unwrap());
+        assert!(add_group_member(&redis_handle, "tiimi a", "Anna")
+            .await
+            .unwrap());
+        assert!(!add_group_member(&redis_handle, "tiimi a", "ANNA")
+            .await
+            .unwrap());
+        assert_eq!(
+            load_employee_groups(&redis_handle).await.unwrap(),
+            EmployeeGroups::new([("tiimi a".to_string(), names(&["Anna", "Pekka"]))])
+        );
+
+        assert!(remove_group_member(&redis_handle, "tiimi a", "anna")
+            .await
+            .unwrap());
+        assert!(!remove_group_member(&redis_handle, "tiimi a", "anna")
+            .await
+            .unwrap());
+        assert!(remove_group_member(&redis_handle, "tiimi a", "Pekka")
+            .await
+            .unwrap());
+        assert_eq!(
+            load_employee_groups(&redis_handle).await.unwrap(),
+            EmployeeGroups::default()
+        );
+    }
+}
@@ -1,33 +1,37 @@
 use super::actor::{WorkScheduleActor, WorkScheduleActorHandle};
-use super::models::{EmployeeSchedule, WorkScheduleEntry};
+use super::glossary;
+use super::models::{ContextLink, CoverageInfo, DaySchedules, EmployeeSchedule, WorkScheduleEntry};
+use super::overlap::{DuplicateShift, KeepChoice};
+use super::profiles;
+use super::reconcile::{ReconcileMode, ReconcileReport};
+use super::render::ScheduleFormatter;
 use crate::components::redis_service::RedisActorHandle;
+use crate::components::EventBus;
 use crate::config::Config;
 use crate::error::BotResult;
-use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
+use tracing::warn;
 
 /// Handle for interacting with the Work Schedule actor
 #[derive(Clone)]
 pub struct WorkScheduleHandle {
     actor_handle: WorkScheduleActorHandle,
+    redis_handle: RedisActorHandle,
     _actor_task: Arc<JoinHandle<()>>,
 }
 
 impl WorkScheduleHandle {
     /// Create a new WorkScheduleHandle and spawn the actor
-    pub fn new(config: Arc<RwLock<Config>>, redis_handle: RedisActorHandle) -> Self {
-        // Create the actor and get its handle
-        let (mut actor, handle) = WorkScheduleActor::new(config, redis_handle);
-
-        // Spawn a task to run the actor
-        let actor_task = tokio::spawn(async move {
-            actor.run().await;
-        });
+    pub fn new(config: Arc<RwLock<Config>>, redis_handle: RedisActorHandle, bus: EventBus) -> Self {
+        // Spawn the actor under supervision so a crash doesn't leave the handle dead
+        let (handle, actor_task) =
+            WorkScheduleActor::spawn_supervised(config, redis_handle.clone(), bus);
 
         Self {
             actor_handle: handle,
+            redis_handle,
             _actor_task: Arc::new(actor_task),
         }
     }
@@ -37,6 +41,22 @@ impl WorkScheduleHandle {
         self.actor_handle.get_employees().await
     }
 
+    /// Get how far each employee's stored schedule reaches, sorted by employee
+    pub async fn get_employees_coverage(&self) -> BotResult<Vec<CoverageInfo>> {
+        self.actor_handle.get_employees_coverage().await
+    }
+
+    /// Get the entries every employee has stored in a date range, sorted by employee
+    pub async fn get_stored_entries_for_range(
+        &self,
+        start_date: impl Into<String>,
+        end_date: impl Into<String>,
+    ) -> BotResult<Vec<EmployeeSchedule>> {
+        self.actor_handle
+            .get_stored_entries_for_range(start_date, end_date)
+            .await
+    }
+
     /// Get schedule for a specific employee
     pub async fn get_schedule_for_employee(
         &self,
@@ -46,10 +66,7 @@ impl WorkScheduleHandle {
     }
 
     /// Get schedule for all employees on a specific date
-    pub async fn get_schedule_for_date(
-        &self,
-        date: impl Into<String>,
-    ) -> BotResult<HashMap<String, WorkScheduleEntry>> {
+    pub async fn get_schedule_for_date(&self, date: impl Into<String>) -> BotResult<DaySchedules> {
         self.actor_handle.get_schedule_for_date(date).await
     }
 
@@ -88,6 +105,104 @@ impl WorkScheduleHandle {
         Ok(WorkScheduleEntry::new(date))
     }
 
+    /// Get duplicate entries stored for dates in a range
+    pub async fn get_duplicates(
+        &self,
+        start_date: impl Into<String>,
+        end_date: impl Into<String>,
+    ) -> BotResult<Vec<DuplicateShift>> {
+        self.actor_handle.get_duplicates(start_date, end_date).await
+    }
+
+    /// Resolve duplicates for an employee and date by keeping one of the entries.
+    /// `changed_by` is the Discord user shown in the change feed.
+    pub async fn resolve_duplicate(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        keep: KeepChoice,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        self.actor_handle
+            .resolve_duplicate(employee, date, keep, changed_by)
+            .await
+    }
+
+    /// Replace an employee's entry for the entry's date, e.g. with an approved correction.
+    /// `changed_by` is the Discord user shown in the change feed.
+    pub async fn correct_entry(
+        &self,
+        employee: impl Into<String>,
+        entry: WorkScheduleEntry,
+        changed_by: Option<u64>,
+    ) -> BotResult<WorkScheduleEntry> {
+        self.actor_handle
+            .correct_entry(employee, entry, changed_by)
+            .await
+    }
+
+    /// Link a message to an employee's stored entry for a date, or remove the link with None.
+    /// Returns None when nothing is stored for the date.
+    pub async fn set_context_link(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        link: Option<ContextLink>,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        self.actor_handle
+            .set_context_link(employee, date, link)
+            .await
+    }
+
+    /// Record the hours an employee actually worked on a stored day, or clear them with None.
+    /// Returns None when nothing is stored for the date.
+    pub async fn set_actual_hours(
+        &self,
+        employee: impl Into<String>,
+        date: impl Into<String>,
+        actual: Option<(String, String)>,
+        actor: impl Into<String>,
+    ) -> BotResult<Option<WorkScheduleEntry>> {
+        self.actor_handle
+            .set_actual_hours(employee, date, actual, actor)
+            .await
+    }
+
+    /// Find day entries and dates sets that disagree, repairing them in repair mode
+    pub async fn reconcile(&self, mode: ReconcileMode) -> BotResult<ReconcileReport> {
+        self.actor_handle.reconcile(mode).await
+    }
+
+    /// A formatter showing notes through the glossary in the bot's locale and employees with
+    /// their emojis. Without a readable glossary notes are shown untranslated, and without
+    /// readable profiles every employee gets their fallback emoji.
+    pub async fn formatter(&self) -> ScheduleFormatter {
+        let glossary = glossary::load_glossary(&self.redis_handle)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load the note glossary: {}", e);
+                Default::default()
+            });
+        let profiles = profiles::load_employee_profiles(&self.redis_handle)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load the employee profiles: {}", e);
+                Default::default()
+            });
+        ScheduleFormatter::new(glossary, rust_i18n::locale().to_string()).with_profiles(profiles)
+    }
+
+    /// Count the untranslated notes a formatter has shown
+    pub async fn record_missing_notes(&self, formatter: &ScheduleFormatter) {
+        let missing = formatter.take_missing();
+        if missing.is_empty() {
+            return;
+        }
+        if let Err(e) = glossary::record_missing_terms(&self.redis_handle, &missing).await {
+            warn!("Failed to count untranslated notes: {}", e);
+        }
+    }
+
     /// Shutdown the actor
     pub async fn shutdown(&self) -> BotResult<()> {
         self.actor_handle.shutdown().await
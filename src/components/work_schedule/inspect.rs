@@ -0,0 +1,68 @@
+use super::actor::keys;
+use super::employee::EmployeeId;
+use crate::components::redis_service::RedisActorHandle;
+use crate::error::BotResult;
+
+/// A day entry and its index as stored in Redis, for debugging what the bot reads
+#[derive(Debug, Clone)]
+pub struct StoredEntry {
+    /// Name of the day key
+    pub key: String,
+    /// Raw stored value, if the key exists
+    pub raw: Option<String>,
+    /// Seconds until the key expires, or None if it doesn't expire
+    pub ttl_secs: Option<u64>,
+    /// Whether the date is listed in the employee's dates set
+    pub in_dates: bool,
+}
+
+/// Disagreement between a day key and the employee's dates set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// The day key exists but its date isn't in the dates set, so range reads skip it
+    MissingFromDates,
+    /// The dates set lists the date but the day key doesn't exist
+    MissingEntry,
+}
+
+impl StoredEntry {
+    /// How the day key and the dates set disagree, if they do
+    pub fn inconsistency(&self) -> Option<Inconsistency> {
+        match (self.raw.is_some(), self.in_dates) {
+            (true, false) => Some(Inconsistency::MissingFromDates),
+            (false, true) => Some(Inconsistency::MissingEntry),
+            _ => None,
+        }
+    }
+}
+
+/// Read the raw day entry of an employee and whether its date is indexed
+pub async fn stored_entry(
+    redis_handle: &RedisActorHandle,
+    employee: &EmployeeId,
+    date: &str,
+) -> BotResult<StoredEntry> {
+    let key = keys::day_key(employee, date)?;
+    let raw: Option<String> = redis_handle.get(&key).await?;
+    let ttl_secs = redis_handle.ttl(&key).await?;
+    let in_dates = redis_handle
+        .sismember(&keys::dates_key(employee)?, date)
+        .await?;
+
+    Ok(StoredEntry {
+        key: key.to_string(),
+        raw,
+        ttl_secs,
+        in_dates,
+    })
+}
+
+/// Dates listed in an employee's dates set, sorted
+pub async fn stored_dates(
+    redis_handle: &RedisActorHandle,
+    employee: &EmployeeId,
+) -> BotResult<Vec<String>> {
+    let mut dates: Vec<String> = redis_handle.smembers(&keys::dates_key(employee)?).await?;
+    dates.sort();
+    Ok(dates)
+}
@@ -1,34 +1,71 @@
 mod actor;
+pub mod actuals;
+pub mod audit;
+mod changes;
+pub mod corrections;
+pub mod credentials;
+pub mod diff;
+mod employee;
+pub mod exceptions;
+pub mod glossary;
+pub mod groups;
 mod handle;
+pub mod inspect;
 pub mod models;
 mod notifications;
+pub mod overlap;
+pub mod parse_failures;
+mod pinned;
+pub mod profiles;
+pub mod quality;
+pub mod reconcile;
+pub mod render;
 mod scheduler;
+pub mod stats;
 pub mod time;
-
+pub mod upload_channel;
+pub mod uploads;
+pub mod week_nav;
+pub mod weekly_edits;
+
+// Shared with the work hours web interface
+pub use actor::keys;
+pub use employee::EmployeeId;
 pub use handle::WorkScheduleHandle;
+pub use notifications::{build_daily_notification, build_weekly_notification};
+pub use scheduler::notification_handler;
 
 use super::redis_service::RedisActorHandle;
+use super::work_schedule::audit::spawn_audit_relay;
+use super::work_schedule::changes::spawn_change_feed;
+use super::work_schedule::pinned::spawn_pinned_today;
 use super::work_schedule::scheduler::WorkScheduleScheduler;
+use super::work_schedule::weekly_edits::spawn_weekly_edits;
+use super::EventBus;
 use crate::config::Config;
 use crate::error::BotResult;
-use crate::utils::scheduler::Scheduler;
+use crate::utils::scheduler::{clear_notification_state, Scheduler, SharedContext};
 use async_trait::async_trait;
-use lazy_static::lazy_static;
 use poise::serenity_prelude as serenity;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
-lazy_static! {
-    static ref SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
-}
-
 /// Work Schedule component for tracking employee work hours
 #[derive(Default)]
 pub struct WorkSchedule {
     handle: RwLock<Option<WorkScheduleHandle>>,
-    ctx: RwLock<Option<Arc<serenity::Context>>>,
+    ctx: RwLock<Option<SharedContext>>,
+    pinned_task: RwLock<Option<JoinHandle<()>>>,
+    change_feed_task: RwLock<Option<JoinHandle<()>>>,
+    audit_relay_task: RwLock<Option<JoinHandle<()>>>,
+    weekly_edits_task: RwLock<Option<JoinHandle<()>>>,
+    /// Bus the background tasks subscribe to, kept from `create`
+    bus: RwLock<Option<EventBus>>,
+    /// Whether this instance started the notification scheduler
+    scheduler_started: AtomicBool,
 }
 
 impl WorkSchedule {
@@ -37,6 +74,12 @@ impl WorkSchedule {
         Self {
             handle: RwLock::new(None),
             ctx: RwLock::new(None),
+            pinned_task: RwLock::new(None),
+            change_feed_task: RwLock::new(None),
+            audit_relay_task: RwLock::new(None),
+            weekly_edits_task: RwLock::new(None),
+            bus: RwLock::new(None),
+            scheduler_started: AtomicBool::new(false),
         }
     }
 
@@ -53,30 +96,91 @@ impl super::Component for WorkSchedule {
         "work_schedule"
     }
 
-    async fn init(
+    async fn create(
         &self,
-        ctx: &serenity::Context,
         config: Arc<RwLock<Config>>,
         redis_handle: RedisActorHandle,
+        bus: EventBus,
     ) -> BotResult<()> {
-        // Store context for scheduler
-        *self.ctx.write().await = Some(Arc::new(ctx.clone()));
-
         // Create a new handle if one doesn't exist
         let mut handle_lock = self.handle.write().await;
         if handle_lock.is_none() {
-            // Pass the redis_handle to the WorkScheduleHandle
-            *handle_lock = Some(WorkScheduleHandle::new(config.clone(), redis_handle));
+            // Pass the redis_handle and bus to the WorkScheduleHandle
+            *handle_lock = Some(WorkScheduleHandle::new(config, redis_handle, bus.clone()));
         }
+        drop(handle_lock);
+        *self.bus.write().await = Some(bus);
+
+        Ok(())
+    }
+
+    async fn attach(&self, ctx: &serenity::Context) -> BotResult<()> {
+        // Hand the latest context to the scheduler; on reconnects the running loops pick it up
+        SharedContext::attach(&self.ctx, Arc::new(ctx.clone())).await;
+
+        Ok(())
+    }
 
-        // Get the handle and context for the scheduler
-        let handle = handle_lock.as_ref().unwrap().clone();
-        let ctx = Arc::new(ctx.clone());
+    async fn start_background(
+        &self,
+        config: Arc<RwLock<Config>>,
+        redis_handle: RedisActorHandle,
+    ) -> BotResult<()> {
+        let (Some(shared_ctx), Some(handle), Some(bus)) = (
+            self.ctx.read().await.clone(),
+            self.handle.read().await.clone(),
+            self.bus.read().await.clone(),
+        ) else {
+            warn!("Work Schedule isn't initialized, not starting its scheduler");
+            return Ok(());
+        };
+
+        // Keep the pinned today message up to date; the task checks whether it's enabled
+        let mut pinned_task = self.pinned_task.write().await;
+        if pinned_task.is_none() {
+            *pinned_task = Some(spawn_pinned_today(
+                shared_ctx.clone(),
+                config.clone(),
+                handle.clone(),
+                redis_handle.clone(),
+                &bus,
+            ));
+        }
+        drop(pinned_task);
+
+        // Post schedule changes; the task checks whether a channel is configured
+        let mut change_feed_task = self.change_feed_task.write().await;
+        if change_feed_task.is_none() {
+            *change_feed_task = Some(spawn_change_feed(shared_ctx.clone(), config.clone(), &bus));
+        }
+        drop(change_feed_task);
+
+        // Pass on changes made through work_hours, which can't reach the bus itself
+        let mut audit_relay_task = self.audit_relay_task.write().await;
+        if audit_relay_task.is_none() {
+            *audit_relay_task = Some(spawn_audit_relay(redis_handle.clone(), bus.clone()));
+        }
+        drop(audit_relay_task);
+
+        // Keep the posted weekly notifications up to date with corrections
+        let mut weekly_edits_task = self.weekly_edits_task.write().await;
+        if weekly_edits_task.is_none() {
+            *weekly_edits_task = Some(spawn_weekly_edits(
+                shared_ctx.clone(),
+                config.clone(),
+                handle.clone(),
+                redis_handle.clone(),
+                &bus,
+            ));
+        }
+        drop(weekly_edits_task);
 
         // Start the notification scheduler only if it hasn't been started yet
-        if !SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        if !self.scheduler_started.swap(true, Ordering::SeqCst) {
             info!("Starting Work Schedule notification scheduler");
-            if let Err(e) = WorkScheduleScheduler::start(ctx, config, handle).await {
+            if let Err(e) =
+                WorkScheduleScheduler::start(shared_ctx, config, handle, redis_handle).await
+            {
                 error!("Failed to start Work Schedule scheduler: {}", e);
             }
         } else {
@@ -86,20 +190,50 @@ impl super::Component for WorkSchedule {
         Ok(())
     }
 
-    async fn shutdown(&self) -> BotResult<()> {
-        // Shutdown the handle if it exists
-        let handle_lock = self.handle.read().await;
-        if let Some(handle) = &*handle_lock {
-            handle.shutdown().await?;
+    async fn stop_background(&self) -> BotResult<()> {
+        // Stop refreshing the pinned today message
+        if let Some(task) = self.pinned_task.write().await.take() {
+            task.abort();
+        }
+
+        // Stop posting schedule changes
+        if let Some(task) = self.change_feed_task.write().await.take() {
+            task.abort();
+        }
+
+        // Stop relaying work_hours changes
+        if let Some(task) = self.audit_relay_task.write().await.take() {
+            task.abort();
+        }
+
+        // Stop updating posted weekly notifications
+        if let Some(task) = self.weekly_edits_task.write().await.take() {
+            task.abort();
         }
 
         // Stop the scheduler
         let scheduler = WorkScheduleScheduler;
         scheduler.stop().await?;
+        self.scheduler_started.store(false, Ordering::SeqCst);
 
         Ok(())
     }
 
+    async fn shutdown(&self) -> BotResult<()> {
+        // Stop the background tasks first, so they're stopped even if the handle fails to shut
+        // down and a restarted instance can start them again
+        let stopped = self.stop_background().await;
+        clear_notification_state(&WorkScheduleScheduler::component_type()).await;
+
+        // Shutdown the handle if it exists
+        let handle_lock = self.handle.read().await;
+        if let Some(handle) = &*handle_lock {
+            handle.shutdown().await?;
+        }
+
+        stopped
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
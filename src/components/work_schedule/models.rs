@@ -1,11 +1,106 @@
+use crate::components::work_schedule::employee::compare_names;
+use crate::components::work_schedule::overlap::OverlapKind;
+use serde::{Deserialize, Serialize};
+
+/// Version of the entry JSON written to Redis.
+///
+/// Version 1 entries have a single `start_time`/`end_time` pair; version 2 holds a list of
+/// shifts. Older entries are still read and converted on the fly.
+pub const ENTRY_WIRE_VERSION: u8 = 2;
+
+/// A continuous block of work within a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShiftRange {
+    /// Start time (HH:MM)
+    pub start: Option<String>,
+    /// End time (HH:MM)
+    pub end: Option<String>,
+}
+
+impl ShiftRange {
+    /// Create a shift with both a start and an end
+    pub fn new(start: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            start: Some(start.into()),
+            end: Some(end.into()),
+        }
+    }
+
+    /// Start and end in minutes since midnight, if both are known
+    pub fn minutes(&self) -> Option<(u32, u32)> {
+        Some((
+            parse_minutes(self.start.as_deref()?)?,
+            parse_minutes(self.end.as_deref()?)?,
+        ))
+    }
+
+    /// Length of the shift in minutes, if both ends are known
+    pub fn duration_minutes(&self) -> Option<u32> {
+        self.minutes().map(|(start, end)| end.saturating_sub(start))
+    }
+
+    /// Whether the shift has ended by `now` (minutes since midnight). Shifts without a known
+    /// end never have.
+    pub fn has_ended(&self, now: u32) -> bool {
+        self.end
+            .as_deref()
+            .and_then(parse_minutes)
+            .is_some_and(|end| end <= now)
+    }
+
+    /// Format the shift as a human-readable string
+    pub fn format(&self) -> String {
+        match (self.start.as_ref(), self.end.as_ref()) {
+            (Some(start), Some(end)) => {
+                t!("work_schedule_time_range", start = start, end = end).to_string()
+            }
+            (Some(start), None) => t!("work_schedule_starting_at", time = start).to_string(),
+            (None, Some(end)) => t!("work_schedule_ending_at", time = end).to_string(),
+            (None, None) => t!("work_schedule_no_hours").to_string(),
+        }
+    }
+}
+
+/// Parse a "HH:MM" time into minutes since midnight
+pub fn parse_minutes(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours <= 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Schedule cell codes meaning the employee is on vacation, stored as the entry's notes
+pub const VACATION_CODES: [&str; 3] = ["vv", "vl", "loma"];
+
+/// Discord message linked to an entry for context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextLink {
+    /// Link to the message
+    pub url: String,
+    /// Start of the message's content when it was linked
+    pub excerpt: String,
+}
+
 /// Represents a work schedule entry for an employee
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "WireEntry", into = "WireEntry")]
 pub struct WorkScheduleEntry {
     pub date: String,
-    pub start_time: Option<String>,
-    pub end_time: Option<String>,
+    /// Shifts worked on the day, in order
+    pub shifts: Vec<ShiftRange>,
     pub is_day_off: bool,
     pub notes: Option<String>,
+    /// Unpaid break taken during the day, in minutes
+    pub break_minutes: Option<u16>,
+    /// Set when the entry was merged from duplicates or otherwise looks wrong
+    pub overlap: Option<OverlapKind>,
+    /// Upload the entry was parsed from, so later edits count against that parse
+    pub upload_id: Option<String>,
+    /// Message an admin linked for context, see `/liitä_viesti`
+    pub context_link: Option<ContextLink>,
+    /// When work actually started (HH:MM), recorded afterwards with `/toteuma`
+    pub actual_start: Option<String>,
+    /// When work actually ended (HH:MM)
+    pub actual_end: Option<String>,
 }
 
 impl WorkScheduleEntry {
@@ -13,26 +108,298 @@ impl WorkScheduleEntry {
     pub fn new(date: String) -> Self {
         Self {
             date,
-            start_time: None,
-            end_time: None,
+            shifts: Vec::new(),
             is_day_off: false,
             notes: None,
+            break_minutes: None,
+            overlap: None,
+            upload_id: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
         }
     }
 
+    /// Check whether the day is marked as vacation with one of [`VACATION_CODES`]
+    pub fn is_vacation(&self) -> bool {
+        self.notes.as_deref().is_some_and(|notes| {
+            VACATION_CODES
+                .iter()
+                .any(|code| notes.trim().eq_ignore_ascii_case(code))
+        })
+    }
+
+    /// Check whether the employee works on the day
+    pub fn is_working(&self) -> bool {
+        !self.is_day_off && self.shifts.iter().any(|shift| shift.start.is_some())
+    }
+
+    /// Total working time in minutes over all shifts with a known start and end, less the
+    /// unpaid break
+    pub fn total_minutes(&self) -> u32 {
+        if self.is_day_off {
+            return 0;
+        }
+        let worked: u32 = self
+            .shifts
+            .iter()
+            .filter_map(ShiftRange::duration_minutes)
+            .sum();
+        worked.saturating_sub(self.break_minutes.map_or(0, u32::from))
+    }
+
+    /// Time actually worked in minutes, less the unpaid break, if both actual ends are recorded
+    pub fn actual_minutes(&self) -> Option<u32> {
+        let actual = ShiftRange {
+            start: self.actual_start.clone(),
+            end: self.actual_end.clone(),
+        };
+        let worked = actual.duration_minutes()?;
+        Some(worked.saturating_sub(self.break_minutes.map_or(0, u32::from)))
+    }
+
     /// Format the schedule as a human-readable string
     pub fn format(&self) -> String {
+        self.format_with_marker(None)
+    }
+
+    /// Format the schedule as of `now` (minutes since midnight), crossing out shifts that have
+    /// already ended
+    pub fn format_at(&self, now: u32) -> String {
+        self.format_with_marker(Some(now))
+    }
+
+    fn format_with_marker(&self, now: Option<u32>) -> String {
+        let text = self.format_hours(now);
+        match self.overlap {
+            Some(_) => format!("⚠️ {text}"),
+            None => text,
+        }
+    }
+
+    /// Format the working hours without any warning marker
+    fn format_hours(&self, now: Option<u32>) -> String {
         if self.is_day_off {
             return t!("work_schedule_day_off").to_string();
         }
+        if self.shifts.is_empty() {
+            return t!("work_schedule_no_hours").to_string();
+        }
 
-        match (self.start_time.as_ref(), self.end_time.as_ref()) {
-            (Some(start), Some(end)) => {
-                t!("work_schedule_time_range", start = start, end = end).to_string()
-            }
-            (Some(start), None) => t!("work_schedule_starting_at", time = start).to_string(),
-            (None, Some(end)) => t!("work_schedule_ending_at", time = end).to_string(),
-            (None, None) => t!("work_schedule_no_hours").to_string(),
+        let hours = self
+            .shifts
+            .iter()
+            .map(|shift| match now {
+                Some(now) if shift.has_ended(now) => format!("~~{}~~", shift.format()),
+                _ => shift.format(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.break_minutes {
+            Some(minutes) => format!("{hours} {}", t!("work_schedule_break", minutes = minutes)),
+            None => hours,
+        }
+    }
+}
+
+/// Entry factories for tests
+#[cfg(any(test, feature = "test-util"))]
+impl WorkScheduleEntry {
+    /// A day with one shift from `start` to `end`
+    pub fn working(date: &str, start: &str, end: &str) -> Self {
+        let mut entry = Self::new(date.to_string());
+        entry.shifts.push(ShiftRange::new(start, end));
+        entry
+    }
+
+    /// A day off
+    pub fn day_off(date: &str) -> Self {
+        let mut entry = Self::new(date.to_string());
+        entry.is_day_off = true;
+        entry
+    }
+}
+
+/// Entry JSON as stored in Redis, covering every wire version
+#[derive(Serialize, Deserialize)]
+struct WireEntry {
+    /// Missing in version 1 entries
+    #[serde(default = "legacy_version")]
+    v: u8,
+    date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shifts: Option<Vec<ShiftRange>>,
+    /// Version 1 only
+    #[serde(default, skip_serializing)]
+    start_time: Option<String>,
+    /// Version 1 only
+    #[serde(default, skip_serializing)]
+    end_time: Option<String>,
+    #[serde(default)]
+    is_day_off: bool,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    break_minutes: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overlap: Option<OverlapKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upload_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context_link: Option<ContextLink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actual_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actual_end: Option<String>,
+}
+
+fn legacy_version() -> u8 {
+    1
+}
+
+impl From<WireEntry> for WorkScheduleEntry {
+    fn from(wire: WireEntry) -> Self {
+        let shifts = match wire.shifts {
+            Some(shifts) => shifts,
+            None if wire.start_time.is_some() || wire.end_time.is_some() => vec![ShiftRange {
+                start: wire.start_time,
+                end: wire.end_time,
+            }],
+            None => Vec::new(),
+        };
+
+        Self {
+            date: wire.date,
+            shifts,
+            is_day_off: wire.is_day_off,
+            notes: wire.notes,
+            break_minutes: wire.break_minutes,
+            overlap: wire.overlap,
+            upload_id: wire.upload_id,
+            context_link: wire.context_link,
+            actual_start: wire.actual_start,
+            actual_end: wire.actual_end,
+        }
+    }
+}
+
+impl From<WorkScheduleEntry> for WireEntry {
+    fn from(entry: WorkScheduleEntry) -> Self {
+        Self {
+            v: ENTRY_WIRE_VERSION,
+            date: entry.date,
+            shifts: Some(entry.shifts),
+            start_time: None,
+            end_time: None,
+            is_day_off: entry.is_day_off,
+            notes: entry.notes,
+            break_minutes: entry.break_minutes,
+            overlap: entry.overlap,
+            upload_id: entry.upload_id,
+            context_link: entry.context_link,
+            actual_start: entry.actual_start,
+            actual_end: entry.actual_end,
+        }
+    }
+}
+
+/// Parse an entry read from Redis, also telling whether it was stored in an older format
+pub fn parse_stored_entry(json: &str) -> serde_json::Result<(WorkScheduleEntry, bool)> {
+    let wire: WireEntry = serde_json::from_str(json)?;
+    let outdated = wire.v < ENTRY_WIRE_VERSION;
+    Ok((wire.into(), outdated))
+}
+
+/// How far an employee's stored schedule reaches
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CoverageInfo {
+    pub employee: String,
+    /// Earliest stored date, if any
+    pub first_date: Option<String>,
+    /// Latest stored date, if any
+    pub last_date: Option<String>,
+    /// Number of stored dates
+    pub day_count: usize,
+    /// Number of stored dates the employee works on
+    pub working_day_count: usize,
+}
+
+impl CoverageInfo {
+    /// Coverage of an employee's stored dates and the entries read for them. Dates whose entry
+    /// is missing count as days but not as working days.
+    pub fn new(employee: String, dates: &[String], entries: &[WorkScheduleEntry]) -> Self {
+        Self {
+            employee,
+            // Dates are YYYY-MM-DD, so they sort chronologically as strings
+            first_date: dates.iter().min().cloned(),
+            last_date: dates.iter().max().cloned(),
+            day_count: dates.len(),
+            working_day_count: entries.iter().filter(|entry| entry.is_working()).count(),
+        }
+    }
+}
+
+/// The entries employees have stored for a day, kept in Finnish alphabetical order of the
+/// employee names so notifications and commands list them the same way every time, and the
+/// employees without one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DaySchedules {
+    entries: Vec<(String, WorkScheduleEntry)>,
+    missing: Vec<String>,
+}
+
+impl DaySchedules {
+    /// Employees and their entries in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &WorkScheduleEntry)> {
+        self.entries
+            .iter()
+            .map(|(employee, entry)| (employee, entry))
+    }
+
+    /// Entries in the order of their employees' names
+    pub fn values(&self) -> impl Iterator<Item = &WorkScheduleEntry> {
+        self.entries.iter().map(|(_, entry)| entry)
+    }
+
+    /// Entry of an employee, by the exact stored name
+    pub fn get(&self, employee: &str) -> Option<&WorkScheduleEntry> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == employee)
+            .map(|(_, entry)| entry)
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Employees with nothing stored for the day, in name order
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+
+    /// Set the employees with nothing stored for the day
+    pub fn with_missing(mut self, missing: impl IntoIterator<Item = String>) -> Self {
+        self.missing = missing.into_iter().collect();
+        self.missing.sort_by(|a, b| compare_names(a, b));
+        self
+    }
+
+    /// Keep only the employees, with or without an entry, `keep` returns true for
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.entries.retain(|(employee, _)| keep(employee));
+        self.missing.retain(|employee| keep(employee));
+    }
+}
+
+impl FromIterator<(String, WorkScheduleEntry)> for DaySchedules {
+    fn from_iter<I: IntoIterator<Item = (String, WorkScheduleEntry)>>(iter: I) -> Self {
+        let mut entries: Vec<_> = iter.into_iter().collect();
+        entries.sort_by(|a, b| compare_names(&a.0, &b.0));
+        Self {
+            entries,
+            missing: Vec::new(),
         }
     }
 }
@@ -43,3 +410,312 @@ pub struct EmployeeSchedule {
     pub employee: String,
     pub schedule: Vec<WorkScheduleEntry>,
 }
+
+impl EmployeeSchedule {
+    /// First working entry from `today` on, skipping days off and days with only a code.
+    ///
+    /// Today only counts while one of its shifts is still ahead of `now_minutes`.
+    pub fn next_shift(&self, today: &str, now_minutes: u32) -> Option<&WorkScheduleEntry> {
+        let mut upcoming: Vec<&WorkScheduleEntry> = self
+            .schedule
+            .iter()
+            .filter(|entry| entry.date.as_str() >= today && entry.is_working())
+            .collect();
+        upcoming.sort_by(|a, b| a.date.cmp(&b.date));
+
+        upcoming.into_iter().find(|entry| {
+            entry.date.as_str() > today
+                || entry.shifts.iter().any(|shift| {
+                    // A shift without a known end counts until it starts
+                    let last = shift.end.as_deref().or(shift.start.as_deref());
+                    last.and_then(parse_minutes)
+                        .is_some_and(|minutes| minutes > now_minutes)
+                })
+        })
+    }
+
+    /// Last date the schedule has an entry for
+    pub fn last_date(&self) -> Option<&str> {
+        self.schedule.iter().map(|entry| entry.date.as_str()).max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn split_shift() -> WorkScheduleEntry {
+        WorkScheduleEntry {
+            shifts: vec![
+                ShiftRange::new("08:00", "12:00"),
+                ShiftRange::new("16:00", "20:00"),
+            ],
+            ..WorkScheduleEntry::new("2025-01-06".to_string())
+        }
+    }
+
+    #[test]
+    fn test_v2_round_trip() {
+        let entry = split_shift();
+        let value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "v": 2,
+                "date": "2025-01-06",
+                "shifts": [
+                    {"start": "08:00", "end": "12:00"},
+                    {"start": "16:00", "end": "20:00"}
+                ],
+                "is_day_off": false,
+                "notes": null
+            })
+        );
+
+        let parsed: WorkScheduleEntry = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, entry);
+
+        let mut flagged = entry;
+        flagged.overlap = Some(OverlapKind::Nested);
+        let json = serde_json::to_string(&flagged).unwrap();
+        assert_eq!(parse_stored_entry(&json).unwrap(), (flagged, false));
+    }
+
+    #[test]
+    fn test_context_link_is_optional_on_the_wire() {
+        // Entries stored before context links existed read without one
+        let stored = r#"{"v":2,"date":"2025-01-06","shifts":[],"is_day_off":true,"notes":null}"#;
+        let (entry, outdated) = parse_stored_entry(stored).unwrap();
+        assert!(!outdated);
+        assert_eq!(entry.context_link, None);
+
+        let mut linked = split_shift();
+        linked.context_link = Some(ContextLink {
+            url: "https://discord.com/channels/1/2/3".to_string(),
+            excerpt: "Vaihdetaan vuoroja".to_string(),
+        });
+        let value = serde_json::to_value(&linked).unwrap();
+        assert_eq!(
+            value["context_link"],
+            json!({
+                "url": "https://discord.com/channels/1/2/3",
+                "excerpt": "Vaihdetaan vuoroja"
+            })
+        );
+        assert_eq!(
+            parse_stored_entry(&value.to_string()).unwrap(),
+            (linked, false)
+        );
+    }
+
+    #[test]
+    fn test_v1_entries_are_migrated() {
+        let legacy = r#"{"date":"2025-01-06","start_time":"07:00","end_time":"15:00","is_day_off":false,"notes":"Kassa"}"#;
+        let (entry, outdated) = parse_stored_entry(legacy).unwrap();
+        assert!(outdated);
+        assert_eq!(entry.shifts, vec![ShiftRange::new("07:00", "15:00")]);
+        assert_eq!(entry.notes.as_deref(), Some("Kassa"));
+
+        // Writing it back produces the new format without the legacy fields
+        let value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(value["v"], 2);
+        assert!(value.get("start_time").is_none());
+        assert_eq!(
+            parse_stored_entry(&value.to_string()).unwrap(),
+            (entry, false)
+        );
+
+        let start_only = r#"{"date":"2025-01-06","start_time":"07:00","end_time":null,"is_day_off":false,"notes":null}"#;
+        let entry: WorkScheduleEntry = serde_json::from_str(start_only).unwrap();
+        assert_eq!(
+            entry.shifts,
+            vec![ShiftRange {
+                start: Some("07:00".to_string()),
+                end: None
+            }]
+        );
+
+        let day_off = r#"{"date":"2025-01-06","start_time":null,"end_time":null,"is_day_off":true,"notes":null}"#;
+        let entry: WorkScheduleEntry = serde_json::from_str(day_off).unwrap();
+        assert!(entry.shifts.is_empty());
+        assert!(entry.is_day_off);
+    }
+
+    #[test]
+    fn test_legacy_duplicates_array_is_readable() {
+        let legacy = r#"[{"date":"2025-01-06","start_time":"08:00","end_time":"12:00","is_day_off":false,"notes":null,"overlap":"nested"}]"#;
+        let entries: Vec<WorkScheduleEntry> = serde_json::from_str(legacy).unwrap();
+        assert_eq!(entries[0].shifts, vec![ShiftRange::new("08:00", "12:00")]);
+        assert_eq!(entries[0].overlap, Some(OverlapKind::Nested));
+    }
+
+    #[test]
+    fn test_format_shifts() {
+        assert_eq!(split_shift().format(), "08:00–12:00, 16:00–20:00");
+
+        let mut single = WorkScheduleEntry::new("2025-01-06".to_string());
+        single.shifts.push(ShiftRange::new("07:00", "15:00"));
+        assert_eq!(single.format(), "07:00–15:00");
+
+        single.shifts.push(ShiftRange {
+            start: Some("18:00".to_string()),
+            end: None,
+        });
+        assert_eq!(single.format(), "07:00–15:00, Starting at 18:00");
+
+        let mut flagged = split_shift();
+        flagged.overlap = Some(OverlapKind::Overlapping);
+        assert_eq!(flagged.format(), "⚠️ 08:00–12:00, 16:00–20:00");
+
+        let empty = WorkScheduleEntry::new("2025-01-06".to_string());
+        assert_eq!(empty.format(), t!("work_schedule_no_hours"));
+
+        let mut day_off = split_shift();
+        day_off.is_day_off = true;
+        assert_eq!(day_off.format(), t!("work_schedule_day_off"));
+    }
+
+    #[test]
+    fn test_next_shift_skips_days_off_and_codes() {
+        let entry = |date: &str, shifts: Vec<ShiftRange>| WorkScheduleEntry {
+            shifts,
+            ..WorkScheduleEntry::new(date.to_string())
+        };
+        let mut day_off = entry("2025-01-07", Vec::new());
+        day_off.is_day_off = true;
+        // A training code without any hours
+        let mut code_only = entry("2025-01-09", Vec::new());
+        code_only.notes = Some("K".to_string());
+
+        let schedule = EmployeeSchedule {
+            employee: "Pekka".to_string(),
+            // Stored out of order, with gaps between the dates
+            schedule: vec![
+                entry("2025-01-13", vec![ShiftRange::new("10:00", "18:00")]),
+                code_only,
+                entry("2025-01-03", vec![ShiftRange::new("08:00", "16:00")]),
+                day_off,
+                entry("2025-01-06", vec![ShiftRange::new("08:00", "12:00")]),
+            ],
+        };
+
+        // Today's shift is still ahead
+        let next = schedule.next_shift("2025-01-06", 7 * 60).unwrap();
+        assert_eq!(next.date, "2025-01-06");
+
+        // Once it ends, the day off and the code-only day are skipped
+        let next = schedule.next_shift("2025-01-06", 12 * 60).unwrap();
+        assert_eq!(next.date, "2025-01-13");
+
+        assert!(schedule.next_shift("2025-01-14", 0).is_none());
+        assert_eq!(schedule.last_date(), Some("2025-01-13"));
+        assert_eq!(EmployeeSchedule::default().last_date(), None);
+    }
+
+    #[test]
+    fn test_total_minutes_sums_all_shifts() {
+        assert_eq!(split_shift().total_minutes(), 8 * 60);
+        assert!(split_shift().is_working());
+
+        let mut partial = split_shift();
+        partial.shifts.push(ShiftRange {
+            start: Some("21:00".to_string()),
+            end: None,
+        });
+        assert_eq!(partial.total_minutes(), 8 * 60);
+
+        let mut day_off = split_shift();
+        day_off.is_day_off = true;
+        assert_eq!(day_off.total_minutes(), 0);
+        assert!(!day_off.is_working());
+    }
+
+    #[test]
+    fn test_break_is_stored_subtracted_and_shown() {
+        let entry = WorkScheduleEntry {
+            shifts: vec![ShiftRange::new("09:00", "17:00")],
+            break_minutes: Some(30),
+            ..WorkScheduleEntry::new("2025-01-06".to_string())
+        };
+        assert_eq!(entry.total_minutes(), 7 * 60 + 30);
+        assert_eq!(entry.format(), "09:00–17:00 (30 min break)");
+
+        let value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(value["break_minutes"], 30);
+        let (parsed, outdated) = parse_stored_entry(&value.to_string()).unwrap();
+        assert_eq!(parsed, entry);
+        assert!(!outdated);
+
+        // Entries stored before breaks existed have none
+        assert_eq!(split_shift().break_minutes, None);
+        assert!(serde_json::to_value(split_shift())
+            .unwrap()
+            .get("break_minutes")
+            .is_none());
+    }
+
+    #[test]
+    fn test_ended_shifts_are_crossed_out() {
+        let entry = split_shift();
+        // Before, during and after the first shift, and after both
+        assert_eq!(entry.format_at(7 * 60), "08:00–12:00, 16:00–20:00");
+        assert_eq!(entry.format_at(11 * 60 + 59), "08:00–12:00, 16:00–20:00");
+        assert_eq!(entry.format_at(12 * 60), "~~08:00–12:00~~, 16:00–20:00");
+        assert_eq!(entry.format_at(21 * 60), "~~08:00–12:00~~, ~~16:00–20:00~~");
+
+        // Shifts without a known end never end, and day off texts aren't crossed out
+        let open_ended = WorkScheduleEntry {
+            shifts: vec![ShiftRange {
+                start: Some("18:00".to_string()),
+                end: None,
+            }],
+            ..WorkScheduleEntry::new("2025-01-06".to_string())
+        };
+        assert_eq!(open_ended.format_at(23 * 60), "Starting at 18:00");
+        let mut day_off = split_shift();
+        day_off.is_day_off = true;
+        assert_eq!(day_off.format_at(23 * 60), t!("work_schedule_day_off"));
+    }
+
+    #[test]
+    fn test_coverage_of_stored_dates() {
+        let dates = |dates: &[&str]| dates.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        let working = |date: &str| WorkScheduleEntry {
+            shifts: vec![ShiftRange::new("08:00", "16:00")],
+            ..WorkScheduleEntry::new(date.to_string())
+        };
+        let day_off = |date: &str| WorkScheduleEntry {
+            is_day_off: true,
+            ..WorkScheduleEntry::new(date.to_string())
+        };
+
+        let empty = CoverageInfo::new("Anna".to_string(), &[], &[]);
+        assert_eq!(empty.first_date, None);
+        assert_eq!(empty.last_date, None);
+        assert_eq!((empty.day_count, empty.working_day_count), (0, 0));
+
+        // Set members come in any order, and a date may have lost its entry
+        let sparse = CoverageInfo::new(
+            "Bertil".to_string(),
+            &dates(&["2025-03-20", "2025-01-06", "2025-02-14"]),
+            &[working("2025-01-06"), day_off("2025-03-20")],
+        );
+        assert_eq!(sparse.first_date.as_deref(), Some("2025-01-06"));
+        assert_eq!(sparse.last_date.as_deref(), Some("2025-03-20"));
+        assert_eq!((sparse.day_count, sparse.working_day_count), (3, 1));
+
+        let week: Vec<String> = (6..=12).map(|day| format!("2025-01-{day:02}")).collect();
+        let entries: Vec<WorkScheduleEntry> = week
+            .iter()
+            .map(|date| match date.as_str() {
+                "2025-01-11" | "2025-01-12" => day_off(date),
+                _ => working(date),
+            })
+            .collect();
+        let dense = CoverageInfo::new("Cecilia".to_string(), &week, &entries);
+        assert_eq!(dense.first_date.as_deref(), Some("2025-01-06"));
+        assert_eq!(dense.last_date.as_deref(), Some("2025-01-12"));
+        assert_eq!((dense.day_count, dense.working_day_count), (7, 5));
+    }
+}
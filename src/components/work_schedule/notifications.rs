@@ -1,35 +1,79 @@
+use crate::components::work_schedule::diff::{week_over_week, DayChange};
+use crate::components::work_schedule::groups::EmployeeFilter;
 use crate::components::work_schedule::handle::WorkScheduleHandle;
+use crate::components::work_schedule::models::{DaySchedules, WorkScheduleEntry};
+use crate::components::work_schedule::render::{no_data_line, ScheduleFormatter};
+use crate::components::work_schedule::stats::HoursBudget;
+use crate::components::work_schedule::weekly_edits::weekly_messages_key;
 use crate::error::{work_schedule_error, BotResult};
-use chrono::{Duration, NaiveDate};
-use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use crate::theme::{Theme, ThemeColor};
+use crate::utils::i18n::weekday_short_name;
+use crate::utils::notifier::{DailyReplace, Delivery, Notification, NotificationSink};
+use crate::utils::time::week_label;
+use chrono::{Datelike, Duration, NaiveDate};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
 use rust_i18n::t;
 use tracing::info;
 
-/// Send daily notification for today's work schedule
-pub async fn send_daily_notification(
-    ctx: &serenity::Context,
-    channel_id: u64,
-    handle: &WorkScheduleHandle,
-    date: &str,
-) -> BotResult<()> {
-    info!("Sending daily work schedule notification for {}", date);
+/// Most lines the weekly notification's changes section lists before collapsing the rest
+const MAX_WEEK_CHANGE_LINES: usize = 10;
 
-    // Get schedule for all employees for today
-    let schedules = handle.get_schedule_for_date(date).await?;
+/// Add a note explaining the ⚠️ marker if any of the entries were flagged
+fn with_overlap_note<'a>(
+    embed: CreateEmbed,
+    entries: impl IntoIterator<Item = &'a WorkScheduleEntry>,
+) -> CreateEmbed {
+    if entries.into_iter().any(|entry| entry.overlap.is_some()) {
+        embed.footer(CreateEmbedFooter::new(t!("work_schedule_overlap_note")))
+    } else {
+        embed
+    }
+}
 
+/// Fetch the schedules of a day and the day after for the employees passing `filter`, and
+/// build the daily notification
+pub async fn build_daily_notification(
+    handle: &WorkScheduleHandle,
+    date: &str,
+    filter: &EmployeeFilter,
+    theme: &Theme,
+) -> BotResult<Notification> {
     // Calculate tomorrow's date
     let today = NaiveDate::parse_from_str(date, "%Y-%m-%d")
         .map_err(|e| work_schedule_error(&format!("Failed to parse date: {e}")))?;
-    let tomorrow = today + Duration::days(1);
-    let tomorrow_str = tomorrow.format("%Y-%m-%d").to_string();
+    let tomorrow = (today + Duration::days(1)).format("%Y-%m-%d").to_string();
 
-    // Get schedule for all employees for tomorrow
-    let tomorrow_schedules = handle.get_schedule_for_date(&tomorrow_str).await?;
+    let mut schedules = handle.get_schedule_for_date(date).await?;
+    let mut tomorrow_schedules = handle.get_schedule_for_date(&tomorrow).await?;
+    schedules.retain(|employee| filter.allows(employee));
+    tomorrow_schedules.retain(|employee| filter.allows(employee));
+
+    let formatter = handle.formatter().await;
+    let notification = daily_notification(
+        date,
+        &tomorrow,
+        &schedules,
+        &tomorrow_schedules,
+        &formatter,
+        theme,
+    );
+    handle.record_missing_notes(&formatter).await;
+    Ok(notification)
+}
 
+/// Build the daily notification from the schedules of a day and the day after
+fn daily_notification(
+    date: &str,
+    tomorrow_str: &str,
+    schedules: &DaySchedules,
+    tomorrow_schedules: &DaySchedules,
+    formatter: &ScheduleFormatter,
+    theme: &Theme,
+) -> Notification {
     // Create an embed for the notification
     let mut embed = CreateEmbed::new()
         .title(t!("work_schedule_daily_title", date = date))
-        .color(0x00_FF_00); // Green color
+        .color(theme.color_or(ThemeColor::Success, 0x00_FF_00)); // Green by default
 
     // Handle today's schedules
     if schedules.is_empty() {
@@ -51,12 +95,15 @@ pub async fn send_daily_notification(
         } else {
             // Add today's schedules
             embed = embed.field(t!("work_schedule_today_section"), "\u{200B}", false);
-            for (employee, entry) in &schedules {
-                let schedule_text = entry.format();
-                embed = embed.field(employee, schedule_text, true);
+            for (employee, entry) in schedules.iter() {
+                let schedule_text = formatter.format(entry);
+                embed = embed.field(formatter.employee(employee), schedule_text, true);
             }
         }
     }
+    if let Some(line) = no_data_line(schedules) {
+        embed = embed.field("\u{200B}", line, false);
+    }
 
     // Handle tomorrow's schedules
     embed = embed.field("\u{200B}", "\u{200B}", false); // Empty field as separator
@@ -84,140 +131,467 @@ pub async fn send_daily_notification(
                 "\u{200B}",
                 false,
             );
-            for (employee, entry) in &tomorrow_schedules {
-                let schedule_text = entry.format();
-                embed = embed.field(employee, schedule_text, true);
+            for (employee, entry) in tomorrow_schedules.iter() {
+                let schedule_text = formatter.format(entry);
+                embed = embed.field(formatter.employee(employee), schedule_text, true);
             }
         }
     }
+    if let Some(line) = no_data_line(tomorrow_schedules) {
+        embed = embed.field("\u{200B}", line, false);
+    }
 
-    // Add a happy GIF if everyone has a day off today
+    // Add a happy GIF if everyone with an entry has a day off today
     let all_day_off_today =
-        schedules.is_empty() || schedules.iter().all(|(_, entry)| entry.is_day_off);
+        !schedules.is_empty() && schedules.iter().all(|(_, entry)| entry.is_day_off);
 
     if all_day_off_today {
         embed = embed.image("https://media2.giphy.com/media/v1.Y2lkPTc5MGI3NjExYnp2ZzRxZ2o3MDJ3Ymtrbm8wa25nZDA5a2N5a3V6eDY4cXBqMHhvaSZlcD12MV9pbnRlcm5hbF9naWZfYnlfaWQmY3Q9Zw/Xf8D9Qf8OCKnMvNnru/giphy.gif");
     }
 
-    // Send the notification
-    ChannelId::new(channel_id)
-        .send_message(
-            ctx,
-            CreateMessage::new()
-                .content(t!("work_schedule_daily_greeting"))
-                .embed(embed),
-        )
-        .await
-        .map_err(|e| work_schedule_error(&format!("Failed to send message: {e}")))?;
+    let embed = with_overlap_note(embed, schedules.values().chain(tomorrow_schedules.values()));
 
-    Ok(())
+    Notification {
+        content: Some(t!("work_schedule_daily_greeting").to_string()),
+        embed: theme.brand(embed),
+    }
 }
 
-/// Send weekly notification for the upcoming week's work schedule
-pub async fn send_weekly_notification(
-    ctx: &serenity::Context,
+/// Send daily notification for today's work schedule of the employees passing `filter`
+pub async fn send_daily_notification(
+    sink: &dyn NotificationSink,
     channel_id: u64,
     handle: &WorkScheduleHandle,
-    start_date: &str,
-    end_date: &str,
+    date: &str,
+    filter: &EmployeeFilter,
+    mode: DailyReplace,
+    theme: &Theme,
 ) -> BotResult<()> {
     info!(
-        "Sending weekly work schedule notification for {} to {}",
-        start_date, end_date
+        "Sending daily work schedule notification for {} to channel {}",
+        date, channel_id
     );
 
-    // Get all employees
-    let employees = handle.get_employees().await?;
+    let notification = build_daily_notification(handle, date, filter, theme).await?;
+    sink.deliver(
+        &Delivery::daily("work_schedule", channel_id, mode),
+        &notification,
+    )
+    .await
+}
 
-    if employees.is_empty() {
+/// Fetch the entries of every employee passing `filter` for the week and build the weekly
+/// notification.
+///
+/// Employees with contract hours in `budget` get their weekly total compared against them.
+pub async fn build_weekly_notification(
+    handle: &WorkScheduleHandle,
+    start_date: &str,
+    end_date: &str,
+    budget: &HoursBudget,
+    filter: &EmployeeFilter,
+    theme: &Theme,
+) -> BotResult<Notification> {
+    // The same weekdays of the week before, to list what changed
+    let previous_week = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(|date| (date - Duration::days(7)).format("%Y-%m-%d").to_string())
+    };
+    let previous_range = previous_week(start_date).zip(previous_week(end_date));
+
+    let mut schedules = Vec::new();
+    let mut last_week = Vec::new();
+    for employee in handle.get_employees().await? {
+        if !filter.allows(&employee) {
+            continue;
+        }
+        let schedule = handle
+            .get_schedule_for_date_range(&employee, start_date, end_date)
+            .await?;
+        if let Some((start, end)) = &previous_range {
+            let previous = handle
+                .get_schedule_for_date_range(&employee, start, end)
+                .await?;
+            last_week.push((employee.clone(), previous.schedule));
+        }
+        schedules.push((employee, schedule.schedule));
+    }
+
+    let formatter = handle.formatter().await;
+    let notification = weekly_notification(
+        start_date, end_date, &schedules, &last_week, budget, &formatter, theme,
+    );
+    handle.record_missing_notes(&formatter).await;
+    notification
+}
+
+/// Build the weekly notification from each employee's entries for the week, listing the days
+/// that differ from `last_week`
+fn weekly_notification(
+    start_date: &str,
+    end_date: &str,
+    schedules: &[(String, Vec<WorkScheduleEntry>)],
+    last_week: &[(String, Vec<WorkScheduleEntry>)],
+    budget: &HoursBudget,
+    formatter: &ScheduleFormatter,
+    theme: &Theme,
+) -> BotResult<Notification> {
+    let range = NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
+        .ok()
+        .zip(NaiveDate::parse_from_str(end_date, "%Y-%m-%d").ok());
+    let mut title = t!(
+        "work_schedule_weekly_title",
+        start_date = start_date,
+        end_date = end_date
+    )
+    .to_string();
+    if let Some((_, last)) = range {
+        title = format!("{title} · {}", week_label(last, &rust_i18n::locale()));
+    }
+    let content = Some(t!("work_schedule_weekly_greeting").to_string());
+
+    if schedules.is_empty() {
         // If there are no employees, send an embed message indicating that
         let embed = CreateEmbed::new()
-            .title(t!(
-                "work_schedule_weekly_title",
-                start_date = start_date,
-                end_date = end_date
-            ))
+            .title(title)
             .description(t!("work_schedule_no_employees"))
-            .color(0x00_AA_FF);
-
-        ChannelId::new(channel_id)
-            .send_message(
-                ctx,
-                CreateMessage::new()
-                    .content(t!("work_schedule_weekly_greeting"))
-                    .embed(embed),
-            )
-            .await
-            .map_err(|e| work_schedule_error(&format!("Failed to send message: {e}")))?;
-        return Ok(());
+            .color(theme.color_or(ThemeColor::Primary, 0x00_AA_FF));
+        return Ok(Notification {
+            content,
+            embed: theme.brand(embed),
+        });
     }
 
     // Create an embed for the notification
     let mut embed = CreateEmbed::new()
-        .title(t!(
-            "work_schedule_weekly_title",
-            start_date = start_date,
-            end_date = end_date
-        ))
-        .color(0x00_00_FF); // Blue color
-
-    // For each employee, get their schedule for the week
-    for employee in employees {
-        let schedule = handle
-            .get_schedule_for_date_range(&employee, start_date, end_date)
-            .await?;
+        .title(title)
+        .color(theme.color_or(ThemeColor::Primary, 0x00_00_FF)); // Blue by default
 
+    // For each employee, describe their schedule for the week
+    let mut flagged = Vec::new();
+    for (employee, entries) in schedules.iter() {
         // Create a string representation of the schedule
         let mut schedule_text = String::new();
-        for entry in &schedule.schedule {
+        for entry in entries {
             // Parse date to get day of week
             let naive_date = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d")
                 .map_err(|e| work_schedule_error(&format!("Failed to parse date: {e}")))?;
 
             // Format the day name (e.g., "Mon") and date (e.g., "2025-04-01")
-            let weekday_num = naive_date
-                .format("%u")
-                .to_string()
-                .parse::<u32>()
-                .unwrap_or(0);
-            let day_name = match weekday_num {
-                1 => t!("day_short_monday"),
-                2 => t!("day_short_tuesday"),
-                3 => t!("day_short_wednesday"),
-                4 => t!("day_short_thursday"),
-                5 => t!("day_short_friday"),
-                6 => t!("day_short_saturday"),
-                7 => t!("day_short_sunday"),
-                _ => t!("day_short_unknown"),
-            };
+            let day_name = weekday_short_name(naive_date.weekday());
 
             // Format the schedule entry with day name
             schedule_text.push_str(&format!(
                 "**{}** ({}): {}\n",
                 day_name,
                 entry.date,
-                entry.format()
+                formatter.format(entry)
             ));
         }
 
+        // Compare the week against the employee's contract
+        if let Some((start, end)) = range.filter(|_| !schedule_text.is_empty()) {
+            for line in budget.week_summaries(employee, entries, start, end) {
+                schedule_text.push_str(&line);
+                schedule_text.push('\n');
+            }
+        }
+
+        flagged.extend(entries.iter().filter(|entry| entry.overlap.is_some()));
+
         // Add the employee's schedule to the embed or indicate no schedule
         if schedule_text.is_empty() {
-            embed = embed.field(employee, t!("work_schedule_no_entries_found"), false);
+            embed = embed.field(
+                formatter.employee(employee),
+                t!("work_schedule_no_entries_found"),
+                false,
+            );
         } else {
-            embed = embed.field(employee, schedule_text, false);
+            embed = embed.field(formatter.employee(employee), schedule_text, false);
         }
     }
 
-    // Send the notification
-    ChannelId::new(channel_id)
-        .send_message(
-            ctx,
-            CreateMessage::new()
-                .content(t!("work_schedule_weekly_greeting"))
-                .embed(embed),
+    let changes = week_over_week(schedules, last_week);
+    if !changes.is_empty() {
+        embed = embed.field(
+            t!("work_schedule_week_changes_title"),
+            week_changes_text(&changes, formatter),
+            false,
+        );
+    }
+
+    Ok(Notification {
+        content,
+        embed: theme.brand(with_overlap_note(embed, flagged)),
+    })
+}
+
+/// One line per changed day, collapsing the lines past [`MAX_WEEK_CHANGE_LINES`]
+fn week_changes_text(changes: &[DayChange], formatter: &ScheduleFormatter) -> String {
+    let mut lines: Vec<String> = changes
+        .iter()
+        .take(MAX_WEEK_CHANGE_LINES)
+        .map(|change| {
+            let day_name = NaiveDate::parse_from_str(&change.after.date, "%Y-%m-%d")
+                .map(|date| weekday_short_name(date.weekday()))
+                .unwrap_or_default();
+            t!(
+                "work_schedule_week_change_line",
+                employee = formatter.employee(&change.employee),
+                day = day_name,
+                before = formatter.format(&change.before),
+                after = formatter.format(&change.after)
+            )
+            .to_string()
+        })
+        .collect();
+    if changes.len() > MAX_WEEK_CHANGE_LINES {
+        lines.push(
+            t!(
+                "work_schedule_week_changes_more",
+                count = changes.len() - MAX_WEEK_CHANGE_LINES
+            )
+            .to_string(),
+        );
+    }
+    lines.join("\n")
+}
+
+/// Send weekly notification for the upcoming week's work schedule of the employees passing
+/// `filter`.
+///
+/// Employees with contract hours in `budget` get their weekly total compared against them.
+/// `source_image` is a file name and its bytes, attached next to the summary when given.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_weekly_notification(
+    sink: &dyn NotificationSink,
+    channel_id: u64,
+    handle: &WorkScheduleHandle,
+    start_date: &str,
+    end_date: &str,
+    budget: &HoursBudget,
+    filter: &EmployeeFilter,
+    source_image: Option<(String, Vec<u8>)>,
+    theme: &Theme,
+) -> BotResult<()> {
+    info!(
+        "Sending weekly work schedule notification for {} to {} to channel {}",
+        start_date, end_date, channel_id
+    );
+
+    let notification =
+        build_weekly_notification(handle, start_date, end_date, budget, filter, theme).await?;
+    sink.deliver(
+        &Delivery::once("work_schedule", channel_id)
+            .attach(source_image)
+            .remember_in(weekly_messages_key(start_date)?),
+        &notification,
+    )
+    .await
+    .map_err(|e| work_schedule_error(&format!("Failed to send message: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::stats::ContractHours;
+    use crate::utils::embed::render_embed;
+
+    #[test]
+    fn test_daily_notification_snapshot() {
+        // Ö sorts after Z in Finnish, not next to O
+        let today = DaySchedules::from_iter([
+            (
+                "Öhman".to_string(),
+                WorkScheduleEntry::working("2025-03-10", "09:00", "17:00"),
+            ),
+            (
+                "Pekka".to_string(),
+                WorkScheduleEntry::working("2025-03-10", "12:00", "20:00"),
+            ),
+            (
+                "Anna".to_string(),
+                WorkScheduleEntry::working("2025-03-10", "07:00", "15:00"),
+            ),
+            (
+                "Oona".to_string(),
+                WorkScheduleEntry::working("2025-03-10", "10:00", "18:00"),
+            ),
+        ]);
+        // Employees without a stored entry are named once instead of getting a blank row each,
+        // and don't stop the day counting as everyone's day off
+        let tomorrow = DaySchedules::from_iter([
+            (
+                "Pekka".to_string(),
+                WorkScheduleEntry::day_off("2025-03-11"),
+            ),
+            ("Anna".to_string(), WorkScheduleEntry::day_off("2025-03-11")),
+        ])
+        .with_missing(["Öhman".to_string(), "Oona".to_string()]);
+
+        let notification = daily_notification(
+            "2025-03-10",
+            "2025-03-11",
+            &today,
+            &tomorrow,
+            &ScheduleFormatter::default(),
+            &Theme::default(),
+        );
+        assert_eq!(
+            notification.content.as_deref(),
+            Some("Good morning! Here's today's and tomorrow's work schedules:")
+        );
+        let expected = "\
+# Work Schedules (2025-03-10)
+## Today's Schedule
+\u{200B}
+## Anna
+07:00–15:00
+## Oona
+10:00–18:00
+## Pekka
+12:00–20:00
+## Öhman
+09:00–17:00
+## \u{200B}
+\u{200B}
+## Tomorrow's Schedule (2025-03-11)
+Everyone has a day off today! Time to celebrate! 🎉
+## \u{200B}
+No data: Oona, Öhman
+";
+        assert_eq!(render_embed(&notification.embed), expected);
+    }
+
+    #[test]
+    fn test_weekly_notification_snapshot() {
+        let schedules = vec![
+            (
+                "Anna".to_string(),
+                vec![
+                    WorkScheduleEntry::working("2025-03-10", "08:00", "16:00"),
+                    WorkScheduleEntry::working("2025-03-11", "08:00", "16:00"),
+                    WorkScheduleEntry::day_off("2025-03-12"),
+                ],
+            ),
+            ("Pekka".to_string(), Vec::new()),
+        ];
+        let budget = HoursBudget::new(
+            [ContractHours {
+                employee: "Anna".to_string(),
+                hours_per_week: 16.0,
+            }],
+            2.0,
+        );
+
+        let notification = weekly_notification(
+            "2025-03-10",
+            "2025-03-16",
+            &schedules,
+            &[],
+            &budget,
+            &ScheduleFormatter::default(),
+            &Theme::default(),
         )
-        .await
-        .map_err(|e| work_schedule_error(&format!("Failed to send message: {e}")))?;
+        .unwrap();
+        let expected = "\
+# Weekly Work Schedule (2025-03-10 to 2025-03-16) · Week 11
+## Anna
+**Mon** (2025-03-10): 08:00–16:00
+**Tue** (2025-03-11): 08:00–16:00
+**Wed** (2025-03-12): Day off
+Σ 16 h / 16 h
 
-    Ok(())
+## Pekka
+No schedule entries found.
+";
+        assert_eq!(render_embed(&notification.embed), expected);
+
+        let notification = weekly_notification(
+            "2025-03-10",
+            "2025-03-16",
+            &[],
+            &[],
+            &budget,
+            &ScheduleFormatter::default(),
+            &Theme::default(),
+        )
+        .unwrap();
+        let expected = "\
+# Weekly Work Schedule (2025-03-10 to 2025-03-16) · Week 11
+No employees found with schedules.
+";
+        assert_eq!(render_embed(&notification.embed), expected);
+    }
+
+    #[test]
+    fn test_weekly_notification_lists_changes_vs_last_week() {
+        let schedules = vec![
+            (
+                "Anna".to_string(),
+                vec![
+                    WorkScheduleEntry::working("2025-03-10", "09:00", "17:00"),
+                    WorkScheduleEntry::day_off("2025-03-11"),
+                ],
+            ),
+            (
+                "Pekka".to_string(),
+                vec![WorkScheduleEntry::working("2025-03-10", "08:00", "16:00")],
+            ),
+        ];
+        let last_week = vec![
+            (
+                "Anna".to_string(),
+                vec![
+                    WorkScheduleEntry::working("2025-03-03", "08:00", "16:00"),
+                    WorkScheduleEntry::working("2025-03-04", "08:00", "16:00"),
+                ],
+            ),
+            (
+                "Pekka".to_string(),
+                vec![WorkScheduleEntry::working("2025-03-03", "08:00", "16:00")],
+            ),
+        ];
+
+        let notification = weekly_notification(
+            "2025-03-10",
+            "2025-03-16",
+            &schedules,
+            &last_week,
+            &HoursBudget::new([], 2.0),
+            &ScheduleFormatter::default(),
+            &Theme::default(),
+        )
+        .unwrap();
+        let expected = "\
+# Weekly Work Schedule (2025-03-10 to 2025-03-16) · Week 11
+## Anna
+**Mon** (2025-03-10): 09:00–17:00
+**Tue** (2025-03-11): Day off
+
+## Pekka
+**Mon** (2025-03-10): 08:00–16:00
+
+## Changes vs last week
+Anna, Mon: 08:00–16:00 → 09:00–17:00
+Anna, Tue: 08:00–16:00 → Day off
+";
+        assert_eq!(render_embed(&notification.embed), expected);
+    }
+
+    #[test]
+    fn test_week_changes_are_capped() {
+        let changes: Vec<DayChange> = (0..12)
+            .map(|n| DayChange {
+                employee: format!("E{n}"),
+                before: WorkScheduleEntry::working("2025-03-03", "08:00", "16:00"),
+                after: WorkScheduleEntry::day_off("2025-03-10"),
+            })
+            .collect();
+
+        let text = week_changes_text(&changes, &ScheduleFormatter::default());
+        assert_eq!(text.lines().count(), 11);
+        assert_eq!(text.lines().last(), Some("+2 more"));
+    }
 }
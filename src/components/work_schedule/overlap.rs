@@ -0,0 +1,277 @@
+use crate::components::work_schedule::models::{ShiftRange, WorkScheduleEntry};
+use serde::{Deserialize, Serialize};
+
+/// How entries for the same employee and date relate to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapKind {
+    /// The entries are exact copies of each other
+    Identical,
+    /// One shift lies completely inside the other
+    Nested,
+    /// The shifts partially overlap
+    Overlapping,
+    /// The entries can't be merged, e.g. a split shift or a shift and a day off
+    Conflicting,
+    /// The shift starts and ends at the same time
+    ZeroLength,
+}
+
+impl OverlapKind {
+    /// Locale key describing the kind
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            OverlapKind::Identical => "overlap_identical",
+            OverlapKind::Nested => "overlap_nested",
+            OverlapKind::Overlapping => "overlap_overlapping",
+            OverlapKind::Conflicting => "overlap_conflicting",
+            OverlapKind::ZeroLength => "overlap_zero_length",
+        }
+    }
+}
+
+/// Which of the duplicate entries to keep when resolving them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepChoice {
+    First,
+    Last,
+}
+
+/// Duplicate entries stored for one employee and date
+#[derive(Debug, Clone)]
+pub struct DuplicateShift {
+    pub employee: String,
+    pub date: String,
+    pub entries: Vec<WorkScheduleEntry>,
+    pub kind: OverlapKind,
+}
+
+/// Working time of an entry as minute ranges, if every shift has both a start and an end
+fn ranges(entry: &WorkScheduleEntry) -> Option<Vec<(u32, u32)>> {
+    if entry.is_day_off || entry.shifts.is_empty() {
+        return None;
+    }
+    entry.shifts.iter().map(ShiftRange::minutes).collect()
+}
+
+/// Check whether every range of `inner` lies inside some range of `outer`
+fn covers(outer: &[(u32, u32)], inner: &[(u32, u32)]) -> bool {
+    inner
+        .iter()
+        .all(|i| outer.iter().any(|o| o.0 <= i.0 && i.1 <= o.1))
+}
+
+/// Check whether any ranges of the two lists overlap
+fn intersects(a: &[(u32, u32)], b: &[(u32, u32)]) -> bool {
+    a.iter().any(|a| b.iter().any(|b| a.0 < b.1 && b.0 < a.1))
+}
+
+/// Classify how two entries for the same date relate
+pub fn classify(a: &WorkScheduleEntry, b: &WorkScheduleEntry) -> OverlapKind {
+    match (ranges(a), ranges(b)) {
+        (Some(a), Some(b)) if a == b => OverlapKind::Identical,
+        (Some(a), Some(b)) if covers(&a, &b) || covers(&b, &a) => OverlapKind::Nested,
+        (Some(a), Some(b)) if intersects(&a, &b) => OverlapKind::Overlapping,
+        (None, None) if a.is_day_off == b.is_day_off && a.shifts == b.shifts => {
+            OverlapKind::Identical
+        }
+        _ => OverlapKind::Conflicting,
+    }
+}
+
+/// Check whether any of an entry's shifts has zero length
+fn is_zero_length(entry: &WorkScheduleEntry) -> bool {
+    ranges(entry).is_some_and(|ranges| ranges.iter().any(|(start, end)| start == end))
+}
+
+/// Join the shifts of two entries, combining the ones that overlap
+fn union_shifts(a: &WorkScheduleEntry, b: &WorkScheduleEntry) -> Vec<ShiftRange> {
+    let mut shifts: Vec<(u32, &ShiftRange)> = a
+        .shifts
+        .iter()
+        .chain(&b.shifts)
+        .filter_map(|shift| shift.minutes().map(|(start, _)| (start, shift)))
+        .collect();
+    shifts.sort_by_key(|(start, _)| *start);
+
+    let mut merged: Vec<ShiftRange> = Vec::new();
+    for (_, shift) in shifts {
+        if let Some(last) = merged.last_mut() {
+            if let (Some(current), Some(next)) = (last.minutes(), shift.minutes()) {
+                if next.0 <= current.1 {
+                    if next.1 > current.1 {
+                        last.end = shift.end.clone();
+                    }
+                    continue;
+                }
+            }
+        }
+        merged.push(shift.clone());
+    }
+    merged
+}
+
+/// Merge the entries stored for one employee and date into a single entry.
+///
+/// Identical copies collapse silently. Nested and overlapping shifts become one shift covering
+/// both, and entries that can't be merged keep the first one; both cases, as well as a
+/// zero-length shift, are flagged in `overlap` so the output can warn about them.
+pub fn merge_entries(entries: &[WorkScheduleEntry]) -> Option<WorkScheduleEntry> {
+    let (first, rest) = entries.split_first()?;
+    let mut merged = first.clone();
+
+    for entry in rest {
+        let kind = classify(&merged, entry);
+
+        // Widen the merged shifts to cover both
+        if matches!(kind, OverlapKind::Nested | OverlapKind::Overlapping) {
+            merged.shifts = union_shifts(&merged, entry);
+        }
+
+        if kind != OverlapKind::Identical {
+            merged.overlap = merged.overlap.max(Some(kind));
+        }
+        if merged.notes.is_none() {
+            merged.notes = entry.notes.clone();
+        }
+        if merged.break_minutes.is_none() {
+            merged.break_minutes = entry.break_minutes;
+        }
+    }
+
+    if merged.overlap.is_none() && is_zero_length(&merged) {
+        merged.overlap = Some(OverlapKind::ZeroLength);
+    }
+
+    Some(merged)
+}
+
+/// Overall kind of a group of duplicate entries
+pub fn duplicate_kind(entries: &[WorkScheduleEntry]) -> OverlapKind {
+    merge_entries(entries)
+        .and_then(|merged| merged.overlap)
+        .unwrap_or(OverlapKind::Identical)
+}
+
+/// Pick the entry to keep when resolving duplicates
+pub fn pick_entry(entries: &[WorkScheduleEntry], keep: KeepChoice) -> Option<WorkScheduleEntry> {
+    let entry = match keep {
+        KeepChoice::First => entries.first(),
+        KeepChoice::Last => entries.last(),
+    }?;
+
+    let mut entry = entry.clone();
+    entry.overlap = None;
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(start: &str, end: &str) -> WorkScheduleEntry {
+        let mut entry = WorkScheduleEntry::new("2025-01-06".to_string());
+        entry.shifts.push(ShiftRange::new(start, end));
+        entry
+    }
+
+    fn hours(entry: &WorkScheduleEntry) -> (Option<&str>, Option<&str>) {
+        assert_eq!(entry.shifts.len(), 1);
+        (
+            entry.shifts[0].start.as_deref(),
+            entry.shifts[0].end.as_deref(),
+        )
+    }
+
+    #[test]
+    fn test_identical_entries_collapse_silently() {
+        let merged = merge_entries(&[shift("08:00", "16:00"), shift("08:00", "16:00")]).unwrap();
+        assert_eq!(hours(&merged), (Some("08:00"), Some("16:00")));
+        assert_eq!(merged.overlap, None);
+
+        let mut day_off = WorkScheduleEntry::new("2025-01-06".to_string());
+        day_off.is_day_off = true;
+        let merged = merge_entries(&[day_off.clone(), day_off]).unwrap();
+        assert!(merged.is_day_off);
+        assert_eq!(merged.overlap, None);
+    }
+
+    #[test]
+    fn test_nested_shift_keeps_outer_range() {
+        let entries = [shift("10:00", "14:00"), shift("08:00", "16:00")];
+        assert_eq!(classify(&entries[0], &entries[1]), OverlapKind::Nested);
+
+        let merged = merge_entries(&entries).unwrap();
+        assert_eq!(hours(&merged), (Some("08:00"), Some("16:00")));
+        assert_eq!(merged.overlap, Some(OverlapKind::Nested));
+    }
+
+    #[test]
+    fn test_partially_overlapping_shifts_are_joined() {
+        let entries = [shift("08:00", "12:30"), shift("12:00", "16:00")];
+        assert_eq!(classify(&entries[0], &entries[1]), OverlapKind::Overlapping);
+
+        let merged = merge_entries(&entries).unwrap();
+        assert_eq!(hours(&merged), (Some("08:00"), Some("16:00")));
+        assert_eq!(merged.overlap, Some(OverlapKind::Overlapping));
+    }
+
+    #[test]
+    fn test_unmergeable_entries_keep_first() {
+        // A split shift with a break in between
+        let entries = [shift("08:00", "12:00"), shift("13:00", "17:00")];
+        let merged = merge_entries(&entries).unwrap();
+        assert_eq!(hours(&merged), (Some("08:00"), Some("12:00")));
+        assert_eq!(merged.overlap, Some(OverlapKind::Conflicting));
+        assert_eq!(duplicate_kind(&entries), OverlapKind::Conflicting);
+
+        let last = pick_entry(&entries, KeepChoice::Last).unwrap();
+        assert_eq!(hours(&last), (Some("13:00"), Some("17:00")));
+        assert_eq!(last.overlap, None);
+    }
+
+    #[test]
+    fn test_zero_length_shift_is_flagged() {
+        let merged = merge_entries(&[shift("09:00", "09:00")]).unwrap();
+        assert_eq!(merged.overlap, Some(OverlapKind::ZeroLength));
+
+        let merged = merge_entries(&[shift("09:00", "17:00")]).unwrap();
+        assert_eq!(merged.overlap, None);
+        assert!(merge_entries(&[]).is_none());
+    }
+
+    #[test]
+    fn test_split_shifts_merge_per_block() {
+        let mut split = WorkScheduleEntry::new("2025-01-06".to_string());
+        split.shifts = vec![
+            ShiftRange::new("08:00", "12:00"),
+            ShiftRange::new("16:00", "20:00"),
+        ];
+
+        // A copy covering only the morning block is nested in the split shift
+        assert_eq!(
+            classify(&split, &shift("09:00", "11:00")),
+            OverlapKind::Nested
+        );
+
+        let merged = merge_entries(&[split.clone(), shift("11:00", "13:00")]).unwrap();
+        assert_eq!(
+            merged.shifts,
+            vec![
+                ShiftRange::new("08:00", "13:00"),
+                ShiftRange::new("16:00", "20:00")
+            ]
+        );
+        assert_eq!(merged.overlap, Some(OverlapKind::Overlapping));
+
+        // The evening block alone doesn't touch the morning shift
+        assert_eq!(
+            classify(&shift("08:00", "12:00"), &shift("16:00", "20:00")),
+            OverlapKind::Conflicting
+        );
+        assert_eq!(
+            merge_entries(&[split.clone(), split]).unwrap().overlap,
+            None
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Failed schedule parses kept with the model's raw response, so a failure can be reproduced
+//! after the fact. The work hours web interface stores them and the bot lists them.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::{parse_failure_key, WORK_HOURS_PARSE_FAILURES};
+use crate::error::BotResult;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Days a parse failure is kept
+pub const PARSE_FAILURE_TTL_SECONDS: i64 = 14 * 24 * 60 * 60;
+/// Largest stored parse failure; the raw response and the table are cut to fit
+pub const MAX_PARSE_FAILURE_BYTES: usize = 256 * 1024;
+/// Parse failures listed, newest first
+pub const MAX_LISTED_PARSE_FAILURES: usize = 25;
+
+/// Where in the pipeline the parse failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseFailureStage {
+    /// No schedule could be read from the model's response
+    Extraction,
+    /// The schedule was read but rejected by validation, e.g. as suspect
+    Validation,
+}
+
+impl fmt::Display for ParseFailureStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Extraction => write!(f, "extraction"),
+            Self::Validation => write!(f, "validation"),
+        }
+    }
+}
+
+/// A prompt sent to the model and its raw response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelExchange {
+    /// Model provider, e.g. "gemini"
+    pub provider: String,
+    pub model: String,
+    /// Employee whose row the prompt asked for
+    pub employee: String,
+    /// Year the prompt completed the dates with
+    pub year: u32,
+    /// Table read from the image, given to the model as a guide
+    pub markdown: String,
+    /// The model's response as it came
+    pub response: String,
+}
+
+/// A failed parse with everything needed to retry it offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseFailure {
+    /// Id of the failure, like an upload id
+    pub id: String,
+    /// Unix timestamp of the failure
+    pub failed_at: i64,
+    pub stage: ParseFailureStage,
+    /// The parser's error
+    pub error: String,
+    /// Hash of the uploaded image, as stored with successful uploads
+    pub image_hash: String,
+    pub exchange: ModelExchange,
+    /// Whether the response or the table were cut to fit the size limit
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl ParseFailure {
+    /// Serialize the failure, cutting the response and then the table until it fits in
+    /// `MAX_PARSE_FAILURE_BYTES`
+    pub fn to_capped_json(&self) -> serde_json::Result<String> {
+        let json = serde_json::to_string(self)?;
+        if json.len() <= MAX_PARSE_FAILURE_BYTES {
+            return Ok(json);
+        }
+
+        let mut capped = self.clone();
+        capped.truncated = true;
+        // Cut the response first, then the table if the response alone isn't enough
+        loop {
+            let json = serde_json::to_string(&capped)?;
+            let excess = json.len().saturating_sub(MAX_PARSE_FAILURE_BYTES);
+            if excess == 0 {
+                return Ok(json);
+            }
+            let text = if capped.exchange.response.is_empty() {
+                &mut capped.exchange.markdown
+            } else {
+                &mut capped.exchange.response
+            };
+            if text.is_empty() {
+                // Nothing left to cut; the other fields are small
+                return Ok(json);
+            }
+            // Drop characters from the end until their escaped length covers the excess
+            let mut end = text.len();
+            let mut cut = 0;
+            for (i, c) in text.char_indices().rev() {
+                if cut >= excess {
+                    break;
+                }
+                cut += escaped_len(c);
+                end = i;
+            }
+            text.truncate(end);
+        }
+    }
+}
+
+/// Length of a character once escaped in a JSON string
+fn escaped_len(c: char) -> usize {
+    match c {
+        '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+        c if c < ' ' => 6,
+        c => c.len_utf8(),
+    }
+}
+
+/// Load the latest parse failures, newest first, skipping ones that have expired
+pub async fn load_parse_failures(redis_handle: &RedisActorHandle) -> BotResult<Vec<ParseFailure>> {
+    let ids: Vec<String> = redis_handle
+        .lrange(
+            &WORK_HOURS_PARSE_FAILURES,
+            0,
+            MAX_LISTED_PARSE_FAILURES as isize - 1,
+        )
+        .await?;
+    let keys = ids
+        .iter()
+        .map(|id| parse_failure_key(id))
+        .collect::<BotResult<Vec<_>>>()?;
+    let stored: Vec<Option<String>> = redis_handle.mget(&keys).await?;
+
+    Ok(stored
+        .iter()
+        .flatten()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(response: String) -> ParseFailure {
+        ParseFailure {
+            id: "anna-1736150400".to_string(),
+            failed_at: 1_736_150_400,
+            stage: ParseFailureStage::Extraction,
+            error: "Could not extract valid JSON from the model response".to_string(),
+            image_hash: "00ff00ff00ff00ff".to_string(),
+            exchange: ModelExchange {
+                provider: "gemini".to_string(),
+                model: "gemini-2.5-pro".to_string(),
+                employee: "Anna".to_string(),
+                year: 2025,
+                markdown: "| Nimi | 6.1. |\n|---|---|\n| Anna | 9-17 |".to_string(),
+                response,
+            },
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_large_response_is_truncated() {
+        let small = failure("I'm sorry, I can't read this image.".to_string());
+        let json = small.to_capped_json().unwrap();
+        assert_eq!(serde_json::from_str::<ParseFailure>(&json).unwrap(), small);
+
+        // Multibyte characters so the cut has to land on a character boundary
+        let large = failure("ä\"".repeat(MAX_PARSE_FAILURE_BYTES));
+        let json = large.to_capped_json().unwrap();
+        assert!(json.len() <= MAX_PARSE_FAILURE_BYTES);
+        let stored: ParseFailure = serde_json::from_str(&json).unwrap();
+        assert!(stored.truncated);
+        assert!(large
+            .exchange
+            .response
+            .starts_with(&stored.exchange.response));
+        assert!(stored.exchange.response.len() > MAX_PARSE_FAILURE_BYTES / 2);
+        assert_eq!(stored.exchange.markdown, large.exchange.markdown);
+    }
+}
@@ -0,0 +1,221 @@
+use super::handle::WorkScheduleHandle;
+use super::models::DaySchedules;
+use super::render::ScheduleFormatter;
+use crate::components::event_bus::{EventBus, ScheduleUpdated};
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::config::Config;
+use crate::error::BotResult;
+use crate::utils::notifier::{DiscordNotifier, Notification, Notifier};
+use crate::utils::redact::Redacted;
+use crate::utils::scheduler::SharedContext;
+use chrono::{Local, Timelike};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+use rust_i18n::t;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How often the pinned message is refreshed, so shifts get crossed out as they end
+pub const PINNED_REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Redis key holding the id of the pinned "Today" message in a channel
+pub fn pinned_today_key(channel_id: u64) -> Key {
+    Key::fixed("work_schedule:pinned_today").id(channel_id)
+}
+
+/// The "Today" message for a date as of `now` (minutes since midnight)
+pub fn today_notification(
+    date: &str,
+    schedules: &DaySchedules,
+    now: u32,
+    formatter: &ScheduleFormatter,
+) -> Notification {
+    let mut embed = CreateEmbed::new()
+        .title(t!("work_schedule_pinned_title", date = date))
+        .color(0x00_FF_00)
+        .footer(CreateEmbedFooter::new(t!(
+            "work_schedule_pinned_updated",
+            time = format!("{:02}:{:02}", now / 60, now % 60)
+        )));
+
+    if schedules.is_empty() {
+        embed = embed.description(t!("work_schedule_daily_no_schedules", date = date));
+    } else {
+        for (employee, entry) in schedules.iter() {
+            embed = embed.field(
+                formatter.employee(employee),
+                formatter.format_at(entry, now),
+                true,
+            );
+        }
+    }
+
+    Notification {
+        content: None,
+        embed,
+    }
+}
+
+/// Edit the pinned message of a channel, or post and pin a new one if there is none yet or
+/// it was deleted. Returns the id of the pinned message.
+pub async fn update_pinned(
+    notifier: &dyn Notifier,
+    redis_handle: &RedisActorHandle,
+    channel_id: u64,
+    notification: Notification,
+) -> BotResult<u64> {
+    let key = pinned_today_key(channel_id);
+    let stored = redis_handle
+        .get::<Option<u64>>(&key)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to read pinned today message id: {}", e);
+            None
+        });
+
+    if let Some(message_id) = stored {
+        match notifier
+            .edit(channel_id, message_id, notification.clone())
+            .await
+        {
+            Ok(()) => return Ok(message_id),
+            Err(e) => warn!(
+                "Failed to edit pinned today message {}, posting a new one: {}",
+                message_id, e
+            ),
+        }
+    }
+
+    let message_id = notifier.send(channel_id, notification).await?;
+    if let Err(e) = notifier.pin(channel_id, message_id).await {
+        warn!("Failed to pin today message {}: {}", message_id, e);
+    }
+    redis_handle.set(&key, message_id).await?;
+    info!(
+        "Posted today message {} in channel {}",
+        message_id, channel_id
+    );
+    Ok(message_id)
+}
+
+/// Refresh the pinned message with the current state of today's schedule
+async fn refresh_pinned(
+    ctx: &SharedContext,
+    handle: &WorkScheduleHandle,
+    redis_handle: &RedisActorHandle,
+    channel_id: u64,
+) -> BotResult<()> {
+    let now = Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    let schedules = handle.get_schedule_for_date(&date).await?;
+    // Untranslated notes aren't counted here, as every refresh would count them again
+    let formatter = handle.formatter().await;
+    let notification = today_notification(
+        &date,
+        &schedules,
+        now.hour() * 60 + now.minute(),
+        &formatter,
+    );
+
+    let notifier = DiscordNotifier::from_http(Arc::clone(&ctx.current().await.http));
+    update_pinned(&notifier, redis_handle, channel_id, notification).await?;
+    Ok(())
+}
+
+/// Start the task keeping the pinned "Today" message up to date while it's enabled.
+///
+/// It refreshes on an interval and whenever a schedule changes.
+pub fn spawn_pinned_today(
+    ctx: SharedContext,
+    config: Arc<RwLock<Config>>,
+    handle: WorkScheduleHandle,
+    redis_handle: RedisActorHandle,
+    bus: &EventBus,
+) -> JoinHandle<()> {
+    let mut updates = bus.subscribe::<ScheduleUpdated>();
+
+    tokio::spawn(async move {
+        info!("Pinned today message updater started");
+        loop {
+            let (enabled, channel_id) = {
+                let config = config.read().await;
+                (config.pinned_today_message, config.calendar_channel_id)
+            };
+            if enabled {
+                if let Err(e) = refresh_pinned(&ctx, &handle, &redis_handle, channel_id).await {
+                    warn!("Failed to refresh pinned today message: {}", e);
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(PINNED_REFRESH_INTERVAL) => {}
+                Ok(ScheduleUpdated(employee, _)) = updates.recv() => {
+                    debug!(
+                        "Schedule of {} changed, refreshing pinned today message",
+                        Redacted(&employee)
+                    );
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::notifier::recording::{Call, RecordingNotifier};
+
+    fn notification() -> Notification {
+        today_notification(
+            "2025-01-06",
+            &DaySchedules::default(),
+            8 * 60,
+            &ScheduleFormatter::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_message_is_pinned_once_then_edited() {
+        let redis_handle = RedisActorHandle::fake();
+        let notifier = RecordingNotifier::default();
+
+        assert_eq!(
+            update_pinned(&notifier, &redis_handle, 5, notification())
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            update_pinned(&notifier, &redis_handle, 5, notification())
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            notifier.calls(),
+            [Call::Send(1), Call::Pin(1), Call::Edit(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_message_is_recreated() {
+        let redis_handle = RedisActorHandle::fake();
+        redis_handle.set(&pinned_today_key(5), 7).await.unwrap();
+        let notifier = RecordingNotifier::with_missing(&[7]);
+
+        let message_id = update_pinned(&notifier, &redis_handle, 5, notification())
+            .await
+            .unwrap();
+        assert_eq!(message_id, 1);
+        assert_eq!(
+            notifier.calls(),
+            [Call::Edit(7), Call::Send(1), Call::Pin(1)]
+        );
+
+        // The new message is the one edited from now on
+        let stored: Option<u64> = redis_handle.get(&pinned_today_key(5)).await.unwrap();
+        assert_eq!(stored, Some(1));
+    }
+}
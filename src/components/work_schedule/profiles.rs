@@ -0,0 +1,324 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::employee::EmployeeId;
+use crate::components::work_schedule::keys::WORK_HOURS_EMPLOYEE_PROFILES;
+use crate::error::{work_schedule_error, BotResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+use url::Url;
+
+/// Emojis employees without one of their own get, picked by a hash of their name
+const FALLBACK_EMOJIS: [&str; 16] = [
+    "🦊", "🐻", "🐼", "🐨", "🐯", "🦁", "🐸", "🐵", "🐧", "🦉", "🦄", "🐝", "🐢", "🐙", "🦋", "🐳",
+];
+
+/// Longest emoji accepted, in characters. Flags, skin tones and family emojis are several
+/// characters long.
+const MAX_EMOJI_LENGTH: usize = 16;
+
+/// How long the avatar check waits for the image host
+const AVATAR_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How an employee is shown in schedule embeds
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmployeeProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+impl EmployeeProfile {
+    fn is_empty(&self) -> bool {
+        self.emoji.is_none() && self.avatar_url.is_none()
+    }
+}
+
+/// Display metadata of every employee, keyed by employee slug
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmployeeProfiles {
+    profiles: HashMap<String, EmployeeProfile>,
+}
+
+impl EmployeeProfiles {
+    /// Create profiles from employee names and their metadata
+    pub fn new(profiles: impl IntoIterator<Item = (String, EmployeeProfile)>) -> Self {
+        Self {
+            profiles: profiles
+                .into_iter()
+                .map(|(name, profile)| (EmployeeId::new(&name).slug().to_string(), profile))
+                .collect(),
+        }
+    }
+
+    /// The employee's stored metadata, if they have any
+    pub fn get(&self, employee: &str) -> Option<&EmployeeProfile> {
+        self.profiles.get(EmployeeId::new(employee).slug())
+    }
+
+    /// The employee's emoji, or one picked from their name if they haven't set one
+    pub fn emoji<'a>(&'a self, employee: &str) -> &'a str {
+        self.get(employee)
+            .and_then(|profile| profile.emoji.as_deref())
+            .unwrap_or_else(|| fallback_emoji(employee))
+    }
+
+    /// The employee's name prefixed with their emoji, e.g. "🦊 Anna"
+    pub fn label(&self, employee: &str) -> String {
+        format!("{} {employee}", self.emoji(employee))
+    }
+
+    /// The employee's avatar image, if they have one
+    pub fn avatar_url(&self, employee: &str) -> Option<&str> {
+        self.get(employee)?.avatar_url.as_deref()
+    }
+}
+
+/// Emoji for an employee without one of their own. The same name always gets the same emoji,
+/// so it stays put across restarts and however the name is capitalized.
+pub fn fallback_emoji(employee: &str) -> &'static str {
+    // FNV-1a, since std's hasher is randomly seeded per process
+    let hash = EmployeeId::new(employee)
+        .slug()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    FALLBACK_EMOJIS[(hash % FALLBACK_EMOJIS.len() as u64) as usize]
+}
+
+/// Check that a value looks like a single emoji: either a custom Discord emoji such as
+/// `<:kahvi:123>` or a short run of non-ASCII characters
+pub fn parse_emoji(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if let Some(inner) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+        let inner = inner.strip_prefix('a').unwrap_or(inner);
+        let valid = inner
+            .strip_prefix(':')
+            .and_then(|v| v.split_once(':'))
+            .is_some_and(|(name, id)| {
+                !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    && !id.is_empty()
+                    && id.chars().all(|c| c.is_ascii_digit())
+            });
+        return if valid {
+            Ok(value.to_string())
+        } else {
+            Err(format!("Invalid custom emoji: {value}"))
+        };
+    }
+
+    if value.is_empty()
+        || value.chars().count() > MAX_EMOJI_LENGTH
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_ascii_alphanumeric())
+        || value.is_ascii()
+    {
+        return Err(format!("Not an emoji: {value}"));
+    }
+    Ok(value.to_string())
+}
+
+/// Check that an avatar URL can be embedded: Discord only shows https images
+pub fn validate_avatar_url(value: &str) -> Result<Url, String> {
+    let url = Url::parse(value.trim()).map_err(|e| format!("Invalid URL {value}: {e}"))?;
+    if url.scheme() != "https" {
+        return Err(format!("Avatar URL must use https: {value}"));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(format!("Avatar URL has no host: {value}"));
+    }
+    Ok(url)
+}
+
+/// Whether a Content-Type header value is an image type
+pub fn is_image_content_type(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|mime| mime.trim().to_ascii_lowercase().starts_with("image/"))
+}
+
+/// Ask the image host whether the avatar URL points at an image
+pub async fn check_avatar_image(url: &Url) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .head(url.clone())
+        .timeout(AVATAR_CHECK_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if is_image_content_type(content_type) {
+        Ok(())
+    } else {
+        Err(format!("{url} is not an image ({content_type})"))
+    }
+}
+
+/// Load the employee profiles, skipping unreadable ones
+pub async fn load_employee_profiles(
+    redis_handle: &RedisActorHandle,
+) -> BotResult<EmployeeProfiles> {
+    let stored: HashMap<String, String> =
+        redis_handle.hgetall(&WORK_HOURS_EMPLOYEE_PROFILES).await?;
+
+    Ok(EmployeeProfiles::new(stored.into_iter().filter_map(
+        |(slug, json)| match serde_json::from_str(&json) {
+            Ok(profile) => Some((slug, profile)),
+            Err(e) => {
+                warn!("Ignoring invalid employee profile {}: {}", slug, e);
+                None
+            }
+        },
+    )))
+}
+
+/// Change an employee's profile, removing it once it has nothing set
+async fn update_profile(
+    redis_handle: &RedisActorHandle,
+    employee: &str,
+    update: impl FnOnce(&mut EmployeeProfile),
+) -> BotResult<()> {
+    let employee = EmployeeId::new(employee);
+    let profiles = load_employee_profiles(redis_handle).await?;
+    let mut profile = profiles
+        .get(employee.display())
+        .cloned()
+        .unwrap_or_default();
+    update(&mut profile);
+
+    if profile.is_empty() {
+        return redis_handle
+            .hdel(&WORK_HOURS_EMPLOYEE_PROFILES, employee.slug())
+            .await;
+    }
+    let json = serde_json::to_string(&profile)
+        .map_err(|e| work_schedule_error(&format!("Failed to serialize employee profile: {e}")))?;
+    redis_handle
+        .hset(&WORK_HOURS_EMPLOYEE_PROFILES, employee.slug(), json)
+        .await
+}
+
+/// Set or clear an employee's emoji
+pub async fn set_employee_emoji(
+    redis_handle: &RedisActorHandle,
+    employee: &str,
+    emoji: Option<String>,
+) -> BotResult<()> {
+    update_profile(redis_handle, employee, |profile| profile.emoji = emoji).await
+}
+
+/// Set or clear an employee's avatar URL
+pub async fn set_employee_avatar(
+    redis_handle: &RedisActorHandle,
+    employee: &str,
+    avatar_url: Option<String>,
+) -> BotResult<()> {
+    update_profile(redis_handle, employee, |profile| {
+        profile.avatar_url = avatar_url
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_emoji_is_deterministic() {
+        assert_eq!(
+            fallback_emoji("Anna Mäkinen"),
+            fallback_emoji("anna  makinen")
+        );
+        assert!(FALLBACK_EMOJIS.contains(&fallback_emoji("Pekka")));
+
+        // A handful of names shouldn't all land on the same emoji
+        let picked: std::collections::HashSet<_> = ["Anna", "Pekka", "Liisa", "Mikko", "Sari"]
+            .iter()
+            .map(|name| fallback_emoji(name))
+            .collect();
+        assert!(picked.len() > 1);
+
+        let profiles = EmployeeProfiles::default();
+        assert_eq!(
+            profiles.label("Anna"),
+            format!("{} Anna", fallback_emoji("Anna"))
+        );
+        assert_eq!(profiles.avatar_url("Anna"), None);
+    }
+
+    #[test]
+    fn test_parse_emoji() {
+        assert_eq!(parse_emoji(" 🦊 ").unwrap(), "🦊");
+        assert_eq!(parse_emoji("👩🏽‍💻").unwrap(), "👩🏽‍💻");
+        assert_eq!(parse_emoji("<:kahvi:123>").unwrap(), "<:kahvi:123>");
+        assert_eq!(parse_emoji("<a:kahvi_2:123>").unwrap(), "<a:kahvi_2:123>");
+        assert!(parse_emoji("").is_err());
+        assert!(parse_emoji("abc").is_err());
+        assert!(parse_emoji("🦊 🐻").is_err());
+        assert!(parse_emoji("<:kahvi:abc>").is_err());
+        assert!(parse_emoji(":fox:").is_err());
+    }
+
+    #[test]
+    fn test_avatar_url_validation() {
+        assert!(validate_avatar_url("https://example.com/anna.png").is_ok());
+        assert!(validate_avatar_url("http://example.com/anna.png").is_err());
+        assert!(validate_avatar_url("ftp://example.com/anna.png").is_err());
+        assert!(validate_avatar_url("not a url").is_err());
+
+        assert!(is_image_content_type("image/png"));
+        assert!(is_image_content_type("Image/WEBP; charset=binary"));
+        assert!(!is_image_content_type("text/html; charset=utf-8"));
+        assert!(!is_image_content_type(""));
+    }
+
+    #[tokio::test]
+    async fn test_profiles_are_stored_and_cleared() {
+        let redis_handle = RedisActorHandle::fake();
+
+        set_employee_emoji(&redis_handle, "Anna Mäkinen", Some("🦊".to_string()))
+            .await
+            .unwrap();
+        set_employee_avatar(
+            &redis_handle,
+            "anna makinen",
+            Some("https://example.com/anna.png".to_string()),
+        )
+        .await
+        .unwrap();
+        let profiles = load_employee_profiles(&redis_handle).await.unwrap();
+        assert_eq!(profiles.label("ANNA MÄKINEN"), "🦊 ANNA MÄKINEN");
+        assert_eq!(
+            profiles.avatar_url("Anna Mäkinen"),
+            Some("https://example.com/anna.png")
+        );
+
+        // Clearing the emoji keeps the avatar
+        set_employee_emoji(&redis_handle, "Anna Mäkinen", None)
+            .await
+            .unwrap();
+        let profiles = load_employee_profiles(&redis_handle).await.unwrap();
+        assert_eq!(
+            profiles.emoji("Anna Mäkinen"),
+            fallback_emoji("Anna Mäkinen")
+        );
+        assert!(profiles.avatar_url("Anna Mäkinen").is_some());
+
+        set_employee_avatar(&redis_handle, "Anna Mäkinen", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            load_employee_profiles(&redis_handle).await.unwrap(),
+            EmployeeProfiles::default()
+        );
+    }
+}
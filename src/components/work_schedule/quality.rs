@@ -0,0 +1,367 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::{WORK_HOURS_PARSE_EDITS, WORK_HOURS_PARSE_RECORDS};
+use crate::components::work_schedule::EmployeeId;
+use crate::error::BotResult;
+use crate::utils::i18n::format_number;
+use crate::utils::time::{week_bounds, WeekStart};
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Weeks of parse quality shown unless asked otherwise
+pub const DEFAULT_QUALITY_WEEKS: u32 = 8;
+/// Most weeks of parse quality aggregated at once
+pub const MAX_QUALITY_WEEKS: u32 = 52;
+
+/// Levels of a sparkline, lowest first
+const SPARK_LEVELS: [char; 5] = ['▁', '▂', '▄', '▆', '█'];
+/// Shown in a sparkline for a week without uploads
+const SPARK_GAP: char = '·';
+
+/// How the parser did on one upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseRecord {
+    /// Id of the upload, also the stem of the stored image's file name
+    pub upload_id: String,
+    /// Unix timestamp of the parse
+    pub parsed_at: i64,
+    /// Display name of the employee the schedule belongs to
+    pub employee: String,
+    /// Model provider that read the image
+    pub provider: String,
+    /// Number of days parsed
+    pub days: usize,
+    /// Days with neither hours, a day off nor a note
+    pub empty_cells: usize,
+    /// Days whose cell wasn't recognized as hours or a vacation code and was kept as a note
+    pub low_confidence: usize,
+    /// Validation warnings other than unrecognized cells, e.g. duplicate dates
+    pub warnings: Vec<String>,
+    /// Entries of the upload edited by hand since, counted separately in Redis
+    #[serde(default)]
+    pub manual_edits: u64,
+}
+
+/// Id of an upload, e.g. "anna-m-kinen-1736150400"
+pub fn upload_id(employee: &EmployeeId, uploaded_at: i64) -> String {
+    let slug: String = employee
+        .slug()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{slug}-{uploaded_at}")
+}
+
+/// Parse quality of the uploads within one week
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeeklyQuality {
+    /// First day of the week (YYYY-MM-DD)
+    pub week_start: String,
+    pub uploads: usize,
+    pub days: usize,
+    pub empty_cells: usize,
+    pub low_confidence: usize,
+    pub warnings: usize,
+    pub manual_edits: u64,
+}
+
+/// A trend shown by `/laatu`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityMetric {
+    EmptyCells,
+    LowConfidence,
+    Warnings,
+    ManualEdits,
+}
+
+impl QualityMetric {
+    pub const ALL: [QualityMetric; 4] = [
+        QualityMetric::EmptyCells,
+        QualityMetric::LowConfidence,
+        QualityMetric::Warnings,
+        QualityMetric::ManualEdits,
+    ];
+
+    /// Translation key of the metric's name
+    pub fn label_key(self) -> &'static str {
+        match self {
+            QualityMetric::EmptyCells => "quality_empty_cells",
+            QualityMetric::LowConfidence => "quality_low_confidence",
+            QualityMetric::Warnings => "quality_warnings",
+            QualityMetric::ManualEdits => "quality_manual_edits",
+        }
+    }
+}
+
+impl WeeklyQuality {
+    fn new(week_start: NaiveDate) -> Self {
+        Self {
+            week_start: week_start.format("%Y-%m-%d").to_string(),
+            uploads: 0,
+            days: 0,
+            empty_cells: 0,
+            low_confidence: 0,
+            warnings: 0,
+            manual_edits: 0,
+        }
+    }
+
+    /// Average of a metric per upload, so busy weeks don't look worse. `None` without uploads.
+    pub fn per_upload(&self, metric: QualityMetric) -> Option<f64> {
+        if self.uploads == 0 {
+            return None;
+        }
+        let total = match metric {
+            QualityMetric::EmptyCells => self.empty_cells as f64,
+            QualityMetric::LowConfidence => self.low_confidence as f64,
+            QualityMetric::Warnings => self.warnings as f64,
+            QualityMetric::ManualEdits => self.manual_edits as f64,
+        };
+        Some(total / self.uploads as f64)
+    }
+}
+
+/// Sum up the records of the `weeks` weeks ending with the one containing `today`, oldest
+/// first. Weeks without uploads are included with zero counts.
+pub fn weekly_quality(
+    records: &[ParseRecord],
+    today: NaiveDate,
+    weeks: u32,
+    week_start: WeekStart,
+) -> Vec<WeeklyQuality> {
+    let weeks = weeks.clamp(1, MAX_QUALITY_WEEKS);
+    let (current, _) = week_bounds(today, week_start);
+    let first = current - Duration::weeks(i64::from(weeks) - 1);
+
+    let mut aggregates: Vec<WeeklyQuality> = (0..weeks)
+        .map(|week| WeeklyQuality::new(first + Duration::weeks(i64::from(week))))
+        .collect();
+    for record in records {
+        let Some(parsed_on) = Local
+            .timestamp_opt(record.parsed_at, 0)
+            .single()
+            .map(|parsed_at| parsed_at.date_naive())
+        else {
+            continue;
+        };
+        let (record_week, _) = week_bounds(parsed_on, week_start);
+        let Ok(index) = usize::try_from((record_week - first).num_weeks()) else {
+            continue;
+        };
+        let Some(week) = aggregates.get_mut(index) else {
+            continue;
+        };
+
+        week.uploads += 1;
+        week.days += record.days;
+        week.empty_cells += record.empty_cells;
+        week.low_confidence += record.low_confidence;
+        week.warnings += record.warnings.len();
+        week.manual_edits += record.manual_edits;
+    }
+    aggregates
+}
+
+/// Render values as a sparkline such as "▁▂▄▆█", scaled between the smallest and largest
+/// value. Missing values are shown as "·" and a flat line stays at the lowest level.
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    let present = values.iter().flatten().copied();
+    let min = present.clone().fold(f64::INFINITY, f64::min);
+    let max = present.fold(f64::NEG_INFINITY, f64::max);
+    let top = (SPARK_LEVELS.len() - 1) as f64;
+
+    values
+        .iter()
+        .map(|value| match value {
+            None => SPARK_GAP,
+            Some(_) if max <= min => SPARK_LEVELS[0],
+            Some(value) => SPARK_LEVELS[((value - min) / (max - min) * top).round() as usize],
+        })
+        .collect()
+}
+
+/// One line per metric with its sparkline and its value in the latest week with uploads, e.g.
+/// "**Empty cells** ▁▂▄▆█ 1.5"
+pub fn quality_trend(weeks: &[WeeklyQuality]) -> Vec<String> {
+    let locale = rust_i18n::locale();
+    let latest = weeks.iter().rev().find(|week| week.uploads > 0);
+    let line = |label: String, values: Vec<Option<f64>>, value: Option<f64>| {
+        let value = value.map_or_else(|| "-".to_string(), |v| format_number(v, &locale));
+        format!("**{label}** {} {value}", sparkline(&values))
+    };
+
+    let mut lines = vec![line(
+        t!("quality_uploads").to_string(),
+        weeks.iter().map(|week| Some(week.uploads as f64)).collect(),
+        latest.map(|week| week.uploads as f64),
+    )];
+    for metric in QualityMetric::ALL {
+        lines.push(line(
+            t!(metric.label_key()).to_string(),
+            weeks.iter().map(|week| week.per_upload(metric)).collect(),
+            latest.and_then(|week| week.per_upload(metric)),
+        ));
+    }
+    lines
+}
+
+/// Load every stored parse record with its manual edit count, oldest first
+pub async fn load_parse_records(redis_handle: &RedisActorHandle) -> BotResult<Vec<ParseRecord>> {
+    let stored: Vec<String> = redis_handle.hvals(&WORK_HOURS_PARSE_RECORDS).await?;
+    let edits: HashMap<String, u64> = redis_handle.hgetall(&WORK_HOURS_PARSE_EDITS).await?;
+
+    let mut records: Vec<ParseRecord> = stored
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| warn!("Ignoring invalid parse record: {}", e))
+                .ok()
+        })
+        .collect();
+    for record in &mut records {
+        record.manual_edits = edits.get(&record.upload_id).copied().unwrap_or(0);
+    }
+    records.sort_by_key(|record| record.parsed_at);
+    Ok(records)
+}
+
+/// Count a manual edit of an entry parsed from the upload
+pub async fn record_manual_edit(redis_handle: &RedisActorHandle, upload_id: &str) -> BotResult<()> {
+    redis_handle
+        .hincrby(&WORK_HOURS_PARSE_EDITS, upload_id, 1)
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn record(parsed_on: &str, empty_cells: usize, manual_edits: u64) -> ParseRecord {
+        // Midday, so the date is the same in every timezone the tests run in
+        let parsed_at = Local
+            .from_local_datetime(
+                &date(parsed_on).and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+            )
+            .unwrap()
+            .timestamp();
+        ParseRecord {
+            upload_id: format!("anna-{parsed_at}"),
+            parsed_at,
+            employee: "Anna".to_string(),
+            provider: "gemini".to_string(),
+            days: 14,
+            empty_cells,
+            low_confidence: 1,
+            warnings: vec!["2025-01-06: shift has zero length".to_string()],
+            manual_edits,
+        }
+    }
+
+    #[test]
+    fn test_weekly_quality_sums_records_per_week() {
+        let records = [
+            // Too old for three weeks
+            record("2024-12-31", 9, 9),
+            record("2025-01-06", 2, 1),
+            record("2025-01-12", 4, 0),
+            record("2025-01-22", 1, 3),
+        ];
+
+        let weeks = weekly_quality(&records, date("2025-01-22"), 3, WeekStart::Monday);
+
+        let starts: Vec<&str> = weeks.iter().map(|week| week.week_start.as_str()).collect();
+        assert_eq!(starts, ["2025-01-06", "2025-01-13", "2025-01-20"]);
+        assert_eq!(
+            weeks[0],
+            WeeklyQuality {
+                week_start: "2025-01-06".to_string(),
+                uploads: 2,
+                days: 28,
+                empty_cells: 6,
+                low_confidence: 2,
+                warnings: 2,
+                manual_edits: 1,
+            }
+        );
+        assert_eq!(weeks[1].uploads, 0);
+        assert_eq!(weeks[1].per_upload(QualityMetric::EmptyCells), None);
+        assert_eq!(weeks[2].manual_edits, 3);
+
+        assert_eq!(weeks[0].per_upload(QualityMetric::EmptyCells), Some(3.0));
+        assert_eq!(weeks[0].per_upload(QualityMetric::ManualEdits), Some(0.5));
+    }
+
+    #[test]
+    fn test_weekly_quality_follows_week_start() {
+        // A Sunday belongs to the next week when weeks start on Sunday
+        let records = [record("2025-01-12", 1, 0)];
+
+        let monday = weekly_quality(&records, date("2025-01-13"), 2, WeekStart::Monday);
+        assert_eq!(monday[0].week_start, "2025-01-06");
+        assert_eq!(monday[0].uploads, 1);
+
+        let sunday = weekly_quality(&records, date("2025-01-13"), 2, WeekStart::Sunday);
+        assert_eq!(sunday[1].week_start, "2025-01-12");
+        assert_eq!(sunday[1].uploads, 1);
+    }
+
+    #[test]
+    fn test_weekly_quality_clamps_week_count() {
+        assert_eq!(
+            weekly_quality(&[], date("2025-01-22"), 0, WeekStart::Monday).len(),
+            1
+        );
+        assert_eq!(
+            weekly_quality(&[], date("2025-01-22"), 500, WeekStart::Monday).len(),
+            MAX_QUALITY_WEEKS as usize
+        );
+    }
+
+    #[test]
+    fn test_sparkline_scales_between_min_and_max() {
+        let values = [Some(0.0), Some(1.0), Some(2.0), Some(3.0), Some(4.0)];
+        assert_eq!(sparkline(&values), "▁▂▄▆█");
+
+        let values = [Some(10.0), None, Some(20.0), Some(15.0)];
+        assert_eq!(sparkline(&values), "▁·█▄");
+    }
+
+    #[test]
+    fn test_sparkline_flat_and_empty() {
+        assert_eq!(sparkline(&[Some(2.0), Some(2.0)]), "▁▁");
+        assert_eq!(sparkline(&[None, None]), "··");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_quality_trend_lines() {
+        let records = [record("2025-01-06", 2, 1), record("2025-01-20", 4, 0)];
+        let weeks = weekly_quality(&records, date("2025-01-22"), 3, WeekStart::Monday);
+
+        assert_eq!(
+            quality_trend(&weeks),
+            [
+                "**Uploads** █▁█ 1",
+                "**Empty cells** ▁·█ 4",
+                "**Unrecognized cells** ▁·▁ 1",
+                "**Warnings** ▁·▁ 1",
+                "**Manual edits** █·▁ 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upload_id_is_a_safe_file_stem() {
+        let id = upload_id(&EmployeeId::new("Anna Mäkinen"), 1736150400);
+        assert!(id.ends_with("-1736150400"));
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    }
+}
@@ -0,0 +1,234 @@
+use super::handle::WorkScheduleHandle;
+use crate::config::Config;
+use crate::utils::embed::{truncate, DESCRIPTION_LIMIT};
+use crate::utils::scheduler::{sleep_until_target_time, SharedContext};
+use crate::utils::time::{next_daily_time, resolve_local};
+use chrono::Local;
+use poise::serenity_prelude::{ChannelId, CreateEmbed, CreateMessage};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info};
+
+/// Color of the reconciliation summary
+const SUMMARY_COLOR: u32 = 0xFF_A5_00;
+
+/// What the nightly reconciliation does with the inconsistencies it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconcileMode {
+    /// Only report them
+    #[default]
+    Report,
+    /// Fix the indexes and delete orphaned entries
+    Repair,
+}
+
+impl FromStr for ReconcileMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "report" => Ok(ReconcileMode::Report),
+            "repair" => Ok(ReconcileMode::Repair),
+            _ => Err(format!("Unknown reconcile mode: {s}")),
+        }
+    }
+}
+
+/// Inconsistencies between the stored day entries and their indexes. Employees are listed by
+/// their employees set member and entries as `(member, date)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// Whether the inconsistencies were repaired
+    pub mode: ReconcileMode,
+    /// Employees without a single stored entry, removed from the employees set when repairing
+    pub empty_employees: Vec<String>,
+    /// Dates listed in a dates set whose day key is missing, unlisted when repairing
+    pub missing_entries: Vec<(String, String)>,
+    /// Day keys of known employees missing from their dates set, listed when repairing
+    pub unindexed_entries: Vec<(String, String)>,
+    /// Day keys of employees outside the employees set, deleted when repairing
+    pub orphaned_entries: Vec<(String, String)>,
+}
+
+impl ReconcileReport {
+    /// Whether nothing was out of place
+    pub fn is_clean(&self) -> bool {
+        self.empty_employees.is_empty()
+            && self.missing_entries.is_empty()
+            && self.unindexed_entries.is_empty()
+            && self.orphaned_entries.is_empty()
+    }
+
+    /// Sort the findings so the summary reads the same every night
+    pub fn sort(&mut self) {
+        self.empty_employees.sort();
+        self.missing_entries.sort();
+        self.unindexed_entries.sort();
+        self.orphaned_entries.sort();
+    }
+}
+
+/// Lines of one kind of finding, e.g. "anna 2025-01-06, bertil 2025-01-07"
+fn entry_list(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(member, date)| format!("{member} {date}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Summary of a reconciliation for the error channel
+pub fn summary_embed(report: &ReconcileReport) -> CreateEmbed {
+    let title = match report.mode {
+        ReconcileMode::Report => t!("reconcile_report_title"),
+        ReconcileMode::Repair => t!("reconcile_repair_title"),
+    };
+
+    let mut lines = Vec::new();
+    let sections = [
+        (
+            "reconcile_empty_employees",
+            report.empty_employees.len(),
+            report.empty_employees.join(", "),
+        ),
+        (
+            "reconcile_missing_entries",
+            report.missing_entries.len(),
+            entry_list(&report.missing_entries),
+        ),
+        (
+            "reconcile_unindexed_entries",
+            report.unindexed_entries.len(),
+            entry_list(&report.unindexed_entries),
+        ),
+        (
+            "reconcile_orphaned_entries",
+            report.orphaned_entries.len(),
+            entry_list(&report.orphaned_entries),
+        ),
+    ];
+    for (key, count, list) in sections {
+        if count > 0 {
+            lines.push(format!("**{}**: {list}", t!(key, count = count)));
+        }
+    }
+
+    CreateEmbed::new()
+        .title(title)
+        .description(truncate(&lines.join("\n"), DESCRIPTION_LIMIT))
+        .color(SUMMARY_COLOR)
+}
+
+/// Run a reconciliation and post its summary to the error channel unless everything was fine
+async fn reconcile(ctx: &SharedContext, config: &RwLock<Config>, handle: &WorkScheduleHandle) {
+    let mode = config.read().await.reconcile_mode;
+    let report = match handle.reconcile(mode).await {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Work schedule reconciliation failed: {}", e);
+            return;
+        }
+    };
+
+    info!(
+        "Work schedule reconciliation ({:?}): {} empty employees, {} missing, {} unindexed and {} orphaned entries",
+        report.mode,
+        report.empty_employees.len(),
+        report.missing_entries.len(),
+        report.unindexed_entries.len(),
+        report.orphaned_entries.len()
+    );
+    if report.is_clean() {
+        return;
+    }
+    let Some(channel_id) = config.read().await.error_channel_id else {
+        return;
+    };
+
+    let ctx = ctx.current().await;
+    if let Err(e) = ChannelId::new(channel_id)
+        .send_message(&ctx, CreateMessage::new().embed(summary_embed(&report)))
+        .await
+    {
+        error!("Failed to send reconciliation summary: {}", e);
+    }
+}
+
+/// Start the task reconciling the stored work schedules every night at `reconcile_time`
+pub fn spawn_reconcile(
+    ctx: SharedContext,
+    config: Arc<RwLock<Config>>,
+    handle: WorkScheduleHandle,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let reconcile_time = config.read().await.reconcile_time;
+            let Some(next_time) = next_daily_time(&Local::now(), reconcile_time) else {
+                sleep(Duration::from_secs(3600)).await;
+                continue;
+            };
+            let Some(local_time) = resolve_local(&Local, next_time) else {
+                sleep(Duration::from_secs(3600)).await;
+                continue;
+            };
+
+            info!(
+                "Next work schedule reconciliation scheduled for {}",
+                next_time
+            );
+            if let Err(e) = sleep_until_target_time(local_time).await {
+                error!("Error while waiting for reconciliation: {:?}", e);
+                sleep(Duration::from_secs(60)).await;
+                continue;
+            }
+
+            reconcile(&ctx, &config, &handle).await;
+
+            // Don't run twice if we woke up slightly early
+            sleep(Duration::from_secs(60)).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::embed::render_embed;
+
+    #[test]
+    fn test_parse_reconcile_mode() {
+        assert_eq!("report".parse(), Ok(ReconcileMode::Report));
+        assert_eq!(" Repair ".parse(), Ok(ReconcileMode::Repair));
+        assert!("fix".parse::<ReconcileMode>().is_err());
+    }
+
+    #[test]
+    fn test_summary_snapshot() {
+        let report = ReconcileReport {
+            mode: ReconcileMode::Repair,
+            empty_employees: vec!["liisa".to_string()],
+            missing_entries: vec![
+                ("anna".to_string(), "2025-01-06".to_string()),
+                ("anna".to_string(), "2025-01-07".to_string()),
+            ],
+            unindexed_entries: Vec::new(),
+            orphaned_entries: vec![("pekka".to_string(), "2025-01-08".to_string())],
+        };
+        assert!(!report.is_clean());
+        assert!(ReconcileReport::default().is_clean());
+
+        let expected = "\
+# Work schedule keys repaired
+**Employees without entries (1)**: liisa
+**Dates without an entry (2)**: anna 2025-01-06, anna 2025-01-07
+**Entries of unknown employees (1)**: pekka 2025-01-08
+";
+        assert_eq!(render_embed(&summary_embed(&report)), expected);
+    }
+}
@@ -0,0 +1,868 @@
+use crate::components::work_schedule::glossary::{self, NoteGlossary};
+use crate::components::work_schedule::models::{DaySchedules, EmployeeSchedule, WorkScheduleEntry};
+use crate::components::work_schedule::profiles::EmployeeProfiles;
+use crate::components::work_schedule::stats::{DayRange, Variance, WeekendShare};
+use crate::error::BotResult;
+use crate::utils::embed::truncate;
+use crate::utils::i18n::{format_hours, format_number, weekday_name, weekday_short_name};
+use crate::utils::render::{View, ViewLine};
+use crate::utils::time::week_label;
+use chrono::{Datelike, Duration, NaiveDate};
+use rust_i18n::t;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Color of the schedule replies
+const SCHEDULE_COLOR: u32 = 0x00_99_FF;
+
+/// Shown when everyone has the day off
+const DAY_OFF_IMAGE: &str = "https://media.giphy.com/media/v1.Y2lkPTc5MGI3NjExdG9nM3J1YnA1NHcxc2cwcmE5bjNqOWF1eHZsY3h3MDBxbDl5aGdldiZlcD12MV9pbnRlcm5hbF9naWZfYnlfaWQmY3Q9Zw/DKnMqdm9i980E/giphy.gif";
+
+/// Formats entries for display, showing their notes through the glossary.
+///
+/// Known notes are shown translated with the original in parentheses, e.g. "Day off request
+/// (Toive vp)". Unknown ones are shown as they are and counted, so the most common ones can be
+/// added to the glossary.
+///
+/// With employee profiles attached, employee names are prefixed with their emoji.
+#[derive(Debug, Default)]
+pub struct ScheduleFormatter {
+    glossary: NoteGlossary,
+    locale: String,
+    profiles: Option<EmployeeProfiles>,
+    missing: Mutex<BTreeMap<String, u64>>,
+}
+
+impl ScheduleFormatter {
+    /// Create a formatter translating notes into `locale`
+    pub fn new(glossary: NoteGlossary, locale: impl Into<String>) -> Self {
+        Self {
+            glossary,
+            locale: locale.into(),
+            profiles: None,
+            missing: Mutex::default(),
+        }
+    }
+
+    /// Show employee names with the emojis and avatars of `profiles`
+    pub fn with_profiles(mut self, profiles: EmployeeProfiles) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// An employee's name as shown, prefixed with their emoji when profiles are attached
+    pub fn employee(&self, employee: &str) -> String {
+        match &self.profiles {
+            Some(profiles) => profiles.label(employee),
+            None => employee.to_string(),
+        }
+    }
+
+    /// The employee's avatar image, for views about a single employee
+    pub fn avatar_url(&self, employee: &str) -> Option<&str> {
+        self.profiles.as_ref()?.avatar_url(employee)
+    }
+
+    /// Format an entry with its note
+    pub fn format(&self, entry: &WorkScheduleEntry) -> String {
+        self.with_note(entry, entry.format())
+    }
+
+    /// Format an entry with its note as of `now` (minutes since midnight), crossing out shifts
+    /// that have already ended
+    pub fn format_at(&self, entry: &WorkScheduleEntry, now: u32) -> String {
+        self.with_note(entry, entry.format_at(now))
+    }
+
+    /// Format an entry with its note, the hours actually worked and a link to the message an
+    /// admin attached for context, for views showing the schedule in full
+    pub fn format_full(&self, entry: &WorkScheduleEntry) -> String {
+        let mut text = self.format(entry);
+        if let (Some(start), Some(end)) = (&entry.actual_start, &entry.actual_end) {
+            let actual = t!("work_schedule_actual_hours", start = start, end = end);
+            text = format!("{text} · {actual}");
+        }
+        match &entry.context_link {
+            Some(link) => format!(
+                "{text} · [{}]({})",
+                t!("work_schedule_context_link"),
+                link.url
+            ),
+            None => text,
+        }
+    }
+
+    fn with_note(&self, entry: &WorkScheduleEntry, text: String) -> String {
+        match self.note(entry) {
+            Some(note) => format!("{text} · {note}"),
+            None => text,
+        }
+    }
+
+    /// The entry's note as shown, translated if the glossary knows it
+    pub fn note(&self, entry: &WorkScheduleEntry) -> Option<String> {
+        let note = entry.notes.as_deref()?.trim();
+        if note.is_empty() {
+            return None;
+        }
+        match self.glossary.translate(note, &self.locale) {
+            Some(display) => Some(format!("{display} ({note})")),
+            None => {
+                let mut missing = self.missing.lock().unwrap_or_else(|e| e.into_inner());
+                *missing.entry(glossary::term_name(note)).or_default() += 1;
+                Some(note.to_string())
+            }
+        }
+    }
+
+    /// Untranslated terms shown since the last call, with how many times each was shown
+    pub fn take_missing(&self) -> BTreeMap<String, u64> {
+        std::mem::take(&mut *self.missing.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// A date with its weekday, e.g. "Monday (2025-03-10)"
+fn day_header(date: &str) -> String {
+    match parse_date(date) {
+        Some(parsed) => format!("{} ({date})", weekday_name(parsed.weekday())),
+        None => date.to_string(),
+    }
+}
+
+/// Every employee's entries over a range, one field per employee and one line per day
+pub fn week_overview(
+    title: String,
+    schedules: Vec<(String, BotResult<Vec<WorkScheduleEntry>>)>,
+    formatter: &ScheduleFormatter,
+) -> View {
+    schedules.into_iter().fold(
+        View::new(title, SCHEDULE_COLOR),
+        |view, (employee, entries)| {
+            let lines = match entries {
+                Ok(entries) if entries.is_empty() => {
+                    vec![ViewLine::new(t!("work_schedule_no_entries_found"))]
+                }
+                Ok(entries) => entries
+                    .iter()
+                    .map(|entry| match parse_date(&entry.date) {
+                        Some(date) => ViewLine::on(date, formatter.format_full(entry)),
+                        None => ViewLine::new(format!(
+                            "{}: {}",
+                            entry.date,
+                            formatter.format_full(entry)
+                        )),
+                    })
+                    .collect(),
+                Err(e) => vec![ViewLine::new(t!(
+                    "work_schedule_error_fetching",
+                    resource = employee,
+                    error = e.to_string()
+                ))],
+            };
+            view.bulleted_field(formatter.employee(&employee), lines)
+        },
+    )
+}
+
+/// One employee's entries, one field per day. `range` is shown under the title when given.
+pub fn employee_days(
+    title: String,
+    range: Option<(&str, &str)>,
+    employee: &str,
+    entries: &[WorkScheduleEntry],
+    formatter: &ScheduleFormatter,
+) -> View {
+    let mut view = View::new(title, SCHEDULE_COLOR);
+    if let Some(avatar_url) = formatter.avatar_url(employee) {
+        view = view.thumbnail(avatar_url);
+    }
+    if let Some((start, end)) = range {
+        view = view.description(format!("{start} – {end}"));
+    }
+    if entries.is_empty() {
+        return view.description(t!(
+            "work_schedule_no_entries_for_employee",
+            employee = employee
+        ));
+    }
+
+    // Dates sort chronologically as strings
+    let mut days: BTreeMap<&str, Vec<&WorkScheduleEntry>> = BTreeMap::new();
+    for entry in entries {
+        days.entry(&entry.date).or_default().push(entry);
+    }
+    for (date, entries) in days {
+        let lines = entries
+            .iter()
+            .map(|entry| ViewLine::new(formatter.format_full(entry)))
+            .collect();
+        view = if entries.len() > 1 {
+            view.bulleted_field(day_header(date), lines)
+        } else {
+            view.field(day_header(date), lines)
+        };
+    }
+    view
+}
+
+/// Everyone's entries on a day, one field per employee in name order
+pub fn day_schedules(date: &str, schedules: &DaySchedules, formatter: &ScheduleFormatter) -> View {
+    let view = View::new(
+        t!("work_schedule_date_title", date = day_header(date)),
+        SCHEDULE_COLOR,
+    );
+    let view = if schedules.is_empty() {
+        view.description(t!("work_schedule_no_schedules_found", date = date))
+    } else if schedules.values().all(|entry| entry.is_day_off) {
+        view.description(t!("work_schedule_all_day_off"))
+            .image(DAY_OFF_IMAGE)
+    } else {
+        schedules.iter().fold(view, |view, (employee, entry)| {
+            view.field(
+                formatter.employee(employee),
+                vec![ViewLine::new(formatter.format_full(entry))],
+            )
+        })
+    };
+    match no_data_line(schedules) {
+        Some(line) => view.footer(line),
+        None => view,
+    }
+}
+
+/// Shifts worked on the weekends starting on `saturdays`, one field per weekend
+pub fn weekend_overview(
+    title: String,
+    saturdays: &[NaiveDate],
+    schedules: &[EmployeeSchedule],
+    formatter: &ScheduleFormatter,
+) -> View {
+    saturdays
+        .iter()
+        .fold(View::new(title, SCHEDULE_COLOR), |view, saturday| {
+            let weekend = DayRange {
+                first: *saturday,
+                last: *saturday + Duration::days(1),
+            };
+            let mut lines = Vec::new();
+            for day in [weekend.first, weekend.last] {
+                let date = day.format("%Y-%m-%d").to_string();
+                for schedule in schedules {
+                    for entry in &schedule.schedule {
+                        if entry.date == date && entry.is_working() {
+                            lines.push(ViewLine::on(
+                                day,
+                                format!(
+                                    "{}: {}",
+                                    formatter.employee(&schedule.employee),
+                                    formatter.format_full(entry)
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            if lines.is_empty() {
+                lines.push(ViewLine::new(t!("weekend_nobody")));
+            }
+            view.field(
+                format!(
+                    "{} · {}",
+                    week_label(weekend.last, &rust_i18n::locale()),
+                    weekend.format()
+                ),
+                lines,
+            )
+        })
+}
+
+/// Width of the bars comparing weekend shares
+const FAIRNESS_BAR_WIDTH: usize = 10;
+
+/// Weekend shifts per employee over a window of `weeks`, busiest first, with bars scaled to
+/// the busiest so an uneven split stands out
+pub fn weekend_fairness_view(
+    title: String,
+    weeks: u32,
+    shares: &[WeekendShare],
+    formatter: &ScheduleFormatter,
+) -> View {
+    let view = View::new(title, SCHEDULE_COLOR);
+    if shares.is_empty() {
+        return view.description(t!("weekend_fairness_none"));
+    }
+
+    let locale = rust_i18n::locale();
+    let most = shares
+        .iter()
+        .map(|share| share.prorated)
+        .fold(0.0, f64::max);
+    let lines = shares
+        .iter()
+        .map(|share| {
+            let filled = if most > 0.0 {
+                (share.prorated / most * FAIRNESS_BAR_WIDTH as f64).round() as usize
+            } else {
+                0
+            };
+            let bar = format!(
+                "{}{}",
+                "▰".repeat(filled),
+                "▱".repeat(FAIRNESS_BAR_WIDTH - filled)
+            );
+            ViewLine::new(format!(
+                "`{bar}` **{}**: {}",
+                formatter.employee(&share.employee),
+                t!(
+                    "weekend_fairness_line",
+                    prorated = format_number(share.prorated, &locale),
+                    shifts = share.shifts,
+                    weeks = share.weeks_with_data
+                )
+            ))
+        })
+        .collect();
+    view.description(t!("weekend_fairness_description", weeks = weeks))
+        .field("\u{200B}", lines)
+}
+
+/// Planned against actual hours per employee, with the total over everyone
+pub fn actuals_variance_view(
+    title: String,
+    variances: &[(String, Variance)],
+    formatter: &ScheduleFormatter,
+) -> View {
+    let view = View::new(title, SCHEDULE_COLOR);
+    let mut total = Variance::default();
+    for (_, variance) in variances {
+        total.merge(variance);
+    }
+    if total.recorded_days == 0 && total.missing_days == 0 {
+        return view.description(t!("actuals_stats_none"));
+    }
+
+    let locale = rust_i18n::locale();
+    let line = |variance: &Variance| {
+        let hours = |minutes: f64| format_hours(minutes / 60.0, &locale);
+        let difference = variance.difference_minutes();
+        let sign = if difference > 0 { "+" } else { "" };
+        let mut line = t!(
+            "actuals_stats_line",
+            planned = hours(f64::from(variance.planned_minutes)),
+            actual = hours(f64::from(variance.actual_minutes)),
+            difference = format!("{sign}{}", hours(difference as f64)),
+            days = variance.recorded_days
+        )
+        .to_string();
+        if variance.missing_days > 0 {
+            let missing = t!("actuals_stats_missing", days = variance.missing_days);
+            line = format!("{line} · {missing}");
+        }
+        line
+    };
+    let lines = variances
+        .iter()
+        .map(|(employee, variance)| {
+            ViewLine::new(format!(
+                "**{}**: {}",
+                formatter.employee(employee),
+                line(variance)
+            ))
+        })
+        .collect();
+    view.description(format!("{}: {}", t!("actuals_stats_total"), line(&total)))
+        .field("\u{200B}", lines)
+}
+
+/// Line naming the employees with nothing stored for the day, if there are any
+pub fn no_data_line(schedules: &DaySchedules) -> Option<String> {
+    if schedules.missing().is_empty() {
+        return None;
+    }
+    Some(
+        t!(
+            "work_schedule_no_data",
+            employees = schedules.missing().join(", ")
+        )
+        .to_string(),
+    )
+}
+
+/// Widest line of a compact table, which fits a phone screen without wrapping
+pub const COMPACT_WIDTH: usize = 34;
+
+/// Widest employee name in a compact table, leaving room for the times and the code
+const COMPACT_NAME_WIDTH: usize = 12;
+
+/// Width of a compact table's times column, one "HH:MM-HH:MM" shift
+const COMPACT_TIMES_WIDTH: usize = 11;
+
+/// A day as a compact table labels it, e.g. "Mon 10."
+fn compact_day_label(date: &str) -> String {
+    match parse_date(date) {
+        Some(parsed) => format!("{} {}.", weekday_short_name(parsed.weekday()), parsed.day()),
+        None => date.to_string(),
+    }
+}
+
+/// An entry's shifts without their break, e.g. "07:00-15:00", or "-" on days without hours
+fn compact_times(entry: &WorkScheduleEntry) -> String {
+    if entry.is_day_off || entry.shifts.is_empty() {
+        return "-".to_string();
+    }
+    entry
+        .shifts
+        .iter()
+        .map(|shift| {
+            format!(
+                "{}-{}",
+                shift.start.as_deref().unwrap_or(""),
+                shift.end.as_deref().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A table row: `label` padded to `label_width`, the times and the entry's code (its note as
+/// written, or a day off marker), cut to [`COMPACT_WIDTH`]
+fn compact_row(label: &str, label_width: usize, entry: &WorkScheduleEntry) -> String {
+    let label = truncate(label, label_width);
+    let times = compact_times(entry);
+    let code = match entry.notes.as_deref().map(str::trim) {
+        Some(note) if !note.is_empty() => note.to_string(),
+        _ if entry.is_day_off => t!("work_schedule_compact_day_off").to_string(),
+        _ => String::new(),
+    };
+    let row = format!("{label:<label_width$} {times:<COMPACT_TIMES_WIDTH$} {code}");
+    truncate(row.trim_end(), COMPACT_WIDTH)
+}
+
+/// Rows of labelled entries, the labels padded to the longest one up to `max_label`
+fn compact_rows(rows: &[(String, &WorkScheduleEntry)], max_label: usize) -> Vec<String> {
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(max_label);
+    rows.iter()
+        .map(|(label, entry)| compact_row(label, label_width, entry))
+        .collect()
+}
+
+fn code_block(rows: &[String]) -> String {
+    format!("```\n{}\n```", rows.join("\n"))
+}
+
+/// One employee's entries as a single table with a row per day
+pub fn compact_employee_days(title: String, employee: &str, entries: &[WorkScheduleEntry]) -> View {
+    let view = View::new(title, SCHEDULE_COLOR);
+    if entries.is_empty() {
+        return view.description(t!(
+            "work_schedule_no_entries_for_employee",
+            employee = employee
+        ));
+    }
+
+    let mut entries: Vec<&WorkScheduleEntry> = entries.iter().collect();
+    entries.sort_by(|a, b| a.date.cmp(&b.date));
+    let rows: Vec<_> = entries
+        .into_iter()
+        .map(|entry| (compact_day_label(&entry.date), entry))
+        .collect();
+    view.description(code_block(&compact_rows(&rows, COMPACT_WIDTH)))
+}
+
+/// Every employee's entries over a range as a table per day, with a row per employee
+pub fn compact_week_overview(
+    title: String,
+    schedules: Vec<(String, BotResult<Vec<WorkScheduleEntry>>)>,
+) -> View {
+    let mut errors = Vec::new();
+    let mut days: BTreeMap<String, Vec<(String, WorkScheduleEntry)>> = BTreeMap::new();
+    for (employee, entries) in schedules {
+        match entries {
+            Ok(entries) => {
+                for entry in entries {
+                    days.entry(entry.date.clone())
+                        .or_default()
+                        .push((employee.clone(), entry));
+                }
+            }
+            Err(e) => errors.push(
+                t!(
+                    "work_schedule_error_fetching",
+                    resource = employee,
+                    error = e.to_string()
+                )
+                .to_string(),
+            ),
+        }
+    }
+
+    let mut view = View::new(title, SCHEDULE_COLOR);
+    if days.is_empty() && errors.is_empty() {
+        return view.description(t!("work_schedule_no_entries_found"));
+    }
+    if !errors.is_empty() {
+        view = view.description(errors.join("\n"));
+    }
+    days.into_iter().fold(view, |view, (date, entries)| {
+        let rows: Vec<_> = entries
+            .iter()
+            .map(|(employee, entry)| (employee.clone(), entry))
+            .collect();
+        let table = code_block(&compact_rows(&rows, COMPACT_NAME_WIDTH));
+        view.field(day_header(&date), vec![ViewLine::new(table)])
+    })
+}
+
+/// Everyone's entries on a day as a single table, with a row per employee in name order
+pub fn compact_day_schedules(date: &str, schedules: &DaySchedules) -> View {
+    let view = View::new(
+        t!("work_schedule_date_title", date = day_header(date)),
+        SCHEDULE_COLOR,
+    );
+    let view = if schedules.is_empty() {
+        view.description(t!("work_schedule_no_schedules_found", date = date))
+    } else {
+        let rows: Vec<_> = schedules
+            .iter()
+            .map(|(employee, entry)| (employee.clone(), entry))
+            .collect();
+        view.description(code_block(&compact_rows(&rows, COMPACT_NAME_WIDTH)))
+    };
+    match no_data_line(schedules) {
+        Some(line) => view.footer(line),
+        None => view,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::{ContextLink, ShiftRange};
+    use crate::components::work_schedule::profiles::{fallback_emoji, EmployeeProfile};
+    use crate::error::other_error;
+
+    fn fixture_week() -> View {
+        week_overview(
+            "Work schedule 2025-03-10 – 2025-03-16".to_string(),
+            vec![
+                (
+                    "Anna".to_string(),
+                    Ok(vec![
+                        WorkScheduleEntry::working("2025-03-10", "07:00", "15:00"),
+                        WorkScheduleEntry::day_off("2025-03-11"),
+                        WorkScheduleEntry::working("2025-03-15", "10:00", "18:00"),
+                    ]),
+                ),
+                ("Matti".to_string(), Ok(Vec::new())),
+                ("Pekka".to_string(), Err(other_error("timeout"))),
+            ],
+            &ScheduleFormatter::default(),
+        )
+    }
+
+    /// The field names and entry texts an embed shows, without day labels and bullets
+    fn embed_items(view: &View) -> Vec<(String, Vec<String>)> {
+        let value = serde_json::to_value(view.to_embed()).unwrap();
+        value["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                let items = field["value"]
+                    .as_str()
+                    .unwrap()
+                    .lines()
+                    .map(|line| {
+                        let line = line.trim_start_matches("• ");
+                        match line.split_once("**: ") {
+                            Some((_, text)) => text.to_string(),
+                            None => line.to_string(),
+                        }
+                    })
+                    .collect();
+                (field["name"].as_str().unwrap().to_string(), items)
+            })
+            .collect()
+    }
+
+    /// The field names and entry texts of the plain text, without day labels
+    fn text_items(view: &View) -> Vec<(String, Vec<String>)> {
+        let text = view.to_text().join("\n");
+        text.split("\n\n")
+            .skip(1)
+            .map(|block| {
+                let mut lines = block.lines();
+                let name = lines.next().unwrap().to_string();
+                let items = lines
+                    .map(|line| match line.split_once(".: ") {
+                        Some((_, text)) => text.to_string(),
+                        None => line.to_string(),
+                    })
+                    .collect();
+                (name, items)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_embed_and_text_show_the_same_week() {
+        let view = fixture_week();
+        let items = embed_items(&view);
+        assert_eq!(items, text_items(&view));
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].1.len(), 3);
+
+        let text = view.to_text().join("\n");
+        assert!(text.starts_with("Work schedule 2025-03-10 – 2025-03-16\n\nAnna\n"));
+        assert!(text.contains("\nMonday 10.03.: 07:00–15:00\n"));
+        assert!(text.contains("\nSaturday 15.03.: 10:00–18:00\n"));
+        assert!(!text.contains("**"));
+        assert!(!text.contains('•'));
+    }
+
+    #[test]
+    fn test_notes_are_translated_or_counted() {
+        let glossary = NoteGlossary::new([(
+            "Toive vp".to_string(),
+            BTreeMap::from([("en".to_string(), "Day off request".to_string())]),
+        )]);
+        let formatter = ScheduleFormatter::new(glossary, "en-US");
+
+        let mut request = WorkScheduleEntry::day_off("2025-03-11");
+        request.notes = Some("toive VP".to_string());
+        assert_eq!(
+            formatter.format(&request),
+            format!(
+                "{} · Day off request (toive VP)",
+                t!("work_schedule_day_off")
+            )
+        );
+
+        let mut payday = WorkScheduleEntry::working("2025-03-14", "08:00", "16:00");
+        payday.notes = Some(" Palkat ".to_string());
+        assert_eq!(formatter.format(&payday), "08:00–16:00 · Palkat");
+        assert_eq!(
+            formatter.format_at(&payday, 17 * 60),
+            "~~08:00–16:00~~ · Palkat"
+        );
+        assert_eq!(
+            formatter.format(&WorkScheduleEntry::working("2025-03-10", "07:00", "15:00")),
+            "07:00–15:00"
+        );
+
+        // Only the unknown term is counted, once per time it was shown
+        assert_eq!(
+            formatter.take_missing(),
+            BTreeMap::from([("palkat".to_string(), 2)])
+        );
+        assert!(formatter.take_missing().is_empty());
+    }
+
+    #[test]
+    fn test_context_link_is_shown_in_full_views_only() {
+        let mut linked = WorkScheduleEntry::working("2025-03-10", "07:00", "15:00");
+        linked.context_link = Some(ContextLink {
+            url: "https://discord.com/channels/1/2/3".to_string(),
+            excerpt: "Vaihdoin vuoron Matin kanssa".to_string(),
+        });
+        let formatter = ScheduleFormatter::default();
+        assert_eq!(formatter.format(&linked), "07:00–15:00");
+        assert_eq!(
+            formatter.format_full(&linked),
+            "07:00–15:00 · [📎 context](https://discord.com/channels/1/2/3)"
+        );
+
+        let view = employee_days(
+            "Anna".to_string(),
+            None,
+            "Anna",
+            &[
+                linked.clone(),
+                WorkScheduleEntry::working("2025-03-11", "07:00", "15:00"),
+            ],
+            &formatter,
+        );
+        let text = view.to_text().join("\n");
+        assert_eq!(text.matches("[📎 context](").count(), 1);
+
+        linked.actual_start = Some("07:00".to_string());
+        linked.actual_end = Some("15:30".to_string());
+        assert_eq!(formatter.format(&linked), "07:00–15:00");
+        assert_eq!(
+            formatter.format_full(&linked),
+            "07:00–15:00 · actual 07:00–15:30 · [📎 context](https://discord.com/channels/1/2/3)"
+        );
+    }
+
+    #[test]
+    fn test_profiles_label_employees_and_set_the_thumbnail() {
+        let profiles = EmployeeProfiles::new([(
+            "Anna".to_string(),
+            EmployeeProfile {
+                emoji: Some("🌻".to_string()),
+                avatar_url: Some("https://example.com/anna.png".to_string()),
+            },
+        )]);
+        let formatter = ScheduleFormatter::default().with_profiles(profiles);
+        assert_eq!(formatter.employee("anna"), "🌻 anna");
+        assert_eq!(
+            formatter.employee("Matti"),
+            format!("{} Matti", fallback_emoji("Matti"))
+        );
+        assert_eq!(ScheduleFormatter::default().employee("Anna"), "Anna");
+
+        let week = week_overview(
+            "Week".to_string(),
+            vec![("Anna".to_string(), Ok(Vec::new()))],
+            &formatter,
+        );
+        assert_eq!(week.fields[0].name, "🌻 Anna");
+        assert_eq!(week.thumbnail, None);
+
+        let days = employee_days(
+            "Anna".to_string(),
+            None,
+            "Anna",
+            &[WorkScheduleEntry::working("2025-03-10", "07:00", "15:00")],
+            &formatter,
+        );
+        assert_eq!(
+            days.thumbnail.as_deref(),
+            Some("https://example.com/anna.png")
+        );
+    }
+
+    #[test]
+    fn test_weekend_views() {
+        let saturday = NaiveDate::from_ymd_opt(2025, 3, 15).unwrap();
+        let schedules = vec![
+            EmployeeSchedule {
+                employee: "Anna".to_string(),
+                schedule: vec![
+                    WorkScheduleEntry::working("2025-03-14", "07:00", "15:00"),
+                    WorkScheduleEntry::working("2025-03-16", "10:00", "16:00"),
+                ],
+            },
+            EmployeeSchedule {
+                employee: "Pekka".to_string(),
+                schedule: vec![
+                    WorkScheduleEntry::working("2025-03-15", "12:00", "20:00"),
+                    WorkScheduleEntry::day_off("2025-03-16"),
+                ],
+            },
+        ];
+        let view = weekend_overview(
+            "Weekends".to_string(),
+            &[saturday, saturday + Duration::weeks(1)],
+            &schedules,
+            &ScheduleFormatter::default(),
+        );
+        assert_eq!(
+            view.to_text().join("\n"),
+            "Weekends\n\nWeek 11 · 15.3.–16.3.\nSaturday 15.03.: Pekka: 12:00–20:00\n\
+             Sunday 16.03.: Anna: 10:00–16:00\n\nWeek 12 · 22.3.–23.3.\nNobody works"
+        );
+
+        let share = |employee: &str, prorated| WeekendShare {
+            employee: employee.to_string(),
+            shifts: 3,
+            weeks_with_data: 6,
+            prorated,
+        };
+        let view = weekend_fairness_view(
+            "Fairness".to_string(),
+            12,
+            &[share("Anna", 6.0), share("Pekka", 1.5)],
+            &ScheduleFormatter::default(),
+        );
+        let lines: Vec<&str> = view.fields[0]
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "`▰▰▰▰▰▰▰▰▰▰` **Anna**: 6 · 3 shifts in 6 weeks with data",
+                "`▰▰▰▱▱▱▱▱▱▱` **Pekka**: 1.5 · 3 shifts in 6 weeks with data",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compact_tables_fit_a_phone() {
+        let mut long_note = WorkScheduleEntry::working("2025-03-12", "07:00", "15:00");
+        long_note.notes = Some("Koulutuspäivä pääkonttorilla Tampereella".to_string());
+        let mut split = WorkScheduleEntry::working("2025-03-13", "07:00", "11:00");
+        split.shifts.push(ShiftRange::new("12:00", "16:00"));
+        let mut vacation = WorkScheduleEntry::day_off("2025-03-11");
+        vacation.notes = Some("L".to_string());
+        let entries = vec![
+            split,
+            WorkScheduleEntry::working("2025-03-10", "07:00", "15:00"),
+            vacation,
+            long_note,
+            WorkScheduleEntry::day_off("2025-03-14"),
+        ];
+
+        let view = compact_employee_days("Anna".to_string(), "Anna", &entries);
+        let table = view.description.unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+        assert_eq!(rows.first(), Some(&"```"));
+        assert_eq!(rows.last(), Some(&"```"));
+        assert_eq!(
+            rows[1..rows.len() - 1],
+            [
+                "Mon 10. 07:00-15:00",
+                "Tue 11. -           L",
+                "Wed 12. 07:00-15:00 Koulutuspäivä…",
+                "Thu 13. 07:00-11:00,12:00-16:00",
+                "Fri 14. -           off",
+            ]
+        );
+
+        let schedules = vec![
+            (
+                "Aleksandra Häkkinen-Väänänen".to_string(),
+                Ok(vec![long_note_entry("2025-03-10")]),
+            ),
+            (
+                "Anna".to_string(),
+                Ok(vec![WorkScheduleEntry::day_off("2025-03-10")]),
+            ),
+            ("Pekka".to_string(), Err(other_error("timeout"))),
+        ];
+        let view = compact_week_overview("Week".to_string(), schedules);
+        assert!(view.description.unwrap().contains("Pekka"));
+        assert_eq!(view.fields.len(), 1);
+        assert_eq!(view.fields[0].name, "Monday (2025-03-10)");
+        let table = &view.fields[0].lines[0].text;
+        assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            [
+                "```",
+                "Aleksandra … 07:00-15:00 Koulutus…",
+                "Anna         -           off",
+                "```"
+            ]
+        );
+        for row in table.lines() {
+            assert!(row.chars().count() <= COMPACT_WIDTH, "{row}");
+        }
+    }
+
+    fn long_note_entry(date: &str) -> WorkScheduleEntry {
+        let mut entry = WorkScheduleEntry::working(date, "07:00", "15:00");
+        entry.notes = Some("Koulutuspäivä pääkonttorilla".to_string());
+        entry
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{Local, TimeZone};
+use chrono::Local;
 use lazy_static::lazy_static;
 use poise::serenity_prelude as serenity;
 use std::future::Future;
@@ -10,22 +10,34 @@ use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration as TokioDuration};
 use tracing::{debug, error, info, warn};
 
+use super::exceptions::send_exceptions_digest;
+use super::groups::{load_employee_groups, route_notifications, EmployeeFilter};
 use super::handle::WorkScheduleHandle;
 use super::notifications::{send_daily_notification, send_weekly_notification};
+use super::reconcile::spawn_reconcile;
+use super::stats::weekly_budget;
 use super::time::calculate_next_notification;
+use super::uploads::find_source_image;
+use crate::components::redis_service::RedisActorHandle;
 use crate::config::Config;
 use crate::error::BotResult;
+use crate::guild_config::{notification_settings, NotificationSettings};
+use crate::theme::Theme;
+use crate::utils::notifier::{notification_sinks, DailyReplace, Sinks};
 use crate::utils::scheduler::{
-    is_notification_sent, reset_notification_flag, sleep_until_target_time, try_claim_notification,
+    deliver_notification, is_notification_sent, next_wake_time, reset_notification_flag,
+    retry_pending_notifications, sleep_until_target_time, try_claim_notification,
     update_last_sent_date, update_notification_flags, NotificationHandler, NotificationType,
-    Scheduler,
+    Scheduler, SharedContext,
 };
-use crate::utils::time::get_weekly_date_range;
+use crate::utils::time::{get_weekly_date_range, resolve_local};
+use crate::watchdog::{SchedulerHeartbeat, Target};
 
 lazy_static! {
     static ref SCHEDULER_INSTANCES: AtomicU32 = AtomicU32::new(0);
     static ref SCHEDULER_TASK_RUNNING: AtomicBool = AtomicBool::new(false);
     static ref SCHEDULER_TASK: RwLock<Option<JoinHandle<()>>> = RwLock::new(None);
+    static ref RECONCILE_TASK: RwLock<Option<JoinHandle<()>>> = RwLock::new(None);
 }
 
 /// Work Schedule scheduler implementation
@@ -40,42 +52,136 @@ impl Default for WorkScheduleScheduler {
 /// WorkSchedule notification handler implementation
 struct WorkScheduleNotificationHandler {
     handle: WorkScheduleHandle,
+    redis_handle: RedisActorHandle,
+    config: Arc<RwLock<Config>>,
 }
 
 impl NotificationHandler for WorkScheduleNotificationHandler {
     fn send_daily_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
         let handle = self.handle.clone();
 
         Box::pin(async move {
             let today = Local::now().format("%Y-%m-%d").to_string();
+            let config = self.config.read().await.clone();
+            let mode = DailyReplace::from_config(&config);
+            let sink = notification_sinks(http, &self.redis_handle, &config, &sinks);
             info!("Sending daily work schedule notification for {}", today);
-            send_daily_notification(ctx, channel_id, &handle, &today).await
+            for (channel_id, filter) in self.routes(&handle, channel_id).await? {
+                let theme = Theme::for_channel(http, &self.redis_handle, channel_id).await;
+                send_daily_notification(&sink, channel_id, &handle, &today, &filter, mode, &theme)
+                    .await?;
+            }
+            Ok(())
         })
     }
 
     fn send_weekly_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
         let handle = self.handle.clone();
 
         Box::pin(async move {
+            let config = self.config.read().await.clone();
             let now = Local::now();
-            let (start_date, end_date) = get_weekly_date_range(&now);
+            let (start_date, end_date) = get_weekly_date_range(&now, config.week_starts_on);
             info!(
                 "Sending weekly work schedule notification for {} to {}",
                 start_date, end_date
             );
-            send_weekly_notification(ctx, channel_id, &handle, &start_date, &end_date).await
+
+            let budget = weekly_budget(&self.redis_handle, &config).await;
+            let source_image = if config.attach_source_image_weekly {
+                find_source_image(&self.redis_handle, &config, &start_date, &end_date).await
+            } else {
+                None
+            };
+
+            let sink = notification_sinks(http, &self.redis_handle, &config, &sinks);
+            for (channel_id, filter) in self.routes(&handle, channel_id).await? {
+                let theme = Theme::for_channel(http, &self.redis_handle, channel_id).await;
+                send_weekly_notification(
+                    &sink,
+                    channel_id,
+                    &handle,
+                    &start_date,
+                    &end_date,
+                    &budget,
+                    &filter,
+                    source_image.clone(),
+                    &theme,
+                )
+                .await?;
+            }
+
+            send_exceptions_digest(
+                http,
+                &handle,
+                &self.redis_handle,
+                &config,
+                &start_date,
+                &end_date,
+            )
+            .await;
+            Ok(())
         })
     }
 }
 
+impl WorkScheduleNotificationHandler {
+    /// Channels to notify and the employees each one shows
+    async fn routes(
+        &self,
+        handle: &WorkScheduleHandle,
+        default_channel: u64,
+    ) -> BotResult<Vec<(u64, EmployeeFilter)>> {
+        notification_routes(handle, &self.redis_handle, &self.config, default_channel).await
+    }
+}
+
+/// Channels to notify and the employees each one shows, everyone in `default_channel` unless
+/// notification routes are configured
+pub(super) async fn notification_routes(
+    handle: &WorkScheduleHandle,
+    redis_handle: &RedisActorHandle,
+    config: &RwLock<Config>,
+    default_channel: u64,
+) -> BotResult<Vec<(u64, EmployeeFilter)>> {
+    let routes = config.read().await.notification_routes.clone();
+    if routes.is_empty() {
+        return Ok(vec![(default_channel, EmployeeFilter::All)]);
+    }
+
+    let groups = load_employee_groups(redis_handle).await?;
+    let employees = handle.get_employees().await?;
+    Ok(route_notifications(
+        &routes,
+        &groups,
+        &employees,
+        default_channel,
+    ))
+}
+
+/// Notification handler for sending outside the scheduler, e.g. from a one-shot run
+pub fn notification_handler(
+    handle: WorkScheduleHandle,
+    redis_handle: RedisActorHandle,
+    config: Arc<RwLock<Config>>,
+) -> Arc<dyn NotificationHandler> {
+    Arc::new(WorkScheduleNotificationHandler {
+        handle,
+        redis_handle,
+        config,
+    })
+}
+
 impl Scheduler for WorkScheduleScheduler {
     type Handle = WorkScheduleHandle;
 
@@ -85,9 +191,10 @@ impl Scheduler for WorkScheduleScheduler {
 
     /// Start the notification scheduler
     fn start(
-        ctx: Arc<serenity::Context>,
+        ctx: SharedContext,
         config: Arc<RwLock<Config>>,
         handle: Self::Handle,
+        redis_handle: RedisActorHandle,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send>> {
         Box::pin(async move {
             // Increment instance counter and log
@@ -103,13 +210,6 @@ impl Scheduler for WorkScheduleScheduler {
                 instance_count
             );
 
-            // Read config values
-            let config_read = config.read().await;
-            let daily_time = config_read.daily_notification_time.clone();
-            let weekly_time = config_read.weekly_notification_time.clone();
-            let channel_id = config_read.calendar_channel_id; // Reusing calendar channel for now
-            drop(config_read);
-
             // Only spawn the scheduler task if it's not already running
             if !SCHEDULER_TASK_RUNNING.swap(true, Ordering::SeqCst) {
                 info!("Starting Work Schedule notification task");
@@ -117,6 +217,8 @@ impl Scheduler for WorkScheduleScheduler {
                 // Create the notification handler
                 let notification_handler = WorkScheduleNotificationHandler {
                     handle: handle.clone(),
+                    redis_handle: redis_handle.clone(),
+                    config: Arc::clone(&config),
                 };
                 let notification_handler = Arc::new(notification_handler);
 
@@ -124,9 +226,7 @@ impl Scheduler for WorkScheduleScheduler {
                 let component_type = Self::component_type();
 
                 // Clone values for the task
-                let ctx_clone = Arc::clone(&ctx);
-                let daily_time = daily_time.clone();
-                let weekly_time = weekly_time.clone();
+                let ctx_clone = ctx.clone();
                 let component_type_clone = component_type.clone();
                 let config_for_task = Arc::clone(&config);
 
@@ -134,18 +234,19 @@ impl Scheduler for WorkScheduleScheduler {
                 let task = tokio::spawn(async move {
                     run_scheduler_loop(
                         ctx_clone,
-                        &daily_time,
-                        &weekly_time,
-                        channel_id,
                         notification_handler,
                         &component_type_clone,
                         config_for_task,
+                        redis_handle,
                     )
                     .await;
                 });
 
                 // Store the task handle in the static storage
                 *SCHEDULER_TASK.write().await = Some(task);
+
+                // Check the stored keys against their indexes every night
+                *RECONCILE_TASK.write().await = Some(spawn_reconcile(ctx, config, handle));
             } else {
                 warn!(
                     "Work Schedule notification task is already running, skipping initialization"
@@ -165,6 +266,13 @@ impl Scheduler for WorkScheduleScheduler {
                 task.abort();
                 SCHEDULER_TASK_RUNNING.store(false, Ordering::SeqCst);
             }
+            if let Some(task) = RECONCILE_TASK.write().await.take() {
+                info!("Aborting work schedule reconciliation task");
+                task.abort();
+            }
+
+            // Starting again, e.g. after regaining leadership, isn't a second instance
+            SCHEDULER_INSTANCES.store(0, Ordering::SeqCst);
 
             info!("Work Schedule scheduler stopped");
             Ok(())
@@ -174,28 +282,45 @@ impl Scheduler for WorkScheduleScheduler {
 
 /// Main scheduler loop that handles notification timing and sending
 async fn run_scheduler_loop(
-    ctx: Arc<serenity::Context>,
-    daily_time: &str,
-    weekly_time: &str,
-    channel_id: u64,
+    ctx: SharedContext,
     handler: Arc<dyn NotificationHandler>,
     component_type: &str,
     config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
 ) {
+    let liveness =
+        SchedulerHeartbeat::register("work_schedule", component_type, redis_handle.clone());
     loop {
         // Get the current time
         let now = Local::now();
         let today = now.format("%Y-%m-%d").to_string();
-        let (week_start_date, _) = get_weekly_date_range(&now);
+        let week_start = config.read().await.week_starts_on;
+        let (week_start_date, _) = get_weekly_date_range(&now, week_start);
+        let NotificationSettings {
+            channel_id,
+            daily_time,
+            weekly_time,
+        } = notification_settings(&redis_handle, &config).await;
 
         // Update flags based on current date
         update_notification_flags(&today, &week_start_date, component_type).await;
 
+        // Retry notifications that couldn't be delivered earlier
+        let has_pending = retry_pending_notifications(
+            &ctx,
+            handler.as_ref(),
+            &redis_handle,
+            component_type,
+            week_start,
+        )
+        .await;
+
         // Read config to determine if daily/weekly notifications are disabled
         let (daily_disabled, weekly_disabled) = {
             let cfg = config.read().await;
             (
-                cfg.disable_work_schedule_daily_notifications,
+                // The combined digest covers the daily schedule
+                cfg.disable_work_schedule_daily_notifications || cfg.combined_daily_digest,
                 cfg.disable_work_schedule_weekly_notifications,
             )
         };
@@ -209,7 +334,7 @@ async fn run_scheduler_loop(
             sleep(TokioDuration::from_secs(3600)).await; // Sleep an hour before re-checking
             continue;
         } else if daily_disabled {
-            match crate::utils::time::next_weekly_time(&now, weekly_time) {
+            match crate::utils::time::next_weekly_time(&now, weekly_time, week_start) {
                 Some(t) => ("weekly".to_string(), t),
                 None => {
                     error!("Failed to calculate next weekly notification time");
@@ -227,7 +352,7 @@ async fn run_scheduler_loop(
                 }
             }
         } else {
-            match calculate_next_notification(&now, daily_time, weekly_time) {
+            match calculate_next_notification(&now, daily_time, weekly_time, week_start) {
                 Ok(result) => result,
                 Err(e) => {
                     error!("Error calculating next notification time: {}", e);
@@ -270,23 +395,40 @@ async fn run_scheduler_loop(
         );
 
         // Convert NaiveDateTime to DateTime<Local> for the sleep_until_target_time function
-        let local_time = match Local.from_local_datetime(&next_time) {
-            chrono::LocalResult::Single(dt) => dt,
-            _ => {
+        let local_time = match resolve_local(&Local, next_time) {
+            Some(dt) => dt,
+            None => {
                 error!("Failed to convert NaiveDateTime to DateTime<Local>, using current time");
                 Local::now()
             }
         };
 
-        // Sleep until the target time
-        if let Err(e) = sleep_until_target_time(local_time).await {
+        // Sleep until the target time, waking up earlier to retry parked notifications
+        let wake_time = next_wake_time(local_time, has_pending);
+        let target = Target::new(notification_type_enum.clone(), local_time, week_start);
+        liveness.waiting_until(wake_time, Some(target)).await;
+        if let Err(e) = sleep_until_target_time(wake_time).await {
             error!("Error while waiting for target time: {:?}", e);
             sleep(TokioDuration::from_secs(60)).await; // Wait a minute before retrying
             continue;
         }
+        if wake_time < local_time {
+            continue;
+        }
 
         // Try to claim the notification
-        if !try_claim_notification(notification_type_enum.clone(), component_type).await {
+        let date = match notification_type_enum {
+            NotificationType::Daily => today.clone(),
+            NotificationType::Weekly => week_start_date.clone(),
+        };
+        if !try_claim_notification(
+            notification_type_enum.clone(),
+            component_type,
+            &redis_handle,
+            &date,
+        )
+        .await
+        {
             info!(
                 "[{}] {:?} notification already claimed by another instance",
                 component_type, notification_type_enum
@@ -300,7 +442,8 @@ async fn run_scheduler_loop(
         let (daily_disabled_now, weekly_disabled_now) = {
             let cfg = config.read().await;
             (
-                cfg.disable_work_schedule_daily_notifications,
+                // The combined digest covers the daily schedule
+                cfg.disable_work_schedule_daily_notifications || cfg.combined_daily_digest,
                 cfg.disable_work_schedule_weekly_notifications,
             )
         };
@@ -308,7 +451,7 @@ async fn run_scheduler_loop(
         // If the selected type is disabled now, skip sending and mark as "done" for this period
         if matches!(notification_type_enum, NotificationType::Daily) && daily_disabled_now {
             info!(
-                "[{}] Daily work schedule notifications are disabled via env; skipping send",
+                "[{}] Daily work schedule notifications are disabled or sent in the digest; skipping send",
                 component_type
             );
             update_last_sent_date(NotificationType::Daily, &today, component_type).await;
@@ -327,29 +470,37 @@ async fn run_scheduler_loop(
             continue;
         }
 
-        let result = match notification_type_enum {
-            NotificationType::Daily => handler.send_daily_notification(&ctx, channel_id).await,
-            NotificationType::Weekly => handler.send_weekly_notification(&ctx, channel_id).await,
-        };
+        // Send through the latest context, parking the notification if Discord is unreachable
+        let result = deliver_notification(
+            &ctx,
+            handler.as_ref(),
+            &redis_handle,
+            component_type,
+            notification_type_enum.clone(),
+            &date,
+            channel_id,
+        )
+        .await;
 
         // Handle the notification result
         if let Err(e) = result {
             error!(
-                "[{}] Failed to send {:?} work schedule notification: {}",
+                "[{}] Failed to send {:?} work schedule notification, retrying later: {}",
                 component_type, notification_type_enum, e
             );
-            // Reset the flag if sending failed so we can try again
-            reset_notification_flag(notification_type_enum.clone(), component_type).await;
+            // Not sent yet, so the pending retry can claim it
+            reset_notification_flag(
+                notification_type_enum.clone(),
+                component_type,
+                &redis_handle,
+                &date,
+            )
+            .await;
         } else {
             info!(
                 "[{}] Successfully sent {:?} work schedule notification",
                 component_type, notification_type_enum
             );
-            // Update the last sent date
-            let date = match notification_type_enum {
-                NotificationType::Daily => today.clone(),
-                NotificationType::Weekly => week_start_date.clone(),
-            };
             update_last_sent_date(notification_type_enum, &date, component_type).await;
         }
 
@@ -0,0 +1,756 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::WORK_HOURS_CONTRACT_HOURS;
+use crate::components::work_schedule::models::{EmployeeSchedule, WorkScheduleEntry};
+use crate::components::work_schedule::EmployeeId;
+use crate::config::Config;
+use crate::error::{work_schedule_error, BotResult};
+use crate::utils::i18n::format_hours;
+use crate::utils::time::{week_bounds, WeekStart};
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Hours a week may differ from the contract before it's flagged, unless configured otherwise
+pub const DEFAULT_TOLERANCE_HOURS: f64 = 2.0;
+
+/// Read the tolerance setting, falling back to the default when it's unset or invalid
+pub fn parse_tolerance(value: Option<&str>) -> f64 {
+    value
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .unwrap_or(DEFAULT_TOLERANCE_HOURS)
+}
+
+/// Weekly hours an employee's contract specifies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractHours {
+    /// Display name of the employee
+    pub employee: String,
+    pub hours_per_week: f64,
+}
+
+/// Scheduled time within one Monday-to-Sunday week
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeekTotal {
+    /// Monday of the week
+    pub week_start: NaiveDate,
+    /// Working time over all shifts with a known start and end
+    pub minutes: u32,
+    /// Weekdays (Monday to Friday) of the week inside the range the total covers
+    pub covered_weekdays: u32,
+}
+
+/// How a week compares to the contract
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deviation {
+    Within,
+    /// Hours over the contract
+    Over(f64),
+    /// Hours under the contract
+    Under(f64),
+}
+
+impl WeekTotal {
+    /// Contract hours for the part of the week the total covers, spread over the weekdays
+    pub fn expected_minutes(&self, contract_hours: f64) -> f64 {
+        contract_hours * 60.0 * f64::from(self.covered_weekdays) / 5.0
+    }
+
+    /// Compare the total to the contract, ignoring differences within the tolerance
+    pub fn deviation(&self, contract_hours: f64, tolerance_hours: f64) -> Deviation {
+        let difference = (f64::from(self.minutes) - self.expected_minutes(contract_hours)) / 60.0;
+        if difference.abs() <= tolerance_hours {
+            Deviation::Within
+        } else if difference > 0.0 {
+            Deviation::Over(difference)
+        } else {
+            Deviation::Under(-difference)
+        }
+    }
+
+    /// One-line summary such as "Σ 45 h / 37.5 h · 🔴 +7.5 h over"
+    pub fn summary(&self, contract_hours: f64, tolerance_hours: f64) -> String {
+        let locale = rust_i18n::locale();
+        let format_hours = |hours: f64| format_hours(hours, &locale);
+        let total = t!(
+            "contract_hours_total",
+            total = format_hours(f64::from(self.minutes) / 60.0),
+            contract = format_hours(self.expected_minutes(contract_hours) / 60.0)
+        );
+        match self.deviation(contract_hours, tolerance_hours) {
+            Deviation::Within => total.to_string(),
+            Deviation::Over(hours) => format!(
+                "{total} · {}",
+                t!("contract_hours_over", hours = format_hours(hours))
+            ),
+            Deviation::Under(hours) => format!(
+                "{total} · {}",
+                t!("contract_hours_under", hours = format_hours(hours))
+            ),
+        }
+    }
+}
+
+/// Sum the entries from `start` to `end` (inclusive) per week.
+///
+/// Weeks cut off by the start or end of the range only cover the days inside it, so their
+/// contract hours are prorated instead of flagging a half week as under-scheduled.
+pub fn weekly_totals(
+    entries: &[WorkScheduleEntry],
+    start: NaiveDate,
+    end: NaiveDate,
+    week_start: WeekStart,
+) -> Vec<WeekTotal> {
+    let mut minutes_by_date: HashMap<NaiveDate, u32> = HashMap::new();
+    for entry in entries {
+        if let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") {
+            *minutes_by_date.entry(date).or_default() += entry.total_minutes();
+        }
+    }
+
+    let mut totals: Vec<WeekTotal> = Vec::new();
+    for date in start.iter_days().take_while(|date| *date <= end) {
+        let (first, _) = week_bounds(date, week_start);
+        if totals.last().is_none_or(|week| week.week_start != first) {
+            totals.push(WeekTotal {
+                week_start: first,
+                minutes: 0,
+                covered_weekdays: 0,
+            });
+        }
+        let Some(week) = totals.last_mut() else {
+            continue;
+        };
+
+        week.minutes += minutes_by_date.get(&date).copied().unwrap_or(0);
+        if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            week.covered_weekdays += 1;
+        }
+    }
+    totals
+}
+
+/// Contract hours of every employee that has them, with the tolerance for flagging a week
+#[derive(Debug, Clone)]
+pub struct HoursBudget {
+    /// Weekly hours by employee slug
+    contracts: HashMap<String, f64>,
+    pub tolerance_hours: f64,
+    /// First day of the weeks the contract hours are compared over
+    pub week_start: WeekStart,
+}
+
+impl HoursBudget {
+    pub fn new(contracts: impl IntoIterator<Item = ContractHours>, tolerance_hours: f64) -> Self {
+        Self {
+            contracts: contracts
+                .into_iter()
+                .map(|contract| {
+                    let slug = EmployeeId::new(&contract.employee).slug().to_string();
+                    (slug, contract.hours_per_week)
+                })
+                .collect(),
+            tolerance_hours,
+            week_start: WeekStart::default(),
+        }
+    }
+
+    /// Compare weeks starting on another day than Monday
+    pub fn with_week_start(mut self, week_start: WeekStart) -> Self {
+        self.week_start = week_start;
+        self
+    }
+
+    /// Weekly contract hours of an employee, if set
+    pub fn contract_for(&self, employee: &str) -> Option<f64> {
+        self.contracts
+            .get(EmployeeId::new(employee).slug())
+            .copied()
+    }
+
+    /// Summary line per week of the range for an employee with contract hours
+    pub fn week_summaries(
+        &self,
+        employee: &str,
+        entries: &[WorkScheduleEntry],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Vec<String> {
+        let Some(contract_hours) = self.contract_for(employee) else {
+            return Vec::new();
+        };
+        weekly_totals(entries, start, end, self.week_start)
+            .iter()
+            .map(|week| week.summary(contract_hours, self.tolerance_hours))
+            .collect()
+    }
+}
+
+/// Consecutive days from `first` to `last`, both included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayRange {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl DayRange {
+    /// Format as "7.7.–25.7.", or "7.7." for a single day
+    pub fn format(&self) -> String {
+        let first = self.first.format("%-d.%-m.").to_string();
+        if self.first == self.last {
+            first
+        } else {
+            format!("{first}–{}", self.last.format("%-d.%-m."))
+        }
+    }
+}
+
+/// Compress dates into runs of consecutive days, in order
+pub fn compress_dates(dates: &[NaiveDate]) -> Vec<DayRange> {
+    let mut dates = dates.to_vec();
+    dates.sort();
+    dates.dedup();
+
+    let mut ranges: Vec<DayRange> = Vec::new();
+    for date in dates {
+        match ranges.last_mut() {
+            Some(range) if range.last.succ_opt() == Some(date) => range.last = date,
+            _ => ranges.push(DayRange {
+                first: date,
+                last: date,
+            }),
+        }
+    }
+    ranges
+}
+
+/// The week most employees are away in for at least a day, as its first day and the number of
+/// employees. Ties go to the earliest week.
+pub fn busiest_week(
+    away_by_employee: &[Vec<NaiveDate>],
+    week_start: WeekStart,
+) -> Option<(NaiveDate, usize)> {
+    let mut away_by_week: HashMap<NaiveDate, usize> = HashMap::new();
+    for dates in away_by_employee {
+        let mut weeks: Vec<NaiveDate> = dates
+            .iter()
+            .map(|date| week_bounds(*date, week_start).0)
+            .collect();
+        weeks.sort();
+        weeks.dedup();
+        for week in weeks {
+            *away_by_week.entry(week).or_default() += 1;
+        }
+    }
+
+    away_by_week
+        .into_iter()
+        .max_by(|(a_week, a_count), (b_week, b_count)| {
+            a_count.cmp(b_count).then(b_week.cmp(a_week))
+        })
+}
+
+/// Whether a date falls on a Saturday or Sunday
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Weekend shifts an employee worked over a window of weeks
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekendShare {
+    /// Display name of the employee
+    pub employee: String,
+    /// Saturdays and Sundays worked
+    pub shifts: u32,
+    /// Weeks of the window the employee has any stored entries in
+    pub weeks_with_data: u32,
+    /// Shifts scaled to the whole window, so employees who joined partway through compare
+    /// fairly with the rest
+    pub prorated: f64,
+}
+
+/// Count everyone's weekend shifts from `start` to `end` (inclusive), busiest first.
+///
+/// Employees with stored entries for only part of the window, such as new hires, have their
+/// count prorated by the weeks they have data for. Employees without any entries in the window
+/// are left out.
+pub fn weekend_fairness(
+    schedules: &[EmployeeSchedule],
+    start: NaiveDate,
+    end: NaiveDate,
+    week_start: WeekStart,
+) -> Vec<WeekendShare> {
+    let week_of = |date: NaiveDate| week_bounds(date, week_start).0;
+    let window_weeks = {
+        let mut weeks: Vec<NaiveDate> = start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(week_of)
+            .collect();
+        weeks.dedup();
+        weeks.len() as u32
+    };
+
+    let mut shares: Vec<WeekendShare> = schedules
+        .iter()
+        .filter_map(|schedule| {
+            let mut weeks = Vec::new();
+            let mut shifts = 0;
+            for entry in &schedule.schedule {
+                let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") else {
+                    continue;
+                };
+                if date < start || date > end {
+                    continue;
+                }
+                weeks.push(week_of(date));
+                if is_weekend(date) && entry.is_working() {
+                    shifts += 1;
+                }
+            }
+            weeks.sort();
+            weeks.dedup();
+            let weeks_with_data = weeks.len() as u32;
+            (weeks_with_data > 0).then(|| WeekendShare {
+                employee: schedule.employee.clone(),
+                shifts,
+                weeks_with_data,
+                prorated: f64::from(shifts) * f64::from(window_weeks) / f64::from(weeks_with_data),
+            })
+        })
+        .collect();
+    shares.sort_by(|a, b| {
+        b.prorated
+            .total_cmp(&a.prorated)
+            .then_with(|| a.employee.cmp(&b.employee))
+    });
+    shares
+}
+
+/// Planned and actually worked time over past days
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Variance {
+    /// Planned time of the days with actual hours recorded
+    pub planned_minutes: u32,
+    /// Time actually worked on those days
+    pub actual_minutes: u32,
+    /// Days with actual hours recorded
+    pub recorded_days: u32,
+    /// Working days still without actual hours, left out of the totals
+    pub missing_days: u32,
+}
+
+impl Variance {
+    /// Add a day. Days with actual hours count even when none were planned, such as an
+    /// unplanned shift on a day off.
+    pub fn add(&mut self, entry: &WorkScheduleEntry) {
+        match entry.actual_minutes() {
+            Some(actual) => {
+                self.planned_minutes += entry.total_minutes();
+                self.actual_minutes += actual;
+                self.recorded_days += 1;
+            }
+            None if entry.is_working() => self.missing_days += 1,
+            None => {}
+        }
+    }
+
+    /// Add up another variance
+    pub fn merge(&mut self, other: &Variance) {
+        self.planned_minutes += other.planned_minutes;
+        self.actual_minutes += other.actual_minutes;
+        self.recorded_days += other.recorded_days;
+        self.missing_days += other.missing_days;
+    }
+
+    /// Actual minus planned time, negative when less was worked than planned
+    pub fn difference_minutes(&self) -> i64 {
+        i64::from(self.actual_minutes) - i64::from(self.planned_minutes)
+    }
+}
+
+/// Compare the planned and actual hours of the entries from `start` to `end` (inclusive)
+pub fn variance(entries: &[WorkScheduleEntry], start: NaiveDate, end: NaiveDate) -> Variance {
+    let mut variance = Variance::default();
+    for entry in entries {
+        let Ok(date) = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d") else {
+            continue;
+        };
+        if date >= start && date <= end {
+            variance.add(entry);
+        }
+    }
+    variance
+}
+
+/// Load the contract hours of every employee, skipping unreadable records
+pub async fn load_contract_hours(redis_handle: &RedisActorHandle) -> BotResult<Vec<ContractHours>> {
+    let stored: Vec<String> = redis_handle.hvals(&WORK_HOURS_CONTRACT_HOURS).await?;
+
+    let mut contracts: Vec<ContractHours> = stored
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| warn!("Ignoring invalid contract hours: {}", e))
+                .ok()
+        })
+        .collect();
+    contracts.sort_by(|a, b| a.employee.cmp(&b.employee));
+    Ok(contracts)
+}
+
+/// Budget the weekly notification compares schedules against. Contract hours that can't be
+/// loaded are left out so the notification still goes out.
+pub async fn weekly_budget(redis_handle: &RedisActorHandle, config: &Config) -> HoursBudget {
+    let contracts = load_contract_hours(redis_handle).await.unwrap_or_else(|e| {
+        warn!("Failed to load contract hours: {}", e);
+        Vec::new()
+    });
+    HoursBudget::new(contracts, config.contract_hours_tolerance)
+        .with_week_start(config.week_starts_on)
+}
+
+/// Set an employee's weekly contract hours, or remove them with `None`
+pub async fn set_contract_hours(
+    redis_handle: &RedisActorHandle,
+    employee: &str,
+    hours_per_week: Option<f64>,
+) -> BotResult<()> {
+    let employee = EmployeeId::new(employee);
+    let Some(hours_per_week) = hours_per_week else {
+        return redis_handle
+            .hdel(&WORK_HOURS_CONTRACT_HOURS, employee.slug())
+            .await;
+    };
+
+    let contract = ContractHours {
+        employee: employee.display().to_string(),
+        hours_per_week,
+    };
+    let json = serde_json::to_string(&contract)
+        .map_err(|e| work_schedule_error(&format!("Failed to serialize contract hours: {e}")))?;
+    redis_handle
+        .hset(&WORK_HOURS_CONTRACT_HOURS, employee.slug(), json)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::ShiftRange;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    /// An 8 hour day
+    fn workday(date: &str) -> WorkScheduleEntry {
+        WorkScheduleEntry {
+            shifts: vec![ShiftRange::new("08:00", "16:00")],
+            ..WorkScheduleEntry::new(date.to_string())
+        }
+    }
+
+    #[test]
+    fn test_full_week_over_and_under() {
+        // Monday 2025-01-06 to Sunday 2025-01-12
+        let mut entries: Vec<_> = (6..=10)
+            .map(|day| workday(&format!("2025-01-{day:02}")))
+            .collect();
+        entries.push(workday("2025-01-11"));
+        entries.push(WorkScheduleEntry {
+            shifts: vec![ShiftRange::new("10:00", "15:30")],
+            ..WorkScheduleEntry::new("2025-01-12".to_string())
+        });
+
+        let totals = weekly_totals(
+            &entries,
+            date("2025-01-06"),
+            date("2025-01-12"),
+            WeekStart::Monday,
+        );
+        assert_eq!(
+            totals,
+            [WeekTotal {
+                week_start: date("2025-01-06"),
+                minutes: 53 * 60 + 30,
+                covered_weekdays: 5,
+            }]
+        );
+        assert_eq!(totals[0].deviation(37.5, 2.0), Deviation::Over(16.0));
+        assert_eq!(
+            totals[0].summary(37.5, 2.0),
+            "Σ 53.5 h / 37.5 h · 🔴 +16 h over"
+        );
+
+        let short = weekly_totals(
+            &entries[..3],
+            date("2025-01-06"),
+            date("2025-01-12"),
+            WeekStart::Monday,
+        );
+        assert_eq!(short[0].deviation(37.5, 2.0), Deviation::Under(13.5));
+        assert_eq!(short[0].deviation(24.0, 2.0), Deviation::Within);
+    }
+
+    #[test]
+    fn test_partial_weeks_at_range_edges_are_prorated() {
+        // Thursday 2025-01-09 to Tuesday 2025-01-14 spans the end of one week and the start
+        // of the next
+        let entries = [
+            workday("2025-01-09"),
+            workday("2025-01-10"),
+            workday("2025-01-13"),
+            workday("2025-01-14"),
+            // Outside the range
+            workday("2025-01-15"),
+        ];
+
+        let totals = weekly_totals(
+            &entries,
+            date("2025-01-09"),
+            date("2025-01-14"),
+            WeekStart::Monday,
+        );
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].week_start, date("2025-01-06"));
+        assert_eq!(totals[0].covered_weekdays, 2);
+        assert_eq!(totals[1].week_start, date("2025-01-13"));
+        assert_eq!(totals[1].covered_weekdays, 2);
+
+        // Two 8 hour days against two fifths of 40 hours is right on target
+        for week in &totals {
+            assert_eq!(week.minutes, 16 * 60);
+            assert_eq!(week.deviation(40.0, 0.5), Deviation::Within);
+            assert_eq!(week.summary(40.0, 0.5), "Σ 16 h / 16 h");
+        }
+
+        // A range ending on a weekend covers no weekdays of that week, so any work is extra
+        let weekend = weekly_totals(
+            &[workday("2025-01-11")],
+            date("2025-01-11"),
+            date("2025-01-12"),
+            WeekStart::Monday,
+        );
+        assert_eq!(weekend[0].covered_weekdays, 0);
+        assert_eq!(weekend[0].deviation(37.5, 2.0), Deviation::Over(8.0));
+    }
+
+    #[test]
+    fn test_sunday_start_week_is_one_total() {
+        // Sunday 2025-01-05 to Saturday 2025-01-11
+        let entries: Vec<_> = (5..=11)
+            .map(|day| workday(&format!("2025-01-{day:02}")))
+            .collect();
+
+        let totals = weekly_totals(
+            &entries,
+            date("2025-01-05"),
+            date("2025-01-11"),
+            WeekStart::Sunday,
+        );
+        assert_eq!(
+            totals,
+            [WeekTotal {
+                week_start: date("2025-01-05"),
+                minutes: 7 * 8 * 60,
+                covered_weekdays: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_budget_matches_employee_names() {
+        let budget = HoursBudget::new(
+            [ContractHours {
+                employee: "Anna Mäkinen".to_string(),
+                hours_per_week: 37.5,
+            }],
+            DEFAULT_TOLERANCE_HOURS,
+        );
+        assert_eq!(budget.contract_for("anna  makinen"), Some(37.5));
+        assert!(budget
+            .week_summaries("Pekka", &[], date("2025-01-06"), date("2025-01-12"))
+            .is_empty());
+        assert_eq!(
+            budget.week_summaries("Anna Mäkinen", &[], date("2025-01-06"), date("2025-01-12")),
+            ["Σ 0 h / 37.5 h · 🟡 −37.5 h under"]
+        );
+    }
+
+    #[test]
+    fn test_single_days_are_ranges_of_their_own() {
+        let ranges = compress_dates(&[date("2025-07-07"), date("2025-07-09")]);
+        assert_eq!(
+            ranges.iter().map(DayRange::format).collect::<Vec<_>>(),
+            ["7.7.", "9.7."]
+        );
+        assert!(compress_dates(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_ranges_cross_week_boundaries() {
+        // Thursday to the next Tuesday, unordered and with a duplicate
+        let dates = [
+            "2025-07-14",
+            "2025-07-10",
+            "2025-07-11",
+            "2025-07-15",
+            "2025-07-12",
+            "2025-07-13",
+            "2025-07-11",
+        ]
+        .map(date);
+        assert_eq!(
+            compress_dates(&dates),
+            [DayRange {
+                first: date("2025-07-10"),
+                last: date("2025-07-15"),
+            }]
+        );
+        assert_eq!(compress_dates(&dates)[0].format(), "10.7.–15.7.");
+    }
+
+    #[test]
+    fn test_gaps_split_ranges() {
+        let dates = [
+            "2025-07-07",
+            "2025-07-08",
+            "2025-07-10",
+            "2025-07-31",
+            "2025-08-01",
+        ]
+        .map(date);
+        assert_eq!(
+            compress_dates(&dates)
+                .iter()
+                .map(DayRange::format)
+                .collect::<Vec<_>>(),
+            ["7.7.–8.7.", "10.7.", "31.7.–1.8."]
+        );
+    }
+
+    #[test]
+    fn test_busiest_week_counts_employees_once() {
+        let away = vec![
+            // Away all of the week of 7.7. and on Monday the next week
+            ["2025-07-07", "2025-07-08", "2025-07-14"]
+                .map(date)
+                .to_vec(),
+            ["2025-07-15"].map(date).to_vec(),
+            ["2025-07-09"].map(date).to_vec(),
+            Vec::new(),
+        ];
+        // Both weeks have two employees away, so the earlier one wins
+        assert_eq!(
+            busiest_week(&away, WeekStart::Monday),
+            Some((date("2025-07-07"), 2))
+        );
+        assert_eq!(busiest_week(&[], WeekStart::Monday), None);
+    }
+
+    fn schedule(employee: &str, entries: Vec<WorkScheduleEntry>) -> EmployeeSchedule {
+        EmployeeSchedule {
+            employee: employee.to_string(),
+            schedule: entries,
+        }
+    }
+
+    #[test]
+    fn test_weekend_fairness_prorates_new_hires() {
+        // Four weeks from Monday 2025-01-06 to Sunday 2025-02-02
+        let (start, end) = (date("2025-01-06"), date("2025-02-02"));
+        let weekends = [
+            "2025-01-11",
+            "2025-01-12",
+            "2025-01-18",
+            "2025-01-25",
+            "2025-02-01",
+        ];
+        let anna = schedule("Anna", weekends[..3].iter().map(|d| workday(d)).collect());
+        let mut pekka_entries = vec![workday("2025-01-06"), workday("2025-01-18")];
+        // Days off and entries outside the window don't count as shifts
+        pekka_entries.push(WorkScheduleEntry {
+            is_day_off: true,
+            ..WorkScheduleEntry::new("2025-01-19".to_string())
+        });
+        pekka_entries.push(workday("2025-01-04"));
+        let pekka = schedule("Pekka", pekka_entries);
+        // Joined for the last week only
+        let liisa = schedule("Liisa", vec![workday("2025-02-01")]);
+        let nobody = schedule("Mikko", vec![workday("2024-12-28")]);
+
+        let shares = weekend_fairness(&[pekka, anna, liisa, nobody], start, end, WeekStart::Monday);
+        let share = |employee: &str, shifts, weeks_with_data, prorated| WeekendShare {
+            employee: employee.to_string(),
+            shifts,
+            weeks_with_data,
+            prorated,
+        };
+        assert_eq!(
+            shares,
+            vec![
+                share("Anna", 3, 2, 6.0),
+                // One shift in her only week is a lot over the whole window
+                share("Liisa", 1, 1, 4.0),
+                share("Pekka", 1, 2, 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variance_skips_days_without_actuals() {
+        let actual = |date: &str, start: &str, end: &str| WorkScheduleEntry {
+            actual_start: Some(start.to_string()),
+            actual_end: Some(end.to_string()),
+            ..workday(date)
+        };
+        let entries = vec![
+            // 30 minutes over
+            actual("2025-03-03", "08:00", "16:30"),
+            // An hour under, with the break left out of both
+            WorkScheduleEntry {
+                break_minutes: Some(30),
+                ..actual("2025-03-04", "09:00", "16:00")
+            },
+            // Nothing recorded yet
+            workday("2025-03-05"),
+            // Only a start isn't a full record
+            WorkScheduleEntry {
+                actual_start: Some("08:00".to_string()),
+                ..workday("2025-03-06")
+            },
+            // Called in on a day off
+            WorkScheduleEntry {
+                is_day_off: true,
+                actual_start: Some("10:00".to_string()),
+                actual_end: Some("12:00".to_string()),
+                ..WorkScheduleEntry::new("2025-03-07".to_string())
+            },
+            WorkScheduleEntry {
+                is_day_off: true,
+                ..WorkScheduleEntry::new("2025-03-08".to_string())
+            },
+            // Outside the range
+            actual("2025-03-10", "08:00", "20:00"),
+        ];
+
+        let totals = variance(&entries, date("2025-03-03"), date("2025-03-09"));
+        assert_eq!(
+            totals,
+            Variance {
+                planned_minutes: 8 * 60 + 450,
+                actual_minutes: 510 + 390 + 120,
+                recorded_days: 3,
+                missing_days: 2,
+            }
+        );
+        assert_eq!(totals.difference_minutes(), 30 - 60 + 120);
+
+        // Nothing recorded is no variance at all
+        let none = variance(&entries[2..4], date("2025-03-03"), date("2025-03-09"));
+        assert_eq!(none.difference_minutes(), 0);
+        assert_eq!(none.missing_days, 2);
+    }
+}
@@ -1,19 +1,20 @@
 use crate::error::{work_schedule_error, BotResult};
-use crate::utils::time;
+use crate::utils::time::{self, TimeOfDay, WeekStart};
 use chrono::{Local, NaiveDateTime};
 
 /// Calculate the next notification time (either daily or weekly)
 pub fn calculate_next_notification(
     now: &chrono::DateTime<Local>,
-    daily_time: &str,
-    weekly_time: &str,
+    daily_time: TimeOfDay,
+    weekly_time: TimeOfDay,
+    week_start: WeekStart,
 ) -> BotResult<(String, NaiveDateTime)> {
     // Calculate next daily notification time
     let next_daily = time::next_daily_time(now, daily_time)
         .ok_or_else(|| work_schedule_error("Failed to calculate next daily notification time"))?;
 
     // Calculate next weekly notification time
-    let next_weekly = time::next_weekly_time(now, weekly_time)
+    let next_weekly = time::next_weekly_time(now, weekly_time, week_start)
         .ok_or_else(|| work_schedule_error("Failed to calculate next weekly notification time"))?;
 
     // Determine which notification comes next
@@ -0,0 +1,236 @@
+//! Schedule uploads from images posted in the upload channel.
+//!
+//! The Discord side lives in the bot's message handler; this decides what to do with a posted
+//! message and turns the pipeline's answer into the reply.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::uploads::{
+    upload_error_message, UploadPipeline, UploadResponse, MAX_FILE_SIZE,
+};
+use crate::user_preferences::get_user_preferences;
+use crate::utils::i18n::format_hours;
+use crate::utils::render::{View, ViewLine};
+use rust_i18n::t;
+use std::cmp::Ordering;
+use std::time::Duration;
+use tracing::warn;
+
+/// Reaction acknowledging a posted schedule image
+pub const ACK_REACTION: char = '👀';
+
+/// Select menu asking an unlinked poster whose schedule the image is
+pub const EMPLOYEE_SELECT_ID: &str = "schedule_upload:employee";
+
+/// How long the select menu waits for the poster
+pub const EMPLOYEE_SELECT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Button storing an upload that was held back for its period
+pub const CONFIRM_BUTTON_ID: &str = "schedule_upload:confirm";
+
+/// How long the confirm button waits for the poster, well within the time work_hours holds the
+/// upload
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Options a Discord select menu can hold
+pub const MAX_EMPLOYEE_OPTIONS: usize = 25;
+
+/// Image extensions the parser reads
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+/// An attachment of a posted message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostedAttachment {
+    pub file_name: String,
+    pub content_type: Option<String>,
+    /// Size of the file in bytes
+    pub size: u32,
+}
+
+impl PostedAttachment {
+    /// Whether the attachment looks like an image, by its content type or else its extension
+    pub fn is_image(&self) -> bool {
+        match &self.content_type {
+            Some(content_type) => content_type.starts_with("image/"),
+            None => self
+                .file_name
+                .rsplit_once('.')
+                .is_some_and(|(_, extension)| {
+                    IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                }),
+        }
+    }
+}
+
+/// A message posted in a guild channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostedMessage {
+    pub channel_id: u64,
+    pub author_id: u64,
+    pub from_bot: bool,
+    pub attachments: Vec<PostedAttachment>,
+}
+
+/// What to do with a posted message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadStep {
+    /// Not a schedule upload
+    Ignore,
+    /// Upload the attachment at this index for the poster's linked employee
+    Upload { attachment: usize, employee: String },
+    /// The poster has no linked employee, so ask whose schedule the attachment is
+    AskEmployee { attachment: usize },
+    /// The image is larger than the upload limit, so it isn't downloaded
+    TooLarge,
+}
+
+/// Decide what to do with a message. Only the first image of a message in the upload channel is
+/// uploaded, for the employee the poster has linked with `/preferences`, and only when it's
+/// within the size the web app accepts.
+pub async fn upload_step(
+    redis_handle: &RedisActorHandle,
+    upload_channel_id: Option<u64>,
+    message: &PostedMessage,
+) -> UploadStep {
+    if message.from_bot || upload_channel_id != Some(message.channel_id) {
+        return UploadStep::Ignore;
+    }
+    let Some(attachment) = message
+        .attachments
+        .iter()
+        .position(PostedAttachment::is_image)
+    else {
+        return UploadStep::Ignore;
+    };
+    if message.attachments[attachment].size as usize > MAX_FILE_SIZE {
+        return UploadStep::TooLarge;
+    }
+
+    match get_user_preferences(redis_handle, message.author_id)
+        .await
+        .employee
+    {
+        Some(employee) => UploadStep::Upload {
+            attachment,
+            employee,
+        },
+        None => UploadStep::AskEmployee { attachment },
+    }
+}
+
+/// Reply to an upload, with the id of the upload to confirm when it was held for its period
+#[derive(Debug, Clone)]
+pub struct UploadReply {
+    pub view: View,
+    pub pending_id: Option<String>,
+}
+
+/// Upload an image for an employee and describe how it went
+pub async fn upload_image(
+    pipeline: &dyn UploadPipeline,
+    employee: &str,
+    data: Vec<u8>,
+) -> UploadReply {
+    match pipeline.upload(employee, data).await {
+        Ok(response) => UploadReply {
+            view: upload_reply(employee, &response),
+            pending_id: match response {
+                UploadResponse::SuspiciousPeriod { pending_id, .. } => Some(pending_id),
+                _ => None,
+            },
+        },
+        Err(e) => {
+            warn!("Schedule upload from Discord failed: {}", e);
+            UploadReply {
+                view: View::error(&t!("schedule_upload_title"), &t!("schedule_upload_failed")),
+                pending_id: None,
+            }
+        }
+    }
+}
+
+/// Store an upload held back for its period and describe how it went
+pub async fn confirm_upload(
+    pipeline: &dyn UploadPipeline,
+    employee: &str,
+    pending_id: &str,
+) -> View {
+    match pipeline.confirm(pending_id).await {
+        Ok(response) => upload_reply(employee, &response),
+        Err(e) => {
+            warn!("Confirming schedule upload {} failed: {}", pending_id, e);
+            View::error(&t!("schedule_upload_title"), &t!("schedule_upload_failed"))
+        }
+    }
+}
+
+/// Hours over or under the contract, e.g. "🔴 +2.5 h over"
+fn contract_delta(minutes: i64) -> String {
+    let locale = rust_i18n::locale();
+    let hours = format_hours(minutes.unsigned_abs() as f64 / 60.0, &locale);
+    match minutes.cmp(&0) {
+        Ordering::Greater => t!("contract_hours_over", hours = hours),
+        Ordering::Less => t!("contract_hours_under", hours = hours),
+        Ordering::Equal => t!("schedule_upload_matches_contract"),
+    }
+    .to_string()
+}
+
+/// Reply describing the pipeline's answer to an upload
+pub fn upload_reply(employee: &str, response: &UploadResponse) -> View {
+    let title = t!("schedule_upload_title");
+    match response {
+        UploadResponse::Stored(summary) => {
+            let mut view = View::success(
+                &title,
+                &t!(
+                    "schedule_upload_stored",
+                    employee = summary.employee,
+                    start_date = summary.start_date,
+                    end_date = summary.end_date,
+                    days = summary.days,
+                    working_days = summary.working_days
+                ),
+            );
+            if !summary.notes.is_empty() {
+                let lines = summary
+                    .notes
+                    .iter()
+                    .map(|(date, note)| ViewLine::new(format!("{date}: {note}")))
+                    .collect();
+                view = view.bulleted_field(t!("schedule_upload_notes"), lines);
+            }
+            if let Some(minutes) = summary.contract_delta_minutes {
+                view = view.field(
+                    t!("contract_hours_title"),
+                    vec![ViewLine::new(contract_delta(minutes))],
+                );
+            }
+            view
+        }
+        UploadResponse::SuspiciousPeriod { issue, summary, .. } => View::warning(
+            &title,
+            &t!(
+                "schedule_upload_suspicious",
+                employee = summary.employee,
+                start_date = summary.start_date,
+                end_date = summary.end_date,
+                reason = issue.message()
+            ),
+        ),
+        UploadResponse::AlreadyStored => View::info(
+            &title,
+            &t!("schedule_upload_already_stored", employee = employee),
+        ),
+        UploadResponse::Rejected { code, detail } => rejected_reply(code, detail.as_deref()),
+    }
+}
+
+/// Reply to an upload refused with an upload error code
+pub fn rejected_reply(code: &str, detail: Option<&str>) -> View {
+    let message = upload_error_message(code).unwrap_or_else(|| t!("schedule_upload_failed").into());
+    let message = match detail {
+        Some(detail) => format!("{message}\n`{detail}`"),
+        None => message,
+    };
+    View::warning(&t!("schedule_upload_title"), &message)
+}
@@ -0,0 +1,539 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::keys::WORK_HOURS_UPLOADS;
+use crate::components::work_schedule::models::WorkScheduleEntry;
+use crate::components::work_schedule::stats::{weekly_totals, HoursBudget};
+use crate::config::Config;
+use crate::error::{work_schedule_error, BotResult};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+/// Number of uploads remembered, newest first
+pub const MAX_STORED_UPLOADS: isize = 100;
+
+/// Discord's attachment size limit for bots without boosts
+pub const MAX_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+
+/// A schedule image kept after it was parsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredUpload {
+    /// Display name of the employee the schedule belongs to
+    pub employee: String,
+    /// File name inside the upload directory
+    pub file_name: String,
+    /// First date of the parsed schedule (YYYY-MM-DD)
+    pub start_date: String,
+    /// Last date of the parsed schedule (YYYY-MM-DD)
+    pub end_date: String,
+    /// Unix timestamp of the upload
+    pub uploaded_at: i64,
+    /// Hash of the image contents, used to recognize the same image uploaded again
+    #[serde(default)]
+    pub image_hash: Option<String>,
+}
+
+impl StoredUpload {
+    /// Check whether the parsed schedule overlaps the date range
+    pub fn covers(&self, start_date: &str, end_date: &str) -> bool {
+        self.start_date.as_str() <= end_date && start_date <= self.end_date.as_str()
+    }
+
+    /// Check whether a file name is one the web app could have written, so it's safe to join
+    /// onto the upload directory
+    pub fn is_valid_file_name(file_name: &str) -> bool {
+        !file_name.is_empty()
+            && !file_name.starts_with('.')
+            && file_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+}
+
+/// Pick the most recent upload covering the date range, optionally for a single employee
+pub fn latest_upload<'a>(
+    uploads: &'a [StoredUpload],
+    start_date: &str,
+    end_date: &str,
+    employee: Option<&str>,
+) -> Option<&'a StoredUpload> {
+    uploads
+        .iter()
+        .filter(|upload| upload.covers(start_date, end_date))
+        .filter(|upload| employee.is_none_or(|name| upload.employee.eq_ignore_ascii_case(name)))
+        .max_by_key(|upload| upload.uploaded_at)
+}
+
+/// Where the bot reads stored schedule images from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageSource {
+    /// The upload directory of a work_hours app on the same machine
+    #[default]
+    File,
+    /// The work_hours API, when the bot and the web app run separately
+    Http,
+}
+
+impl FromStr for ImageSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "file" => Ok(ImageSource::File),
+            "http" => Ok(ImageSource::Http),
+            _ => Err(format!("Unknown schedule image source: {s}")),
+        }
+    }
+}
+
+/// Largest schedule image accepted, from the upload form and the upload channel alike
+pub const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Upload error codes that may be shown to the uploader
+pub const UPLOAD_ERROR_CODES: [&str; 9] = [
+    "empty_file",
+    "too_large",
+    "too_many_pixels",
+    "bad_format",
+    "name_invalid",
+    "parse_failed",
+    "parser_unavailable",
+    "parse_suspect",
+    "upload_expired",
+];
+
+/// Localized message for an upload error code
+pub fn upload_error_message(code: &str) -> Option<String> {
+    UPLOAD_ERROR_CODES
+        .contains(&code)
+        .then(|| t!(format!("upload_error_{code}")).to_string())
+}
+
+/// What was parsed from a schedule image uploaded through the work_hours API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSummary {
+    /// Display name of the employee the schedule was stored for
+    pub employee: String,
+    /// First date of the parsed schedule (YYYY-MM-DD)
+    pub start_date: String,
+    /// Last date of the parsed schedule (YYYY-MM-DD)
+    pub end_date: String,
+    /// Days in the schedule
+    pub days: usize,
+    /// Days with hours
+    pub working_days: usize,
+    /// Dates whose cell wasn't recognized as hours, with the text kept as a note
+    pub notes: Vec<(String, String)>,
+    /// Minutes scheduled over (or, when negative, under) the employee's contract hours for the
+    /// parsed period, zero within the tolerance, when they have contract hours
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_delta_minutes: Option<i64>,
+}
+
+impl UploadSummary {
+    /// Summarize the days parsed for an employee
+    pub fn new(employee: &str, entries: &[WorkScheduleEntry]) -> Self {
+        let dates = entries.iter().map(|entry| entry.date.as_str());
+        Self {
+            employee: employee.to_string(),
+            start_date: dates.clone().min().unwrap_or_default().to_string(),
+            end_date: dates.max().unwrap_or_default().to_string(),
+            days: entries.len(),
+            working_days: entries.iter().filter(|entry| entry.is_working()).count(),
+            notes: entries
+                .iter()
+                .filter_map(|entry| Some((entry.date.clone(), entry.notes.clone()?)))
+                .collect(),
+            contract_delta_minutes: None,
+        }
+    }
+
+    /// Compare the parsed days to the employee's contract hours, prorated like the weekly
+    /// totals for weeks the period only partly covers. Differences within the tolerance count
+    /// as matching the contract.
+    pub fn with_contract(mut self, budget: &HoursBudget, entries: &[WorkScheduleEntry]) -> Self {
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        let (Some(contract_hours), Some(start), Some(end)) = (
+            budget.contract_for(&self.employee),
+            date(&self.start_date),
+            date(&self.end_date),
+        ) else {
+            return self;
+        };
+        let delta: f64 = weekly_totals(entries, start, end, budget.week_start)
+            .iter()
+            .map(|week| f64::from(week.minutes) - week.expected_minutes(contract_hours))
+            .sum();
+        let within = delta.abs() <= budget.tolerance_hours * 60.0;
+        self.contract_delta_minutes = Some(if within { 0 } else { delta.round() as i64 });
+        self
+    }
+}
+
+/// Why a parsed schedule doesn't look like the upcoming period an upload is expected to cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeriodIssue {
+    /// More than half of the parsed days are already past
+    MostlyPast,
+    /// The parsed days end before next week starts
+    EndsBeforeNextWeek,
+}
+
+impl PeriodIssue {
+    /// Localized explanation shown to the uploader
+    pub fn message(&self) -> String {
+        match self {
+            PeriodIssue::MostlyPast => t!("upload_period_mostly_past"),
+            PeriodIssue::EndsBeforeNextWeek => t!("upload_period_ends_before_next_week"),
+        }
+        .to_string()
+    }
+}
+
+/// Answer of the work_hours upload API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UploadResponse {
+    /// The schedule was parsed and stored
+    Stored(UploadSummary),
+    /// The same image was stored for the employee moments ago, so it wasn't parsed again
+    AlreadyStored,
+    /// The schedule was parsed but covers an unexpected period, so it's held until the upload
+    /// is confirmed with `pending_id`
+    SuspiciousPeriod {
+        pending_id: String,
+        issue: PeriodIssue,
+        summary: UploadSummary,
+    },
+    /// The image or the parse didn't pass validation. `code` is one of the upload form's error
+    /// codes, e.g. `bad_format` or `parse_failed`.
+    Rejected {
+        code: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+}
+
+/// Parses and stores a schedule image for an employee
+#[async_trait]
+pub trait UploadPipeline: Send + Sync {
+    async fn upload(&self, employee: &str, data: Vec<u8>) -> BotResult<UploadResponse>;
+
+    /// Store an upload held back for its period after the uploader confirmed it
+    async fn confirm(&self, pending_id: &str) -> BotResult<UploadResponse>;
+}
+
+/// The upload pipeline of a work_hours app, reached over its API. Uploads for the same
+/// employee are serialized and deduplicated there like the web form's.
+#[derive(Debug, Clone)]
+pub struct WorkHoursUploads {
+    url: String,
+    api_token: String,
+}
+
+impl WorkHoursUploads {
+    /// Upload through the work_hours app configured for the bot
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            url: config.work_hours_url.trim_end_matches('/').to_string(),
+            api_token: config.work_hours_api_token.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl UploadPipeline for WorkHoursUploads {
+    async fn upload(&self, employee: &str, data: Vec<u8>) -> BotResult<UploadResponse> {
+        let url = format!("{}/api/v1/uploads", self.url);
+        let response = reqwest::Client::new()
+            .post(&url)
+            .query(&[("employee", employee)])
+            .bearer_auth(&self.api_token)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(data)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| work_schedule_error(&format!("Failed to upload to {url}: {e}")))?;
+        Ok(response.json().await?)
+    }
+
+    async fn confirm(&self, pending_id: &str) -> BotResult<UploadResponse> {
+        let url = format!("{}/api/v1/uploads/{pending_id}/confirm", self.url);
+        let response = reqwest::Client::new()
+            .post(&url)
+            .bearer_auth(&self.api_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| work_schedule_error(&format!("Failed to confirm upload at {url}: {e}")))?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Read a stored upload's image from the configured source
+async fn load_image(config: &Config, upload: &StoredUpload) -> Result<Vec<u8>, String> {
+    if !StoredUpload::is_valid_file_name(&upload.file_name) {
+        return Err(format!("Invalid upload file name: {}", upload.file_name));
+    }
+
+    match config.schedule_image_source {
+        ImageSource::File => {
+            let path = Path::new(&config.schedule_upload_dir).join(&upload.file_name);
+            tokio::fs::read(&path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+        }
+        ImageSource::Http => {
+            let url = format!(
+                "{}/api/v1/uploads/{}",
+                config.work_hours_url.trim_end_matches('/'),
+                upload.file_name
+            );
+            let response = reqwest::Client::new()
+                .get(&url)
+                .bearer_auth(&config.work_hours_api_token)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| format!("Failed to download {url}: {e}"))?;
+            response
+                .bytes()
+                .await
+                .map(|bytes| bytes.to_vec())
+                .map_err(|e| format!("Failed to download {url}: {e}"))
+        }
+    }
+}
+
+/// Find the newest stored image covering the date range and load it for attaching.
+///
+/// Problems are logged and yield None so a missing image never holds up a notification.
+pub async fn find_source_image(
+    redis_handle: &RedisActorHandle,
+    config: &Config,
+    start_date: &str,
+    end_date: &str,
+) -> Option<(String, Vec<u8>)> {
+    let stored = match redis_handle
+        .lrange::<Vec<String>>(&WORK_HOURS_UPLOADS, 0, MAX_STORED_UPLOADS - 1)
+        .await
+    {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Failed to list stored schedule uploads: {}", e);
+            return None;
+        }
+    };
+
+    let uploads: Vec<StoredUpload> = stored
+        .iter()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect();
+    let Some(upload) = latest_upload(&uploads, start_date, end_date, None) else {
+        debug!(
+            "No stored schedule image covers {} to {}",
+            start_date, end_date
+        );
+        return None;
+    };
+
+    let data = match load_image(config, upload).await {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Skipping the schedule image: {}", e);
+            return None;
+        }
+    };
+
+    let file_name = upload.file_name.clone();
+    let fitted =
+        tokio::task::spawn_blocking(move || fit_attachment(&file_name, data, MAX_ATTACHMENT_BYTES))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|fitted| fitted);
+    match fitted {
+        Ok(image) => Some(image),
+        Err(e) => {
+            warn!("Skipping the schedule image {}: {}", upload.file_name, e);
+            None
+        }
+    }
+}
+
+/// Times an oversized image is shrunk by a quarter before giving up on attaching it
+const MAX_SHRINK_STEPS: usize = 6;
+
+/// Fit an image within `limit` bytes for attaching. Larger images are re-encoded as JPEG and
+/// shrunk until they fit, which renames them to `.jpg`.
+fn fit_attachment(
+    file_name: &str,
+    data: Vec<u8>,
+    limit: usize,
+) -> Result<(String, Vec<u8>), String> {
+    if data.len() <= limit {
+        return Ok((file_name.to_string(), data));
+    }
+
+    let decoded = image::load_from_memory(&data).map_err(|e| {
+        format!(
+            "{} bytes over the attachment limit and unreadable: {e}",
+            data.len()
+        )
+    })?;
+    let mut image = image::DynamicImage::ImageRgb8(decoded.to_rgb8());
+    for _ in 0..=MAX_SHRINK_STEPS {
+        let mut jpeg = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .map_err(|e| format!("Failed to re-encode: {e}"))?;
+        if jpeg.len() <= limit {
+            debug!(
+                "Shrank schedule image {} from {} to {} bytes",
+                file_name,
+                data.len(),
+                jpeg.len()
+            );
+            let name = Path::new(file_name).with_extension("jpg");
+            return Ok((name.to_string_lossy().into_owned(), jpeg));
+        }
+        image = image.resize(
+            image.width() * 3 / 4,
+            image.height() * 3 / 4,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+    Err(format!(
+        "{} bytes, still over the attachment limit after shrinking",
+        data.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::ShiftRange;
+    use crate::components::work_schedule::stats::ContractHours;
+
+    fn upload(employee: &str, start: &str, end: &str, uploaded_at: i64) -> StoredUpload {
+        StoredUpload {
+            employee: employee.to_string(),
+            file_name: format!("{}-{uploaded_at}.png", employee.to_lowercase()),
+            start_date: start.to_string(),
+            end_date: end.to_string(),
+            uploaded_at,
+            image_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_latest_upload_covering_range() {
+        let uploads = [
+            upload("Anna", "2025-01-06", "2025-01-19", 100),
+            upload("Pekka", "2025-01-06", "2025-01-12", 300),
+            // Newest, but for the following weeks
+            upload("Anna", "2025-01-20", "2025-02-02", 400),
+            // Starts mid-week, still overlapping
+            upload("Anna", "2025-01-10", "2025-01-23", 200),
+        ];
+
+        let week = ("2025-01-06", "2025-01-12");
+        assert_eq!(
+            latest_upload(&uploads, week.0, week.1, None),
+            Some(&uploads[1])
+        );
+        assert_eq!(
+            latest_upload(&uploads, week.0, week.1, Some("anna")),
+            Some(&uploads[3])
+        );
+        assert_eq!(
+            latest_upload(&uploads, "2025-01-27", "2025-02-02", None),
+            Some(&uploads[2])
+        );
+        assert_eq!(
+            latest_upload(&uploads, "2025-03-03", "2025-03-09", None),
+            None
+        );
+        assert_eq!(latest_upload(&uploads, week.0, week.1, Some("Liisa")), None);
+    }
+
+    #[test]
+    fn test_summary_compares_to_contract() {
+        // Monday to Wednesday, 8 hours a day
+        let entries: Vec<_> = (10..=12)
+            .map(|day| WorkScheduleEntry {
+                shifts: vec![ShiftRange::new("08:00", "16:00")],
+                ..WorkScheduleEntry::new(format!("2025-03-{day}"))
+            })
+            .collect();
+        let contract = |hours_per_week| ContractHours {
+            employee: "Anna".to_string(),
+            hours_per_week,
+        };
+
+        // Three fifths of 37.5 hours is 22.5, so 24 hours is 1.5 over
+        let budget = HoursBudget::new([contract(37.5)], 1.0);
+        let summary = UploadSummary::new("Anna", &entries).with_contract(&budget, &entries);
+        assert_eq!(summary.contract_delta_minutes, Some(90));
+
+        let budget = HoursBudget::new([contract(37.5)], 2.0);
+        let summary = UploadSummary::new("Anna", &entries).with_contract(&budget, &entries);
+        assert_eq!(summary.contract_delta_minutes, Some(0));
+
+        let budget = HoursBudget::new([contract(50.0)], 2.0);
+        let summary = UploadSummary::new("Anna", &entries).with_contract(&budget, &entries);
+        assert_eq!(summary.contract_delta_minutes, Some(-360));
+
+        // Without contract hours there's nothing to compare to
+        let summary = UploadSummary::new("Bert", &entries).with_contract(&budget, &entries);
+        assert_eq!(summary.contract_delta_minutes, None);
+    }
+
+    #[test]
+    fn test_file_names_and_sources() {
+        assert!(StoredUpload::is_valid_file_name("anna-1736150400.png"));
+        assert!(!StoredUpload::is_valid_file_name("../secrets.env"));
+        assert!(!StoredUpload::is_valid_file_name("a/b.png"));
+        assert!(!StoredUpload::is_valid_file_name(""));
+
+        assert_eq!("HTTP".parse::<ImageSource>(), Ok(ImageSource::Http));
+        assert_eq!(" file ".parse::<ImageSource>(), Ok(ImageSource::File));
+        assert!("s3".parse::<ImageSource>().is_err());
+    }
+
+    #[test]
+    fn test_oversized_images_are_shrunk_to_fit() {
+        // Noise doesn't compress, so the PNG is far over the limit
+        let mut seed = 1u32;
+        let noise = image::RgbImage::from_fn(200, 200, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = seed.to_be_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut png = Vec::new();
+        noise
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let limit = 20_000;
+        assert!(png.len() > limit);
+
+        let (name, data) = fit_attachment("anna-1.png", png.clone(), limit).unwrap();
+        assert_eq!(name, "anna-1.jpg");
+        assert!(data.len() <= limit);
+        let shrunk = image::load_from_memory(&data).unwrap();
+        assert!(shrunk.width() < 200 && shrunk.height() < 200);
+
+        // Images within the limit are attached as they are
+        let (name, data) = fit_attachment("anna-1.png", png.clone(), png.len()).unwrap();
+        assert_eq!((name.as_str(), data), ("anna-1.png", png));
+        assert!(fit_attachment("anna-1.png", vec![0; limit + 1], limit).is_err());
+    }
+}
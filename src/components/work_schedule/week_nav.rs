@@ -0,0 +1,179 @@
+//! Buttons stepping a week overview back and forth.
+//!
+//! Everything a press needs is in the button's custom id, so the buttons keep working after a
+//! restart without the bot remembering which message shows what.
+
+use crate::user_preferences::ScheduleStyle;
+use chrono::{Duration, NaiveDate};
+
+/// Custom id prefix of the week navigation buttons
+pub const BUTTON_PREFIX: &str = "weeknav";
+
+/// How many weeks the buttons reach before or after the current one
+pub const MAX_WEEK_OFFSET: i64 = 8;
+
+/// Discord's limit on a component's custom id
+const MAX_CUSTOM_ID_LEN: usize = 100;
+
+/// Whose shifts a week view shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeekTarget {
+    All,
+    Employee(String),
+    Group(String),
+}
+
+impl WeekTarget {
+    /// The target for a command's employee and group options; an employee wins over a group
+    pub fn from_options(employee: Option<String>, group: Option<String>) -> Self {
+        match (employee, group) {
+            (Some(employee), _) => WeekTarget::Employee(employee),
+            (None, Some(group)) => WeekTarget::Group(group),
+            (None, None) => WeekTarget::All,
+        }
+    }
+}
+
+/// A week of a target's schedule, as stored in a navigation button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeekNav {
+    /// First day of the week shown
+    pub week_start: NaiveDate,
+    pub target: WeekTarget,
+    pub style: ScheduleStyle,
+}
+
+impl WeekNav {
+    /// Custom id of a button showing this week, or None if the name doesn't fit one
+    pub fn button_id(&self) -> Option<String> {
+        let target = match &self.target {
+            WeekTarget::All => "a:".to_string(),
+            WeekTarget::Employee(employee) => format!("e:{employee}"),
+            WeekTarget::Group(group) => format!("g:{group}"),
+        };
+        // Normal weeks keep the ids of buttons made before there were styles
+        let style = match self.style {
+            ScheduleStyle::Normal => "",
+            ScheduleStyle::Compact => "c:",
+        };
+        let id = format!(
+            "{BUTTON_PREFIX}:{style}{}:{target}",
+            self.week_start.format("%Y-%m-%d")
+        );
+        (id.len() <= MAX_CUSTOM_ID_LEN).then_some(id)
+    }
+
+    /// Read the week and target of a navigation button
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let rest = custom_id.strip_prefix(BUTTON_PREFIX)?.strip_prefix(':')?;
+        let (style, rest) = match rest.strip_prefix("c:") {
+            Some(rest) => (ScheduleStyle::Compact, rest),
+            None => (ScheduleStyle::Normal, rest),
+        };
+        let (week_start, target) = rest.split_once(':')?;
+        let week_start = NaiveDate::parse_from_str(week_start, "%Y-%m-%d").ok()?;
+        let target = match target.split_once(':')? {
+            ("a", _) => WeekTarget::All,
+            ("e", name) if !name.is_empty() => WeekTarget::Employee(name.to_string()),
+            ("g", name) if !name.is_empty() => WeekTarget::Group(name.to_string()),
+            _ => return None,
+        };
+        Some(Self {
+            week_start,
+            target,
+            style,
+        })
+    }
+
+    /// The same target a number of weeks later, or earlier when negative
+    pub fn shifted(&self, weeks: i64) -> Self {
+        Self {
+            week_start: self.week_start + Duration::weeks(weeks),
+            target: self.target.clone(),
+            style: self.style,
+        }
+    }
+}
+
+/// Keep a requested week start within reach of the current week's start
+pub fn clamp_week(current: NaiveDate, requested: NaiveDate) -> NaiveDate {
+    let reach = Duration::weeks(MAX_WEEK_OFFSET);
+    requested.clamp(current - reach, current + reach)
+}
+
+/// Whether the previous and next weeks of `shown` are within reach of `current`
+pub fn can_step(current: NaiveDate, shown: NaiveDate) -> (bool, bool) {
+    let reach = Duration::weeks(MAX_WEEK_OFFSET);
+    (shown > current - reach, shown < current + reach)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_button_ids_round_trip() {
+        for target in [
+            WeekTarget::All,
+            WeekTarget::Employee("Matti Meikäläinen".to_string()),
+            WeekTarget::Group("aamu:vuoro".to_string()),
+        ] {
+            let nav = WeekNav {
+                week_start: date("2025-03-10"),
+                target,
+                style: ScheduleStyle::Normal,
+            };
+            let id = nav.button_id().unwrap();
+            assert!(id.starts_with("weeknav:2025-03-10:"));
+            assert_eq!(WeekNav::parse(&id), Some(nav.clone()));
+
+            let compact = WeekNav {
+                style: ScheduleStyle::Compact,
+                ..nav
+            };
+            let id = compact.button_id().unwrap();
+            assert!(id.starts_with("weeknav:c:2025-03-10:"));
+            assert_eq!(WeekNav::parse(&id), Some(compact));
+        }
+
+        assert_eq!(WeekNav::parse("weeknav:2025-03-10:e:"), None);
+        assert_eq!(WeekNav::parse("weeknav:2025-13-10:a:"), None);
+        assert_eq!(WeekNav::parse("weeknav:2025-03-10:x:Matti"), None);
+        assert_eq!(WeekNav::parse("correction:approve:1"), None);
+
+        let long = WeekNav {
+            week_start: date("2025-03-10"),
+            target: WeekTarget::Employee("x".repeat(90)),
+            style: ScheduleStyle::Normal,
+        };
+        assert_eq!(long.button_id(), None);
+    }
+
+    #[test]
+    fn test_weeks_clamp_to_reach() {
+        let current = date("2025-03-10");
+        assert_eq!(clamp_week(current, date("2025-03-17")), date("2025-03-17"));
+        assert_eq!(clamp_week(current, date("2025-06-30")), date("2025-05-05"));
+        assert_eq!(clamp_week(current, date("2024-12-02")), date("2025-01-13"));
+
+        assert_eq!(can_step(current, current), (true, true));
+        assert_eq!(can_step(current, date("2025-05-05")), (true, false));
+        assert_eq!(can_step(current, date("2025-01-13")), (false, true));
+    }
+
+    #[test]
+    fn test_shifted_keeps_target() {
+        let nav = WeekNav {
+            week_start: date("2025-03-10"),
+            target: WeekTarget::Group("ilta".to_string()),
+            style: ScheduleStyle::Compact,
+        };
+        assert_eq!(nav.shifted(-1).week_start, date("2025-03-03"));
+        assert_eq!(nav.shifted(1).target, nav.target);
+        assert_eq!(nav.shifted(1).style, nav.style);
+    }
+}
@@ -0,0 +1,406 @@
+use super::groups::EmployeeFilter;
+use super::handle::WorkScheduleHandle;
+use super::notifications::build_weekly_notification;
+use super::scheduler::notification_routes;
+use super::stats::weekly_budget;
+use crate::components::event_bus::{EventBus, ScheduleUpdated};
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::config::Config;
+use crate::error::BotResult;
+use crate::theme::Theme;
+use crate::utils::notifier::{DiscordNotifier, Notification, Notifier};
+use crate::utils::redact::Redacted;
+use crate::utils::scheduler::SharedContext;
+use crate::utils::time::{week_bounds, WeekStart};
+use chrono::{Duration as ChronoDuration, Local, NaiveDate};
+use poise::serenity_prelude::CreateEmbedFooter;
+use rust_i18n::t;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Shortest time between two edits of the same weekly notification
+pub const EDIT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Redis hash holding the ids of the weekly notifications posted for a week, by channel id
+pub fn weekly_messages_key(week_start: &str) -> BotResult<Key> {
+    Key::fixed("work_schedule:weekly_messages").segment(week_start)
+}
+
+/// A posted weekly notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WeeklyMessage {
+    pub week_start: NaiveDate,
+    pub channel_id: u64,
+    pub message_id: u64,
+}
+
+/// First days of the weeks the dates fall in, skipping dates that don't parse
+pub fn touched_weeks(dates: &[String], week_start: WeekStart) -> BTreeSet<NaiveDate> {
+    dates
+        .iter()
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .map(|date| week_bounds(date, week_start).0)
+        .collect()
+}
+
+/// The weekly notifications posted for the weeks starting on `weeks`
+pub async fn notified_messages(
+    redis_handle: &RedisActorHandle,
+    weeks: &BTreeSet<NaiveDate>,
+) -> BotResult<Vec<WeeklyMessage>> {
+    let mut messages = Vec::new();
+    for week_start in weeks {
+        let key = weekly_messages_key(&week_start.format("%Y-%m-%d").to_string())?;
+        let stored: HashMap<String, u64> = redis_handle.hgetall(&key).await?;
+        for (channel_id, message_id) in stored {
+            let Ok(channel_id) = channel_id.parse() else {
+                continue;
+            };
+            messages.push(WeeklyMessage {
+                week_start: *week_start,
+                channel_id,
+                message_id,
+            });
+        }
+    }
+    messages.sort();
+    Ok(messages)
+}
+
+/// Holds back edits so each message is edited at most once per [`EDIT_INTERVAL`].
+///
+/// The first change to a message is edited in right away; changes soon after it are collected
+/// into one edit once the interval has passed.
+#[derive(Debug, Default)]
+pub struct EditDebouncer {
+    /// When each message was last edited
+    edited: HashMap<u64, Instant>,
+    /// Messages waiting for an edit, with when it's due
+    pending: BTreeMap<WeeklyMessage, Instant>,
+}
+
+impl EditDebouncer {
+    /// Create a debouncer that hasn't edited anything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that a message needs an edit as of `now`
+    pub fn touch(&mut self, message: WeeklyMessage, now: Instant) {
+        let due = self
+            .edited
+            .get(&message.message_id)
+            .map_or(now, |at| (*at + EDIT_INTERVAL).max(now));
+        self.pending.entry(message).or_insert(due);
+    }
+
+    /// When the next edit is due, or None if no message is waiting
+    pub fn due_at(&self) -> Option<Instant> {
+        self.pending.values().min().copied()
+    }
+
+    /// Take the messages due for an edit at `now`, counting them as edited
+    pub fn take_due(&mut self, now: Instant) -> Vec<WeeklyMessage> {
+        self.edited
+            .retain(|_, at| now.saturating_duration_since(*at) < EDIT_INTERVAL);
+
+        let due: Vec<WeeklyMessage> = self
+            .pending
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(message, _)| *message)
+            .collect();
+        for message in &due {
+            self.pending.remove(message);
+            self.edited.insert(message.message_id, now);
+        }
+        due
+    }
+}
+
+/// Add when the notification was updated to its footer, after any footer it already has
+pub fn with_updated_footer(mut notification: Notification, time: &str) -> Notification {
+    let updated = t!("work_schedule_weekly_updated", time = time).to_string();
+    // CreateEmbed can't be read back, so the current footer comes from its JSON
+    let footer = serde_json::to_value(&notification.embed)
+        .ok()
+        .and_then(|json| json["footer"]["text"].as_str().map(str::to_string));
+    let text = match footer {
+        Some(footer) => format!("{footer} · {updated}"),
+        None => updated,
+    };
+    notification.embed = notification.embed.footer(CreateEmbedFooter::new(text));
+    notification
+}
+
+/// Edit a weekly notification, forgetting it if it can't be edited anymore, e.g. because it
+/// was deleted. Returns whether it was edited.
+pub async fn update_message(
+    notifier: &dyn Notifier,
+    redis_handle: &RedisActorHandle,
+    message: WeeklyMessage,
+    notification: Notification,
+) -> BotResult<bool> {
+    match notifier
+        .edit(message.channel_id, message.message_id, notification)
+        .await
+    {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            info!(
+                "Weekly notification {} can't be edited anymore, no longer updating it: {}",
+                message.message_id, e
+            );
+            let key = weekly_messages_key(&message.week_start.format("%Y-%m-%d").to_string())?;
+            redis_handle
+                .hdel(&key, &message.channel_id.to_string())
+                .await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Rebuild a weekly notification from the current schedules and edit it in place
+async fn refresh_message(
+    ctx: &SharedContext,
+    config: &RwLock<Config>,
+    handle: &WorkScheduleHandle,
+    redis_handle: &RedisActorHandle,
+    message: WeeklyMessage,
+) -> BotResult<()> {
+    let start_date = message.week_start.format("%Y-%m-%d").to_string();
+    let end_date = (message.week_start + ChronoDuration::days(6))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    // The channel shows the employees routed to it now; without a route, everyone
+    let default_channel = config.read().await.calendar_channel_id;
+    let filter = notification_routes(handle, redis_handle, config, default_channel)
+        .await?
+        .into_iter()
+        .find(|(channel_id, _)| *channel_id == message.channel_id)
+        .map_or(EmployeeFilter::All, |(_, filter)| filter);
+    let budget = weekly_budget(redis_handle, &config.read().await.clone()).await;
+
+    let http = Arc::clone(&ctx.current().await.http);
+    let theme = Theme::for_channel(&http, redis_handle, message.channel_id).await;
+    let notification =
+        build_weekly_notification(handle, &start_date, &end_date, &budget, &filter, &theme).await?;
+    let notification = with_updated_footer(
+        notification,
+        &Local::now().format("%Y-%m-%d %H:%M").to_string(),
+    );
+
+    let notifier = DiscordNotifier::from_http(http);
+    if update_message(&notifier, redis_handle, message, notification).await? {
+        info!(
+            "Updated weekly notification {} of {}",
+            message.message_id, start_date
+        );
+    }
+    Ok(())
+}
+
+/// Start the task editing posted weekly notifications when a schedule change touches their
+/// week.
+///
+/// Changes made through the bot are seen, and so are uploads and imports to the work hours
+/// app when it runs in the bot's process. A separately running app's uploads show up in the
+/// next notification.
+pub fn spawn_weekly_edits(
+    ctx: SharedContext,
+    config: Arc<RwLock<Config>>,
+    handle: WorkScheduleHandle,
+    redis_handle: RedisActorHandle,
+    bus: &EventBus,
+) -> JoinHandle<()> {
+    let mut updates = bus.subscribe::<ScheduleUpdated>();
+
+    tokio::spawn(async move {
+        info!("Weekly notification updater started");
+        let mut debouncer = EditDebouncer::new();
+        loop {
+            let due_at = debouncer.due_at();
+            let wait = async move {
+                match due_at {
+                    Some(at) => tokio::time::sleep_until(at.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                update = updates.recv() => match update {
+                    Ok(ScheduleUpdated(employee, dates)) => {
+                        let weeks = touched_weeks(&dates, config.read().await.week_starts_on);
+                        let messages = match notified_messages(&redis_handle, &weeks).await {
+                            Ok(messages) => messages,
+                            Err(e) => {
+                                warn!("Failed to read weekly notification ids: {}", e);
+                                continue;
+                            }
+                        };
+                        for message in messages {
+                            debug!(
+                                "Schedule of {} changed, updating weekly notification {}",
+                                Redacted(&employee),
+                                message.message_id
+                            );
+                            debouncer.touch(message, Instant::now());
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Weekly notification updater missed {} schedule updates", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = wait => {
+                    for message in debouncer.take_due(Instant::now()) {
+                        if let Err(e) =
+                            refresh_message(&ctx, &config, &handle, &redis_handle, message).await
+                        {
+                            warn!(
+                                "Failed to update weekly notification {}: {}",
+                                message.message_id, e
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::notifier::recording::{Call, RecordingNotifier};
+    use crate::utils::notifier::remember_message;
+    use poise::serenity_prelude::CreateEmbed;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn message(message_id: u64) -> WeeklyMessage {
+        WeeklyMessage {
+            week_start: date("2025-03-10"),
+            channel_id: 5,
+            message_id,
+        }
+    }
+
+    #[test]
+    fn test_touched_weeks() {
+        let dates: Vec<String> = ["2025-03-10", "2025-03-16", "2025-03-17", "not a date"]
+            .iter()
+            .map(|date| date.to_string())
+            .collect();
+
+        // 2025-03-10 is a Monday and 2025-03-16 the Sunday of the same week
+        assert_eq!(
+            touched_weeks(&dates, WeekStart::Monday),
+            BTreeSet::from([date("2025-03-10"), date("2025-03-17")])
+        );
+        assert_eq!(
+            touched_weeks(&dates, WeekStart::Sunday),
+            BTreeSet::from([date("2025-03-09"), date("2025-03-16")])
+        );
+        assert!(touched_weeks(&[], WeekStart::Monday).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_notified_weeks_are_edited() {
+        let redis_handle = RedisActorHandle::fake();
+        let key = weekly_messages_key("2025-03-10").unwrap();
+        remember_message(&redis_handle, &key, 5, 11).await.unwrap();
+        remember_message(&redis_handle, &key, 6, 12).await.unwrap();
+
+        let touched = touched_weeks(
+            &["2025-03-12".to_string(), "2025-03-20".to_string()],
+            WeekStart::Monday,
+        );
+        let messages = notified_messages(&redis_handle, &touched).await.unwrap();
+        let ids: Vec<(u64, u64)> = messages
+            .iter()
+            .map(|message| (message.channel_id, message.message_id))
+            .collect();
+        assert_eq!(ids, [(5, 11), (6, 12)]);
+    }
+
+    #[test]
+    fn test_edits_are_debounced_per_message() {
+        let mut debouncer = EditDebouncer::new();
+        let start = Instant::now();
+        assert_eq!(debouncer.due_at(), None);
+
+        // The first change is edited in right away
+        debouncer.touch(message(1), start);
+        assert_eq!(debouncer.take_due(start), [message(1)]);
+
+        // Later changes wait for the interval and are edited in once
+        let soon = start + Duration::from_secs(60);
+        debouncer.touch(message(1), soon);
+        debouncer.touch(message(1), soon + Duration::from_secs(30));
+        debouncer.touch(message(2), soon);
+        assert_eq!(debouncer.take_due(soon), [message(2)]);
+        assert_eq!(debouncer.due_at(), Some(start + EDIT_INTERVAL));
+        assert!(debouncer
+            .take_due(start + EDIT_INTERVAL - Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(debouncer.take_due(start + EDIT_INTERVAL), [message(1)]);
+        assert_eq!(debouncer.due_at(), None);
+
+        // Once the interval has passed without edits, changes go out right away again
+        let later = start + EDIT_INTERVAL * 3;
+        debouncer.touch(message(1), later);
+        assert_eq!(debouncer.take_due(later), [message(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_message_is_forgotten() {
+        let redis_handle = RedisActorHandle::fake();
+        let key = weekly_messages_key("2025-03-10").unwrap();
+        remember_message(&redis_handle, &key, 5, 7).await.unwrap();
+        let notifier = RecordingNotifier::with_missing(&[7]);
+        let notification = Notification {
+            content: None,
+            embed: CreateEmbed::new(),
+        };
+
+        let edited = update_message(&notifier, &redis_handle, message(7), notification)
+            .await
+            .unwrap();
+        assert!(!edited);
+        assert_eq!(notifier.calls(), [Call::Edit(7)]);
+        let touched = BTreeSet::from([date("2025-03-10")]);
+        assert!(notified_messages(&redis_handle, &touched)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_updated_time_follows_the_footer() {
+        let updated = |embed: CreateEmbed| {
+            let notification = with_updated_footer(
+                Notification {
+                    content: None,
+                    embed,
+                },
+                "2025-03-12 14:05",
+            );
+            serde_json::to_value(&notification.embed).unwrap()["footer"]["text"].clone()
+        };
+
+        assert_eq!(updated(CreateEmbed::new()), "Updated 2025-03-12 14:05");
+        assert_eq!(
+            updated(CreateEmbed::new().footer(CreateEmbedFooter::new("Myymälä"))),
+            "Myymälä · Updated 2025-03-12 14:05"
+        );
+    }
+}
@@ -1,14 +1,32 @@
-use crate::error::{env_error, BotResult};
+use crate::components::redis_service::StorageBackend;
+use crate::components::work_schedule::groups::parse_notification_routes;
+use crate::components::work_schedule::reconcile::ReconcileMode;
+use crate::components::work_schedule::stats::parse_tolerance;
+use crate::components::work_schedule::uploads::ImageSource;
+use crate::error::{config_error, env_error, BotResult};
+use crate::utils::rate_limits::RateLimits;
+use crate::utils::time::{is_within_time_range, parse_time, TimeOfDay, WeekStart};
 use dotenvy::dotenv;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
+use tracing::info;
 
 /// Default activity text for the bot
 pub const DEFAULT_ACTIVITY: &str = "DOTA2";
 
+/// SQLite file used with the sqlite storage backend when SQLITE_PATH isn't set
+pub const DEFAULT_SQLITE_PATH: &str = "data/mussubotti.db";
+
+/// Default presence templates, see `crate::presence` for the placeholders
+pub const DEFAULT_PRESENCE_ROTATION: [&str; 3] = [
+    "{activity}",
+    "Next event: {next_event} in {next_event_in}",
+    "{working_today} people working today",
+];
+
 /// Main configuration structure for the bot
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -32,10 +50,14 @@ pub struct Config {
     pub activity: String,
     /// Redis connection URL
     pub redis_url: String,
-    /// Daily notification time in 24h format (HH:MM)
-    pub daily_notification_time: String,
-    /// Weekly notification time in 24h format (HH:MM)
-    pub weekly_notification_time: String,
+    /// Whether data is kept in Redis or in a local SQLite file
+    pub storage_backend: StorageBackend,
+    /// SQLite file used with the sqlite storage backend
+    pub sqlite_path: String,
+    /// Daily notification time in 24h local time
+    pub daily_notification_time: TimeOfDay,
+    /// Weekly notification time in 24h local time
+    pub weekly_notification_time: TimeOfDay,
     /// Bot locale
     pub bot_locale: String,
     /// Interval in seconds for checking new calendar events (default: 300)
@@ -46,11 +68,142 @@ pub struct Config {
     pub disable_work_schedule_daily_notifications: bool,
     /// When true, disables weekly work schedule notifications
     pub disable_work_schedule_weekly_notifications: bool,
+    /// Experimental features enabled in guilds without their own feature flags
+    pub default_features: Vec<String>,
+    /// Per-user and per-guild command rate limits by command category
+    pub rate_limits: RateLimits,
+    /// When true, the weekly calendar notification lists days without events
+    pub show_empty_days: bool,
+    /// Presence templates rotated through every few minutes
+    pub presence_rotation: Vec<String>,
+    /// When true, the previous daily notification is deleted before a new one is posted
+    pub delete_previous_daily_notification: bool,
+    /// When true, the previous daily notification is edited in place instead of posting a new one
+    pub edit_previous_daily_notification: bool,
+    /// When true, the weekly work schedule notification attaches the newest uploaded schedule image
+    pub attach_source_image_weekly: bool,
+    /// Where stored schedule images are read from
+    pub schedule_image_source: ImageSource,
+    /// Directory the work_hours app stores uploaded schedule images in
+    pub schedule_upload_dir: String,
+    /// Base URL of the work_hours web app
+    pub work_hours_url: String,
+    /// Admin token used to call the work_hours API
+    pub work_hours_api_token: String,
+    /// Discord channel ID receiving alerts that need an admin's attention
+    pub error_channel_id: Option<u64>,
+    /// Time range (HH:MM-HH:MM) when calendar polling pauses if the quiet_hours feature is enabled
+    pub quiet_hours: Option<String>,
+    /// Prefix for text commands, overridable per guild with `/config set prefix`
+    pub command_prefix: String,
+    /// Hours a scheduled week may differ from the employee's contract before it's flagged
+    pub contract_hours_tolerance: f64,
+    /// Channel new members are greeted in; greetings are off when unset
+    pub welcome_channel_id: Option<u64>,
+    /// Keep a pinned "Today" message with today's shifts in the notification channel
+    pub pinned_today_message: bool,
+    /// First day of the week for weekly ranges and the weekly notifications (default: Monday)
+    pub week_starts_on: WeekStart,
+    /// Send one morning digest with calendar events and work schedules instead of two daily notifications
+    pub combined_daily_digest: bool,
+    /// Redact employee names and schedule contents in logs and error messages
+    pub log_redaction: bool,
+    /// Google Calendar API calls allowed a day before admins are warned, or 0 to not track a budget
+    pub calendar_api_daily_budget: u64,
+    /// Prefetch employees, this week's schedules and calendar events after startup
+    pub warm_cache_on_start: bool,
+    /// Channels the work schedule notifications of each employee group go to, by group name.
+    /// Employees outside the routed groups stay in the calendar channel.
+    pub notification_routes: HashMap<String, u64>,
+    /// Channel getting a line for every work schedule change; the change feed is off when unset
+    pub schedule_changes_channel_id: Option<u64>,
+    /// Time the stored work schedule keys are checked against their indexes every night
+    pub reconcile_time: TimeOfDay,
+    /// Whether the nightly check only reports inconsistencies or also repairs them
+    pub reconcile_mode: ReconcileMode,
+    /// Elect a leader through Redis so only one of several replicas runs the schedulers
+    pub leader_election: bool,
+    /// Channel whose posted schedule images are parsed and stored for the poster's employee;
+    /// uploads from Discord are off when unset
+    pub schedule_upload_channel_id: Option<u64>,
+    /// Days before now the calendar is fetched from (default: 0)
+    pub calendar_window_past_days: u32,
+    /// Days after now the calendar is fetched until (default: 28)
+    pub calendar_window_future_days: u32,
+    /// Telegram bot token; scheduled notifications are also sent to Telegram when this and
+    /// the chat id are set and the bot is built with the telegram feature
+    pub telegram_bot_token: Option<String>,
+    /// Telegram chat the scheduled notifications are sent to
+    pub telegram_chat_id: Option<String>,
+    /// Seconds a command may run before it's cancelled and the invoker told to try again
+    /// (default: 25)
+    pub command_timeout_seconds: u64,
+    /// Local address the liveness probe reads the bot's status from, None to not serve it
+    /// (default: 127.0.0.1:8686)
+    pub probe_addr: Option<String>,
+    /// Seconds without a gateway heartbeat before the probe fails (default: 120)
+    pub probe_heartbeat_max_age_seconds: u64,
+    /// Seconds a scheduler may oversleep its wake-up before the probe fails (default: 600)
+    pub probe_scheduler_grace_seconds: u64,
+    /// Channels where private and confidential calendar events are shown with their details;
+    /// everywhere else they're shown as a placeholder with only their time
+    pub show_private_event_details_channel_ids: Vec<u64>,
+    /// Users who get the weekly exceptions digest as a DM (default: none)
+    pub manager_user_ids: Vec<u64>,
+    /// Google Calendar whose events name who's on call, shown in the daily notification and
+    /// `/päivystys` (default: none)
+    pub oncall_calendar_id: Option<String>,
+}
+
+/// Read a time of day from an environment variable, or `default` when it's unset.
+///
+/// Times written differently from their canonical form, like "6:00" for "06:00", are logged
+/// with the value they were read as.
+fn time_from_env(name: &str, default: &str) -> BotResult<TimeOfDay> {
+    let raw = env::var(name).unwrap_or_else(|_| default.to_string());
+    let time =
+        parse_time(&raw).map_err(|e| config_error(&format!("Invalid {name} \"{raw}\": {e}")))?;
+    if time.to_string() != raw {
+        info!("{} \"{}\" read as {}", name, raw, time);
+    }
+    Ok(time)
 }
 
+/// Read a comma separated list of Discord ids from an environment variable, or none when it's
+/// unset
+fn ids_from_env(name: &str) -> BotResult<Vec<u64>> {
+    match env::var(name) {
+        Ok(v) => v
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse::<u64>()
+                    .map_err(|_| config_error(&format!("Invalid id in {name}: {id}")))
+            })
+            .collect(),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+/// Seconds a command may run unless `COMMAND_TIMEOUT_SECONDS` says otherwise
+pub const DEFAULT_COMMAND_TIMEOUT_SECONDS: u64 = 25;
+
+/// Address the probe status is served on unless `PROBE_ADDR` says otherwise
+pub const DEFAULT_PROBE_ADDR: &str = "127.0.0.1:8686";
+
+/// File the enabled components are read from unless another one is given with `--config`
+pub const COMPONENTS_FILE: &str = "config/components.toml";
+
 impl Config {
     /// Load configuration from environment and config file
     pub fn load() -> BotResult<Self> {
+        Self::load_with_components(None)
+    }
+
+    /// Load configuration, reading the enabled components from `components_file` instead of
+    /// [`COMPONENTS_FILE`]. Unlike the default file, a given file has to exist and parse.
+    pub fn load_with_components(components_file: Option<&Path>) -> BotResult<Self> {
         // Load .env file if it exists
         dotenv().ok();
 
@@ -64,10 +217,8 @@ impl Config {
             env::var("GOOGLE_CALENDAR_ID").map_err(|_| env_error("GOOGLE_CALENDAR_ID"))?;
 
         // Optional notification times with defaults
-        let daily_notification_time =
-            env::var("DAILY_NOTIFICATION_TIME").unwrap_or_else(|_| "06:00".to_string());
-        let weekly_notification_time =
-            env::var("WEEKLY_NOTIFICATION_TIME").unwrap_or_else(|_| "06:00".to_string());
+        let daily_notification_time = time_from_env("DAILY_NOTIFICATION_TIME", "06:00")?;
+        let weekly_notification_time = time_from_env("WEEKLY_NOTIFICATION_TIME", "06:00")?;
 
         // New events check interval (default: 5 minutes/300 seconds)
         let new_events_check_interval = env::var("NEW_EVENTS_CHECK_INTERVAL")
@@ -96,6 +247,14 @@ impl Config {
         let redis_url =
             env::var("REDIS_URL").unwrap_or_else(|_| String::from("redis://127.0.0.1:6379"));
 
+        // Storage backend (default: redis) and the file of the sqlite one
+        let storage_backend = match env::var("STORAGE_BACKEND") {
+            Ok(v) => v.parse::<StorageBackend>().map_err(|e| config_error(&e))?,
+            Err(_) => StorageBackend::default(),
+        };
+        let sqlite_path =
+            env::var("SQLITE_PATH").unwrap_or_else(|_| String::from(DEFAULT_SQLITE_PATH));
+
         // Bot locale
         let bot_locale = env::var("BOT_LOCALE").unwrap_or_else(|_| "en-US".to_string());
 
@@ -115,19 +274,245 @@ impl Config {
                 .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
                 .unwrap_or(false);
 
+        // Experimental features enabled by default (comma-separated feature names)
+        let default_features = env::var("DEFAULT_FEATURES")
+            .map(|v| {
+                v.split(',')
+                    .map(|f| f.trim().to_string())
+                    .filter(|f| !f.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Command rate limits (calls/seconds per command category)
+        let rate_limits = RateLimits::from_env();
+
+        // List days without events in the weekly calendar notification (default: false)
+        let show_empty_days = env::var("SHOW_EMPTY_DAYS")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Presence templates, separated by '|' since templates may contain commas
+        let presence_rotation = env::var("PRESENCE_ROTATION")
+            .ok()
+            .map(|v| {
+                v.split('|')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|templates| !templates.is_empty())
+            .unwrap_or_else(|| {
+                DEFAULT_PRESENCE_ROTATION
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect()
+            });
+
+        // Remove yesterday's daily notifications before posting new ones (default: false)
+        let delete_previous_daily_notification = env::var("DELETE_PREVIOUS_DAILY_NOTIFICATION")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Edit the previous daily notification instead of reposting, keeping pins and links
+        // working (default: false)
+        let edit_previous_daily_notification = env::var("EDIT_PREVIOUS_DAILY_NOTIFICATION")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Attach the uploaded schedule image to the weekly work schedule notification
+        // (default: false)
+        let attach_source_image_weekly = env::var("ATTACH_SOURCE_IMAGE_WEEKLY")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Stored schedule images are read from the upload directory or the work_hours API
+        let schedule_image_source = match env::var("SCHEDULE_IMAGE_SOURCE") {
+            Ok(v) => v.parse::<ImageSource>().map_err(|e| config_error(&e))?,
+            Err(_) => ImageSource::default(),
+        };
+
+        let schedule_upload_dir =
+            env::var("SCHEDULE_UPLOAD_DIR").unwrap_or_else(|_| "uploads".to_string());
+
+        let work_hours_url =
+            env::var("WORK_HOURS_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+
+        let work_hours_api_token = env::var("WORK_HOURS_API_TOKEN").unwrap_or_default();
+
+        // Channel for alerts that need an admin's attention, such as expired credentials
+        let error_channel_id = env::var("ERROR_CHANNEL_ID")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok());
+
+        let quiet_hours = env::var("QUIET_HOURS")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        if let Some(range) = &quiet_hours {
+            if is_within_time_range(range, 0, 0).is_none() {
+                return Err(config_error(&format!("Invalid QUIET_HOURS range: {range}")));
+            }
+        }
+
+        let command_prefix = env::var("COMMAND_PREFIX")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "!".to_string());
+
+        let contract_hours_tolerance =
+            parse_tolerance(env::var("CONTRACT_HOURS_TOLERANCE").ok().as_deref());
+
+        let welcome_channel_id = env::var("WELCOME_CHANNEL_ID")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok());
+
+        // Keep a pinned message with today's shifts in the notification channel, refreshed
+        // through the day (default: false)
+        let pinned_today_message = env::var("PINNED_TODAY_MESSAGE")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        let week_starts_on = match env::var("WEEK_STARTS_ON") {
+            Ok(v) => v.parse::<WeekStart>().map_err(|e| config_error(&e))?,
+            Err(_) => WeekStart::default(),
+        };
+
+        // Combine the daily calendar and work schedule notifications into one digest
+        // (default: false)
+        let combined_daily_digest = env::var("COMBINED_DAILY_DIGEST")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Keep employee names out of logs and error messages (default: false)
+        let log_redaction = env::var("LOG_REDACTION")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Daily Google Calendar API call budget (default: 0, no budget)
+        let calendar_api_daily_budget = env::var("CALENDAR_API_DAILY_BUDGET")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        // Prefetch schedules and calendar events after startup so the first commands are fast
+        // (default: true)
+        let warm_cache_on_start = env::var("WARM_CACHE_ON_START")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(true);
+
+        // Work schedule notification channels per employee group (`group=channel_id`, comma
+        // separated), everything goes to the calendar channel when unset
+        let notification_routes = match env::var("NOTIFICATION_ROUTES") {
+            Ok(v) => parse_notification_routes(&v).map_err(|e| config_error(&e))?,
+            Err(_) => HashMap::new(),
+        };
+
+        let schedule_changes_channel_id = env::var("SCHEDULE_CHANGES_CHANNEL_ID")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok());
+
+        // Nightly check of the stored work schedule keys (default: report at 03:30)
+        let reconcile_time = time_from_env("RECONCILE_TIME", "03:30")?;
+        let reconcile_mode = match env::var("RECONCILE_MODE") {
+            Ok(v) => v.parse::<ReconcileMode>().map_err(|e| config_error(&e))?,
+            Err(_) => ReconcileMode::default(),
+        };
+
+        // Run the schedulers only on the replica holding the leader lease in Redis
+        // (default: false, this instance always runs them)
+        let leader_election = env::var("LEADER_ELECTION")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        // Schedule images posted here are uploaded to the work_hours app (default: off)
+        let schedule_upload_channel_id = env::var("SCHEDULE_UPLOAD_CHANNEL_ID")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok());
+
+        // Window of calendar events fetched around now (default: the next 4 weeks)
+        let calendar_window_past_days = env::var("CALENDAR_WINDOW_PAST_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let calendar_window_future_days = env::var("CALENDAR_WINDOW_FUTURE_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(28);
+
+        // Telegram chat mirroring the scheduled notifications (default: off)
+        let telegram_bot_token = env::var("TELEGRAM_BOT_TOKEN")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let telegram_chat_id = env::var("TELEGRAM_CHAT_ID").ok().filter(|v| !v.is_empty());
+
+        // Time a command may take, kept below Discord's interaction token expiry (default: 25s)
+        let command_timeout_seconds = env::var("COMMAND_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECONDS);
+
+        // Liveness probe status endpoint, turned off with PROBE_ADDR=off
+        let probe_addr = match env::var("PROBE_ADDR") {
+            Ok(addr) if addr.trim().is_empty() || addr.trim().eq_ignore_ascii_case("off") => None,
+            Ok(addr) => Some(addr.trim().to_string()),
+            Err(_) => Some(DEFAULT_PROBE_ADDR.to_string()),
+        };
+        let probe_heartbeat_max_age_seconds = env::var("PROBE_HEARTBEAT_MAX_AGE_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(120);
+        let probe_scheduler_grace_seconds = env::var("PROBE_SCHEDULER_GRACE_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(600);
+
+        // Channels allowed to see the details of private calendar events, comma separated
+        // (default: none)
+        let show_private_event_details_channel_ids =
+            ids_from_env("SHOW_PRIVATE_EVENT_DETAILS_CHANNEL_IDS")?;
+
+        // Users who get the weekly exceptions digest, comma separated (default: none)
+        let manager_user_ids = ids_from_env("MANAGER_USER_IDS")?;
+
+        // Calendar of on-call rotations (default: none)
+        let oncall_calendar_id = env::var("ONCALL_CALENDAR_ID")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+
         // Initialize default components
         let mut components = HashMap::new();
         components.insert("google_calendar".to_string(), true);
 
         // Load components configuration from file if it exists
-        if let Ok(content) = fs::read_to_string("config/components.toml") {
-            if let Ok(file_components) = toml::from_str::<HashMap<String, bool>>(&content) {
-                // Merge with defaults
-                for (key, value) in file_components {
-                    components.insert(key, value);
-                }
+        let file_components = match components_file {
+            Some(path) => {
+                let content = fs::read_to_string(path).map_err(|e| {
+                    config_error(&format!("Failed to read {}: {e}", path.display()))
+                })?;
+                let parsed = toml::from_str::<HashMap<String, bool>>(&content).map_err(|e| {
+                    config_error(&format!("Failed to parse {}: {e}", path.display()))
+                })?;
+                Some(parsed)
             }
-        }
+            None => fs::read_to_string(COMPONENTS_FILE)
+                .ok()
+                .and_then(|content| toml::from_str::<HashMap<String, bool>>(&content).ok()),
+        };
+        // Merge with defaults
+        components.extend(file_components.unwrap_or_default());
 
         Ok(Config {
             discord_token,
@@ -140,6 +525,8 @@ impl Config {
             timezone,
             activity,
             redis_url,
+            storage_backend,
+            sqlite_path,
             daily_notification_time,
             weekly_notification_time,
             bot_locale,
@@ -147,13 +534,129 @@ impl Config {
             llama_api_key,
             disable_work_schedule_daily_notifications,
             disable_work_schedule_weekly_notifications,
+            default_features,
+            rate_limits,
+            show_empty_days,
+            presence_rotation,
+            delete_previous_daily_notification,
+            edit_previous_daily_notification,
+            attach_source_image_weekly,
+            schedule_image_source,
+            schedule_upload_dir,
+            work_hours_url,
+            work_hours_api_token,
+            error_channel_id,
+            quiet_hours,
+            command_prefix,
+            contract_hours_tolerance,
+            welcome_channel_id,
+            pinned_today_message,
+            week_starts_on,
+            combined_daily_digest,
+            log_redaction,
+            calendar_api_daily_budget,
+            warm_cache_on_start,
+            notification_routes,
+            schedule_changes_channel_id,
+            reconcile_time,
+            reconcile_mode,
+            leader_election,
+            schedule_upload_channel_id,
+            calendar_window_past_days,
+            calendar_window_future_days,
+            telegram_bot_token,
+            telegram_chat_id,
+            command_timeout_seconds,
+            probe_addr,
+            probe_heartbeat_max_age_seconds,
+            probe_scheduler_grace_seconds,
+            show_private_event_details_channel_ids,
+            manager_user_ids,
+            oncall_calendar_id,
         })
     }
 
-    /// Check if a component is enabled
-    #[allow(dead_code)]
+    /// A config for tests, with placeholder credentials, every component enabled and the
+    /// defaults of `load` elsewhere
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn for_tests() -> Self {
+        Self {
+            discord_token: "test_token".to_string(),
+            google_client_id: "test_client_id".to_string(),
+            google_client_secret: "test_client_secret".to_string(),
+            google_calendar_id: "test_calendar_id".to_string(),
+            calendar_channel_id: 123456789,
+            guild_id: 987654321,
+            components: HashMap::new(),
+            timezone: "UTC".to_string(),
+            activity: "Testing".to_string(),
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            storage_backend: Default::default(),
+            sqlite_path: String::new(),
+            daily_notification_time: "06:00".parse().unwrap(),
+            weekly_notification_time: "06:00".parse().unwrap(),
+            bot_locale: "en-US".to_string(),
+            new_events_check_interval: 300,
+            llama_api_key: "test_llama_api_key".to_string(),
+            disable_work_schedule_daily_notifications: false,
+            disable_work_schedule_weekly_notifications: false,
+            default_features: Vec::new(),
+            rate_limits: RateLimits::default(),
+            show_empty_days: false,
+            presence_rotation: Vec::new(),
+            delete_previous_daily_notification: false,
+            edit_previous_daily_notification: false,
+            attach_source_image_weekly: false,
+            schedule_image_source: ImageSource::File,
+            schedule_upload_dir: "uploads".to_string(),
+            work_hours_url: "http://localhost:3000".to_string(),
+            work_hours_api_token: String::new(),
+            error_channel_id: None,
+            quiet_hours: None,
+            command_prefix: "!".to_string(),
+            contract_hours_tolerance: 2.0,
+            welcome_channel_id: None,
+            pinned_today_message: false,
+            week_starts_on: WeekStart::Monday,
+            combined_daily_digest: false,
+            log_redaction: false,
+            calendar_api_daily_budget: 0,
+            warm_cache_on_start: false,
+            notification_routes: HashMap::new(),
+            schedule_changes_channel_id: None,
+            reconcile_time: "03:30".parse().unwrap(),
+            reconcile_mode: ReconcileMode::Report,
+            leader_election: false,
+            schedule_upload_channel_id: None,
+            calendar_window_past_days: 0,
+            calendar_window_future_days: 28,
+            telegram_bot_token: None,
+            telegram_chat_id: None,
+            command_timeout_seconds: 25,
+            probe_addr: None,
+            probe_heartbeat_max_age_seconds: 120,
+            probe_scheduler_grace_seconds: 600,
+            show_private_event_details_channel_ids: Vec::new(),
+            manager_user_ids: Vec::new(),
+            oncall_calendar_id: None,
+        }
+    }
+
+    /// Check if a component is enabled. Components missing from the config are enabled.
     pub fn is_component_enabled(&self, name: &str) -> bool {
-        *self.components.get(name).unwrap_or(&false)
+        *self.components.get(name).unwrap_or(&true)
+    }
+
+    /// Whether private calendar events may be shown with their details in a channel
+    pub fn shows_private_event_details(&self, channel_id: u64) -> bool {
+        self.show_private_event_details_channel_ids
+            .contains(&channel_id)
+    }
+
+    /// Whether scheduled notifications to a channel may show private event details. They're
+    /// also mirrored to Telegram when it's set up, where details are never shown.
+    pub fn notifications_show_private_event_details(&self, channel_id: u64) -> bool {
+        self.shows_private_event_details(channel_id) && self.telegram_chat_id.is_none()
     }
 
     /// Update component enabled status
@@ -172,7 +675,7 @@ impl Config {
         }
 
         let toml_str = toml::to_string(&self.components)?;
-        fs::write("config/components.toml", toml_str)?;
+        fs::write(COMPONENTS_FILE, toml_str)?;
 
         Ok(())
     }
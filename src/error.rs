@@ -29,6 +29,17 @@ pub enum ErrorImpl {
     #[diagnostic(code(mussubot::google_calendar))]
     GoogleCalendar(String),
 
+    #[error("Google authorization error: {0}")]
+    #[diagnostic(
+        code(mussubot::google_auth),
+        help("Re-authorize the bot with the get_calendar_token tool")
+    )]
+    GoogleAuth(String),
+
+    #[error("Network error: {0}")]
+    #[diagnostic(code(mussubot::network))]
+    Network(String),
+
     #[error("Work Schedule error: {0}")]
     #[diagnostic(code(mussubot::work_schedule))]
     WorkSchedule(String),
@@ -41,10 +52,26 @@ pub enum ErrorImpl {
     #[diagnostic(code(mussubot::io))]
     Io(#[from] std::io::Error),
 
+    #[error("Invalid Redis key: {0}")]
+    #[diagnostic(code(mussubot::invalid_key))]
+    InvalidKey(String),
+
     #[error("Serialization error: {0}")]
     #[diagnostic(code(mussubot::serialization))]
     Serialization(String),
 
+    #[error("Panicked: {0}")]
+    #[diagnostic(code(mussubot::panicked))]
+    Panicked(String),
+
+    #[error("Maintenance mode: {0}")]
+    #[diagnostic(code(mussubot::maintenance))]
+    Maintenance(String),
+
+    #[error("Undelivered to {sinks}: {1}", sinks = .0.join(", "))]
+    #[diagnostic(code(mussubot::undelivered))]
+    Undelivered(Vec<String>, String),
+
     #[error("Other error: {0}")]
     #[diagnostic(code(mussubot::other))]
     Other(String),
@@ -59,6 +86,21 @@ impl Deref for Error {
     }
 }
 
+impl Error {
+    /// Whether the error means the stored credentials no longer work
+    pub fn is_auth(&self) -> bool {
+        matches!(*self.0, ErrorImpl::GoogleAuth(_))
+    }
+
+    /// Names of the notification sinks that didn't get a notification, when only some failed
+    pub fn undelivered_sinks(&self) -> Option<&[String]> {
+        match &*self.0 {
+            ErrorImpl::Undelivered(sinks, _) => Some(sinks),
+            _ => None,
+        }
+    }
+}
+
 // Implement Display for Error
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -184,11 +226,41 @@ pub fn google_calendar_error(message: &str) -> Error {
     Error(Box::new(ErrorImpl::GoogleCalendar(message.to_string())))
 }
 
+/// Helper to create Google authorization errors
+pub fn google_auth_error(message: &str) -> Error {
+    Error(Box::new(ErrorImpl::GoogleAuth(message.to_string())))
+}
+
+/// Helper to create network errors
+pub fn network_error(message: &str) -> Error {
+    Error(Box::new(ErrorImpl::Network(message.to_string())))
+}
+
 /// Helper to create Work Schedule errors
 pub fn work_schedule_error(message: &str) -> Error {
     Error(Box::new(ErrorImpl::WorkSchedule(message.to_string())))
 }
 
+/// Helper to create invalid Redis key errors
+pub fn invalid_key_error(message: &str) -> Error {
+    Error(Box::new(ErrorImpl::InvalidKey(message.to_string())))
+}
+
+/// Helper to create errors from a caught panic's message
+pub fn panicked_error(message: &str) -> Error {
+    Error(Box::new(ErrorImpl::Panicked(message.to_string())))
+}
+
+/// Helper to create errors for work held back by maintenance mode
+pub fn maintenance_error(message: &str) -> Error {
+    Error(Box::new(ErrorImpl::Maintenance(message.to_string())))
+}
+
+/// Helper to create errors for a notification some sinks didn't get
+pub fn undelivered_error(sinks: Vec<String>, message: &str) -> Error {
+    Error(Box::new(ErrorImpl::Undelivered(sinks, message.to_string())))
+}
+
 /// Helper to create other errors
 #[allow(dead_code)]
 pub fn other_error(message: &str) -> Error {
@@ -0,0 +1,194 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::BotResult;
+use std::fmt;
+use std::str::FromStr;
+use tracing::warn;
+
+/// Experimental features that can be toggled per guild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Feature {
+    #[name = "image_rendering"]
+    ImageRendering,
+    #[name = "ai_questions"]
+    AiQuestions,
+    #[name = "shift_swap"]
+    ShiftSwap,
+    #[name = "quiet_hours"]
+    QuietHours,
+}
+
+impl Feature {
+    /// All known features
+    pub const ALL: [Feature; 4] = [
+        Feature::ImageRendering,
+        Feature::AiQuestions,
+        Feature::ShiftSwap,
+        Feature::QuietHours,
+    ];
+
+    /// Stable identifier used in config and commands
+    pub fn key(&self) -> &'static str {
+        match self {
+            Feature::ImageRendering => "image_rendering",
+            Feature::AiQuestions => "ai_questions",
+            Feature::ShiftSwap => "shift_swap",
+            Feature::QuietHours => "quiet_hours",
+        }
+    }
+
+    /// Bit used in the stored bitset. Never reorder these.
+    fn bit(&self) -> u64 {
+        match self {
+            Feature::ImageRendering => 1 << 0,
+            Feature::AiQuestions => 1 << 1,
+            Feature::ShiftSwap => 1 << 2,
+            Feature::QuietHours => 1 << 3,
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Feature::ALL
+            .into_iter()
+            .find(|feature| feature.key().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown feature: {s}"))
+    }
+}
+
+/// Set of enabled features, stored as a bitset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeatureFlags(u64);
+
+impl FeatureFlags {
+    /// Build flags from feature names, skipping unknown ones
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Self {
+        let mut flags = Self::default();
+        for name in names {
+            match name.as_ref().parse::<Feature>() {
+                Ok(feature) => flags.enable(feature),
+                Err(e) => warn!("Ignoring default feature: {}", e),
+            }
+        }
+        flags
+    }
+
+    /// Parse flags from their stored representation
+    pub fn from_stored(value: &str) -> Option<Self> {
+        value.trim().parse::<u64>().ok().map(Self)
+    }
+
+    /// Representation written to Redis
+    pub fn to_stored(self) -> String {
+        self.0.to_string()
+    }
+
+    /// Pick the stored guild flags, or the defaults when the guild has none (or they're unreadable)
+    pub fn resolve(stored: Option<&str>, defaults: FeatureFlags) -> Self {
+        stored.and_then(Self::from_stored).unwrap_or(defaults)
+    }
+
+    /// Check whether a feature is enabled
+    pub fn contains(&self, feature: Feature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+
+    /// Enable a feature
+    pub fn enable(&mut self, feature: Feature) {
+        self.0 |= feature.bit();
+    }
+
+    /// Disable a feature
+    pub fn disable(&mut self, feature: Feature) {
+        self.0 &= !feature.bit();
+    }
+}
+
+/// Redis key holding a guild's feature flags
+pub fn guild_features_key(guild_id: u64) -> Key {
+    Key::fixed("features").id(guild_id)
+}
+
+/// Load a guild's feature flags, falling back to the defaults
+pub async fn get_guild_features(
+    redis_handle: &RedisActorHandle,
+    guild_id: u64,
+    defaults: FeatureFlags,
+) -> FeatureFlags {
+    match redis_handle
+        .get::<Option<String>>(&guild_features_key(guild_id))
+        .await
+    {
+        Ok(stored) => FeatureFlags::resolve(stored.as_deref(), defaults),
+        Err(e) => {
+            warn!(
+                "Failed to read feature flags for guild {}, using defaults: {}",
+                guild_id, e
+            );
+            defaults
+        }
+    }
+}
+
+/// Persist a guild's feature flags
+pub async fn set_guild_features(
+    redis_handle: &RedisActorHandle,
+    guild_id: u64,
+    flags: FeatureFlags,
+) -> BotResult<()> {
+    redis_handle
+        .set(&guild_features_key(guild_id), flags.to_stored())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_persistence_round_trip() {
+        let mut flags = FeatureFlags::default();
+        flags.enable(Feature::AiQuestions);
+        flags.enable(Feature::ShiftSwap);
+
+        let restored = FeatureFlags::from_stored(&flags.to_stored()).unwrap();
+        assert_eq!(restored, flags);
+        assert!(restored.contains(Feature::AiQuestions));
+        assert!(restored.contains(Feature::ShiftSwap));
+        assert!(!restored.contains(Feature::ImageRendering));
+
+        let mut restored = restored;
+        restored.disable(Feature::ShiftSwap);
+        assert!(restored.contains(Feature::AiQuestions));
+        assert!(!restored.contains(Feature::ShiftSwap));
+    }
+
+    #[test]
+    fn test_default_fallback() {
+        let defaults = FeatureFlags::from_names(&["image_rendering", "bogus"]);
+        assert!(defaults.contains(Feature::ImageRendering));
+        assert!(!defaults.contains(Feature::AiQuestions));
+
+        // No stored value or garbage falls back to the defaults
+        assert_eq!(FeatureFlags::resolve(None, defaults), defaults);
+        assert_eq!(
+            FeatureFlags::resolve(Some("not-a-number"), defaults),
+            defaults
+        );
+
+        // An explicitly stored empty set wins over the defaults
+        let stored = FeatureFlags::default().to_stored();
+        assert_eq!(
+            FeatureFlags::resolve(Some(&stored), defaults),
+            FeatureFlags::default()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::config::Config;
+use crate::error::{other_error, BotResult};
+use crate::theme::ThemeConfig;
+use crate::utils::time::TimeOfDay;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Settings an admin has chosen for a guild with `/setup`.
+///
+/// Unset values fall back to the global config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildConfig {
+    /// Channel receiving the notifications
+    #[serde(default)]
+    pub notification_channel_id: Option<u64>,
+    /// Daily notification time in 24h format (HH:MM)
+    #[serde(default)]
+    pub daily_notification_time: Option<String>,
+    /// Weekly notification time in 24h format (HH:MM)
+    #[serde(default)]
+    pub weekly_notification_time: Option<String>,
+    /// Locale used for the guild's messages
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// IANA timezone for commands run in the guild
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Prefix for text commands in the guild
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Colors and branding of the guild's messages
+    #[serde(default)]
+    pub theme: ThemeConfig,
+}
+
+/// Where and when the scheduled notifications are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationSettings {
+    /// Channel the daily and weekly notifications are posted to
+    pub channel_id: u64,
+    /// Time of the daily notifications
+    pub daily_time: TimeOfDay,
+    /// Time of the weekly notifications
+    pub weekly_time: TimeOfDay,
+}
+
+impl GuildConfig {
+    /// The notification settings chosen for the guild, falling back to `config` for unset or
+    /// invalid ones
+    pub fn notification_settings(&self, config: &Config) -> NotificationSettings {
+        let time_or = |time: &Option<String>, fallback: TimeOfDay| {
+            time.as_deref()
+                .and_then(|time| {
+                    time.parse()
+                        .map_err(|_| warn!("Ignoring invalid notification time {}", time))
+                        .ok()
+                })
+                .unwrap_or(fallback)
+        };
+
+        NotificationSettings {
+            channel_id: self
+                .notification_channel_id
+                .unwrap_or(config.calendar_channel_id),
+            daily_time: time_or(
+                &self.daily_notification_time,
+                config.daily_notification_time,
+            ),
+            weekly_time: time_or(
+                &self.weekly_notification_time,
+                config.weekly_notification_time,
+            ),
+        }
+    }
+
+    /// The locale chosen for the guild, or the bot's
+    pub fn locale_or<'a>(&'a self, config: &'a Config) -> &'a str {
+        self.locale.as_deref().unwrap_or(&config.bot_locale)
+    }
+}
+
+/// Notification settings of the bot's guild, read each time so `/setup` changes apply to the
+/// next notification
+pub async fn notification_settings(
+    redis_handle: &RedisActorHandle,
+    config: &RwLock<Config>,
+) -> NotificationSettings {
+    let guild_id = config.read().await.guild_id;
+    let guild_config = get_guild_config(redis_handle, guild_id).await;
+    guild_config.notification_settings(&*config.read().await)
+}
+
+/// Use the locale chosen for the bot's guild, or the configured one
+pub async fn apply_locale(redis_handle: &RedisActorHandle, config: &RwLock<Config>) {
+    let guild_id = config.read().await.guild_id;
+    let guild_config = get_guild_config(redis_handle, guild_id).await;
+    let config = config.read().await;
+    crate::utils::i18n::set_locale(guild_config.locale_or(&config));
+}
+
+/// Redis key holding a guild's config
+pub fn guild_config_key(guild_id: u64) -> Key {
+    Key::fixed("guild_config").id(guild_id)
+}
+
+/// Load a guild's config, or an empty one if it has none (or it's unreadable)
+pub async fn get_guild_config(redis_handle: &RedisActorHandle, guild_id: u64) -> GuildConfig {
+    let stored = match redis_handle
+        .get::<Option<String>>(&guild_config_key(guild_id))
+        .await
+    {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Failed to read config for guild {}: {}", guild_id, e);
+            None
+        }
+    };
+
+    stored
+        .and_then(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| warn!("Ignoring invalid config for guild {}: {}", guild_id, e))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// Persist a guild's config
+pub async fn set_guild_config(
+    redis_handle: &RedisActorHandle,
+    guild_id: u64,
+    config: &GuildConfig,
+) -> BotResult<()> {
+    let json = serde_json::to_string(config)
+        .map_err(|e| other_error(&format!("Failed to serialize guild config: {e}")))?;
+    redis_handle.set(&guild_config_key(guild_id), json).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_guild_settings_override_config() {
+        let redis_handle = RedisActorHandle::fake();
+        let config = RwLock::new(Config::for_tests());
+        let (guild_id, defaults) = {
+            let config = config.read().await;
+            (config.guild_id, config.clone())
+        };
+
+        let settings = notification_settings(&redis_handle, &config).await;
+        assert_eq!(settings.channel_id, defaults.calendar_channel_id);
+        assert_eq!(settings.daily_time, defaults.daily_notification_time);
+
+        let chosen = GuildConfig {
+            notification_channel_id: Some(42),
+            daily_notification_time: Some("08:15".to_string()),
+            weekly_notification_time: Some("not a time".to_string()),
+            ..Default::default()
+        };
+        set_guild_config(&redis_handle, guild_id, &chosen)
+            .await
+            .unwrap();
+
+        let settings = notification_settings(&redis_handle, &config).await;
+        assert_eq!(settings.channel_id, 42);
+        assert_eq!(settings.daily_time, "08:15".parse().unwrap());
+        // An invalid time falls back to the config
+        assert_eq!(settings.weekly_time, defaults.weekly_notification_time);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::commands::work::get_work_schedule_handle;
+use crate::commands::{
+    create_error_embed, create_success_embed, create_warning_embed, CommandContext,
+};
+use crate::components::work_schedule::corrections::{
+    claim_correction, finish_correction, parse_button_id, release_correction, Correction,
+    CorrectionAction, CorrectionClaim,
+};
+use crate::error::BotResult;
+use crate::maintenance::get_maintenance;
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use poise::Modal;
+use rust_i18n::t;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long the reject modal waits for the admin to submit
+const REJECT_MODAL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Modal asking an admin why a correction is rejected
+struct RejectModal {
+    reason: String,
+}
+
+impl poise::Modal for RejectModal {
+    fn create(_defaults: Option<Self>, custom_id: String) -> serenity::CreateInteractionResponse {
+        serenity::CreateInteractionResponse::Modal(
+            serenity::CreateModal::new(custom_id, t!("correction_reject_modal_title")).components(
+                vec![serenity::CreateActionRow::InputText(
+                    serenity::CreateInputText::new(
+                        serenity::InputTextStyle::Paragraph,
+                        t!("correction_reject_modal_label"),
+                        "reason",
+                    )
+                    .max_length(500),
+                )],
+            ),
+        )
+    }
+
+    fn parse(mut data: serenity::ModalInteractionData) -> Result<Self, &'static str> {
+        Ok(Self {
+            reason: poise::find_modal_text(&mut data, "reason").unwrap_or_default(),
+        })
+    }
+}
+
+/// Answer a press of a correction's approve or reject button in the admin channel
+pub async fn handle_button(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+) -> BotResult<()> {
+    let Some((action, id)) = parse_button_id(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    let is_admin = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.administrator());
+    if !is_admin {
+        let response = serenity::CreateInteractionResponseMessage::new()
+            .embed(create_warning_embed(
+                &t!("correction_title"),
+                &t!("correction_admins_only"),
+            ))
+            .ephemeral(true);
+        interaction
+            .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    }
+
+    match action {
+        CorrectionAction::Approve => approve(ctx, data, interaction, id).await,
+        CorrectionAction::Reject => reject(ctx, data, interaction, id).await,
+    }
+}
+
+/// Apply a correction through the schedule's write path and tell the requester
+async fn approve(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+    id: &str,
+) -> BotResult<()> {
+    let redis_handle = data.redis();
+    if let Some(maintenance) = get_maintenance(&redis_handle).await {
+        let response = serenity::CreateInteractionResponseMessage::new()
+            .embed(create_warning_embed(
+                &t!("maintenance_title"),
+                &maintenance.message(),
+            ))
+            .ephemeral(true);
+        interaction
+            .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+            .await?;
+        return Ok(());
+    }
+    let correction = match claim_correction(&redis_handle, id).await? {
+        CorrectionClaim::Claimed(correction) => *correction,
+        claim => {
+            let response = undecidable_response(interaction, &claim);
+            interaction.create_response(ctx, response).await?;
+            return Ok(());
+        }
+    };
+
+    let handle =
+        get_work_schedule_handle(data.component_manager.as_ref(), data.config.clone()).await;
+    let admin = interaction.user.id;
+    let entry = match handle
+        .correct_entry(
+            &correction.employee,
+            correction.entry.clone(),
+            Some(admin.get()),
+        )
+        .await
+    {
+        Ok(entry) => entry,
+        Err(e) => {
+            // Leave the buttons so the correction can be approved once the problem is fixed
+            release_correction(&redis_handle, id).await?;
+            let response = serenity::CreateInteractionResponseMessage::new()
+                .embed(create_error_embed(
+                    &t!("correction_title"),
+                    &t!("correction_failed", error = e.to_string()),
+                ))
+                .ephemeral(true);
+            interaction
+                .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+                .await?;
+            return Ok(());
+        }
+    };
+    finish_correction(&redis_handle, id).await?;
+    info!("Correction {} approved by {}", id, admin);
+
+    let message = t!(
+        "correction_approved_dm",
+        employee = correction.employee,
+        date = correction.date,
+        after = entry.format()
+    );
+    let embed = create_success_embed(&t!("correction_title"), &message);
+    notify_requester(ctx, &correction, embed).await;
+
+    let status = t!("correction_approved_status", admin = admin.mention());
+    let response = decided_response(interaction, &status);
+    interaction.create_response(ctx, response).await?;
+    Ok(())
+}
+
+/// Ask the admin for a reason, then drop the correction and tell the requester why
+async fn reject(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+    id: &str,
+) -> BotResult<()> {
+    // The modal is the response to the button press
+    let custom_id = format!("{}:{}", interaction.data.custom_id, interaction.id);
+    interaction
+        .create_response(ctx, RejectModal::create(None, custom_id.clone()))
+        .await?;
+    let Some(submit) = serenity::ModalInteractionCollector::new(ctx)
+        .filter(move |submit| submit.data.custom_id == custom_id)
+        .timeout(REJECT_MODAL_TIMEOUT)
+        .await
+    else {
+        return Ok(());
+    };
+    let modal = RejectModal::parse(submit.data.clone()).map_err(serenity::Error::Other)?;
+    let reason = modal.reason.trim().to_string();
+
+    let redis_handle = data.redis();
+    let correction = match claim_correction(&redis_handle, id).await? {
+        CorrectionClaim::Claimed(correction) => *correction,
+        claim => {
+            let response = undecidable_response(interaction, &claim);
+            submit.create_response(ctx, response).await?;
+            return Ok(());
+        }
+    };
+    finish_correction(&redis_handle, id).await?;
+    let admin = interaction.user.id;
+    info!("Correction {} rejected by {}", id, admin);
+
+    let message = t!(
+        "correction_rejected_dm",
+        employee = correction.employee,
+        date = correction.date,
+        reason = reason
+    );
+    let embed = create_warning_embed(&t!("correction_title"), &message);
+    notify_requester(ctx, &correction, embed).await;
+
+    let status = t!(
+        "correction_rejected_status",
+        admin = admin.mention(),
+        reason = reason
+    );
+    submit
+        .create_response(ctx, decided_response(interaction, &status))
+        .await?;
+    Ok(())
+}
+
+/// DM the requester about the decision. They may have DMs closed, which doesn't undo it.
+async fn notify_requester(
+    ctx: &serenity::Context,
+    correction: &Correction,
+    embed: serenity::CreateEmbed,
+) {
+    let user_id = serenity::UserId::new(correction.requested_by);
+    if let Err(e) = user_id
+        .direct_message(ctx, serenity::CreateMessage::new().embed(embed))
+        .await
+    {
+        warn!("Failed to DM correction {} decision: {}", correction.id, e);
+    }
+}
+
+/// The correction's message with its buttons replaced by who decided it
+fn decided_response(
+    interaction: &serenity::ComponentInteraction,
+    status: &str,
+) -> serenity::CreateInteractionResponse {
+    let embeds = interaction
+        .message
+        .embeds
+        .iter()
+        .map(|embed| {
+            serenity::CreateEmbed::from(embed.clone())
+                .footer(serenity::CreateEmbedFooter::new(status))
+        })
+        .collect();
+    serenity::CreateInteractionResponse::UpdateMessage(
+        serenity::CreateInteractionResponseMessage::new()
+            .embeds(embeds)
+            .components(Vec::new()),
+    )
+}
+
+/// Answer for a correction that can't be decided anymore. An expired one loses its buttons;
+/// one another admin just decided is updated by their response.
+fn undecidable_response(
+    interaction: &serenity::ComponentInteraction,
+    claim: &CorrectionClaim,
+) -> serenity::CreateInteractionResponse {
+    match claim {
+        CorrectionClaim::Expired => decided_response(interaction, &t!("correction_expired")),
+        _ => serenity::CreateInteractionResponse::Message(
+            serenity::CreateInteractionResponseMessage::new()
+                .embed(create_warning_embed(
+                    &t!("correction_title"),
+                    &t!("correction_expired"),
+                ))
+                .ephemeral(true),
+        ),
+    }
+}
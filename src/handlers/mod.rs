@@ -1,2 +1,116 @@
-// This module will contain Discord event handlers
-// Currently empty and can be expanded in the future as needed
+use crate::commands::CommandContext;
+use crate::components::work_schedule::corrections::BUTTON_PREFIX;
+use crate::components::work_schedule::week_nav::BUTTON_PREFIX as WEEK_NAV_PREFIX;
+use crate::config::Config;
+use crate::error::Error;
+use crate::utils::event_log;
+use poise::serenity_prelude as serenity;
+
+pub mod correction;
+pub mod schedule_upload;
+pub mod week_nav;
+pub mod welcome;
+
+/// Handle gateway events that aren't commands
+pub async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    framework: poise::FrameworkContext<'_, CommandContext, Error>,
+    data: &CommandContext,
+) -> Result<(), Error> {
+    if event_log::is_capturing() {
+        record_event(event, &*data.config.read().await);
+    }
+
+    match event {
+        serenity::FullEvent::Ready { .. } => {
+            // Schedulers and the watchdog's restarts keep using the context of the session they
+            // started in unless handed the new one after a reconnect
+            if let Some(component_manager) = &data.component_manager {
+                component_manager.attach_all(ctx).await;
+            }
+            Ok(())
+        }
+        serenity::FullEvent::GuildMemberAddition { new_member } => {
+            welcome::greet_member(ctx, data, new_member).await
+        }
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(interaction),
+        } => {
+            if interaction.data.custom_id.starts_with(BUTTON_PREFIX) {
+                correction::handle_button(ctx, data, interaction).await
+            } else if interaction.data.custom_id.starts_with(WEEK_NAV_PREFIX) {
+                week_nav::handle_button(ctx, data, interaction).await
+            } else {
+                welcome::handle_button(ctx, data, interaction, &framework.options().commands).await
+            }
+        }
+        serenity::FullEvent::Message { new_message } => {
+            schedule_upload::handle_message(ctx, data, new_message).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether a channel is one the bot posts to
+fn is_bot_channel(config: &Config, channel_id: u64) -> bool {
+    channel_id == config.calendar_channel_id
+        || [
+            config.error_channel_id,
+            config.welcome_channel_id,
+            config.schedule_changes_channel_id,
+            config.schedule_upload_channel_id,
+        ]
+        .contains(&Some(channel_id))
+        || config
+            .notification_routes
+            .values()
+            .any(|id| *id == channel_id)
+}
+
+/// Add the high-level events to the `/debug events` log
+fn record_event(event: &serenity::FullEvent, config: &Config) {
+    match event {
+        serenity::FullEvent::Ready { data_about_bot } => event_log::record("ready", || {
+            format!(
+                "{} in {} guilds",
+                data_about_bot.user.name,
+                data_about_bot.guilds.len()
+            )
+        }),
+        serenity::FullEvent::Resume { .. } => event_log::record("resume", String::new),
+        serenity::FullEvent::InteractionCreate { interaction } => {
+            event_log::record("interaction", || match interaction {
+                serenity::Interaction::Command(command) => format!(
+                    "/{} by {} in {}",
+                    command.data.name, command.user.id, command.channel_id
+                ),
+                serenity::Interaction::Component(component) => format!(
+                    "button {} by {} in {}",
+                    component.data.custom_id, component.user.id, component.channel_id
+                ),
+                other => format!("{:?} {}", other.kind(), other.id()),
+            })
+        }
+        serenity::FullEvent::Message { new_message }
+            if is_bot_channel(config, new_message.channel_id.get()) =>
+        {
+            event_log::record("message", || {
+                format!(
+                    "{} by {} in {}",
+                    new_message.id, new_message.author.id, new_message.channel_id
+                )
+            })
+        }
+        serenity::FullEvent::Ratelimit { data } => event_log::record("ratelimit", || {
+            format!(
+                "{:?} {} waiting {} ms{}",
+                data.method,
+                data.path,
+                data.timeout.as_millis(),
+                if data.global { " (global)" } else { "" }
+            )
+        }),
+        _ => {}
+    }
+}
@@ -0,0 +1,241 @@
+use crate::commands::work::get_work_schedule_handle;
+use crate::commands::{create_info_embed, create_warning_embed, CommandContext};
+use crate::components::work_schedule::upload_channel::{
+    confirm_upload, rejected_reply, upload_image, upload_step, PostedAttachment, PostedMessage,
+    UploadReply, UploadStep, ACK_REACTION, CONFIRM_BUTTON_ID, CONFIRM_TIMEOUT, EMPLOYEE_SELECT_ID,
+    EMPLOYEE_SELECT_TIMEOUT, MAX_EMPLOYEE_OPTIONS,
+};
+use crate::components::work_schedule::uploads::WorkHoursUploads;
+use crate::error::BotResult;
+use crate::maintenance::get_maintenance;
+use crate::utils::render::View;
+use poise::serenity_prelude as serenity;
+use rust_i18n::t;
+use tracing::warn;
+
+/// Upload the schedule image of a message posted in the upload channel, replying with the result
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    message: &serenity::Message,
+) -> BotResult<()> {
+    let Some(upload_channel_id) = data.config.read().await.schedule_upload_channel_id else {
+        return Ok(());
+    };
+
+    let posted = PostedMessage {
+        channel_id: message.channel_id.get(),
+        author_id: message.author.id.get(),
+        from_bot: message.author.bot,
+        attachments: message
+            .attachments
+            .iter()
+            .map(|attachment| PostedAttachment {
+                file_name: attachment.filename.clone(),
+                content_type: attachment.content_type.clone(),
+                size: attachment.size,
+            })
+            .collect(),
+    };
+    let step = upload_step(&data.redis(), Some(upload_channel_id), &posted).await;
+    if !matches!(step, UploadStep::Ignore) {
+        if let Some(maintenance) = get_maintenance(&data.redis()).await {
+            let reply = serenity::CreateMessage::new()
+                .embed(create_warning_embed(
+                    &t!("maintenance_title"),
+                    &maintenance.message(),
+                ))
+                .reference_message(message);
+            message.channel_id.send_message(ctx, reply).await?;
+            return Ok(());
+        }
+    }
+    let (attachment, employee) = match step {
+        UploadStep::Ignore => return Ok(()),
+        UploadStep::TooLarge => {
+            let reply = serenity::CreateMessage::new()
+                .embed(rejected_reply("too_large", None).to_embed())
+                .reference_message(message);
+            message.channel_id.send_message(ctx, reply).await?;
+            return Ok(());
+        }
+        UploadStep::Upload {
+            attachment,
+            employee,
+        } => {
+            acknowledge(ctx, message).await;
+            (attachment, employee)
+        }
+        UploadStep::AskEmployee { attachment } => {
+            acknowledge(ctx, message).await;
+            match ask_employee(ctx, data, message).await? {
+                Some(employee) => (attachment, employee),
+                None => return Ok(()),
+            }
+        }
+    };
+
+    let pipeline = WorkHoursUploads::from_config(&*data.config.read().await);
+    let reply = match message.attachments[attachment].download().await {
+        Ok(image) => {
+            let _typing = message.channel_id.start_typing(&ctx.http);
+            upload_image(&pipeline, &employee, image).await
+        }
+        Err(e) => {
+            warn!("Failed to download schedule image {}: {}", message.id, e);
+            UploadReply {
+                view: View::error(&t!("schedule_upload_title"), &t!("schedule_upload_failed")),
+                pending_id: None,
+            }
+        }
+    };
+
+    let mut reply_message = serenity::CreateMessage::new()
+        .embed(reply.view.to_embed())
+        .reference_message(message);
+    if reply.pending_id.is_some() {
+        let button = serenity::CreateButton::new(CONFIRM_BUTTON_ID)
+            .label(t!("schedule_upload_confirm_button"))
+            .style(serenity::ButtonStyle::Primary);
+        reply_message =
+            reply_message.components(vec![serenity::CreateActionRow::Buttons(vec![button])]);
+    }
+    let mut sent = message.channel_id.send_message(ctx, reply_message).await?;
+    let Some(pending_id) = reply.pending_id else {
+        return Ok(());
+    };
+
+    let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .message_id(sent.id)
+        .author_id(message.author.id)
+        .custom_ids(vec![CONFIRM_BUTTON_ID.to_string()])
+        .timeout(CONFIRM_TIMEOUT)
+        .await
+    else {
+        // Nobody confirmed, so drop the button and leave the upload unstored
+        if let Err(e) = sent
+            .edit(ctx, serenity::EditMessage::new().components(Vec::new()))
+            .await
+        {
+            warn!("Failed to remove confirm button from {}: {}", sent.id, e);
+        }
+        return Ok(());
+    };
+
+    let view = confirm_upload(&pipeline, &employee, &pending_id).await;
+    let response = serenity::CreateInteractionResponseMessage::new()
+        .embed(view.to_embed())
+        .components(Vec::new());
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(response),
+        )
+        .await?;
+    Ok(())
+}
+
+/// React to a message to show the bot picked it up
+async fn acknowledge(ctx: &serenity::Context, message: &serenity::Message) {
+    if let Err(e) = message
+        .react(
+            ctx,
+            serenity::ReactionType::Unicode(ACK_REACTION.to_string()),
+        )
+        .await
+    {
+        warn!("Failed to react to schedule upload {}: {}", message.id, e);
+    }
+}
+
+/// Ask the poster whose schedule the image is with a select menu of the known employees.
+/// Returns `None` when there's no one to pick or the poster didn't answer in time.
+async fn ask_employee(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    message: &serenity::Message,
+) -> BotResult<Option<String>> {
+    let title = t!("schedule_upload_title");
+    let handle =
+        get_work_schedule_handle(data.component_manager.as_ref(), data.config.clone()).await;
+    let employees = handle.get_employees().await?;
+    if employees.is_empty() {
+        message
+            .channel_id
+            .send_message(
+                ctx,
+                serenity::CreateMessage::new()
+                    .embed(create_warning_embed(
+                        &title,
+                        &t!("schedule_upload_no_employees"),
+                    ))
+                    .reference_message(message),
+            )
+            .await?;
+        return Ok(None);
+    }
+
+    let options = employees
+        .into_iter()
+        .take(MAX_EMPLOYEE_OPTIONS)
+        .map(|employee| serenity::CreateSelectMenuOption::new(employee.clone(), employee))
+        .collect();
+    let menu = serenity::CreateSelectMenu::new(
+        EMPLOYEE_SELECT_ID,
+        serenity::CreateSelectMenuKind::String { options },
+    )
+    .placeholder(t!("schedule_upload_select_placeholder"));
+    let prompt = message
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(create_info_embed(
+                    &title,
+                    &t!("schedule_upload_ask_employee"),
+                ))
+                .components(vec![serenity::CreateActionRow::SelectMenu(menu)])
+                .reference_message(message),
+        )
+        .await?;
+
+    let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .message_id(prompt.id)
+        .author_id(message.author.id)
+        .custom_ids(vec![EMPLOYEE_SELECT_ID.to_string()])
+        .timeout(EMPLOYEE_SELECT_TIMEOUT)
+        .await
+    else {
+        // Nobody answered, so drop the menu
+        if let Err(e) = prompt.delete(ctx).await {
+            warn!(
+                "Failed to delete schedule upload prompt {}: {}",
+                prompt.id, e
+            );
+        }
+        return Ok(None);
+    };
+
+    let serenity::ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind
+    else {
+        return Ok(None);
+    };
+    let Some(employee) = values.first().cloned() else {
+        return Ok(None);
+    };
+
+    // Replace the menu with the choice so it can't be picked twice
+    let response = serenity::CreateInteractionResponseMessage::new()
+        .embed(create_info_embed(
+            &title,
+            &t!("schedule_upload_uploading", employee = employee),
+        ))
+        .components(Vec::new());
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(response),
+        )
+        .await?;
+    Ok(Some(employee))
+}
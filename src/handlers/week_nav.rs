@@ -0,0 +1,51 @@
+use crate::commands::work::{render_week, week_buttons};
+use crate::commands::CommandContext;
+use crate::components::work_schedule::week_nav::{clamp_week, WeekNav};
+use crate::error::BotResult;
+use crate::utils::time::week_bounds;
+use chrono::Local;
+use poise::serenity_prelude as serenity;
+
+/// Answer a press of a week overview's ◀️ or ▶️ button by showing the week it points at in
+/// place of the current one
+pub async fn handle_button(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+) -> BotResult<()> {
+    let Some(requested) = WeekNav::parse(&interaction.data.custom_id) else {
+        return Ok(());
+    };
+
+    // Buttons outlive the day they were made on, so the reach is measured from today
+    let week_start = data.config.read().await.week_starts_on;
+    let (current, _) = week_bounds(Local::now().date_naive(), week_start);
+    let (requested_start, _) = week_bounds(requested.week_start, week_start);
+    let nav = WeekNav {
+        week_start: clamp_week(current, requested_start),
+        target: requested.target,
+        style: requested.style,
+    };
+
+    // Fetching a week can outlast the interaction's deadline
+    interaction
+        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    let theme = data.theme(interaction.guild_id).await;
+    match render_week(data, &nav).await {
+        Ok(view) => {
+            let edit = serenity::EditInteractionResponse::new()
+                .embed(view.to_themed_embed(&theme))
+                .components(week_buttons(current, &nav, true).unwrap_or_default());
+            interaction.edit_response(ctx, edit).await?;
+        }
+        Err(notice) => {
+            let followup = serenity::CreateInteractionResponseFollowup::new()
+                .embed(notice.to_themed_embed(&theme))
+                .ephemeral(true);
+            interaction.create_followup(ctx, followup).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,263 @@
+use crate::commands::{create_info_embed, create_success_embed, CommandContext};
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::{BotResult, Error};
+use crate::user_preferences::{get_user_preferences, set_user_preferences};
+use poise::serenity_prelude::{self as serenity, Mentionable};
+use poise::Modal;
+use rust_i18n::t;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long a greeted member is remembered, so gateway replays don't greet them twice
+pub const GREETED_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Button opening the name link modal
+pub const LINK_BUTTON_ID: &str = "welcome:link";
+
+/// Button showing the command list
+pub const HELP_BUTTON_ID: &str = "welcome:help";
+
+/// How long the link modal waits for the member to submit
+const LINK_MODAL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Redis key marking a member as greeted in a guild
+pub fn greeted_key(guild_id: u64, user_id: u64) -> Key {
+    Key::fixed("welcome:greeted").id(guild_id).id(user_id)
+}
+
+/// Mark a member as greeted, returning false if they already were within the last day
+pub async fn mark_greeted(
+    redis_handle: &RedisActorHandle,
+    guild_id: u64,
+    user_id: u64,
+) -> BotResult<bool> {
+    redis_handle
+        .set_nx_ex(
+            &greeted_key(guild_id, user_id),
+            chrono::Utc::now().timestamp(),
+            GREETED_TTL_SECS,
+        )
+        .await
+}
+
+/// Forget a greeting that couldn't be sent, so a replay can try again
+async fn unmark_greeted(redis_handle: &RedisActorHandle, guild_id: u64, user_id: u64) {
+    if let Err(e) = redis_handle.del(&greeted_key(guild_id, user_id)).await {
+        warn!("Failed to clear greeting of user {}: {}", user_id, e);
+    }
+}
+
+/// Welcome embed for a new member
+pub fn welcome_embed(user_id: serenity::UserId) -> serenity::CreateEmbed {
+    create_info_embed(
+        &t!("welcome_title"),
+        &t!("welcome_description", member = user_id.mention()),
+    )
+}
+
+/// Welcome message mentioning the member, with the onboarding buttons
+pub fn welcome_message(user_id: serenity::UserId) -> serenity::CreateMessage {
+    serenity::CreateMessage::new()
+        .content(user_id.mention().to_string())
+        .embed(welcome_embed(user_id))
+        .components(vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(LINK_BUTTON_ID)
+                .label(t!("welcome_link_button"))
+                .style(serenity::ButtonStyle::Primary),
+            serenity::CreateButton::new(HELP_BUTTON_ID)
+                .label(t!("welcome_help_button"))
+                .style(serenity::ButtonStyle::Secondary),
+        ])])
+}
+
+/// Greet a member who joined, unless greetings are off or they were greeted already
+pub async fn greet_member(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    member: &serenity::Member,
+) -> BotResult<()> {
+    if member.user.bot {
+        return Ok(());
+    }
+    let Some(channel_id) = data.config.read().await.welcome_channel_id else {
+        return Ok(());
+    };
+
+    let redis_handle = data.redis();
+    let (guild_id, user_id) = (member.guild_id.get(), member.user.id.get());
+    if !mark_greeted(&redis_handle, guild_id, user_id).await? {
+        info!("User {} was already greeted in guild {}", user_id, guild_id);
+        return Ok(());
+    }
+
+    let message = welcome_message(member.user.id);
+    if let Err(e) = serenity::ChannelId::new(channel_id)
+        .send_message(ctx, message)
+        .await
+    {
+        unmark_greeted(&redis_handle, guild_id, user_id).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Modal asking for the employee name to link
+struct LinkModal {
+    employee: String,
+}
+
+impl poise::Modal for LinkModal {
+    fn create(defaults: Option<Self>, custom_id: String) -> serenity::CreateInteractionResponse {
+        let employee = defaults
+            .map(|defaults| defaults.employee)
+            .unwrap_or_default();
+        serenity::CreateInteractionResponse::Modal(
+            serenity::CreateModal::new(custom_id, t!("welcome_link_modal_title")).components(vec![
+                serenity::CreateActionRow::InputText(
+                    serenity::CreateInputText::new(
+                        serenity::InputTextStyle::Short,
+                        t!("welcome_link_modal_label"),
+                        "employee",
+                    )
+                    .required(false)
+                    .max_length(100)
+                    .value(employee),
+                ),
+            ]),
+        )
+    }
+
+    fn parse(mut data: serenity::ModalInteractionData) -> Result<Self, &'static str> {
+        Ok(Self {
+            // Left empty to unlink
+            employee: poise::find_modal_text(&mut data, "employee").unwrap_or_default(),
+        })
+    }
+}
+
+/// Public commands with their descriptions, one per line
+pub fn command_list<U, E>(commands: &[poise::Command<U, E>]) -> String {
+    commands
+        .iter()
+        .filter(|command| command.required_permissions.is_empty())
+        .map(|command| match &command.description {
+            Some(description) => format!("`/{}` - {description}", command.name),
+            None => format!("`/{}`", command.name),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Answer a press of one of the welcome message's buttons
+pub async fn handle_button(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+    commands: &[poise::Command<CommandContext, Error>],
+) -> BotResult<()> {
+    match interaction.data.custom_id.as_str() {
+        LINK_BUTTON_ID => link_name(ctx, data, interaction).await,
+        HELP_BUTTON_ID => {
+            let response = serenity::CreateInteractionResponseMessage::new()
+                .embed(create_info_embed(
+                    &t!("welcome_help_title"),
+                    &command_list(commands),
+                ))
+                .ephemeral(true);
+            interaction
+                .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+                .await?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Ask the member for their employee name and store it in their preferences
+async fn link_name(
+    ctx: &serenity::Context,
+    data: &CommandContext,
+    interaction: &serenity::ComponentInteraction,
+) -> BotResult<()> {
+    let redis_handle = data.redis();
+    let user_id = interaction.user.id.get();
+    let mut preferences = get_user_preferences(&redis_handle, user_id).await;
+    let defaults = LinkModal {
+        employee: preferences.employee.clone().unwrap_or_default(),
+    };
+
+    // The modal is the response to the button press
+    let custom_id = format!("{LINK_BUTTON_ID}:{}", interaction.id);
+    interaction
+        .create_response(ctx, LinkModal::create(Some(defaults), custom_id.clone()))
+        .await?;
+    let Some(submit) = serenity::ModalInteractionCollector::new(ctx)
+        .filter(move |submit| submit.data.custom_id == custom_id)
+        .timeout(LINK_MODAL_TIMEOUT)
+        .await
+    else {
+        return Ok(());
+    };
+    let modal = LinkModal::parse(submit.data.clone()).map_err(serenity::Error::Other)?;
+
+    let employee = modal.employee.trim().to_string();
+    let message = if employee.is_empty() {
+        preferences.employee = None;
+        t!("preferences_employee_cleared")
+    } else {
+        preferences.employee = Some(employee.clone());
+        t!("preferences_employee_set", employee = employee)
+    };
+    set_user_preferences(&redis_handle, user_id, &preferences).await?;
+
+    let response = serenity::CreateInteractionResponseMessage::new()
+        .embed(create_success_embed(&t!("preferences_title"), &message))
+        .ephemeral(true);
+    submit
+        .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_members_are_greeted_once() {
+        let redis_handle = RedisActorHandle::fake();
+        assert!(mark_greeted(&redis_handle, 1, 42).await.unwrap());
+        // A replayed join event for the same member
+        assert!(!mark_greeted(&redis_handle, 1, 42).await.unwrap());
+
+        // Other members and other guilds are greeted separately
+        assert!(mark_greeted(&redis_handle, 1, 43).await.unwrap());
+        assert!(mark_greeted(&redis_handle, 2, 42).await.unwrap());
+
+        // A failed greeting can be retried
+        unmark_greeted(&redis_handle, 1, 42).await;
+        assert!(mark_greeted(&redis_handle, 1, 42).await.unwrap());
+    }
+
+    #[test]
+    fn test_welcome_message_mentions_member() {
+        let message = welcome_message(serenity::UserId::new(42));
+        let value = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(value["content"], "<@42>");
+        let embed = &value["embeds"][0];
+        assert_eq!(embed["title"], t!("welcome_title").as_ref());
+        assert!(embed["description"].as_str().unwrap().contains("<@42>"));
+
+        let buttons = &value["components"][0]["components"];
+        assert_eq!(buttons[0]["custom_id"], LINK_BUTTON_ID);
+        assert_eq!(buttons[1]["custom_id"], HELP_BUTTON_ID);
+    }
+
+    #[test]
+    fn test_command_list_leaves_out_admin_commands() {
+        let list = command_list(&crate::commands::get_all_application_commands());
+        assert!(list.contains("`/seuraava_vuoro`"));
+        assert!(!list.contains("`/setup`"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Leader election between bot replicas.
+//!
+//! Every replica serves commands, but only the one holding the leader lease in Redis runs the
+//! schedulers and background tasks. The leader renews the lease well before it expires; when it
+//! stops renewing, e.g. because it crashed or lost Redis, another replica takes the lease over
+//! once it has expired.
+
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::BotResult;
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// How long the lease lasts without being renewed
+pub const LEASE_TTL: Duration = Duration::from_secs(30);
+/// How often the leader renews the lease and followers try to take it
+pub const RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Redis key holding the id of the leader
+pub const LEADER_KEY: Key = Key::fixed("bot:leader");
+
+lazy_static! {
+    /// Whether this instance currently leads
+    static ref IS_LEADER: AtomicBool = AtomicBool::new(false);
+    /// Times this instance has gained or lost leadership
+    static ref LEADERSHIP_CHANGES: AtomicU64 = AtomicU64::new(0);
+}
+
+/// Id of this instance, from the hostname and process id
+pub fn instance_id() -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("{host}-{}", std::process::id())
+}
+
+/// Whether this instance currently leads, and how many times that has changed
+pub fn leadership_metrics() -> (bool, u64) {
+    (
+        IS_LEADER.load(Ordering::SeqCst),
+        LEADERSHIP_CHANGES.load(Ordering::SeqCst),
+    )
+}
+
+/// Id of the instance holding the lease, if any
+pub async fn current_leader(redis_handle: &RedisActorHandle) -> BotResult<Option<String>> {
+    redis_handle.get(&LEADER_KEY).await
+}
+
+/// One instance's side of the election
+#[derive(Debug, Clone)]
+pub struct LeaderElection {
+    redis_handle: RedisActorHandle,
+    instance_id: String,
+    ttl: Duration,
+}
+
+impl LeaderElection {
+    /// Create an election for the instance, with leases lasting `ttl`
+    pub fn new(redis_handle: RedisActorHandle, instance_id: String, ttl: Duration) -> Self {
+        Self {
+            redis_handle,
+            instance_id,
+            ttl,
+        }
+    }
+
+    /// Renew the lease if this instance holds it, or take it if it's free. Returns whether this
+    /// instance leads afterwards; Redis errors count as not leading.
+    pub async fn campaign(&self) -> bool {
+        match self.try_campaign().await {
+            Ok(leading) => leading,
+            Err(e) => {
+                warn!("Failed to renew the leader lease: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn try_campaign(&self) -> BotResult<bool> {
+        if self.renew().await? {
+            return Ok(true);
+        }
+        self.redis_handle
+            .set_nx_ex(&LEADER_KEY, &self.instance_id, self.ttl.as_secs())
+            .await
+    }
+
+    /// Extend the lease if it still names this instance. The holder is checked and the expiry
+    /// reset in one step, so a lease that expired and went to another instance in between
+    /// stays with that instance.
+    async fn renew(&self) -> BotResult<bool> {
+        self.redis_handle
+            .expire_if_eq(&LEADER_KEY, &self.instance_id, self.ttl.as_secs())
+            .await
+    }
+
+    /// Give up the lease if this instance holds it, so a follower can take over right away.
+    /// Checked and deleted in one step, so a lease another instance took meanwhile is kept.
+    pub async fn resign(&self) -> BotResult<()> {
+        self.redis_handle
+            .del_if_eq(&LEADER_KEY, &self.instance_id)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Leadership of this instance as seen by the tasks that only the leader runs
+#[derive(Debug, Clone)]
+pub struct Leadership {
+    state: watch::Receiver<bool>,
+}
+
+impl Leadership {
+    /// Follow the leadership published on a watch channel
+    pub fn new(state: watch::Receiver<bool>) -> Self {
+        Self { state }
+    }
+
+    /// Whether this instance leads right now
+    pub fn is_leader(&self) -> bool {
+        *self.state.borrow()
+    }
+
+    /// Wait until this instance leads. The returned token is cancelled when it stops leading;
+    /// `None` means the election has ended, e.g. on shutdown.
+    pub async fn next_term(&mut self) -> Option<CancellationToken> {
+        self.state.wait_for(|leading| *leading).await.ok()?;
+
+        let term = CancellationToken::new();
+        let mut state = self.state.clone();
+        let token = term.clone();
+        tokio::spawn(async move {
+            // An ended election ends the term too
+            let _ = state.wait_for(|leading| !*leading).await;
+            token.cancel();
+        });
+        Some(term)
+    }
+}
+
+/// Publish a change of leadership to the watchers and the metrics
+fn publish(state: &watch::Sender<bool>, instance_id: &str, leading: bool) {
+    if *state.borrow() == leading {
+        return;
+    }
+    IS_LEADER.store(leading, Ordering::SeqCst);
+    LEADERSHIP_CHANGES.fetch_add(1, Ordering::SeqCst);
+    if leading {
+        info!(leader = true, instance = instance_id, "Became the leader");
+    } else {
+        warn!(leader = false, instance = instance_id, "Lost leadership");
+    }
+    let _ = state.send(leading);
+}
+
+/// Run the election until shutdown. With `enabled` false there are no other replicas, so this
+/// instance leads from the start without touching Redis.
+pub fn spawn_election(
+    redis_handle: RedisActorHandle,
+    enabled: bool,
+    mut shutdown: watch::Receiver<bool>,
+) -> Leadership {
+    let (state, leadership) = watch::channel(false);
+    let election = LeaderElection::new(redis_handle, instance_id(), LEASE_TTL);
+
+    tokio::spawn(async move {
+        if !enabled {
+            publish(&state, &election.instance_id, true);
+            let _ = shutdown.changed().await;
+            publish(&state, &election.instance_id, false);
+            return;
+        }
+
+        info!("Taking part in leader election as {}", election.instance_id);
+        loop {
+            let leading = election.campaign().await;
+            publish(&state, &election.instance_id, leading);
+
+            tokio::select! {
+                _ = tokio::time::sleep(RENEW_INTERVAL) => {}
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        publish(&state, &election.instance_id, false);
+        if let Err(e) = election.resign().await {
+            warn!("Failed to give up the leader lease: {}", e);
+        }
+    });
+
+    Leadership::new(leadership)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::redis_service::FakeClock;
+
+    fn instances(redis_handle: &RedisActorHandle) -> [LeaderElection; 2] {
+        ["host-a-1", "host-b-2"]
+            .map(|id| LeaderElection::new(redis_handle.clone(), id.to_string(), LEASE_TTL))
+    }
+
+    #[tokio::test]
+    async fn test_only_one_instance_leads() {
+        let redis_handle = RedisActorHandle::fake();
+        let [a, b] = instances(&redis_handle);
+
+        assert!(a.campaign().await);
+        assert!(!b.campaign().await);
+
+        // Renewing keeps the lease with the leader
+        for _ in 0..3 {
+            assert!(a.campaign().await);
+            assert!(!b.campaign().await);
+        }
+        assert_eq!(
+            current_leader(&redis_handle).await.unwrap().as_deref(),
+            Some("host-a-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_over_after_lease_expires() {
+        let clock = FakeClock::new();
+        let redis_handle = RedisActorHandle::fake_with_clock(clock.clone());
+        let [a, b] = instances(&redis_handle);
+        assert!(a.campaign().await);
+
+        // The leader stops renewing; the lease holds until its TTL runs out
+        clock.advance(LEASE_TTL - Duration::from_secs(1));
+        assert!(!b.campaign().await);
+        clock.advance(Duration::from_secs(1));
+        assert!(b.campaign().await);
+
+        // The old leader finds the lease taken and doesn't overwrite it
+        assert!(!a.campaign().await);
+        assert_eq!(
+            current_leader(&redis_handle).await.unwrap().as_deref(),
+            Some("host-b-2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_leader_keeps_off_a_lease_that_changed_hands() {
+        let clock = FakeClock::new();
+        let redis_handle = RedisActorHandle::fake_with_clock(clock.clone());
+        let [a, b] = instances(&redis_handle);
+        assert!(a.campaign().await);
+
+        // The leader last saw itself holding the lease, which then expires and goes to the
+        // follower before the leader gets to renew or resign
+        assert_eq!(
+            current_leader(&redis_handle).await.unwrap().as_deref(),
+            Some("host-a-1")
+        );
+        clock.advance(LEASE_TTL);
+        assert!(b.campaign().await);
+
+        assert!(!a.renew().await.unwrap());
+        a.resign().await.unwrap();
+        assert_eq!(
+            current_leader(&redis_handle).await.unwrap().as_deref(),
+            Some("host-b-2")
+        );
+
+        // The new leader's lease keeps its own expiry
+        clock.advance(LEASE_TTL - Duration::from_secs(1));
+        assert!(!a.campaign().await);
+        clock.advance(Duration::from_secs(1));
+        assert!(a.campaign().await);
+    }
+
+    #[tokio::test]
+    async fn test_resigning_frees_the_lease_only_for_its_holder() {
+        let redis_handle = RedisActorHandle::fake();
+        let [a, b] = instances(&redis_handle);
+        assert!(a.campaign().await);
+
+        b.resign().await.unwrap();
+        assert!(!b.campaign().await);
+
+        a.resign().await.unwrap();
+        assert!(b.campaign().await);
+    }
+
+    #[tokio::test]
+    async fn test_term_is_cancelled_when_leadership_is_lost() {
+        let (state, receiver) = watch::channel(false);
+        let mut leadership = Leadership::new(receiver);
+
+        state.send(true).unwrap();
+        let term = leadership.next_term().await.unwrap();
+        assert!(leadership.is_leader());
+        assert!(!term.is_cancelled());
+
+        state.send(false).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), term.cancelled())
+            .await
+            .unwrap();
+
+        // Once the election ends there are no more terms
+        drop(state);
+        assert!(leadership.next_term().await.is_none());
+    }
+}
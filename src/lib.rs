@@ -1,10 +1,22 @@
 #[macro_use]
 extern crate rust_i18n;
 
+pub mod builder;
 pub mod components;
 pub mod config;
 pub mod error;
+pub mod features;
+pub mod guild_config;
+pub mod leader;
+pub mod maintenance;
+pub mod presence;
+pub mod probe;
+pub mod theme;
+pub mod user_preferences;
 pub mod utils;
+pub mod watchdog;
+#[cfg(feature = "web-interface")]
+pub mod web;
 
 // Initialize i18n
 i18n!("locales", fallback = "en");
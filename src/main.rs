@@ -1,15 +1,23 @@
 #[macro_use]
 extern crate rust_i18n;
 
+mod cli;
+#[cfg(feature = "web-interface")]
+mod combined;
 mod commands;
-mod components;
-mod config;
-mod error;
 mod handlers;
+mod prefix;
 mod shutdown;
 mod startup;
-mod utils;
 
+#[cfg(feature = "web-interface")]
+use mussubotti::web;
+use mussubotti::{
+    components, config, error, features, guild_config, leader, maintenance, presence, probe, theme,
+    user_preferences, utils, watchdog,
+};
+
+use clap::Parser;
 use tracing::info;
 
 // Initialize i18n
@@ -17,14 +25,42 @@ i18n!("locales", fallback = "en");
 
 #[tokio::main]
 async fn main() -> miette::Result<()> {
-    // Initialize logging
-    startup::init_logging()?;
+    let cli = cli::Cli::parse();
 
-    info!("Starting Mussubot");
+    // Initialize logging
+    let log_config = cli.log.config(
+        std::env::var("RUST_LOG").ok().as_deref(),
+        "info,serenity=warn,poise=warn",
+    );
+    startup::init_logging(&log_config)?;
 
     // Load configuration
-    let config = startup::load_config().await?;
+    let config = startup::load_config(cli.config.as_deref()).await?;
+
+    // Report the running bot's health and exit, for a liveness probe
+    if cli.probe {
+        let (addr, thresholds) = {
+            let config = config.read().await;
+            (
+                config.probe_addr.clone(),
+                probe::ProbeThresholds::from_config(&config),
+            )
+        };
+        let Some(addr) = addr else {
+            eprintln!("unhealthy: the probe is turned off with PROBE_ADDR");
+            std::process::exit(1);
+        };
+        std::process::exit(probe::run_probe(&addr, &thresholds).await);
+    }
+
+    // Send a single notification and exit, e.g. from a CronJob
+    if let Some(notification) = cli.send_notification {
+        info!("Sending {:?} and exiting", notification);
+        return Ok(cli::send_one_shot(config, notification).await?);
+    }
+
+    info!("Starting Mussubot");
 
     // Start the bot
-    startup::start_bot(config).await
+    startup::start_bot(config, cli.with_web).await
 }
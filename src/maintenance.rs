@@ -0,0 +1,90 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::{other_error, BotResult};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Redis key holding the maintenance state while it's on
+pub const MAINTENANCE_KEY: Key = Key::fixed("bot:maintenance");
+
+/// Maintenance mode: the bot keeps answering queries but sends no notifications and refuses
+/// changes to schedules
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Maintenance {
+    /// Message shown instead of the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Discord user who turned it on
+    pub enabled_by: u64,
+    /// Unix timestamp of when it was turned on
+    pub enabled_at: i64,
+}
+
+impl Maintenance {
+    /// Message telling users why a change was refused
+    pub fn message(&self) -> String {
+        self.message
+            .clone()
+            .unwrap_or_else(|| t!("maintenance_default_message").to_string())
+    }
+}
+
+/// Read the maintenance state, None when it's off. A state that can't be read counts as off,
+/// so a Redis hiccup never silences the bot.
+pub async fn get_maintenance(redis_handle: &RedisActorHandle) -> Option<Maintenance> {
+    let stored = match redis_handle.get::<Option<String>>(&MAINTENANCE_KEY).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Failed to read the maintenance state: {}", e);
+            None
+        }
+    };
+    stored.and_then(|json| {
+        serde_json::from_str(&json)
+            .map_err(|e| warn!("Ignoring invalid maintenance state: {}", e))
+            .ok()
+    })
+}
+
+/// Turn maintenance mode on, or off with None
+pub async fn set_maintenance(
+    redis_handle: &RedisActorHandle,
+    maintenance: Option<&Maintenance>,
+) -> BotResult<()> {
+    let Some(maintenance) = maintenance else {
+        return redis_handle.del(&MAINTENANCE_KEY).await;
+    };
+    let json = serde_json::to_string(maintenance)
+        .map_err(|e| other_error(&format!("Failed to serialize the maintenance state: {e}")))?;
+    redis_handle.set(&MAINTENANCE_KEY, json).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_maintenance_is_stored_and_cleared() {
+        let redis_handle = RedisActorHandle::fake();
+        assert_eq!(get_maintenance(&redis_handle).await, None);
+
+        let maintenance = Maintenance {
+            message: Some("Moving to the new schedule system".to_string()),
+            enabled_by: 42,
+            enabled_at: 1_700_000_000,
+        };
+        set_maintenance(&redis_handle, Some(&maintenance))
+            .await
+            .unwrap();
+        let stored = get_maintenance(&redis_handle).await.unwrap();
+        assert_eq!(stored, maintenance);
+        assert_eq!(stored.message(), "Moving to the new schedule system");
+
+        set_maintenance(&redis_handle, None).await.unwrap();
+        assert_eq!(get_maintenance(&redis_handle).await, None);
+
+        // Unreadable state doesn't keep the bot silent
+        redis_handle.set(&MAINTENANCE_KEY, "{").await.unwrap();
+        assert_eq!(get_maintenance(&redis_handle).await, None);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::commands::CommandContext;
+use crate::components::redis_service::RedisActorHandle;
+use crate::error::Error;
+use crate::guild_config::get_guild_config;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Longest prefix a guild can set
+pub const MAX_PREFIX_LEN: usize = 5;
+
+/// How long a guild's prefix is trusted before the guild config is read again
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Pick the prefix for a message: the guild's override, then the global config
+pub fn resolve_prefix(guild: Option<&str>, global: &str) -> String {
+    guild
+        .map(str::trim)
+        .filter(|prefix| !prefix.is_empty())
+        .unwrap_or(global)
+        .to_string()
+}
+
+/// Check a prefix an admin wants to set, returning it trimmed
+pub fn validate_prefix(prefix: &str) -> Option<String> {
+    let prefix = prefix.trim();
+    let valid = !prefix.is_empty()
+        && prefix.chars().count() <= MAX_PREFIX_LEN
+        && !prefix.chars().any(char::is_whitespace);
+    valid.then(|| prefix.to_string())
+}
+
+/// Guild prefix overrides read from the guild config.
+///
+/// Entries expire after a while and are dropped right away when `/config set prefix` changes
+/// them, so a message doesn't cost a Redis round trip.
+#[derive(Debug, Default)]
+pub struct PrefixCache {
+    guilds: RwLock<HashMap<u64, (Option<String>, Instant)>>,
+}
+
+impl PrefixCache {
+    /// The guild's prefix override, if it has one
+    pub async fn guild_prefix(
+        &self,
+        redis_handle: &RedisActorHandle,
+        guild_id: u64,
+    ) -> Option<String> {
+        if let Some((prefix, loaded_at)) = self.guilds.read().await.get(&guild_id) {
+            if loaded_at.elapsed() < CACHE_TTL {
+                return prefix.clone();
+            }
+        }
+
+        let prefix = get_guild_config(redis_handle, guild_id).await.prefix;
+        self.guilds
+            .write()
+            .await
+            .insert(guild_id, (prefix.clone(), Instant::now()));
+        prefix
+    }
+
+    /// Forget a guild's cached prefix after it changed
+    pub async fn invalidate(&self, guild_id: u64) {
+        self.guilds.write().await.remove(&guild_id);
+    }
+}
+
+/// Prefix for a message, wired to poise's `dynamic_prefix`
+pub async fn dynamic_prefix(
+    ctx: poise::PartialContext<'_, CommandContext, Error>,
+) -> Result<Option<String>, Error> {
+    let global = ctx.data.config.read().await.command_prefix.clone();
+    let guild = match ctx.guild_id {
+        Some(guild_id) => {
+            ctx.data
+                .prefix_cache
+                .guild_prefix(&ctx.data.redis(), guild_id.get())
+                .await
+        }
+        None => None,
+    };
+
+    Ok(Some(resolve_prefix(guild.as_deref(), &global)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guild_config::{set_guild_config, GuildConfig};
+
+    #[test]
+    fn test_prefix_fallback_chain() {
+        assert_eq!(resolve_prefix(Some("?"), "!"), "?");
+        assert_eq!(resolve_prefix(None, "!"), "!");
+        assert_eq!(resolve_prefix(Some("  "), "$"), "$");
+    }
+
+    #[test]
+    fn test_validate_prefix() {
+        assert_eq!(validate_prefix(" ?? "), Some("??".to_string()));
+        assert_eq!(validate_prefix(""), None);
+        assert_eq!(validate_prefix("m b"), None);
+        assert_eq!(validate_prefix("toolong"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_stored_prefix_until_invalidated() {
+        let redis = RedisActorHandle::fake();
+        let cache = PrefixCache::default();
+        let with_prefix = |prefix: &str| GuildConfig {
+            prefix: Some(prefix.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(cache.guild_prefix(&redis, 1).await, None);
+
+        set_guild_config(&redis, 1, &with_prefix("?"))
+            .await
+            .unwrap();
+        // Still the cached value until the change is announced
+        assert_eq!(cache.guild_prefix(&redis, 1).await, None);
+        cache.invalidate(1).await;
+        assert_eq!(cache.guild_prefix(&redis, 1).await.as_deref(), Some("?"));
+
+        // Other guilds keep their own entries
+        set_guild_config(&redis, 2, &with_prefix("$"))
+            .await
+            .unwrap();
+        assert_eq!(cache.guild_prefix(&redis, 2).await.as_deref(), Some("$"));
+        assert_eq!(cache.guild_prefix(&redis, 1).await.as_deref(), Some("?"));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::components::event_bus::{EventsRefreshed, ScheduleUpdated};
+use crate::components::google_calendar::models::CalendarEvent;
+use crate::components::google_calendar::time::get_event_start;
+use crate::components::redis_service::RedisActorHandle;
+use crate::components::work_schedule::{WorkSchedule, WorkScheduleHandle};
+use crate::components::ComponentManager;
+use crate::config::Config;
+use crate::utils::redact::Redacted;
+use chrono::{Duration, Local};
+use poise::serenity_prelude as serenity;
+use rust_i18n::t;
+use serenity::model::user::OnlineStatus;
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch, Notify, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info};
+
+/// How often the presence moves on to the next template
+const PRESENCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Values available to presence templates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PresenceData {
+    /// Configured activity text, `{activity}`
+    pub activity: String,
+    /// Summary of the next calendar event and time until it starts, `{next_event}` and
+    /// `{next_event_in}`
+    pub next_event: Option<(String, Duration)>,
+    /// Number of people working today, `{working_today}`
+    pub working_today: Option<usize>,
+}
+
+/// Format a duration compactly, e.g. "45m", "2h 15m" or "3d 4h"
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+/// Render a presence template, or None if it refers to data that isn't available
+pub fn render_template(template: &str, data: &PresenceData) -> Option<String> {
+    let mut text = template.replace("{activity}", &data.activity);
+
+    if text.contains("{next_event}") || text.contains("{next_event_in}") {
+        let (summary, starts_in) = data.next_event.as_ref()?;
+        text = text
+            .replace("{next_event}", summary)
+            .replace("{next_event_in}", &format_duration(*starts_in));
+    }
+
+    if text.contains("{working_today}") {
+        text = text.replace("{working_today}", &data.working_today?.to_string());
+    }
+
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Pick the first renderable template starting from `index`, returning it and its position
+pub fn next_presence(
+    templates: &[String],
+    index: usize,
+    data: &PresenceData,
+) -> Option<(usize, String)> {
+    (0..templates.len())
+        .map(|offset| (index + offset) % templates.len())
+        .find_map(|i| render_template(&templates[i], data).map(|text| (i, text)))
+}
+
+/// Handle used to force a presence update
+#[derive(Debug, Clone, Default)]
+pub struct PresenceHandle {
+    refresh: Arc<Notify>,
+}
+
+impl PresenceHandle {
+    /// Create a new handle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the updater to refresh the presence right away
+    pub fn refresh(&self) {
+        self.refresh.notify_one();
+    }
+}
+
+/// Get the work schedule handle if the component is running
+async fn work_schedule_handle(component_manager: &ComponentManager) -> Option<WorkScheduleHandle> {
+    component_manager
+        .get_component_by_name("work_schedule")?
+        .as_any()
+        .downcast_ref::<WorkSchedule>()?
+        .get_handle()
+        .await
+}
+
+/// Gather the template data from the latest calendar events and the work schedule
+async fn collect_data(
+    config: &Arc<RwLock<Config>>,
+    events: Option<&[CalendarEvent]>,
+    component_manager: &ComponentManager,
+) -> PresenceData {
+    let activity = config.read().await.activity.clone();
+    let now = Local::now();
+
+    let next_event = events.and_then(|events| {
+        events
+            .iter()
+            .filter_map(|event| {
+                let start = get_event_start(event).ok()??;
+                (start > now).then(|| {
+                    // The presence is public, so private events never show their details
+                    let summary = if event.is_private() {
+                        t!("calendar_private_event").to_string()
+                    } else {
+                        event
+                            .summary
+                            .clone()
+                            .unwrap_or_else(|| t!("calendar_unnamed_event").to_string())
+                    };
+                    (summary, start - now)
+                })
+            })
+            .min_by_key(|(_, starts_in)| *starts_in)
+    });
+
+    let today = now.date_naive().format("%Y-%m-%d").to_string();
+    let working_today = match work_schedule_handle(component_manager).await {
+        Some(handle) => match handle.get_schedule_for_date(today).await {
+            Ok(schedules) => Some(
+                schedules
+                    .values()
+                    .filter(|entry| entry.is_working())
+                    .count(),
+            ),
+            Err(e) => {
+                debug!("Work schedule unavailable for presence: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    PresenceData {
+        activity,
+        next_event,
+        working_today,
+    }
+}
+
+/// Start the task rotating the bot's presence until shutdown.
+///
+/// Calendar events and schedule changes arrive over the component event bus, starting from
+/// the events cached in Redis.
+pub fn spawn_presence_updater(
+    ctx: serenity::Context,
+    config: Arc<RwLock<Config>>,
+    component_manager: Arc<ComponentManager>,
+    redis_handle: RedisActorHandle,
+    handle: PresenceHandle,
+    shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    let bus = component_manager.bus();
+    let calendar_rx = bus.subscribe::<EventsRefreshed>();
+    let schedule_rx = bus.subscribe::<ScheduleUpdated>();
+
+    tokio::spawn(async move {
+        info!("Presence updater started");
+        let events = match redis_handle.get_events().await {
+            Ok(events) => Some(events),
+            Err(e) => {
+                debug!("No cached calendar events for presence: {}", e);
+                None
+            }
+        };
+        rotate_presence(
+            config,
+            component_manager,
+            events,
+            handle,
+            (calendar_rx, schedule_rx),
+            shutdown,
+            |text| {
+                ctx.set_presence(
+                    Some(serenity::ActivityData::playing(text)),
+                    OnlineStatus::Online,
+                )
+            },
+        )
+        .await;
+        info!("Presence updater stopped");
+    })
+}
+
+/// Show the rotation through `set_presence` until shutdown, moving to the next template every
+/// `PRESENCE_INTERVAL` however often the data changes in between
+async fn rotate_presence(
+    config: Arc<RwLock<Config>>,
+    component_manager: Arc<ComponentManager>,
+    mut events: Option<Vec<CalendarEvent>>,
+    handle: PresenceHandle,
+    (mut calendar_rx, mut schedule_rx): (
+        broadcast::Receiver<EventsRefreshed>,
+        broadcast::Receiver<ScheduleUpdated>,
+    ),
+    mut shutdown: watch::Receiver<bool>,
+    mut set_presence: impl FnMut(String),
+) {
+    let mut index = 0;
+    let start = tokio::time::Instant::now() + PRESENCE_INTERVAL;
+    let mut rotation = tokio::time::interval_at(start, PRESENCE_INTERVAL);
+    rotation.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let templates = config.read().await.presence_rotation.clone();
+        let data = collect_data(&config, events.as_deref(), &component_manager).await;
+
+        if let Some((shown, text)) = next_presence(&templates, index, &data) {
+            debug!("Setting presence to {}", text);
+            set_presence(text);
+            index = shown;
+        }
+
+        tokio::select! {
+            _ = rotation.tick() => {
+                index += 1;
+            }
+            _ = handle.refresh.notified() => {
+                debug!("Presence refresh requested");
+            }
+            Ok(EventsRefreshed(refreshed)) = calendar_rx.recv() => {
+                events = Some(refreshed);
+            }
+            Ok(ScheduleUpdated(employee, _)) = schedule_rx.recv() => {
+                debug!("Schedule of {} changed, updating presence", Redacted(&employee));
+            }
+            _ = shutdown.changed() => {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> PresenceData {
+        PresenceData {
+            activity: "DOTA2".to_string(),
+            next_event: Some(("Sauna".to_string(), Duration::minutes(135))),
+            working_today: Some(3),
+        }
+    }
+
+    #[test]
+    fn test_render_template_placeholders() {
+        let data = data();
+        assert_eq!(
+            render_template("{activity}", &data),
+            Some("DOTA2".to_string())
+        );
+        assert_eq!(
+            render_template("Next event: {next_event} in {next_event_in}", &data),
+            Some("Next event: Sauna in 2h 15m".to_string())
+        );
+        assert_eq!(
+            render_template("{working_today} people working today", &data),
+            Some("3 people working today".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unavailable_sources_are_skipped() {
+        let data = PresenceData {
+            activity: "DOTA2".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(render_template("{next_event} soon", &data), None);
+        assert_eq!(render_template("{working_today} working", &data), None);
+
+        let templates: Vec<String> = ["{working_today} working", "{next_event}", "{activity}"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            next_presence(&templates, 0, &data),
+            Some((2, "DOTA2".to_string()))
+        );
+        // Wraps around to the start of the rotation
+        assert_eq!(
+            next_presence(&templates, 3, &data),
+            Some((2, "DOTA2".to_string()))
+        );
+    }
+
+    /// Calendar refreshes more often than the interval don't hold the rotation back
+    #[tokio::test(start_paused = true)]
+    async fn test_rotation_advances_between_refreshes() {
+        let config = Arc::new(RwLock::new(Config {
+            presence_rotation: vec!["first".to_string(), "second".to_string()],
+            ..Config::for_tests()
+        }));
+        let component_manager = Arc::new(ComponentManager::new(Arc::clone(&config)));
+        let bus = component_manager.bus();
+        let receivers = (bus.subscribe(), bus.subscribe());
+        let (shutdown, shutdown_recv) = watch::channel(false);
+        let (shown_tx, mut shown) = tokio::sync::mpsc::unbounded_channel();
+
+        let rotation = tokio::spawn(rotate_presence(
+            config,
+            component_manager,
+            Some(Vec::new()),
+            PresenceHandle::new(),
+            receivers,
+            shutdown_recv,
+            move |text| shown_tx.send(text).unwrap(),
+        ));
+        assert_eq!(shown.recv().await.unwrap(), "first");
+
+        // Four refreshes within one interval each show the same template again
+        for _ in 0..4 {
+            tokio::time::sleep(PRESENCE_INTERVAL / 4 - std::time::Duration::from_secs(1)).await;
+            bus.publish(EventsRefreshed(Vec::new()));
+            assert_eq!(shown.recv().await.unwrap(), "first");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        assert_eq!(shown.recv().await.unwrap(), "second");
+
+        shutdown.send(true).unwrap();
+        rotation.await.unwrap();
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(135)), "2h 15m");
+        assert_eq!(format_duration(Duration::hours(76)), "3d 4h");
+        assert_eq!(format_duration(Duration::minutes(-5)), "0m");
+    }
+}
@@ -0,0 +1,468 @@
+//! Liveness probe for container health checks.
+//!
+//! The running bot serves a status snapshot as JSON on a local TCP port, and `--probe` reads it
+//! and exits 0 when the bot is healthy or 1 when it isn't. A container spec can then restart a
+//! bot stuck without its gateway connection with `exec: ["/app/mussubot", "--probe"]`.
+
+use crate::components::redis_service::RedisActorHandle;
+use crate::config::Config;
+use crate::leader::instance_id;
+use crate::watchdog::stale_schedulers;
+use chrono::{DateTime, Local, Utc};
+use lazy_static::lazy_static;
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How often the server looks at the shards' heartbeats
+pub const GATEWAY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the store gets to answer a ping while a status is built
+const REDIS_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the probe waits for the bot to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest status the probe reads
+const MAX_STATUS_BYTES: u64 = 64 * 1024;
+
+lazy_static! {
+    /// Next wake-up of each running scheduler as a unix timestamp, with the registration owning it
+    static ref SCHEDULERS: Mutex<BTreeMap<&'static str, (u64, i64)>> = Mutex::new(BTreeMap::new());
+}
+
+static NEXT_REGISTRATION: AtomicU64 = AtomicU64::new(0);
+
+/// What the running bot reports to the probe
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeStatus {
+    /// When the status was built, as a unix timestamp
+    pub now: i64,
+    /// When the bot started
+    pub started_at: i64,
+    /// Whether every shard is connected to the gateway
+    pub gateway_connected: bool,
+    /// Last heartbeat acknowledged by the gateway, if any yet
+    pub last_heartbeat: Option<i64>,
+    /// Whether the store answered a ping
+    pub redis_reachable: bool,
+    /// When each running scheduler means to wake up next
+    #[serde(default)]
+    pub schedulers: BTreeMap<String, i64>,
+    /// This instance's schedulers whose Redis heartbeat went stale, with how long they've been
+    /// silent in seconds
+    #[serde(default)]
+    pub stale_schedulers: BTreeMap<String, i64>,
+}
+
+/// How stale the bot may get before the probe fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeThresholds {
+    /// Longest time without a gateway heartbeat, counted from the start until the first one
+    pub heartbeat_max_age: Duration,
+    /// How long a scheduler may oversleep its wake-up
+    pub scheduler_grace: Duration,
+}
+
+impl ProbeThresholds {
+    /// Thresholds set in the config
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            heartbeat_max_age: Duration::from_secs(config.probe_heartbeat_max_age_seconds),
+            scheduler_grace: Duration::from_secs(config.probe_scheduler_grace_seconds),
+        }
+    }
+}
+
+/// Why a status counts as unhealthy, empty when it's healthy
+pub fn problems(status: &ProbeStatus, thresholds: &ProbeThresholds) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // Heartbeats only arrive while connected, so a short reconnect doesn't fail the probe
+    let max_age = thresholds.heartbeat_max_age.as_secs() as i64;
+    let heartbeat_age = status.now - status.last_heartbeat.unwrap_or(status.started_at);
+    if heartbeat_age > max_age {
+        let connection = if status.gateway_connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+        problems.push(match status.last_heartbeat {
+            Some(_) => format!("no gateway heartbeat for {heartbeat_age}s ({connection})"),
+            None => {
+                format!("no gateway heartbeat since starting {heartbeat_age}s ago ({connection})")
+            }
+        });
+    }
+
+    if !status.redis_reachable {
+        problems.push("the store doesn't answer".to_string());
+    }
+
+    let grace = thresholds.scheduler_grace.as_secs() as i64;
+    for (scheduler, wake) in &status.schedulers {
+        let overdue = status.now - wake;
+        if overdue > grace {
+            problems.push(format!("scheduler {scheduler} is {overdue}s late"));
+        }
+    }
+
+    for (scheduler, silent) in &status.stale_schedulers {
+        problems.push(format!(
+            "scheduler {scheduler} has had no heartbeat for {silent}s"
+        ));
+    }
+
+    problems
+}
+
+/// Reports a scheduler's wake-ups to the probe while it runs. Dropping it, also when the
+/// scheduler task is aborted, stops the reports.
+pub struct SchedulerLiveness {
+    name: &'static str,
+    registration: u64,
+}
+
+impl SchedulerLiveness {
+    /// Start reporting for a scheduler, taking over from an earlier one with the same name
+    pub fn register(name: &'static str) -> Self {
+        let registration = NEXT_REGISTRATION.fetch_add(1, Ordering::Relaxed);
+        lock_schedulers().insert(name, (registration, Utc::now().timestamp()));
+        Self { name, registration }
+    }
+
+    /// Report that the scheduler sleeps until `wake`
+    pub fn waiting_until(&self, wake: DateTime<Local>) {
+        lock_schedulers().insert(self.name, (self.registration, wake.timestamp()));
+    }
+}
+
+impl Drop for SchedulerLiveness {
+    fn drop(&mut self) {
+        let mut schedulers = lock_schedulers();
+        if schedulers
+            .get(self.name)
+            .is_some_and(|(registration, _)| *registration == self.registration)
+        {
+            schedulers.remove(self.name);
+        }
+    }
+}
+
+fn lock_schedulers() -> std::sync::MutexGuard<'static, BTreeMap<&'static str, (u64, i64)>> {
+    SCHEDULERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Next wake-ups of the running schedulers
+fn scheduler_wakes() -> BTreeMap<String, i64> {
+    lock_schedulers()
+        .iter()
+        .map(|(name, (_, wake))| (name.to_string(), *wake))
+        .collect()
+}
+
+/// Tracks the gateway from the shards' connection stages and heartbeat latencies. Serenity
+/// keeps only the latest latency, so a heartbeat is counted whenever it changes.
+#[derive(Debug, Default)]
+struct GatewayTracker {
+    latencies: HashMap<u32, Duration>,
+    connected: bool,
+    last_heartbeat: Option<i64>,
+}
+
+impl GatewayTracker {
+    /// Take in each shard's id, whether it's connected and its latest latency
+    fn observe(&mut self, shards: &[(u32, bool, Option<Duration>)], now: i64) {
+        self.connected = !shards.is_empty() && shards.iter().all(|(_, connected, _)| *connected);
+        for (shard, connected, latency) in shards {
+            let Some(latency) = latency else { continue };
+            if *connected && self.latencies.insert(*shard, *latency) != Some(*latency) {
+                self.last_heartbeat = Some(now);
+            }
+        }
+    }
+}
+
+/// Each shard's id, whether it's connected to the gateway and its latest heartbeat latency
+pub async fn shard_states(
+    shard_manager: &serenity::ShardManager,
+) -> Vec<(u32, bool, Option<Duration>)> {
+    shard_manager
+        .runners
+        .lock()
+        .await
+        .iter()
+        .map(|(id, runner)| {
+            (
+                id.0,
+                runner.stage == serenity::ConnectionStage::Connected,
+                runner.latency,
+            )
+        })
+        .collect()
+}
+
+/// Serve the status on `addr` until shutdown
+pub fn spawn_probe_server(
+    addr: String,
+    shard_manager: Arc<serenity::ShardManager>,
+    redis_handle: RedisActorHandle,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Failed to start the probe server on {}: {}", addr, e);
+                return;
+            }
+        };
+        info!("Probe server listening on {}", addr);
+
+        let started_at = Utc::now().timestamp();
+        let mut tracker = GatewayTracker::default();
+        let mut poll = tokio::time::interval(GATEWAY_POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {
+                    let shards = shard_states(&shard_manager).await;
+                    tracker.observe(&shards, Utc::now().timestamp());
+                }
+                accepted = listener.accept() => {
+                    let Ok((mut stream, _)) = accepted else { continue };
+                    let redis_reachable = matches!(
+                        tokio::time::timeout(REDIS_PING_TIMEOUT, redis_handle.ping()).await,
+                        Ok(Ok(()))
+                    );
+                    let now = Utc::now().timestamp();
+                    let instance = instance_id();
+                    let stale = stale_schedulers(&redis_handle, &instance, now);
+                    let stale_schedulers = match tokio::time::timeout(REDIS_PING_TIMEOUT, stale).await {
+                        Ok(Ok(stale)) => stale,
+                        _ => BTreeMap::new(),
+                    };
+                    let status = ProbeStatus {
+                        now,
+                        started_at,
+                        gateway_connected: tracker.connected,
+                        last_heartbeat: tracker.last_heartbeat,
+                        redis_reachable,
+                        schedulers: scheduler_wakes(),
+                        stale_schedulers,
+                    };
+                    if let Err(e) = write_status(&mut stream, &status).await {
+                        debug!("Failed to answer a probe: {}", e);
+                    }
+                }
+                _ = shutdown.changed() => {
+                    info!("Probe server stopped");
+                    return;
+                }
+            }
+        }
+    })
+}
+
+async fn write_status(stream: &mut TcpStream, status: &ProbeStatus) -> std::io::Result<()> {
+    let mut json = serde_json::to_vec(status)?;
+    json.push(b'\n');
+    stream.write_all(&json).await?;
+    stream.shutdown().await
+}
+
+/// Read the running bot's status from `addr`
+pub async fn read_status(addr: &str) -> Result<ProbeStatus, String> {
+    let read = async {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("can't reach the bot on {addr}: {e}"))?;
+        let mut json = Vec::new();
+        stream
+            .take(MAX_STATUS_BYTES)
+            .read_to_end(&mut json)
+            .await
+            .map_err(|e| format!("failed to read the status: {e}"))?;
+        serde_json::from_slice(&json).map_err(|e| format!("unreadable status: {e}"))
+    };
+    tokio::time::timeout(PROBE_TIMEOUT, read)
+        .await
+        .map_err(|_| format!("the bot on {addr} didn't answer in time"))?
+}
+
+/// Check the running bot and print why it's unhealthy, returning the process exit code
+pub async fn run_probe(addr: &str, thresholds: &ProbeThresholds) -> i32 {
+    let problems = match read_status(addr).await {
+        Ok(status) => problems(&status, thresholds),
+        Err(e) => vec![e],
+    };
+    for problem in &problems {
+        eprintln!("unhealthy: {problem}");
+    }
+    i32::from(!problems.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLDS: ProbeThresholds = ProbeThresholds {
+        heartbeat_max_age: Duration::from_secs(120),
+        scheduler_grace: Duration::from_secs(600),
+    };
+
+    fn healthy() -> ProbeStatus {
+        ProbeStatus {
+            now: 10_000,
+            started_at: 1_000,
+            gateway_connected: true,
+            last_heartbeat: Some(9_960),
+            redis_reachable: true,
+            schedulers: BTreeMap::from([("work_schedule".to_string(), 30_000)]),
+            stale_schedulers: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_healthy_status_passes() {
+        assert!(problems(&healthy(), &THRESHOLDS).is_empty());
+
+        // A reconnect shorter than the heartbeat age is fine
+        let reconnecting = ProbeStatus {
+            gateway_connected: false,
+            ..healthy()
+        };
+        assert!(problems(&reconnecting, &THRESHOLDS).is_empty());
+
+        // Schedulers may oversleep a little
+        let mut waking = healthy();
+        waking.schedulers.insert("digest".to_string(), 9_500);
+        assert!(problems(&waking, &THRESHOLDS).is_empty());
+    }
+
+    #[test]
+    fn test_stale_heartbeat_fails() {
+        let stuck = ProbeStatus {
+            gateway_connected: false,
+            last_heartbeat: Some(9_000),
+            ..healthy()
+        };
+        assert_eq!(
+            problems(&stuck, &THRESHOLDS),
+            ["no gateway heartbeat for 1000s (disconnected)"]
+        );
+
+        // Starting up counts against the same limit until the first heartbeat
+        let starting = ProbeStatus {
+            started_at: 9_950,
+            last_heartbeat: None,
+            ..healthy()
+        };
+        assert!(problems(&starting, &THRESHOLDS).is_empty());
+        let never_connected = ProbeStatus {
+            gateway_connected: false,
+            last_heartbeat: None,
+            ..healthy()
+        };
+        assert_eq!(
+            problems(&never_connected, &THRESHOLDS),
+            ["no gateway heartbeat since starting 9000s ago (disconnected)"]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_store_and_late_scheduler_fail() {
+        let mut status = ProbeStatus {
+            redis_reachable: false,
+            ..healthy()
+        };
+        status
+            .schedulers
+            .insert("google_calendar".to_string(), 9_000);
+        status.stale_schedulers.insert("digest".to_string(), 7_300);
+        assert_eq!(
+            problems(&status, &THRESHOLDS),
+            [
+                "the store doesn't answer",
+                "scheduler google_calendar is 1000s late",
+                "scheduler digest has had no heartbeat for 7300s"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heartbeats_are_counted_when_latency_changes() {
+        let mut tracker = GatewayTracker::default();
+        tracker.observe(&[(0, false, None)], 100);
+        assert!(!tracker.connected);
+        assert_eq!(tracker.last_heartbeat, None);
+
+        tracker.observe(&[(0, true, Some(Duration::from_millis(40)))], 105);
+        assert!(tracker.connected);
+        assert_eq!(tracker.last_heartbeat, Some(105));
+
+        // The same latency is the same heartbeat
+        tracker.observe(&[(0, true, Some(Duration::from_millis(40)))], 110);
+        assert_eq!(tracker.last_heartbeat, Some(105));
+
+        tracker.observe(
+            &[(0, true, Some(Duration::from_millis(41))), (1, false, None)],
+            115,
+        );
+        assert!(!tracker.connected);
+        assert_eq!(tracker.last_heartbeat, Some(115));
+    }
+
+    #[test]
+    fn test_scheduler_registration_is_removed_on_drop() {
+        let first = SchedulerLiveness::register("probe_test");
+        let wake = Local::now() + chrono::Duration::hours(1);
+        first.waiting_until(wake);
+        assert_eq!(scheduler_wakes().get("probe_test"), Some(&wake.timestamp()));
+
+        // A restarted scheduler takes over, and the old one going away leaves it be
+        let second = SchedulerLiveness::register("probe_test");
+        drop(first);
+        assert!(scheduler_wakes().contains_key("probe_test"));
+        drop(second);
+        assert!(!scheduler_wakes().contains_key("probe_test"));
+    }
+
+    async fn serve_once(payload: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(payload).await.unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_probe_exit_codes() {
+        let status = serde_json::to_vec(&healthy()).unwrap().leak();
+        assert_eq!(run_probe(&serve_once(status).await, &THRESHOLDS).await, 0);
+
+        let stale = ProbeStatus {
+            last_heartbeat: Some(0),
+            ..healthy()
+        };
+        let stale = serde_json::to_vec(&stale).unwrap().leak();
+        assert_eq!(run_probe(&serve_once(stale).await, &THRESHOLDS).await, 1);
+
+        assert_eq!(run_probe(&serve_once(b"oops").await, &THRESHOLDS).await, 1);
+
+        // Nothing listening
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert_eq!(run_probe(&addr, &THRESHOLDS).await, 1);
+    }
+}
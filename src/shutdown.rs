@@ -1,23 +1,46 @@
 use crate::components::redis_service::RedisActorHandle;
 use crate::components::ComponentManager;
-use std::sync::Arc;
-use tokio::sync::oneshot;
-use tracing::{error, info};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
 
 #[cfg(unix)]
 use tokio::signal::unix::{signal, SignalKind};
 #[cfg(windows)]
 use tokio::signal::windows::{ctrl_break, ctrl_c};
 
+/// How long background tasks still using the store get to finish after being told to stop
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Background tasks the shutdown waits for before the store goes away, such as the web server
+/// finishing its requests in the combined mode
+pub type DrainedTasks = Arc<Mutex<Vec<JoinHandle<()>>>>;
+
 /// Set up signal handlers for graceful shutdown
 pub async fn handle_signals(
     shutdown_send: oneshot::Sender<()>,
+    background_shutdown: watch::Sender<bool>,
+    drained_tasks: DrainedTasks,
     component_manager: Arc<ComponentManager>,
     redis_handle: RedisActorHandle,
 ) {
     // Wait for a termination signal
     wait_for_signal().await;
 
+    // Stop background tasks such as the presence updater
+    let _ = background_shutdown.send(true);
+
+    // Let the tasks still using the store finish
+    let tasks = std::mem::take(&mut *drained_tasks.lock().unwrap_or_else(|e| e.into_inner()));
+    if tokio::time::timeout(DRAIN_TIMEOUT, futures::future::join_all(tasks))
+        .await
+        .is_err()
+    {
+        warn!("Background tasks didn't stop in {:?}", DRAIN_TIMEOUT);
+    }
+
     // Shut down all components
     if let Err(e) = component_manager.shutdown_all().await {
         error!("Error shutting down components: {:?}", e);
@@ -32,6 +55,9 @@ pub async fn handle_signals(
         info!("Redis actor shut down successfully");
     }
 
+    // Export the spans still waiting in the batch
+    crate::utils::telemetry::shutdown();
+
     // Send shutdown signal to main task
     let _ = shutdown_send.send(());
 }
@@ -1,36 +1,51 @@
+#[cfg(feature = "web-interface")]
+use crate::combined::{spawn_gateway_watch, start_web_server, web_state};
+use crate::commands::calendar::get_calendar_handle;
+use crate::commands::work::get_work_schedule_handle;
 use crate::commands::{create_error_embed, get_all_application_commands, CommandContext};
+use crate::components::redis_service::{spawn_store, RedisActorHandle};
+use crate::components::warmup::{
+    warm_up, CalendarWarmUp, WarmUp, WorkScheduleWarmUp, WARM_UP_TIMEOUT,
+};
 use crate::components::{
-    google_calendar::GoogleCalendar, work_schedule::WorkSchedule, ComponentManager,
+    digest::Digest, google_calendar::GoogleCalendar, work_schedule::WorkSchedule, ComponentManager,
 };
 use crate::config::Config;
 use crate::error::{other_error, Error};
+use crate::leader::{spawn_election, Leadership};
+use crate::presence::{spawn_presence_updater, PresenceHandle};
+use crate::probe::spawn_probe_server;
 use crate::shutdown;
+use crate::utils::logging::LogConfig;
+use crate::utils::telemetry;
+use crate::watchdog::run_watchdog;
 use poise::serenity_prelude as serenity;
 use rust_i18n::t;
-use serenity::model::user::OnlineStatus;
+use std::path::Path;
+#[cfg(feature = "web-interface")]
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use tokio::sync::{oneshot, RwLock};
-use tracing::{error, info};
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
-
-/// Initialize logging with environment-based configuration
-pub fn init_logging() -> miette::Result<()> {
-    let subscriber = FmtSubscriber::builder()
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("info,serenity=warn,poise=warn")),
-        )
-        .finish();
+use tokio::sync::{oneshot, watch, RwLock};
+use tracing::{debug, error, info, info_span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
-    tracing::subscriber::set_global_default(subscriber)
+/// Initialize logging from the resolved logging flags
+pub fn init_logging(log_config: &LogConfig) -> miette::Result<()> {
+    tracing_subscriber::registry()
+        .with(log_config.env_filter())
+        .with(log_config.fmt_layer(std::io::stdout))
+        .with(telemetry::export_layer("mussubotti"))
+        .try_init()
         .map_err(|e| other_error(&format!("Failed to set up logging: {e}")))?;
 
     Ok(())
 }
 
-/// Load and initialize the application config
-pub async fn load_config() -> miette::Result<Arc<RwLock<Config>>> {
-    match Config::load() {
+/// Load and initialize the application config, reading the enabled components from
+/// `components_file` when one is given
+pub async fn load_config(components_file: Option<&Path>) -> miette::Result<Arc<RwLock<Config>>> {
+    match Config::load_with_components(components_file) {
         Ok(config) => Ok(Arc::new(RwLock::new(config))),
         Err(e) => {
             error!("Failed to load configuration: {:?}", e);
@@ -39,77 +54,156 @@ pub async fn load_config() -> miette::Result<Arc<RwLock<Config>>> {
     }
 }
 
-/// Initialize and start the Discord bot
-pub async fn start_bot(config: Arc<RwLock<Config>>) -> miette::Result<()> {
+/// Initialize and start the Discord bot, serving the work hours web app alongside it when
+/// `with_web` is set
+pub async fn start_bot(config: Arc<RwLock<Config>>, with_web: bool) -> miette::Result<()> {
     // Get Discord token and activity
     let token = {
         let config_read = config.read().await;
         config_read.discord_token.clone()
     };
 
-    let activity = {
-        let config_read = config.read().await;
-        config_read.activity.clone()
-    };
-
     // Set locale from config
     {
         let config_read = config.read().await;
         crate::utils::i18n::set_locale(&config_read.bot_locale);
         info!("Setting locale to {}", config_read.bot_locale);
+        crate::utils::redact::set_log_redaction(config_read.log_redaction);
     }
 
     // Set up framework options
     let options = poise::FrameworkOptions {
         commands: get_all_application_commands(),
         on_error: |error| Box::pin(on_error(error)),
+        command_check: Some(|ctx| Box::pin(crate::commands::feature::feature_gate(ctx))),
+        event_handler: |ctx, event, framework, data| {
+            Box::pin(crate::handlers::event_handler(ctx, event, framework, data))
+        },
+        pre_command: |ctx| {
+            Box::pin(async move {
+                // Kept with the invocation, so the exported span lasts as long as the command
+                let span = info_span!(
+                    "command",
+                    command = %ctx.command().qualified_name,
+                    guild_id = ctx.guild_id().map(|id| id.get()),
+                );
+                span.in_scope(|| debug!("Running command"));
+                ctx.set_invocation_data(span).await;
+            })
+        },
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("!".to_string()),
+            dynamic_prefix: Some(|ctx| Box::pin(crate::prefix::dynamic_prefix(ctx))),
+            mention_as_prefix: true,
             ..Default::default()
         },
         ..Default::default()
     };
 
-    // Set intents
-    let intents = serenity::GatewayIntents::non_privileged();
+    // Set intents. Member joins need the privileged members intent, so it's only asked for
+    // when greetings are on. Likewise attachments of posted messages need the message content
+    // intent, only asked for when schedule uploads from a channel are on
+    let mut intents = serenity::GatewayIntents::non_privileged();
+    if config.read().await.welcome_channel_id.is_some() {
+        intents |= serenity::GatewayIntents::GUILD_MEMBERS;
+    }
+    if config.read().await.schedule_upload_channel_id.is_some() {
+        intents |= serenity::GatewayIntents::MESSAGE_CONTENT;
+    }
 
     // Initialize component manager
     let mut component_manager = ComponentManager::new(Arc::clone(&config));
 
-    // Initialize Redis service
-    let (mut redis_actor, redis_handle) =
-        crate::components::redis_service::RedisActor::new(Arc::clone(&config));
+    // Initialize the Redis service, or the SQLite store standing in for it
+    let redis_handle = spawn_store(Arc::clone(&config)).await?;
 
-    // Spawn Redis actor task
-    tokio::spawn(async move {
-        redis_actor.run().await;
-    });
+    // The locale chosen with /setup for the bot's guild wins over the configured one
+    crate::guild_config::apply_locale(&redis_handle, &config).await;
 
     // Register Google Calendar component
-    component_manager.register(GoogleCalendar::new());
+    component_manager.register(GoogleCalendar::new);
 
     // Register Work Schedule component
-    component_manager.register(WorkSchedule::new());
+    component_manager.register(WorkSchedule::new);
+
+    // Register the combined daily digest, which stays idle unless enabled
+    component_manager.register(Digest::new);
 
     // Create a shared component manager
     let component_manager = Arc::new(component_manager);
 
-    // Create a shared data context for commands
-    let command_data = CommandContext::new(Arc::clone(&config))
-        .with_component_manager(Arc::clone(&component_manager));
+    // Handle for forcing presence updates
+    let presence_handle = PresenceHandle::new();
 
     // Create shutdown channel
     let (shutdown_send, shutdown_recv) = oneshot::channel();
 
+    // Create shutdown broadcast for background tasks
+    let (background_shutdown, background_shutdown_recv) = watch::channel(false);
+
+    // Find out whether this replica runs the schedulers
+    let leader_election = config.read().await.leader_election;
+    let leadership = spawn_election(
+        redis_handle.clone(),
+        leader_election,
+        background_shutdown_recv.clone(),
+    );
+
+    // Create a shared data context for commands
+    let command_data = CommandContext::new(Arc::clone(&config))
+        .with_component_manager(Arc::clone(&component_manager))
+        .with_redis_handle(redis_handle.clone())
+        .with_presence_handle(presence_handle.clone())
+        .with_leadership(leadership.clone());
+
+    // Kept for the probe server and the gateway watch, started once the client exists
+    let probe_addr = config.read().await.probe_addr.clone();
+    let probe_redis = redis_handle.clone();
+    let probe_shutdown = background_shutdown_recv.clone();
+    #[cfg(feature = "web-interface")]
+    let gateway_shutdown = background_shutdown_recv.clone();
+
+    // Tasks the shutdown waits for, like the web server in the combined mode
+    let drained_tasks = shutdown::DrainedTasks::default();
+
+    // The web app's health check follows the gateway in the combined mode
+    #[cfg(feature = "web-interface")]
+    let gateway_connected = with_web.then(Arc::<AtomicBool>::default);
+    #[cfg(not(feature = "web-interface"))]
+    if with_web {
+        tracing::warn!("--with-web needs the web-interface feature, serving only the bot");
+    }
+
     // Clone redis handle for shutdown handler
     let shutdown_redis = redis_handle.clone();
 
     // Clone component manager for shutdown handler
     let shutdown_components = Arc::clone(&component_manager);
+    let shutdown_drained = Arc::clone(&drained_tasks);
+
+    // Kept for the web server, started once the components are up
+    #[cfg(feature = "web-interface")]
+    let web = gateway_connected.clone().map(|gateway_connected| {
+        (
+            web_state(
+                redis_handle.clone(),
+                component_manager.bus(),
+                gateway_connected,
+            ),
+            background_shutdown_recv.clone(),
+            Arc::clone(&drained_tasks),
+        )
+    });
 
     // Spawn signal handler task
     tokio::spawn(async move {
-        shutdown::handle_signals(shutdown_send, shutdown_components, shutdown_redis).await;
+        shutdown::handle_signals(
+            shutdown_send,
+            background_shutdown,
+            shutdown_drained,
+            shutdown_components,
+            shutdown_redis,
+        )
+        .await;
     });
 
     // Create framework with new poise API
@@ -120,12 +214,15 @@ pub async fn start_bot(config: Arc<RwLock<Config>>) -> miette::Result<()> {
                 Box::pin(async move {
                     info!("{} is connected!", ready.user.name);
 
-                    // Set the bot's status
-                    ctx.set_presence(
-                        Some(serenity::ActivityData::playing(&activity)),
-                        OnlineStatus::Online,
+                    // Start rotating the bot's status
+                    spawn_presence_updater(
+                        ctx.clone(),
+                        Arc::clone(&config),
+                        Arc::clone(&component_manager),
+                        redis_handle.clone(),
+                        presence_handle,
+                        background_shutdown_recv,
                     );
-                    info!("Setting activity to {}", activity);
 
                     // Initialize components
                     let components = Arc::clone(&component_manager);
@@ -136,6 +233,33 @@ pub async fn start_bot(config: Arc<RwLock<Config>>) -> miette::Result<()> {
                         error!("Failed to initialize components: {:?}", e);
                     }
 
+                    // Serve the work hours web app from this process
+                    #[cfg(feature = "web-interface")]
+                    if let Some((state, shutdown, drained)) = web {
+                        if let Some(server) = start_web_server(state, shutdown).await {
+                            drained
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .push(server);
+                        }
+                    }
+
+                    // Run the schedulers while this replica leads
+                    tokio::spawn(run_leader_tasks(
+                        leadership,
+                        Arc::clone(&component_manager),
+                        Arc::clone(&config),
+                        redis_handle.clone(),
+                    ));
+
+                    // Fetch what the first commands need in the background
+                    if config.read().await.warm_cache_on_start {
+                        tokio::spawn(warm_caches(
+                            Arc::clone(&component_manager),
+                            Arc::clone(&config),
+                        ));
+                    }
+
                     // Register slash commands
                     if let Err(e) =
                         poise::builtins::register_globally(ctx, &framework.options().commands).await
@@ -155,6 +279,22 @@ pub async fn start_bot(config: Arc<RwLock<Config>>) -> miette::Result<()> {
     info!("Starting bot...");
     let mut client = client_result.map_err(Error::from)?;
 
+    // Follow the gateway for the web app's health check
+    #[cfg(feature = "web-interface")]
+    if let Some(connected) = gateway_connected {
+        spawn_gateway_watch(client.shard_manager.clone(), connected, gateway_shutdown);
+    }
+
+    // Serve the liveness probe its status
+    if let Some(addr) = probe_addr {
+        spawn_probe_server(
+            addr,
+            client.shard_manager.clone(),
+            probe_redis,
+            probe_shutdown,
+        );
+    }
+
     // Create a separate task to handle the client
     let client_handle = tokio::spawn(async move {
         if let Err(e) = client.start().await {
@@ -184,6 +324,45 @@ pub async fn start_bot(config: Arc<RwLock<Config>>) -> miette::Result<()> {
     }
 }
 
+/// Start the components' schedulers, their watchdog and background tasks every time this
+/// replica becomes the leader, and stop them when its term ends
+async fn run_leader_tasks(
+    mut leadership: Leadership,
+    component_manager: Arc<ComponentManager>,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
+) {
+    while let Some(term) = leadership.next_term().await {
+        component_manager
+            .start_background_all(Arc::clone(&config), redis_handle.clone())
+            .await;
+        let watchdog = tokio::spawn(run_watchdog(
+            Arc::clone(&component_manager),
+            Arc::clone(&config),
+            redis_handle.clone(),
+        ));
+        term.cancelled().await;
+        info!("Leadership ended, stopping schedulers");
+        watchdog.abort();
+        component_manager.stop_background_all().await;
+    }
+}
+
+/// Prefetch the employees, this week's schedules and the upcoming calendar events
+async fn warm_caches(component_manager: Arc<ComponentManager>, config: Arc<RwLock<Config>>) {
+    let week_start = config.read().await.week_starts_on;
+    let mut sources: Vec<Box<dyn WarmUp>> = Vec::new();
+    if !component_manager.is_disabled("work_schedule") {
+        let handle = get_work_schedule_handle(Some(&component_manager), Arc::clone(&config)).await;
+        sources.push(Box::new(WorkScheduleWarmUp { handle, week_start }));
+    }
+    if !component_manager.is_disabled("google_calendar") {
+        let handle = get_calendar_handle(Some(&component_manager), config).await;
+        sources.push(Box::new(CalendarWarmUp(handle)));
+    }
+    warm_up(&sources, WARM_UP_TIMEOUT).await;
+}
+
 /// Handle errors from commands
 async fn on_error(error: poise::FrameworkError<'_, CommandContext, Error>) {
     match error {
@@ -0,0 +1,273 @@
+use crate::components::redis_service::RedisActorHandle;
+use crate::guild_config::get_guild_config;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateEmbedFooter};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Longest footer text a theme can set
+pub const MAX_FOOTER_LEN: usize = 200;
+
+/// Accent a message is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeColor {
+    Primary,
+    Success,
+    Warning,
+    Error,
+}
+
+impl ThemeColor {
+    /// Color used when the guild's theme doesn't set one
+    pub const fn default_value(self) -> u32 {
+        match self {
+            ThemeColor::Primary => 0x0099FF, // Blue color
+            ThemeColor::Success => 0x00FF00, // Green color
+            ThemeColor::Warning => 0xFFAA00, // Orange color
+            ThemeColor::Error => 0xFF0000,   // Red color
+        }
+    }
+}
+
+/// Theme settings of a guild as stored in its config, set with `/config set theme`.
+///
+/// Colors are kept as `#RRGGBB` strings so the stored config stays readable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub primary: Option<String>,
+    #[serde(default)]
+    pub success: Option<String>,
+    #[serde(default)]
+    pub warning: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Small logo shown in the corner of notifications
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    /// Footer text of notifications
+    #[serde(default)]
+    pub footer_text: Option<String>,
+}
+
+/// Parse a color written as `#RRGGBB`, `RRGGBB` or `0xRRGGBB`
+pub fn parse_hex_color(value: &str) -> Result<u32, String> {
+    let trimmed = value.trim();
+    let hex = trimmed
+        .strip_prefix('#')
+        .or_else(|| trimmed.strip_prefix("0x"))
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{value}' is not a color like #1E90FF"));
+    }
+    u32::from_str_radix(hex, 16).map_err(|e| format!("'{value}' is not a color: {e}"))
+}
+
+/// Check a thumbnail URL, which Discord only shows over HTTP(S)
+pub fn validate_thumbnail_url(value: &str) -> Result<String, String> {
+    let url = url::Url::parse(value.trim()).map_err(|e| format!("'{value}' is not a URL: {e}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("'{value}' is not an http(s) URL"));
+    }
+    Ok(url.to_string())
+}
+
+/// Check a footer text's length
+pub fn validate_footer(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > MAX_FOOTER_LEN {
+        return Err(format!(
+            "the footer must be 1 to {MAX_FOOTER_LEN} characters"
+        ));
+    }
+    Ok(value.to_string())
+}
+
+/// Colors and branding a guild's messages are drawn with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme {
+    primary: Option<u32>,
+    success: Option<u32>,
+    warning: Option<u32>,
+    error: Option<u32>,
+    pub thumbnail_url: Option<String>,
+    pub footer_text: Option<String>,
+}
+
+impl Theme {
+    /// Theme from a guild's stored settings. Colors that no longer parse are logged and left
+    /// at their defaults.
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        let color = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .and_then(|value| match parse_hex_color(value) {
+                    Ok(color) => Some(color),
+                    Err(e) => {
+                        warn!("Ignoring theme {} color: {}", name, e);
+                        None
+                    }
+                })
+        };
+        Self {
+            primary: color("primary", &config.primary),
+            success: color("success", &config.success),
+            warning: color("warning", &config.warning),
+            error: color("error", &config.error),
+            thumbnail_url: config.thumbnail_url.clone(),
+            footer_text: config.footer_text.clone(),
+        }
+    }
+
+    /// Theme of a guild, or the default one outside guilds
+    pub async fn resolve(redis_handle: &RedisActorHandle, guild_id: Option<u64>) -> Self {
+        match guild_id {
+            Some(guild_id) => {
+                Self::from_config(&get_guild_config(redis_handle, guild_id).await.theme)
+            }
+            None => Self::default(),
+        }
+    }
+
+    /// Theme of the guild a channel belongs to, for scheduled notifications posted to it.
+    /// Channels that can't be looked up get the default theme.
+    pub async fn for_channel(
+        http: &serenity::Http,
+        redis_handle: &RedisActorHandle,
+        channel_id: u64,
+    ) -> Self {
+        let guild_id = match ChannelId::new(channel_id).to_channel(http).await {
+            Ok(channel) => channel.guild().map(|channel| channel.guild_id.get()),
+            Err(e) => {
+                debug!(
+                    "Failed to look up the guild of channel {}: {}",
+                    channel_id, e
+                );
+                None
+            }
+        };
+        Self::resolve(redis_handle, guild_id).await
+    }
+
+    /// The guild's color for a role, or the role's default
+    pub fn color(&self, role: ThemeColor) -> u32 {
+        self.color_or(role, role.default_value())
+    }
+
+    /// The guild's color for a role, or `default` for messages with an accent of their own
+    pub fn color_or(&self, role: ThemeColor, default: u32) -> u32 {
+        let color = match role {
+            ThemeColor::Primary => self.primary,
+            ThemeColor::Success => self.success,
+            ThemeColor::Warning => self.warning,
+            ThemeColor::Error => self.error,
+        };
+        color.unwrap_or(default)
+    }
+
+    /// Add the guild's thumbnail and footer to an embed. An embed's own footer is kept.
+    pub fn brand(&self, mut embed: CreateEmbed) -> CreateEmbed {
+        if let Some(url) = &self.thumbnail_url {
+            embed = embed.thumbnail(url);
+        }
+        if let Some(footer) = &self.footer_text {
+            // CreateEmbed can't be read back, so the footer is only added to embeds rendered
+            // without one
+            let has_footer = serde_json::to_value(&embed)
+                .map(|json| json.get("footer").is_some())
+                .unwrap_or(false);
+            if !has_footer {
+                embed = embed.footer(CreateEmbedFooter::new(footer));
+            }
+        }
+        embed
+    }
+
+    /// Embed with a title and description in one of the theme's colors
+    pub fn embed(&self, role: ThemeColor, title: &str, description: &str) -> CreateEmbed {
+        CreateEmbed::new()
+            .title(title)
+            .description(description)
+            .color(self.color(role))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_colors_parse() {
+        assert_eq!(parse_hex_color("#1E90FF"), Ok(0x1E90FF));
+        assert_eq!(parse_hex_color("1e90ff"), Ok(0x1E90FF));
+        assert_eq!(parse_hex_color(" 0x00ff00 "), Ok(0x00FF00));
+
+        for invalid in ["", "#", "#FFF", "#1E90FF00", "#GGGGGG", "blue", "#+12345"] {
+            assert!(parse_hex_color(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_colors_fall_back_to_defaults() {
+        let theme = Theme::from_config(&ThemeConfig {
+            primary: Some("#112233".to_string()),
+            // Stored before validation or edited by hand
+            success: Some("green".to_string()),
+            ..Default::default()
+        });
+
+        assert_eq!(theme.color(ThemeColor::Primary), 0x112233);
+        assert_eq!(theme.color_or(ThemeColor::Primary, 0x4285F4), 0x112233);
+        assert_eq!(theme.color(ThemeColor::Success), 0x00FF00);
+        assert_eq!(theme.color_or(ThemeColor::Success, 0x34A853), 0x34A853);
+        assert_eq!(theme.color(ThemeColor::Error), 0xFF0000);
+
+        let default = Theme::default();
+        for role in [
+            ThemeColor::Primary,
+            ThemeColor::Success,
+            ThemeColor::Warning,
+            ThemeColor::Error,
+        ] {
+            assert_eq!(default.color(role), role.default_value());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guilds_without_a_theme_get_the_default() {
+        let redis_handle = RedisActorHandle::fake();
+        let mut config = get_guild_config(&redis_handle, 1).await;
+        config.theme.warning = Some("#123456".to_string());
+        crate::guild_config::set_guild_config(&redis_handle, 1, &config)
+            .await
+            .unwrap();
+
+        let themed = Theme::resolve(&redis_handle, Some(1)).await;
+        assert_eq!(themed.color(ThemeColor::Warning), 0x123456);
+        assert_eq!(
+            Theme::resolve(&redis_handle, Some(2)).await,
+            Theme::default()
+        );
+        assert_eq!(Theme::resolve(&redis_handle, None).await, Theme::default());
+    }
+
+    #[test]
+    fn test_branding_keeps_an_existing_footer() {
+        let theme = Theme {
+            thumbnail_url: Some("https://example.com/logo.png".to_string()),
+            footer_text: Some("Myymälä".to_string()),
+            ..Default::default()
+        };
+
+        let branded = serde_json::to_value(theme.brand(CreateEmbed::new())).unwrap();
+        assert_eq!(branded["thumbnail"]["url"], "https://example.com/logo.png");
+        assert_eq!(branded["footer"]["text"], "Myymälä");
+
+        let own_footer = CreateEmbed::new().footer(CreateEmbedFooter::new("Week 11"));
+        let branded = serde_json::to_value(theme.brand(own_footer)).unwrap();
+        assert_eq!(branded["footer"]["text"], "Week 11");
+
+        assert!(validate_thumbnail_url("ftp://example.com/logo.png").is_err());
+        assert!(validate_footer(&"x".repeat(MAX_FOOTER_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::{other_error, BotResult};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Settings a user has chosen for themselves with `/preferences`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserPreferences {
+    /// IANA timezone name, e.g. "Europe/Helsinki"
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Employee name commands default to when none is given
+    #[serde(default)]
+    pub employee: Option<String>,
+    /// How schedule and calendar command replies are shown
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// How schedule replies lay out their shifts
+    #[serde(default)]
+    pub schedule_style: ScheduleStyle,
+}
+
+/// How a user reads command replies
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Discord embeds
+    #[default]
+    #[name = "embed"]
+    Embed,
+    /// Plain line-based messages, easier to follow with a screen reader
+    #[name = "text"]
+    Text,
+}
+
+/// How schedule replies lay out shifts
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleStyle {
+    /// A field per employee or day
+    #[default]
+    #[name = "normal"]
+    Normal,
+    /// Fixed-width tables in code blocks, which don't wrap on a phone
+    #[name = "compact"]
+    Compact,
+}
+
+/// Redis key holding a user's preferences
+pub fn user_preferences_key(user_id: u64) -> Key {
+    Key::fixed("user_preferences").id(user_id)
+}
+
+/// Load a user's preferences, or empty ones if they have none (or they're unreadable)
+pub async fn get_user_preferences(
+    redis_handle: &RedisActorHandle,
+    user_id: u64,
+) -> UserPreferences {
+    let stored = match redis_handle
+        .get::<Option<String>>(&user_preferences_key(user_id))
+        .await
+    {
+        Ok(stored) => stored,
+        Err(e) => {
+            warn!("Failed to read preferences for user {}: {}", user_id, e);
+            None
+        }
+    };
+
+    stored
+        .and_then(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| warn!("Ignoring invalid preferences for user {}: {}", user_id, e))
+                .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// Every user linked to an employee with `/preferences`, with the employee's name as they gave
+/// it. Users whose preferences can't be read are left out.
+pub async fn linked_employees(redis_handle: &RedisActorHandle) -> BotResult<Vec<(u64, String)>> {
+    let user_ids: Vec<u64> = redis_handle
+        .scan_prefix(&Key::fixed("user_preferences"))
+        .await?
+        .iter()
+        .filter_map(|key| key.rsplit(':').next()?.parse().ok())
+        .collect();
+    let keys: Vec<Key> = user_ids
+        .iter()
+        .map(|id| user_preferences_key(*id))
+        .collect();
+    let stored: Vec<Option<String>> = redis_handle.mget(&keys).await?;
+
+    Ok(user_ids
+        .into_iter()
+        .zip(stored)
+        .filter_map(|(user_id, json)| {
+            let preferences: UserPreferences = serde_json::from_str(&json?).ok()?;
+            Some((user_id, preferences.employee?))
+        })
+        .collect())
+}
+
+/// Persist a user's preferences
+pub async fn set_user_preferences(
+    redis_handle: &RedisActorHandle,
+    user_id: u64,
+    preferences: &UserPreferences,
+) -> BotResult<()> {
+    let json = serde_json::to_string(preferences)
+        .map_err(|e| other_error(&format!("Failed to serialize user preferences: {e}")))?;
+    redis_handle.set(&user_preferences_key(user_id), json).await
+}
+
+/// Where a command's timezone came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimezoneSource {
+    /// Given with the command
+    Parameter,
+    /// The user's `/preferences`
+    User,
+    /// The guild's config
+    Guild,
+    /// The bot's TIMEZONE setting
+    Global,
+}
+
+impl TimezoneSource {
+    /// Locale key describing the source
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            TimezoneSource::Parameter => "timezone_source_parameter",
+            TimezoneSource::User => "timezone_source_user",
+            TimezoneSource::Guild => "timezone_source_guild",
+            TimezoneSource::Global => "timezone_source_global",
+        }
+    }
+}
+
+/// Pick the timezone for a command: an explicit parameter, then the user's preference, then the
+/// guild config and finally the global config.
+///
+/// Only an invalid parameter is an error, returned as the rejected name. Stored values that no
+/// longer parse are skipped, and an invalid global timezone falls back to UTC.
+pub fn resolve_timezone(
+    parameter: Option<&str>,
+    user: Option<&str>,
+    guild: Option<&str>,
+    global: &str,
+) -> Result<(Tz, TimezoneSource), String> {
+    if let Some(parameter) = parameter {
+        return parameter
+            .trim()
+            .parse::<Tz>()
+            .map(|tz| (tz, TimezoneSource::Parameter))
+            .map_err(|_| parameter.to_string());
+    }
+
+    let stored = [(user, TimezoneSource::User), (guild, TimezoneSource::Guild)];
+    let tz = stored
+        .into_iter()
+        .find_map(|(name, source)| Some((name?.parse::<Tz>().ok()?, source)))
+        .unwrap_or_else(|| (global.parse().unwrap_or(Tz::UTC), TimezoneSource::Global));
+    Ok(tz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timezone_resolution_order() {
+        let helsinki = chrono_tz::Europe::Helsinki;
+        let london = chrono_tz::Europe::London;
+        let tokyo = chrono_tz::Asia::Tokyo;
+
+        assert_eq!(
+            resolve_timezone(
+                Some("Asia/Tokyo"),
+                Some("Europe/London"),
+                Some("Europe/Helsinki"),
+                "UTC"
+            ),
+            Ok((tokyo, TimezoneSource::Parameter))
+        );
+        assert_eq!(
+            resolve_timezone(None, Some("Europe/London"), Some("Europe/Helsinki"), "UTC"),
+            Ok((london, TimezoneSource::User))
+        );
+        assert_eq!(
+            resolve_timezone(None, None, Some("Europe/Helsinki"), "UTC"),
+            Ok((helsinki, TimezoneSource::Guild))
+        );
+        assert_eq!(
+            resolve_timezone(None, None, None, "Asia/Tokyo"),
+            Ok((tokyo, TimezoneSource::Global))
+        );
+    }
+
+    #[test]
+    fn test_invalid_timezones() {
+        // A mistyped parameter is reported rather than silently replaced
+        assert_eq!(
+            resolve_timezone(Some("Helsinki"), Some("Europe/London"), None, "UTC"),
+            Err("Helsinki".to_string())
+        );
+
+        // Stored values that stopped parsing fall through to the next source
+        assert_eq!(
+            resolve_timezone(None, Some("Mars/Olympus"), Some("Europe/Helsinki"), "UTC"),
+            Ok((chrono_tz::Europe::Helsinki, TimezoneSource::Guild))
+        );
+        assert_eq!(
+            resolve_timezone(None, None, None, "nowhere"),
+            Ok((Tz::UTC, TimezoneSource::Global))
+        );
+    }
+}
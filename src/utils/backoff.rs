@@ -0,0 +1,175 @@
+use crate::error::BotResult;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Longest wait between polls while failures keep coming
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Consecutive authorization failures before admins are alerted
+pub const AUTH_ALERT_THRESHOLD: u32 = 10;
+
+/// How a poll went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollOutcome {
+    Success,
+    /// Temporary failure, e.g. the network or the API is down
+    Failure,
+    /// The credentials were rejected; retrying won't help until someone re-authorizes
+    AuthFailure,
+}
+
+impl PollOutcome {
+    /// Classify the result of a poll
+    pub fn of<T>(result: &BotResult<T>) -> Self {
+        match result {
+            Ok(_) => PollOutcome::Success,
+            Err(e) if e.is_auth() => PollOutcome::AuthFailure,
+            Err(_) => PollOutcome::Failure,
+        }
+    }
+}
+
+/// What to do after recording a poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollDecision {
+    /// Wait before the next poll, without jitter
+    pub delay: Duration,
+    /// Whether admins should now be alerted about the failing credentials
+    pub alert: bool,
+}
+
+/// Exponential backoff for a polling loop.
+///
+/// Every consecutive failure doubles the wait from the normal interval up to [`MAX_BACKOFF`],
+/// and a success goes back to the normal interval.
+#[derive(Debug, Clone)]
+pub struct PollBackoff {
+    interval: Duration,
+    failures: u32,
+    auth_failures: u32,
+    alerted: bool,
+}
+
+impl PollBackoff {
+    /// Create a backoff polling at `interval` while things work
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            failures: 0,
+            auth_failures: 0,
+            alerted: false,
+        }
+    }
+
+    /// Number of failures in a row
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Record how a poll went and decide when to poll next
+    pub fn record(&mut self, outcome: PollOutcome) -> PollDecision {
+        match outcome {
+            PollOutcome::Success => {
+                self.failures = 0;
+                self.auth_failures = 0;
+                self.alerted = false;
+            }
+            PollOutcome::Failure => {
+                self.failures += 1;
+                self.auth_failures = 0;
+            }
+            PollOutcome::AuthFailure => {
+                self.failures += 1;
+                self.auth_failures += 1;
+            }
+        }
+
+        // Alert once per streak of auth failures
+        let alert = !self.alerted && self.auth_failures >= AUTH_ALERT_THRESHOLD;
+        self.alerted |= alert;
+
+        // Never wait less than the normal interval, even if it's above the cap
+        let factor = 2u32.saturating_pow(self.failures.min(16));
+        let delay = self
+            .interval
+            .saturating_mul(factor)
+            .min(MAX_BACKOFF.max(self.interval));
+
+        PollDecision { delay, alert }
+    }
+}
+
+/// Spread a backoff delay randomly over its upper half so restarted instances don't retry in
+/// lockstep
+pub fn with_jitter(delay: Duration) -> Duration {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(delay.as_nanos() as u64);
+    let fraction = (hasher.finish() % 1000) as f64 / 1000.0;
+    delay.mul_f64(0.5 + fraction / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{google_auth_error, network_error};
+
+    const INTERVAL: Duration = Duration::from_secs(300);
+
+    fn delays(backoff: &mut PollBackoff, outcomes: &[PollOutcome]) -> Vec<u64> {
+        outcomes
+            .iter()
+            .map(|outcome| backoff.record(*outcome).delay.as_secs())
+            .collect()
+    }
+
+    #[test]
+    fn test_backoff_progression_and_reset() {
+        use PollOutcome::*;
+
+        let mut backoff = PollBackoff::new(INTERVAL);
+        assert_eq!(
+            delays(&mut backoff, &[Success, Failure, Failure, Failure, Failure]),
+            [300, 600, 1200, 2400, 3600]
+        );
+        // Capped at an hour
+        assert_eq!(delays(&mut backoff, &[Failure, Failure]), [3600, 3600]);
+        assert_eq!(backoff.failures(), 6);
+
+        assert_eq!(delays(&mut backoff, &[Success, Failure]), [300, 600]);
+    }
+
+    #[test]
+    fn test_auth_failures_alert_once() {
+        let mut backoff = PollBackoff::new(INTERVAL);
+        let results: Vec<BotResult<()>> = (0..AUTH_ALERT_THRESHOLD + 3)
+            .map(|_| Err(google_auth_error("invalid_grant")))
+            .collect();
+
+        let alerts: Vec<bool> = results
+            .iter()
+            .map(|result| backoff.record(PollOutcome::of(result)).alert)
+            .collect();
+        assert_eq!(alerts.iter().filter(|alert| **alert).count(), 1);
+        assert!(alerts[AUTH_ALERT_THRESHOLD as usize - 1]);
+
+        // A network failure breaks the streak, a success allows a new alert later
+        let mut backoff = PollBackoff::new(INTERVAL);
+        for _ in 0..AUTH_ALERT_THRESHOLD - 1 {
+            assert!(!backoff.record(PollOutcome::AuthFailure).alert);
+        }
+        let network: BotResult<()> = Err(network_error("connection refused"));
+        assert_eq!(PollOutcome::of(&network), PollOutcome::Failure);
+        assert!(!backoff.record(PollOutcome::of(&network)).alert);
+        assert!(!backoff.record(PollOutcome::AuthFailure).alert);
+        assert_eq!(PollOutcome::of(&Ok(())), PollOutcome::Success);
+    }
+
+    #[test]
+    fn test_jitter_stays_in_upper_half() {
+        for _ in 0..100 {
+            let delay = with_jitter(INTERVAL);
+            assert!(delay >= INTERVAL / 2 && delay <= INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Lines sent on their own within `BATCH_WINDOW` before the rest are collapsed
+pub const BATCH_LIMIT: usize = 5;
+
+/// Window the batch limit applies to, and how long collapsed lines are held
+pub const BATCH_WINDOW: Duration = Duration::from_secs(30);
+
+/// Message the batcher decided to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Batched {
+    /// A line on its own
+    Line(String),
+    /// Lines collapsed into one summary message
+    Summary(Vec<String>),
+}
+
+/// Batches one-line messages so bursts don't run into Discord's rate limits.
+///
+/// Up to `BATCH_LIMIT` lines within `BATCH_WINDOW` are sent one by one. Lines past that are
+/// held and sent together once `BATCH_WINDOW` has passed since the first of them.
+#[derive(Debug, Default)]
+pub struct LineBatcher {
+    sent: VecDeque<Instant>,
+    held: Vec<String>,
+    held_since: Option<Instant>,
+}
+
+impl LineBatcher {
+    /// Create a batcher that hasn't sent anything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a line at `now`, returning the message to send right away if any
+    pub fn push(&mut self, line: String, now: Instant) -> Option<Batched> {
+        while self
+            .sent
+            .front()
+            .is_some_and(|at| now.saturating_duration_since(*at) >= BATCH_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+
+        if self.held.is_empty() && self.sent.len() < BATCH_LIMIT {
+            self.sent.push_back(now);
+            return Some(Batched::Line(line));
+        }
+
+        self.held_since.get_or_insert(now);
+        self.held.push(line);
+        None
+    }
+
+    /// When the held lines should be flushed, or None if nothing is held
+    pub fn due_at(&self) -> Option<Instant> {
+        self.held_since.map(|at| at + BATCH_WINDOW)
+    }
+
+    /// Take the held lines once they're due at `now`
+    pub fn flush(&mut self, now: Instant) -> Option<Batched> {
+        if self.due_at().is_none_or(|due| now < due) {
+            return None;
+        }
+
+        self.held_since = None;
+        self.sent.push_back(now);
+        let mut lines = std::mem::take(&mut self.held);
+        Some(if lines.len() == 1 {
+            Batched::Line(lines.remove(0))
+        } else {
+            Batched::Summary(lines)
+        })
+    }
+}
+
+/// Hosts Discord's message links point to
+const MESSAGE_LINK_HOSTS: [&str; 4] = [
+    "discord.com",
+    "ptb.discord.com",
+    "canary.discord.com",
+    "discordapp.com",
+];
+
+/// Message a "Copy Message Link" URL points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLink {
+    /// None for direct messages, whose links have `@me` in place of the guild
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub message_id: u64,
+}
+
+/// Parse a message link like `https://discord.com/channels/<guild or @me>/<channel>/<message>`
+pub fn parse_message_link(link: &str) -> Option<MessageLink> {
+    let link = link.trim().trim_start_matches('<').trim_end_matches('>');
+    let rest = link
+        .strip_prefix("https://")
+        .or_else(|| link.strip_prefix("http://"))?;
+    let (host, path) = rest.split_once('/')?;
+    if !MESSAGE_LINK_HOSTS.contains(&host) {
+        return None;
+    }
+
+    let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let ["channels", guild, channel, message] = parts[..] else {
+        return None;
+    };
+    let id = |part: &str| part.parse::<u64>().ok().filter(|id| *id != 0);
+    let guild_id = match guild {
+        "@me" => None,
+        guild => Some(id(guild)?),
+    };
+    Some(MessageLink {
+        guild_id,
+        channel_id: id(channel)?,
+        message_id: id(message)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(batcher: &mut LineBatcher, start: Instant, offsets: &[u64]) -> Vec<Option<Batched>> {
+        offsets
+            .iter()
+            .map(|secs| batcher.push(format!("line {secs}"), start + Duration::from_secs(*secs)))
+            .collect()
+    }
+
+    #[test]
+    fn test_lines_within_the_limit_are_sent_right_away() {
+        let start = Instant::now();
+        let mut batcher = LineBatcher::new();
+
+        let sent = lines(&mut batcher, start, &[0, 1, 2, 3, 4]);
+        assert!(sent
+            .iter()
+            .all(|message| matches!(message, Some(Batched::Line(_)))));
+        assert_eq!(batcher.due_at(), None);
+
+        // The first lines slide out of the window
+        assert_eq!(
+            batcher.push("late".to_string(), start + Duration::from_secs(30)),
+            Some(Batched::Line("late".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_burst_is_collapsed_into_a_summary() {
+        let start = Instant::now();
+        let mut batcher = LineBatcher::new();
+
+        let sent = lines(&mut batcher, start, &[0, 0, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(sent.iter().filter(|message| message.is_some()).count(), 5);
+        assert_eq!(batcher.due_at(), Some(start + Duration::from_secs(31)));
+
+        assert_eq!(batcher.flush(start + Duration::from_secs(30)), None);
+        assert_eq!(
+            batcher.flush(start + Duration::from_secs(31)),
+            Some(Batched::Summary(vec![
+                "line 1".to_string(),
+                "line 2".to_string(),
+                "line 3".to_string(),
+            ]))
+        );
+        assert_eq!(batcher.due_at(), None);
+        assert_eq!(batcher.flush(start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_lines_are_held_while_others_wait() {
+        let start = Instant::now();
+        let mut batcher = LineBatcher::new();
+        lines(&mut batcher, start, &[0, 0, 0, 0, 0, 10]);
+
+        // The window has room again, but the line must not overtake the held one
+        assert_eq!(
+            batcher.push("after".to_string(), start + Duration::from_secs(35)),
+            None
+        );
+        assert_eq!(
+            batcher.flush(start + Duration::from_secs(40)),
+            Some(Batched::Summary(vec![
+                "line 10".to_string(),
+                "after".to_string()
+            ]))
+        );
+
+        // The summary counts towards the limit, and a single held line is sent as it is
+        lines(&mut batcher, start, &[41, 41, 41, 41]);
+        batcher.push("alone".to_string(), start + Duration::from_secs(42));
+        assert_eq!(
+            batcher.flush(start + Duration::from_secs(72)),
+            Some(Batched::Line("alone".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_guild_message_links_are_parsed() {
+        let expected = Some(MessageLink {
+            guild_id: Some(111),
+            channel_id: 222,
+            message_id: 333,
+        });
+        for link in [
+            "https://discord.com/channels/111/222/333",
+            "https://ptb.discord.com/channels/111/222/333",
+            "https://canary.discord.com/channels/111/222/333",
+            "https://discordapp.com/channels/111/222/333",
+            " <https://discord.com/channels/111/222/333/> ",
+        ] {
+            assert_eq!(parse_message_link(link), expected, "{link}");
+        }
+    }
+
+    #[test]
+    fn test_dm_message_links_are_parsed() {
+        assert_eq!(
+            parse_message_link("https://discord.com/channels/@me/222/333"),
+            Some(MessageLink {
+                guild_id: None,
+                channel_id: 222,
+                message_id: 333,
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_links_are_rejected() {
+        for link in [
+            "",
+            "333",
+            "https://example.com/channels/111/222/333",
+            "https://discord.com.example.com/channels/111/222/333",
+            "https://discord.com/channels/111/222",
+            "https://discord.com/channels/111/222/333/444",
+            "https://discord.com/channels/111/abc/333",
+            "https://discord.com/channels/111/222/0",
+            "https://discord.com/guilds/111/222/333",
+            "ftp://discord.com/channels/111/222/333",
+        ] {
+            assert_eq!(parse_message_link(link), None, "{link}");
+        }
+    }
+}
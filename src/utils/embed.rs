@@ -0,0 +1,126 @@
+/// Maximum length of an embed field value
+pub const FIELD_VALUE_LIMIT: usize = 1024;
+/// Maximum length of an embed description
+pub const DESCRIPTION_LIMIT: usize = 4096;
+/// Maximum number of fields in an embed
+pub const MAX_FIELDS: usize = 25;
+/// Maximum combined length of all text in an embed
+pub const TOTAL_LIMIT: usize = 6000;
+
+/// Truncate text to at most `limit` characters, marking the cut with an ellipsis
+pub fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(limit.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Join lines into chunks that each fit an embed field value, breaking only between lines
+pub fn split_lines(lines: &[String], limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in lines {
+        let line = truncate(line, limit);
+        let line_len = line.chars().count();
+
+        // +1 for the newline joining it to the previous line
+        if !current.is_empty() && current_len + 1 + line_len > limit {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+            current_len += 1;
+        }
+        current.push_str(&line);
+        current_len += line_len;
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Split a field whose value may be too long into several, continuing under a blank name
+pub fn split_field(name: &str, lines: &[String]) -> Vec<(String, String)> {
+    split_lines(lines, FIELD_VALUE_LIMIT)
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let name = if i == 0 { name } else { "\u{200B}" };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Keep as many fields as fit within the field count and total length limits of an embed.
+///
+/// `used` is the length of the other embed text (title, description, footer).
+pub fn limit_fields(fields: Vec<(String, String)>, used: usize) -> Vec<(String, String)> {
+    let mut total = used;
+    fields
+        .into_iter()
+        .take(MAX_FIELDS)
+        .take_while(|(name, value)| {
+            total += name.chars().count() + value.chars().count();
+            total <= TOTAL_LIMIT
+        })
+        .collect()
+}
+
+/// Render an embed as text for snapshot tests: the title, description, fields and footer
+#[cfg(test)]
+pub(crate) fn render_embed(embed: &poise::serenity_prelude::CreateEmbed) -> String {
+    let value = serde_json::to_value(embed).unwrap();
+    let mut text = format!("# {}\n", value["title"].as_str().unwrap_or_default());
+    if let Some(description) = value["description"].as_str() {
+        text.push_str(&format!("{description}\n"));
+    }
+    for field in value["fields"].as_array().into_iter().flatten() {
+        text.push_str(&format!(
+            "## {}\n{}\n",
+            field["name"].as_str().unwrap(),
+            field["value"].as_str().unwrap()
+        ));
+    }
+    if let Some(footer) = value["footer"]["text"].as_str() {
+        text.push_str(&format!("-- {footer}\n"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_lines_respects_limit() {
+        let lines: Vec<String> = (0..5).map(|i| format!("line {i}")).collect();
+        let chunks = split_lines(&lines, 13);
+        assert_eq!(chunks, vec!["line 0\nline 1", "line 2\nline 3", "line 4"]);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 13));
+
+        // A single overlong line is truncated rather than dropped
+        let chunks = split_lines(&["x".repeat(20)], 10);
+        assert_eq!(chunks, vec![format!("{}…", "x".repeat(9))]);
+    }
+
+    #[test]
+    fn test_limit_fields() {
+        let fields: Vec<(String, String)> = (0..30)
+            .map(|i| (format!("f{i}"), "v".to_string()))
+            .collect();
+        assert_eq!(limit_fields(fields.clone(), 0).len(), MAX_FIELDS);
+
+        let big = vec![
+            ("a".to_string(), "x".repeat(3000)),
+            ("b".to_string(), "x".repeat(3000)),
+        ];
+        assert_eq!(limit_fields(big, 10).len(), 1);
+    }
+}
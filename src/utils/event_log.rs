@@ -0,0 +1,192 @@
+//! Ring buffer of recent gateway events for `/debug events`.
+//!
+//! Capture is off by default and costs a single atomic load per event while off, so the
+//! handlers can record unconditionally.
+
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Events kept before the oldest ones are dropped
+pub const EVENT_LOG_CAPACITY: usize = 500;
+
+/// Whether gateway events are being captured
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref EVENT_LOG: Mutex<EventLog> = Mutex::new(EventLog::new(EVENT_LOG_CAPACITY));
+}
+
+/// A captured gateway event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedEvent {
+    pub at: DateTime<Local>,
+    /// Kind of event, e.g. "interaction" or "ready"
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+impl LoggedEvent {
+    /// Whether the kind or detail contains `filter`, ignoring case
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.kind.contains(&filter) || self.detail.to_lowercase().contains(&filter)
+    }
+
+    /// One line for the event log, e.g. `12:30:05 interaction /day by 1234 in 5678`
+    pub fn format(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.at.format("%H:%M:%S"),
+            self.kind,
+            self.detail
+        )
+    }
+}
+
+/// Fixed-size log of the latest events, with an optional filter for what gets captured
+#[derive(Debug)]
+pub struct EventLog {
+    entries: VecDeque<LoggedEvent>,
+    capacity: usize,
+    filter: Option<String>,
+}
+
+impl EventLog {
+    /// Create an empty log keeping at most `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            filter: None,
+        }
+    }
+
+    /// Add an event passing the filter, dropping the oldest one when the log is full
+    pub fn push(&mut self, event: LoggedEvent) {
+        if let Some(filter) = &self.filter {
+            if !event.matches(filter) {
+                return;
+            }
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(event);
+    }
+
+    /// The latest `limit` events matching `filter`, oldest first
+    pub fn recent(&self, filter: Option<&str>, limit: usize) -> Vec<LoggedEvent> {
+        let mut events: Vec<LoggedEvent> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|event| filter.is_none_or(|filter| event.matches(filter)))
+            .take(limit)
+            .cloned()
+            .collect();
+        events.reverse();
+        events
+    }
+}
+
+/// Start capturing events, only those matching `filter` when given. Events captured earlier
+/// are kept.
+pub fn start_capture(filter: Option<String>) {
+    lock().filter = filter.filter(|filter| !filter.trim().is_empty());
+    CAPTURING.store(true, Ordering::Relaxed);
+}
+
+/// Stop capturing events, keeping the ones captured so far
+pub fn stop_capture() {
+    CAPTURING.store(false, Ordering::Relaxed);
+}
+
+/// Whether events are being captured, and the filter they must match
+pub fn capture_state() -> (bool, Option<String>) {
+    (is_capturing(), lock().filter.clone())
+}
+
+/// Whether events are being captured
+pub fn is_capturing() -> bool {
+    CAPTURING.load(Ordering::Relaxed)
+}
+
+/// Record an event if capture is on. `detail` is only built then.
+pub fn record(kind: &'static str, detail: impl FnOnce() -> String) {
+    if !is_capturing() {
+        return;
+    }
+    lock().push(LoggedEvent {
+        at: Local::now(),
+        kind,
+        detail: detail(),
+    });
+}
+
+/// The latest `limit` captured events matching `filter`, oldest first
+pub fn recent_events(filter: Option<&str>, limit: usize) -> Vec<LoggedEvent> {
+    lock().recent(filter, limit)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, EventLog> {
+    EVENT_LOG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: &'static str, detail: &str) -> LoggedEvent {
+        LoggedEvent {
+            at: Local::now(),
+            kind,
+            detail: detail.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_oldest_events_are_dropped_when_full() {
+        let mut log = EventLog::new(3);
+        for i in 0..5 {
+            log.push(event("interaction", &format!("/day #{i}")));
+        }
+
+        assert_eq!(log.recent(None, EVENT_LOG_CAPACITY).len(), 3);
+        let details: Vec<String> = log
+            .recent(None, 10)
+            .into_iter()
+            .map(|event| event.detail)
+            .collect();
+        assert_eq!(details, ["/day #2", "/day #3", "/day #4"]);
+
+        // The limit keeps the newest ones
+        let details: Vec<String> = log
+            .recent(None, 2)
+            .into_iter()
+            .map(|event| event.detail)
+            .collect();
+        assert_eq!(details, ["/day #3", "/day #4"]);
+    }
+
+    #[test]
+    fn test_events_are_filtered_when_captured_and_shown() {
+        let mut log = EventLog::new(10);
+        log.filter = Some("Day".to_string());
+        log.push(event("interaction", "/day by 1"));
+        log.push(event("ready", "Mussubot in 3 guilds"));
+        log.push(event("interaction", "/tyovuorot by 2"));
+        assert_eq!(log.recent(None, 10).len(), 1);
+
+        log.filter = None;
+        log.push(event("ready", "Mussubot in 3 guilds"));
+        log.push(event("ratelimit", "POST /channels after 1200 ms"));
+
+        assert_eq!(log.recent(Some("READY"), 10).len(), 1);
+        assert_eq!(log.recent(Some("guilds"), 10)[0].kind, "ready");
+        assert_eq!(log.recent(Some("interaction"), 10).len(), 1);
+        assert!(log.recent(Some("resume"), 10).is_empty());
+    }
+}
@@ -1,4 +1,113 @@
+use chrono::{TimeDelta, Weekday};
+use rust_i18n::t;
+
 /// Set the current locale
 pub fn set_locale(locale: &str) {
     rust_i18n::set_locale(locale);
 }
+
+/// Localized full name of a weekday
+pub fn weekday_name(weekday: Weekday) -> String {
+    match weekday {
+        Weekday::Mon => t!("day_monday"),
+        Weekday::Tue => t!("day_tuesday"),
+        Weekday::Wed => t!("day_wednesday"),
+        Weekday::Thu => t!("day_thursday"),
+        Weekday::Fri => t!("day_friday"),
+        Weekday::Sat => t!("day_saturday"),
+        Weekday::Sun => t!("day_sunday"),
+    }
+    .to_string()
+}
+
+/// Localized abbreviated name of a weekday, e.g. "Mon"
+pub fn weekday_short_name(weekday: Weekday) -> String {
+    match weekday {
+        Weekday::Mon => t!("day_short_monday"),
+        Weekday::Tue => t!("day_short_tuesday"),
+        Weekday::Wed => t!("day_short_wednesday"),
+        Weekday::Thu => t!("day_short_thursday"),
+        Weekday::Fri => t!("day_short_friday"),
+        Weekday::Sat => t!("day_short_saturday"),
+        Weekday::Sun => t!("day_short_sunday"),
+    }
+    .to_string()
+}
+
+/// Format a number in a locale, rounded half-up to two decimals with trailing zeros trimmed,
+/// e.g. "37.5" in English and "37,5" in Finnish
+pub fn format_number(value: f64, locale: &str) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // Don't print "-0" for small negatives that round to zero
+    let rounded = if rounded == 0.0 { 0.0 } else { rounded };
+    let text = format!("{rounded:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    text.replace('.', &t!("number_decimal_separator", locale = locale))
+}
+
+/// Format hours with the locale's unit, e.g. "37.5 h" in English and "37,5 t" in Finnish
+pub fn format_hours(hours: f64, locale: &str) -> String {
+    format!(
+        "{} {}",
+        format_number(hours, locale),
+        t!("hours_unit", locale = locale)
+    )
+}
+
+/// Localized phrase for how far ahead something is, e.g. "in 2 days", in the largest whole unit
+pub fn humanize_duration(duration: TimeDelta) -> String {
+    let (count, one, other) = if duration.num_days() > 0 {
+        (duration.num_days(), "relative_day", "relative_days")
+    } else if duration.num_hours() > 0 {
+        (duration.num_hours(), "relative_hour", "relative_hours")
+    } else if duration.num_minutes() > 0 {
+        (
+            duration.num_minutes(),
+            "relative_minute",
+            "relative_minutes",
+        )
+    } else {
+        return t!("relative_now").to_string();
+    };
+
+    let key = if count == 1 { one } else { other };
+    t!(key, count = count).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_humanize_duration_uses_largest_unit() {
+        assert_eq!(humanize_duration(TimeDelta::days(2)), "in 2 days");
+        assert_eq!(
+            humanize_duration(TimeDelta::days(1) + TimeDelta::hours(5)),
+            "in 1 day"
+        );
+        assert_eq!(humanize_duration(TimeDelta::hours(3)), "in 3 hours");
+        assert_eq!(humanize_duration(TimeDelta::minutes(1)), "in 1 minute");
+        assert_eq!(humanize_duration(TimeDelta::seconds(30)), "now");
+        assert_eq!(humanize_duration(TimeDelta::hours(-1)), "now");
+    }
+
+    #[test]
+    fn test_number_and_hour_formatting() {
+        let cases = [
+            (0.0, "0", "0", "0 h", "0 t"),
+            (0.25, "0.25", "0,25", "0.25 h", "0,25 t"),
+            (40.0, "40", "40", "40 h", "40 t"),
+            (37.5, "37.5", "37,5", "37.5 h", "37,5 t"),
+            (7.125, "7.13", "7,13", "7.13 h", "7,13 t"),
+            (1.999, "2", "2", "2 h", "2 t"),
+            (-0.001, "0", "0", "0 h", "0 t"),
+            (-2.5, "-2.5", "-2,5", "-2.5 h", "-2,5 t"),
+        ];
+        for (value, en, fi, en_hours, fi_hours) in cases {
+            assert_eq!(format_number(value, "en"), en, "{value}");
+            assert_eq!(format_number(value, "fi-FI"), fi, "{value}");
+            assert_eq!(format_hours(value, "en"), en_hours, "{value}");
+            assert_eq!(format_hours(value, "fi-FI"), fi_hours, "{value}");
+        }
+    }
+}
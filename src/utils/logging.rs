@@ -0,0 +1,173 @@
+use clap::{Args, ValueEnum};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Filter used with `--verbose`, overriding RUST_LOG
+const VERBOSE_FILTER: &str = "debug";
+/// Filter used with `--quiet`, overriding RUST_LOG
+const QUIET_FILTER: &str = "warn";
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines, colored when writing to a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the fields of the current span and its parents
+    Json,
+}
+
+/// Logging flags shared by the binaries
+#[derive(Debug, Clone, Default, Args)]
+pub struct LogArgs {
+    /// Log output format
+    #[arg(long, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+    /// Log debug messages, overriding RUST_LOG
+    #[arg(long, short, conflicts_with = "quiet")]
+    pub verbose: bool,
+    /// Log only warnings and errors, overriding RUST_LOG
+    #[arg(long, short)]
+    pub quiet: bool,
+}
+
+/// Subscriber settings the logging flags resolve to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Filter directives, e.g. `info,serenity=warn`
+    pub filter: String,
+}
+
+impl LogArgs {
+    /// Resolve the flags against RUST_LOG and the binary's default filter. `--verbose` and
+    /// `--quiet` win over RUST_LOG, which wins over the default.
+    pub fn config(&self, rust_log: Option<&str>, default_filter: &str) -> LogConfig {
+        let filter = if self.verbose {
+            VERBOSE_FILTER
+        } else if self.quiet {
+            QUIET_FILTER
+        } else {
+            rust_log
+                .map(str::trim)
+                .filter(|filter| !filter.is_empty())
+                .unwrap_or(default_filter)
+        };
+        LogConfig {
+            format: self.log_format,
+            filter: filter.to_string(),
+        }
+    }
+}
+
+impl LogConfig {
+    /// Filter layer for the directives, falling back to `info` when they don't parse
+    pub fn env_filter(&self) -> EnvFilter {
+        EnvFilter::try_new(&self.filter).unwrap_or_else(|_| EnvFilter::new("info"))
+    }
+
+    /// Output layer in the configured format, writing to `writer`
+    pub fn fmt_layer<S, W>(&self, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    {
+        match self.format {
+            LogFormat::Pretty => tracing_subscriber::fmt::layer().with_writer(writer).boxed(),
+            LogFormat::Json => tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(writer)
+                .boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        log: LogArgs,
+    }
+
+    fn config(args: &[&str], rust_log: Option<&str>) -> LogConfig {
+        TestCli::try_parse_from([&["test"], args].concat())
+            .unwrap()
+            .log
+            .config(rust_log, "info,serenity=warn")
+    }
+
+    #[test]
+    fn test_flags_resolve_to_filters() {
+        let pretty = |filter: &str| LogConfig {
+            format: LogFormat::Pretty,
+            filter: filter.to_string(),
+        };
+        assert_eq!(config(&[], None), pretty("info,serenity=warn"));
+        assert_eq!(config(&[], Some("  ")), pretty("info,serenity=warn"));
+        assert_eq!(config(&[], Some("trace")), pretty("trace"));
+        assert_eq!(config(&["--verbose"], Some("error")), pretty("debug"));
+        assert_eq!(config(&["-q"], Some("trace")), pretty("warn"));
+        assert_eq!(
+            config(&["--log-format", "json", "--quiet"], None),
+            LogConfig {
+                format: LogFormat::Json,
+                filter: "warn".to_string(),
+            }
+        );
+
+        assert!(TestCli::try_parse_from(["test", "--verbose", "--quiet"]).is_err());
+        assert!(TestCli::try_parse_from(["test", "--log-format", "xml"]).is_err());
+    }
+
+    /// Writer collecting the output in memory
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines_include_span_fields() {
+        let output = Captured::default();
+        let writer = output.clone();
+        let config = config(&["--log-format", "json"], None);
+        let subscriber = tracing_subscriber::registry()
+            .with(config.env_filter())
+            .with(config.fmt_layer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("upload", employee = "hash");
+            let _entered = span.enter();
+            tracing::info!("stored");
+            tracing::debug!("filtered out");
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["fields"]["message"], "stored");
+        assert_eq!(lines[0]["span"]["employee"], "hash");
+        assert_eq!(lines[0]["spans"][0]["name"], "upload");
+    }
+}
@@ -1,5 +1,21 @@
 // This module will contain utility functions
 
+pub mod backoff;
+pub mod discord;
+pub mod embed;
+pub mod event_log;
 pub mod i18n;
+pub mod logging;
+pub mod notifier;
+pub mod pending;
+pub mod rate_limits;
+pub mod redact;
+pub mod render;
 pub mod scheduler;
+#[cfg(feature = "telegram")]
+pub mod telegram;
+// Only the telegram feature sends the rendered messages
+#[cfg_attr(not(feature = "telegram"), allow(dead_code))]
+pub mod telegram_format;
+pub mod telemetry;
 pub mod time;
@@ -0,0 +1,743 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::config::Config;
+use crate::error::{undelivered_error, BotResult};
+use async_trait::async_trait;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateAttachment, CreateEmbed, CreateMessage, EditMessage,
+    MessageId,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// What happens to the previous daily notification when a new one is posted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyReplace {
+    /// Leave it in the channel
+    Keep,
+    /// Delete it before posting the new one
+    Delete,
+    /// Edit it in place so pins and links keep working
+    Edit,
+}
+
+impl DailyReplace {
+    /// Read the mode from the config, editing taking precedence over deleting
+    pub fn from_config(config: &Config) -> Self {
+        if config.edit_previous_daily_notification {
+            DailyReplace::Edit
+        } else if config.delete_previous_daily_notification {
+            DailyReplace::Delete
+        } else {
+            DailyReplace::Keep
+        }
+    }
+}
+
+/// Message content and embed of a notification
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub content: Option<String>,
+    pub embed: CreateEmbed,
+}
+
+/// Posts, edits and deletes notification messages
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Post a message, returning its id
+    async fn send(&self, channel_id: u64, notification: Notification) -> BotResult<u64>;
+
+    /// Replace the content of an earlier message
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        notification: Notification,
+    ) -> BotResult<()>;
+
+    /// Delete an earlier message
+    async fn delete(&self, channel_id: u64, message_id: u64) -> BotResult<()>;
+
+    /// Pin an earlier message to its channel
+    async fn pin(&self, channel_id: u64, message_id: u64) -> BotResult<()>;
+}
+
+/// Notifier talking to Discord
+pub struct DiscordNotifier {
+    http: Arc<serenity::Http>,
+}
+
+impl DiscordNotifier {
+    /// Create a notifier using the context's HTTP client
+    pub fn new(ctx: &serenity::Context) -> Self {
+        Self::from_http(Arc::clone(&ctx.http))
+    }
+
+    /// Create a notifier using a standalone HTTP client, without a gateway connection
+    pub fn from_http(http: Arc<serenity::Http>) -> Self {
+        Self { http }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    async fn send(&self, channel_id: u64, notification: Notification) -> BotResult<u64> {
+        let mut message = CreateMessage::new().embed(notification.embed);
+        if let Some(content) = notification.content {
+            message = message.content(content);
+        }
+
+        let message = ChannelId::new(channel_id)
+            .send_message(&self.http, message)
+            .await?;
+        Ok(message.id.get())
+    }
+
+    async fn edit(
+        &self,
+        channel_id: u64,
+        message_id: u64,
+        notification: Notification,
+    ) -> BotResult<()> {
+        let mut message = EditMessage::new().embed(notification.embed);
+        if let Some(content) = notification.content {
+            message = message.content(content);
+        }
+
+        ChannelId::new(channel_id)
+            .edit_message(&self.http, MessageId::new(message_id), message)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, channel_id: u64, message_id: u64) -> BotResult<()> {
+        ChannelId::new(channel_id)
+            .delete_message(&self.http, MessageId::new(message_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn pin(&self, channel_id: u64, message_id: u64) -> BotResult<()> {
+        ChannelId::new(channel_id)
+            .pin(&self.http, MessageId::new(message_id))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Redis key holding the id of the latest daily notification of a component in a channel
+pub fn last_daily_key(component: &str, channel_id: u64) -> BotResult<Key> {
+    Ok(Key::fixed("notifications:last_daily")
+        .segment(component)?
+        .id(channel_id))
+}
+
+/// Post a daily notification, handling the previous one according to `mode`.
+///
+/// Failing to delete or edit the previous message (already deleted, missing permissions) is
+/// logged and a new message is posted instead. Returns the id to remember for the next day.
+pub async fn replace_daily(
+    notifier: &dyn Notifier,
+    channel_id: u64,
+    previous: Option<u64>,
+    notification: Notification,
+    mode: DailyReplace,
+) -> BotResult<u64> {
+    match (mode, previous) {
+        (DailyReplace::Edit, Some(message_id)) => {
+            match notifier
+                .edit(channel_id, message_id, notification.clone())
+                .await
+            {
+                Ok(()) => return Ok(message_id),
+                Err(e) => warn!(
+                    "Failed to edit previous daily notification {}, posting a new one: {}",
+                    message_id, e
+                ),
+            }
+        }
+        (DailyReplace::Delete, Some(message_id)) => {
+            if let Err(e) = notifier.delete(channel_id, message_id).await {
+                warn!(
+                    "Failed to delete previous daily notification {}: {}",
+                    message_id, e
+                );
+            }
+        }
+        _ => {}
+    }
+
+    notifier.send(channel_id, notification).await
+}
+
+/// Post a component's daily notification, remembering its id in Redis for the next day
+pub async fn send_daily(
+    notifier: &dyn Notifier,
+    redis_handle: &RedisActorHandle,
+    component: &str,
+    channel_id: u64,
+    notification: Notification,
+    mode: DailyReplace,
+) -> BotResult<()> {
+    let key = last_daily_key(component, channel_id)?;
+
+    let previous = if mode == DailyReplace::Keep {
+        None
+    } else {
+        redis_handle
+            .get::<Option<u64>>(&key)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to read previous daily notification id: {}", e);
+                None
+            })
+    };
+
+    let message_id = replace_daily(notifier, channel_id, previous, notification, mode).await?;
+
+    if let Err(e) = redis_handle.set(&key, message_id).await {
+        warn!("Failed to store daily notification id: {}", e);
+    }
+    debug!(
+        "Daily notification of {} is message {}",
+        component, message_id
+    );
+
+    Ok(())
+}
+
+/// Where a scheduled notification goes on Discord and what happens to the previous one
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Component sending the notification, e.g. "work_schedule"
+    pub component: &'static str,
+    pub channel_id: u64,
+    /// How the previous message is replaced, for daily notifications
+    pub daily: Option<DailyReplace>,
+    /// File attached to the Discord message, e.g. the weekly schedule's source image
+    pub attachment: Option<(String, Vec<u8>)>,
+    /// Redis hash the Discord message's id is stored in under its channel id, so it can be
+    /// edited later
+    pub remember: Option<Key>,
+}
+
+impl Delivery {
+    /// A daily notification, replacing the previous one according to `mode`
+    pub fn daily(component: &'static str, channel_id: u64, mode: DailyReplace) -> Self {
+        Self {
+            component,
+            channel_id,
+            daily: Some(mode),
+            attachment: None,
+            remember: None,
+        }
+    }
+
+    /// A notification posted once, e.g. the weekly one
+    pub fn once(component: &'static str, channel_id: u64) -> Self {
+        Self {
+            component,
+            channel_id,
+            daily: None,
+            attachment: None,
+            remember: None,
+        }
+    }
+
+    /// Attach a file, given as its name and bytes, on Discord
+    pub fn attach(mut self, attachment: Option<(String, Vec<u8>)>) -> Self {
+        self.attachment = attachment;
+        self
+    }
+
+    /// Store the posted message's id in the hash at `key`, e.g. to edit the weekly
+    /// notification when the week's schedule changes. Only used for notifications posted once.
+    pub fn remember_in(mut self, key: Key) -> Self {
+        self.remember = Some(key);
+        self
+    }
+}
+
+/// How long remembered message ids are kept
+pub const REMEMBERED_MESSAGE_TTL_SECS: u64 = 14 * 24 * 60 * 60;
+
+/// Destination of scheduled notifications, such as Discord or a Telegram chat.
+///
+/// Interactive command replies don't go through sinks; they stay on Discord.
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Name used in logs, e.g. "discord"
+    fn name(&self) -> &'static str;
+
+    /// Deliver a notification. Sinks outside Discord ignore the channel and the replacing.
+    async fn deliver(&self, delivery: &Delivery, notification: &Notification) -> BotResult<()>;
+}
+
+/// Posts notifications to the delivery's Discord channel
+pub struct DiscordSink {
+    http: Arc<serenity::Http>,
+    redis_handle: RedisActorHandle,
+}
+
+impl DiscordSink {
+    pub fn new(http: Arc<serenity::Http>, redis_handle: RedisActorHandle) -> Self {
+        Self { http, redis_handle }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for DiscordSink {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
+
+    async fn deliver(&self, delivery: &Delivery, notification: &Notification) -> BotResult<()> {
+        let notifier = DiscordNotifier::from_http(Arc::clone(&self.http));
+        if let Some(mode) = delivery.daily {
+            return send_daily(
+                &notifier,
+                &self.redis_handle,
+                delivery.component,
+                delivery.channel_id,
+                notification.clone(),
+                mode,
+            )
+            .await;
+        }
+
+        let message_id = match &delivery.attachment {
+            None => {
+                notifier
+                    .send(delivery.channel_id, notification.clone())
+                    .await?
+            }
+            Some((file_name, data)) => {
+                let mut message = CreateMessage::new()
+                    .embed(notification.embed.clone())
+                    .add_file(CreateAttachment::bytes(data.clone(), file_name.clone()));
+                if let Some(content) = &notification.content {
+                    message = message.content(content);
+                }
+                ChannelId::new(delivery.channel_id)
+                    .send_message(&self.http, message)
+                    .await?
+                    .id
+                    .get()
+            }
+        };
+
+        if let Some(key) = &delivery.remember {
+            // The message is out either way, it just won't be kept up to date
+            if let Err(e) =
+                remember_message(&self.redis_handle, key, delivery.channel_id, message_id).await
+            {
+                warn!(
+                    "Failed to store the id of {} notification {}: {}",
+                    delivery.component, message_id, e
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Store a message's id in the hash at `key` under its channel id
+pub async fn remember_message(
+    redis_handle: &RedisActorHandle,
+    key: &Key,
+    channel_id: u64,
+    message_id: u64,
+) -> BotResult<()> {
+    redis_handle
+        .hset(key, &channel_id.to_string(), message_id)
+        .await?;
+    redis_handle.expire(key, REMEMBERED_MESSAGE_TTL_SECS).await
+}
+
+/// The sinks a delivery goes to, by name: all of them, or the ones a notification that
+/// failed on some sinks still has to reach
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sinks(Option<Vec<String>>);
+
+impl Sinks {
+    /// Every configured sink
+    pub fn all() -> Self {
+        Self(None)
+    }
+
+    /// Only the named sinks
+    pub fn only(names: &[String]) -> Self {
+        Self(Some(names.to_vec()))
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Whether the sink with this name gets the delivery
+    pub fn includes(&self, name: &str) -> bool {
+        self.0
+            .as_ref()
+            .is_none_or(|names| names.iter().any(|n| n == name))
+    }
+}
+
+/// Delivers to several sinks, each one independently of the others.
+///
+/// A delivery fails when any sink fails, with an error naming the failed sinks, so a retry can
+/// go to them alone instead of repeating the notification where it already arrived.
+pub struct FanOutSink {
+    sinks: Vec<Box<dyn NotificationSink>>,
+}
+
+impl FanOutSink {
+    /// Fan out to `primary` alone, until more sinks are added with `with`
+    pub fn new(primary: Box<dyn NotificationSink>) -> Self {
+        Self {
+            sinks: vec![primary],
+        }
+    }
+
+    #[cfg_attr(not(feature = "telegram"), allow(dead_code))]
+    pub fn with(mut self, sink: Box<dyn NotificationSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Keep only the sinks `sinks` includes
+    pub fn only(mut self, sinks: &Sinks) -> Self {
+        self.sinks.retain(|sink| sinks.includes(sink.name()));
+        self
+    }
+
+    /// Deliver to every sink, returning each sink's name and result in order
+    pub async fn deliver_all(
+        &self,
+        delivery: &Delivery,
+        notification: &Notification,
+    ) -> Vec<(&'static str, BotResult<()>)> {
+        let mut results = Vec::with_capacity(self.sinks.len());
+        for sink in &self.sinks {
+            results.push((sink.name(), sink.deliver(delivery, notification).await));
+        }
+        results
+    }
+}
+
+#[async_trait]
+impl NotificationSink for FanOutSink {
+    fn name(&self) -> &'static str {
+        "fan-out"
+    }
+
+    async fn deliver(&self, delivery: &Delivery, notification: &Notification) -> BotResult<()> {
+        let mut failed = Vec::new();
+        let mut reasons = Vec::new();
+        for (name, result) in self.deliver_all(delivery, notification).await {
+            if let Err(e) = result {
+                warn!(
+                    "Failed to deliver {} notification to {}: {}",
+                    delivery.component, name, e
+                );
+                failed.push(name.to_string());
+                reasons.push(format!("{name}: {e}"));
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(undelivered_error(failed, &reasons.join("; ")))
+        }
+    }
+}
+
+/// Sinks for scheduled notifications: Discord, plus Telegram when it's configured, limited
+/// to `only`
+pub fn notification_sinks(
+    http: &Arc<serenity::Http>,
+    redis_handle: &RedisActorHandle,
+    config: &Config,
+    only: &Sinks,
+) -> FanOutSink {
+    let sinks = FanOutSink::new(Box::new(DiscordSink::new(
+        Arc::clone(http),
+        redis_handle.clone(),
+    )));
+
+    #[cfg(feature = "telegram")]
+    if let Some(telegram) = crate::utils::telegram::TelegramSink::from_config(config) {
+        return sinks.with(Box::new(telegram)).only(only);
+    }
+    #[cfg(not(feature = "telegram"))]
+    if config.telegram_bot_token.is_some() {
+        warn!("TELEGRAM_BOT_TOKEN is set, but this build has no telegram feature");
+    }
+
+    sinks.only(only)
+}
+
+/// Notifier double shared by the notification tests
+#[cfg(test)]
+pub(crate) mod recording {
+    use super::*;
+    use crate::error::other_error;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Call {
+        Send(u64),
+        /// A send rejected by `failing_sends`
+        FailedSend,
+        Edit(u64),
+        Delete(u64),
+        Pin(u64),
+    }
+
+    /// Records calls instead of talking to Discord, handing out increasing message ids
+    #[derive(Default)]
+    pub struct RecordingNotifier {
+        calls: Mutex<Vec<Call>>,
+        next_id: Mutex<u64>,
+        missing: Vec<u64>,
+        failing_sends: Mutex<u32>,
+    }
+
+    impl RecordingNotifier {
+        pub fn with_missing(missing: &[u64]) -> Self {
+            Self {
+                missing: missing.to_vec(),
+                ..Default::default()
+            }
+        }
+
+        pub fn calls(&self) -> Vec<Call> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        /// Fail the next `count` sends as if Discord was unreachable
+        pub fn failing_sends(count: u32) -> Self {
+            Self {
+                failing_sends: Mutex::new(count),
+                ..Default::default()
+            }
+        }
+
+        fn check(&self, message_id: u64) -> BotResult<()> {
+            if self.missing.contains(&message_id) {
+                Err(other_error("Unknown Message"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn send(&self, _channel_id: u64, _notification: Notification) -> BotResult<u64> {
+            let mut failing = self.failing_sends.lock().unwrap();
+            if *failing > 0 {
+                *failing -= 1;
+                self.calls.lock().unwrap().push(Call::FailedSend);
+                return Err(other_error("Service Unavailable"));
+            }
+            drop(failing);
+
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            self.calls.lock().unwrap().push(Call::Send(*next_id));
+            Ok(*next_id)
+        }
+
+        async fn edit(
+            &self,
+            _channel_id: u64,
+            message_id: u64,
+            _notification: Notification,
+        ) -> BotResult<()> {
+            self.calls.lock().unwrap().push(Call::Edit(message_id));
+            self.check(message_id)
+        }
+
+        async fn delete(&self, _channel_id: u64, message_id: u64) -> BotResult<()> {
+            self.calls.lock().unwrap().push(Call::Delete(message_id));
+            self.check(message_id)
+        }
+
+        async fn pin(&self, _channel_id: u64, message_id: u64) -> BotResult<()> {
+            self.calls.lock().unwrap().push(Call::Pin(message_id));
+            self.check(message_id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::recording::{Call, RecordingNotifier};
+    use super::*;
+
+    fn notification() -> Notification {
+        Notification {
+            content: None,
+            embed: CreateEmbed::new().title("Today"),
+        }
+    }
+
+    /// Post on three consecutive days, feeding each returned id into the next day
+    async fn post_days(notifier: &RecordingNotifier, mode: DailyReplace) -> Vec<u64> {
+        let mut previous = None;
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let id = replace_daily(notifier, 1, previous, notification(), mode)
+                .await
+                .unwrap();
+            ids.push(id);
+            previous = Some(id);
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_delete_mode_removes_previous_message() {
+        let notifier = RecordingNotifier::default();
+        assert_eq!(post_days(&notifier, DailyReplace::Delete).await, [1, 2, 3]);
+        assert_eq!(
+            notifier.calls(),
+            [
+                Call::Send(1),
+                Call::Delete(1),
+                Call::Send(2),
+                Call::Delete(2),
+                Call::Send(3)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_edit_mode_keeps_message_id() {
+        let notifier = RecordingNotifier::default();
+        assert_eq!(post_days(&notifier, DailyReplace::Edit).await, [1, 1, 1]);
+        assert_eq!(
+            notifier.calls(),
+            [Call::Send(1), Call::Edit(1), Call::Edit(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_previous_message_is_ignored() {
+        // Someone deleted yesterday's message by hand
+        let notifier = RecordingNotifier::with_missing(&[7]);
+
+        let id = replace_daily(&notifier, 1, Some(7), notification(), DailyReplace::Delete)
+            .await
+            .unwrap();
+        assert_eq!(id, 1);
+
+        let id = replace_daily(&notifier, 1, Some(7), notification(), DailyReplace::Edit)
+            .await
+            .unwrap();
+        assert_eq!(id, 2);
+
+        assert_eq!(
+            notifier.calls(),
+            [Call::Delete(7), Call::Send(1), Call::Edit(7), Call::Send(2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keep_mode_only_posts() {
+        let notifier = RecordingNotifier::default();
+        assert_eq!(post_days(&notifier, DailyReplace::Keep).await, [1, 2, 3]);
+        assert_eq!(
+            notifier.calls(),
+            [Call::Send(1), Call::Send(2), Call::Send(3)]
+        );
+        assert_eq!(
+            last_daily_key("work_schedule", 42).unwrap().as_str(),
+            "notifications:last_daily:work_schedule:42"
+        );
+    }
+
+    /// Sink that records deliveries, failing every one when `fail` is set
+    struct TestSink {
+        name: &'static str,
+        fail: bool,
+        delivered: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl NotificationSink for TestSink {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn deliver(
+            &self,
+            _delivery: &Delivery,
+            _notification: &Notification,
+        ) -> BotResult<()> {
+            self.delivered.lock().unwrap().push(self.name);
+            if self.fail {
+                Err(crate::error::other_error("Bad Gateway"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn fan_out(failing: &[&'static str]) -> (FanOutSink, Arc<std::sync::Mutex<Vec<&'static str>>>) {
+        let delivered = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = |name| {
+            Box::new(TestSink {
+                name,
+                fail: failing.contains(&name),
+                delivered: Arc::clone(&delivered),
+            })
+        };
+        let fan_out = FanOutSink::new(sink("discord"))
+            .with(sink("telegram"))
+            .with(sink("matrix"));
+        (fan_out, delivered)
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_reports_failures_per_sink() {
+        let (sinks, delivered) = fan_out(&["telegram"]);
+        let delivery = Delivery::once("work_schedule", 1);
+
+        let results = sinks.deliver_all(&delivery, &notification()).await;
+        let outcome: Vec<_> = results
+            .iter()
+            .map(|(name, result)| (*name, result.is_ok()))
+            .collect();
+        assert_eq!(
+            outcome,
+            [("discord", true), ("telegram", false), ("matrix", true)]
+        );
+        // A failing sink doesn't stop the ones after it
+        assert_eq!(
+            *delivered.lock().unwrap(),
+            ["discord", "telegram", "matrix"]
+        );
+
+        // Any sink's failure fails the delivery, naming the sinks to retry
+        let error = sinks.deliver(&delivery, &notification()).await.unwrap_err();
+        assert_eq!(
+            error.undelivered_sinks(),
+            Some(&["telegram".to_string()][..])
+        );
+
+        // The retry only goes to those
+        let (sinks, delivered) = fan_out(&[]);
+        let retry = sinks.only(&Sinks::only(&["telegram".to_string()]));
+        retry.deliver(&delivery, &notification()).await.unwrap();
+        assert_eq!(*delivered.lock().unwrap(), ["telegram"]);
+
+        let (sinks, _) = fan_out(&["discord", "matrix"]);
+        let error = sinks.deliver(&delivery, &notification()).await.unwrap_err();
+        assert_eq!(
+            error.undelivered_sinks(),
+            Some(&["discord".to_string(), "matrix".to_string()][..])
+        );
+    }
+}
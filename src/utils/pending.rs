@@ -0,0 +1,317 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::error::{other_error, BotResult};
+use crate::utils::notifier::Sinks;
+use crate::utils::scheduler::NotificationType;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// Attempts made when a scheduled notification is due
+pub const SEND_ATTEMPTS: u32 = 3;
+
+/// Pause between those attempts
+pub const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long a parked notification keeps being retried, in seconds
+pub const CATCH_UP_WINDOW_SECS: i64 = 6 * 60 * 60;
+
+/// How often the scheduler loops wake up while notifications are parked
+pub const PENDING_RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A scheduled notification that couldn't be delivered yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingNotification {
+    /// Component that owns the notification
+    pub component: String,
+    #[serde(rename = "type")]
+    pub notification_type: NotificationType,
+    /// Day of a daily notification, or first day of the week of a weekly one (YYYY-MM-DD)
+    pub date: String,
+    pub channel_id: u64,
+    /// Unix timestamp of the first failed delivery
+    pub parked_at: i64,
+    /// Sinks that still have to get it, when it reached the others
+    #[serde(default, skip_serializing_if = "Sinks::is_all")]
+    pub sinks: Sinks,
+}
+
+impl PendingNotification {
+    /// Redis hash field identifying the notification within its component
+    pub fn field(&self) -> String {
+        let kind = match self.notification_type {
+            NotificationType::Daily => "daily",
+            NotificationType::Weekly => "weekly",
+        };
+        format!("{kind}:{}:{}", self.date, self.channel_id)
+    }
+
+    /// Check whether the notification is still worth sending: it belongs to the current day or
+    /// week, which is what the handlers build, and the catch-up window hasn't run out
+    pub fn is_current(&self, today: &str, week_start_date: &str, now: i64) -> bool {
+        let period = match self.notification_type {
+            NotificationType::Daily => today,
+            NotificationType::Weekly => week_start_date,
+        };
+        self.date == period && now - self.parked_at <= CATCH_UP_WINDOW_SECS
+    }
+}
+
+/// Redis hash holding a component's parked notifications
+pub fn pending_key(component: &str) -> BotResult<Key> {
+    Key::fixed("notifications:pending").segment(component)
+}
+
+/// Run `send` until it succeeds, at most `attempts` times with `delay` in between.
+///
+/// Each attempt is given the sinks to deliver to. When an attempt reached some sinks but not
+/// others, `sinks` is narrowed to the failed ones, so later attempts, and a parked retry, don't
+/// repeat the notification where it already arrived.
+pub async fn send_with_retry<F, Fut>(
+    mut send: F,
+    sinks: &mut Sinks,
+    attempts: u32,
+    delay: Duration,
+) -> BotResult<()>
+where
+    F: FnMut(Sinks) -> Fut,
+    Fut: Future<Output = BotResult<()>>,
+{
+    let mut attempt = 1;
+    loop {
+        match send(sinks.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                if let Some(failed) = e.undelivered_sinks() {
+                    *sinks = Sinks::only(failed);
+                }
+                if attempt >= attempts {
+                    return Err(e);
+                }
+                warn!(
+                    "Notification attempt {}/{} failed, retrying in {:?}: {}",
+                    attempt, attempts, delay, e
+                );
+                attempt += 1;
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Remember a notification for the scheduler to retry
+pub async fn park_notification(
+    redis_handle: &RedisActorHandle,
+    pending: &PendingNotification,
+) -> BotResult<()> {
+    let json = serde_json::to_string(pending)
+        .map_err(|e| other_error(&format!("Failed to serialize pending notification: {e}")))?;
+    redis_handle
+        .hset(&pending_key(&pending.component)?, &pending.field(), json)
+        .await
+}
+
+/// Load a component's parked notifications, skipping unreadable records
+pub async fn load_pending(
+    redis_handle: &RedisActorHandle,
+    component: &str,
+) -> BotResult<Vec<PendingNotification>> {
+    let stored: Vec<String> = redis_handle.hvals(&pending_key(component)?).await?;
+
+    Ok(stored
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_str(json)
+                .map_err(|e| warn!("Ignoring invalid pending notification: {}", e))
+                .ok()
+        })
+        .collect())
+}
+
+/// Forget a parked notification once it's delivered or stale
+pub async fn remove_pending(
+    redis_handle: &RedisActorHandle,
+    pending: &PendingNotification,
+) -> BotResult<()> {
+    redis_handle
+        .hdel(&pending_key(&pending.component)?, &pending.field())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::undelivered_error;
+    use crate::utils::notifier::recording::{Call, RecordingNotifier};
+    use crate::utils::notifier::{Notification, Notifier};
+    use crate::utils::scheduler::{
+        deliver_notification, retry_pending_notifications, NotificationHandler, SharedContext,
+    };
+    use crate::utils::time::WeekStart;
+    use poise::serenity_prelude::{self as serenity, CreateEmbed};
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+
+    fn pending(notification_type: NotificationType, date: &str) -> PendingNotification {
+        PendingNotification {
+            component: "work_schedule".to_string(),
+            notification_type,
+            date: date.to_string(),
+            channel_id: 42,
+            parked_at: 1_000_000,
+            sinks: Sinks::all(),
+        }
+    }
+
+    async fn send(notifier: &RecordingNotifier) -> BotResult<()> {
+        let notification = Notification {
+            content: None,
+            embed: CreateEmbed::new().title("Today"),
+        };
+        notifier.send(42, notification).await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_pending_notification_lifecycle() {
+        // Discord is down for the whole first round of attempts
+        let notifier = RecordingNotifier::failing_sends(SEND_ATTEMPTS);
+        let result = send_with_retry(
+            |_| send(&notifier),
+            &mut Sinks::all(),
+            SEND_ATTEMPTS,
+            Duration::ZERO,
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(notifier.calls(), [Call::FailedSend; 3]);
+
+        // The record survives the trip through Redis
+        let parked = pending(NotificationType::Daily, "2025-01-06");
+        let json = serde_json::to_string(&parked).unwrap();
+        let parked: PendingNotification = serde_json::from_str(&json).unwrap();
+        assert_eq!(parked.field(), "daily:2025-01-06:42");
+
+        // A later loop iteration retries it and it goes through
+        assert!(parked.is_current("2025-01-06", "2025-01-06", parked.parked_at + 300));
+        send_with_retry(|_| send(&notifier), &mut Sinks::all(), 1, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(notifier.calls().last(), Some(&Call::Send(1)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_after_first_success() {
+        let notifier = RecordingNotifier::failing_sends(1);
+        send_with_retry(
+            |_| send(&notifier),
+            &mut Sinks::all(),
+            SEND_ATTEMPTS,
+            Duration::ZERO,
+        )
+        .await
+        .unwrap();
+        assert_eq!(notifier.calls(), [Call::FailedSend, Call::Send(1)]);
+    }
+
+    /// Handler whose daily sends fail on the sinks scripted for them, in order, and succeed
+    /// once the script runs out
+    #[derive(Default)]
+    struct ScriptedHandler {
+        failures: Mutex<VecDeque<&'static str>>,
+        sent_to: Mutex<Vec<Sinks>>,
+    }
+
+    impl NotificationHandler for ScriptedHandler {
+        fn send_daily_notification<'a>(
+            &'a self,
+            _http: &'a Arc<serenity::Http>,
+            _channel_id: u64,
+            sinks: Sinks,
+        ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+            self.sent_to.lock().unwrap().push(sinks);
+            let failure = self.failures.lock().unwrap().pop_front();
+            Box::pin(async move {
+                match failure {
+                    Some(sink) => Err(undelivered_error(vec![sink.to_string()], "unreachable")),
+                    None => Ok(()),
+                }
+            })
+        }
+
+        fn send_weekly_notification<'a>(
+            &'a self,
+            _http: &'a Arc<serenity::Http>,
+            _channel_id: u64,
+            _sinks: Sinks,
+        ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_undelivered_notification_is_parked_and_retried() {
+        let redis = RedisActorHandle::fake();
+        let ctx = SharedContext::new(Arc::new(Arc::new(serenity::Http::new(""))));
+        let component = "pending_round_trip";
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+        // Discord takes the first attempt, Telegram stays down for all of them
+        let handler = ScriptedHandler::default();
+        handler
+            .failures
+            .lock()
+            .unwrap()
+            .extend(["telegram"; SEND_ATTEMPTS as usize]);
+        let result = deliver_notification(
+            &ctx,
+            &handler,
+            &redis,
+            component,
+            NotificationType::Daily,
+            &today,
+            42,
+        )
+        .await;
+        assert_eq!(
+            result.unwrap_err().undelivered_sinks(),
+            Some(&["telegram".to_string()][..])
+        );
+
+        // It's parked for Telegram alone
+        let telegram = Sinks::only(&["telegram".to_string()]);
+        let parked = load_pending(&redis, component).await.unwrap();
+        assert_eq!(parked.len(), 1);
+        assert_eq!(parked[0].field(), format!("daily:{today}:42"));
+        assert_eq!(parked[0].sinks, telegram);
+
+        // The scheduler loop's retry goes through and clears it
+        let waiting =
+            retry_pending_notifications(&ctx, &handler, &redis, component, WeekStart::Monday).await;
+        assert!(!waiting);
+        assert!(load_pending(&redis, component).await.unwrap().is_empty());
+        assert_eq!(
+            *handler.sent_to.lock().unwrap(),
+            [
+                Sinks::all(),
+                telegram.clone(),
+                telegram.clone(),
+                telegram.clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stale_notifications_expire() {
+        let daily = pending(NotificationType::Daily, "2025-01-06");
+        let window_end = daily.parked_at + CATCH_UP_WINDOW_SECS;
+        assert!(daily.is_current("2025-01-06", "2025-01-06", window_end));
+        assert!(!daily.is_current("2025-01-06", "2025-01-06", window_end + 1));
+        // The handlers would build the next day's notification by now
+        assert!(!daily.is_current("2025-01-07", "2025-01-06", daily.parked_at));
+
+        let weekly = pending(NotificationType::Weekly, "2025-01-06");
+        assert!(weekly.is_current("2025-01-08", "2025-01-06", weekly.parked_at));
+        assert!(!weekly.is_current("2025-01-13", "2025-01-13", weekly.parked_at));
+    }
+}
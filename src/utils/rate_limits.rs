@@ -0,0 +1,313 @@
+use crate::components::redis_service::{Key, RedisActorHandle};
+use serde::{Deserialize, Serialize};
+use std::env;
+use tracing::warn;
+
+/// A sliding window limit: at most `max_calls` within `window_secs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub max_calls: u32,
+    pub window_secs: u64,
+}
+
+impl RateLimit {
+    /// Create a new limit
+    pub const fn new(max_calls: u32, window_secs: u64) -> Self {
+        Self {
+            max_calls,
+            window_secs,
+        }
+    }
+
+    /// Parse a limit in `calls/seconds` format, e.g. `5/60`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (calls, secs) = value.trim().split_once('/')?;
+        let max_calls = calls.trim().parse().ok()?;
+        let window_secs = secs.trim().parse().ok().filter(|secs| *secs > 0)?;
+        Some(Self::new(max_calls, window_secs))
+    }
+}
+
+/// Categories of commands sharing a rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandCategory {
+    /// Commands reading work schedules from Redis
+    Schedule,
+    /// Commands hitting the Google Calendar API
+    Calendar,
+}
+
+impl CommandCategory {
+    fn key(&self) -> &'static str {
+        match self {
+            CommandCategory::Schedule => "schedule",
+            CommandCategory::Calendar => "calendar",
+        }
+    }
+}
+
+/// Configured per-user and per-guild limits for each command category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    pub schedule_per_user: RateLimit,
+    pub schedule_per_guild: RateLimit,
+    pub calendar_per_user: RateLimit,
+    pub calendar_per_guild: RateLimit,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            schedule_per_user: RateLimit::new(5, 60),
+            schedule_per_guild: RateLimit::new(30, 60),
+            calendar_per_user: RateLimit::new(2, 60),
+            calendar_per_guild: RateLimit::new(10, 60),
+        }
+    }
+}
+
+impl RateLimits {
+    /// Load limits from the environment, keeping defaults for unset or invalid values
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: RateLimit| match env::var(name) {
+            Ok(value) => RateLimit::parse(&value).unwrap_or_else(|| {
+                warn!("Invalid {} value '{}', using default", name, value);
+                default
+            }),
+            Err(_) => default,
+        };
+
+        Self {
+            schedule_per_user: read("RATE_LIMIT_SCHEDULE_USER", defaults.schedule_per_user),
+            schedule_per_guild: read("RATE_LIMIT_SCHEDULE_GUILD", defaults.schedule_per_guild),
+            calendar_per_user: read("RATE_LIMIT_CALENDAR_USER", defaults.calendar_per_user),
+            calendar_per_guild: read("RATE_LIMIT_CALENDAR_GUILD", defaults.calendar_per_guild),
+        }
+    }
+
+    /// Get the (per user, per guild) limits for a category
+    pub fn for_category(&self, category: CommandCategory) -> (RateLimit, RateLimit) {
+        match category {
+            CommandCategory::Schedule => (self.schedule_per_user, self.schedule_per_guild),
+            CommandCategory::Calendar => (self.calendar_per_user, self.calendar_per_guild),
+        }
+    }
+}
+
+/// Outcome of a rate limit check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allowed,
+    Limited { retry_after_secs: u64 },
+}
+
+/// Redis key for a rate limit bucket
+fn bucket_key(category: CommandCategory, scope: &'static str, id: u64) -> Key {
+    Key::fixed("rate_limit")
+        .name(category.key())
+        .name(scope)
+        .id(id)
+}
+
+/// Check the per-user and per-guild limits for a command invocation. The call is only recorded
+/// once every limit allows it, so a command refused by one limit doesn't count towards another.
+/// The windows are checked and the call recorded in one Redis script, so parallel invocations
+/// can't slip past a limit together.
+///
+/// Redis failures fail open so an outage doesn't lock everyone out of commands.
+pub async fn check_rate_limit(
+    redis_handle: &RedisActorHandle,
+    limits: &RateLimits,
+    category: CommandCategory,
+    user_id: u64,
+    guild_id: Option<u64>,
+) -> RateLimitDecision {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    check_rate_limit_at(redis_handle, limits, category, user_id, guild_id, now_ms).await
+}
+
+async fn check_rate_limit_at(
+    redis_handle: &RedisActorHandle,
+    limits: &RateLimits,
+    category: CommandCategory,
+    user_id: u64,
+    guild_id: Option<u64>,
+    now_ms: i64,
+) -> RateLimitDecision {
+    let (user_limit, guild_limit) = limits.for_category(category);
+    let mut windows = vec![(
+        bucket_key(category, "user", user_id),
+        user_limit.window_secs,
+        user_limit.max_calls,
+    )];
+    if let Some(guild_id) = guild_id {
+        windows.push((
+            bucket_key(category, "guild", guild_id),
+            guild_limit.window_secs,
+            guild_limit.max_calls,
+        ));
+    }
+
+    let member = format!("{now_ms}-{}", uuid::Uuid::new_v4());
+    match redis_handle
+        .zadd_within_limits(&windows, now_ms, &member)
+        .await
+    {
+        Ok(None) => RateLimitDecision::Allowed,
+        Ok(Some(retry_after_ms)) => RateLimitDecision::Limited {
+            retry_after_secs: retry_after_ms.div_ceil(1000).max(1),
+        },
+        Err(e) => {
+            warn!(
+                "Rate limit check failed for user {}, allowing: {}",
+                user_id, e
+            );
+            RateLimitDecision::Allowed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimits = RateLimits {
+        schedule_per_user: RateLimit::new(2, 60),
+        schedule_per_guild: RateLimit::new(30, 60),
+        calendar_per_user: RateLimit::new(2, 60),
+        calendar_per_guild: RateLimit::new(10, 60),
+    };
+
+    async fn check_at(redis_handle: &RedisActorHandle, now_ms: i64) -> RateLimitDecision {
+        check_rate_limit_at(
+            redis_handle,
+            &LIMIT,
+            CommandCategory::Schedule,
+            1,
+            None,
+            now_ms,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_allows_until_limit_reached() {
+        let redis_handle = RedisActorHandle::fake();
+        let now = 1_000_000;
+        assert_eq!(
+            check_at(&redis_handle, now - 10_000).await,
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            check_at(&redis_handle, now - 5_000).await,
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            check_at(&redis_handle, now).await,
+            RateLimitDecision::Limited {
+                retry_after_secs: 50
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_old_calls_slide_out_of_window() {
+        let now = 1_000_000;
+        // The first call is exactly one window old and no longer counts
+        let redis_handle = RedisActorHandle::fake();
+        check_at(&redis_handle, now - 60_000).await;
+        check_at(&redis_handle, now - 1_000).await;
+        assert_eq!(
+            check_at(&redis_handle, now).await,
+            RateLimitDecision::Allowed
+        );
+
+        let redis_handle = RedisActorHandle::fake();
+        check_at(&redis_handle, now - 59_500).await;
+        check_at(&redis_handle, now - 1_000).await;
+        assert_eq!(
+            check_at(&redis_handle, now).await,
+            RateLimitDecision::Limited {
+                retry_after_secs: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(RateLimit::parse("5/60"), Some(RateLimit::new(5, 60)));
+        assert_eq!(RateLimit::parse(" 2 / 30 "), Some(RateLimit::new(2, 30)));
+        assert_eq!(RateLimit::parse("5"), None);
+        assert_eq!(RateLimit::parse("5/0"), None);
+        assert_eq!(RateLimit::parse("x/60"), None);
+    }
+
+    async fn user_calls(redis_handle: &RedisActorHandle, user_id: u64) -> usize {
+        let key = bucket_key(CommandCategory::Schedule, "user", user_id);
+        let calls: Vec<(String, i64)> = redis_handle.zrange_withscores(&key).await.unwrap();
+        calls.len()
+    }
+
+    #[tokio::test]
+    async fn test_guild_denial_leaves_user_uncharged() {
+        let redis_handle = RedisActorHandle::fake();
+        let limits = RateLimits {
+            schedule_per_user: RateLimit::new(5, 60),
+            schedule_per_guild: RateLimit::new(1, 60),
+            ..RateLimits::default()
+        };
+        let check = |user_id, guild_id| {
+            check_rate_limit(
+                &redis_handle,
+                &limits,
+                CommandCategory::Schedule,
+                user_id,
+                guild_id,
+            )
+        };
+
+        assert_eq!(check(1, Some(10)).await, RateLimitDecision::Allowed);
+        assert_eq!(user_calls(&redis_handle, 1).await, 1);
+
+        // The guild is out of calls, so the second user isn't charged for the refused command
+        assert!(matches!(
+            check(2, Some(10)).await,
+            RateLimitDecision::Limited { .. }
+        ));
+        assert_eq!(user_calls(&redis_handle, 2).await, 0);
+
+        // Outside the guild only their own limit applies
+        assert_eq!(check(2, None).await, RateLimitDecision::Allowed);
+        assert_eq!(user_calls(&redis_handle, 2).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_checks_allow_exactly_the_limit() {
+        let redis_handle = RedisActorHandle::fake();
+        let limits = RateLimits::default();
+        let max_calls = limits.schedule_per_user.max_calls as usize;
+
+        let checks = (0..max_calls * 4).map(|_| {
+            let (redis_handle, limits) = (redis_handle.clone(), limits.clone());
+            tokio::spawn(async move {
+                check_rate_limit(
+                    &redis_handle,
+                    &limits,
+                    CommandCategory::Schedule,
+                    1,
+                    Some(10),
+                )
+                .await
+            })
+        });
+        let decisions = futures::future::join_all(checks).await;
+
+        let allowed = decisions
+            .into_iter()
+            .filter(|decision| *decision.as_ref().unwrap() == RateLimitDecision::Allowed)
+            .count();
+        assert_eq!(allowed, max_calls);
+        assert_eq!(user_calls(&redis_handle, 1).await, max_calls);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::utils::telemetry::employee_hash;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether logs and error messages redact employee names, set once from the config at startup
+static LOG_REDACTION: AtomicBool = AtomicBool::new(false);
+
+/// Turn log redaction on or off
+pub fn set_log_redaction(enabled: bool) {
+    LOG_REDACTION.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether log redaction is on
+pub fn log_redaction() -> bool {
+    LOG_REDACTION.load(Ordering::Relaxed)
+}
+
+/// Pseudonym for an employee name: the initial and a hash suffix, e.g. `A-3f9c1e`.
+///
+/// The suffix follows the canonical name, so every spelling of a name gets the same pseudonym
+/// and log lines about one employee can still be correlated.
+pub fn redact_name(name: &str) -> String {
+    let initial = name
+        .trim()
+        .chars()
+        .next()
+        .map(|c| c.to_uppercase().to_string())
+        .unwrap_or_else(|| "?".to_string());
+    format!("{initial}-{}", &employee_hash(name)[..6])
+}
+
+/// Schedule contents for logging, replaced by their size when redaction is on
+pub fn redact_contents(text: &str) -> String {
+    if log_redaction() {
+        format!("<{} bytes redacted>", text.len())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Displays an employee name, or its pseudonym when log redaction is on.
+///
+/// Wrap names in log statements and error messages with this; data sent to users keeps the
+/// plain name.
+pub struct Redacted<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if log_redaction() {
+            f.write_str(&redact_name(&self.0.to_string()))
+        } else {
+            self.0.fmt(f)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_name_is_stable_per_name() {
+        let anna = redact_name("Anna Mäkinen");
+        assert_eq!(anna, redact_name("Anna Mäkinen"));
+        assert_eq!(anna, redact_name("anna  makinen"));
+        assert_ne!(anna, redact_name("Anna Virtanen"));
+        assert!(anna.starts_with("A-"), "{anna}");
+        assert!(!anna.contains("Anna"), "{anna}");
+        assert_eq!(redact_name(""), format!("?-{}", &employee_hash("")[..6]));
+    }
+}
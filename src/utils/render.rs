@@ -0,0 +1,260 @@
+use crate::theme::{Theme, ThemeColor};
+use crate::utils::embed::{limit_fields, split_field, split_lines};
+use crate::utils::i18n::{weekday_name, weekday_short_name};
+use chrono::{Datelike, NaiveDate};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+
+/// Maximum length of a Discord message
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// A line of a reply, optionally about a specific day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewLine {
+    pub day: Option<NaiveDate>,
+    /// May contain bold markdown, which the plain text renderer drops
+    pub text: String,
+}
+
+impl ViewLine {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            day: None,
+            text: text.into(),
+        }
+    }
+
+    pub fn on(day: NaiveDate, text: impl Into<String>) -> Self {
+        Self {
+            day: Some(day),
+            text: text.into(),
+        }
+    }
+}
+
+/// A named group of lines, e.g. one employee's week
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewField {
+    pub name: String,
+    pub lines: Vec<ViewLine>,
+    /// Whether the embed shows the lines as a bulleted list
+    pub bulleted: bool,
+}
+
+/// The content of a command reply, rendered either as an embed or as plain text messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    pub title: String,
+    pub description: Option<String>,
+    pub fields: Vec<ViewField>,
+    pub footer: Option<String>,
+    /// Only shown in the embed
+    pub image: Option<String>,
+    /// Only shown in the embed
+    pub thumbnail: Option<String>,
+    pub color: u32,
+    /// Theme color replacing `color` in guilds that set one
+    pub role: Option<ThemeColor>,
+}
+
+impl View {
+    pub fn new(title: impl Into<String>, color: u32) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            fields: Vec::new(),
+            footer: None,
+            image: None,
+            thumbnail: None,
+            color,
+            role: None,
+        }
+    }
+
+    /// A view in a theme color, the same as the command embed helpers use
+    pub fn themed(title: impl Into<String>, role: ThemeColor) -> Self {
+        Self {
+            role: Some(role),
+            ..Self::new(title, role.default_value())
+        }
+    }
+
+    pub fn success(title: &str, description: &str) -> Self {
+        Self::themed(title, ThemeColor::Success).description(description)
+    }
+
+    pub fn info(title: &str, description: &str) -> Self {
+        Self::themed(title, ThemeColor::Primary).description(description)
+    }
+
+    pub fn warning(title: &str, description: &str) -> Self {
+        Self::themed(title, ThemeColor::Warning).description(description)
+    }
+
+    pub fn error(title: &str, description: &str) -> Self {
+        Self::themed(title, ThemeColor::Error).description(description)
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn field(mut self, name: impl Into<String>, lines: Vec<ViewLine>) -> Self {
+        self.fields.push(ViewField {
+            name: name.into(),
+            lines,
+            bulleted: false,
+        });
+        self
+    }
+
+    pub fn bulleted_field(mut self, name: impl Into<String>, lines: Vec<ViewLine>) -> Self {
+        self.fields.push(ViewField {
+            name: name.into(),
+            lines,
+            bulleted: true,
+        });
+        self
+    }
+
+    pub fn footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = Some(footer.into());
+        self
+    }
+
+    /// Add a note after the footer, or make it the footer when there's none
+    pub fn footer_note(mut self, note: &str) -> Self {
+        self.footer = Some(match self.footer.take() {
+            Some(footer) => format!("{footer} · {note}"),
+            None => note.to_string(),
+        });
+        self
+    }
+
+    pub fn image(mut self, url: impl Into<String>) -> Self {
+        self.image = Some(url.into());
+        self
+    }
+
+    pub fn thumbnail(mut self, url: impl Into<String>) -> Self {
+        self.thumbnail = Some(url.into());
+        self
+    }
+
+    /// Render as an embed in a guild's theme
+    pub fn to_themed_embed(&self, theme: &Theme) -> CreateEmbed {
+        let color = match self.role {
+            Some(role) => theme.color_or(role, self.color),
+            None => self.color,
+        };
+        theme.brand(self.to_embed().color(color))
+    }
+
+    /// Render as an embed, with abbreviated day names and fields split and cut to Discord's
+    /// limits
+    pub fn to_embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::new().title(&self.title).color(self.color);
+        let mut used = self.title.chars().count();
+        if let Some(description) = &self.description {
+            embed = embed.description(description);
+            used += description.chars().count();
+        }
+        if let Some(footer) = &self.footer {
+            embed = embed.footer(CreateEmbedFooter::new(footer));
+            used += footer.chars().count();
+        }
+        if let Some(image) = &self.image {
+            embed = embed.image(image);
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            embed = embed.thumbnail(thumbnail);
+        }
+
+        let fields = self
+            .fields
+            .iter()
+            .flat_map(|field| {
+                let lines: Vec<String> = field
+                    .lines
+                    .iter()
+                    .map(|line| {
+                        let text = match line.day {
+                            Some(day) => {
+                                format!("**{}**: {}", weekday_short_name(day.weekday()), line.text)
+                            }
+                            None => line.text.clone(),
+                        };
+                        if field.bulleted {
+                            format!("• {text}")
+                        } else {
+                            text
+                        }
+                    })
+                    .collect();
+                split_field(&field.name, &lines)
+            })
+            .collect();
+        for (name, value) in limit_fields(fields, used) {
+            embed = embed.field(name, value, false);
+        }
+        embed
+    }
+
+    /// Render as plain text for screen readers: one item per line, full day names and no
+    /// markdown, split into messages that fit Discord's limit
+    pub fn to_text(&self) -> Vec<String> {
+        let mut lines = vec![plain(&self.title)];
+        if let Some(description) = &self.description {
+            lines.extend(description.lines().map(plain));
+        }
+        for field in &self.fields {
+            lines.push(String::new());
+            // Fields continuing the previous one have a blank name
+            if !field.name.trim_matches('\u{200B}').trim().is_empty() {
+                lines.push(plain(&field.name));
+            }
+            lines.extend(field.lines.iter().map(|line| match line.day {
+                Some(day) => format!(
+                    "{} {}: {}",
+                    weekday_name(day.weekday()),
+                    day.format("%d.%m."),
+                    plain(&line.text)
+                ),
+                None => plain(&line.text),
+            }));
+        }
+        if let Some(footer) = &self.footer {
+            lines.push(String::new());
+            lines.push(plain(footer));
+        }
+        split_lines(&lines, MESSAGE_LIMIT)
+    }
+}
+
+/// Drop bold markdown, which screen readers would otherwise read out
+fn plain(text: &str) -> String {
+    text.replace("**", "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_split_between_lines() {
+        let lines: Vec<ViewLine> = (0..300)
+            .map(|i| ViewLine::new(format!("**Line** number {i}")))
+            .collect();
+        let messages = View::info("Title", "Description")
+            .field("Field", lines)
+            .to_text();
+
+        assert!(messages.len() > 1);
+        assert!(messages
+            .iter()
+            .all(|message| message.chars().count() <= MESSAGE_LIMIT));
+        assert!(messages[0].starts_with("Title\nDescription\n\nField\nLine number 0\n"));
+        assert!(messages.iter().all(|message| !message.contains("**")));
+        assert!(messages.last().unwrap().ends_with("Line number 299"));
+    }
+}
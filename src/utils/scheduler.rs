@@ -1,17 +1,29 @@
 use chrono::{DateTime, Local};
+use futures::FutureExt;
 use lazy_static::lazy_static;
 use poise::serenity_prelude as serenity;
+use std::any::Any;
 use std::collections::HashMap;
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, sleep_until, Duration as TokioDuration, Instant};
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
+use crate::components::redis_service::{Key, RedisActorHandle};
 use crate::config::Config;
-use crate::error::BotResult;
+use crate::error::{maintenance_error, panicked_error, BotResult};
+use crate::maintenance::get_maintenance;
+use crate::utils::notifier::Sinks;
+use crate::utils::pending::{
+    load_pending, park_notification, remove_pending, send_with_retry, PendingNotification,
+    PENDING_RETRY_INTERVAL, RETRY_DELAY, SEND_ATTEMPTS,
+};
+use crate::utils::time::{get_weekly_date_range, WeekStart};
 
 lazy_static! {
     /// Track the last daily notification date by component type
@@ -23,13 +35,67 @@ lazy_static! {
     pub static ref WEEKLY_NOTIFICATIONS_SENT: RwLock<HashMap<String, bool>> = RwLock::new(HashMap::new());
 }
 
+/// Number of notification sends that panicked since startup
+static NOTIFICATION_PANICS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of notification sends that panicked since startup
+pub fn notification_panics() -> u64 {
+    NOTIFICATION_PANICS.load(Ordering::Relaxed)
+}
+
 /// Notification type
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NotificationType {
     Daily,
     Weekly,
 }
 
+/// Serenity context shared between a component and its scheduler tasks.
+///
+/// The component swaps in the fresh context on every ready event (gateway reconnects
+/// included), and the scheduler loops read the latest value right before each send.
+pub struct SharedContext<C = serenity::Context> {
+    inner: Arc<RwLock<Arc<C>>>,
+}
+
+impl<C> Clone for SharedContext<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<C: Send + Sync> SharedContext<C> {
+    /// Create a new shared context
+    pub fn new(ctx: Arc<C>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(ctx)),
+        }
+    }
+
+    /// Replace the context seen by all clones
+    pub async fn set(&self, ctx: Arc<C>) {
+        *self.inner.write().await = ctx;
+    }
+
+    /// Get the latest context
+    pub async fn current(&self) -> Arc<C> {
+        Arc::clone(&*self.inner.read().await)
+    }
+
+    /// Put `ctx` in a component's context slot. A context already there is swapped in place,
+    /// so scheduler loops holding a clone of it pick up the new one.
+    pub async fn attach(slot: &RwLock<Option<Self>>, ctx: Arc<C>) {
+        let mut slot = slot.write().await;
+        match &*slot {
+            Some(shared_ctx) => shared_ctx.set(ctx).await,
+            None => *slot = Some(Self::new(ctx)),
+        }
+    }
+}
+
 /// Trait for component schedulers that handle periodic notifications
 pub trait Scheduler: Send + 'static {
     /// The type of handle used by this scheduler
@@ -41,9 +107,10 @@ pub trait Scheduler: Send + 'static {
 
     /// Start the scheduler with the necessary context
     fn start(
-        ctx: Arc<serenity::Context>,
+        ctx: SharedContext,
         config: Arc<RwLock<Config>>,
         handle: Self::Handle,
+        redis_handle: RedisActorHandle,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send>>;
 
     /// Stop the scheduler gracefully
@@ -52,18 +119,20 @@ pub trait Scheduler: Send + 'static {
 
 /// Common notification handler trait to be implemented by components
 pub trait NotificationHandler: Send + Sync + 'static {
-    /// Send a daily notification
+    /// Send a daily notification to `sinks`
     fn send_daily_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>>;
 
-    /// Send a weekly notification
+    /// Send a weekly notification to `sinks`
     fn send_weekly_notification<'a>(
         &'a self,
-        ctx: &'a serenity::Context,
+        http: &'a Arc<serenity::Http>,
         channel_id: u64,
+        sinks: Sinks,
     ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>>;
 }
 
@@ -148,11 +217,84 @@ pub async fn update_notification_flags(today: &str, week_start_date: &str, compo
     }
 }
 
-/// Try to claim a notification slot to prevent duplicates
+/// How long a claim is kept in Redis, comfortably longer than the week it may cover
+pub const CLAIM_TTL_SECS: u64 = 8 * 24 * 60 * 60;
+
+/// Redis key claiming a component's notification for a day, or a week by its start date
+pub fn claim_key(
+    component_type: &str,
+    notification_type: &NotificationType,
+    date: &str,
+) -> BotResult<Key> {
+    let kind = match notification_type {
+        NotificationType::Daily => "daily",
+        NotificationType::Weekly => "weekly",
+    };
+    Key::fixed("notifications:claimed")
+        .segment(component_type)?
+        .name(kind)
+        .segment(date)
+}
+
+/// Claim a notification in Redis, shared by every instance and the one-shot CLI.
+///
+/// Returns false when someone else already claimed it.
+pub async fn claim_in_redis(
+    redis_handle: &RedisActorHandle,
+    component_type: &str,
+    notification_type: &NotificationType,
+    date: &str,
+) -> BotResult<bool> {
+    redis_handle
+        .set_nx_ex(
+            &claim_key(component_type, notification_type, date)?,
+            chrono::Utc::now().timestamp(),
+            CLAIM_TTL_SECS,
+        )
+        .await
+}
+
+/// Give up a Redis claim after sending failed, so a later attempt can take it
+pub async fn release_claim(
+    redis_handle: &RedisActorHandle,
+    component_type: &str,
+    notification_type: &NotificationType,
+    date: &str,
+) -> BotResult<()> {
+    redis_handle
+        .del(&claim_key(component_type, notification_type, date)?)
+        .await
+}
+
+/// Try to claim a notification slot to prevent duplicates.
+///
+/// The slot is claimed in this process first and then in Redis, so a notification already
+/// sent by another instance or a one-shot run is skipped. If Redis can't be reached, the local
+/// claim alone decides.
 pub async fn try_claim_notification(
     notification_type: NotificationType,
     component_type: &str,
+    redis_handle: &RedisActorHandle,
+    date: &str,
 ) -> bool {
+    if !claim_locally(notification_type.clone(), component_type).await {
+        return false;
+    }
+
+    match claim_in_redis(redis_handle, component_type, &notification_type, date).await {
+        Ok(claimed) => claimed,
+        Err(e) => {
+            warn!(
+                "[{}] Failed to claim {:?} notification in Redis, relying on the local claim: {}",
+                component_type, notification_type, e
+            );
+            true
+        }
+    }
+}
+
+/// Claim a notification slot within this process
+async fn claim_locally(notification_type: NotificationType, component_type: &str) -> bool {
     match notification_type {
         NotificationType::Daily => {
             let mut daily_sent = DAILY_NOTIFICATIONS_SENT.write().await;
@@ -175,8 +317,20 @@ pub async fn try_claim_notification(
     }
 }
 
-/// Reset notification flag if sending failed
-pub async fn reset_notification_flag(notification_type: NotificationType, component_type: &str) {
+/// Reset notification flag if sending failed, releasing the Redis claim too
+pub async fn reset_notification_flag(
+    notification_type: NotificationType,
+    component_type: &str,
+    redis_handle: &RedisActorHandle,
+    date: &str,
+) {
+    if let Err(e) = release_claim(redis_handle, component_type, &notification_type, date).await {
+        warn!(
+            "[{}] Failed to release {:?} notification claim: {}",
+            component_type, notification_type, e
+        );
+    }
+
     match notification_type {
         NotificationType::Daily => {
             DAILY_NOTIFICATIONS_SENT
@@ -235,3 +389,556 @@ pub async fn update_last_sent_date(
         }
     }
 }
+
+/// Forget a component's notification flags and dates, so an instance created after a restart
+/// starts from a clean slate. The claims in Redis still keep it from sending anything twice.
+pub async fn clear_notification_state(component_type: &str) {
+    LAST_DAILY_DATES.write().await.remove(component_type);
+    LAST_WEEKLY_START_DATES.write().await.remove(component_type);
+    DAILY_NOTIFICATIONS_SENT
+        .write()
+        .await
+        .remove(component_type);
+    WEEKLY_NOTIFICATIONS_SENT
+        .write()
+        .await
+        .remove(component_type);
+}
+
+/// A context notifications are sent through
+pub trait HttpContext: Send + Sync + 'static {
+    fn http(&self) -> &Arc<serenity::Http>;
+}
+
+impl HttpContext for serenity::Context {
+    fn http(&self) -> &Arc<serenity::Http> {
+        &self.http
+    }
+}
+
+impl HttpContext for Arc<serenity::Http> {
+    fn http(&self) -> &Arc<serenity::Http> {
+        self
+    }
+}
+
+/// Send a notification once through the latest context
+async fn send_once<C: HttpContext>(
+    ctx: &SharedContext<C>,
+    handler: &dyn NotificationHandler,
+    notification_type: &NotificationType,
+    channel_id: u64,
+    sinks: Sinks,
+) -> BotResult<()> {
+    let ctx = ctx.current().await;
+    send_with_http(ctx.http(), handler, notification_type, channel_id, sinks).await
+}
+
+/// Send a notification once through an HTTP client.
+///
+/// A panic while building or sending it fails this send with a `Panicked` error, so it goes
+/// through the usual failure handling instead of ending the scheduler loop.
+pub async fn send_with_http(
+    http: &Arc<serenity::Http>,
+    handler: &dyn NotificationHandler,
+    notification_type: &NotificationType,
+    channel_id: u64,
+    sinks: Sinks,
+) -> BotResult<()> {
+    let send = async {
+        match notification_type {
+            NotificationType::Daily => {
+                handler
+                    .send_daily_notification(http, channel_id, sinks)
+                    .await
+            }
+            NotificationType::Weekly => {
+                handler
+                    .send_weekly_notification(http, channel_id, sinks)
+                    .await
+            }
+        }
+    };
+
+    // Handlers keep their state behind actors and locks, and every send builds its
+    // notification from scratch, so nothing a panicked send left behind is reused
+    match AssertUnwindSafe(send).catch_unwind().await {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            let count = NOTIFICATION_PANICS.fetch_add(1, Ordering::Relaxed) + 1;
+            error!(
+                notification_panics = count,
+                "{:?} notification to channel {} panicked: {}",
+                notification_type,
+                channel_id,
+                message
+            );
+            Err(panicked_error(&message))
+        }
+    }
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Hold a notification back while maintenance mode is on, parking it so it's sent once
+/// maintenance ends if that's within its catch-up window. Returns whether it was held back.
+pub async fn held_for_maintenance(
+    redis_handle: &RedisActorHandle,
+    component_type: &str,
+    notification_type: &NotificationType,
+    date: &str,
+    channel_id: u64,
+) -> bool {
+    if get_maintenance(redis_handle).await.is_none() {
+        return false;
+    }
+    let pending = PendingNotification {
+        component: component_type.to_string(),
+        notification_type: notification_type.clone(),
+        date: date.to_string(),
+        channel_id,
+        parked_at: chrono::Utc::now().timestamp(),
+        sinks: Sinks::all(),
+    };
+    info!(
+        "[{}] Suppressed {} during maintenance",
+        component_type,
+        pending.field()
+    );
+
+    // Keep the first park time, so the catch-up window runs from when it was due
+    let parked = load_pending(redis_handle, component_type)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .any(|parked| parked.field() == pending.field());
+    if !parked {
+        if let Err(e) = park_notification(redis_handle, &pending).await {
+            error!(
+                "[{}] Failed to park {} until maintenance ends: {}",
+                component_type,
+                pending.field(),
+                e
+            );
+        }
+    }
+    true
+}
+
+/// Send a scheduled notification, retrying a few times and parking it in Redis for the
+/// scheduler loop if Discord stays unreachable.
+///
+/// `date` is the day of a daily notification or the week start of a weekly one.
+pub async fn deliver_notification<C: HttpContext>(
+    ctx: &SharedContext<C>,
+    handler: &dyn NotificationHandler,
+    redis_handle: &RedisActorHandle,
+    component_type: &str,
+    notification_type: NotificationType,
+    date: &str,
+    channel_id: u64,
+) -> BotResult<()> {
+    if held_for_maintenance(
+        redis_handle,
+        component_type,
+        &notification_type,
+        date,
+        channel_id,
+    )
+    .await
+    {
+        // Failing the send makes the caller release the claim, so the parked notification can
+        // take it once maintenance ends
+        return Err(maintenance_error("notifications are paused"));
+    }
+
+    let mut sinks = Sinks::all();
+    let result = send_with_retry(
+        |sinks| send_once(ctx, handler, &notification_type, channel_id, sinks),
+        &mut sinks,
+        SEND_ATTEMPTS,
+        RETRY_DELAY,
+    )
+    .await;
+
+    if result.is_err() {
+        let pending = PendingNotification {
+            component: component_type.to_string(),
+            notification_type,
+            date: date.to_string(),
+            channel_id,
+            parked_at: chrono::Utc::now().timestamp(),
+            sinks,
+        };
+        if let Err(e) = park_notification(redis_handle, &pending).await {
+            error!(
+                "[{}] Failed to park {} for a retry: {}",
+                component_type,
+                pending.field(),
+                e
+            );
+        }
+    }
+
+    result
+}
+
+/// Retry a component's parked notifications, dropping the ones that went stale.
+///
+/// A delivered notification is marked sent for its day or week. Returns whether any are still
+/// waiting, in which case the loop should come back within `PENDING_RETRY_INTERVAL`.
+pub async fn retry_pending_notifications<C: HttpContext>(
+    ctx: &SharedContext<C>,
+    handler: &dyn NotificationHandler,
+    redis_handle: &RedisActorHandle,
+    component_type: &str,
+    week_start: WeekStart,
+) -> bool {
+    let pending = match load_pending(redis_handle, component_type).await {
+        Ok(pending) => pending,
+        Err(e) => {
+            debug!(
+                "[{}] Failed to load pending notifications: {}",
+                component_type, e
+            );
+            return false;
+        }
+    };
+
+    if !pending.is_empty() && get_maintenance(redis_handle).await.is_some() {
+        debug!(
+            "[{}] Holding {} parked notifications until maintenance ends",
+            component_type,
+            pending.len()
+        );
+        return true;
+    }
+
+    let now = Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let (week_start_date, _) = get_weekly_date_range(&now, week_start);
+    let mut waiting = false;
+
+    for notification in pending {
+        let deliver = if !notification.is_current(&today, &week_start_date, now.timestamp()) {
+            warn!(
+                "[{}] Giving up on {}, its catch-up window has passed",
+                component_type,
+                notification.field()
+            );
+            false
+        } else {
+            // Claiming fails when it was sent some other way in the meantime
+            try_claim_notification(
+                notification.notification_type.clone(),
+                component_type,
+                redis_handle,
+                &notification.date,
+            )
+            .await
+        };
+
+        if deliver {
+            let result = send_once(
+                ctx,
+                handler,
+                &notification.notification_type,
+                notification.channel_id,
+                notification.sinks.clone(),
+            )
+            .await;
+            if let Err(e) = result {
+                warn!(
+                    "[{}] Retrying {} failed: {}",
+                    component_type,
+                    notification.field(),
+                    e
+                );
+                // Keep the sinks it reached this time from getting it again
+                if let Some(failed) = e.undelivered_sinks() {
+                    let narrowed = PendingNotification {
+                        sinks: Sinks::only(failed),
+                        ..notification.clone()
+                    };
+                    if narrowed != notification {
+                        if let Err(e) = park_notification(redis_handle, &narrowed).await {
+                            warn!(
+                                "[{}] Failed to update {}: {}",
+                                component_type,
+                                notification.field(),
+                                e
+                            );
+                        }
+                    }
+                }
+                reset_notification_flag(
+                    notification.notification_type.clone(),
+                    component_type,
+                    redis_handle,
+                    &notification.date,
+                )
+                .await;
+                waiting = true;
+                continue;
+            }
+
+            info!(
+                "[{}] Delivered {} after an earlier failure",
+                component_type,
+                notification.field()
+            );
+            update_last_sent_date(
+                notification.notification_type.clone(),
+                &notification.date,
+                component_type,
+            )
+            .await;
+        }
+
+        if let Err(e) = remove_pending(redis_handle, &notification).await {
+            warn!(
+                "[{}] Failed to remove {}: {}",
+                component_type,
+                notification.field(),
+                e
+            );
+        }
+    }
+
+    waiting
+}
+
+/// When a loop should wake up: at the target, or sooner while notifications are parked
+pub fn next_wake_time(target: DateTime<Local>, has_pending: bool) -> DateTime<Local> {
+    if !has_pending {
+        return target;
+    }
+    let retry_at = Local::now()
+        + chrono::Duration::from_std(PENDING_RETRY_INTERVAL).unwrap_or(chrono::Duration::zero());
+    target.min(retry_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorImpl;
+    use crate::utils::pending::send_with_retry;
+    use std::sync::atomic::AtomicBool;
+    use tokio::sync::{mpsc, Notify};
+
+    /// Panics on its first daily send, like an embed builder tripping over a bad date
+    #[derive(Default)]
+    struct PanicsOnce {
+        panicked: AtomicBool,
+    }
+
+    impl NotificationHandler for PanicsOnce {
+        fn send_daily_notification<'a>(
+            &'a self,
+            _http: &'a Arc<serenity::Http>,
+            _channel_id: u64,
+            _sinks: Sinks,
+        ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+            Box::pin(async move {
+                if !self.panicked.swap(true, Ordering::SeqCst) {
+                    panic!("date out of range");
+                }
+                Ok(())
+            })
+        }
+
+        fn send_weekly_notification<'a>(
+            &'a self,
+            _http: &'a Arc<serenity::Http>,
+            _channel_id: u64,
+            _sinks: Sinks,
+        ) -> Pin<Box<dyn Future<Output = BotResult<()>> + Send + 'a>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleared_state_lets_a_restart_claim_again() {
+        let component_type = "cleared_component";
+        update_notification_flags("2024-01-02", "2024-01-01", component_type).await;
+        assert!(claim_locally(NotificationType::Daily, component_type).await);
+        assert!(!claim_locally(NotificationType::Daily, component_type).await);
+
+        clear_notification_state(component_type).await;
+        assert!(!is_notification_sent(NotificationType::Daily, component_type).await);
+        assert!(!LAST_DAILY_DATES.read().await.contains_key(component_type));
+        assert!(claim_locally(NotificationType::Daily, component_type).await);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_send_fails_and_is_retried() {
+        let http = Arc::new(serenity::Http::new(""));
+        let handler = PanicsOnce::default();
+        let before = notification_panics();
+
+        let error = send_with_http(&http, &handler, &NotificationType::Daily, 1, Sinks::all())
+            .await
+            .unwrap_err();
+        assert!(matches!(&*error, ErrorImpl::Panicked(message) if message == "date out of range"));
+        assert!(notification_panics() > before);
+
+        // The next attempt goes through as usual
+        let handler = PanicsOnce::default();
+        send_with_retry(
+            |sinks| send_with_http(&http, &handler, &NotificationType::Daily, 1, sinks),
+            &mut Sinks::all(),
+            2,
+            TokioDuration::ZERO,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shared_context_swap_is_seen_by_next_iteration() {
+        let shared = SharedContext::new(Arc::new("first ready".to_string()));
+        let (seen_tx, mut seen_rx) = mpsc::channel(4);
+        let tick = Arc::new(Notify::new());
+
+        // Mimic a scheduler loop: wait for the next iteration, then read the latest context
+        let loop_ctx = shared.clone();
+        let loop_tick = Arc::clone(&tick);
+        let task = tokio::spawn(async move {
+            loop {
+                loop_tick.notified().await;
+                let ctx = loop_ctx.current().await;
+                if seen_tx.send(ctx.as_str().to_string()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        tick.notify_one();
+        assert_eq!(seen_rx.recv().await.unwrap(), "first ready");
+
+        // A reconnect hands the component a new context
+        shared.set(Arc::new("after reconnect".to_string())).await;
+
+        tick.notify_one();
+        assert_eq!(seen_rx.recv().await.unwrap(), "after reconnect");
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_attach_swaps_context_of_running_scheduler() {
+        let slot = RwLock::new(None);
+        SharedContext::attach(&slot, Arc::new("first ready".to_string())).await;
+
+        // The scheduler keeps a clone from when it was started
+        let scheduler_ctx = slot.read().await.clone().unwrap();
+        assert_eq!(scheduler_ctx.current().await.as_str(), "first ready");
+
+        // Ready after a reconnect attaches the new context
+        SharedContext::attach(&slot, Arc::new("after reconnect".to_string())).await;
+        assert_eq!(scheduler_ctx.current().await.as_str(), "after reconnect");
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_skips_notification_sent_by_one_shot_run() {
+        let redis = RedisActorHandle::fake();
+        let component = "claims_one_shot";
+        let week = "2025-01-06";
+
+        // The CronJob claims and sends the weekly notification from its own process
+        assert!(
+            claim_in_redis(&redis, component, &NotificationType::Weekly, week)
+                .await
+                .unwrap()
+        );
+
+        // The long-running scheduler wakes up for the same week and leaves it alone
+        assert!(!try_claim_notification(NotificationType::Weekly, component, &redis, week).await);
+        assert!(is_notification_sent(NotificationType::Weekly, component).await);
+
+        // Other notifications are unaffected
+        assert!(try_claim_notification(NotificationType::Daily, component, &redis, week).await);
+        assert_eq!(
+            claim_key(component, &NotificationType::Weekly, week)
+                .unwrap()
+                .as_str(),
+            "notifications:claimed:claims_one_shot:weekly:2025-01-06"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_releases_claim() {
+        let redis = RedisActorHandle::fake();
+        let component = "claims_release";
+        let today = "2025-01-07";
+
+        // The CronJob couldn't reach Discord and gave its claim back
+        assert!(
+            claim_in_redis(&redis, component, &NotificationType::Daily, today)
+                .await
+                .unwrap()
+        );
+        release_claim(&redis, component, &NotificationType::Daily, today)
+            .await
+            .unwrap();
+
+        // So the scheduler sends it, and a later one-shot run doesn't send it twice
+        assert!(try_claim_notification(NotificationType::Daily, component, &redis, today).await);
+        assert!(
+            !claim_in_redis(&redis, component, &NotificationType::Daily, today)
+                .await
+                .unwrap()
+        );
+
+        // A failed scheduler send frees it for the next attempt in either path
+        reset_notification_flag(NotificationType::Daily, component, &redis, today).await;
+        assert!(
+            claim_in_redis(&redis, component, &NotificationType::Daily, today)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_holds_notifications_without_consuming_claims() {
+        use crate::maintenance::{set_maintenance, Maintenance};
+
+        let redis = RedisActorHandle::fake();
+        let component = "claims_maintenance";
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let maintenance = Maintenance {
+            message: None,
+            enabled_by: 1,
+            enabled_at: 0,
+        };
+        set_maintenance(&redis, Some(&maintenance)).await.unwrap();
+
+        // The scheduler claims the notification as it comes due, is held back and gives the
+        // claim back like after any failed send
+        assert!(try_claim_notification(NotificationType::Daily, component, &redis, &today).await);
+        assert!(held_for_maintenance(&redis, component, &NotificationType::Daily, &today, 7).await);
+        reset_notification_flag(NotificationType::Daily, component, &redis, &today).await;
+        let parked = load_pending(&redis, component).await.unwrap();
+        assert_eq!(parked.len(), 1);
+
+        // Holding it back again doesn't restart its catch-up window
+        assert!(held_for_maintenance(&redis, component, &NotificationType::Daily, &today, 7).await);
+        assert_eq!(load_pending(&redis, component).await.unwrap(), parked);
+
+        // Once maintenance ends, the parked notification is current and can still be claimed
+        set_maintenance(&redis, None).await.unwrap();
+        assert!(
+            !held_for_maintenance(&redis, component, &NotificationType::Daily, &today, 7).await
+        );
+        assert!(parked[0].is_current(&today, "", chrono::Utc::now().timestamp()));
+        assert!(try_claim_notification(NotificationType::Daily, component, &redis, &today).await);
+    }
+}
@@ -0,0 +1,63 @@
+//! Telegram sink for scheduled notifications, sending them through the Bot API
+
+use crate::config::Config;
+use crate::error::{other_error, BotResult};
+use crate::utils::notifier::{Delivery, Notification, NotificationSink};
+use crate::utils::telegram_format::notification_markdown_v2;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Sends notifications to a Telegram chat as MarkdownV2 messages
+pub struct TelegramSink {
+    token: String,
+    chat_id: String,
+    client: reqwest::Client,
+}
+
+impl TelegramSink {
+    pub fn new(token: String, chat_id: String) -> Self {
+        Self {
+            token,
+            chat_id,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create a sink when both the bot token and the chat id are configured
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match (&config.telegram_bot_token, &config.telegram_chat_id) {
+            (Some(token), Some(chat_id)) => Some(Self::new(token.clone(), chat_id.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for TelegramSink {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn deliver(&self, _delivery: &Delivery, notification: &Notification) -> BotResult<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let body = json!({
+            "chat_id": self.chat_id,
+            "text": notification_markdown_v2(notification),
+            "parse_mode": "MarkdownV2",
+        });
+        // The URL holds the bot token, so keep it out of the error
+        self.client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                other_error(&format!(
+                    "Failed to send Telegram message: {}",
+                    e.without_url()
+                ))
+            })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Telegram MarkdownV2 versions of notification embeds.
+//!
+//! Telegram rejects a MarkdownV2 message with an unescaped reserved character anywhere outside
+//! an entity, so everything not produced by the conversion itself is escaped.
+
+use crate::utils::notifier::Notification;
+
+/// Maximum length of a Telegram message
+pub const MESSAGE_LIMIT: usize = 4096;
+
+/// Characters MarkdownV2 reserves outside entities
+const RESERVED: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\',
+];
+
+/// Escape every reserved character of MarkdownV2
+pub fn escape_markdown_v2(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if RESERVED.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape text inside a code entity, where only the backtick and backslash are reserved
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+/// Convert Discord markdown to MarkdownV2. Bold, strikethrough, underline and inline code are
+/// kept when they're closed; anything else, including unbalanced markers, is escaped as text.
+pub fn discord_to_markdown_v2(text: &str) -> String {
+    // Discord marker and the MarkdownV2 one it becomes
+    const MARKERS: [(&str, &str); 3] = [("**", "*"), ("~~", "~"), ("__", "__")];
+
+    let mut converted = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                let code = &rest[1..1 + end];
+                if !code.is_empty() {
+                    converted.push('`');
+                    converted.push_str(&escape_code(code));
+                    converted.push('`');
+                    rest = &rest[end + 2..];
+                    continue;
+                }
+            }
+        }
+        for (discord, telegram) in MARKERS {
+            let Some(after) = rest.strip_prefix(discord) else {
+                continue;
+            };
+            if let Some(end) = after.find(discord).filter(|&end| end > 0) {
+                converted.push_str(telegram);
+                converted.push_str(&discord_to_markdown_v2(&after[..end]));
+                converted.push_str(telegram);
+                rest = &after[end + discord.len()..];
+                continue 'outer;
+            }
+        }
+        converted.push_str(&escape_markdown_v2(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    converted
+}
+
+/// Remove Discord user, role and channel mentions, which Telegram would show as raw markup
+fn strip_mentions(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let mention = rest[start + 1..].find('>').and_then(|end| {
+            let inner = &rest[start + 1..start + 1 + end];
+            let id = inner
+                .strip_prefix("@&")
+                .or_else(|| inner.strip_prefix("@!"))
+                .or_else(|| inner.strip_prefix('@'))
+                .or_else(|| inner.strip_prefix('#'))?;
+            (!id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())).then_some(end)
+        });
+        match mention {
+            Some(end) => {
+                stripped.push_str(&rest[..start]);
+                rest = &rest[start + end + 2..];
+                // Drop the space the mention was separated with
+                if stripped.is_empty() || stripped.ends_with(char::is_whitespace) {
+                    rest = rest.strip_prefix(' ').unwrap_or(rest);
+                }
+            }
+            None => {
+                stripped.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Render a notification as one MarkdownV2 message: the content, the embed's bold title, its
+/// description, its fields under bold names and its footer in italics.
+///
+/// Discord mentions are dropped, and parts that don't fit in a Telegram message are dropped from
+/// the end, marked with an ellipsis.
+pub fn notification_markdown_v2(notification: &Notification) -> String {
+    let embed = serde_json::to_value(&notification.embed).unwrap_or_default();
+    let text = |value: Option<&str>| {
+        value
+            .map(strip_mentions)
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let mut parts = Vec::new();
+    if let Some(content) = text(notification.content.as_deref()) {
+        parts.push(discord_to_markdown_v2(&content));
+    }
+    if let Some(title) = text(embed["title"].as_str()) {
+        parts.push(format!("*{}*", escape_markdown_v2(&title)));
+    }
+    if let Some(description) = text(embed["description"].as_str()) {
+        parts.push(discord_to_markdown_v2(&description));
+    }
+    for field in embed["fields"].as_array().into_iter().flatten() {
+        let value = text(field["value"].as_str()).map(|value| discord_to_markdown_v2(&value));
+        // Continuation fields of a split field have a zero-width space as their name
+        let name = text(field["name"].as_str())
+            .filter(|name| name != "\u{200B}")
+            .map(|name| format!("*{}*", escape_markdown_v2(&name)));
+        match (name, value) {
+            (Some(name), Some(value)) => parts.push(format!("{name}\n{value}")),
+            (Some(part), None) | (None, Some(part)) => parts.push(part),
+            (None, None) => {}
+        }
+    }
+    if let Some(footer) = text(embed["footer"]["text"].as_str()) {
+        parts.push(format!("_{}_", escape_markdown_v2(&footer)));
+    }
+
+    join_within_limit(parts, MESSAGE_LIMIT)
+}
+
+/// Join parts with blank lines, keeping as many as fit within `limit` characters
+fn join_within_limit(parts: Vec<String>, limit: usize) -> String {
+    let ellipsis = escape_markdown_v2("…");
+    let mut message = String::new();
+    let mut len = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let separator = if message.is_empty() { 0 } else { 2 };
+        let part_len = part.chars().count();
+        // Leave room for the ellipsis unless this is the last part
+        let reserve = if i + 1 == parts.len() { 0 } else { 3 };
+        if len + separator + part_len + reserve > limit {
+            if !message.is_empty() {
+                message.push_str("\n\n");
+            }
+            message.push_str(&ellipsis);
+            break;
+        }
+        if separator > 0 {
+            message.push_str("\n\n");
+        }
+        message.push_str(part);
+        len += separator + part_len;
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+
+    #[test]
+    fn test_reserved_characters_are_escaped() {
+        assert_eq!(
+            escape_markdown_v2("Klo 9.00-17.00 (Anna) #1 [x] a_b *c* ~d~ `e` >f +g =h |i {j} !k \\"),
+            "Klo 9\\.00\\-17\\.00 \\(Anna\\) \\#1 \\[x\\] a\\_b \\*c\\* \\~d\\~ \\`e\\` \\>f \\+g \\=h \\|i \\{j\\} \\!k \\\\"
+        );
+        assert_eq!(escape_markdown_v2("Työvuorot ma 1/2"), "Työvuorot ma 1/2");
+    }
+
+    #[test]
+    fn test_discord_markdown_is_converted() {
+        assert_eq!(
+            discord_to_markdown_v2("**Anna**: 9.00-17.00"),
+            "*Anna*: 9\\.00\\-17\\.00"
+        );
+        assert_eq!(
+            discord_to_markdown_v2("~~Peruttu~~ __uusi__"),
+            "~Peruttu~ __uusi__"
+        );
+        assert_eq!(
+            discord_to_markdown_v2("**bold ~~struck.~~**"),
+            "*bold ~struck\\.~*"
+        );
+        assert_eq!(
+            discord_to_markdown_v2("`a_b.c` and `x\\y`"),
+            "`a_b.c` and `x\\\\y`"
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_markers_are_escaped() {
+        assert_eq!(discord_to_markdown_v2("2 ** 3"), "2 \\*\\* 3");
+        assert_eq!(discord_to_markdown_v2("a `b"), "a \\`b");
+        assert_eq!(discord_to_markdown_v2("****"), "\\*\\*\\*\\*");
+        assert_eq!(discord_to_markdown_v2("*italic*"), "\\*italic\\*");
+    }
+
+    #[test]
+    fn test_notification_is_rendered() {
+        let notification = Notification {
+            content: Some("<@&1>".to_string()),
+            embed: CreateEmbed::new()
+                .title("Tänään (ma 1.1.)")
+                .description("Kaikki paikalla!")
+                .field("Anna", "**9.00-17.00**", false)
+                .field("\u{200B}", "Jatkuu", false)
+                .footer(CreateEmbedFooter::new("Päivitetty 8.00")),
+        };
+        assert_eq!(
+            notification_markdown_v2(&notification),
+            "*Tänään \\(ma 1\\.1\\.\\)*\n\nKaikki paikalla\\!\n\n*Anna*\n*9\\.00\\-17\\.00*\n\nJatkuu\n\n_Päivitetty 8\\.00_"
+        );
+    }
+
+    #[test]
+    fn test_mentions_are_stripped() {
+        assert_eq!(strip_mentions("<@&1> <@!22> Vuorot <#3> <@4>"), "Vuorot ");
+        assert_eq!(strip_mentions("a <b> <@x> <@>"), "a <b> <@x> <@>");
+    }
+
+    #[test]
+    fn test_long_notification_is_cut_between_parts() {
+        let parts = vec!["a".repeat(10), "b".repeat(10), "c".repeat(10)];
+        assert_eq!(
+            join_within_limit(parts.clone(), 25),
+            format!("{}\n\n{}\n\n…", "a".repeat(10), "b".repeat(10))
+        );
+        assert_eq!(join_within_limit(parts, 34).chars().count(), 34);
+        assert_eq!(join_within_limit(vec!["a".repeat(10)], 5), "…");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::components::work_schedule::EmployeeId;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tracing::{warn, Subscriber};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Environment variable naming the OTLP collector traces are exported to
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Boxed layer stacked on top of the fmt output
+pub type ExportLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// Pseudonymous employee tag for spans, so traces can be correlated without carrying names
+pub fn employee_hash(name: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    EmployeeId::new(name).slug().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Provider of the exported spans, kept for flushing them at shutdown
+#[cfg(feature = "otel")]
+static TRACER_PROVIDER: std::sync::OnceLock<opentelemetry_sdk::trace::SdkTracerProvider> =
+    std::sync::OnceLock::new();
+
+/// Trace export layer for the endpoint, or None when traces stay local
+fn export_layer_for<S>(endpoint: Option<&str>, service_name: &'static str) -> Option<ExportLayer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    let endpoint = endpoint.map(str::trim).filter(|e| !e.is_empty())?;
+
+    #[cfg(feature = "otel")]
+    return match otlp_layer(endpoint, service_name) {
+        Ok(layer) => Some(layer),
+        Err(e) => {
+            warn!("Failed to set up trace export to {}: {}", endpoint, e);
+            None
+        }
+    };
+
+    #[cfg(not(feature = "otel"))]
+    {
+        // No OTLP exporter is compiled into this build, so spans only reach the fmt output
+        warn!(
+            "{} is set to {} for {}, but this build has no otel feature",
+            OTLP_ENDPOINT_ENV, endpoint, service_name
+        );
+        None
+    }
+}
+
+/// Layer sending spans in batches to the collector's OTLP/HTTP endpoint
+#[cfg(feature = "otel")]
+fn otlp_layer<S>(endpoint: &str, service_name: &'static str) -> Result<ExportLayer<S>, String>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+    let tracer = provider.tracer(service_name);
+    if TRACER_PROVIDER.set(provider).is_err() {
+        return Err("trace export is already set up".to_string());
+    }
+
+    Ok(Box::new(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Trace export layer configured from the environment, reporting spans as `service_name`.
+/// Stacking the result is a no-op when export isn't configured.
+pub fn export_layer<S>(service_name: &'static str) -> Option<ExportLayer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    export_layer_for(
+        std::env::var(OTLP_ENDPOINT_ENV).ok().as_deref(),
+        service_name,
+    )
+}
+
+/// Export the spans still waiting in the batch and stop exporting. Does nothing when traces
+/// stay local.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            warn!("Failed to flush exported traces: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_layers_stack_with_and_without_endpoint() {
+        for endpoint in [None, Some(""), Some("http://localhost:4317")] {
+            let subscriber = tracing_subscriber::registry()
+                .with(tracing_subscriber::fmt::layer().with_test_writer())
+                .with(export_layer_for(endpoint, "test"));
+
+            tracing::subscriber::with_default(subscriber, || {
+                let span = tracing::info_span!("parse_schedule", employee = "test");
+                let _entered = span.enter();
+                tracing::info!("inside span");
+            });
+        }
+    }
+
+    #[test]
+    fn test_employee_hash_follows_canonical_name() {
+        assert_eq!(
+            employee_hash("Anna Mäkinen"),
+            employee_hash("anna  makinen")
+        );
+        assert_ne!(employee_hash("Anna"), employee_hash("Pekka"));
+        assert_eq!(employee_hash("Anna").len(), 16);
+    }
+}
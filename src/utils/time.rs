@@ -1,25 +1,195 @@
-use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone};
+use chrono::{
+    DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike, Weekday,
+};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tracing::info;
+
+/// First day of the week for weekly ranges and notifications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    /// ISO weeks, Monday to Sunday
+    #[default]
+    Monday,
+    /// Sunday to Saturday
+    Sunday,
+}
 
-/// Parse time string in HH:MM format
-pub fn parse_time(time_str: &str) -> Option<(u32, u32)> {
-    let parts: Vec<&str> = time_str.split(':').collect();
-    if parts.len() != 2 {
-        return None;
+impl WeekStart {
+    /// The weekday weeks start on
+    pub fn weekday(self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
     }
-    let hour = parts[0].parse::<u32>().ok()?;
-    let minute = parts[1].parse::<u32>().ok()?;
-    if hour > 23 || minute > 59 {
-        return None;
+}
+
+impl FromStr for WeekStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mon" | "monday" => Ok(WeekStart::Monday),
+            "sun" | "sunday" => Ok(WeekStart::Sunday),
+            _ => Err(format!("Unknown first day of the week: {s}")),
+        }
     }
-    Some((hour, minute))
 }
 
-/// Calculate next daily notification time
-pub fn next_daily_time(current_time: &DateTime<Local>, time_str: &str) -> Option<NaiveDateTime> {
-    let (hour, minute) = parse_time(time_str)?;
+/// First and last day of the week containing `date`
+pub fn week_bounds(date: NaiveDate, start: WeekStart) -> (NaiveDate, NaiveDate) {
+    let days_since_start = date.weekday().days_since(start.weekday());
+    let first = date - Duration::days(i64::from(days_since_start));
+    (first, first + Duration::days(6))
+}
+
+/// Time of day a notification or a nightly task runs at, in 24h local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(NaiveTime);
+
+impl TimeOfDay {
+    /// The time as a chrono time
+    pub fn time(self) -> NaiveTime {
+        self.0
+    }
+
+    /// The time on `date`
+    pub fn on(self, date: NaiveDate) -> NaiveDateTime {
+        date.and_time(self.0)
+    }
+}
+
+/// Canonical form: HH:MM, or HH:MM:SS when the seconds aren't zero
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.second() == 0 {
+            write!(f, "{}", self.0.format("%H:%M"))
+        } else {
+            write!(f, "{}", self.0.format("%H:%M:%S"))
+        }
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_time(s)
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        parse_time(&value)
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+/// Parse a time in H:MM, HH:MM or HH:MM:SS format, explaining what's wrong when it's invalid
+pub fn parse_time(time_str: &str) -> Result<TimeOfDay, String> {
+    let parts: Vec<&str> = time_str.trim().split(':').collect();
+    let (hour, minute, second) = match parts.as_slice() {
+        [hour, minute] => (*hour, *minute, "00"),
+        [hour, minute, second] => (*hour, *minute, *second),
+        _ => return Err("expected H:MM, HH:MM or HH:MM:SS".to_string()),
+    };
+    let number = |part: &str, digits: std::ops::RangeInclusive<usize>, name: &str| {
+        if digits.contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(part.parse::<u32>().unwrap_or_default())
+        } else if *digits.start() == 2 {
+            Err(format!("{name} must be two digits"))
+        } else {
+            Err(format!("{name} must be one or two digits"))
+        }
+    };
+    let hour = number(hour, 1..=2, "hour")?;
+    let minute = number(minute, 2..=2, "minutes")?;
+    let second = number(second, 2..=2, "seconds")?;
+
+    if hour == 24 && minute == 0 && second == 0 {
+        return Err("24:00 is the end of the day; use 00:00 for midnight".to_string());
+    }
+    if hour > 23 {
+        return Err("hour must be between 0 and 23".to_string());
+    }
+    if minute > 59 {
+        return Err("minutes must be between 00 and 59".to_string());
+    }
+    if second > 59 {
+        return Err("seconds must be between 00 and 59".to_string());
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+        .map(TimeOfDay)
+        .ok_or_else(|| "invalid time".to_string())
+}
+
+/// Check whether a time falls in an "HH:MM-HH:MM" range, which may wrap past midnight.
+///
+/// The start is inclusive and the end exclusive. Returns None if the range is malformed.
+pub fn is_within_time_range(range: &str, hour: u32, minute: u32) -> Option<bool> {
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (parse_time(start).ok()?, parse_time(end).ok()?);
+    let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+    let (start, end) = (start.time(), end.time());
+
+    Some(if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    })
+}
 
+/// Longest daylight saving gap skipped over when resolving a wall clock time
+const MAX_DST_GAP_MINUTES: i64 = 3 * 60;
+
+/// Turn a wall clock time into an instant in `zone`. A time skipped when clocks spring forward
+/// moves to the first instant after the gap, and a time repeated when they fall back takes the
+/// earlier offset, so a notification at 03:30 still fires once on those nights.
+pub fn resolve_local<Z: TimeZone>(zone: &Z, time: NaiveDateTime) -> Option<DateTime<Z>> {
+    match zone.from_local_datetime(&time) {
+        LocalResult::Single(dt) => Some(dt),
+        LocalResult::Ambiguous(earlier, _) => {
+            info!(
+                "{} happens twice as clocks go back, using the earlier one",
+                time
+            );
+            Some(earlier)
+        }
+        LocalResult::None => {
+            let resolved = (1..=MAX_DST_GAP_MINUTES).find_map(|minutes| {
+                zone.from_local_datetime(&(time + Duration::minutes(minutes)))
+                    .earliest()
+            })?;
+            info!(
+                "{} is skipped as clocks spring forward, using {}",
+                time,
+                resolved.naive_local()
+            );
+            Some(resolved)
+        }
+    }
+}
+
+/// Calculate next daily notification time
+pub fn next_daily_time<Z: TimeZone>(
+    current_time: &DateTime<Z>,
+    time: TimeOfDay,
+) -> Option<NaiveDateTime> {
     // Create a datetime for today at the specified time
-    let mut next_time = current_time.date_naive().and_hms_opt(hour, minute, 0)?;
+    let mut next_time = time.on(current_time.date_naive());
 
     // If the time has already passed today, schedule for tomorrow
     if current_time.naive_local() >= next_time {
@@ -29,79 +199,180 @@ pub fn next_daily_time(current_time: &DateTime<Local>, time_str: &str) -> Option
     Some(next_time)
 }
 
-/// Calculate next weekly notification time (for Monday)
-pub fn next_weekly_time(current_time: &DateTime<Local>, time_str: &str) -> Option<NaiveDateTime> {
-    let (hour, minute) = parse_time(time_str)?;
-
-    // Calculate days until next Monday
-    let days_until_monday = (7 - current_time.weekday().num_days_from_monday()) % 7;
-
-    // Create a datetime for the next Monday at the specified time
-    let mut next_time = current_time
+/// Calculate next weekly notification time, sent on the first day of the week
+pub fn next_weekly_time<Z: TimeZone>(
+    current_time: &DateTime<Z>,
+    time: TimeOfDay,
+    week_start: WeekStart,
+) -> Option<NaiveDateTime> {
+    // Calculate days until the next start of a week
+    let days_until_start = week_start.weekday().days_since(current_time.weekday());
+
+    // Create a datetime for the next start of a week at the specified time
+    let mut next_time = time.on(current_time
         .date_naive()
-        .checked_add_signed(chrono::Duration::days(days_until_monday as i64))?
-        .and_hms_opt(hour, minute, 0)?;
+        .checked_add_signed(chrono::Duration::days(days_until_start as i64))?);
 
-    // If today is Monday but the time has passed, schedule for next Monday
-    if days_until_monday == 0 && current_time.naive_local() >= next_time {
+    // If the week starts today but the time has passed, schedule for next week
+    if days_until_start == 0 && current_time.naive_local() >= next_time {
         next_time = next_time.checked_add_signed(chrono::Duration::days(7))?;
     }
 
     Some(next_time)
 }
 
-/// Calculate next notification time (generic version for calendar)
-pub fn next_notification_time(
-    current_time: DateTime<Local>,
-    target_time: &str,
+/// Calculate next notification time (generic version for calendar), in the time zone of
+/// `current_time`
+pub fn next_notification_time<Z: TimeZone>(
+    current_time: DateTime<Z>,
+    target_time: TimeOfDay,
     is_weekly: bool,
-) -> Option<DateTime<Local>> {
-    let (target_hour, target_minute) = parse_time(target_time)?;
+    week_start: WeekStart,
+) -> Option<DateTime<Z>> {
+    let zone = current_time.timezone();
+    let mut date = current_time.date_naive();
+
+    // Step a calendar day at a time so the wall clock time stays put across DST changes; a
+    // weekly notification is at most a week away
+    for _ in 0..=7 {
+        if !is_weekly || date.weekday() == week_start.weekday() {
+            let next = resolve_local(&zone, target_time.on(date))?;
+            if next > current_time {
+                return Some(next);
+            }
+        }
+        date = date.succ_opt()?;
+    }
+    None
+}
 
-    let next = current_time
-        .date_naive()
-        .and_hms_opt(target_hour, target_minute, 0)?;
+/// Get date range for weekly schedule
+pub fn get_weekly_date_range(now: &DateTime<Local>, week_start: WeekStart) -> (String, String) {
+    let (first, last) = week_bounds(now.date_naive(), week_start);
 
-    let mut next = match Local.from_local_datetime(&next) {
-        chrono::LocalResult::Single(dt) => dt,
+    // Format dates as YYYY-MM-DD
+    let start_date = first.format("%Y-%m-%d").to_string();
+    let end_date = last.format("%Y-%m-%d").to_string();
+
+    (start_date, end_date)
+}
+
+/// Furthest a short `d.m.` date or a week number may resolve from today
+const SHORT_DATE_MAX_DAYS: i64 = 183;
+
+/// Words for days relative to today, in English and in `locale`, with their offsets
+fn relative_day(word: &str, locale: &str) -> Option<i64> {
+    [
+        ("date_yesterday", -1),
+        ("date_today", 0),
+        ("date_tomorrow", 1),
+    ]
+    .into_iter()
+    .find(|(key, _)| {
+        [t!(*key, locale = "en"), t!(*key, locale = locale)]
+            .iter()
+            .any(|name| name.to_lowercase() == word)
+    })
+    .map(|(_, offset)| offset)
+}
+
+/// Weekdays with their English names, which also name their locale keys
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "monday"),
+    (Weekday::Tue, "tuesday"),
+    (Weekday::Wed, "wednesday"),
+    (Weekday::Thu, "thursday"),
+    (Weekday::Fri, "friday"),
+    (Weekday::Sat, "saturday"),
+    (Weekday::Sun, "sunday"),
+];
+
+/// Weekday named by `word`, with full and abbreviated names in English and in `locale`
+fn weekday_named(word: &str, locale: &str) -> Option<Weekday> {
+    WEEKDAYS
+        .into_iter()
+        .find(|(_, english)| {
+            *english == word
+                || english[..3] == *word
+                || t!(format!("day_{english}"), locale = locale).to_lowercase() == word
+                || t!(format!("day_short_{english}"), locale = locale).to_lowercase() == word
+        })
+        .map(|(weekday, _)| weekday)
+}
+
+/// Finnish `d.m.` date resolving to the year that puts it closest to today, or a full `d.m.yyyy`
+fn finnish_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let parts: Vec<&str> = input.trim_end_matches('.').split('.').collect();
+    let (day, month) = match parts.as_slice() {
+        [day, month] | [day, month, _] => (day.parse().ok()?, month.parse().ok()?),
         _ => return None,
     };
-
-    // If we've already passed the target time today, move to tomorrow
-    if next <= current_time {
-        next += Duration::days(1);
+    if let [_, _, year] = parts.as_slice() {
+        return NaiveDate::from_ymd_opt(year.parse().ok()?, month, day);
     }
 
-    // For weekly notifications, ensure it's on Monday
-    if is_weekly {
-        while next.weekday() != chrono::Weekday::Mon {
-            next += Duration::days(1);
-        }
+    (today.year() - 1..=today.year() + 1)
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|date| (*date - today).num_days().abs())
+        .filter(|date| (*date - today).num_days().abs() <= SHORT_DATE_MAX_DAYS)
+}
+
+/// Monday of an ISO week typed as "vko27", "vk 27", "w27" or "week 27", in the week-based
+/// year that puts it closest to today, like a short date
+fn iso_week_monday(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let number = ["viikko", "vko", "vk", "week", "wk", "w"]
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix))?
+        .trim_start_matches(['.', ' ']);
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
     }
+    let week = number.parse().ok()?;
 
-    Some(next)
+    let year = today.iso_week().year();
+    (year - 1..=year + 1)
+        .filter_map(|year| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon))
+        .min_by_key(|date| (*date - today).num_days().abs())
+        .filter(|date| (*date - today).num_days().abs() <= SHORT_DATE_MAX_DAYS)
 }
 
-/// Get date range for weekly schedule (Monday to Sunday)
-pub fn get_weekly_date_range(now: &DateTime<Local>) -> (String, String) {
-    // Calculate Monday of the current week
-    let monday = now
-        .date_naive()
-        .checked_sub_signed(chrono::Duration::days(
-            (now.weekday().num_days_from_monday() % 7) as i64,
-        ))
-        .unwrap_or_else(|| now.date_naive());
-
-    // Calculate Sunday of the current week (Monday + 6 days)
-    let sunday = monday
-        .checked_add_signed(chrono::Duration::days(6))
-        .unwrap_or(monday);
+/// Label of the ISO week containing `date`, e.g. "Week 27" or "Vko 27". Weeks starting on
+/// Sunday take the number of their Monday, so label them by a later day such as their last.
+pub fn week_label(date: NaiveDate, locale: &str) -> String {
+    t!("week_label", week = date.iso_week().week(), locale = locale).to_string()
+}
 
-    // Format dates as YYYY-MM-DD
-    let start_date = monday.format("%Y-%m-%d").to_string();
-    let end_date = sunday.format("%Y-%m-%d").to_string();
+/// Label of the ISO weeks from `first` to `last`, e.g. "Week 27" or "Week 52–3"
+pub fn week_range_label(first: NaiveDate, last: NaiveDate, locale: &str) -> String {
+    let (first_week, last_week) = (first.iso_week(), last.iso_week());
+    if first_week == last_week {
+        return week_label(first, locale);
+    }
+    format!("{}–{}", week_label(first, locale), last_week.week())
+}
 
-    (start_date, end_date)
+/// Parse a date typed by a user: an ISO date, "today", "tomorrow" or "yesterday", a weekday
+/// name, an ISO week like "vko27" or "w27", or a Finnish `d.m.` date. Words are accepted in
+/// English and in `locale`.
+///
+/// A weekday resolves to its next occurrence, which is today when today is that weekday, and a
+/// week to its Monday.
+pub fn parse_user_date(input: &str, today: NaiveDate, locale: &str) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+    if let Some(offset) = relative_day(&input, locale) {
+        return Some(today + Duration::days(offset));
+    }
+    if let Some(weekday) = weekday_named(&input, locale) {
+        let days = weekday.days_since(today.weekday());
+        return Some(today + Duration::days(i64::from(days)));
+    }
+    if let Some(monday) = iso_week_monday(&input, today) {
+        return Some(monday);
+    }
+    finnish_date(&input, today)
 }
 
 #[cfg(test)]
@@ -109,20 +380,181 @@ mod tests {
     use super::*;
     use chrono::{Local, TimeZone};
 
+    #[test]
+    fn test_parse_user_date() {
+        // A Wednesday
+        let today = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2025, m, d);
+
+        for (input, locale, expected) in [
+            ("2025-04-01", "en", date(4, 1)),
+            (" Today ", "en", date(3, 12)),
+            ("tomorrow", "en", date(3, 13)),
+            ("yesterday", "en", date(3, 11)),
+            ("tänään", "fi-FI", date(3, 12)),
+            ("Huomenna", "fi-FI", date(3, 13)),
+            ("eilen", "fi-FI", date(3, 11)),
+            // English words work in either locale
+            ("tomorrow", "fi-FI", date(3, 13)),
+            ("friday", "en", date(3, 14)),
+            ("Fri", "en", date(3, 14)),
+            ("perjantai", "fi-FI", date(3, 14)),
+            ("pe", "fi-FI", date(3, 14)),
+            ("monday", "en", date(3, 17)),
+            ("maanantai", "fi-FI", date(3, 17)),
+            // The weekday of today is today, not a week from now
+            ("wednesday", "en", date(3, 12)),
+            ("keskiviikko", "fi-FI", date(3, 12)),
+            ("24.12.", "fi-FI", NaiveDate::from_ymd_opt(2024, 12, 24)),
+            ("15.9.", "fi-FI", NaiveDate::from_ymd_opt(2024, 9, 15)),
+            ("1.9", "en", date(9, 1)),
+            ("7.3.2026", "fi-FI", NaiveDate::from_ymd_opt(2026, 3, 7)),
+            ("vko12", "fi-FI", date(3, 17)),
+            ("Vko 11", "en", date(3, 10)),
+            ("w27", "en", date(6, 30)),
+            ("week 1", "en", NaiveDate::from_ymd_opt(2024, 12, 30)),
+        ] {
+            assert_eq!(parse_user_date(input, today, locale), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_user_date_rejects_invalid_input() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+
+        for (input, locale) in [
+            ("", "en"),
+            ("someday", "en"),
+            // Finnish words need the Finnish locale
+            ("huomenna", "en"),
+            ("2025-02-30", "en"),
+            ("31.2.", "fi-FI"),
+            // The closest February 29th is over six months away
+            ("29.2.", "fi-FI"),
+            ("1.2.3.4", "fi-FI"),
+            ("vko", "fi-FI"),
+            ("vko54", "fi-FI"),
+            ("w-1", "en"),
+            // 2025 has no week 53 and 2026's is too far away
+            ("w53", "en"),
+        ] {
+            assert_eq!(parse_user_date(input, today, locale), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_week_numbers_across_the_year_boundary() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        // 2024-12-30 is the Monday of 2025's first ISO week
+        assert_eq!(week_label(date(2024, 12, 29), "en"), "Week 52");
+        assert_eq!(week_label(date(2024, 12, 30), "en"), "Week 1");
+        assert_eq!(week_label(date(2025, 1, 5), "fi-FI"), "Vko 1");
+        assert_eq!(week_label(date(2025, 1, 6), "fi-FI"), "Vko 2");
+        // 2020 ended in week 53, which reached into January
+        assert_eq!(week_label(date(2021, 1, 3), "en"), "Week 53");
+        assert_eq!(
+            week_range_label(date(2024, 12, 23), date(2025, 1, 12), "fi-FI"),
+            "Vko 52–2"
+        );
+        assert_eq!(
+            week_range_label(date(2024, 12, 30), date(2025, 1, 5), "en"),
+            "Week 1"
+        );
+
+        // Typed weeks resolve to the week-based year closest to today
+        let new_years_eve = date(2024, 12, 31);
+        assert_eq!(
+            parse_user_date("vko1", new_years_eve, "fi-FI"),
+            Some(date(2024, 12, 30))
+        );
+        assert_eq!(
+            parse_user_date("vko52", new_years_eve, "fi-FI"),
+            Some(date(2024, 12, 23))
+        );
+        assert_eq!(
+            parse_user_date("w53", date(2021, 1, 2), "en"),
+            Some(date(2020, 12, 28))
+        );
+        assert_eq!(
+            parse_user_date("w22", date(2021, 6, 1), "en"),
+            Some(date(2021, 5, 31))
+        );
+    }
+
+    /// A time known to be valid
+    fn at(time: &str) -> TimeOfDay {
+        time.parse().unwrap()
+    }
+
     #[test]
     fn test_parse_time() {
-        // Valid cases
-        assert_eq!(parse_time("00:00"), Some((0, 0)));
-        assert_eq!(parse_time("12:30"), Some((12, 30)));
-        assert_eq!(parse_time("23:59"), Some((23, 59)));
+        // Valid cases, normalized to HH:MM or HH:MM:SS
+        for (input, canonical) in [
+            ("00:00", "00:00"),
+            ("12:30", "12:30"),
+            ("23:59", "23:59"),
+            ("6:00", "06:00"),
+            (" 7:05 ", "07:05"),
+            ("06:00:00", "06:00"),
+            ("06:00:30", "06:00:30"),
+            ("23:59:59", "23:59:59"),
+        ] {
+            assert_eq!(
+                parse_time(input).map(|time| time.to_string()),
+                Ok(canonical.to_string()),
+                "{input}"
+            );
+        }
 
         // Invalid cases
-        assert_eq!(parse_time("24:00"), None); // Hour out of range
-        assert_eq!(parse_time("12:60"), None); // Minute out of range
-        assert_eq!(parse_time("12:30:45"), None); // Too many parts
-        assert_eq!(parse_time("12"), None); // Too few parts
-        assert_eq!(parse_time("12:ab"), None); // Invalid minute
-        assert_eq!(parse_time("ab:30"), None); // Invalid hour
+        assert_eq!(
+            parse_time("24:00"),
+            Err("24:00 is the end of the day; use 00:00 for midnight".to_string())
+        );
+        assert_eq!(
+            parse_time("24:00:00"),
+            Err("24:00 is the end of the day; use 00:00 for midnight".to_string())
+        );
+        for (input, error) in [
+            ("24:30", "hour must be between 0 and 23"),
+            ("12:60", "minutes must be between 00 and 59"),
+            ("12:30:60", "seconds must be between 00 and 59"),
+            ("12:5", "minutes must be two digits"),
+            ("123:00", "hour must be one or two digits"),
+            ("12:30:4", "seconds must be two digits"),
+            ("12:30:45:00", "expected H:MM, HH:MM or HH:MM:SS"),
+            ("12", "expected H:MM, HH:MM or HH:MM:SS"),
+            ("12:ab", "minutes must be two digits"),
+            ("ab:30", "hour must be one or two digits"),
+            ("+6:00", "hour must be one or two digits"),
+            ("", "expected H:MM, HH:MM or HH:MM:SS"),
+        ] {
+            assert_eq!(parse_time(input), Err(error.to_string()), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_time_of_day_serde_uses_the_canonical_form() {
+        let time: TimeOfDay = serde_json::from_str("\"6:00\"").unwrap();
+        assert_eq!(serde_json::to_string(&time).unwrap(), "\"06:00\"");
+        assert!(serde_json::from_str::<TimeOfDay>("\"24:00\"").is_err());
+    }
+
+    #[test]
+    fn test_is_within_time_range() {
+        // Range past midnight
+        assert_eq!(is_within_time_range("22:00-07:00", 23, 30), Some(true));
+        assert_eq!(is_within_time_range("22:00-07:00", 6, 59), Some(true));
+        assert_eq!(is_within_time_range("22:00-07:00", 7, 0), Some(false));
+        assert_eq!(is_within_time_range("22:00-07:00", 12, 0), Some(false));
+
+        // Range within a day
+        assert_eq!(is_within_time_range("12:00 - 13:00", 12, 0), Some(true));
+        assert_eq!(is_within_time_range("12:00-13:00", 13, 0), Some(false));
+
+        assert_eq!(is_within_time_range("22:00", 23, 0), None);
+        assert_eq!(is_within_time_range("22:00-25:00", 23, 0), None);
     }
 
     #[test]
@@ -131,28 +563,25 @@ mod tests {
         let now = Local.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap();
 
         // Test time later today
-        let result = next_daily_time(&now, "15:30").unwrap();
+        let result = next_daily_time(&now, at("15:30")).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-01 15:30"
         );
 
         // Test time earlier today (should be tomorrow)
-        let result = next_daily_time(&now, "09:30").unwrap();
+        let result = next_daily_time(&now, at("09:30")).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 09:30"
         );
 
         // Test exactly current time (should be tomorrow)
-        let result = next_daily_time(&now, "10:00").unwrap();
+        let result = next_daily_time(&now, at("10:00")).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 10:00"
         );
-
-        // Test invalid time
-        assert_eq!(next_daily_time(&now, "25:00"), None);
     }
 
     #[test]
@@ -161,7 +590,7 @@ mod tests {
         let sunday = Local.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap();
 
         // Next Monday from Sunday
-        let result = next_weekly_time(&sunday, "15:30").unwrap();
+        let result = next_weekly_time(&sunday, at("15:30"), WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 15:30"
@@ -171,14 +600,14 @@ mod tests {
         let monday = Local.with_ymd_and_hms(2023, 1, 2, 10, 0, 0).unwrap();
 
         // Test time later on Monday
-        let result = next_weekly_time(&monday, "15:30").unwrap();
+        let result = next_weekly_time(&monday, at("15:30"), WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 15:30"
         );
 
         // Test time earlier on Monday (should be next Monday)
-        let result = next_weekly_time(&monday, "09:30").unwrap();
+        let result = next_weekly_time(&monday, at("09:30"), WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-09 09:30"
@@ -188,7 +617,7 @@ mod tests {
         let wednesday = Local.with_ymd_and_hms(2023, 1, 4, 10, 0, 0).unwrap();
 
         // Next Monday from Wednesday
-        let result = next_weekly_time(&wednesday, "15:30").unwrap();
+        let result = next_weekly_time(&wednesday, at("15:30"), WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-09 15:30"
@@ -201,21 +630,21 @@ mod tests {
         let sunday = Local.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap();
 
         // Daily notification, later today
-        let result = next_notification_time(sunday, "15:30", false).unwrap();
+        let result = next_notification_time(sunday, at("15:30"), false, WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-01 15:30"
         );
 
         // Daily notification, earlier today (should be tomorrow)
-        let result = next_notification_time(sunday, "09:30", false).unwrap();
+        let result = next_notification_time(sunday, at("09:30"), false, WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 09:30"
         );
 
         // Weekly notification on Monday
-        let result = next_notification_time(sunday, "15:30", true).unwrap();
+        let result = next_notification_time(sunday, at("15:30"), true, WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-02 15:30"
@@ -225,7 +654,8 @@ mod tests {
         let wednesday = Local.with_ymd_and_hms(2023, 1, 4, 10, 0, 0).unwrap();
 
         // Weekly notification from Wednesday (should be next Monday)
-        let result = next_notification_time(wednesday, "15:30", true).unwrap();
+        let result =
+            next_notification_time(wednesday, at("15:30"), true, WeekStart::Monday).unwrap();
         assert_eq!(
             result.format("%Y-%m-%d %H:%M").to_string(),
             "2023-01-09 15:30"
@@ -236,20 +666,138 @@ mod tests {
     fn test_get_weekly_date_range() {
         // Monday, 2023-01-02
         let monday = Local.with_ymd_and_hms(2023, 1, 2, 10, 0, 0).unwrap();
-        let (start, end) = get_weekly_date_range(&monday);
+        let (start, end) = get_weekly_date_range(&monday, WeekStart::Monday);
         assert_eq!(start, "2023-01-02");
         assert_eq!(end, "2023-01-08");
 
         // Wednesday, 2023-01-04
         let wednesday = Local.with_ymd_and_hms(2023, 1, 4, 10, 0, 0).unwrap();
-        let (start, end) = get_weekly_date_range(&wednesday);
+        let (start, end) = get_weekly_date_range(&wednesday, WeekStart::Monday);
         assert_eq!(start, "2023-01-02");
         assert_eq!(end, "2023-01-08");
 
         // Sunday, 2023-01-08
         let sunday = Local.with_ymd_and_hms(2023, 1, 8, 10, 0, 0).unwrap();
-        let (start, end) = get_weekly_date_range(&sunday);
+        let (start, end) = get_weekly_date_range(&sunday, WeekStart::Monday);
         assert_eq!(start, "2023-01-02");
         assert_eq!(end, "2023-01-08");
     }
+
+    #[test]
+    fn test_notification_times_across_helsinki_dst_changes() {
+        use chrono_tz::Europe::Helsinki;
+        let local = |y, m, d, h, min| Helsinki.with_ymd_and_hms(y, m, d, h, min, 0).unwrap();
+        let wall = |y, m, d, h, min| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, min, 0)
+                .unwrap()
+        };
+        let show = |dt: DateTime<chrono_tz::Tz>| dt.format("%Y-%m-%d %H:%M %:z").to_string();
+
+        // Clocks spring forward from 03:00 to 04:00 on 2025-03-30, so 03:30 doesn't exist
+        let gap = resolve_local(&Helsinki, wall(2025, 3, 30, 3, 30)).unwrap();
+        assert_eq!(show(gap), "2025-03-30 04:00 +03:00");
+        let result = next_notification_time(
+            local(2025, 3, 29, 12, 0),
+            at("03:30"),
+            false,
+            WeekStart::Monday,
+        )
+        .unwrap();
+        assert_eq!(result, gap);
+        // The night after is back to normal
+        let result = next_notification_time(result, at("03:30"), false, WeekStart::Monday).unwrap();
+        assert_eq!(show(result), "2025-03-31 03:30 +03:00");
+        // 2025-03-30 is a Sunday
+        let result = next_notification_time(
+            local(2025, 3, 27, 12, 0),
+            at("03:30"),
+            true,
+            WeekStart::Sunday,
+        )
+        .unwrap();
+        assert_eq!(result, gap);
+
+        // Clocks fall back from 04:00 to 03:00 on 2025-10-26, so 03:30 happens twice
+        let repeated = resolve_local(&Helsinki, wall(2025, 10, 26, 3, 30)).unwrap();
+        assert_eq!(show(repeated), "2025-10-26 03:30 +03:00");
+        let result = next_notification_time(
+            local(2025, 10, 25, 12, 0),
+            at("03:30"),
+            false,
+            WeekStart::Monday,
+        )
+        .unwrap();
+        assert_eq!(result, repeated);
+        // Only once: the next one is the following night
+        let result = next_notification_time(result, at("03:30"), false, WeekStart::Monday).unwrap();
+        assert_eq!(show(result), "2025-10-27 03:30 +02:00");
+
+        // A day's step keeps the wall clock time instead of adding 24 hours
+        let result = next_notification_time(
+            local(2025, 3, 29, 12, 0),
+            at("09:00"),
+            false,
+            WeekStart::Monday,
+        )
+        .unwrap();
+        assert_eq!(
+            next_daily_time(&local(2025, 3, 29, 12, 0), at("09:00")),
+            Some(wall(2025, 3, 30, 9, 0))
+        );
+        assert_eq!(show(result), "2025-03-30 09:00 +03:00");
+    }
+
+    #[test]
+    fn test_sunday_start_weeks_across_year_boundary() {
+        let date = |value| NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+
+        // Tuesday, 2024-12-31 belongs to the week starting on Sunday, 2024-12-29
+        assert_eq!(
+            week_bounds(date("2024-12-31"), WeekStart::Sunday),
+            (date("2024-12-29"), date("2025-01-04"))
+        );
+        assert_eq!(
+            week_bounds(date("2025-01-04"), WeekStart::Sunday),
+            (date("2024-12-29"), date("2025-01-04"))
+        );
+        assert_eq!(
+            week_bounds(date("2025-01-05"), WeekStart::Sunday),
+            (date("2025-01-05"), date("2025-01-11"))
+        );
+        // The same day in an ISO week
+        assert_eq!(
+            week_bounds(date("2024-12-31"), WeekStart::Monday),
+            (date("2024-12-30"), date("2025-01-05"))
+        );
+
+        // Wednesday, 2025-01-01
+        let new_year = Local.with_ymd_and_hms(2025, 1, 1, 10, 0, 0).unwrap();
+        let (start, end) = get_weekly_date_range(&new_year, WeekStart::Sunday);
+        assert_eq!(start, "2024-12-29");
+        assert_eq!(end, "2025-01-04");
+
+        let result = next_weekly_time(&new_year, at("06:00"), WeekStart::Sunday).unwrap();
+        assert_eq!(
+            result.format("%Y-%m-%d %H:%M").to_string(),
+            "2025-01-05 06:00"
+        );
+
+        // Saturday, 2024-12-28 is the last day of the week
+        let saturday = Local.with_ymd_and_hms(2024, 12, 28, 10, 0, 0).unwrap();
+        let result =
+            next_notification_time(saturday, at("06:00"), true, WeekStart::Sunday).unwrap();
+        assert_eq!(
+            result.format("%Y-%m-%d %H:%M").to_string(),
+            "2024-12-29 06:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_week_start() {
+        assert_eq!("Mon".parse::<WeekStart>(), Ok(WeekStart::Monday));
+        assert_eq!(" sunday ".parse::<WeekStart>(), Ok(WeekStart::Sunday));
+        assert!("tue".parse::<WeekStart>().is_err());
+    }
 }
@@ -0,0 +1,739 @@
+//! Scheduler watchdog.
+//!
+//! Every scheduler loop writes a heartbeat to Redis before it goes to sleep, saying when it
+//! means to wake up and which notification it waits for. While this instance leads, the
+//! watchdog looks at the heartbeats every minute: a scheduler silent for more than twice the
+//! sleep it announced is stale, which is alerted once in the error channel and answered with a
+//! restart of its component. Notifications still unsent when their catch-up window runs out
+//! are reported on their own, since no restart brings them back.
+
+use crate::components::redis_service::{Key, RedisActorHandle};
+use crate::components::{ComponentManager, RestartReport};
+use crate::config::Config;
+use crate::error::{other_error, BotResult};
+use crate::leader::instance_id;
+use crate::probe::SchedulerLiveness;
+use crate::utils::pending::CATCH_UP_WINDOW_SECS;
+use crate::utils::scheduler::{claim_key, NotificationType, SharedContext, CLAIM_TTL_SECS};
+use crate::utils::time::{get_weekly_date_range, WeekStart};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{error, warn};
+
+/// How often the watchdog looks at the heartbeats
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Shortest sleep a heartbeat is judged by, so a scheduler waking up right away gets time to
+/// do its work before it counts as stale
+const MIN_EXPECTED_SLEEP_SECS: i64 = 5 * 60;
+
+/// How long stale heartbeats and missed notifications are kept around for `/status`
+const RETENTION_SECS: i64 = 24 * 60 * 60;
+
+/// A scheduler's report that it's about to sleep
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// Component restarted when the scheduler goes stale
+    pub component: String,
+    /// Instance running the scheduler
+    pub instance: String,
+    /// When the heartbeat was written, as a unix timestamp
+    pub at: i64,
+    /// When the scheduler means to wake up
+    pub wake: i64,
+}
+
+impl Heartbeat {
+    /// When the scheduler counts as stale: twice the sleep it announced after the heartbeat
+    pub fn deadline(&self) -> i64 {
+        self.at + 2 * (self.wake - self.at).max(MIN_EXPECTED_SLEEP_SECS)
+    }
+
+    /// Whether the scheduler has been silent for too long
+    pub fn is_stale(&self, now: i64) -> bool {
+        now > self.deadline()
+    }
+}
+
+/// A notification a scheduler waits for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub notification_type: NotificationType,
+    /// Day of a daily notification, or first day of the week of a weekly one, as claimed
+    pub date: String,
+    /// When the notification is due, as a unix timestamp
+    pub due: i64,
+}
+
+impl Target {
+    /// The notification of a type due at a time
+    pub fn new(
+        notification_type: NotificationType,
+        due: DateTime<Local>,
+        week_start: WeekStart,
+    ) -> Self {
+        let date = match notification_type {
+            NotificationType::Daily => due.format("%Y-%m-%d").to_string(),
+            NotificationType::Weekly => get_weekly_date_range(&due, week_start).0,
+        };
+        Self {
+            notification_type,
+            date,
+            due: due.timestamp(),
+        }
+    }
+
+    /// Redis hash field identifying the notification within its component
+    fn field(&self) -> String {
+        format!("{}:{}", kind(&self.notification_type), self.date)
+    }
+
+    fn from_field(field: &str, due: i64) -> Option<Self> {
+        let (kind, date) = field.split_once(':')?;
+        let notification_type = match kind {
+            "daily" => NotificationType::Daily,
+            "weekly" => NotificationType::Weekly,
+            _ => return None,
+        };
+        Some(Self {
+            notification_type,
+            date: date.to_string(),
+            due,
+        })
+    }
+}
+
+/// Something the watchdog found wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// A scheduler stopped writing heartbeats
+    Stale {
+        scheduler: String,
+        heartbeat: Heartbeat,
+    },
+    /// A notification wasn't sent before its catch-up window ran out
+    Missed { component: String, target: Target },
+}
+
+fn kind(notification_type: &NotificationType) -> &'static str {
+    match notification_type {
+        NotificationType::Daily => "daily",
+        NotificationType::Weekly => "weekly",
+    }
+}
+
+/// Redis key of a scheduler's latest heartbeat
+pub fn heartbeat_key(scheduler: &str) -> BotResult<Key> {
+    Key::fixed("scheduler:heartbeat").segment(scheduler)
+}
+
+/// Redis hash of the notifications a component's scheduler has waited for and not yet seen sent
+fn due_key(component: &str) -> BotResult<Key> {
+    Key::fixed("scheduler:due").segment(component)
+}
+
+/// Redis key claiming the alert about a finding, so it's only posted once
+fn alert_key(finding: &Finding) -> BotResult<Key> {
+    match finding {
+        // A heartbeat written after the alert starts a new episode
+        Finding::Stale {
+            scheduler,
+            heartbeat,
+        } => Ok(Key::fixed("scheduler:alerted")
+            .segment(scheduler)?
+            .id(heartbeat.at.max(0) as u64)),
+        Finding::Missed { component, target } => Key::fixed("scheduler:alerted")
+            .segment(component)?
+            .name(kind(&target.notification_type))
+            .segment(&target.date),
+    }
+}
+
+/// Store a heartbeat, and the notification the scheduler waits for if any
+pub async fn write_heartbeat(
+    redis_handle: &RedisActorHandle,
+    scheduler: &str,
+    heartbeat: &Heartbeat,
+    target: Option<&Target>,
+) -> BotResult<()> {
+    let json = serde_json::to_string(heartbeat)
+        .map_err(|e| other_error(&format!("Failed to serialize the heartbeat: {e}")))?;
+    let ttl = (heartbeat.deadline() - heartbeat.at + RETENTION_SECS) as u64;
+    redis_handle
+        .set_ex(&heartbeat_key(scheduler)?, json, ttl)
+        .await?;
+
+    if let Some(target) = target {
+        let key = due_key(&heartbeat.component)?;
+        redis_handle.hset(&key, &target.field(), target.due).await?;
+        redis_handle.expire(&key, CLAIM_TTL_SECS).await?;
+    }
+    Ok(())
+}
+
+/// Every scheduler's latest heartbeat, by scheduler name. Heartbeats that can't be read are
+/// left out.
+pub async fn load_heartbeats(
+    redis_handle: &RedisActorHandle,
+) -> BotResult<BTreeMap<String, Heartbeat>> {
+    let names: Vec<String> = redis_handle
+        .scan_prefix(&Key::fixed("scheduler:heartbeat"))
+        .await?
+        .iter()
+        .filter_map(|key| Some(key.rsplit(':').next()?.to_string()))
+        .collect();
+    let keys = names
+        .iter()
+        .map(|name| heartbeat_key(name))
+        .collect::<BotResult<Vec<Key>>>()?;
+    if keys.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let stored: Vec<Option<String>> = redis_handle.mget(&keys).await?;
+
+    Ok(names
+        .into_iter()
+        .zip(stored)
+        .filter_map(|(name, json)| Some((name, serde_json::from_str(&json?).ok()?)))
+        .collect())
+}
+
+/// Schedulers of an instance that have gone stale, with how long they've been silent in seconds
+pub async fn stale_schedulers(
+    redis_handle: &RedisActorHandle,
+    instance: &str,
+    now: i64,
+) -> BotResult<BTreeMap<String, i64>> {
+    Ok(load_heartbeats(redis_handle)
+        .await?
+        .into_iter()
+        .filter(|(_, heartbeat)| heartbeat.instance == instance && heartbeat.is_stale(now))
+        .map(|(scheduler, heartbeat)| (scheduler, now - heartbeat.at))
+        .collect())
+}
+
+/// Notifications whose catch-up window has run out without them being claimed as sent.
+///
+/// Sent notifications are forgotten on the way, and so are missed ones once they've been
+/// reported for a day.
+async fn missed_notifications(
+    redis_handle: &RedisActorHandle,
+    now: i64,
+) -> BotResult<Vec<Finding>> {
+    let mut missed = Vec::new();
+    for key in redis_handle
+        .scan_prefix(&Key::fixed("scheduler:due"))
+        .await?
+    {
+        let Some(component) = key.rsplit(':').next() else {
+            continue;
+        };
+        let key = due_key(component)?;
+        let due: HashMap<String, i64> = redis_handle.hgetall(&key).await?;
+        for (field, due) in due {
+            let Some(target) = Target::from_field(&field, due) else {
+                redis_handle.hdel(&key, &field).await?;
+                continue;
+            };
+            let claim = claim_key(component, &target.notification_type, &target.date)?;
+            let sent = redis_handle.get::<Option<i64>>(&claim).await?.is_some();
+            let window_end = target.due + CATCH_UP_WINDOW_SECS;
+            if sent || now > window_end + RETENTION_SECS {
+                redis_handle.hdel(&key, &field).await?;
+            } else if now > window_end {
+                missed.push(Finding::Missed {
+                    component: component.to_string(),
+                    target,
+                });
+            }
+        }
+    }
+    Ok(missed)
+}
+
+/// Everything wrong with the schedulers at `now`: stale heartbeats of any instance and missed
+/// notifications
+pub async fn inspect(redis_handle: &RedisActorHandle, now: i64) -> BotResult<Vec<Finding>> {
+    let mut findings: Vec<Finding> = load_heartbeats(redis_handle)
+        .await?
+        .into_iter()
+        .filter(|(_, heartbeat)| heartbeat.is_stale(now))
+        .map(|(scheduler, heartbeat)| Finding::Stale {
+            scheduler,
+            heartbeat,
+        })
+        .collect();
+    let mut missed = missed_notifications(redis_handle, now).await?;
+    missed.sort_by_key(|finding| match finding {
+        Finding::Missed { target, .. } => target.due,
+        Finding::Stale { .. } => 0,
+    });
+    findings.extend(missed);
+    Ok(findings)
+}
+
+/// Findings to alert about: this instance's stale schedulers, since only it can restart them,
+/// and missed notifications. Each is returned once, even across instances.
+pub async fn new_alerts(
+    redis_handle: &RedisActorHandle,
+    instance: &str,
+    now: i64,
+) -> BotResult<Vec<Finding>> {
+    let mut alerts = Vec::new();
+    for finding in inspect(redis_handle, now).await? {
+        if matches!(&finding, Finding::Stale { heartbeat, .. } if heartbeat.instance != instance) {
+            continue;
+        }
+        if redis_handle
+            .set_nx_ex(&alert_key(&finding)?, now, CLAIM_TTL_SECS)
+            .await?
+        {
+            alerts.push(finding);
+        }
+    }
+    Ok(alerts)
+}
+
+/// Reports a scheduler's sleeps to the probe and as heartbeats to Redis
+pub struct SchedulerHeartbeat {
+    liveness: SchedulerLiveness,
+    scheduler: &'static str,
+    component: String,
+    redis_handle: RedisActorHandle,
+}
+
+impl SchedulerHeartbeat {
+    /// Start reporting for a scheduler of a component
+    pub fn register(
+        scheduler: &'static str,
+        component: &str,
+        redis_handle: RedisActorHandle,
+    ) -> Self {
+        Self {
+            liveness: SchedulerLiveness::register(scheduler),
+            scheduler,
+            component: component.to_string(),
+            redis_handle,
+        }
+    }
+
+    /// Report that the scheduler sleeps until `wake`, waiting for `target` if it's a
+    /// notification. A heartbeat that can't be stored is only logged.
+    pub async fn waiting_until(&self, wake: DateTime<Local>, target: Option<Target>) {
+        self.liveness.waiting_until(wake);
+        let heartbeat = Heartbeat {
+            component: self.component.clone(),
+            instance: instance_id(),
+            at: Utc::now().timestamp(),
+            wake: wake.timestamp(),
+        };
+        if let Err(e) = write_heartbeat(
+            &self.redis_handle,
+            self.scheduler,
+            &heartbeat,
+            target.as_ref(),
+        )
+        .await
+        {
+            warn!("Failed to store the {} heartbeat: {}", self.scheduler, e);
+        }
+    }
+}
+
+/// What the watchdog does about its findings, through the context of a gateway session
+#[async_trait]
+pub trait Responder<C>: Send + Sync {
+    /// Restart a component, or return `None` when it isn't restarted
+    async fn restart(&self, component: &str) -> Option<RestartReport>;
+
+    /// Post an alert in the error channel
+    async fn alert(&self, ctx: &C, embed: CreateEmbed);
+}
+
+/// Restarts the bot's components and posts to its error channel
+struct BotResponder {
+    component_manager: Arc<ComponentManager>,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
+}
+
+#[async_trait]
+impl Responder<serenity::Context> for BotResponder {
+    async fn restart(&self, component: &str) -> Option<RestartReport> {
+        // Disabled components aren't restarted
+        if self.component_manager.is_disabled(component) {
+            return None;
+        }
+        self.component_manager
+            .restart(component, self.redis_handle.clone(), true)
+            .await
+    }
+
+    async fn alert(&self, ctx: &serenity::Context, embed: CreateEmbed) {
+        send_alert(ctx, &self.config, embed).await;
+    }
+}
+
+/// Restart the component of a stale scheduler and alert about the finding. The restarted
+/// component is attached to the manager's latest session, and the alert's context is read right
+/// before sending, so a reconnect since the watchdog started doesn't leave it posting through
+/// the old session.
+pub async fn respond<C: Send + Sync>(
+    ctx: &SharedContext<C>,
+    responder: &impl Responder<C>,
+    finding: &Finding,
+) {
+    let embed = match finding {
+        Finding::Stale {
+            scheduler,
+            heartbeat,
+        } => {
+            error!(
+                "Scheduler {} has been silent since {}, restarting {}",
+                scheduler, heartbeat.at, heartbeat.component
+            );
+            let restart = responder.restart(&heartbeat.component).await;
+            stale_embed(scheduler, heartbeat, restart)
+        }
+        Finding::Missed { component, target } => {
+            error!(
+                "The {} {} notification for {} was missed",
+                component,
+                kind(&target.notification_type),
+                target.date
+            );
+            missed_embed(component, target)
+        }
+    };
+    responder.alert(&*ctx.current().await, embed).await;
+}
+
+/// Look at the heartbeats until the task is aborted when leadership ends. Findings are
+/// answered through the context of the latest gateway session.
+pub async fn run_watchdog(
+    component_manager: Arc<ComponentManager>,
+    config: Arc<RwLock<Config>>,
+    redis_handle: RedisActorHandle,
+) {
+    let instance = instance_id();
+    let responder = BotResponder {
+        component_manager,
+        config,
+        redis_handle,
+    };
+    let mut interval = tokio::time::interval(WATCHDOG_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = Utc::now().timestamp();
+        let alerts = match new_alerts(&responder.redis_handle, &instance, now).await {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                warn!("Watchdog failed to check the heartbeats: {}", e);
+                continue;
+            }
+        };
+        let Some(ctx) = responder.component_manager.session().await else {
+            continue;
+        };
+        for finding in alerts {
+            respond(&ctx, &responder, &finding).await;
+        }
+    }
+}
+
+fn stale_embed(
+    scheduler: &str,
+    heartbeat: &Heartbeat,
+    restart: Option<RestartReport>,
+) -> CreateEmbed {
+    let mut description = t!(
+        "watchdog_stale",
+        scheduler = scheduler,
+        since = format!("<t:{}:R>", heartbeat.at),
+        wake = format!("<t:{}:R>", heartbeat.wake)
+    )
+    .to_string();
+    // Disabled components aren't restarted
+    let outcome = match restart.map(|report| report.init_error) {
+        Some(None) => t!("watchdog_restarted", component = heartbeat.component),
+        Some(Some(error)) => t!(
+            "watchdog_restart_failed",
+            component = heartbeat.component,
+            error = error
+        ),
+        None => t!("watchdog_not_restarted", component = heartbeat.component),
+    };
+    description.push_str(&format!("\n\n{outcome}"));
+    CreateEmbed::new()
+        .title(t!("watchdog_stale_title"))
+        .description(description)
+        .color(0xFF_00_00)
+}
+
+fn missed_embed(component: &str, target: &Target) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(t!("watchdog_missed_title"))
+        .description(t!(
+            "watchdog_missed",
+            component = component,
+            kind = t!(format!("watchdog_kind_{}", kind(&target.notification_type))),
+            date = target.date,
+            due = format!("<t:{}:f>", target.due)
+        ))
+        .color(0xFF_A5_00)
+}
+
+async fn send_alert(ctx: &serenity::Context, config: &Arc<RwLock<Config>>, embed: CreateEmbed) {
+    let Some(channel_id) = config.read().await.error_channel_id else {
+        return;
+    };
+    if let Err(e) = ChannelId::new(channel_id)
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await
+    {
+        error!("Failed to send watchdog alert: {}", e);
+    }
+}
+
+/// A line of `/status` about a finding
+pub fn status_line(finding: &Finding) -> String {
+    match finding {
+        Finding::Stale {
+            scheduler,
+            heartbeat,
+        } => t!(
+            "status_scheduler_stale",
+            scheduler = scheduler,
+            since = format!("<t:{}:R>", heartbeat.at),
+            wake = format!("<t:{}:R>", heartbeat.wake)
+        )
+        .to_string(),
+        Finding::Missed { component, target } => t!(
+            "status_notification_missed",
+            component = component,
+            kind = t!(format!("watchdog_kind_{}", kind(&target.notification_type))),
+            date = target.date
+        )
+        .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scheduler::claim_in_redis;
+    use std::sync::Mutex;
+
+    const NOW: i64 = 1_741_680_000;
+
+    fn heartbeat(instance: &str, at: i64, wake: i64) -> Heartbeat {
+        Heartbeat {
+            component: "work_schedule".to_string(),
+            instance: instance.to_string(),
+            at,
+            wake,
+        }
+    }
+
+    fn daily(date: &str, due: i64) -> Target {
+        Target {
+            notification_type: NotificationType::Daily,
+            date: date.to_string(),
+            due,
+        }
+    }
+
+    #[test]
+    fn test_stale_after_twice_the_sleep() {
+        // Sleeping for an hour, so silent for two is stale
+        let hourly = heartbeat("a", NOW, NOW + 3600);
+        assert!(!hourly.is_stale(NOW + 7200));
+        assert!(hourly.is_stale(NOW + 7201));
+
+        // Short sleeps are given a few minutes anyway
+        let busy = heartbeat("a", NOW, NOW);
+        assert!(!busy.is_stale(NOW + 300));
+        assert!(busy.is_stale(NOW + 601));
+    }
+
+    #[tokio::test]
+    async fn test_stale_heartbeat_is_alerted_once() {
+        let redis_handle = RedisActorHandle::fake();
+        let sleeping = heartbeat("leader", NOW, NOW + 600);
+        write_heartbeat(&redis_handle, "work_schedule", &sleeping, None)
+            .await
+            .unwrap();
+        // Another instance's stale scheduler is its own to restart
+        let other = Heartbeat {
+            component: "digest".to_string(),
+            ..heartbeat("follower", NOW, NOW + 600)
+        };
+        write_heartbeat(&redis_handle, "digest", &other, None)
+            .await
+            .unwrap();
+
+        // Asleep as announced
+        assert!(new_alerts(&redis_handle, "leader", NOW + 1200)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let later = NOW + 1300;
+        let stale = stale_schedulers(&redis_handle, "leader", later)
+            .await
+            .unwrap();
+        assert_eq!(stale, BTreeMap::from([("work_schedule".to_string(), 1300)]));
+        assert_eq!(inspect(&redis_handle, later).await.unwrap().len(), 2);
+
+        let alerts = new_alerts(&redis_handle, "leader", later).await.unwrap();
+        assert_eq!(
+            alerts,
+            [Finding::Stale {
+                scheduler: "work_schedule".to_string(),
+                heartbeat: sleeping,
+            }]
+        );
+        assert!(new_alerts(&redis_handle, "leader", later + 60)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Once the scheduler beats again, going stale again is a new alert
+        let restarted = heartbeat("leader", later + 60, later + 120);
+        write_heartbeat(&redis_handle, "work_schedule", &restarted, None)
+            .await
+            .unwrap();
+        assert!(new_alerts(&redis_handle, "leader", later + 120)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            new_alerts(&redis_handle, "leader", later + 1000)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missed_notification_is_reported_distinctly() {
+        let redis_handle = RedisActorHandle::fake();
+        let due = NOW - CATCH_UP_WINDOW_SECS - 60;
+        let waiting = heartbeat("leader", NOW - 60, NOW + 3600);
+        write_heartbeat(
+            &redis_handle,
+            "work_schedule",
+            &waiting,
+            Some(&daily("2025-03-10", due)),
+        )
+        .await
+        .unwrap();
+        write_heartbeat(
+            &redis_handle,
+            "work_schedule",
+            &waiting,
+            Some(&daily("2025-03-11", NOW - 60)),
+        )
+        .await
+        .unwrap();
+        write_heartbeat(
+            &redis_handle,
+            "work_schedule",
+            &waiting,
+            Some(&daily("2025-03-09", due - 86_400)),
+        )
+        .await
+        .unwrap();
+        // The 9th was sent, and today's is still within its catch-up window
+        claim_in_redis(
+            &redis_handle,
+            "work_schedule",
+            &NotificationType::Daily,
+            "2025-03-09",
+        )
+        .await
+        .unwrap();
+
+        let missed = Finding::Missed {
+            component: "work_schedule".to_string(),
+            target: daily("2025-03-10", due),
+        };
+        assert_eq!(
+            new_alerts(&redis_handle, "leader", NOW).await.unwrap(),
+            std::slice::from_ref(&missed)
+        );
+        assert!(new_alerts(&redis_handle, "leader", NOW + 60)
+            .await
+            .unwrap()
+            .is_empty());
+        // Still shown in /status until it's a day old
+        assert_eq!(inspect(&redis_handle, NOW + 60).await.unwrap(), [missed]);
+        assert!(inspect(&redis_handle, due + CATCH_UP_WINDOW_SECS + RETENTION_SECS + 1)
+            .await
+            .unwrap()
+            .iter()
+            .all(|finding| !matches!(finding, Finding::Missed { target, .. } if target.date == "2025-03-10")));
+
+        // The sent notification was forgotten
+        let fields: HashMap<String, i64> = redis_handle
+            .hgetall(&due_key("work_schedule").unwrap())
+            .await
+            .unwrap();
+        assert!(!fields.contains_key("daily:2025-03-09"));
+    }
+
+    /// Records the restarted components and which context each alert went through
+    #[derive(Default)]
+    struct RecordingResponder {
+        restarts: Mutex<Vec<String>>,
+        alerts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Responder<String> for RecordingResponder {
+        async fn restart(&self, component: &str) -> Option<RestartReport> {
+            self.restarts.lock().unwrap().push(component.to_string());
+            None
+        }
+
+        async fn alert(&self, ctx: &String, _embed: CreateEmbed) {
+            self.alerts.lock().unwrap().push(ctx.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_alert_after_reconnect_uses_new_context() {
+        let slot = RwLock::new(None);
+        SharedContext::attach(&slot, Arc::new("first ready".to_string())).await;
+        // The watchdog holds a clone from when it started leading
+        let ctx = slot.read().await.clone().unwrap();
+        let responder = RecordingResponder::default();
+        let stale = Finding::Stale {
+            scheduler: "work_schedule".to_string(),
+            heartbeat: heartbeat("leader", NOW, NOW + 600),
+        };
+
+        respond(&ctx, &responder, &stale).await;
+
+        // A second ready event attaches the new session's context
+        SharedContext::attach(&slot, Arc::new("second ready".to_string())).await;
+        respond(&ctx, &responder, &stale).await;
+
+        assert_eq!(
+            *responder.restarts.lock().unwrap(),
+            ["work_schedule", "work_schedule"]
+        );
+        assert_eq!(
+            *responder.alerts.lock().unwrap(),
+            ["first ready", "second ready"]
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Monthly archive of the schedules, their source images and the audit trail, for labor
+//! inspection requests.
+//!
+//! The zip is written while it's sent, by async_zip's streaming writer: entries are stored as
+//! they are, with their sizes and CRCs in data descriptors after the data, so no entry is held
+//! in memory or read twice. The images are compressed already, and the CSV and audit trail of
+//! a month are small.
+
+use crate::components::work_schedule::audit::AuditRecord;
+use crate::components::work_schedule::uploads::StoredUpload;
+use async_zip::error::ZipError;
+use async_zip::{Compression, ZipDateTime, ZipEntryBuilder};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::AsyncWriteExt as _;
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use tracing::{error, info, warn};
+
+use crate::web::auth::JwtAuth;
+use crate::web::model::{WorkHoursDb, WorkSchedule};
+use crate::web::schedule_api::{all_schedules, export_csv};
+use crate::web::AppState;
+
+/// Bytes read and sent at a time, which also bounds the buffer between the writer and the
+/// response
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Name of the manifest entry, written last
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// A calendar month the archive covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveMonth {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl ArchiveMonth {
+    /// Read a YYYY-MM month
+    pub fn parse(value: &str) -> Option<Self> {
+        let first = NaiveDate::parse_from_str(&format!("{}-01", value.trim()), "%Y-%m-%d").ok()?;
+        let last = first
+            .checked_add_months(chrono::Months::new(1))?
+            .pred_opt()?;
+        Some(Self { first, last })
+    }
+
+    /// Whether a YYYY-MM-DD date falls in the month
+    fn contains(&self, date: &str) -> bool {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .is_ok_and(|date| self.first <= date && date <= self.last)
+    }
+}
+
+impl fmt::Display for ArchiveMonth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first.format("%Y-%m"))
+    }
+}
+
+/// An archived file with its size and checksum
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub size: u64,
+    /// Hex SHA-256 of the file's contents
+    pub sha256: String,
+}
+
+/// What the archive holds, written as its last entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The archived month (YYYY-MM)
+    pub month: String,
+    pub generated_at: DateTime<Utc>,
+    /// Every other entry, in the order they were written
+    pub files: Vec<ManifestFile>,
+    /// Uploads recorded for the month whose image file couldn't be read
+    pub missing_images: Vec<String>,
+}
+
+/// Writes a zip archive entry by entry as its data is read, noting each entry's checksum
+pub struct ZipWriter<W: AsyncWrite + Unpin> {
+    zip: async_zip::tokio::write::ZipFileWriter<W>,
+    modified: ZipDateTime,
+}
+
+fn zip_error(e: ZipError) -> io::Error {
+    match e {
+        ZipError::UpstreamReadError(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+impl<W: AsyncWrite + Unpin> ZipWriter<W> {
+    /// Start an archive whose entries are dated `modified`
+    pub fn new(out: W, modified: DateTime<Utc>) -> Self {
+        Self {
+            zip: async_zip::tokio::write::ZipFileWriter::with_tokio(out),
+            modified: ZipDateTime::from_chrono(&modified),
+        }
+    }
+
+    /// Write an entry, reading its data to the end
+    pub async fn write_entry<R: AsyncRead + Unpin>(
+        &mut self,
+        name: &str,
+        mut data: R,
+    ) -> io::Result<ManifestFile> {
+        let entry = ZipEntryBuilder::new(name.to_string().into(), Compression::Stored)
+            .last_modification_date(self.modified);
+        let mut writer = self
+            .zip
+            .write_entry_stream(entry)
+            .await
+            .map_err(zip_error)?;
+
+        let mut sha = Context::new(&SHA256);
+        let mut size = 0u64;
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            let n = data.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            sha.update(&buf[..n]);
+            size += n as u64;
+            writer.write_all(&buf[..n]).await?;
+        }
+        writer.close().await.map_err(zip_error)?;
+
+        let sha256 = sha
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Ok(ManifestFile {
+            name: name.to_string(),
+            size,
+            sha256,
+        })
+    }
+
+    /// Write the central directory, ending the archive
+    pub async fn finish(self) -> io::Result<W> {
+        let mut out = self.zip.close().await.map_err(zip_error)?.into_inner();
+        out.flush().await?;
+        Ok(out)
+    }
+}
+
+/// What goes into a month's archive, read before anything is sent so a database failure can
+/// still be answered with an error
+pub struct ArchiveSources {
+    pub month: ArchiveMonth,
+    pub schedules: Vec<WorkSchedule>,
+    /// Uploads of schedules overlapping the month, one per image
+    pub uploads: Vec<StoredUpload>,
+    /// Audit records of the month's days, oldest first
+    pub audit: Vec<AuditRecord>,
+}
+
+impl ArchiveSources {
+    /// Read the month's schedules, uploads and audit records
+    pub async fn load(db: &dyn WorkHoursDb, month: ArchiveMonth) -> Result<Self, String> {
+        let first = month.first.format("%Y-%m-%d").to_string();
+        let last = month.last.format("%Y-%m-%d").to_string();
+
+        let mut uploads: Vec<StoredUpload> = db
+            .list_uploads()
+            .await?
+            .into_iter()
+            .filter(|upload| upload.covers(&first, &last))
+            .filter(|upload| StoredUpload::is_valid_file_name(&upload.file_name))
+            .collect();
+        uploads.sort_by_key(|upload| upload.uploaded_at);
+        let mut seen = HashSet::new();
+        uploads.retain(|upload| seen.insert(upload.file_name.clone()));
+
+        let audit = db
+            .list_audit()
+            .await?
+            .into_iter()
+            .filter(|record| month.contains(&record.date))
+            .collect();
+
+        Ok(Self {
+            month,
+            schedules: all_schedules(db).await?,
+            uploads,
+            audit,
+        })
+    }
+}
+
+/// Write a month's archive: `schedules.csv` as the CSV export has it, the month's images under
+/// `images/`, its audit records as `audit.jsonl` and a manifest with the checksums of them all
+pub async fn write_archive<W: AsyncWrite + Unpin>(
+    sources: ArchiveSources,
+    upload_dir: &std::path::Path,
+    now: DateTime<Utc>,
+    out: W,
+) -> Result<W, String> {
+    let write_error = |e: io::Error| format!("Failed to write the archive: {e}");
+    let mut zip = ZipWriter::new(out, now);
+    let mut files = Vec::new();
+
+    let month = sources.month;
+    let csv = export_csv(&sources.schedules, Some(month.first), Some(month.last))?;
+    files.push(
+        zip.write_entry("schedules.csv", csv.as_slice())
+            .await
+            .map_err(write_error)?,
+    );
+
+    let mut missing_images = Vec::new();
+    for upload in &sources.uploads {
+        let path = upload_dir.join(&upload.file_name);
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => files.push(
+                zip.write_entry(&format!("images/{}", upload.file_name), file)
+                    .await
+                    .map_err(write_error)?,
+            ),
+            Err(e) => {
+                warn!("Failed to open schedule image {}: {}", upload.file_name, e);
+                missing_images.push(upload.file_name.clone());
+            }
+        }
+    }
+
+    let mut audit = Vec::new();
+    for record in &sources.audit {
+        serde_json::to_writer(&mut audit, record)
+            .map_err(|e| format!("Failed to serialize audit record: {e}"))?;
+        audit.push(b'\n');
+    }
+    files.push(
+        zip.write_entry("audit.jsonl", audit.as_slice())
+            .await
+            .map_err(write_error)?,
+    );
+
+    let manifest = Manifest {
+        month: month.to_string(),
+        generated_at: now,
+        files,
+        missing_images,
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize the manifest: {e}"))?;
+    zip.write_entry(MANIFEST_NAME, manifest.as_slice())
+        .await
+        .map_err(write_error)?;
+
+    zip.finish().await.map_err(write_error)
+}
+
+/// Handler streaming the archive of a month, `GET /api/v1/archive/{YYYY-MM}.zip` (admin only).
+///
+/// The archive is written as it's sent; a failure after the first bytes can only end the
+/// download early, which leaves a zip without its central directory.
+pub async fn archive_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(file_name): Path<String>,
+) -> Response {
+    if !auth.claims.is_admin() {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let Some(month) = file_name.strip_suffix(".zip").and_then(ArchiveMonth::parse) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let sources = match ArchiveSources::load(state.db.as_ref(), month).await {
+        Ok(sources) => sources,
+        Err(e) => {
+            error!("Failed to read the archive of {}: {}", month, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let (writer, reader) = tokio::io::duplex(CHUNK_SIZE);
+    let upload_dir = state.upload_dir.clone();
+    tokio::spawn(async move {
+        match write_archive(sources, &upload_dir, Utc::now(), writer).await {
+            Ok(_) => info!("Sent the archive of {} to {}", month, auth.claims.sub),
+            Err(e) => error!("Failed to send the archive of {}: {}", month, e),
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"schedules-{month}.zip\""),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::models::ShiftRange;
+    use crate::web::model::{InMemoryDb, WorkDay};
+    use async_zip::base::read::mem::ZipFileReader;
+    use std::sync::Arc;
+
+    /// Read the entries of an archive back with async_zip's reader, which checks each entry's
+    /// CRC
+    async fn read_zip(data: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+        let zip = ZipFileReader::new(data).await.unwrap();
+        let mut entries = Vec::new();
+        for index in 0..zip.file().entries().len() {
+            let mut reader = zip.reader_with_entry(index).await.unwrap();
+            let name = reader.entry().filename().as_str().unwrap().to_string();
+            let mut contents = Vec::new();
+            reader.read_to_end_checked(&mut contents).await.unwrap();
+            entries.push((name, contents));
+        }
+        entries
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        ring::digest::digest(&SHA256, data)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn day(date: &str) -> WorkDay {
+        WorkDay {
+            date: date.to_string(),
+            shifts: vec![ShiftRange::new("07:00", "15:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        }
+    }
+
+    fn upload(file_name: &str, start_date: &str, end_date: &str) -> StoredUpload {
+        StoredUpload {
+            employee: "Anna".to_string(),
+            file_name: file_name.to_string(),
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+            uploaded_at: 1_740_000_000,
+            image_hash: None,
+        }
+    }
+
+    fn audit(date: &str) -> AuditRecord {
+        AuditRecord {
+            seq: 0,
+            at: 1_740_000_000,
+            employee: "Anna".to_string(),
+            date: date.to_string(),
+            before: None,
+            after: Some(day(date).to_entry()),
+            source: "json_import".to_string(),
+            actor: Some("admin".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_archive_month() {
+        let month = ArchiveMonth::parse("2024-02").unwrap();
+        assert_eq!(month.last, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert!(month.contains("2024-02-01") && !month.contains("2024-03-01"));
+        assert_eq!(month.to_string(), "2024-02");
+        assert_eq!(ArchiveMonth::parse("2024-13"), None);
+        assert_eq!(ArchiveMonth::parse("2024-02-01"), None);
+    }
+
+    #[tokio::test]
+    async fn test_archive_entries_and_manifest() {
+        let db = Arc::new(InMemoryDb::default());
+        let mut schedule = WorkSchedule::new("Anna".to_string());
+        schedule.days = vec![day("2025-02-28"), day("2025-03-03"), day("2025-03-31")];
+        db.set_schedule("Anna", &schedule).await.unwrap();
+        for stored in [
+            upload("anna-march.png", "2025-03-03", "2025-03-16"),
+            upload("anna-gone.png", "2025-03-17", "2025-03-30"),
+            upload("anna-feb.png", "2025-02-01", "2025-02-28"),
+        ] {
+            db.record_upload(&stored).await.unwrap();
+        }
+        db.record_audit(vec![audit("2025-02-28"), audit("2025-03-03")])
+            .await
+            .unwrap();
+
+        let upload_dir =
+            std::env::temp_dir().join(format!("work_hours_archive_{}", std::process::id()));
+        std::fs::create_dir_all(&upload_dir).unwrap();
+        std::fs::write(upload_dir.join("anna-march.png"), b"\x89PNG\r\n\x1a\nmarch").unwrap();
+        std::fs::write(upload_dir.join("anna-feb.png"), b"\x89PNG\r\n\x1a\nfeb").unwrap();
+
+        let month = ArchiveMonth::parse("2025-03").unwrap();
+        let sources = ArchiveSources::load(db.as_ref(), month).await.unwrap();
+        let now = "2025-04-01T12:00:00Z".parse().unwrap();
+        let zip = write_archive(sources, &upload_dir, now, Vec::new())
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&upload_dir).unwrap();
+
+        let entries = read_zip(zip).await;
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "schedules.csv",
+                "images/anna-march.png",
+                "audit.jsonl",
+                "manifest.json"
+            ]
+        );
+
+        let csv = String::from_utf8(entries[0].1.clone()).unwrap();
+        assert!(csv.contains("2025-03-03") && csv.contains("2025-03-31"));
+        assert!(!csv.contains("2025-02-28"));
+        assert_eq!(entries[1].1, b"\x89PNG\r\n\x1a\nmarch");
+        let audit = String::from_utf8(entries[2].1.clone()).unwrap();
+        assert_eq!(audit.lines().count(), 1);
+        assert!(audit.contains("\"date\":\"2025-03-03\""));
+
+        let manifest: Manifest = serde_json::from_slice(&entries[3].1).unwrap();
+        assert_eq!(manifest.month, "2025-03");
+        assert_eq!(manifest.generated_at, now);
+        assert_eq!(manifest.missing_images, ["anna-gone.png"]);
+        let listed: Vec<ManifestFile> = entries[..3]
+            .iter()
+            .map(|(name, contents)| ManifestFile {
+                name: name.clone(),
+                size: contents.len() as u64,
+                sha256: sha256(contents),
+            })
+            .collect();
+        assert_eq!(manifest.files, listed);
+    }
+}
@@ -9,6 +9,7 @@ use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use subtle::ConstantTimeEq;
 use tracing::error;
 
 /// User credentials structure
@@ -18,6 +19,15 @@ pub struct Credentials {
     pub password: String,
 }
 
+/// Role of employee-scoped magic link tokens
+pub const EMPLOYEE_ROLE: &str = "employee";
+
+/// Role of the bot's static service token, which may only use the upload API
+pub const SERVICE_ROLE: &str = "service";
+
+/// Magic links are long-lived; revoke them by bumping the employee's token version
+const MAGIC_LINK_EXPIRATION_DAYS: i64 = 365;
+
 /// JWT claims structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -31,6 +41,26 @@ pub struct Claims {
     pub exp: usize,
     /// Issued at (as UTC timestamp)
     pub iat: usize,
+    /// Token version for employee-scoped tokens, compared against the stored version
+    #[serde(default)]
+    pub ver: Option<u64>,
+}
+
+impl Claims {
+    /// Check if the token belongs to an admin
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    /// Check if the token is an employee-scoped magic link token
+    pub fn is_employee_scoped(&self) -> bool {
+        self.role == EMPLOYEE_ROLE
+    }
+
+    /// Check if the token is the bot's service token
+    pub fn is_service(&self) -> bool {
+        self.role == SERVICE_ROLE
+    }
 }
 
 /// Authentication configuration
@@ -44,6 +74,8 @@ pub struct AuthConfig {
     pub admin_username: String,
     /// Admin password
     pub admin_password: String,
+    /// Static token the bot uses for the upload API, which doesn't expire like a JWT
+    pub service_token: Option<String>,
 }
 
 impl Default for AuthConfig {
@@ -55,6 +87,9 @@ impl Default for AuthConfig {
             admin_username: std::env::var("ADMIN_USERNAME").unwrap_or_else(|_| "admin".to_string()),
             admin_password: std::env::var("ADMIN_PASSWORD")
                 .unwrap_or_else(|_| "password".to_string()),
+            service_token: std::env::var("WORK_HOURS_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
         }
     }
 }
@@ -94,6 +129,11 @@ impl IntoResponse for AuthError {
     }
 }
 
+/// Compare a presented token with the expected one in constant time
+pub fn tokens_match(token: &str, expected: &str) -> bool {
+    token.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
 /// JWT extractor for authentication
 #[derive(Debug, Clone)]
 pub struct JwtAuth {
@@ -181,6 +221,7 @@ impl AuthService {
             role: role.to_string(),
             exp: exp.timestamp() as usize,
             iat: now.timestamp() as usize,
+            ver: None,
         };
 
         encode(
@@ -191,8 +232,47 @@ impl AuthService {
         .map_err(|e| format!("Failed to generate token: {e}"))
     }
 
-    /// Validate a JWT token
+    /// Generate a long-lived magic link token scoped to a single employee
+    pub fn generate_magic_link_token(
+        &self,
+        employee_name: &str,
+        version: u64,
+    ) -> Result<String, String> {
+        let now = Utc::now();
+        let exp = now + Duration::days(MAGIC_LINK_EXPIRATION_DAYS);
+
+        let claims = Claims {
+            sub: employee_name.to_string(),
+            name: Some(employee_name.to_string()),
+            role: EMPLOYEE_ROLE.to_string(),
+            exp: exp.timestamp() as usize,
+            iat: now.timestamp() as usize,
+            ver: Some(version),
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.config.jwt_secret.as_bytes()),
+        )
+        .map_err(|e| format!("Failed to generate magic link token: {e}"))
+    }
+
+    /// Validate a JWT token, or the static service token
     pub fn validate_token(&self, token: &str) -> Result<Claims, AuthError> {
+        if let Some(service_token) = &self.config.service_token {
+            if tokens_match(token, service_token) {
+                return Ok(Claims {
+                    sub: SERVICE_ROLE.to_string(),
+                    name: None,
+                    role: SERVICE_ROLE.to_string(),
+                    exp: usize::MAX,
+                    iat: 0,
+                    ver: None,
+                });
+            }
+        }
+
         decode::<Claims>(
             token,
             &DecodingKey::from_secret(self.config.jwt_secret.as_bytes()),
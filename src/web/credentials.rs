@@ -0,0 +1,340 @@
+//! Daily check of the API keys uploads are parsed with.
+//!
+//! A revoked LlamaIndex key or a disabled Gemini project would otherwise only show up as a
+//! failed upload. Each configured key is tried with a cheap authenticated listing once a day,
+//! the result is stored for the bot's `/status`, and a key that starts being refused is
+//! reported to the Discord webhook.
+
+use crate::components::work_schedule::credentials::{CredentialState, CredentialStatus};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::{Client, StatusCode};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::web::model::WorkHoursDb;
+use crate::web::parser::llamaindex::LLAMA_PARSING_ENDPOINT_EU;
+
+/// How often the task looks for keys due for a check
+const CHECK_TICK: Duration = Duration::from_secs(60 * 60);
+
+/// How long a single check may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Gemini's model listing, the cheapest request needing the key
+const GEMINI_MODELS_URL: &str =
+    "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1";
+
+/// A provider whose key uploads need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyProvider {
+    LlamaIndex,
+    Gemini,
+}
+
+impl KeyProvider {
+    const ALL: [KeyProvider; 2] = [KeyProvider::LlamaIndex, KeyProvider::Gemini];
+
+    /// Name of the provider as stored and given in `SKIP_CREDENTIAL_CHECKS`
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyProvider::LlamaIndex => "llamaindex",
+            KeyProvider::Gemini => "gemini",
+        }
+    }
+
+    /// Environment variable holding the key
+    fn env_var(self) -> &'static str {
+        match self {
+            KeyProvider::LlamaIndex => "LLAMA_API_KEY",
+            KeyProvider::Gemini => "GEMINI_API_KEY",
+        }
+    }
+
+    /// What an answer to the check request says about the key
+    fn classify(self, status: StatusCode) -> CredentialState {
+        match status {
+            status if status.is_success() => CredentialState::Ok,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => CredentialState::AuthFailed,
+            // Gemini answers an invalid key with 400 API_KEY_INVALID
+            StatusCode::BAD_REQUEST if self == KeyProvider::Gemini => CredentialState::AuthFailed,
+            _ => CredentialState::Unreachable,
+        }
+    }
+}
+
+/// Providers to check: those with a key set, less the comma-separated names in `skip`
+pub fn providers_to_check(skip: &str, is_set: impl Fn(&str) -> bool) -> Vec<KeyProvider> {
+    let skipped: Vec<String> = skip
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+    KeyProvider::ALL
+        .into_iter()
+        .filter(|provider| is_set(provider.env_var()))
+        .filter(|provider| !skipped.iter().any(|name| name == provider.name()))
+        .collect()
+}
+
+/// Checks keys and reports refused ones, so the schedule of checks can be tested without
+/// calling the providers
+#[async_trait]
+pub trait CredentialProbe: Send + Sync {
+    /// Try the provider's key
+    async fn check(&self, provider: KeyProvider) -> CredentialState;
+
+    /// Report a key that started being refused
+    async fn alert(&self, message: &str) -> Result<(), String>;
+}
+
+/// Checks keys against the providers and alerts through a Discord webhook, if one is set
+pub struct HttpProbe {
+    client: Client,
+    webhook_url: Option<String>,
+}
+
+impl HttpProbe {
+    /// Probe alerting to `CREDENTIAL_ALERT_WEBHOOK_URL`, if it's set
+    pub fn from_env() -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(CHECK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            webhook_url: env::var("CREDENTIAL_ALERT_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+        }
+    }
+}
+
+#[async_trait]
+impl CredentialProbe for HttpProbe {
+    async fn check(&self, provider: KeyProvider) -> CredentialState {
+        let Ok(key) = env::var(provider.env_var()) else {
+            return CredentialState::AuthFailed;
+        };
+        let request = match provider {
+            KeyProvider::LlamaIndex => self
+                .client
+                .get(format!("{LLAMA_PARSING_ENDPOINT_EU}jobs/?limit=1"))
+                .bearer_auth(key),
+            KeyProvider::Gemini => self
+                .client
+                .get(GEMINI_MODELS_URL)
+                .header("x-goog-api-key", key),
+        };
+        match request.send().await {
+            Ok(response) => provider.classify(response.status()),
+            Err(e) => {
+                warn!("Failed to reach {} for a key check: {}", provider.name(), e);
+                CredentialState::Unreachable
+            }
+        }
+    }
+
+    async fn alert(&self, message: &str) -> Result<(), String> {
+        let Some(url) = &self.webhook_url else {
+            return Ok(());
+        };
+        self.client
+            .post(url)
+            .json(&serde_json::json!({ "content": message }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| format!("Webhook error: {e}"))
+    }
+}
+
+/// Check the keys of `providers` whose last check is a day old, storing the results and
+/// alerting on keys that started being refused
+pub async fn run_credential_checks(
+    db: &dyn WorkHoursDb,
+    probe: &dyn CredentialProbe,
+    providers: &[KeyProvider],
+    now: i64,
+) -> Result<(), String> {
+    let statuses = db.list_credential_statuses().await?;
+    for provider in providers {
+        let previous = statuses
+            .iter()
+            .find(|status| status.provider == provider.name());
+        if previous.is_some_and(|previous| !previous.is_due(now)) {
+            continue;
+        }
+
+        let state = probe.check(*provider).await;
+        let (status, alert) = CredentialStatus::next(previous, provider.name(), state, now);
+        match state {
+            CredentialState::Ok => info!("The {} API key works", provider.name()),
+            CredentialState::AuthFailed => {
+                error!("The {} API key was refused", provider.name())
+            }
+            CredentialState::Unreachable => warn!(
+                "Couldn't check the {} API key, the provider didn't answer",
+                provider.name()
+            ),
+        }
+        db.set_credential_status(&status).await?;
+
+        if alert {
+            let message = t!("credential_alert", provider = provider.name());
+            if let Err(e) = probe.alert(&message).await {
+                warn!("Failed to send the {} key alert: {}", provider.name(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Start checking the configured keys every day. Keys named in `SKIP_CREDENTIAL_CHECKS` are
+/// left alone.
+pub fn spawn_credential_checks(db: Arc<dyn WorkHoursDb>) -> JoinHandle<()> {
+    let skip = env::var("SKIP_CREDENTIAL_CHECKS").unwrap_or_default();
+    let providers = providers_to_check(&skip, |var| {
+        env::var(var).is_ok_and(|value| !value.is_empty())
+    });
+    let probe = HttpProbe::from_env();
+    tokio::spawn(async move {
+        if providers.is_empty() {
+            return;
+        }
+        let mut interval = tokio::time::interval(CHECK_TICK);
+        loop {
+            interval.tick().await;
+            let now = Utc::now().timestamp();
+            if let Err(e) = run_credential_checks(db.as_ref(), &probe, &providers, now).await {
+                warn!("Failed to run the API key checks: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::work_schedule::credentials::CREDENTIAL_CHECK_INTERVAL_SECS;
+    use crate::web::model::InMemoryDb;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const DAY: i64 = CREDENTIAL_CHECK_INTERVAL_SECS;
+
+    /// Answers checks with preset states and records what it was asked
+    #[derive(Default)]
+    struct FakeProbe {
+        states: Mutex<HashMap<&'static str, CredentialState>>,
+        checks: Mutex<Vec<&'static str>>,
+        alerts: Mutex<Vec<String>>,
+    }
+
+    impl FakeProbe {
+        fn set(&self, provider: KeyProvider, state: CredentialState) {
+            self.states.lock().unwrap().insert(provider.name(), state);
+        }
+
+        fn take_checks(&self) -> Vec<&'static str> {
+            std::mem::take(&mut self.checks.lock().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl CredentialProbe for FakeProbe {
+        async fn check(&self, provider: KeyProvider) -> CredentialState {
+            self.checks.lock().unwrap().push(provider.name());
+            self.states.lock().unwrap()[provider.name()]
+        }
+
+        async fn alert(&self, message: &str) -> Result<(), String> {
+            self.alerts.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refused_keys_are_alerted_once() {
+        let db = InMemoryDb::default();
+        let probe = FakeProbe::default();
+        let providers = [KeyProvider::LlamaIndex, KeyProvider::Gemini];
+        probe.set(KeyProvider::LlamaIndex, CredentialState::Ok);
+        probe.set(KeyProvider::Gemini, CredentialState::Ok);
+
+        run_credential_checks(&db, &probe, &providers, 0)
+            .await
+            .unwrap();
+        assert_eq!(probe.take_checks(), ["llamaindex", "gemini"]);
+
+        // Checked at most once a day
+        run_credential_checks(&db, &probe, &providers, DAY - 1)
+            .await
+            .unwrap();
+        assert!(probe.take_checks().is_empty());
+
+        probe.set(KeyProvider::Gemini, CredentialState::AuthFailed);
+        probe.set(KeyProvider::LlamaIndex, CredentialState::Unreachable);
+        run_credential_checks(&db, &probe, &providers, DAY)
+            .await
+            .unwrap();
+        assert_eq!(probe.take_checks().len(), 2);
+        let alerts = probe.alerts.lock().unwrap().clone();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("gemini"), "{}", alerts[0]);
+
+        // Still refused the next day isn't news
+        run_credential_checks(&db, &probe, &providers, 2 * DAY)
+            .await
+            .unwrap();
+        assert_eq!(probe.alerts.lock().unwrap().len(), 1);
+
+        let mut statuses = db.list_credential_statuses().await.unwrap();
+        statuses.sort_by(|a, b| a.provider.cmp(&b.provider));
+        assert_eq!(statuses[0].provider, "gemini");
+        assert_eq!(statuses[0].state, CredentialState::AuthFailed);
+        assert_eq!((statuses[0].since, statuses[0].checked_at), (DAY, 2 * DAY));
+        assert_eq!(statuses[1].state, CredentialState::Unreachable);
+    }
+
+    #[test]
+    fn test_only_set_and_unskipped_keys_are_checked() {
+        let all_set = |_: &str| true;
+        assert_eq!(
+            providers_to_check("", all_set),
+            [KeyProvider::LlamaIndex, KeyProvider::Gemini]
+        );
+        assert_eq!(
+            providers_to_check(" Gemini ,other", all_set),
+            [KeyProvider::LlamaIndex]
+        );
+        assert_eq!(
+            providers_to_check("", |var| var == "GEMINI_API_KEY"),
+            [KeyProvider::Gemini]
+        );
+    }
+
+    #[test]
+    fn test_check_answers_are_classified() {
+        use CredentialState::*;
+        assert_eq!(KeyProvider::Gemini.classify(StatusCode::OK), Ok);
+        assert_eq!(
+            KeyProvider::Gemini.classify(StatusCode::BAD_REQUEST),
+            AuthFailed
+        );
+        assert_eq!(
+            KeyProvider::LlamaIndex.classify(StatusCode::BAD_REQUEST),
+            Unreachable
+        );
+        assert_eq!(
+            KeyProvider::LlamaIndex.classify(StatusCode::UNAUTHORIZED),
+            AuthFailed
+        );
+        assert_eq!(
+            KeyProvider::LlamaIndex.classify(StatusCode::SERVICE_UNAVAILABLE),
+            Unreachable
+        );
+    }
+}
@@ -0,0 +1,1194 @@
+use crate::components::redis_service::{
+    validate_segment, RawConnection, RedisActorHandle, StorageBackend,
+};
+#[cfg(feature = "sqlite")]
+use crate::components::redis_service::{SqliteConnection, SqliteRedis};
+use crate::components::work_schedule::audit::{AuditRecord, MAX_AUDIT_RECORDS};
+use crate::components::work_schedule::credentials::CredentialStatus;
+use crate::components::work_schedule::parse_failures::{
+    ParseFailure, MAX_LISTED_PARSE_FAILURES, PARSE_FAILURE_TTL_SECONDS,
+};
+use crate::components::work_schedule::quality::ParseRecord;
+use crate::components::work_schedule::stats::ContractHours;
+use crate::components::work_schedule::uploads::{StoredUpload, MAX_STORED_UPLOADS};
+use crate::components::work_schedule::EmployeeId;
+use crate::config::DEFAULT_SQLITE_PATH;
+use crate::maintenance::{Maintenance, MAINTENANCE_KEY};
+use crate::utils::redact::Redacted;
+use crate::utils::telemetry::employee_hash;
+use crate::web::model::{
+    merge_schedules, StoredExtraction, WorkDay, WorkHoursDb, WorkSchedule, EXTRACTION_TTL_SECONDS,
+    MAX_STORED_EXTRACTIONS,
+};
+use async_trait::async_trait;
+use chrono::DateTime;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, Client as RedisClient};
+use std::collections::HashMap;
+use std::env;
+use tracing::{info, warn};
+
+/// Redis keys - shared with the main application where both read them
+mod keys {
+    use crate::components::redis_service::Key;
+    pub use crate::components::work_schedule::keys::{
+        duplicate_field, WORK_HOURS_AUDIT, WORK_HOURS_AUDIT_SEQ, WORK_HOURS_CONTRACT_HOURS,
+        WORK_HOURS_CREDENTIALS, WORK_HOURS_DATES, WORK_HOURS_DAY, WORK_HOURS_DUPLICATES,
+        WORK_HOURS_EMPLOYEES, WORK_HOURS_EMPLOYEE_NAMES, WORK_HOURS_PARSE_EDITS,
+        WORK_HOURS_PARSE_FAILURES, WORK_HOURS_PARSE_RECORDS, WORK_HOURS_UPLOADS,
+    };
+    pub const WORK_HOURS_SCHEDULE: Key = Key::fixed("work_hours:schedule");
+    pub const WORK_HOURS_TOKEN_VERSION: Key = Key::fixed("work_hours:token_version");
+    /// Ids of the kept extractions, newest first
+    pub const WORK_HOURS_EXTRACTIONS: Key = Key::fixed("work_hours:extractions");
+    pub const WORK_HOURS_EXTRACTION: Key = Key::fixed("work_hours:extraction");
+    /// 30 days in seconds
+    pub const EXPIRY_SECONDS: i64 = 30 * 24 * 60 * 60;
+    /// Parse records older than a year are dropped
+    pub const PARSE_RECORD_MAX_AGE_SECONDS: i64 = 365 * 24 * 60 * 60;
+
+    /// Key of a full schedule, by employees set member
+    pub fn schedule_key(member: &str) -> Result<Key, String> {
+        WORK_HOURS_SCHEDULE
+            .segment(member)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Key of the set of dates with entries, by employees set member
+    pub fn dates_key(member: &str) -> Result<Key, String> {
+        WORK_HOURS_DATES.segment(member).map_err(|e| e.to_string())
+    }
+
+    /// Key of a single day entry, by employees set member
+    pub fn day_key(member: &str, date: &str) -> Result<Key, String> {
+        WORK_HOURS_DAY
+            .segment(member)
+            .and_then(|key| key.segment(date))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Key of a stored parse failure
+    pub fn parse_failure_key(id: &str) -> Result<Key, String> {
+        crate::components::work_schedule::keys::parse_failure_key(id).map_err(|e| e.to_string())
+    }
+
+    /// Key of the model's days of an upload
+    pub fn extraction_key(upload_id: &str) -> Result<Key, String> {
+        WORK_HOURS_EXTRACTION
+            .segment(upload_id)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Key of the magic link token version of an employee
+    pub fn token_version_key(slug: &str) -> Result<Key, String> {
+        WORK_HOURS_TOKEN_VERSION
+            .segment(slug)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Queue the deletion of every key stored under an employees set member with these dates
+fn delete_member_commands(
+    pipe: &mut redis::Pipeline,
+    member: &str,
+    dates: &[String],
+) -> Result<(), String> {
+    for date in dates {
+        pipe.del(keys::day_key(member, date)?)
+            .ignore()
+            .hdel(keys::WORK_HOURS_DUPLICATES, format!("{member}|{date}"))
+            .ignore();
+    }
+    pipe.del(keys::dates_key(member)?)
+        .ignore()
+        .del(keys::schedule_key(member)?)
+        .ignore()
+        .srem(keys::WORK_HOURS_EMPLOYEES, member)
+        .ignore();
+    Ok(())
+}
+
+/// Every write storing a schedule, as a MULTI/EXEC transaction: the full schedule, the
+/// employee's name, each day entry with its dates set membership, and the duplicates the bot
+/// merges or flags
+fn schedule_transaction(
+    employee: &EmployeeId,
+    schedule: &WorkSchedule,
+) -> Result<redis::Pipeline, String> {
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+
+    let json =
+        serde_json::to_string(schedule).map_err(|e| format!("JSON serialization error: {e}"))?;
+    let key = keys::schedule_key(employee.slug())?;
+    pipe.set(&key, &json)
+        .ignore()
+        .expire(&key, keys::EXPIRY_SECONDS)
+        .ignore();
+
+    // Add to the set of employees, remembering how the name is displayed
+    pipe.sadd(keys::WORK_HOURS_EMPLOYEES, employee.slug())
+        .ignore()
+        .hset(
+            keys::WORK_HOURS_EMPLOYEE_NAMES,
+            employee.slug(),
+            employee.display(),
+        )
+        .ignore();
+
+    // Store individual days for quick access
+    let dates_key = keys::dates_key(employee.slug())?;
+    for day in &schedule.days {
+        let day_key = keys::day_key(employee.slug(), &day.date)?;
+        let mut entry = day.to_entry();
+        entry.upload_id = schedule.upload_id.clone();
+        let day_json = serde_json::to_string(&entry)
+            .map_err(|e| format!("JSON day serialization error: {e}"))?;
+        pipe.sadd(&dates_key, &day.date)
+            .ignore()
+            .set(&day_key, &day_json)
+            .ignore()
+            .expire(&day_key, keys::EXPIRY_SECONDS)
+            .ignore();
+    }
+
+    // Keep every entry for dates the parser emitted more than once so the bot can merge
+    // or flag them, and clear leftovers from earlier uploads for the other dates
+    let duplicates = schedule.duplicate_dates();
+    for day in &schedule.days {
+        let field = keys::duplicate_field(employee, &day.date);
+        match duplicates.get(day.date.as_str()) {
+            Some(entries) => {
+                let entries_json = serde_json::to_string(entries)
+                    .map_err(|e| format!("JSON duplicates serialization error: {e}"))?;
+                pipe.hset(keys::WORK_HOURS_DUPLICATES, &field, &entries_json)
+                    .ignore();
+            }
+            None => {
+                pipe.hdel(keys::WORK_HOURS_DUPLICATES, &field).ignore();
+            }
+        }
+    }
+
+    pipe.expire(&dates_key, keys::EXPIRY_SECONDS).ignore();
+    Ok(pipe)
+}
+
+/// Where the data is kept
+enum Store {
+    Redis(RedisClient),
+    /// The bot's Redis actor, when both run in one process
+    Actor(RedisActorHandle),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteConnection),
+}
+
+/// A connection to either store, speaking the same commands
+enum Connection {
+    Redis(MultiplexedConnection),
+    Actor(RawConnection),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteConnection),
+}
+
+impl redis::aio::ConnectionLike for Connection {
+    fn req_packed_command<'a>(
+        &'a mut self,
+        cmd: &'a redis::Cmd,
+    ) -> redis::RedisFuture<'a, redis::Value> {
+        match self {
+            Connection::Redis(conn) => conn.req_packed_command(cmd),
+            Connection::Actor(conn) => conn.req_packed_command(cmd),
+            #[cfg(feature = "sqlite")]
+            Connection::Sqlite(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipe: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+        match self {
+            Connection::Redis(conn) => conn.req_packed_commands(pipe, offset, count),
+            Connection::Actor(conn) => conn.req_packed_commands(pipe, offset, count),
+            #[cfg(feature = "sqlite")]
+            Connection::Sqlite(conn) => conn.req_packed_commands(pipe, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Connection::Redis(conn) => conn.get_db(),
+            Connection::Actor(conn) => conn.get_db(),
+            #[cfg(feature = "sqlite")]
+            Connection::Sqlite(conn) => conn.get_db(),
+        }
+    }
+}
+
+/// Direct Redis database implementation, or a SQLite file standing in for Redis
+pub struct RedisDB {
+    store: Store,
+}
+
+impl RedisDB {
+    /// Create a new database connection, to Redis or to SQLite as STORAGE_BACKEND selects
+    pub fn new() -> Result<Self, String> {
+        let backend = match env::var("STORAGE_BACKEND") {
+            Ok(v) => v.parse::<StorageBackend>()?,
+            Err(_) => StorageBackend::default(),
+        };
+        if backend == StorageBackend::Sqlite {
+            let path = env::var("SQLITE_PATH").unwrap_or_else(|_| DEFAULT_SQLITE_PATH.to_string());
+            return Self::sqlite(&path);
+        }
+
+        // Use the REDIS_URL environment variable or default to localhost
+        let redis_url =
+            env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+
+        Self::with_url(&redis_url)
+    }
+
+    /// Create a connection to the Redis server at the given URL
+    pub fn with_url(redis_url: &str) -> Result<Self, String> {
+        info!("Connecting to Redis at {}", redis_url);
+
+        let client = RedisClient::open(redis_url)
+            .map_err(|e| format!("Failed to create Redis client: {e}"))?;
+
+        Ok(Self {
+            store: Store::Redis(client),
+        })
+    }
+
+    /// Send every command through the bot's Redis actor, whichever store it is backed by
+    pub fn with_handle(redis_handle: RedisActorHandle) -> Self {
+        Self {
+            store: Store::Actor(redis_handle),
+        }
+    }
+
+    /// Open the SQLite file at the given path, shared with the bot when it uses the same one
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(path: &str) -> Result<Self, String> {
+        info!("Storing data in SQLite at {}", path);
+
+        let store = SqliteRedis::open(path).map_err(|e| e.to_string())?;
+        Ok(Self {
+            store: Store::Sqlite(SqliteConnection::new(store)),
+        })
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn sqlite(_path: &str) -> Result<Self, String> {
+        Err("STORAGE_BACKEND=sqlite needs a build with the sqlite feature".to_string())
+    }
+
+    /// Get a connection to the store
+    async fn get_connection(&self) -> Result<Connection, String> {
+        match &self.store {
+            Store::Redis(client) => client
+                .get_multiplexed_async_connection()
+                .await
+                .map(Connection::Redis)
+                .map_err(|e| format!("Failed to connect to Redis: {e}")),
+            #[cfg(feature = "sqlite")]
+            Store::Sqlite(conn) => Ok(Connection::Sqlite(conn.clone())),
+            Store::Actor(handle) => Ok(Connection::Actor(handle.raw_connection())),
+        }
+    }
+
+    /// Load the schedule stored under an employees set member, which is a slug for canonical
+    /// entries or a raw name for entries written before names were normalized
+    async fn load_member(&self, member: &str) -> Result<Option<WorkSchedule>, String> {
+        let mut conn = self.get_connection().await?;
+
+        let key = keys::schedule_key(member)?;
+        let data: Option<String> = conn
+            .get(&key)
+            .await
+            .map_err(|e| format!("Redis GET error: {e}"))?;
+        if let Some(data) = data {
+            let schedule =
+                serde_json::from_str(&data).map_err(|e| format!("JSON parse error: {e}"))?;
+            return Ok(Some(schedule));
+        }
+
+        // Fall back to the individual day entries if the full schedule has expired
+        let dates_key = keys::dates_key(member)?;
+        let dates: Vec<String> = conn
+            .smembers(&dates_key)
+            .await
+            .map_err(|e| format!("Redis SMEMBERS error: {e}"))?;
+        if dates.is_empty() {
+            return Ok(None);
+        }
+
+        let mut schedule = WorkSchedule::new(member.to_string());
+        schedule.last_updated = DateTime::UNIX_EPOCH;
+        for date in dates {
+            let day_key = keys::day_key(member, &date)?;
+            let day: Option<String> = conn
+                .get(&day_key)
+                .await
+                .map_err(|e| format!("Redis GET error: {e}"))?;
+            if let Some(day) = day {
+                let day: WorkDay =
+                    serde_json::from_str(&day).map_err(|e| format!("JSON day parse error: {e}"))?;
+                schedule.days.push(day);
+            }
+        }
+
+        Ok(Some(schedule))
+    }
+
+    /// Dates stored under an employees set member
+    async fn member_dates(&self, member: &str) -> Result<Vec<String>, String> {
+        let mut conn = self.get_connection().await?;
+        conn.smembers(keys::dates_key(member)?)
+            .await
+            .map_err(|e| format!("Redis SMEMBERS error: {e}"))
+    }
+
+    /// Delete every key stored under an employees set member
+    async fn delete_member(&self, member: &str) -> Result<(), String> {
+        let dates = self.member_dates(member).await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        delete_member_commands(&mut pipe, member, &dates)?;
+
+        let mut conn = self.get_connection().await?;
+        pipe.query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis transaction error: {e}"))
+    }
+
+    /// Merge schedules stored under variant spellings of a name into the canonical slug keys.
+    ///
+    /// Returns the number of employees that were migrated.
+    pub async fn migrate_employee_ids(&self) -> Result<usize, String> {
+        let mut conn = self.get_connection().await?;
+
+        let members: Vec<String> = conn
+            .smembers(keys::WORK_HOURS_EMPLOYEES)
+            .await
+            .map_err(|e| format!("Redis SMEMBERS error: {e}"))?;
+        let names: HashMap<String, String> = conn
+            .hgetall(keys::WORK_HOURS_EMPLOYEE_NAMES)
+            .await
+            .map_err(|e| format!("Redis HGETALL error: {e}"))?;
+
+        // Group the stored members by the employee they belong to
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for member in members {
+            // Members that can't be a key segment have no data this could reach
+            if let Err(e) = validate_segment(&member) {
+                warn!("Skipping employee that can't be migrated: {}", e);
+                continue;
+            }
+            let slug = EmployeeId::new(&member).slug().to_string();
+            groups.entry(slug).or_default().push(member);
+        }
+
+        let mut migrated = 0;
+        for (slug, members) in groups {
+            // Nothing to do when the only member is already the canonical slug
+            if members.iter().all(|member| *member == slug) {
+                continue;
+            }
+
+            let mut variants = Vec::new();
+            for member in &members {
+                match self.load_member(member).await? {
+                    Some(mut schedule) => {
+                        // Slug members carry their display name in the names hash
+                        schedule.employee_name =
+                            names.get(member).cloned().unwrap_or_else(|| member.clone());
+                        variants.push(schedule);
+                    }
+                    None => warn!("No schedule data stored for {}", Redacted(&member)),
+                }
+            }
+
+            // The merged schedule and the removal of the variants go in one transaction, writes
+            // first, so a failure part way leaves the variants in place to migrate again
+            let mut transaction = redis::pipe();
+            transaction.atomic();
+            let mut canonical = None;
+            if !variants.is_empty() {
+                let mut merged = merge_schedules(variants);
+                let employee = EmployeeId::new(&merged.employee_name);
+                info!(
+                    "Merging {} name variants into {}",
+                    members.len(),
+                    Redacted(&employee)
+                );
+                merged.employee_name = employee.display().to_string();
+                transaction = schedule_transaction(&employee, &merged)?;
+                canonical = Some(employee);
+            }
+
+            // The canonical member's keys now hold the merged schedule
+            for member in &members {
+                if canonical.as_ref().is_some_and(|e| e.slug() == member) {
+                    continue;
+                }
+                let dates = self.member_dates(member).await?;
+                delete_member_commands(&mut transaction, member, &dates)?;
+            }
+
+            let mut conn = self.get_connection().await?;
+            transaction
+                .query_async::<()>(&mut conn)
+                .await
+                .map_err(|e| format!("Redis transaction error: {e}"))?;
+            if canonical.is_some() {
+                migrated += 1;
+            }
+        }
+
+        Ok(migrated)
+    }
+}
+
+#[async_trait]
+impl WorkHoursDb for RedisDB {
+    async fn get_schedule(&self, employee_name: &str) -> Result<Option<WorkSchedule>, String> {
+        let employee = EmployeeId::new(employee_name);
+        let key = keys::schedule_key(employee.slug())?;
+
+        // Get a connection
+        let mut conn = self.get_connection().await?;
+
+        // Check if the key exists
+        let exists: bool = conn
+            .exists(&key)
+            .await
+            .map_err(|e| format!("Redis EXISTS error: {e}"))?;
+
+        if !exists {
+            return Ok(None);
+        }
+
+        // Get the data
+        let data: String = conn
+            .get(&key)
+            .await
+            .map_err(|e| format!("Redis GET error: {e}"))?;
+
+        // Parse the JSON
+        let schedule: WorkSchedule =
+            serde_json::from_str(&data).map_err(|e| format!("JSON parse error: {e}"))?;
+
+        Ok(Some(schedule))
+    }
+
+    #[tracing::instrument(skip_all, fields(employee = %employee_hash(employee_name)))]
+    async fn set_schedule(
+        &self,
+        employee_name: &str,
+        schedule: &WorkSchedule,
+    ) -> Result<(), String> {
+        let employee = EmployeeId::new(employee_name);
+        let mut schedule = schedule.clone();
+        schedule.employee_name = employee.display().to_string();
+
+        // Write everything in one transaction so readers never see a half-written schedule
+        let transaction = schedule_transaction(&employee, &schedule)?;
+        let mut conn = self.get_connection().await?;
+        transaction
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis transaction error: {e}"))?;
+
+        let duplicates = schedule.duplicate_dates();
+        if !duplicates.is_empty() {
+            warn!(
+                "Schedule for {} has multiple entries for {} date(s)",
+                Redacted(&employee),
+                duplicates.len()
+            );
+        }
+
+        info!(
+            "Stored schedule for {} with {} days",
+            Redacted(&employee),
+            schedule.days.len()
+        );
+        Ok(())
+    }
+
+    async fn list_employees(&self) -> Result<Vec<String>, String> {
+        // Get a connection
+        let mut conn = self.get_connection().await?;
+
+        // Get all employees from the set
+        let slugs: Vec<String> = conn
+            .smembers(keys::WORK_HOURS_EMPLOYEES)
+            .await
+            .map_err(|e| format!("Redis SMEMBERS error: {e}"))?;
+
+        // Resolve the display names
+        let names: HashMap<String, String> = conn
+            .hgetall(keys::WORK_HOURS_EMPLOYEE_NAMES)
+            .await
+            .map_err(|e| format!("Redis HGETALL error: {e}"))?;
+
+        Ok(slugs
+            .into_iter()
+            .map(|slug| names.get(&slug).cloned().unwrap_or(slug))
+            .collect())
+    }
+
+    async fn delete_schedule(&self, employee_name: &str) -> Result<(), String> {
+        let employee = EmployeeId::new(employee_name);
+        self.delete_member(employee.slug()).await?;
+
+        let mut conn = self.get_connection().await?;
+        conn.hdel::<_, _, ()>(keys::WORK_HOURS_EMPLOYEE_NAMES, employee.slug())
+            .await
+            .map_err(|e| format!("Redis HDEL error: {e}"))?;
+
+        info!("Deleted schedule for {}", Redacted(&employee));
+        Ok(())
+    }
+
+    async fn get_token_version(&self, employee_name: &str) -> Result<u64, String> {
+        let mut conn = self.get_connection().await?;
+        let employee = EmployeeId::new(employee_name);
+        let key = keys::token_version_key(employee.slug())?;
+
+        let version: Option<u64> = conn
+            .get(&key)
+            .await
+            .map_err(|e| format!("Redis GET error: {e}"))?;
+
+        Ok(version.unwrap_or(0))
+    }
+
+    async fn bump_token_version(&self, employee_name: &str) -> Result<u64, String> {
+        let mut conn = self.get_connection().await?;
+        let employee = EmployeeId::new(employee_name);
+        let key = keys::token_version_key(employee.slug())?;
+
+        let version: u64 = conn
+            .incr(&key, 1)
+            .await
+            .map_err(|e| format!("Redis INCR error: {e}"))?;
+
+        info!(
+            "Revoked magic links for {} (token version {})",
+            Redacted(&employee),
+            version
+        );
+        Ok(version)
+    }
+
+    async fn record_upload(&self, upload: &StoredUpload) -> Result<(), String> {
+        let mut conn = self.get_connection().await?;
+        let json =
+            serde_json::to_string(upload).map_err(|e| format!("JSON serialization error: {e}"))?;
+
+        conn.lpush::<_, _, ()>(keys::WORK_HOURS_UPLOADS, &json)
+            .await
+            .map_err(|e| format!("Redis LPUSH error: {e}"))?;
+        conn.ltrim::<_, ()>(keys::WORK_HOURS_UPLOADS, 0, MAX_STORED_UPLOADS - 1)
+            .await
+            .map_err(|e| format!("Redis LTRIM error: {e}"))?;
+        Ok(())
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Vec<String> = conn
+            .lrange(keys::WORK_HOURS_UPLOADS, 0, MAX_STORED_UPLOADS - 1)
+            .await
+            .map_err(|e| format!("Redis LRANGE error: {e}"))?;
+
+        Ok(stored
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn record_parse(&self, record: &ParseRecord) -> Result<(), String> {
+        let mut conn = self.get_connection().await?;
+        let json =
+            serde_json::to_string(record).map_err(|e| format!("JSON serialization error: {e}"))?;
+        conn.hset::<_, _, _, ()>(keys::WORK_HOURS_PARSE_RECORDS, &record.upload_id, &json)
+            .await
+            .map_err(|e| format!("Redis HSET error: {e}"))?;
+
+        // Drop records too old for any trend, with their edit counts
+        let expired: Vec<String> = self
+            .list_parse_records()
+            .await?
+            .into_iter()
+            .filter(|stored| {
+                stored.parsed_at < record.parsed_at - keys::PARSE_RECORD_MAX_AGE_SECONDS
+            })
+            .map(|stored| stored.upload_id)
+            .collect();
+        if !expired.is_empty() {
+            redis::pipe()
+                .hdel(keys::WORK_HOURS_PARSE_RECORDS, &expired)
+                .ignore()
+                .hdel(keys::WORK_HOURS_PARSE_EDITS, &expired)
+                .ignore()
+                .query_async::<()>(&mut conn)
+                .await
+                .map_err(|e| format!("Redis HDEL error: {e}"))?;
+        }
+        Ok(())
+    }
+
+    async fn list_parse_records(&self) -> Result<Vec<ParseRecord>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Vec<String> = conn
+            .hvals(keys::WORK_HOURS_PARSE_RECORDS)
+            .await
+            .map_err(|e| format!("Redis HVALS error: {e}"))?;
+        let edits: HashMap<String, u64> = conn
+            .hgetall(keys::WORK_HOURS_PARSE_EDITS)
+            .await
+            .map_err(|e| format!("Redis HGETALL error: {e}"))?;
+
+        let mut records: Vec<ParseRecord> = stored
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect();
+        for record in &mut records {
+            record.manual_edits = edits.get(&record.upload_id).copied().unwrap_or(0);
+        }
+        records.sort_by_key(|record| record.parsed_at);
+        Ok(records)
+    }
+
+    async fn record_parse_failure(&self, failure: &ParseFailure) -> Result<(), String> {
+        let mut conn = self.get_connection().await?;
+        let key = keys::parse_failure_key(&failure.id)?;
+        let json = failure
+            .to_capped_json()
+            .map_err(|e| format!("JSON serialization error: {e}"))?;
+
+        redis::pipe()
+            .set_ex(&key, &json, PARSE_FAILURE_TTL_SECONDS as u64)
+            .ignore()
+            .lpush(keys::WORK_HOURS_PARSE_FAILURES, &failure.id)
+            .ignore()
+            .ltrim(
+                keys::WORK_HOURS_PARSE_FAILURES,
+                0,
+                MAX_LISTED_PARSE_FAILURES as isize - 1,
+            )
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis SET error: {e}"))
+    }
+
+    async fn list_parse_failures(&self) -> Result<Vec<ParseFailure>, String> {
+        let mut conn = self.get_connection().await?;
+        let ids: Vec<String> = conn
+            .lrange(
+                keys::WORK_HOURS_PARSE_FAILURES,
+                0,
+                MAX_LISTED_PARSE_FAILURES as isize - 1,
+            )
+            .await
+            .map_err(|e| format!("Redis LRANGE error: {e}"))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = ids
+            .iter()
+            .map(|id| keys::parse_failure_key(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Expired failures come back as nil
+        let stored: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis MGET error: {e}"))?;
+
+        Ok(stored
+            .iter()
+            .flatten()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn get_parse_failure(&self, id: &str) -> Result<Option<ParseFailure>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Option<String> = conn
+            .get(keys::parse_failure_key(id)?)
+            .await
+            .map_err(|e| format!("Redis GET error: {e}"))?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| format!("JSON parse error: {e}")))
+            .transpose()
+    }
+
+    async fn record_extraction(&self, extraction: &StoredExtraction) -> Result<(), String> {
+        let mut conn = self.get_connection().await?;
+        let key = keys::extraction_key(&extraction.upload_id)?;
+        let json = serde_json::to_string(extraction)
+            .map_err(|e| format!("JSON serialization error: {e}"))?;
+
+        redis::pipe()
+            .set_ex(&key, &json, EXTRACTION_TTL_SECONDS as u64)
+            .ignore()
+            .lpush(keys::WORK_HOURS_EXTRACTIONS, &extraction.upload_id)
+            .ignore()
+            .ltrim(
+                keys::WORK_HOURS_EXTRACTIONS,
+                0,
+                MAX_STORED_EXTRACTIONS as isize - 1,
+            )
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis SET error: {e}"))
+    }
+
+    async fn list_extractions(&self) -> Result<Vec<StoredExtraction>, String> {
+        let mut conn = self.get_connection().await?;
+        let ids: Vec<String> = conn
+            .lrange(keys::WORK_HOURS_EXTRACTIONS, 0, -1)
+            .await
+            .map_err(|e| format!("Redis LRANGE error: {e}"))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys = ids
+            .iter()
+            .map(|id| keys::extraction_key(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Expired extractions come back as nil
+        let stored: Vec<Option<String>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis MGET error: {e}"))?;
+
+        Ok(stored
+            .iter()
+            .rev()
+            .flatten()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn list_contract_hours(&self) -> Result<Vec<ContractHours>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Vec<String> = conn
+            .hvals(keys::WORK_HOURS_CONTRACT_HOURS)
+            .await
+            .map_err(|e| format!("Redis HVALS error: {e}"))?;
+
+        Ok(stored
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn remove_days(&self, employee_name: &str, dates: &[String]) -> Result<(), String> {
+        let employee = EmployeeId::new(employee_name);
+        let dates_key = keys::dates_key(employee.slug())?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for date in dates {
+            pipe.del(keys::day_key(employee.slug(), date)?)
+                .ignore()
+                .srem(&dates_key, date)
+                .ignore()
+                .hdel(
+                    keys::WORK_HOURS_DUPLICATES,
+                    keys::duplicate_field(&employee, date),
+                )
+                .ignore();
+        }
+
+        let mut conn = self.get_connection().await?;
+        pipe.query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis transaction error: {e}"))
+    }
+
+    async fn record_audit(&self, mut records: Vec<AuditRecord>) -> Result<(), String> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+
+        // Reserve a block of numbers so concurrent writers never share one
+        let last: u64 = conn
+            .incr(keys::WORK_HOURS_AUDIT_SEQ, records.len())
+            .await
+            .map_err(|e| format!("Redis INCRBY error: {e}"))?;
+        let first = last + 1 - records.len() as u64;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (seq, record) in (first..).zip(records.iter_mut()) {
+            record.seq = seq;
+            let json = serde_json::to_string(record)
+                .map_err(|e| format!("JSON serialization error: {e}"))?;
+            pipe.lpush(keys::WORK_HOURS_AUDIT, &json).ignore();
+        }
+        pipe.ltrim(keys::WORK_HOURS_AUDIT, 0, MAX_AUDIT_RECORDS as isize - 1)
+            .ignore();
+        pipe.query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis transaction error: {e}"))
+    }
+
+    async fn list_audit(&self) -> Result<Vec<AuditRecord>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Vec<String> = conn
+            .lrange(keys::WORK_HOURS_AUDIT, 0, -1)
+            .await
+            .map_err(|e| format!("Redis LRANGE error: {e}"))?;
+
+        // The list is newest first
+        stored
+            .iter()
+            .rev()
+            .map(|json| serde_json::from_str(json).map_err(|e| format!("JSON parse error: {e}")))
+            .collect()
+    }
+
+    async fn get_maintenance(&self) -> Result<Option<Maintenance>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Option<String> = conn
+            .get(MAINTENANCE_KEY)
+            .await
+            .map_err(|e| format!("Redis GET error: {e}"))?;
+        stored
+            .map(|json| serde_json::from_str(&json).map_err(|e| format!("JSON parse error: {e}")))
+            .transpose()
+    }
+
+    async fn list_credential_statuses(&self) -> Result<Vec<CredentialStatus>, String> {
+        let mut conn = self.get_connection().await?;
+        let stored: Vec<String> = conn
+            .hvals(keys::WORK_HOURS_CREDENTIALS)
+            .await
+            .map_err(|e| format!("Redis HVALS error: {e}"))?;
+
+        Ok(stored
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn set_credential_status(&self, status: &CredentialStatus) -> Result<(), String> {
+        let json =
+            serde_json::to_string(status).map_err(|e| format!("JSON serialization error: {e}"))?;
+        let mut conn = self.get_connection().await?;
+        conn.hset::<_, _, _, ()>(keys::WORK_HOURS_CREDENTIALS, &status.provider, json)
+            .await
+            .map_err(|e| format!("Redis HSET error: {e}"))
+    }
+
+    async fn ping(&self) -> Result<(), String> {
+        let mut conn = self.get_connection().await?;
+        redis::cmd("PING")
+            .query_async::<String>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis PING error: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::event_bus::EventBus;
+    use crate::components::redis_service::{FakeRedis, RedisActorHandle};
+    use crate::components::work_schedule::groups::EmployeeFilter;
+    use crate::components::work_schedule::models::ShiftRange;
+    use crate::components::work_schedule::profiles::fallback_emoji;
+    use crate::components::work_schedule::stats::HoursBudget;
+    use crate::components::work_schedule::{build_weekly_notification, WorkScheduleHandle};
+    use crate::config::Config;
+    use crate::theme::Theme;
+    use crate::web::model::WorkDayExtraction;
+    use crate::web::parser::convert_to_work_schedule;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn work_day(date: &str, start: &str) -> WorkDay {
+        WorkDay {
+            date: date.to_string(),
+            shifts: vec![ShiftRange::new(start, "16:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        }
+    }
+
+    #[test]
+    fn test_schedule_is_written_in_one_transaction() {
+        let employee = EmployeeId::new("Anna Mäkinen");
+        let schedule = WorkSchedule {
+            employee_name: employee.display().to_string(),
+            days: vec![
+                work_day("2025-01-06", "08:00"),
+                work_day("2025-01-07", "08:00"),
+                work_day("2025-01-07", "10:00"),
+            ],
+            last_updated: Utc::now(),
+            upload_id: Some("anna-m-kinen-1736150400".to_string()),
+            extraction: None,
+        };
+
+        let transaction = schedule_transaction(&employee, &schedule).unwrap();
+        assert!(transaction.is_transaction());
+
+        let mut redis = FakeRedis::default();
+        redis.execute_pipeline(&transaction).unwrap();
+        let members = redis::cmd("SMEMBERS")
+            .arg(keys::dates_key(employee.slug()).unwrap().to_string())
+            .to_owned();
+        assert_eq!(
+            redis.execute(&members).unwrap(),
+            redis::Value::Array(vec![
+                redis::Value::BulkString(b"2025-01-06".to_vec()),
+                redis::Value::BulkString(b"2025-01-07".to_vec()),
+            ])
+        );
+        let day = redis::cmd("GET")
+            .arg(
+                keys::day_key(employee.slug(), "2025-01-06")
+                    .unwrap()
+                    .to_string(),
+            )
+            .to_owned();
+        let redis::Value::BulkString(day) = redis.execute(&day).unwrap() else {
+            panic!("day entry missing");
+        };
+        // Entries point back to their upload so edits can be counted against it
+        let (entry, _) = crate::components::work_schedule::models::parse_stored_entry(
+            std::str::from_utf8(&day).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(entry.upload_id.as_deref(), Some("anna-m-kinen-1736150400"));
+        let duplicates = redis::cmd("HGET")
+            .arg(keys::WORK_HOURS_DUPLICATES.to_string())
+            .arg(keys::duplicate_field(&employee, "2025-01-07"))
+            .to_owned();
+        assert!(matches!(
+            redis.execute(&duplicates).unwrap(),
+            redis::Value::BulkString(_)
+        ));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_store_is_shared_with_the_bot() {
+        let path = std::env::temp_dir().join(format!("work-hours-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let db = RedisDB::sqlite(path).unwrap();
+        let schedule = WorkSchedule {
+            employee_name: "Anna Mäkinen".to_string(),
+            days: vec![work_day("2025-01-06", "08:00")],
+            last_updated: Utc::now(),
+            upload_id: None,
+            extraction: None,
+        };
+        db.set_schedule("Anna Mäkinen", &schedule).await.unwrap();
+        db.ping().await.unwrap();
+
+        assert_eq!(db.list_employees().await.unwrap(), vec!["Anna Mäkinen"]);
+        let stored = db.get_schedule("anna mäkinen").await.unwrap().unwrap();
+        assert_eq!(stored.days.len(), 1);
+        assert_eq!(db.bump_token_version("Anna Mäkinen").await.unwrap(), 1);
+
+        // The bot opening the same file sees the entries
+        let redis_handle = RedisActorHandle::sqlite(path).unwrap();
+        let dates: Vec<String> = redis_handle
+            .smembers(&keys::dates_key(EmployeeId::new("Anna Mäkinen").slug()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(dates, vec!["2025-01-06"]);
+
+        redis_handle.shutdown().await.unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_migration_merges_variants_into_canonical_keys() {
+        let redis_handle = RedisActorHandle::fake();
+        let db = RedisDB::with_handle(redis_handle.clone());
+
+        // An entry from before names were normalized, stored under the raw name
+        let legacy = WorkSchedule {
+            employee_name: "Anna".to_string(),
+            days: vec![work_day("2025-01-06", "08:00")],
+            last_updated: Utc::now(),
+            upload_id: None,
+            extraction: None,
+        };
+        let mut legacy_writes = schedule_transaction(&EmployeeId::new("anna"), &legacy).unwrap();
+        legacy_writes
+            .set(
+                keys::schedule_key("Anna").unwrap(),
+                serde_json::to_string(&legacy).unwrap(),
+            )
+            .ignore()
+            .sadd(keys::WORK_HOURS_EMPLOYEES, "Anna")
+            .ignore()
+            .sadd(keys::dates_key("Anna").unwrap(), "2025-01-07")
+            .ignore()
+            .set(
+                keys::day_key("Anna", "2025-01-07").unwrap(),
+                serde_json::to_string(&work_day("2025-01-07", "09:00").to_entry()).unwrap(),
+            )
+            .ignore();
+        let mut conn = db.get_connection().await.unwrap();
+        legacy_writes.query_async::<()>(&mut conn).await.unwrap();
+
+        assert_eq!(db.migrate_employee_ids().await.unwrap(), 1);
+
+        let members: Vec<String> = redis_handle
+            .smembers(&keys::WORK_HOURS_EMPLOYEES)
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["anna"]);
+        let merged = db.get_schedule("Anna").await.unwrap().unwrap();
+        assert!(merged.days.iter().any(|day| day.date == "2025-01-06"));
+        let legacy_dates: Vec<String> = redis_handle
+            .smembers(&keys::dates_key("Anna").unwrap())
+            .await
+            .unwrap();
+        assert!(legacy_dates.is_empty());
+
+        // Nothing is left to migrate
+        assert_eq!(db.migrate_employee_ids().await.unwrap(), 0);
+    }
+
+    fn test_config() -> Arc<RwLock<Config>> {
+        Arc::new(RwLock::new(Config::for_tests()))
+    }
+
+    /// Take extracted days through conversion, storage and the bot's read path, returning the
+    /// weekly notification embed's fields as (name, value) pairs
+    async fn weekly_fields(employee: &str, extraction: &str) -> Vec<(String, String)> {
+        let days: Vec<WorkDayExtraction> = serde_json::from_str(extraction).unwrap();
+        let schedule = convert_to_work_schedule(employee, days).unwrap();
+        let transaction = schedule_transaction(&EmployeeId::new(employee), &schedule).unwrap();
+        let mut redis = FakeRedis::default();
+        redis.execute_pipeline(&transaction).unwrap();
+
+        let handle = WorkScheduleHandle::new(
+            test_config(),
+            RedisActorHandle::fake_serving(redis),
+            EventBus::new(),
+        );
+        let notification = build_weekly_notification(
+            &handle,
+            "2025-03-10",
+            "2025-03-16",
+            &HoursBudget::new(Vec::new(), 2.0),
+            &EmployeeFilter::All,
+            &Theme::default(),
+        )
+        .await
+        .unwrap();
+
+        let embed = serde_json::to_value(&notification.embed).unwrap();
+        embed["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| {
+                (
+                    field["name"].as_str().unwrap().to_string(),
+                    field["value"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_clean_week_reaches_the_weekly_notification() {
+        let fields = weekly_fields(
+            "Anna Mäkinen",
+            include_str!("../../tests/fixtures/schedule_clean_week.json"),
+        )
+        .await;
+        assert_eq!(
+            fields,
+            vec![(
+                format!("{} Anna Mäkinen", fallback_emoji("Anna Mäkinen")),
+                "**Mon** (2025-03-10): 07:00–15:00\n\
+                 **Tue** (2025-03-11): 07:00–15:00\n\
+                 **Wed** (2025-03-12): 09:00–17:00\n\
+                 **Thu** (2025-03-13): 09:00–17:00\n\
+                 **Fri** (2025-03-14): 12:00–20:00\n\
+                 **Sat** (2025-03-15): Day off\n\
+                 **Sun** (2025-03-16): Day off\n"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_codes_and_vacation_reach_the_weekly_notification() {
+        let fields = weekly_fields(
+            "Matti",
+            include_str!("../../tests/fixtures/schedule_codes_and_vacation.json"),
+        )
+        .await;
+        // Codes like vacation and training are stored as notes shown after the hours, and an
+        // empty cell is kept as a day without hours
+        assert_eq!(
+            fields,
+            vec![(
+                format!("{} Matti", fallback_emoji("Matti")),
+                "**Mon** (2025-03-10): No scheduled hours · LOMA\n\
+                 **Tue** (2025-03-11): No scheduled hours · LOMA\n\
+                 **Wed** (2025-03-12): Day off\n\
+                 **Thu** (2025-03-13): 08:00–16:00\n\
+                 **Fri** (2025-03-14): No scheduled hours · koulutus\n\
+                 **Sat** (2025-03-15): No scheduled hours\n\
+                 **Sun** (2025-03-16): Day off\n"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_shifts_reach_the_weekly_notification() {
+        let fields = weekly_fields(
+            "Pekka",
+            include_str!("../../tests/fixtures/schedule_split_shifts.json"),
+        )
+        .await;
+        // Both ways of writing a break end up as the same break, not as a second shift
+        assert_eq!(
+            fields,
+            vec![(
+                format!("{} Pekka", fallback_emoji("Pekka")),
+                "**Mon** (2025-03-10): 08:00–12:00, 16:00–20:00\n\
+                 **Tue** (2025-03-11): 09:00–17:00 (30 min break)\n\
+                 **Wed** (2025-03-12): 09:00–17:00 (30 min break)\n\
+                 **Thu** (2025-03-13): 07:30–11:30, 15:00–19:00\n\
+                 **Fri** (2025-03-14): No scheduled hours · inventaario\n\
+                 **Sat** (2025-03-15): Day off\n\
+                 **Sun** (2025-03-16): Day off\n"
+                    .to_string()
+            )]
+        );
+    }
+}
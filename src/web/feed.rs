@@ -0,0 +1,274 @@
+use crate::components::work_schedule::models::{parse_minutes, ShiftRange};
+use crate::utils::redact::Redacted;
+use crate::utils::time::get_weekly_date_range;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tracing::error;
+
+use crate::web::model::{WorkDay, WorkSchedule};
+use crate::web::AppState;
+
+/// Version of the feed JSON, bumped on breaking changes to its shape
+pub const FEED_VERSION: u8 = 1;
+
+/// How long dashboards may cache a feed response, in seconds
+const FEED_MAX_AGE: u32 = 60;
+
+/// What kind of day an employee has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayType {
+    /// At least one shift with a start time
+    Work,
+    /// Marked as a day off
+    Off,
+    /// No entry, or an entry without any hours
+    Unscheduled,
+}
+
+/// A shift with times in canonical HH:MM form, or null when unknown
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct FeedShift {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// One employee's day in the feed
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct FeedDay {
+    /// Date (YYYY-MM-DD)
+    pub date: String,
+    pub day_type: DayType,
+    pub shifts: Vec<FeedShift>,
+    pub notes: Option<String>,
+}
+
+/// One employee in the feed, with an entry for every date of the range
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct FeedEmployee {
+    pub name: String,
+    pub days: Vec<FeedDay>,
+}
+
+/// Read-only schedule feed for dashboards
+#[derive(Debug, Clone, Serialize)]
+pub struct Feed {
+    pub version: u8,
+    /// When the response was built (RFC 3339, UTC)
+    pub generated_at: String,
+    /// First date of the feed (YYYY-MM-DD)
+    pub start_date: String,
+    /// Last date of the feed (YYYY-MM-DD)
+    pub end_date: String,
+    /// Last date every employee has a schedule for, if any
+    pub coverage_through: Option<String>,
+    /// Employees sorted by name
+    pub employees: Vec<FeedEmployee>,
+}
+
+impl Feed {
+    /// Hash of the schedule data, leaving out the generation time so unchanged data keeps its tag
+    pub fn etag(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.version.hash(&mut hasher);
+        self.start_date.hash(&mut hasher);
+        self.end_date.hash(&mut hasher);
+        self.coverage_through.hash(&mut hasher);
+        self.employees.hash(&mut hasher);
+        format!("\"{:016x}\"", hasher.finish())
+    }
+}
+
+/// Format a time as zero-padded HH:MM, or None if it can't be read
+fn canonical_time(time: Option<&str>) -> Option<String> {
+    let minutes = parse_minutes(time?)?;
+    Some(format!("{:02}:{:02}", minutes / 60, minutes % 60))
+}
+
+impl From<&ShiftRange> for FeedShift {
+    fn from(shift: &ShiftRange) -> Self {
+        Self {
+            start: canonical_time(shift.start.as_deref()),
+            end: canonical_time(shift.end.as_deref()),
+        }
+    }
+}
+
+impl FeedDay {
+    fn new(date: String, day: Option<&WorkDay>) -> Self {
+        let Some(day) = day else {
+            return Self {
+                date,
+                day_type: DayType::Unscheduled,
+                shifts: Vec::new(),
+                notes: None,
+            };
+        };
+
+        let entry = day.to_entry();
+        let day_type = if entry.is_day_off {
+            DayType::Off
+        } else if entry.is_working() {
+            DayType::Work
+        } else {
+            DayType::Unscheduled
+        };
+        let shifts = match day_type {
+            DayType::Work => day.shifts.iter().map(FeedShift::from).collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            date,
+            day_type,
+            shifts,
+            notes: day.notes.clone(),
+        }
+    }
+}
+
+/// Build the feed for the dates from `start` to `end`, both inclusive
+pub fn build_feed(mut schedules: Vec<WorkSchedule>, start: NaiveDate, end: NaiveDate) -> Feed {
+    schedules.sort_by(|a, b| a.employee_name.cmp(&b.employee_name));
+
+    let dates: Vec<String> = start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .collect();
+
+    let coverage_through = schedules
+        .iter()
+        .map(|schedule| schedule.days.iter().map(|day| day.date.as_str()).max())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|last_dates| last_dates.into_iter().min())
+        .map(str::to_string);
+
+    let employees = schedules
+        .iter()
+        .map(|schedule| FeedEmployee {
+            name: schedule.employee_name.clone(),
+            days: dates
+                .iter()
+                .map(|date| {
+                    // Later entries win, like when schedules are merged
+                    let day = schedule.days.iter().rev().find(|day| &day.date == date);
+                    FeedDay::new(date.clone(), day)
+                })
+                .collect(),
+        })
+        .collect();
+
+    Feed {
+        version: FEED_VERSION,
+        generated_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        coverage_through,
+        employees,
+    }
+}
+
+/// Check the request carries the feed token. Without FEED_TOKEN set the feed doesn't exist.
+fn authorize_feed(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = state.feed_token.as_deref() else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if token == expected => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Check whether an If-None-Match header matches the tag
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Load every employee's schedule
+pub async fn load_schedules(state: &AppState) -> Result<Vec<WorkSchedule>, StatusCode> {
+    let employees = state.db.list_employees().await.map_err(|e| {
+        error!("Failed to list employees for the feed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut schedules = Vec::with_capacity(employees.len());
+    for employee in employees {
+        match state.db.get_schedule(&employee).await {
+            Ok(Some(schedule)) => schedules.push(schedule),
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed to load schedule for {}: {}", Redacted(&employee), e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+    Ok(schedules)
+}
+
+/// Load every schedule and answer with the feed for the date range
+async fn feed_response(
+    state: &AppState,
+    headers: &HeaderMap,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Response, StatusCode> {
+    authorize_feed(state, headers)?;
+
+    let feed = build_feed(load_schedules(state).await?, start, end);
+    let etag = feed.etag();
+    let cache_headers = [
+        (
+            header::CACHE_CONTROL,
+            HeaderValue::from_str(&format!("max-age={FEED_MAX_AGE}")).expect("valid header value"),
+        ),
+        (
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("valid header value"),
+        ),
+    ];
+
+    if etag_matches(headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+    Ok((cache_headers, Json(feed)).into_response())
+}
+
+/// Feed of the current week, from the configured first day of the week
+pub async fn week_feed_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let (start, end) = get_weekly_date_range(&Local::now(), state.week_start);
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d");
+    let (Ok(start), Ok(end)) = (parse(&start), parse(&end)) else {
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    feed_response(&state, &headers, start, end).await
+}
+
+/// Feed of today only
+pub async fn today_feed_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let today = Local::now().date_naive();
+    feed_response(&state, &headers, today, today).await
+}
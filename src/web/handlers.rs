@@ -0,0 +1,1344 @@
+use crate::components::redis_service::validate_segment;
+use crate::components::work_schedule::parse_failures::{ParseFailure, ParseFailureStage};
+use crate::components::work_schedule::quality::{
+    upload_id, weekly_quality, WeeklyQuality, DEFAULT_QUALITY_WEEKS, MAX_QUALITY_WEEKS,
+};
+use crate::components::work_schedule::stats::HoursBudget;
+use crate::components::work_schedule::uploads::{
+    upload_error_message, PeriodIssue, StoredUpload, UploadResponse, UploadSummary, MAX_FILE_SIZE,
+};
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::redact::Redacted;
+use axum::{
+    body::Bytes,
+    extract::{Extension, Form, Multipart, Path, State},
+    http::{header, StatusCode, Uri},
+    response::{Html, IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufRead, Cursor, Seek};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::web::auth::{AuthError, Claims, Credentials, JwtAuth};
+use crate::web::model::{StoredExtraction, WorkSchedule};
+use crate::web::parser::{is_parser_unavailable, parse_schedule_image, ParseError, Provider};
+use crate::web::pending::PendingUpload;
+use crate::web::preprocess::{image_dimensions, preprocess_image, ImageFormat, PreprocessPool};
+use crate::web::render::{html_escape, render_name_suggestions, render_schedule_card};
+use crate::web::spool::{spool_field, SpoolError, SpooledFile};
+use crate::web::validation::{check_period, is_suspect_parse, parse_record};
+use crate::web::AppState;
+
+/// Handler for the index page
+pub async fn index_handler() -> impl IntoResponse {
+    Html(include_str!("../../assets/work_hours/index.html"))
+}
+
+/// Extracts query parameters from the URI
+fn get_query_params(uri: Uri) -> HashMap<String, String> {
+    let query = uri.query().unwrap_or("");
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.split('=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => Some((
+                    key.to_string(),
+                    // Try to URL decode the value
+                    percent_decode(value),
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Simple percent decoding function
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = input
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = byte {
+                    result.push(byte);
+                    i += 3;
+                    continue;
+                }
+                result.push(b'%');
+            }
+            b'+' => result.push(b' '),
+            byte => result.push(byte),
+        }
+        i += 1;
+    }
+
+    // Invalid UTF-8 sequences are replaced rather than rejected
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Simple percent encoding function
+fn percent_encode(input: &str) -> String {
+    input
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.' || b == b'~' {
+                (b as char).to_string()
+            } else if b == b' ' {
+                '+'.to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect()
+}
+
+/// List of acceptable error messages
+const ALLOWED_ERROR_MESSAGES: [&str; 2] = ["Invalid credentials", "Authentication error occurred"];
+
+/// Handler for the login form page
+pub async fn login_form_handler(uri: Uri) -> impl IntoResponse {
+    let params = get_query_params(uri);
+    let error = params.get("error").cloned();
+
+    let html = include_str!("../../assets/work_hours/login.html");
+    let html = if let Some(error_msg) = error {
+        // Only display the error if it's in our allowed list
+        if ALLOWED_ERROR_MESSAGES.contains(&error_msg.as_str()) {
+            html.replace(
+                "<!-- ERROR_MESSAGE -->",
+                &format!("<div class=\"bg-red-600 text-white p-4 rounded mb-4\">{error_msg}</div>"),
+            )
+        } else {
+            // If error not in allowed list, don't show anything
+            html.to_string()
+        }
+    } else {
+        html.to_string()
+    };
+
+    Html(html)
+}
+
+/// Handler for login form submission
+pub async fn login_handler(
+    State(state): State<AppState>,
+    Form(credentials): Form<Credentials>,
+) -> impl IntoResponse {
+    // Authenticate the user
+    match state
+        .auth_service
+        .authenticate(&credentials.username, &credentials.password)
+    {
+        Ok(token) => {
+            info!("User {} successfully authenticated", credentials.username);
+            // Create a response with a redirect and set the auth cookie
+            let cookie = format!("auth_token={token}; Path=/; HttpOnly; SameSite=Strict");
+            let mut response = Redirect::to("/upload").into_response();
+            response.headers_mut().insert(
+                header::SET_COOKIE,
+                header::HeaderValue::from_str(&cookie).unwrap(),
+            );
+            response
+        }
+        Err(AuthError::Unauthorized) => {
+            error!("Failed login attempt for user: {}", credentials.username);
+            let encoded_error = percent_encode(ALLOWED_ERROR_MESSAGES[0]);
+            let mut response =
+                Redirect::to(&format!("/login?error={encoded_error}")).into_response();
+            response.headers_mut().insert(
+                header::SET_COOKIE,
+                header::HeaderValue::from_static("auth_token=; Path=/; HttpOnly; Max-Age=0"),
+            );
+            response
+        }
+        Err(err) => {
+            error!("Authentication error: {:?}", err);
+            let encoded_error = percent_encode(ALLOWED_ERROR_MESSAGES[1]);
+            let mut response =
+                Redirect::to(&format!("/login?error={encoded_error}")).into_response();
+            response.headers_mut().insert(
+                header::SET_COOKIE,
+                header::HeaderValue::from_static("auth_token=; Path=/; HttpOnly; Max-Age=0"),
+            );
+            response
+        }
+    }
+}
+
+/// Redirect back to the upload form with an error code, keeping the entered name
+pub(crate) fn upload_error_redirect(code: &str, name: &str, detail: Option<&str>) -> Redirect {
+    let mut location = format!("/upload?error={code}&name={}", percent_encode(name));
+    if let Some(detail) = detail {
+        location.push_str(&format!("&detail={}", percent_encode(detail)));
+    }
+    Redirect::to(&location)
+}
+
+/// Handler for the upload form page
+pub async fn upload_form_handler(
+    uri: Uri,
+    Extension(auth): Extension<JwtAuth>,
+) -> impl IntoResponse {
+    let params = get_query_params(uri);
+
+    // Prefer the name from a failed upload, then the token, then the default
+    let name_for_value = params
+        .get("name")
+        .cloned()
+        .or_else(|| auth.claims.name.clone())
+        .unwrap_or_else(|| env::var("DEFAULT_EMPLOYEE_NAME").unwrap_or_else(|_| "".to_string()));
+
+    // Only display the error if it's in our allowed list
+    let error_html = params
+        .get("error")
+        .and_then(|code| upload_error_message(code))
+        .map(|message| {
+            let detail = params
+                .get("detail")
+                .map(|detail| {
+                    format!(
+                        "<p class=\"mt-1 text-sm text-red-100\">{}</p>",
+                        html_escape(detail)
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                "<div class=\"bg-red-600 text-white p-4 rounded mb-4\">{}{detail}</div>",
+                html_escape(&message)
+            )
+        })
+        .unwrap_or_default();
+
+    let html = include_str!("../../assets/work_hours/upload.html")
+        .replace(
+            "value=\"\"",
+            &format!("value=\"{}\"", html_escape(&name_for_value)),
+        )
+        .replace("<!-- ERROR_MESSAGE -->", &error_html)
+        .replace(
+            "<!-- EMPLOYEE_SUGGESTIONS -->",
+            &render_name_suggestions(MAX_SUGGEST_QUERY_LEN),
+        );
+
+    Html(html)
+}
+
+/// Handler for the dashboard page
+pub async fn dashboard_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut employees = state.db.list_employees().await.map_err(|e| {
+        error!("Failed to list employees: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    employees.sort();
+
+    let budget = hours_budget(&state).await;
+
+    let mut cards = Vec::new();
+    for employee in &employees {
+        match state.db.get_schedule(employee).await {
+            Ok(Some(schedule)) => cards.push(render_schedule_card(&schedule, None, Some(&budget))),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to load schedule for {}: {}", Redacted(&employee), e),
+        }
+    }
+
+    let html = include_str!("../../assets/work_hours/dashboard.html")
+        .replace("<!-- EMPLOYEE_SCHEDULES -->", &cards.join("\n"))
+        .replace(
+            "<!-- EMPLOYEE_COUNT -->",
+            &format!("Showing {} of {} employees", cards.len(), employees.len()),
+        );
+
+    Ok(Html(html))
+}
+
+/// Response for a generated magic link
+#[derive(Debug, Serialize)]
+pub struct MagicLinkResponse {
+    pub employee: String,
+    pub token: String,
+    pub url: String,
+}
+
+/// Check that the claims may read the given employee's data.
+///
+/// Admins may read anything; magic link tokens only their own employee, and only while
+/// their token version hasn't been revoked.
+pub(crate) async fn authorize_employee_access(
+    state: &AppState,
+    claims: &Claims,
+    employee_name: &str,
+) -> Result<(), StatusCode> {
+    if claims.is_admin() {
+        return Ok(());
+    }
+
+    if !claims.is_employee_scoped()
+        || EmployeeId::new(&claims.sub) != EmployeeId::new(employee_name)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let current_version = state
+        .db
+        .get_token_version(employee_name)
+        .await
+        .map_err(|e| {
+            error!("Failed to read token version: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if claims.ver == Some(current_version) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Most names the suggestion endpoint returns
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Longest name query the suggestion endpoint accepts, in characters
+const MAX_SUGGEST_QUERY_LEN: usize = 64;
+
+/// Stored employee names containing the query, ignoring case and diacritics. Names starting
+/// with it come first.
+fn suggest_employees(mut employees: Vec<String>, query: &str) -> Vec<String> {
+    let query = EmployeeId::new(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+    employees.sort();
+
+    let (mut prefixed, mut contained) = (Vec::new(), Vec::new());
+    for name in employees {
+        let slug = EmployeeId::new(&name).slug().to_string();
+        if slug.starts_with(query.slug()) {
+            prefixed.push(name);
+        } else if slug.contains(query.slug()) {
+            contained.push(name);
+        }
+    }
+    prefixed
+        .into_iter()
+        .chain(contained)
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// Handler suggesting stored employee names for a partial name (admin only)
+pub async fn suggest_employees_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    uri: Uri,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let query = get_query_params(uri).remove("q").unwrap_or_default();
+    if query.chars().count() > MAX_SUGGEST_QUERY_LEN {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let employees = state.db.list_employees().await.map_err(|e| {
+        error!("Failed to list employees for suggestions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(suggest_employees(employees, &query)))
+}
+
+/// Handler returning an employee's stored schedule as JSON
+pub async fn employee_schedule_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(employee_name): Path<String>,
+) -> Result<Json<WorkSchedule>, StatusCode> {
+    authorize_employee_access(&state, &auth.claims, &employee_name).await?;
+
+    match state.db.get_schedule(&employee_name).await {
+        Ok(Some(schedule)) => Ok(Json(schedule)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!(
+                "Failed to load schedule for {}: {}",
+                Redacted(&employee_name),
+                e
+            );
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Handler generating a magic link for an employee (admin only)
+pub async fn create_magic_link_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(employee_name): Path<String>,
+) -> Result<Json<MagicLinkResponse>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let version = state
+        .db
+        .get_token_version(&employee_name)
+        .await
+        .map_err(|e| {
+            error!("Failed to read token version: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let token = state
+        .auth_service
+        .generate_magic_link_token(&employee_name, version)
+        .map_err(|e| {
+            error!("{}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let base_url = env::var("PUBLIC_BASE_URL").unwrap_or_default();
+    let url = format!("{}/me/{token}", base_url.trim_end_matches('/'));
+
+    info!("Generated magic link for {}", Redacted(&employee_name));
+    Ok(Json(MagicLinkResponse {
+        employee: employee_name,
+        token,
+        url,
+    }))
+}
+
+/// Handler revoking all magic links of an employee (admin only)
+pub async fn revoke_magic_link_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(employee_name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    state
+        .db
+        .bump_token_version(&employee_name)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke magic links: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for the employee self-service page reached through a magic link
+pub async fn me_handler(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    let claims = match state.auth_service.validate_token(&token) {
+        Ok(claims) if claims.is_employee_scoped() => claims,
+        _ => return (StatusCode::FORBIDDEN, "This link is invalid or has expired").into_response(),
+    };
+
+    if authorize_employee_access(&state, &claims, &claims.sub)
+        .await
+        .is_err()
+    {
+        return (StatusCode::FORBIDDEN, "This link has been revoked").into_response();
+    }
+
+    let today = Local::now().date_naive();
+    let card = match state.db.get_schedule(&claims.sub).await {
+        Ok(Some(schedule)) => render_schedule_card(&schedule, Some(today), None),
+        Ok(None) => render_schedule_card(&WorkSchedule::new(claims.sub.clone()), Some(today), None),
+        Err(e) => {
+            error!(
+                "Failed to load schedule for {}: {}",
+                Redacted(&claims.sub),
+                e
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let html = include_str!("../../assets/work_hours/me.html").replace("<!-- SCHEDULE -->", &card);
+    Html(html).into_response()
+}
+
+/// Get the default employee name from environment
+fn get_default_employee_name() -> String {
+    env::var("DEFAULT_EMPLOYEE_NAME").unwrap_or_else(|_| "Brian".to_string())
+}
+
+/// Handler for file uploads
+pub async fn upload_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    mut multipart: Multipart,
+) -> Result<Redirect, StatusCode> {
+    let mut name = None;
+    let mut schedule_file = None;
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                error!("Failed to read multipart upload: {}", e);
+                let code = if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    "too_large"
+                } else {
+                    "bad_format"
+                };
+                return Ok(upload_error_redirect(
+                    code,
+                    name.as_deref().unwrap_or_default(),
+                    None,
+                ));
+            }
+        };
+        let field_name = field.name().unwrap_or_default().to_string();
+
+        if field_name == "name" {
+            if let Ok(value) = field.text().await {
+                name = Some(value);
+            }
+        } else if field_name == "schedule_file" {
+            // Written to disk as it arrives, so the image is checked before it's read whole
+            match spool_field(field, &state.upload_dir, MAX_FILE_SIZE).await {
+                Ok(file) => schedule_file = Some(file),
+                Err(SpoolError::Write(e)) => {
+                    error!("Failed to spool uploaded file: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+                Err(e) => {
+                    // Reading the body only fails this late when it exceeds the body limit
+                    error!("Failed to read uploaded file: {}", e);
+                    return Ok(upload_error_redirect(
+                        "too_large",
+                        name.as_deref().unwrap_or_default(),
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Use provided name, or fall back to the name from auth token if available,
+    // or use the default name as last resort
+    let name_val = name.unwrap_or_else(|| {
+        auth.claims
+            .name
+            .clone()
+            .unwrap_or_else(get_default_employee_name)
+    });
+
+    // Store under the canonical display name
+    let name_val = match check_upload_name(&name_val) {
+        Ok(name) => name,
+        // Don't echo back a name too long for the form
+        Err(code) if name_val.len() > MAX_NAME_LENGTH => {
+            return Ok(upload_error_redirect(code, "", None))
+        }
+        Err(code) => return Ok(upload_error_redirect(code, &name_val, None)),
+    };
+
+    // Process the file and schedule
+    let Some(file) = schedule_file else {
+        error!("Missing required fields for upload");
+        return Ok(upload_error_redirect("empty_file", &name_val, None));
+    };
+    let (file_data, format) =
+        match check_spooled_file(&state.preprocess_pool, &file, state.max_image_pixels).await {
+            Ok(checked) => checked,
+            Err(code) => return Ok(upload_error_redirect(code, &name_val, None)),
+        };
+
+    // Parse the schedule without date range
+    let provider = Provider::default();
+    let outcome = process_upload(&state, &name_val, &file_data, format, provider, || {
+        parse_schedule_image(&name_val, &file_data, provider)
+    })
+    .await;
+    upload_redirect(outcome, &name_val)
+}
+
+/// Where the upload form goes after an upload
+fn upload_redirect(outcome: UploadOutcome, name: &str) -> Result<Redirect, StatusCode> {
+    match outcome {
+        UploadOutcome::Stored(_) | UploadOutcome::AlreadyStored => Ok(Redirect::to("/dashboard")),
+        UploadOutcome::SuspiciousPeriod { pending_id, .. } => {
+            Ok(Redirect::to(&format!("/upload/confirm/{pending_id}")))
+        }
+        UploadOutcome::Expired => Ok(upload_error_redirect("upload_expired", name, None)),
+        UploadOutcome::StoreFailed(e) => {
+            error!("Failed to store schedule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        UploadOutcome::ParseFailed(e) => {
+            let (code, detail) = parse_failure(&e);
+            Ok(upload_error_redirect(code, name, detail))
+        }
+    }
+}
+
+/// Handler for the page asking to confirm an upload held for its period
+pub async fn confirm_upload_form_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let Some((summary, issue)) = state.pending_uploads.get(&id) else {
+        return Ok(upload_error_redirect("upload_expired", "", None).into_response());
+    };
+
+    let html = include_str!("../../assets/work_hours/confirm_upload.html")
+        .replace("<!-- EMPLOYEE -->", &html_escape(&summary.employee))
+        .replace(
+            "<!-- DETECTED_RANGE -->",
+            &format!("{} – {}", summary.start_date, summary.end_date),
+        )
+        .replace("<!-- REASON -->", &html_escape(&issue.message()))
+        .replace("<!-- PENDING_ID -->", &html_escape(&id));
+    Ok(Html(html).into_response())
+}
+
+/// Handler storing an upload held for its period after the uploader confirmed it
+pub async fn confirm_upload_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(id): Path<String>,
+) -> Result<Redirect, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    upload_redirect(confirm_upload(&state, &id).await, "")
+}
+
+/// Handler parsing and storing a schedule image posted by the bot, e.g. from a Discord
+/// attachment (admin or service token). The employee is given with `?employee=` and the image
+/// is the body.
+pub async fn api_upload_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    uri: Uri,
+    body: Bytes,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    if !auth.claims.is_admin() && !auth.claims.is_service() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let rejected = |code: &str, detail: Option<&str>| {
+        Ok(Json(UploadResponse::Rejected {
+            code: code.to_string(),
+            detail: detail.map(str::to_string),
+        }))
+    };
+    let name = get_query_params(uri).remove("employee").unwrap_or_default();
+    let name = match check_upload_name(&name) {
+        Ok(name) => name,
+        Err(code) => return rejected(code, None),
+    };
+    let (data, format) =
+        match check_upload_file(&state.preprocess_pool, body, state.max_image_pixels).await {
+            Ok(checked) => checked,
+            Err(code) => return rejected(code, None),
+        };
+
+    let provider = Provider::default();
+    let outcome = process_upload(&state, &name, &data, format, provider, || {
+        parse_schedule_image(&name, &data, provider)
+    })
+    .await;
+    upload_response(outcome)
+}
+
+/// Handler storing an upload held for its period once the bot's user confirmed it (admin or
+/// service token)
+pub async fn api_confirm_upload_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(id): Path<String>,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    if !auth.claims.is_admin() && !auth.claims.is_service() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    upload_response(confirm_upload(&state, &id).await)
+}
+
+/// The upload API's answer for an upload
+fn upload_response(outcome: UploadOutcome) -> Result<Json<UploadResponse>, StatusCode> {
+    let rejected = |code: &str, detail: Option<&str>| {
+        Ok(Json(UploadResponse::Rejected {
+            code: code.to_string(),
+            detail: detail.map(str::to_string),
+        }))
+    };
+    match outcome {
+        UploadOutcome::Stored(summary) => Ok(Json(UploadResponse::Stored(summary))),
+        UploadOutcome::AlreadyStored => Ok(Json(UploadResponse::AlreadyStored)),
+        UploadOutcome::SuspiciousPeriod {
+            pending_id,
+            issue,
+            summary,
+        } => Ok(Json(UploadResponse::SuspiciousPeriod {
+            pending_id,
+            issue,
+            summary,
+        })),
+        UploadOutcome::Expired => rejected("upload_expired", None),
+        UploadOutcome::StoreFailed(e) => {
+            error!("Failed to store schedule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        UploadOutcome::ParseFailed(e) => {
+            let (code, detail) = parse_failure(&e);
+            rejected(code, detail)
+        }
+    }
+}
+
+/// Longest employee name accepted with an upload
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Check the employee name of an upload, returning its canonical display name or the upload
+/// form's error code
+fn check_upload_name(name: &str) -> Result<String, &'static str> {
+    let employee = EmployeeId::new(name);
+    if employee.is_empty() {
+        error!("Employee name cannot be empty");
+        return Err("name_invalid");
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        error!("Employee name is too long");
+        return Err("name_invalid");
+    }
+
+    // Ensure the name contains only valid characters (letters, spaces, and common punctuation)
+    if !name
+        .chars()
+        .all(|c| c.is_alphabetic() || c.is_whitespace() || c == '.' || c == '-' || c == '\'')
+    {
+        error!("Employee name contains invalid characters");
+        return Err("name_invalid");
+    }
+
+    Ok(employee.display().to_string())
+}
+
+/// Check an uploaded file and preprocess the image, or return the upload form's error code
+async fn check_upload_file(
+    pool: &PreprocessPool,
+    data: Bytes,
+    max_pixels: u64,
+) -> Result<(Vec<u8>, ImageFormat), &'static str> {
+    check_upload_size(data.len())?;
+    let header = data.clone();
+    pool.run(move || check_upload_header(Cursor::new(&header[..]), max_pixels))
+        .await
+        .map_err(preprocess_failed)??;
+    pool.run(move || preprocess_upload(Cursor::new(&data[..])))
+        .await
+        .map_err(preprocess_failed)?
+}
+
+/// Check an uploaded file spooled to disk, reading only its header until it has passed
+async fn check_spooled_file(
+    pool: &PreprocessPool,
+    file: &SpooledFile,
+    max_pixels: u64,
+) -> Result<(Vec<u8>, ImageFormat), &'static str> {
+    check_upload_size(file.len())?;
+    let file = file.open().map_err(|e| {
+        error!("Failed to open spooled upload: {}", e);
+        "bad_format"
+    })?;
+    let mut reader = std::io::BufReader::new(file);
+    let reader = pool
+        .run(move || check_upload_header(&mut reader, max_pixels).map(|()| reader))
+        .await
+        .map_err(preprocess_failed)??;
+    pool.run(move || preprocess_upload(reader))
+        .await
+        .map_err(preprocess_failed)?
+}
+
+/// The upload form's error code for image work that didn't finish
+fn preprocess_failed(error: String) -> &'static str {
+    error!("Failed to preprocess upload: {}", error);
+    "bad_format"
+}
+
+/// Check the size of an uploaded file, or return the upload form's error code
+fn check_upload_size(len: usize) -> Result<(), &'static str> {
+    if len == 0 {
+        error!("Uploaded file is empty");
+        return Err("empty_file");
+    }
+
+    if len > MAX_FILE_SIZE {
+        error!("Uploaded file is too large");
+        return Err("too_large");
+    }
+    Ok(())
+}
+
+/// Check the format and dimensions in an image's header, so decompression bombs are turned
+/// away before anything decodes them
+fn check_upload_header(reader: impl BufRead + Seek, max_pixels: u64) -> Result<(), &'static str> {
+    let (_, width, height) = image_dimensions(reader).map_err(|e| {
+        error!("Uploaded file is not a valid image: {}", e);
+        "bad_format"
+    })?;
+    if u64::from(width) * u64::from(height) > max_pixels {
+        error!("Uploaded image is too large: {}×{} pixels", width, height);
+        return Err("too_many_pixels");
+    }
+    Ok(())
+}
+
+/// Preprocess a checked image, or return the upload form's error code
+fn preprocess_upload(reader: impl BufRead + Seek) -> Result<(Vec<u8>, ImageFormat), &'static str> {
+    preprocess_image(reader).map_err(|e| {
+        error!("Uploaded file is not a valid image: {}", e);
+        "bad_format"
+    })
+}
+
+/// The upload form's error code for a failed parse, with the detail shown to the user
+fn parse_failure(error: &str) -> (&'static str, Option<&str>) {
+    if is_parser_unavailable(error) {
+        error!("Schedule parser unavailable: {}", error);
+        ("parser_unavailable", None)
+    } else if is_suspect_parse(error) {
+        warn!("Schedule parse rejected: {}", error);
+        ("parse_suspect", None)
+    } else {
+        error!("Failed to parse schedule: {}", error);
+        // Show the first line of the report so the user knows what went wrong
+        (
+            "parse_failed",
+            Some(error.lines().next().unwrap_or_default()),
+        )
+    }
+}
+
+/// Seconds within which the same image uploaded again for an employee isn't parsed again
+const DUPLICATE_UPLOAD_WINDOW_SECS: i64 = 10 * 60;
+
+/// Result of parsing and storing an uploaded schedule image
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum UploadOutcome {
+    /// The schedule was parsed and stored
+    Stored(UploadSummary),
+    /// The same image was stored for the employee moments ago, so it wasn't parsed again
+    AlreadyStored,
+    /// The schedule covers an unexpected period, so it's held until confirmed with `pending_id`
+    SuspiciousPeriod {
+        pending_id: String,
+        issue: PeriodIssue,
+        summary: UploadSummary,
+    },
+    /// The held upload to confirm was already stored or waited too long
+    Expired,
+    /// The parser failed with this report
+    ParseFailed(String),
+    /// Storing the parsed schedule failed with this error
+    StoreFailed(String),
+}
+
+/// Hash of an image's contents, to recognize the same image uploaded again
+fn image_hash(data: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Parse and store an uploaded schedule image, recording how the parse went.
+///
+/// Uploads for the same employee run one at a time, so a double-submitted form can't
+/// interleave two writes of a schedule. An image identical to one stored for the employee
+/// within the last ten minutes isn't parsed again. A schedule that doesn't cover the coming
+/// weeks is held until the upload is confirmed with [`confirm_upload`].
+pub(crate) async fn process_upload<F, Fut>(
+    state: &AppState,
+    employee: &str,
+    data: &[u8],
+    format: ImageFormat,
+    provider: Provider,
+    parse: F,
+) -> UploadOutcome
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<WorkSchedule, ParseError>>,
+{
+    let id = EmployeeId::new(employee);
+    let _guard = state.upload_locks.lock(&id).await;
+
+    let hash = image_hash(data);
+    let since = Utc::now().timestamp() - DUPLICATE_UPLOAD_WINDOW_SECS;
+    match state.db.list_uploads().await {
+        Ok(uploads) => {
+            let duplicate = uploads.iter().any(|upload| {
+                upload.uploaded_at >= since
+                    && upload.image_hash.as_deref() == Some(hash.as_str())
+                    && EmployeeId::new(&upload.employee) == id
+            });
+            if duplicate {
+                info!(
+                    "Schedule image for {} was already stored, skipping",
+                    Redacted(&id)
+                );
+                return UploadOutcome::AlreadyStored;
+            }
+        }
+        // Parsing again is only slower, so don't fail the upload over it
+        Err(e) => warn!("Failed to list recent uploads: {}", e),
+    }
+
+    let schedule = match parse().await {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            record_parse_failure(state, &id, &hash, &e).await;
+            return UploadOutcome::ParseFailed(e.message);
+        }
+    };
+
+    // No upload path gives the period it's for, so the coming weeks are expected
+    let dates: Vec<NaiveDate> = schedule
+        .days
+        .iter()
+        .filter_map(|day| NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").ok())
+        .collect();
+    if let Some(issue) = check_period(&dates, Local::now().date_naive()) {
+        let summary = upload_summary(state, employee, &schedule).await;
+        info!(
+            "Holding schedule for {} covering {} to {} until confirmed: {:?}",
+            Redacted(employee),
+            summary.start_date,
+            summary.end_date,
+            issue
+        );
+        let pending_id = state.pending_uploads.hold(PendingUpload {
+            employee: employee.to_string(),
+            data: data.to_vec(),
+            format,
+            provider,
+            hash,
+            schedule,
+            issue,
+            summary: summary.clone(),
+        });
+        return UploadOutcome::SuspiciousPeriod {
+            pending_id,
+            issue,
+            summary,
+        };
+    }
+
+    store_parsed(state, employee, data, format, provider, hash, schedule).await
+}
+
+/// Store an upload held for its period, once the uploader confirmed it
+pub(crate) async fn confirm_upload(state: &AppState, pending_id: &str) -> UploadOutcome {
+    let Some(upload) = state.pending_uploads.take(pending_id) else {
+        return UploadOutcome::Expired;
+    };
+    let _guard = state
+        .upload_locks
+        .lock(&EmployeeId::new(&upload.employee))
+        .await;
+    info!(
+        "Held schedule for {} confirmed as {:?}",
+        Redacted(&upload.employee),
+        upload.issue
+    );
+    store_parsed(
+        state,
+        &upload.employee,
+        &upload.data,
+        upload.format,
+        upload.provider,
+        upload.hash,
+        upload.schedule,
+    )
+    .await
+}
+
+/// Summary of a parsed schedule for the uploader, with its hours against the employee's
+/// contract
+async fn upload_summary(
+    state: &AppState,
+    employee: &str,
+    schedule: &WorkSchedule,
+) -> UploadSummary {
+    let entries: Vec<_> = schedule.days.iter().map(|day| day.to_entry()).collect();
+    UploadSummary::new(employee, &entries).with_contract(&hours_budget(state).await, &entries)
+}
+
+/// Contract hours of the employees, without any when they can't be loaded
+async fn hours_budget(state: &AppState) -> HoursBudget {
+    let contracts = state.db.list_contract_hours().await.unwrap_or_else(|e| {
+        warn!("Failed to load contract hours: {}", e);
+        Vec::new()
+    });
+    HoursBudget::new(contracts, state.contract_tolerance_hours).with_week_start(state.week_start)
+}
+
+/// Store a parsed schedule with its image and parse record. The caller holds the employee's
+/// upload lock.
+async fn store_parsed(
+    state: &AppState,
+    employee: &str,
+    data: &[u8],
+    format: ImageFormat,
+    provider: Provider,
+    hash: String,
+    mut schedule: WorkSchedule,
+) -> UploadOutcome {
+    let id = EmployeeId::new(employee);
+    let uploaded_at = Utc::now().timestamp();
+    let upload_id = upload_id(&id, uploaded_at);
+    schedule.upload_id = Some(upload_id.clone());
+    if let Err(e) = state.db.set_schedule(employee, &schedule).await {
+        return UploadOutcome::StoreFailed(e);
+    }
+    state.publish_update(
+        employee,
+        schedule.days.iter().map(|day| day.date.clone()).collect(),
+    );
+    info!(
+        "Schedule for {} processed and stored successfully",
+        Redacted(employee)
+    );
+
+    // Only feeds the quality trend, so the upload still succeeds without it
+    let record = parse_record(&upload_id, uploaded_at, provider, &schedule);
+    if let Err(e) = state.db.record_parse(&record).await {
+        warn!("Failed to record parse quality of {}: {}", upload_id, e);
+    }
+
+    // Kept so the days can be converted again once the meaning of a cell changes
+    if let Some(days) = schedule.extraction.take() {
+        let extraction = StoredExtraction {
+            upload_id: upload_id.clone(),
+            employee: employee.to_string(),
+            extracted_at: uploaded_at,
+            days,
+        };
+        if let Err(e) = state.db.record_extraction(&extraction).await {
+            warn!("Failed to keep the extraction of {}: {}", upload_id, e);
+        }
+    }
+
+    store_upload_image(state, employee, data, format, &schedule, &upload_id, hash).await;
+    UploadOutcome::Stored(upload_summary(state, employee, &schedule).await)
+}
+
+/// Keep the model's response of a failed parse for debugging the prompt. Failures that
+/// happened before the model answered, such as an unreachable parser, aren't kept.
+async fn record_parse_failure(state: &AppState, id: &EmployeeId, hash: &str, error: &ParseError) {
+    let Some((stage, exchange)) = error.failure.as_deref() else {
+        return;
+    };
+
+    let failed_at = Utc::now().timestamp();
+    let failure = ParseFailure {
+        id: upload_id(id, failed_at),
+        failed_at,
+        stage: *stage,
+        error: error.message.clone(),
+        image_hash: hash.to_string(),
+        exchange: exchange.clone(),
+        truncated: false,
+    };
+    match state.db.record_parse_failure(&failure).await {
+        Ok(()) => info!("Kept the model response of failed parse {}", failure.id),
+        Err(e) => warn!("Failed to keep failed parse {}: {}", failure.id, e),
+    }
+}
+
+/// Keep an uploaded image next to its parsed schedule so the bot can attach it to
+/// notifications. Failures are only logged since the schedule itself is already stored.
+async fn store_upload_image(
+    state: &AppState,
+    employee: &str,
+    data: &[u8],
+    format: ImageFormat,
+    schedule: &WorkSchedule,
+    upload_id: &str,
+    image_hash: String,
+) {
+    let dates = schedule.days.iter().map(|day| day.date.as_str());
+    let (Some(start_date), Some(end_date)) = (dates.clone().min(), dates.max()) else {
+        return;
+    };
+
+    let uploaded_at = Utc::now().timestamp();
+    let upload = StoredUpload {
+        employee: employee.to_string(),
+        file_name: format!("{upload_id}.{}", format.extension()),
+        start_date: start_date.to_string(),
+        end_date: end_date.to_string(),
+        uploaded_at,
+        image_hash: Some(image_hash),
+    };
+
+    let path = state.upload_dir.join(&upload.file_name);
+    let written = match tokio::fs::create_dir_all(&state.upload_dir).await {
+        Ok(()) => tokio::fs::write(&path, data).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        warn!("Failed to keep schedule image {}: {}", path.display(), e);
+        return;
+    }
+
+    if let Err(e) = state.db.record_upload(&upload).await {
+        warn!(
+            "Failed to record schedule image {}: {}",
+            upload.file_name, e
+        );
+    }
+}
+
+/// Handler serving a stored schedule image with its detected content type (admin or service
+/// token)
+pub async fn upload_image_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(file_name): Path<String>,
+) -> Result<Response, StatusCode> {
+    if !auth.claims.is_admin() && !auth.claims.is_service() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Only serve images the app has recorded, which also rules out path traversal
+    let uploads = state.db.list_uploads().await.map_err(|e| {
+        error!("Failed to list schedule images: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !StoredUpload::is_valid_file_name(&file_name)
+        || !uploads.iter().any(|upload| upload.file_name == file_name)
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let data = tokio::fs::read(state.upload_dir.join(&file_name))
+        .await
+        .map_err(|e| {
+            warn!("Failed to read schedule image {}: {}", file_name, e);
+            StatusCode::NOT_FOUND
+        })?;
+
+    let content_type = ImageFormat::detect(&data)
+        .map(|format| format.mime_type())
+        .unwrap_or("application/octet-stream");
+    Ok(([(header::CONTENT_TYPE, content_type)], data).into_response())
+}
+
+/// Handler returning weekly parse quality aggregates, oldest week first (admin only)
+pub async fn quality_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    uri: Uri,
+) -> Result<Json<Vec<WeeklyQuality>>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let weeks = match get_query_params(uri).remove("weeks") {
+        Some(weeks) => weeks
+            .parse::<u32>()
+            .ok()
+            .filter(|weeks| (1..=MAX_QUALITY_WEEKS).contains(weeks))
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        None => DEFAULT_QUALITY_WEEKS,
+    };
+
+    let records = state.db.list_parse_records().await.map_err(|e| {
+        error!("Failed to list parse records: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(weekly_quality(
+        &records,
+        Local::now().date_naive(),
+        weeks,
+        state.week_start,
+    )))
+}
+
+/// A parse failure in the listing, without the prompt and response
+#[derive(Debug, Serialize)]
+pub struct ParseFailureSummary {
+    id: String,
+    failed_at: i64,
+    stage: ParseFailureStage,
+    error: String,
+    employee: String,
+    provider: String,
+    model: String,
+    image_hash: String,
+}
+
+impl From<ParseFailure> for ParseFailureSummary {
+    fn from(failure: ParseFailure) -> Self {
+        Self {
+            id: failure.id,
+            failed_at: failure.failed_at,
+            stage: failure.stage,
+            error: failure.error,
+            employee: failure.exchange.employee,
+            provider: failure.exchange.provider,
+            model: failure.exchange.model,
+            image_hash: failure.image_hash,
+        }
+    }
+}
+
+/// Handler listing the latest parse failures, newest first (admin only)
+pub async fn parse_failures_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+) -> Result<Json<Vec<ParseFailureSummary>>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let failures = state.db.list_parse_failures().await.map_err(|e| {
+        error!("Failed to list parse failures: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(failures.into_iter().map(Into::into).collect()))
+}
+
+/// Handler returning a stored parse failure with the prompt and the model's raw response,
+/// for retrying it with `work_hours parse --failure` (admin only)
+pub async fn parse_failure_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(id): Path<String>,
+) -> Result<Json<ParseFailure>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Ids that aren't valid keys can't have been stored
+    if validate_segment(&id).is_err() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    match state.db.get_parse_failure(&id).await {
+        Ok(Some(failure)) => Ok(Json(failure)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get parse failure {}: {}", id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// How long the health checks wait for Redis before reporting it degraded
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Status of the app and its dependencies, as reported by `/health` and `/ready`
+#[derive(Debug, Serialize)]
+pub struct HealthStatus {
+    status: &'static str,
+    redis: &'static str,
+    /// Parser environment variables that aren't set, only checked by `/ready`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_env: Option<Vec<&'static str>>,
+    /// The bot's gateway connection, only reported when the app runs in the bot's process
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gateway: Option<&'static str>,
+    version: &'static str,
+    git_sha: &'static str,
+    uptime_seconds: u64,
+}
+
+impl HealthStatus {
+    fn new(state: &AppState, redis_ok: bool, missing_env: Option<Vec<&'static str>>) -> Self {
+        let gateway_ok = state
+            .gateway_connected
+            .as_ref()
+            .map(|connected| connected.load(Ordering::Relaxed));
+        let healthy =
+            redis_ok && missing_env.as_ref().is_none_or(Vec::is_empty) && gateway_ok != Some(false);
+        Self {
+            status: if healthy { "ok" } else { "degraded" },
+            redis: if redis_ok { "ok" } else { "degraded" },
+            missing_env,
+            gateway: gateway_ok.map(|ok| if ok { "ok" } else { "degraded" }),
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: option_env!("GIT_SHA").unwrap_or("unknown"),
+            uptime_seconds: state.started_at.elapsed().as_secs(),
+        }
+    }
+
+    fn into_response(self) -> Response {
+        let code = if self.status == "ok" {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (code, Json(self)).into_response()
+    }
+}
+
+/// Ping Redis, giving up after `HEALTH_TIMEOUT`
+async fn redis_ok(state: &AppState) -> bool {
+    match tokio::time::timeout(HEALTH_TIMEOUT, state.db.ping()).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            warn!("Health check failed to reach Redis: {}", e);
+            false
+        }
+        Err(_) => {
+            warn!("Health check timed out reaching Redis");
+            false
+        }
+    }
+}
+
+/// Names in `vars` that `is_set` doesn't accept
+pub(crate) fn missing_env_vars(
+    vars: &[&'static str],
+    is_set: impl Fn(&str) -> bool,
+) -> Vec<&'static str> {
+    vars.iter().copied().filter(|var| !is_set(var)).collect()
+}
+
+/// Handler for the liveness check, answering 503 when Redis can't be reached
+pub async fn health_handler(State(state): State<AppState>) -> Response {
+    HealthStatus::new(&state, redis_ok(&state).await, None).into_response()
+}
+
+/// Handler for the readiness check, which also needs the parser to be configured
+pub async fn ready_handler(State(state): State<AppState>) -> Response {
+    let missing = missing_env_vars(&Provider::default().required_env_vars(), |var| {
+        env::var(var).is_ok_and(|value| !value.is_empty())
+    });
+    HealthStatus::new(&state, redis_ok(&state).await, Some(missing)).into_response()
+}
@@ -0,0 +1,386 @@
+use crate::components::work_schedule::models::ShiftRange;
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::redact::Redacted;
+use crate::utils::time::parse_time;
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{error, info};
+
+use crate::web::auth::JwtAuth;
+use crate::web::model::{WorkDay, WorkSchedule};
+use crate::web::AppState;
+
+/// Columns an import file must have; `shifts`, `break_minutes` and `notes` may be left out
+pub const REQUIRED_COLUMNS: [&str; 3] = ["employee", "date", "day_type"];
+
+/// Days written to an employee's schedule at a time
+pub const IMPORT_BATCH_SIZE: usize = 500;
+
+/// Row errors and warnings listed in the report; the counts include the rest
+pub const MAX_REPORTED_ROWS: usize = 50;
+
+/// Query parameters of the import
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Check the file and report what would be imported without writing anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// A row as it appears in the file
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    employee: String,
+    date: String,
+    day_type: String,
+    #[serde(default)]
+    shifts: String,
+    #[serde(default)]
+    break_minutes: Option<u16>,
+    #[serde(default)]
+    notes: String,
+}
+
+/// A problem with a row, numbered by its line in the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowIssue {
+    pub row: u64,
+    pub message: String,
+}
+
+/// What an import did, or would do in a dry run
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Days written, or that would be written
+    pub imported: usize,
+    /// Rows replaced by a later row for the same employee and date
+    pub skipped: usize,
+    /// Rows that couldn't be read
+    pub errors: usize,
+    /// The first row errors
+    pub row_errors: Vec<RowIssue>,
+    /// The first warnings, such as rows replaced by later ones
+    pub warnings: Vec<RowIssue>,
+}
+
+impl ImportReport {
+    fn error(&mut self, row: u64, message: String) {
+        self.errors += 1;
+        if self.row_errors.len() < MAX_REPORTED_ROWS {
+            self.row_errors.push(RowIssue { row, message });
+        }
+    }
+
+    fn warning(&mut self, row: u64, message: String) {
+        if self.warnings.len() < MAX_REPORTED_ROWS {
+            self.warnings.push(RowIssue { row, message });
+        }
+    }
+}
+
+/// A valid row: the day to store and whose schedule it goes to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedDay {
+    pub row: u64,
+    pub employee: String,
+    pub day: WorkDay,
+}
+
+/// Read a time of the file as HH:MM
+pub(crate) fn import_time(time: &str) -> Result<String, String> {
+    let time = parse_time(time).map_err(|e| format!("invalid time \"{}\": {e}", time.trim()))?;
+    Ok(time.time().format("%H:%M").to_string())
+}
+
+/// Read the shifts column, ranges like "08:00-16:00" separated by semicolons
+fn import_shifts(shifts: &str) -> Result<Vec<ShiftRange>, String> {
+    shifts
+        .split(';')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let (start, end) = range
+                .split_once('-')
+                .ok_or_else(|| format!("invalid shift \"{range}\", expected HH:MM-HH:MM"))?;
+            Ok(ShiftRange::new(import_time(start)?, import_time(end)?))
+        })
+        .collect()
+}
+
+/// Turn a row into the day it describes
+fn import_day(row: CsvRow) -> Result<(String, WorkDay), String> {
+    let employee = row.employee.trim().to_string();
+    if employee.is_empty() {
+        return Err("employee is empty".to_string());
+    }
+    let date = NaiveDate::parse_from_str(row.date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("invalid date \"{}\", expected YYYY-MM-DD", row.date.trim()))?;
+    let shifts = import_shifts(&row.shifts)?;
+    let notes = Some(row.notes.trim().to_string()).filter(|notes| !notes.is_empty());
+
+    let day_type = row.day_type.trim().to_lowercase();
+    if !shifts.is_empty() && day_type != "work" {
+        return Err(format!("a day of type {day_type} can't have shifts"));
+    }
+
+    let mut day = WorkDay {
+        date: date.format("%Y-%m-%d").to_string(),
+        shifts,
+        is_day_off: false,
+        notes,
+        break_minutes: row.break_minutes,
+        context_link: None,
+        actual_start: None,
+        actual_end: None,
+    };
+    match day_type.as_str() {
+        "work" if day.shifts.is_empty() => return Err("a work day needs shifts".to_string()),
+        "work" => {}
+        "off" => day.is_day_off = true,
+        // Vacation is a note in the schedule, as in the uploaded images
+        "vacation" => {
+            day.notes.get_or_insert_with(|| "vv".to_string());
+        }
+        "note" if day.notes.is_none() => return Err("a note day needs notes".to_string()),
+        "note" => {}
+        other => {
+            return Err(format!(
+                "unknown day_type \"{other}\", expected work, off, vacation or note"
+            ))
+        }
+    }
+    Ok((employee, day))
+}
+
+/// Read an import file into its valid days, in file order. Where rows repeat an employee's
+/// date the last one wins, with a warning.
+pub fn read_import(data: &[u8], report: &mut ImportReport) -> Result<Vec<ImportedDay>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read the header row: {e}"))?
+        .clone();
+    let missing: Vec<&str> = REQUIRED_COLUMNS
+        .into_iter()
+        .filter(|column| !headers.iter().any(|header| header == *column))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Missing columns: {}", missing.join(", ")));
+    }
+
+    let mut days: Vec<ImportedDay> = Vec::new();
+    let mut rows_by_day: HashMap<(EmployeeId, String), usize> = HashMap::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let row = e.position().map_or(0, |position| position.line());
+                report.error(row, e.to_string());
+                continue;
+            }
+        };
+        let row = record.position().map_or(0, |position| position.line());
+        let parsed = record
+            .deserialize::<CsvRow>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(import_day);
+        let (employee, day) = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                report.error(row, message);
+                continue;
+            }
+        };
+
+        let key = (EmployeeId::new(&employee), day.date.clone());
+        let imported = ImportedDay { row, employee, day };
+        match rows_by_day.get(&key) {
+            Some(&index) => {
+                let replaced = std::mem::replace(&mut days[index], imported);
+                report.skipped += 1;
+                report.warning(
+                    row,
+                    format!(
+                        "replaces row {} for {} on {}",
+                        replaced.row, replaced.employee, key.1
+                    ),
+                );
+            }
+            None => {
+                rows_by_day.insert(key, days.len());
+                days.push(imported);
+            }
+        }
+    }
+    Ok(days)
+}
+
+/// Write days into an employee's stored schedule, replacing what it had for their dates
+async fn upsert_days(state: &AppState, employee: &str, days: Vec<WorkDay>) -> Result<(), String> {
+    let id = EmployeeId::new(employee);
+    // Don't interleave with an upload for the same employee
+    let _guard = state.upload_locks.lock(&id).await;
+
+    let mut schedule = state
+        .db
+        .get_schedule(employee)
+        .await?
+        .unwrap_or_else(|| WorkSchedule::new(employee.to_string()));
+    let dates: HashSet<&str> = days.iter().map(|day| day.date.as_str()).collect();
+    schedule
+        .days
+        .retain(|day| !dates.contains(day.date.as_str()));
+    schedule.days.extend(days);
+    schedule.days.sort_by(|a, b| a.date.cmp(&b.date));
+    schedule.last_updated = Utc::now();
+    state.db.set_schedule(employee, &schedule).await
+}
+
+/// Write imported days in batches, one schedule write per employee in each
+pub async fn store_import(state: &AppState, days: &[ImportedDay]) -> Result<(), String> {
+    for batch in days.chunks(IMPORT_BATCH_SIZE) {
+        let mut by_employee: BTreeMap<&str, (&str, Vec<WorkDay>)> = BTreeMap::new();
+        let ids: Vec<EmployeeId> = batch
+            .iter()
+            .map(|imported| EmployeeId::new(&imported.employee))
+            .collect();
+        for (imported, id) in batch.iter().zip(&ids) {
+            by_employee
+                .entry(id.slug())
+                .or_insert_with(|| (imported.employee.as_str(), Vec::new()))
+                .1
+                .push(imported.day.clone());
+        }
+        for (employee, days) in by_employee.into_values() {
+            upsert_days(state, employee, days).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Handler importing historical schedules from CSV, one day per row (admin only)
+pub async fn import_csv_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<ImportReport>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut report = ImportReport {
+        dry_run: query.dry_run,
+        ..ImportReport::default()
+    };
+    let days = read_import(&body, &mut report).map_err(|e| {
+        info!("Rejected schedule import: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    report.imported = days.len();
+
+    if !query.dry_run {
+        if let Err(e) = store_import(&state, &days).await {
+            error!("Failed to store imported schedules: {}", Redacted(&e));
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        info!(
+            "Imported {} days, skipped {} rows and rejected {}",
+            report.imported, report.skipped, report.errors
+        );
+    }
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(csv: &str) -> (Vec<ImportedDay>, ImportReport) {
+        let mut report = ImportReport::default();
+        let days = read_import(csv.as_bytes(), &mut report).unwrap();
+        (days, report)
+    }
+
+    #[test]
+    fn test_rows_become_days() {
+        let (days, report) = read(
+            "employee,date,day_type,shifts,break_minutes,notes\n\
+             Anna,2024-03-04,work,8:00-12:00; 16:00-20:00,30,\n\
+             Anna,2024-03-05,off,,,\n\
+             Pekka,2024-03-04,vacation,,,\n\
+             Pekka,2024-03-05,note,,,koulutus\n",
+        );
+        assert_eq!(report.errors, 0, "{:?}", report.row_errors);
+        assert_eq!(
+            days[0].day.shifts,
+            [
+                ShiftRange::new("08:00", "12:00"),
+                ShiftRange::new("16:00", "20:00")
+            ]
+        );
+        assert_eq!(days[0].day.break_minutes, Some(30));
+        assert!(days[1].day.is_day_off);
+        assert_eq!(days[2].day.notes.as_deref(), Some("vv"));
+        assert_eq!(days[3].day.notes.as_deref(), Some("koulutus"));
+    }
+
+    #[test]
+    fn test_malformed_rows_are_reported_by_line() {
+        let (days, report) = read(
+            "employee,date,day_type,shifts\n\
+             Anna,2024-03-04,work,08:00-16:00\n\
+             Anna,04.03.2024,work,08:00-16:00\n\
+             Anna,2024-03-05,work,08:00-25:00\n\
+             Anna,2024-03-06,holiday,\n\
+             Anna,2024-03-07,work,\n\
+             Anna,2024-03-08,off,08:00-16:00\n\
+             Anna,2024-03-09\n\
+             ,2024-03-10,off,\n",
+        );
+        assert_eq!(days.len(), 1);
+        assert_eq!(report.errors, 7);
+        let rows: Vec<u64> = report.row_errors.iter().map(|issue| issue.row).collect();
+        assert_eq!(rows, [3, 4, 5, 6, 7, 8, 9]);
+        assert!(report.row_errors[0].message.contains("04.03.2024"));
+        assert!(report.row_errors[1].message.contains("25:00"));
+    }
+
+    #[test]
+    fn test_last_row_for_a_date_wins() {
+        let (days, report) = read(
+            "employee,date,day_type,shifts\n\
+             Anna,2024-03-04,work,08:00-16:00\n\
+             Pekka,2024-03-04,off,\n\
+             anna,2024-03-04,work,10:00-18:00\n",
+        );
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].row, 4);
+        assert_eq!(days[0].day.shifts, [ShiftRange::new("10:00", "18:00")]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(
+            report.warnings,
+            [RowIssue {
+                row: 4,
+                message: "replaces row 2 for Anna on 2024-03-04".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_missing_columns_reject_the_file() {
+        let mut report = ImportReport::default();
+        let error = read_import(b"employee,day_type\nAnna,off\n", &mut report).unwrap_err();
+        assert_eq!(error, "Missing columns: date");
+    }
+}
@@ -0,0 +1,28 @@
+use crate::components::work_schedule::EmployeeId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// One async lock per employee, so requests for the same employee run one at a time while
+/// requests for different employees don't wait on each other
+#[derive(Default)]
+pub struct EmployeeLocks {
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl EmployeeLocks {
+    /// Wait for the employee's lock. Every spelling of a name shares the lock of its canonical
+    /// slug.
+    pub async fn lock(&self, employee: &EmployeeId) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            // Forget locks nobody holds or waits for so the map doesn't grow with every name
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks
+                .entry(employee.slug().to_string())
+                .or_default()
+                .clone()
+        };
+        lock.lock_owned().await
+    }
+}
@@ -0,0 +1,2135 @@
+//! The work hours web app: its state, modules and router. Served by the `work_hours` binary
+//! and, for the combined mode, by the bot.
+
+pub mod archive;
+pub mod auth;
+pub mod credentials;
+pub mod db;
+pub mod feed;
+pub mod handlers;
+pub mod import;
+pub mod locks;
+pub mod model;
+pub mod parser;
+pub mod pending;
+pub mod preprocess;
+pub mod print;
+pub mod render;
+pub mod replay;
+pub mod schedule_api;
+pub mod spool;
+pub mod validation;
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::components::event_bus::{EventBus, ScheduleUpdated};
+use crate::components::work_schedule::stats::parse_tolerance;
+use crate::components::work_schedule::EmployeeId;
+use axum::{
+    body::Body,
+    extract::DefaultBodyLimit,
+    extract::State,
+    http::Request,
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
+    Router,
+};
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use tracing::info;
+
+use self::archive::archive_handler;
+use self::auth::AuthService;
+use self::feed::{today_feed_handler, week_feed_handler};
+use self::handlers::{
+    api_confirm_upload_handler, api_upload_handler, confirm_upload_form_handler,
+    confirm_upload_handler, create_magic_link_handler, dashboard_handler,
+    employee_schedule_handler, health_handler, index_handler, login_form_handler, login_handler,
+    me_handler, parse_failure_handler, parse_failures_handler, quality_handler, ready_handler,
+    revoke_magic_link_handler, suggest_employees_handler, upload_form_handler, upload_handler,
+    upload_image_handler,
+};
+use self::import::import_csv_handler;
+use self::locks::EmployeeLocks;
+use self::model::WorkHoursDb;
+use self::pending::PendingUploads;
+use self::preprocess::PreprocessPool;
+use self::print::print_week_handler;
+use self::replay::replay_handler;
+use self::schedule_api::{
+    actual_hours_handler, export_csv_handler, export_schedule_handler, import_schedule_handler,
+};
+use crate::utils::time::WeekStart;
+
+#[derive(Clone)]
+pub struct AppState {
+    /// Auth service for JWT operations
+    pub auth_service: Arc<AuthService>,
+    /// Database for work hours
+    pub db: Arc<dyn WorkHoursDb>,
+    /// Directory uploaded schedule images are kept in
+    pub upload_dir: PathBuf,
+    /// Static token for the dashboard feed, which is disabled when unset
+    pub feed_token: Option<String>,
+    /// Hours a week may differ from an employee's contract before the dashboard flags it
+    pub contract_tolerance_hours: f64,
+    /// First day of the week for the weekly feed and contract totals
+    pub week_start: WeekStart,
+    /// Locks serializing uploads for the same employee
+    pub upload_locks: Arc<EmployeeLocks>,
+    /// Uploads of an unexpected period waiting for the uploader's confirmation
+    pub pending_uploads: Arc<PendingUploads>,
+    /// When the app was started, for the uptime in health checks
+    pub started_at: Instant,
+    /// Largest width × height accepted for an uploaded image
+    pub max_image_pixels: u64,
+    /// Workers the CPU-heavy image work of uploads runs on
+    pub preprocess_pool: Arc<PreprocessPool>,
+    /// Whether the bot's shards are connected to the gateway, when the app runs in the bot's
+    /// process
+    pub gateway_connected: Option<Arc<AtomicBool>>,
+    /// The bot's event bus, when the app runs in the bot's process
+    pub bus: Option<EventBus>,
+}
+
+impl AppState {
+    /// State for the given store, configured from the environment
+    pub fn from_env(db: Arc<dyn WorkHoursDb>) -> Self {
+        let auth_config = auth::AuthConfig::default();
+        tracing::info!(
+            "Using admin credentials from environment: username={}",
+            auth_config.admin_username
+        );
+        Self {
+            auth_service: Arc::new(AuthService::new(auth_config)),
+            db,
+            upload_dir: std::env::var("SCHEDULE_UPLOAD_DIR")
+                .unwrap_or_else(|_| "uploads".to_string())
+                .into(),
+            feed_token: std::env::var("FEED_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            contract_tolerance_hours: parse_tolerance(
+                std::env::var("CONTRACT_HOURS_TOLERANCE").ok().as_deref(),
+            ),
+            week_start: std::env::var("WEEK_STARTS_ON")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            upload_locks: Arc::default(),
+            pending_uploads: Arc::default(),
+            started_at: Instant::now(),
+            max_image_pixels: std::env::var("MAX_IMAGE_PIXELS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(preprocess::DEFAULT_MAX_IMAGE_PIXELS),
+            preprocess_pool: Arc::new(PreprocessPool::from_env()),
+            gateway_connected: None,
+            bus: None,
+        }
+    }
+
+    /// Tell the bot's components that days of an employee's schedule were stored, so posted
+    /// notifications covering them are brought up to date
+    pub fn publish_update(&self, employee: &str, dates: Vec<String>) {
+        if let Some(bus) = &self.bus {
+            bus.publish(ScheduleUpdated(
+                EmployeeId::new(employee).display().to_string(),
+                dates,
+            ));
+        }
+    }
+}
+
+/// Routes employee-scoped magic link tokens are allowed to reach
+const EMPLOYEE_API_PREFIX: &str = "/api/v1/employees/";
+
+/// Routes the bot's service token is allowed to reach
+const SERVICE_API_PREFIX: &str = "/api/v1/uploads";
+
+/// Authentication middleware
+async fn auth_middleware(
+    req: Request<Body>,
+    next: Next,
+    auth_service: Arc<AuthService>,
+) -> Result<Response, Response> {
+    // Public routes are always allowed
+    let path = req.uri().path();
+    if path == "/"
+        || path == "/login"
+        || path.starts_with("/assets")
+        || path == "/health"
+        || path == "/ready"
+        || path.starts_with("/me/")
+        // The feed checks its own static token
+        || path.starts_with("/feed/")
+    {
+        return Ok(next.run(req).await);
+    }
+
+    // Extract parts to use with extract_token
+    let (parts, body) = req.into_parts();
+
+    // Use the extract_token function from auth module
+    match auth::extract_token(&parts) {
+        Ok(token) => {
+            // Validate the token
+            match auth_service.validate_token(&token) {
+                Ok(claims) => {
+                    // Magic link tokens may only read their own schedule through the API, and
+                    // the service token may only upload and fetch schedule images
+                    let allowed_prefix = if claims.is_service() {
+                        SERVICE_API_PREFIX
+                    } else {
+                        EMPLOYEE_API_PREFIX
+                    };
+                    if !claims.is_admin() && !parts.uri.path().starts_with(allowed_prefix) {
+                        return Err(StatusCode::FORBIDDEN.into_response());
+                    }
+
+                    // Create JwtAuth to pass along
+                    let auth = auth::JwtAuth { claims };
+
+                    // Reconstruct the request with auth data
+                    let mut req = Request::from_parts(parts, body);
+                    req.extensions_mut().insert(auth);
+
+                    // User is authenticated, proceed
+                    Ok(next.run(req).await)
+                }
+                Err(_) => {
+                    // Invalid token, redirect to login
+                    Err(Redirect::to("/login").into_response())
+                }
+            }
+        }
+        Err(_) => {
+            // No token found, redirect to login
+            Err(Redirect::to("/login").into_response())
+        }
+    }
+}
+
+/// Refuse requests that would change schedules while the bot is in maintenance mode. Reads
+/// pass, and so does everything when the mode can't be read.
+async fn maintenance_guard(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if req.method() == Method::GET {
+        return next.run(req).await;
+    }
+    match state.db.get_maintenance().await {
+        Ok(Some(maintenance)) => {
+            info!("Refused {} during maintenance", req.uri().path());
+            (StatusCode::SERVICE_UNAVAILABLE, maintenance.message()).into_response()
+        }
+        Ok(None) => next.run(req).await,
+        Err(e) => {
+            tracing::warn!("Failed to read the maintenance state: {}", e);
+            next.run(req).await
+        }
+    }
+}
+
+/// Build the application router
+pub fn build_router(state: AppState) -> Router {
+    // Create middleware with auth service
+    let auth_service = state.auth_service.clone();
+    let auth_middleware =
+        move |req: Request<Body>, next: Next| auth_middleware(req, next, auth_service.clone());
+
+    // Routes that change schedules, closed while the bot is in maintenance mode
+    let schedule_writes = Router::new()
+        .route("/upload", get(upload_form_handler).post(upload_handler))
+        .route(
+            "/upload/confirm/{id}",
+            get(confirm_upload_form_handler).post(confirm_upload_handler),
+        )
+        .route("/api/v1/uploads", post(api_upload_handler))
+        .route(
+            "/api/v1/uploads/{id}/confirm",
+            post(api_confirm_upload_handler),
+        )
+        .route("/api/v1/import.csv", post(import_csv_handler))
+        .route(
+            "/api/v1/schedule/{employee}/import",
+            post(import_schedule_handler),
+        )
+        .route(
+            "/api/v1/actuals/{employee}/{date}",
+            put(actual_hours_handler),
+        )
+        .route("/api/v1/replay", post(replay_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_guard,
+        ));
+
+    Router::new()
+        .route("/", get(index_handler))
+        .route("/login", get(login_form_handler).post(login_handler))
+        .route("/health", get(health_handler))
+        .route("/ready", get(ready_handler))
+        .merge(schedule_writes)
+        .route("/dashboard", get(dashboard_handler))
+        .route("/print/week", get(print_week_handler))
+        .route("/me/{token}", get(me_handler))
+        .route("/api/v1/employees/suggest", get(suggest_employees_handler))
+        .route(
+            "/api/v1/employees/{name}/schedule",
+            get(employee_schedule_handler),
+        )
+        .route("/api/v1/uploads/{file_name}", get(upload_image_handler))
+        .route(
+            "/api/v1/schedule/{employee}/export",
+            get(export_schedule_handler),
+        )
+        .route("/api/v1/export.csv", get(export_csv_handler))
+        .route("/api/v1/archive/{file_name}", get(archive_handler))
+        .route("/api/v1/quality", get(quality_handler))
+        .route("/api/v1/parse-failures", get(parse_failures_handler))
+        .route("/api/v1/parse-failures/{id}", get(parse_failure_handler))
+        .route("/feed/week.json", get(week_feed_handler))
+        .route("/feed/today.json", get(today_feed_handler))
+        .route(
+            "/api/v1/employees/{name}/magic-link",
+            post(create_magic_link_handler).delete(revoke_magic_link_handler),
+        )
+        // Apply auth middleware
+        .layer(axum::middleware::from_fn(auth_middleware))
+        // Serve static files
+        .nest_service("/assets", ServeDir::new("assets"))
+        // Other middlewares
+        .layer(DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB limit
+        .layer(TraceLayer::new_for_http())
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+/// Address the app listens on, all interfaces on `PORT` or 3000
+pub fn listen_addr() -> SocketAddr {
+    let port = std::env::var("PORT")
+        .ok()
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(3000);
+    SocketAddr::from(([0, 0, 0, 0], port))
+}
+
+/// Serve the app on `listener` until `shutdown` completes, letting requests in flight finish
+pub async fn serve(
+    listener: TcpListener,
+    state: AppState,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let credential_checks = credentials::spawn_credential_checks(state.db.clone());
+    let result = axum::serve(listener, build_router(state))
+        .with_graceful_shutdown(shutdown)
+        .await;
+    credential_checks.abort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::redis_service::RedisActorHandle;
+    use crate::components::work_schedule::audit::AuditRecord;
+    use crate::components::work_schedule::credentials::CredentialStatus;
+    use crate::components::work_schedule::models::{ContextLink, ShiftRange};
+    use crate::components::work_schedule::parse_failures::{ModelExchange, ParseFailure};
+    use crate::components::work_schedule::quality::ParseRecord;
+    use crate::components::work_schedule::stats::{ContractHours, DEFAULT_TOLERANCE_HOURS};
+    use crate::components::work_schedule::uploads::{
+        PeriodIssue, StoredUpload, UploadResponse, UPLOAD_ERROR_CODES,
+    };
+    use crate::components::work_schedule::weekly_edits::{
+        notified_messages, touched_weeks, weekly_messages_key,
+    };
+    use crate::maintenance::Maintenance;
+    use crate::utils::notifier::remember_message;
+    use crate::utils::time::WeekStart;
+    use crate::web::auth::AuthService;
+    use crate::web::handlers::UploadOutcome;
+    use crate::web::model::{
+        InMemoryDb, StoredExtraction, WorkDay, WorkDayExtraction, WorkSchedule,
+    };
+    use crate::web::parser::{convert_to_work_schedule, read_model_response, Provider};
+    use crate::web::preprocess::PreprocessPool;
+    use crate::web::preprocess::{png_header, ImageFormat};
+    use crate::web::render::html_escape;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use chrono::Local;
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    const FEED_TOKEN: &str = "feed_secret";
+    const SERVICE_TOKEN: &str = "service_secret";
+
+    async fn test_state() -> AppState {
+        let db = Arc::new(InMemoryDb::default());
+        for employee in ["Anna", "Pekka"] {
+            db.set_schedule(employee, &WorkSchedule::new(employee.to_string()))
+                .await
+                .unwrap();
+        }
+
+        AppState {
+            auth_service: Arc::new(AuthService::new(auth::AuthConfig {
+                jwt_secret: "test_secret".to_string(),
+                token_expiration_minutes: 60,
+                admin_username: "admin".to_string(),
+                admin_password: "password".to_string(),
+                service_token: Some(SERVICE_TOKEN.to_string()),
+            })),
+            db,
+            upload_dir: std::env::temp_dir().join("work_hours_test_uploads"),
+            feed_token: Some(FEED_TOKEN.to_string()),
+            contract_tolerance_hours: DEFAULT_TOLERANCE_HOURS,
+            week_start: WeekStart::Monday,
+            upload_locks: Arc::default(),
+            pending_uploads: Arc::default(),
+            started_at: Instant::now(),
+            max_image_pixels: crate::web::preprocess::DEFAULT_MAX_IMAGE_PIXELS,
+            preprocess_pool: Arc::new(PreprocessPool::new(2)),
+            gateway_connected: None,
+            bus: None,
+        }
+    }
+
+    async fn get_status(state: &AppState, uri: &str, token: &str) -> StatusCode {
+        let request = Request::builder()
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap();
+
+        build_router(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    fn admin_token(state: &AppState) -> String {
+        state
+            .auth_service
+            .generate_token("admin", None, "admin")
+            .unwrap()
+    }
+
+    async fn get_body(state: &AppState, uri: &str) -> String {
+        let request = Request::builder()
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", admin_token(state)))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn get_feed(state: &AppState, uri: &str, headers: &[(&str, &str)]) -> Response {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        build_router(state.clone())
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Upload a multipart form and return the redirect location
+    async fn upload(state: &AppState, name: &str, file: &[u8]) -> String {
+        let boundary = "test-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\n{name}\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"schedule_file\"; \
+             filename=\"schedule.png\"\r\nContent-Type: image/png\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(file);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header("Authorization", format!("Bearer {}", admin_token(state)))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        response.headers()["location"].to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_is_scoped_to_employee() {
+        let state = test_state().await;
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/Anna/schedule", &token).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/Pekka/schedule", &token).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            get_status(&state, "/dashboard", &token).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stored_images_are_served_to_admins_and_the_bot() {
+        let mut state = test_state().await;
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_uploads_{}", std::process::id()));
+        std::fs::create_dir_all(&state.upload_dir).unwrap();
+        std::fs::write(state.upload_dir.join("anna-1.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        std::fs::write(state.upload_dir.join("unrecorded.png"), b"secret").unwrap();
+        state
+            .db
+            .record_upload(&StoredUpload {
+                employee: "Anna".to_string(),
+                file_name: "anna-1.png".to_string(),
+                start_date: "2025-01-06".to_string(),
+                end_date: "2025-01-19".to_string(),
+                uploaded_at: 1,
+                image_hash: None,
+            })
+            .await
+            .unwrap();
+
+        let admin = admin_token(&state);
+        let request = Request::builder()
+            .uri("/api/v1/uploads/anna-1.png")
+            .header("Authorization", format!("Bearer {admin}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert_eq!(
+            get_status(&state, "/api/v1/uploads/unrecorded.png", &admin).await,
+            StatusCode::NOT_FOUND
+        );
+
+        // The bot's service token reaches the upload API and nothing else
+        assert_eq!(
+            get_status(&state, "/api/v1/uploads/anna-1.png", SERVICE_TOKEN).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get_status(&state, "/dashboard", SERVICE_TOKEN).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/Anna/schedule", SERVICE_TOKEN).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let magic_link = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/api/v1/uploads/anna-1.png", &magic_link).await,
+            StatusCode::FORBIDDEN
+        );
+
+        std::fs::remove_dir_all(&state.upload_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_monthly_archive_is_for_admins_only() {
+        let state = test_state().await;
+        let admin = admin_token(&state);
+        assert_eq!(
+            get_status(&state, "/api/v1/archive/2025-03.zip", &admin).await,
+            StatusCode::OK
+        );
+        for uri in ["/api/v1/archive/2025-13.zip", "/api/v1/archive/2025-03"] {
+            assert_eq!(get_status(&state, uri, &admin).await, StatusCode::NOT_FOUND);
+        }
+
+        let magic_link = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/api/v1/archive/2025-03.zip", &magic_link).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoked_magic_link_is_rejected() {
+        let state = test_state().await;
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+
+        state.db.bump_token_version("Anna").await.unwrap();
+
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/Anna/schedule", &token).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            get_status(&state, &format!("/me/{token}"), "").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_validation_errors_redirect_with_code() {
+        let state = test_state().await;
+
+        assert_eq!(
+            upload(&state, "Anna Mäkinen", b"").await,
+            "/upload?error=empty_file&name=Anna+M%C3%A4kinen"
+        );
+        assert_eq!(
+            upload(&state, "Anna", b"definitely not an image").await,
+            "/upload?error=bad_format&name=Anna"
+        );
+        assert_eq!(
+            upload(&state, "Anna <script>", b"\x89PNG\r\n\x1a\n").await,
+            "/upload?error=name_invalid&name=Anna+%3Cscript%3E"
+        );
+    }
+
+    /// Header of a 20000×20000 PNG, with no pixel data
+    fn huge_png() -> &'static [u8] {
+        static PNG: std::sync::LazyLock<Vec<u8>> =
+            std::sync::LazyLock::new(|| png_header(20_000, 20_000));
+        &PNG
+    }
+
+    #[tokio::test]
+    async fn test_oversized_uploads_are_rejected_before_reading() {
+        let mut state = test_state().await;
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_oversized_{}", std::process::id()));
+
+        assert_eq!(
+            upload(&state, "Anna", huge_png()).await,
+            "/upload?error=too_many_pixels&name=Anna"
+        );
+        // The body limit cuts the request off before even the name is read
+        let body = vec![0u8; 10 * 1024 * 1024 + 1];
+        assert_eq!(
+            upload(&state, "Anna", &body).await,
+            "/upload?error=too_large&name="
+        );
+
+        // A lower limit turns away smaller images too
+        state.max_image_pixels = 1_000;
+        let small = png_header(40, 30);
+        assert_eq!(
+            upload(&state, "Anna", &small).await,
+            "/upload?error=too_many_pixels&name=Anna"
+        );
+
+        // The spooled files are gone once the upload has been answered
+        let left: Vec<_> = std::fs::read_dir(&state.upload_dir)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(left.is_empty(), "left behind: {left:?}");
+        std::fs::remove_dir_all(&state.upload_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_api_upload_answers_with_json() {
+        let state = test_state().await;
+        let post = |uri: &str, token: String, body: &'static [u8]| {
+            let request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::from(body))
+                .unwrap();
+            build_router(state.clone()).oneshot(request)
+        };
+        let rejected = |code: &str| UploadResponse::Rejected {
+            code: code.to_string(),
+            detail: None,
+        };
+        let json = |response: Response| async {
+            let body = http_body_util::BodyExt::collect(response.into_body())
+                .await
+                .unwrap()
+                .to_bytes();
+            serde_json::from_slice::<UploadResponse>(&body).unwrap()
+        };
+
+        let response = post(
+            "/api/v1/uploads?employee=Anna",
+            admin_token(&state),
+            b"not an image",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await, rejected("bad_format"));
+
+        let response = post("/api/v1/uploads", admin_token(&state), b"\x89PNG\r\n\x1a\n")
+            .await
+            .unwrap();
+        assert_eq!(json(response).await, rejected("name_invalid"));
+
+        let response = post(
+            "/api/v1/uploads?employee=Anna",
+            admin_token(&state),
+            huge_png(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(json(response).await, rejected("too_many_pixels"));
+
+        // Only the bot's admin token may upload this way
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        let response = post("/api/v1/uploads?employee=Anna", token, b"\x89PNG\r\n\x1a\n")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    /// A date this many days from today, so parsed schedules cover the coming weeks
+    fn coming_date(days: i64) -> String {
+        (Local::now().date_naive() + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_uploads_parse_once() {
+        let mut state = test_state().await;
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_concurrent_{}", std::process::id()));
+        let parses = std::sync::atomic::AtomicUsize::new(0);
+        let parse = || async {
+            parses.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            // Give the other upload a chance to run while this one is parsing
+            tokio::task::yield_now().await;
+            let mut schedule = WorkSchedule::new("Anna Mäkinen".to_string());
+            schedule.days.push(WorkDay {
+                date: coming_date(7),
+                shifts: vec![ShiftRange::new("08:00", "16:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+            Ok(schedule)
+        };
+        let image = b"\x89PNG\r\n\x1a\nsame image";
+
+        let (first, second) = tokio::join!(
+            handlers::process_upload(
+                &state,
+                "Anna Mäkinen",
+                image,
+                ImageFormat::Png,
+                Provider::Gemini,
+                parse
+            ),
+            handlers::process_upload(
+                &state,
+                "anna  makinen",
+                image,
+                ImageFormat::Png,
+                Provider::Gemini,
+                parse
+            ),
+        );
+        let mut outcomes = [first, second];
+        outcomes.sort_by_key(|outcome| format!("{outcome:?}"));
+        assert!(matches!(
+            outcomes,
+            [UploadOutcome::AlreadyStored, UploadOutcome::Stored(_)]
+        ));
+        assert_eq!(parses.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // A different image is parsed again
+        let outcome = handlers::process_upload(
+            &state,
+            "Anna Mäkinen",
+            b"\x89PNG\r\n\x1a\nnew image",
+            ImageFormat::Png,
+            Provider::Gemini,
+            parse,
+        )
+        .await;
+        assert!(matches!(outcome, UploadOutcome::Stored(_)));
+        assert_eq!(parses.load(std::sync::atomic::Ordering::SeqCst), 2);
+        std::fs::remove_dir_all(&state.upload_dir).ok();
+    }
+
+    /// In the bot's process, an upload reaches the updater of the posted weekly notifications,
+    /// so the one already posted for its week gets edited
+    #[tokio::test]
+    async fn test_upload_queues_an_edit_of_a_notified_week() {
+        let redis_handle = RedisActorHandle::fake();
+        let bus = EventBus::default();
+        let mut updates = bus.subscribe::<ScheduleUpdated>();
+        let mut state = test_state().await;
+        state.bus = Some(bus);
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_notified_{}", std::process::id()));
+
+        let date = coming_date(7);
+        let week = touched_weeks(std::slice::from_ref(&date), WeekStart::Monday);
+        let week_start = week.first().unwrap().format("%Y-%m-%d").to_string();
+        let key = weekly_messages_key(&week_start).unwrap();
+        remember_message(&redis_handle, &key, 5, 11).await.unwrap();
+
+        let parse = || async {
+            let mut schedule = WorkSchedule::new("Anna".to_string());
+            schedule.days.push(WorkDay {
+                date: date.clone(),
+                shifts: vec![ShiftRange::new("08:00", "16:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+            Ok(schedule)
+        };
+        let outcome = handlers::process_upload(
+            &state,
+            "Anna",
+            b"\x89PNG\r\n\x1a\nschedule",
+            ImageFormat::Png,
+            Provider::Gemini,
+            parse,
+        )
+        .await;
+        assert!(matches!(outcome, UploadOutcome::Stored(_)));
+
+        let ScheduleUpdated(employee, dates) = updates.try_recv().unwrap();
+        assert_eq!(employee, "Anna");
+        assert_eq!(dates, [date]);
+        let messages = notified_messages(&redis_handle, &touched_weeks(&dates, WeekStart::Monday))
+            .await
+            .unwrap();
+        let ids: Vec<(u64, u64)> = messages
+            .iter()
+            .map(|message| (message.channel_id, message.message_id))
+            .collect();
+        assert_eq!(ids, [(5, 11)]);
+        std::fs::remove_dir_all(&state.upload_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_uploads_of_past_periods_are_held_until_confirmed() {
+        let mut state = test_state().await;
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_confirm_{}", std::process::id()));
+        let parse = || async {
+            let mut schedule = WorkSchedule::new("Anna".to_string());
+            for days in -14..0 {
+                schedule.days.push(WorkDay {
+                    date: coming_date(days),
+                    shifts: vec![ShiftRange::new("08:00", "16:00")],
+                    is_day_off: false,
+                    notes: None,
+                    break_minutes: None,
+                    context_link: None,
+                    actual_start: None,
+                    actual_end: None,
+                });
+            }
+            Ok(schedule)
+        };
+        let held = |outcome| match outcome {
+            UploadOutcome::SuspiciousPeriod {
+                pending_id,
+                issue,
+                summary,
+            } => {
+                assert_eq!(issue, PeriodIssue::MostlyPast);
+                assert_eq!(summary.start_date, coming_date(-14));
+                assert_eq!(summary.end_date, coming_date(-1));
+                pending_id
+            }
+            other => panic!("upload wasn't held: {other:?}"),
+        };
+        let upload = |image: &'static [u8]| {
+            handlers::process_upload(
+                &state,
+                "Anna",
+                image,
+                ImageFormat::Png,
+                Provider::Gemini,
+                parse,
+            )
+        };
+        let stored_days = || async {
+            let schedule = state.db.get_schedule("Anna").await.unwrap().unwrap();
+            schedule.days.len()
+        };
+
+        // The web form shows what was detected before anything is stored
+        let pending_id = held(upload(b"\x89PNG\r\n\x1a\nold schedule").await);
+        assert_eq!(stored_days().await, 0);
+        let html = get_body(&state, &format!("/upload/confirm/{pending_id}")).await;
+        assert!(html.contains(&format!("{} – {}", coming_date(-14), coming_date(-1))));
+        assert!(html.contains(&html_escape(&PeriodIssue::MostlyPast.message())));
+
+        let confirm = |uri: String| {
+            let request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", admin_token(&state)))
+                .body(Body::empty())
+                .unwrap();
+            build_router(state.clone()).oneshot(request)
+        };
+        let response = confirm(format!("/upload/confirm/{pending_id}"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["location"], "/dashboard");
+        assert_eq!(stored_days().await, 14);
+
+        // Each held upload is stored once
+        let response = confirm(format!("/api/v1/uploads/{pending_id}/confirm"))
+            .await
+            .unwrap();
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            serde_json::from_slice::<UploadResponse>(&body).unwrap(),
+            UploadResponse::Rejected {
+                code: "upload_expired".to_string(),
+                detail: None,
+            }
+        );
+
+        // The bot confirms through the API
+        let pending_id = held(upload(b"\x89PNG\r\n\x1a\nanother old schedule").await);
+        let response = confirm(format!("/api/v1/uploads/{pending_id}/confirm"))
+            .await
+            .unwrap();
+        let body = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert!(matches!(
+            serde_json::from_slice::<UploadResponse>(&body).unwrap(),
+            UploadResponse::Stored(summary) if summary.days == 14
+        ));
+        assert_eq!(state.db.list_uploads().await.unwrap().len(), 2);
+        std::fs::remove_dir_all(&state.upload_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_uploads_feed_the_quality_trend() {
+        let mut state = test_state().await;
+        state.upload_dir =
+            std::env::temp_dir().join(format!("work_hours_quality_{}", std::process::id()));
+        let parse = || async {
+            let mut schedule = WorkSchedule::new("Anna".to_string());
+            for (days, notes) in [(7, None), (8, Some("koulutus"))] {
+                schedule.days.push(WorkDay {
+                    date: coming_date(days),
+                    shifts: Vec::new(),
+                    is_day_off: false,
+                    notes: notes.map(str::to_string),
+                    break_minutes: None,
+                    context_link: None,
+                    actual_start: None,
+                    actual_end: None,
+                });
+            }
+            schedule.extraction = Some(vec![WorkDayExtraction {
+                date: coming_date(8),
+                work_hours: "koulutus".to_string(),
+            }]);
+            Ok(schedule)
+        };
+        let outcome = handlers::process_upload(
+            &state,
+            "Anna",
+            b"\x89PNG\r\n\x1a\nquality",
+            ImageFormat::Png,
+            Provider::OpenAi,
+            parse,
+        )
+        .await;
+        assert!(matches!(outcome, UploadOutcome::Stored(_)));
+
+        // The stored schedule and image share the upload id of the record
+        let records = state.db.list_parse_records().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].provider, "openai");
+        let stored = state.db.get_schedule("Anna").await.unwrap().unwrap();
+        assert_eq!(stored.upload_id.as_ref(), Some(&records[0].upload_id));
+        let uploads = state.db.list_uploads().await.unwrap();
+        assert_eq!(
+            uploads[0].file_name,
+            format!("{}.png", records[0].upload_id)
+        );
+        // The model's days are kept for replaying
+        let extractions = state.db.list_extractions().await.unwrap();
+        assert_eq!(extractions[0].upload_id, records[0].upload_id);
+        assert_eq!(extractions[0].days[0].work_hours, "koulutus");
+
+        let body = get_body(&state, "/api/v1/quality?weeks=2").await;
+        let weeks: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(weeks.as_array().unwrap().len(), 2);
+        assert_eq!(weeks[0]["uploads"], 0);
+        assert_eq!(weeks[1]["uploads"], 1);
+        assert_eq!(weeks[1]["empty_cells"], 1);
+        assert_eq!(weeks[1]["low_confidence"], 1);
+
+        let admin = admin_token(&state);
+        for uri in ["/api/v1/quality?weeks=0", "/api/v1/quality?weeks=many"] {
+            assert_eq!(
+                get_status(&state, uri, &admin).await,
+                StatusCode::BAD_REQUEST
+            );
+        }
+        let magic_link = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/api/v1/quality", &magic_link).await,
+            StatusCode::FORBIDDEN
+        );
+        std::fs::remove_dir_all(&state.upload_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_failed_extraction_keeps_the_model_response() {
+        let state = test_state().await;
+        let exchange = ModelExchange {
+            provider: "gemini".to_string(),
+            model: "gemini-2.5-pro".to_string(),
+            employee: "Anna".to_string(),
+            year: 2025,
+            markdown: "| Nimi | 6.1. |\n|---|---|\n| Anna | 9-17 |".to_string(),
+            response: "Sorry, the image is too blurry to read.".to_string(),
+        };
+        let parse = || async {
+            let (days, _) = read_model_response(exchange.clone())?;
+            Ok(convert_to_work_schedule("Anna", days)?)
+        };
+        let outcome = handlers::process_upload(
+            &state,
+            "Anna",
+            b"\x89PNG\r\n\x1a\nblurry",
+            ImageFormat::Png,
+            Provider::Gemini,
+            parse,
+        )
+        .await;
+        assert!(matches!(outcome, UploadOutcome::ParseFailed(_)));
+
+        let body = get_body(&state, "/api/v1/parse-failures").await;
+        let listed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["stage"], "extraction");
+        assert_eq!(listed[0]["model"], "gemini-2.5-pro");
+        assert!(listed[0].get("exchange").is_none());
+
+        // The full payload is there for retrying offline
+        let id = listed[0]["id"].as_str().unwrap();
+        let body = get_body(&state, &format!("/api/v1/parse-failures/{id}")).await;
+        let failure: ParseFailure = serde_json::from_str(&body).unwrap();
+        assert_eq!(failure.exchange, exchange);
+        assert_eq!(failure.error, listed[0]["error"]);
+        assert!(!failure.image_hash.is_empty());
+
+        let admin = admin_token(&state);
+        assert_eq!(
+            get_status(&state, "/api/v1/parse-failures/anna-0", &admin).await,
+            StatusCode::NOT_FOUND
+        );
+        let magic_link = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/api/v1/parse-failures", &magic_link).await,
+            StatusCode::FORBIDDEN
+        );
+
+        // A parser that couldn't be reached leaves no response to keep
+        let outcome = handlers::process_upload(
+            &state,
+            "Anna",
+            b"\x89PNG\r\n\x1a\nunreachable",
+            ImageFormat::Png,
+            Provider::Gemini,
+            || async { Err("Failed to send request to LlamaIndex".to_string().into()) },
+        )
+        .await;
+        assert!(matches!(outcome, UploadOutcome::ParseFailed(_)));
+        assert_eq!(state.db.list_parse_failures().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upload_error_codes_render_messages() {
+        let state = test_state().await;
+
+        for code in UPLOAD_ERROR_CODES {
+            let location = handlers::upload_error_redirect(code, "Anna Mäkinen", Some("Bad <row>"))
+                .into_response()
+                .headers()["location"]
+                .to_str()
+                .unwrap()
+                .to_string();
+
+            let html = get_body(&state, &location).await;
+            let message = t!(format!("upload_error_{code}")).to_string();
+            assert!(html.contains(&message), "missing message for {code}");
+            assert!(html.contains("value=\"Anna Mäkinen\""));
+            assert!(html.contains("Bad &lt;row&gt;"));
+        }
+
+        // Unknown codes are never rendered
+        let html = get_body(&state, "/upload?error=%3Cb%3Ehacked%3C%2Fb%3E").await;
+        assert!(!html.contains("hacked"));
+    }
+
+    /// Database that can't be reached
+    struct FailingDb;
+
+    #[async_trait::async_trait]
+    impl WorkHoursDb for FailingDb {
+        async fn get_schedule(&self, _: &str) -> Result<Option<WorkSchedule>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn set_schedule(&self, _: &str, _: &WorkSchedule) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_employees(&self) -> Result<Vec<String>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn delete_schedule(&self, _: &str) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn get_token_version(&self, _: &str) -> Result<u64, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn bump_token_version(&self, _: &str) -> Result<u64, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn record_upload(&self, _: &StoredUpload) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_uploads(&self) -> Result<Vec<StoredUpload>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn record_parse(&self, _: &ParseRecord) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_parse_records(&self) -> Result<Vec<ParseRecord>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn record_parse_failure(&self, _: &ParseFailure) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn remove_days(&self, _: &str, _: &[String]) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn record_audit(&self, _: Vec<AuditRecord>) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_audit(&self) -> Result<Vec<AuditRecord>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn get_maintenance(&self) -> Result<Option<Maintenance>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_credential_statuses(&self) -> Result<Vec<CredentialStatus>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn set_credential_status(&self, _: &CredentialStatus) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_parse_failures(&self) -> Result<Vec<ParseFailure>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn get_parse_failure(&self, _: &str) -> Result<Option<ParseFailure>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn record_extraction(&self, _: &StoredExtraction) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_extractions(&self) -> Result<Vec<StoredExtraction>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn list_contract_hours(&self) -> Result<Vec<ContractHours>, String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+
+        async fn ping(&self) -> Result<(), String> {
+            Err("Failed to connect to Redis".to_string())
+        }
+    }
+
+    async fn get_health(state: &AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = get_feed(state, uri, &[]).await;
+        let status = response.status();
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_redis_and_build_info() {
+        let state = test_state().await;
+
+        let (status, body) = get_health(&state, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["redis"], "ok");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["git_sha"].is_string());
+        assert!(body["uptime_seconds"].is_u64());
+        assert!(body.get("missing_env").is_none());
+        assert!(body.get("gateway").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_the_bots_gateway() {
+        let connected = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut state = test_state().await;
+        state.gateway_connected = Some(connected.clone());
+
+        let (status, body) = get_health(&state, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["gateway"], "ok");
+
+        connected.store(false, std::sync::atomic::Ordering::Relaxed);
+        let (status, body) = get_health(&state, "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["gateway"], "degraded");
+        assert_eq!(body["redis"], "ok");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_health_answers_while_images_are_preprocessed() {
+        let state = test_state().await;
+        let jobs: Vec<_> = (0..2)
+            .map(|_| {
+                let pool = state.preprocess_pool.clone();
+                tokio::spawn(async move {
+                    pool.run(|| std::thread::sleep(Duration::from_millis(500)))
+                        .await
+                })
+            })
+            .collect();
+        // Let both jobs start
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let (status, _) =
+            tokio::time::timeout(Duration::from_millis(200), get_health(&state, "/health"))
+                .await
+                .expect("health check waited for preprocessing");
+        assert_eq!(status, StatusCode::OK);
+        for job in jobs {
+            job.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_is_degraded_without_redis() {
+        let mut state = test_state().await;
+        state.db = Arc::new(FailingDb);
+
+        for uri in ["/health", "/ready"] {
+            let (status, body) = get_health(&state, uri).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+            assert_eq!(body["status"], "degraded");
+            assert_eq!(body["redis"], "degraded");
+            assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        }
+    }
+
+    #[test]
+    fn test_missing_parser_env_vars() {
+        let vars = crate::web::parser::Provider::Gemini.required_env_vars();
+        assert_eq!(
+            handlers::missing_env_vars(&vars, |var| var == "LLAMA_API_KEY"),
+            ["GEMINI_API_KEY"]
+        );
+        assert!(handlers::missing_env_vars(&vars, |_| true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_feed_requires_feed_token() {
+        let mut state = test_state().await;
+        let admin = format!("Bearer {}", admin_token(&state));
+
+        for headers in [
+            vec![],
+            vec![("Authorization", "Bearer wrong")],
+            // A JWT doesn't open the feed either
+            vec![("Authorization", admin.as_str())],
+        ] {
+            let response = get_feed(&state, "/feed/week.json", &headers).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let feed_auth = format!("Bearer {FEED_TOKEN}");
+        for uri in ["/feed/week.json", "/feed/today.json"] {
+            let response = get_feed(&state, uri, &[("Authorization", &feed_auth)]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["cache-control"], "max-age=60");
+        }
+
+        state.feed_token = None;
+        let response = get_feed(&state, "/feed/week.json", &[("Authorization", &feed_auth)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_feed_etag_answers_not_modified() {
+        let state = test_state().await;
+        let auth = format!("Bearer {FEED_TOKEN}");
+
+        let response = get_feed(&state, "/feed/week.json", &[("Authorization", &auth)]).await;
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+        let response = get_feed(
+            &state,
+            "/feed/week.json",
+            &[("Authorization", &auth), ("If-None-Match", &etag)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+
+        // Changed data gets a new tag
+        let mut schedule = WorkSchedule::new("Anna".to_string());
+        schedule.add_day(WorkDay {
+            date: Local::now().format("%Y-%m-%d").to_string(),
+            shifts: vec![ShiftRange::new("08:00", "16:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        state.db.set_schedule("Anna", &schedule).await.unwrap();
+
+        let response = get_feed(
+            &state,
+            "/feed/week.json",
+            &[("Authorization", &auth), ("If-None-Match", &etag)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_feed_json_shape() {
+        let state = test_state().await;
+        let (monday, sunday) =
+            crate::utils::time::get_weekly_date_range(&Local::now(), state.week_start);
+        let monday = chrono::NaiveDate::parse_from_str(&monday, "%Y-%m-%d").unwrap();
+        let date = |offset: i64| {
+            (monday + chrono::Duration::days(offset))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+
+        let mut anna = WorkSchedule::new("Anna".to_string());
+        anna.add_day(WorkDay {
+            date: date(0),
+            shifts: vec![
+                ShiftRange::new("8:00", "12:00"),
+                ShiftRange::new("16:00", "20:30"),
+            ],
+            is_day_off: false,
+            notes: Some("Kassa".to_string()),
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        anna.add_day(WorkDay {
+            date: date(1),
+            shifts: Vec::new(),
+            is_day_off: true,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        state.db.set_schedule("Anna", &anna).await.unwrap();
+
+        let mut pekka = WorkSchedule::new("Pekka".to_string());
+        pekka.add_day(WorkDay {
+            date: date(9),
+            shifts: vec![ShiftRange::new("10:00", "18:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        state.db.set_schedule("Pekka", &pekka).await.unwrap();
+
+        let response = get_feed(
+            &state,
+            "/feed/week.json",
+            &[("Authorization", &format!("Bearer {FEED_TOKEN}"))],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        let mut feed: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let generated_at = feed["generated_at"].take();
+        assert!(chrono::DateTime::parse_from_rfc3339(generated_at.as_str().unwrap()).is_ok());
+
+        let unscheduled = |offset| {
+            serde_json::json!({
+                "date": date(offset),
+                "day_type": "unscheduled",
+                "shifts": [],
+                "notes": null
+            })
+        };
+        assert_eq!(
+            feed,
+            serde_json::json!({
+                "version": 1,
+                "generated_at": null,
+                "start_date": date(0),
+                "end_date": sunday,
+                "coverage_through": date(1),
+                "employees": [
+                    {
+                        "name": "Anna",
+                        "days": [
+                            {
+                                "date": date(0),
+                                "day_type": "work",
+                                "shifts": [
+                                    {"start": "08:00", "end": "12:00"},
+                                    {"start": "16:00", "end": "20:30"}
+                                ],
+                                "notes": "Kassa"
+                            },
+                            {
+                                "date": date(1),
+                                "day_type": "off",
+                                "shifts": [],
+                                "notes": null
+                            },
+                            unscheduled(2),
+                            unscheduled(3),
+                            unscheduled(4),
+                            unscheduled(5),
+                            unscheduled(6)
+                        ]
+                    },
+                    {
+                        "name": "Pekka",
+                        "days": (0..7).map(unscheduled).collect::<Vec<_>>()
+                    }
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_shows_hours_against_contract() {
+        let db = Arc::new(InMemoryDb::default());
+        db.add_contract_hours(ContractHours {
+            employee: "Anna".to_string(),
+            hours_per_week: 20.0,
+        })
+        .await;
+
+        // Monday to Wednesday of one week, 8 hours a day
+        let mut anna = WorkSchedule::new("Anna".to_string());
+        for date in ["2025-01-06", "2025-01-07", "2025-01-08"] {
+            anna.add_day(WorkDay {
+                date: date.to_string(),
+                shifts: vec![ShiftRange::new("8:00", "16:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+        }
+        db.set_schedule("Anna", &anna).await.unwrap();
+        db.set_schedule("Pekka", &WorkSchedule::new("Pekka".to_string()))
+            .await
+            .unwrap();
+
+        let state = AppState {
+            db,
+            ..test_state().await
+        };
+        let body = get_body(&state, "/dashboard").await;
+        // Three fifths of the contract is 12 hours, so 24 hours is well over
+        assert!(body.contains("Σ 24 h / 12 h · 🔴 +12 h over"));
+        assert_eq!(body.matches("Σ ").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_links_context_messages() {
+        let db = Arc::new(InMemoryDb::default());
+        let mut anna = WorkSchedule::new("Anna".to_string());
+        anna.add_day(WorkDay {
+            date: "2025-01-06".to_string(),
+            shifts: vec![ShiftRange::new("8:00", "16:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: Some(ContextLink {
+                url: "https://discord.com/channels/1/2/3".to_string(),
+                excerpt: "Vaihto <Matti> kanssa".to_string(),
+            }),
+            actual_start: None,
+            actual_end: None,
+        });
+        anna.add_day(WorkDay {
+            date: "2025-01-07".to_string(),
+            shifts: vec![ShiftRange::new("8:00", "16:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: Some(ContextLink {
+                url: "javascript:alert(1)".to_string(),
+                excerpt: String::new(),
+            }),
+            actual_start: None,
+            actual_end: None,
+        });
+        db.set_schedule("Anna", &anna).await.unwrap();
+
+        let state = AppState {
+            db,
+            ..test_state().await
+        };
+        let body = get_body(&state, "/dashboard").await;
+        assert!(body.contains(
+            "<a href=\"https://discord.com/channels/1/2/3\" title=\"Vaihto &lt;Matti&gt; kanssa\""
+        ));
+        assert_eq!(body.matches("📎 context").count(), 1);
+        assert!(!body.contains("javascript:"));
+    }
+
+    #[tokio::test]
+    async fn test_employee_suggestions_fold_case_and_diacritics() {
+        let state = test_state().await;
+        for employee in ["Anna Mäkinen", "Hanna"] {
+            state
+                .db
+                .set_schedule(employee, &WorkSchedule::new(employee.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let suggest = |query: &str| {
+            let state = state.clone();
+            let uri = format!("/api/v1/employees/suggest?q={query}");
+            async move { get_body(&state, &uri).await }
+        };
+        assert_eq!(suggest("ANNA").await, r#"["Anna","Anna Mäkinen","Hanna"]"#);
+        assert_eq!(suggest("mak").await, r#"["Anna Mäkinen"]"#);
+        assert_eq!(suggest("M%C3%A4K").await, r#"["Anna Mäkinen"]"#);
+        assert_eq!(suggest("anna+m").await, r#"["Anna Mäkinen"]"#);
+
+        let form = get_body(&state, "/upload").await;
+        assert!(form.contains(r#"<datalist id="employee-suggestions">"#));
+        assert!(!form.contains("<!-- EMPLOYEE_SUGGESTIONS -->"));
+    }
+
+    #[tokio::test]
+    async fn test_employee_suggestions_require_admin() {
+        let state = test_state().await;
+        let magic_link = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/suggest?q=an", &magic_link).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            get_status(&state, "/api/v1/employees/suggest?q=an", "invalid").await,
+            StatusCode::SEE_OTHER
+        );
+
+        let long_query = "a".repeat(65);
+        assert_eq!(
+            get_status(
+                &state,
+                &format!("/api/v1/employees/suggest?q={long_query}"),
+                &admin_token(&state)
+            )
+            .await,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_suggestion_queries_return_empty_array() {
+        let state = test_state().await;
+        for uri in [
+            "/api/v1/employees/suggest?q=zzz",
+            "/api/v1/employees/suggest?q=",
+            "/api/v1/employees/suggest?q=%20",
+            "/api/v1/employees/suggest",
+            "/api/v1/employees/suggest?name=Anna",
+        ] {
+            assert_eq!(get_body(&state, uri).await, "[]", "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_print_week_table() {
+        let state = test_state().await;
+        let mut anna = WorkSchedule::new("Anna".to_string());
+        anna.add_day(WorkDay {
+            date: "2025-01-06".to_string(),
+            shifts: vec![ShiftRange::new("8:00", "16:00")],
+            is_day_off: false,
+            notes: Some("Kassa".to_string()),
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        anna.add_day(WorkDay {
+            date: "2025-01-07".to_string(),
+            shifts: Vec::new(),
+            is_day_off: true,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        });
+        state.db.set_schedule("Anna", &anna).await.unwrap();
+
+        let body = get_body(&state, "/print/week?start=2025-01-06").await;
+        assert!(body.contains("<th>Mon 06.01.</th>"), "{body}");
+        assert!(body.contains("<th>Sun 12.01.</th>"), "{body}");
+        assert!(
+            body.contains(
+                "<tr><th scope=\"row\">Anna</th>\
+                 <td class=\"work\">08:00–16:00<div class=\"note\">Kassa</div></td>\
+                 <td class=\"off\">Off</td><td class=\"blank\"></td>"
+            ),
+            "{body}"
+        );
+        // Pekka has no schedule data, so the whole row is blank
+        assert!(
+            body.contains(&format!(
+                "<tr><th scope=\"row\">Pekka</th>{}</tr>",
+                "<td class=\"blank\"></td>".repeat(7)
+            )),
+            "{body}"
+        );
+        assert!(
+            body.contains("Some employees have no schedule data"),
+            "{body}"
+        );
+        assert!(!body.contains("undefined"), "{body}");
+        assert!(!body.contains("<script"), "{body}");
+
+        let body = get_body(&state, "/print/week?start=2025-01-06&notes=false").await;
+        assert!(
+            body.contains("<td class=\"work\">08:00–16:00</td>"),
+            "{body}"
+        );
+        assert!(!body.contains("Kassa"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_print_week_footer_shows_coverage() {
+        let state = test_state().await;
+        for employee in ["Anna", "Pekka"] {
+            let mut schedule = WorkSchedule::new(employee.to_string());
+            schedule.add_day(WorkDay {
+                date: "2025-01-08".to_string(),
+                shifts: vec![ShiftRange::new("10:00", "18:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+            state.db.set_schedule(employee, &schedule).await.unwrap();
+        }
+
+        let body = get_body(&state, "/print/week?start=2025-01-06").await;
+        assert!(
+            body.contains("Schedules cover dates through 08.01.2025"),
+            "{body}"
+        );
+        // Days past the coverage are blank
+        assert!(
+            body.contains(
+                "<td class=\"work\">10:00–18:00</td><td class=\"blank\"></td><td class=\"blank\"></td>"
+            ),
+            "{body}"
+        );
+
+        assert_eq!(
+            get_status(&state, "/print/week?start=06.01.2025", &admin_token(&state)).await,
+            StatusCode::BAD_REQUEST
+        );
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        assert_eq!(
+            get_status(&state, "/print/week", &token).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    /// Post a CSV import and return the status with the JSON report
+    async fn post_import(
+        state: &AppState,
+        uri: &str,
+        csv: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", admin_token(state)))
+            .header("Content-Type", "text/csv")
+            .body(Body::from(csv.to_string()))
+            .unwrap();
+
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    const IMPORT_CSV: &str = "employee,date,day_type,shifts,break_minutes,notes\n\
+        Anna,2024-03-04,work,08:00-16:00,,\n\
+        Anna,2024-03-05,off,,,\n\
+        Anna,2024-03-05,work,12:00-20:00,30,\n\
+        Pekka,2024-03-04,vacation,,,\n\
+        Pekka,2024-13-01,work,08:00-16:00,,\n\
+        Maija,2024-03-04,work,8-16,,\n";
+
+    #[tokio::test]
+    async fn test_import_dry_run_reports_without_writing() {
+        let state = test_state().await;
+        let mut before = state.db.list_employees().await.unwrap();
+        before.sort();
+
+        let (status, report) =
+            post_import(&state, "/api/v1/import.csv?dry_run=true", IMPORT_CSV).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["dry_run"], true);
+        assert_eq!(report["imported"], 3);
+        assert_eq!(report["skipped"], 1);
+        assert_eq!(report["errors"], 2);
+        assert_eq!(report["row_errors"][0]["row"], 6);
+        assert_eq!(report["row_errors"][1]["row"], 7);
+        assert_eq!(report["warnings"][0]["row"], 4);
+
+        let mut after = state.db.list_employees().await.unwrap();
+        after.sort();
+        assert_eq!(after, before);
+        let anna = state.db.get_schedule("Anna").await.unwrap().unwrap();
+        assert!(anna.days.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_upserts_into_stored_schedules() {
+        let state = test_state().await;
+        let mut anna = WorkSchedule::new("Anna".to_string());
+        for (date, start) in [("2024-03-04", "06:00"), ("2024-03-06", "06:00")] {
+            anna.add_day(WorkDay {
+                date: date.to_string(),
+                shifts: vec![ShiftRange::new(start, "14:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+        }
+        state.db.set_schedule("Anna", &anna).await.unwrap();
+
+        let (status, report) = post_import(&state, "/api/v1/import.csv", IMPORT_CSV).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["imported"], 3);
+
+        let anna = state.db.get_schedule("Anna").await.unwrap().unwrap();
+        let days: Vec<(&str, Option<&str>, bool)> = anna
+            .days
+            .iter()
+            .map(|day| {
+                let start = day.shifts.first().and_then(|shift| shift.start.as_deref());
+                (day.date.as_str(), start, day.is_day_off)
+            })
+            .collect();
+        assert_eq!(
+            days,
+            [
+                ("2024-03-04", Some("08:00"), false),
+                ("2024-03-05", Some("12:00"), false),
+                ("2024-03-06", Some("06:00"), false),
+            ]
+        );
+        let pekka = state.db.get_schedule("Pekka").await.unwrap().unwrap();
+        assert_eq!(pekka.days[0].notes.as_deref(), Some("vv"));
+        assert!(state.db.get_schedule("Maija").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_files_without_required_columns() {
+        let state = test_state().await;
+        let (status, _) = post_import(&state, "/api/v1/import.csv", "employee,date\n").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/import.csv")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(IMPORT_CSV))
+            .unwrap();
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    /// Send a JSON request as the admin, returning the status and the JSON body
+    async fn send_json(
+        state: &AppState,
+        method: &str,
+        uri: &str,
+        body: Option<&serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", admin_token(state)))
+            .header("Content-Type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_schedule_export_import_round_trip() {
+        for mode in ["merge", "overwrite"] {
+            let db = Arc::new(InMemoryDb::default());
+            let state = AppState {
+                db: db.clone(),
+                ..test_state().await
+            };
+            let mut schedule = WorkSchedule::new("Anna".to_string());
+            for (date, start) in [
+                ("2024-03-04", "08:00"),
+                ("2024-03-05", "12:00"),
+                ("2024-03-06", "06:00"),
+            ] {
+                schedule.add_day(WorkDay {
+                    date: date.to_string(),
+                    shifts: vec![ShiftRange::new(start, "16:00")],
+                    is_day_off: false,
+                    notes: None,
+                    break_minutes: None,
+                    context_link: None,
+                    actual_start: None,
+                    actual_end: None,
+                });
+            }
+            state.db.set_schedule("Anna", &schedule).await.unwrap();
+
+            let export = "/api/v1/schedule/Anna/export?from=2024-03-01&to=2024-03-31";
+            let (status, exported) = send_json(&state, "GET", export, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(exported.as_array().unwrap().len(), 3);
+            assert_eq!(exported[0]["v"], 2);
+
+            let mut payload = exported.clone();
+            payload[1]["shifts"][0]["start"] = "13:00".into();
+            let import = format!("/api/v1/schedule/Anna/import?mode={mode}");
+            let (status, report) = send_json(&state, "POST", &import, Some(&payload)).await;
+            assert_eq!(status, StatusCode::OK, "{report}");
+            assert_eq!(report["changed"], serde_json::json!(["2024-03-05"]));
+            assert_eq!(report["unchanged"], 2);
+
+            let (_, reexported) = send_json(&state, "GET", export, None).await;
+            assert_eq!(reexported, payload);
+            assert_ne!(reexported[1], exported[1]);
+
+            let audit = db.audit_records().await;
+            assert_eq!(audit.len(), 1);
+            assert_eq!(audit[0].date, "2024-03-05");
+            assert_eq!(audit[0].actor.as_deref(), Some("admin"));
+            // The day looks different, so it shows in the change feed
+            assert!(audit[0].change().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_actual_hours_are_recorded_for_recent_days_only() {
+        let db = Arc::new(InMemoryDb::default());
+        let state = AppState {
+            db: db.clone(),
+            ..test_state().await
+        };
+        let today = chrono::Local::now().date_naive();
+        let date = |days_ago: i64| {
+            (today - chrono::Duration::days(days_ago))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+        let mut schedule = WorkSchedule::new("Anna".to_string());
+        for days_ago in [0, 1, 20] {
+            schedule.add_day(WorkDay {
+                date: date(days_ago),
+                shifts: vec![ShiftRange::new("08:00", "16:00")],
+                is_day_off: false,
+                notes: None,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+        }
+        state.db.set_schedule("Anna", &schedule).await.unwrap();
+
+        let uri = |days_ago: i64| format!("/api/v1/actuals/Anna/{}", date(days_ago));
+        let hours = serde_json::json!({"start": "8.15", "end": "16:45"});
+        let (status, day) = send_json(&state, "PUT", &uri(1), Some(&hours)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(day["actual_start"], "08:15");
+        assert_eq!(day["actual_end"], "16:45");
+
+        // Today hasn't passed, 20 days ago is too old and 2 days ago has nothing stored
+        for (days_ago, expected) in [
+            (0, StatusCode::BAD_REQUEST),
+            (20, StatusCode::BAD_REQUEST),
+            (2, StatusCode::NOT_FOUND),
+        ] {
+            let (status, _) = send_json(&state, "PUT", &uri(days_ago), Some(&hours)).await;
+            assert_eq!(status, expected, "{days_ago} days ago");
+        }
+        let half = serde_json::json!({"start": "08:00"});
+        let (status, _) = send_json(&state, "PUT", &uri(1), Some(&half)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let audit = db.audit_records().await;
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].source, "actual_hours");
+        assert_eq!(audit[0].after.as_ref().unwrap().actual_minutes(), Some(510));
+        // The published schedule didn't change, so the change feed stays quiet
+        assert_eq!(audit[0].change(), None);
+
+        // A JSON import of the day keeps the recorded hours
+        let export = format!("/api/v1/schedule/Anna/export?from={}", date(1));
+        let (_, mut payload) = send_json(&state, "GET", &export, None).await;
+        payload[0]["shifts"][0]["start"] = "09:00".into();
+        payload[0]["actual_start"] = "06:00".into();
+        let (status, _) = send_json(
+            &state,
+            "POST",
+            "/api/v1/schedule/Anna/import",
+            Some(&payload),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let csv = get_body(&state, "/api/v1/export.csv").await;
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("employee,date,day_type,shifts,break_minutes,notes,actual_start,actual_end")
+        );
+        assert!(
+            csv.contains(&format!("Anna,{},work,09:00-16:00,,,08:15,16:45", date(1))),
+            "{csv}"
+        );
+
+        let (status, day) = send_json(&state, "PUT", &uri(1), Some(&serde_json::json!({}))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(day.get("actual_start").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_import_is_validated_and_admin_only() {
+        let state = test_state().await;
+        let payload = serde_json::json!([
+            {"v": 2, "date": "2024-03-04", "shifts": [{"start": "08:00", "end": "26:00"}]},
+            {"v": 2, "date": "2024-03-04", "is_day_off": true}
+        ]);
+        let (status, report) = send_json(
+            &state,
+            "POST",
+            "/api/v1/schedule/Anna/import",
+            Some(&payload),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(report["errors"].as_array().unwrap().len(), 2);
+        assert!(state
+            .db
+            .get_schedule("Anna")
+            .await
+            .unwrap()
+            .unwrap()
+            .days
+            .is_empty());
+
+        let (status, _) = send_json(
+            &state,
+            "GET",
+            "/api/v1/schedule/Anna/export?from=2024-03-31&to=2024-03-01",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let token = state
+            .auth_service
+            .generate_magic_link_token("Anna", 0)
+            .unwrap();
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/v1/schedule/Anna/import")
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_changes_are_refused_during_maintenance() {
+        let db = Arc::new(InMemoryDb::default());
+        let state = AppState {
+            db: db.clone(),
+            ..test_state().await
+        };
+        db.set_maintenance(Some(Maintenance {
+            message: Some("Moving to the new system".to_string()),
+            enabled_by: 1,
+            enabled_at: 0,
+        }))
+        .await;
+
+        let (status, _) = post_import(&state, "/api/v1/import.csv", IMPORT_CSV).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let request = Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header("Authorization", format!("Bearer {}", admin_token(&state)))
+            .body(Body::empty())
+            .unwrap();
+        let response = build_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(&bytes[..], b"Moving to the new system");
+        assert!(state.db.get_schedule("Maija").await.unwrap().is_none());
+
+        // Reads still work
+        get_body(&state, "/upload").await;
+        get_body(&state, "/dashboard").await;
+
+        db.set_maintenance(None).await;
+        let (status, _) = post_import(&state, "/api/v1/import.csv", IMPORT_CSV).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,554 @@
+use crate::components::work_schedule::audit::AuditRecord;
+use crate::components::work_schedule::credentials::CredentialStatus;
+use crate::components::work_schedule::models::{ContextLink, ShiftRange, WorkScheduleEntry};
+use crate::components::work_schedule::parse_failures::{ParseFailure, MAX_LISTED_PARSE_FAILURES};
+use crate::components::work_schedule::quality::ParseRecord;
+use crate::components::work_schedule::stats::ContractHours;
+use crate::components::work_schedule::uploads::{StoredUpload, MAX_STORED_UPLOADS};
+use crate::components::work_schedule::EmployeeId;
+use crate::maintenance::Maintenance;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Represents a day's work hours.
+///
+/// Stored in the same JSON format as the bot's schedule entries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "WorkScheduleEntry", into = "WorkScheduleEntry")]
+pub struct WorkDay {
+    /// The date of the workday (YYYY-MM-DD)
+    pub date: String,
+    /// Shifts worked that day, e.g. two for a split shift
+    pub shifts: Vec<ShiftRange>,
+    /// Whether this is a day off
+    pub is_day_off: bool,
+    /// Any notes for this day
+    pub notes: Option<String>,
+    /// Unpaid break in minutes, e.g. the 30 of "9-17 (30)"
+    pub break_minutes: Option<u16>,
+    /// Discord message an admin linked to the day in the bot
+    pub context_link: Option<ContextLink>,
+    /// When work actually started (HH:MM), recorded once the day has passed
+    pub actual_start: Option<String>,
+    /// When work actually ended (HH:MM)
+    pub actual_end: Option<String>,
+}
+
+impl WorkDay {
+    /// Convert to the entry format the bot reads from Redis
+    pub fn to_entry(&self) -> WorkScheduleEntry {
+        self.clone().into()
+    }
+}
+
+impl From<WorkScheduleEntry> for WorkDay {
+    fn from(entry: WorkScheduleEntry) -> Self {
+        Self {
+            date: entry.date,
+            shifts: entry.shifts,
+            is_day_off: entry.is_day_off,
+            notes: entry.notes,
+            break_minutes: entry.break_minutes,
+            context_link: entry.context_link,
+            actual_start: entry.actual_start,
+            actual_end: entry.actual_end,
+        }
+    }
+}
+
+impl From<WorkDay> for WorkScheduleEntry {
+    fn from(day: WorkDay) -> Self {
+        Self {
+            shifts: day.shifts,
+            is_day_off: day.is_day_off,
+            notes: day.notes,
+            break_minutes: day.break_minutes,
+            context_link: day.context_link,
+            actual_start: day.actual_start,
+            actual_end: day.actual_end,
+            ..WorkScheduleEntry::new(day.date)
+        }
+    }
+}
+
+/// Represents a complete work schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkSchedule {
+    /// The employee name
+    pub employee_name: String,
+    /// The days in the schedule
+    pub days: Vec<WorkDay>,
+    /// When the schedule was last updated
+    pub last_updated: DateTime<Utc>,
+    /// Upload the schedule was parsed from, stored with each day entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_id: Option<String>,
+    /// The model's days the schedule was converted from, kept with the upload for replaying
+    #[serde(skip)]
+    pub extraction: Option<Vec<WorkDayExtraction>>,
+}
+
+impl WorkSchedule {
+    /// Create a new work schedule for an employee
+    pub fn new(employee_name: String) -> Self {
+        Self {
+            employee_name,
+            days: Vec::new(),
+            last_updated: Utc::now(),
+            upload_id: None,
+            extraction: None,
+        }
+    }
+
+    /// Add a day to the schedule
+    pub fn add_day(&mut self, day: WorkDay) {
+        self.days.push(day);
+        self.last_updated = Utc::now();
+    }
+
+    /// Dates the parser produced more than one entry for, with those entries in order
+    pub fn duplicate_dates(&self) -> BTreeMap<&str, Vec<&WorkDay>> {
+        let mut by_date: BTreeMap<&str, Vec<&WorkDay>> = BTreeMap::new();
+        for day in &self.days {
+            by_date.entry(day.date.as_str()).or_default().push(day);
+        }
+        by_date.retain(|_, days| days.len() > 1);
+        by_date
+    }
+}
+
+/// Merge schedules stored under different spellings of the same employee.
+///
+/// When variants have entries for the same date, the most recently updated schedule wins. The
+/// merged schedule is named after that variant too.
+pub fn merge_schedules(mut variants: Vec<WorkSchedule>) -> WorkSchedule {
+    variants.sort_by_key(|schedule| schedule.last_updated);
+
+    let mut days = BTreeMap::new();
+    for schedule in &variants {
+        for day in &schedule.days {
+            days.insert(day.date.clone(), day.clone());
+        }
+    }
+
+    let latest = variants.last();
+    WorkSchedule {
+        employee_name: latest
+            .map(|schedule| {
+                EmployeeId::new(&schedule.employee_name)
+                    .display()
+                    .to_string()
+            })
+            .unwrap_or_default(),
+        days: days.into_values().collect(),
+        last_updated: latest
+            .map(|schedule| schedule.last_updated)
+            .unwrap_or_else(Utc::now),
+        upload_id: None,
+        extraction: None,
+    }
+}
+
+/// Response from the AI parsing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleParsingResult {
+    /// Was the parsing successful
+    pub success: bool,
+    /// Any error message if parsing failed
+    pub error: Option<String>,
+    /// The extracted work days
+    pub days: Vec<WorkDay>,
+}
+
+/// Database trait for storing and retrieving work schedules
+#[async_trait::async_trait]
+pub trait WorkHoursDb: Send + Sync + 'static {
+    /// Get a schedule for an employee
+    async fn get_schedule(&self, employee_name: &str) -> Result<Option<WorkSchedule>, String>;
+
+    /// Store a schedule for an employee
+    async fn set_schedule(
+        &self,
+        employee_name: &str,
+        schedule: &WorkSchedule,
+    ) -> Result<(), String>;
+
+    /// List all employee names with schedules
+    async fn list_employees(&self) -> Result<Vec<String>, String>;
+
+    /// Delete a schedule for an employee
+    async fn delete_schedule(&self, employee_name: &str) -> Result<(), String>;
+
+    /// Get the current magic link token version for an employee
+    async fn get_token_version(&self, employee_name: &str) -> Result<u64, String>;
+
+    /// Bump the magic link token version, revoking all previously issued links
+    async fn bump_token_version(&self, employee_name: &str) -> Result<u64, String>;
+
+    /// Remember a stored schedule image
+    async fn record_upload(&self, upload: &StoredUpload) -> Result<(), String>;
+
+    /// List the remembered schedule images, newest first
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, String>;
+
+    /// Remember how the parser did on an upload
+    async fn record_parse(&self, record: &ParseRecord) -> Result<(), String>;
+
+    /// List the parse records with their manual edit counts, oldest first
+    async fn list_parse_records(&self) -> Result<Vec<ParseRecord>, String>;
+
+    /// Keep a failed parse with the model's raw response for 14 days
+    async fn record_parse_failure(&self, failure: &ParseFailure) -> Result<(), String>;
+
+    /// List the latest parse failures that haven't expired, newest first
+    async fn list_parse_failures(&self) -> Result<Vec<ParseFailure>, String>;
+
+    /// Get a stored parse failure by its id
+    async fn get_parse_failure(&self, id: &str) -> Result<Option<ParseFailure>, String>;
+
+    /// Keep the model's days an upload was converted from for 90 days
+    async fn record_extraction(&self, extraction: &StoredExtraction) -> Result<(), String>;
+
+    /// List the kept extractions that haven't expired, oldest first
+    async fn list_extractions(&self) -> Result<Vec<StoredExtraction>, String>;
+
+    /// List the weekly contract hours set with the bot's `/contract_hours`
+    async fn list_contract_hours(&self) -> Result<Vec<ContractHours>, String>;
+
+    /// Remove single days (YYYY-MM-DD) from an employee's stored schedule
+    async fn remove_days(&self, employee_name: &str, dates: &[String]) -> Result<(), String>;
+
+    /// Number and store audit records of changed days for the bot's change feed
+    async fn record_audit(&self, records: Vec<AuditRecord>) -> Result<(), String>;
+
+    /// List the kept audit records, oldest first
+    async fn list_audit(&self) -> Result<Vec<AuditRecord>, String>;
+
+    /// Read the bot's maintenance mode, None when it's off
+    async fn get_maintenance(&self) -> Result<Option<Maintenance>, String>;
+
+    /// List the latest API key check of every provider
+    async fn list_credential_statuses(&self) -> Result<Vec<CredentialStatus>, String>;
+
+    /// Store the latest API key check of a provider
+    async fn set_credential_status(&self, status: &CredentialStatus) -> Result<(), String>;
+
+    /// Check that the database can be reached
+    async fn ping(&self) -> Result<(), String>;
+}
+
+/// In-memory implementation of the database (for testing)
+#[derive(Debug, Default)]
+pub struct InMemoryDb {
+    schedules: tokio::sync::RwLock<HashMap<String, WorkSchedule>>,
+    token_versions: tokio::sync::RwLock<HashMap<String, u64>>,
+    uploads: tokio::sync::RwLock<Vec<StoredUpload>>,
+    parse_records: tokio::sync::RwLock<Vec<ParseRecord>>,
+    parse_failures: tokio::sync::RwLock<Vec<ParseFailure>>,
+    extractions: tokio::sync::RwLock<Vec<StoredExtraction>>,
+    contract_hours: tokio::sync::RwLock<Vec<ContractHours>>,
+    audit: tokio::sync::RwLock<Vec<AuditRecord>>,
+    maintenance: tokio::sync::RwLock<Option<Maintenance>>,
+    credentials: tokio::sync::RwLock<BTreeMap<String, CredentialStatus>>,
+}
+
+#[async_trait::async_trait]
+impl WorkHoursDb for InMemoryDb {
+    async fn get_schedule(&self, employee_name: &str) -> Result<Option<WorkSchedule>, String> {
+        let schedules = self.schedules.read().await;
+        Ok(schedules
+            .get(EmployeeId::new(employee_name).slug())
+            .cloned())
+    }
+
+    async fn set_schedule(
+        &self,
+        employee_name: &str,
+        schedule: &WorkSchedule,
+    ) -> Result<(), String> {
+        let employee = EmployeeId::new(employee_name);
+        let mut schedule = schedule.clone();
+        schedule.employee_name = employee.display().to_string();
+
+        let mut schedules = self.schedules.write().await;
+        schedules.insert(employee.slug().to_string(), schedule);
+        Ok(())
+    }
+
+    async fn list_employees(&self) -> Result<Vec<String>, String> {
+        let schedules = self.schedules.read().await;
+        Ok(schedules
+            .values()
+            .map(|schedule| schedule.employee_name.clone())
+            .collect())
+    }
+
+    async fn delete_schedule(&self, employee_name: &str) -> Result<(), String> {
+        let mut schedules = self.schedules.write().await;
+        schedules.remove(EmployeeId::new(employee_name).slug());
+        Ok(())
+    }
+
+    async fn get_token_version(&self, employee_name: &str) -> Result<u64, String> {
+        let versions = self.token_versions.read().await;
+        Ok(versions
+            .get(EmployeeId::new(employee_name).slug())
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn bump_token_version(&self, employee_name: &str) -> Result<u64, String> {
+        let mut versions = self.token_versions.write().await;
+        let version = versions
+            .entry(EmployeeId::new(employee_name).slug().to_string())
+            .or_insert(0);
+        *version += 1;
+        Ok(*version)
+    }
+
+    async fn record_upload(&self, upload: &StoredUpload) -> Result<(), String> {
+        let mut uploads = self.uploads.write().await;
+        uploads.insert(0, upload.clone());
+        uploads.truncate(MAX_STORED_UPLOADS as usize);
+        Ok(())
+    }
+
+    async fn list_uploads(&self) -> Result<Vec<StoredUpload>, String> {
+        Ok(self.uploads.read().await.clone())
+    }
+
+    async fn record_parse(&self, record: &ParseRecord) -> Result<(), String> {
+        self.parse_records.write().await.push(record.clone());
+        Ok(())
+    }
+
+    async fn list_parse_records(&self) -> Result<Vec<ParseRecord>, String> {
+        Ok(self.parse_records.read().await.clone())
+    }
+
+    async fn record_parse_failure(&self, failure: &ParseFailure) -> Result<(), String> {
+        self.parse_failures.write().await.insert(0, failure.clone());
+        Ok(())
+    }
+
+    async fn list_parse_failures(&self) -> Result<Vec<ParseFailure>, String> {
+        let failures = self.parse_failures.read().await;
+        Ok(failures
+            .iter()
+            .take(MAX_LISTED_PARSE_FAILURES)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_parse_failure(&self, id: &str) -> Result<Option<ParseFailure>, String> {
+        let failures = self.parse_failures.read().await;
+        Ok(failures.iter().find(|failure| failure.id == id).cloned())
+    }
+
+    async fn record_extraction(&self, extraction: &StoredExtraction) -> Result<(), String> {
+        let mut extractions = self.extractions.write().await;
+        extractions.push(extraction.clone());
+        let excess = extractions.len().saturating_sub(MAX_STORED_EXTRACTIONS);
+        extractions.drain(..excess);
+        Ok(())
+    }
+
+    async fn list_extractions(&self) -> Result<Vec<StoredExtraction>, String> {
+        Ok(self.extractions.read().await.clone())
+    }
+
+    async fn list_contract_hours(&self) -> Result<Vec<ContractHours>, String> {
+        Ok(self.contract_hours.read().await.clone())
+    }
+
+    async fn remove_days(&self, employee_name: &str, dates: &[String]) -> Result<(), String> {
+        let mut schedules = self.schedules.write().await;
+        if let Some(schedule) = schedules.get_mut(EmployeeId::new(employee_name).slug()) {
+            schedule.days.retain(|day| !dates.contains(&day.date));
+        }
+        Ok(())
+    }
+
+    async fn record_audit(&self, records: Vec<AuditRecord>) -> Result<(), String> {
+        let mut audit = self.audit.write().await;
+        for mut record in records {
+            record.seq = audit.len() as u64 + 1;
+            audit.push(record);
+        }
+        Ok(())
+    }
+
+    async fn list_audit(&self) -> Result<Vec<AuditRecord>, String> {
+        Ok(self.audit.read().await.clone())
+    }
+
+    async fn get_maintenance(&self) -> Result<Option<Maintenance>, String> {
+        Ok(self.maintenance.read().await.clone())
+    }
+
+    async fn list_credential_statuses(&self) -> Result<Vec<CredentialStatus>, String> {
+        Ok(self.credentials.read().await.values().cloned().collect())
+    }
+
+    async fn set_credential_status(&self, status: &CredentialStatus) -> Result<(), String> {
+        self.credentials
+            .write()
+            .await
+            .insert(status.provider.clone(), status.clone());
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl InMemoryDb {
+    /// Stand in for an admin turning the bot's maintenance mode on or off
+    pub async fn set_maintenance(&self, maintenance: Option<Maintenance>) {
+        *self.maintenance.write().await = maintenance;
+    }
+
+    /// The audit records written so far, oldest first
+    pub async fn audit_records(&self) -> Vec<AuditRecord> {
+        self.audit.read().await.clone()
+    }
+
+    /// Stand in for the bot setting an employee's contract hours
+    pub async fn add_contract_hours(&self, contract: ContractHours) {
+        self.contract_hours.write().await.push(contract);
+    }
+}
+
+// Define the target extraction structure to match the expected JSON format
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct WorkDayExtraction {
+    pub date: String,
+    pub work_hours: String,
+}
+
+/// Days kept of an extraction
+pub const EXTRACTION_TTL_SECONDS: i64 = 90 * 24 * 60 * 60;
+
+/// Most extractions kept at once
+pub const MAX_STORED_EXTRACTIONS: usize = 500;
+
+/// The model's days an upload was converted from, so they can be converted again once the
+/// meaning of a cell changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredExtraction {
+    /// Upload the days were read from, as stored with the schedule
+    pub upload_id: String,
+    pub employee: String,
+    /// Unix timestamp of the upload
+    pub extracted_at: i64,
+    pub days: Vec<WorkDayExtraction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(date: &str, start: &str) -> WorkDay {
+        WorkDay {
+            date: date.to_string(),
+            shifts: vec![ShiftRange::new(start, "16:00")],
+            is_day_off: false,
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        }
+    }
+
+    fn schedule(name: &str, updated_day: u32, days: Vec<WorkDay>) -> WorkSchedule {
+        WorkSchedule {
+            employee_name: name.to_string(),
+            days,
+            last_updated: Utc
+                .with_ymd_and_hms(2025, 1, updated_day, 12, 0, 0)
+                .unwrap(),
+            upload_id: None,
+            extraction: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_prefers_most_recent_variant_on_conflict() {
+        let older = schedule(
+            "anna  mäkinen",
+            1,
+            vec![day("2025-01-06", "08:00"), day("2025-01-07", "08:00")],
+        );
+        let newer = schedule(
+            "Anna Mäkinen",
+            3,
+            vec![day("2025-01-07", "12:00"), day("2025-01-08", "12:00")],
+        );
+
+        let merged = merge_schedules(vec![newer.clone(), older]);
+
+        assert_eq!(merged.employee_name, "Anna Mäkinen");
+        assert_eq!(merged.last_updated, newer.last_updated);
+        let days: Vec<(&str, Option<&str>)> = merged
+            .days
+            .iter()
+            .map(|day| (day.date.as_str(), day.shifts[0].start.as_deref()))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                ("2025-01-06", Some("08:00")),
+                ("2025-01-07", Some("12:00")),
+                ("2025-01-08", Some("12:00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_dates() {
+        let mut schedule = schedule(
+            "Anna",
+            1,
+            vec![day("2025-01-06", "08:00"), day("2025-01-07", "08:00")],
+        );
+        schedule.add_day(day("2025-01-06", "12:00"));
+
+        let duplicates = schedule.duplicate_dates();
+        assert_eq!(
+            duplicates.keys().copied().collect::<Vec<_>>(),
+            ["2025-01-06"]
+        );
+        let starts: Vec<Option<&str>> = duplicates["2025-01-06"]
+            .iter()
+            .map(|day| day.shifts[0].start.as_deref())
+            .collect();
+        assert_eq!(starts, [Some("08:00"), Some("12:00")]);
+    }
+
+    #[tokio::test]
+    async fn test_name_variants_share_storage() {
+        let db = InMemoryDb::default();
+        db.set_schedule(
+            "anna  mäkinen",
+            &schedule("x", 1, vec![day("2025-01-06", "08:00")]),
+        )
+        .await
+        .unwrap();
+        db.set_schedule(
+            "Anna Mäkinen",
+            &schedule("x", 2, vec![day("2025-01-07", "08:00")]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_employees().await.unwrap(), vec!["Anna Mäkinen"]);
+        let stored = db.get_schedule("ANNA MÄKINEN").await.unwrap().unwrap();
+        assert_eq!(stored.days[0].date, "2025-01-07");
+    }
+}
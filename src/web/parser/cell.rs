@@ -0,0 +1,379 @@
+//! The grammar of a schedule cell as the model reads it: blank, `x` for a day off, one or
+//! more time ranges like `7-15` or `7.30-15.30` with an optional break, or anything else as a
+//! note such as a code.
+
+use crate::components::work_schedule::models::ShiftRange;
+use chrono::NaiveTime;
+
+/// Shortest break a cell can annotate, so hour counts like "(8)" aren't taken for one
+const MIN_BREAK_MINUTES: u16 = 15;
+
+/// Longest break a cell can annotate
+const MAX_BREAK_MINUTES: u16 = 120;
+
+/// A cell's shifts together with the unpaid break noted in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCell {
+    pub shifts: Vec<ShiftRange>,
+    pub break_minutes: Option<u16>,
+}
+
+/// What a cell holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// Nothing was written in the cell
+    Blank,
+    /// An `x`
+    DayOff,
+    /// Time ranges, maybe with a break
+    Shifts(ParsedCell),
+    /// Anything else, such as a code like `vv` or a word like `koulutus`
+    Note(String),
+}
+
+/// Read a cell's contents
+pub fn classify(cell: &str) -> Cell {
+    if cell.is_empty() {
+        Cell::Blank
+    } else if cell.to_lowercase() == "x" {
+        Cell::DayOff
+    } else if let Some(parsed) = parse_cell(cell) {
+        Cell::Shifts(parsed)
+    } else {
+        Cell::Note(cell.to_string())
+    }
+}
+
+/// Write a cell back in the grammar `classify` reads, with times as HH:MM
+pub fn format_cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Blank => String::new(),
+        Cell::DayOff => "x".to_string(),
+        Cell::Shifts(parsed) => {
+            let shifts = parsed
+                .shifts
+                .iter()
+                .map(|shift| {
+                    format!(
+                        "{}-{}",
+                        shift.start.as_deref().unwrap_or_default(),
+                        shift.end.as_deref().unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            match parsed.break_minutes {
+                Some(minutes) => format!("{shifts} ({minutes})"),
+                None => shifts,
+            }
+        }
+        Cell::Note(note) => note.clone(),
+    }
+}
+
+/// Normalize a time string to the HH:MM format
+pub fn normalize_time(time_str: &str) -> String {
+    // Remove any extra whitespace
+    let time_str = time_str.trim();
+
+    // Replace commas with periods
+    let time_str = time_str.replace(',', ".");
+
+    // Try to parse as a time with or without a colon
+    if time_str.contains(':') {
+        // Time already has a colon, just format it properly
+        if let Ok(time) = NaiveTime::parse_from_str(&time_str, "%H:%M") {
+            return time.format("%H:%M").to_string();
+        }
+    } else if time_str.contains('.') {
+        // Time has a period (e.g., "8.30")
+        let parts: Vec<&str> = time_str.split('.').collect();
+        if parts.len() == 2 {
+            if let (Ok(hours), Ok(minutes)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
+                if hours < 24 && minutes < 60 {
+                    return format!("{hours:02}:{minutes:02}");
+                }
+            }
+        }
+    } else {
+        // Just a number (e.g., "8"), assume it's hours
+        if let Ok(hours) = time_str.parse::<u32>() {
+            if hours < 24 {
+                return format!("{hours:02}:00");
+            }
+        }
+    }
+
+    // If all parsing fails, return the original string
+    time_str.to_string()
+}
+
+/// Split a cell into its time ranges, e.g. "8-12, 16-20" or ranges on separate lines.
+///
+/// Commas also serve as decimal separators ("7,30-15"), so a comma only starts a new range
+/// when the text before it already holds a complete range. Returns None if any part isn't a
+/// "start-end" range.
+pub fn parse_shifts(cell: &str) -> Option<Vec<ShiftRange>> {
+    let mut ranges: Vec<String> = Vec::new();
+    for line in cell.lines() {
+        let mut current = String::new();
+        for fragment in line.split(',') {
+            if current.contains('-') && fragment.contains('-') {
+                ranges.push(std::mem::take(&mut current));
+            } else if !current.is_empty() {
+                current.push(',');
+            }
+            current.push_str(fragment);
+        }
+        ranges.push(current);
+    }
+
+    let shifts: Option<Vec<ShiftRange>> = ranges
+        .iter()
+        .map(|range| range.trim())
+        .filter(|range| !range.is_empty())
+        .map(|range| match range.split('-').collect::<Vec<_>>()[..] {
+            [start, end] => Some(ShiftRange::new(normalize_time(start), normalize_time(end))),
+            _ => None,
+        })
+        .collect();
+    shifts.filter(|shifts| !shifts.is_empty())
+}
+
+/// Read a trailing break annotation such as "(30)" or "(30 min)"
+fn parse_break_annotation(annotation: &str) -> Option<u16> {
+    let inner = annotation
+        .trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .trim();
+    let digits = inner
+        .strip_suffix("min")
+        .or_else(|| inner.strip_suffix('m'))
+        .unwrap_or(inner)
+        .trim();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let minutes: u16 = digits.parse().ok()?;
+    (MIN_BREAK_MINUTES..=MAX_BREAK_MINUTES)
+        .contains(&minutes)
+        .then_some(minutes)
+}
+
+/// Length of `inner` if it's a short range strictly inside `shift`, i.e. a break
+fn break_within(shift: &ShiftRange, inner: &ShiftRange) -> Option<u16> {
+    let (start, end) = shift.minutes()?;
+    let (break_start, break_end) = inner.minutes()?;
+    let minutes = u16::try_from(break_end.checked_sub(break_start)?).ok()?;
+    (start < break_start
+        && break_end < end
+        && (MIN_BREAK_MINUTES..=MAX_BREAK_MINUTES).contains(&minutes))
+    .then_some(minutes)
+}
+
+/// Parse a cell's shifts along with its break, given either as trailing minutes in parentheses
+/// ("9-17 (30)") or as a short second range inside the first ("9-17, 12-12.30").
+///
+/// Anything else in parentheses, like "(L)", is left to `parse_shifts`.
+pub fn parse_cell(cell: &str) -> Option<ParsedCell> {
+    let cell = cell.trim();
+    if let Some(open) = cell.rfind('(') {
+        if let Some(break_minutes) = parse_break_annotation(&cell[open..]) {
+            return Some(ParsedCell {
+                shifts: parse_shifts(&cell[..open])?,
+                break_minutes: Some(break_minutes),
+            });
+        }
+    }
+
+    let shifts = parse_shifts(cell)?;
+    if let [shift, inner] = &shifts[..] {
+        if let Some(break_minutes) = break_within(shift, inner) {
+            return Some(ParsedCell {
+                shifts: vec![shift.clone()],
+                break_minutes: Some(break_minutes),
+            });
+        }
+    }
+    Some(ParsedCell {
+        shifts,
+        break_minutes: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn hours(cell: &str) -> Option<Vec<(String, String)>> {
+        parse_shifts(cell).map(|shifts| {
+            shifts
+                .into_iter()
+                .map(|shift| (shift.start.unwrap(), shift.end.unwrap()))
+                .collect()
+        })
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+        Some(
+            expected
+                .iter()
+                .map(|(start, end)| (start.to_string(), end.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_single_and_split_shifts() {
+        assert_eq!(hours("7-15"), pairs(&[("07:00", "15:00")]));
+        assert_eq!(
+            hours("8-12, 16-20"),
+            pairs(&[("08:00", "12:00"), ("16:00", "20:00")])
+        );
+        assert_eq!(
+            hours("8-12\n16-20"),
+            pairs(&[("08:00", "12:00"), ("16:00", "20:00")])
+        );
+    }
+
+    #[test]
+    fn test_comma_decimals_are_not_split() {
+        assert_eq!(hours("7,30-15,30"), pairs(&[("07:30", "15:30")]));
+        assert_eq!(
+            hours("7,30-12,16-20,30"),
+            pairs(&[("07:30", "12:00"), ("16:00", "20:30")])
+        );
+    }
+
+    #[test]
+    fn test_non_ranges_are_rejected() {
+        assert_eq!(hours("koulutus"), None);
+        assert_eq!(hours("8-12-16"), None);
+        assert_eq!(hours(""), None);
+    }
+
+    #[test]
+    fn test_break_annotations() {
+        // Empty shifts mean the cell doesn't parse
+        let check = |cell: &str, shifts: &[(&str, &str)], break_minutes: Option<u16>| {
+            let parsed = parse_cell(cell);
+            assert_eq!(
+                parsed.as_ref().map(|parsed| parsed
+                    .shifts
+                    .iter()
+                    .map(|shift| (shift.start.clone().unwrap(), shift.end.clone().unwrap()))
+                    .collect::<Vec<_>>()),
+                pairs(shifts).filter(|shifts| !shifts.is_empty()),
+                "{cell:?}"
+            );
+            assert_eq!(
+                parsed.and_then(|parsed| parsed.break_minutes),
+                break_minutes,
+                "{cell:?}"
+            );
+        };
+
+        check("9-17 (30)", &[("09:00", "17:00")], Some(30));
+        check("9-17(30)", &[("09:00", "17:00")], Some(30));
+        check("7,30-16 (30 min)", &[("07:30", "16:00")], Some(30));
+        check("8-16.30 (45min)", &[("08:00", "16:30")], Some(45));
+        check("10-18 ( 60m )", &[("10:00", "18:00")], Some(60));
+        check("9-17, 12-12.30", &[("09:00", "17:00")], Some(30));
+        check("9-17\n11.30-12", &[("09:00", "17:00")], Some(30));
+        check("9-17", &[("09:00", "17:00")], None);
+        // Split shifts aren't breaks
+        check(
+            "8-12, 16-20",
+            &[("08:00", "12:00"), ("16:00", "20:00")],
+            None,
+        );
+        // Too long to be a break
+        check(
+            "7-20, 10-15",
+            &[("07:00", "20:00"), ("10:00", "15:00")],
+            None,
+        );
+        // Break minutes without hours
+        check("(30)", &[], None);
+        check("koulutus (30)", &[], None);
+    }
+
+    #[test]
+    fn test_parenthesized_non_breaks_are_not_breaks() {
+        for cell in [
+            "9-17 (L)",
+            "9-17 (8)",
+            "9-17 (7,5)",
+            "9-17 (koulutus)",
+            "9-17 (300)",
+            "x",
+        ] {
+            assert_eq!(
+                parse_cell(cell).and_then(|parsed| parsed.break_minutes),
+                None,
+                "{cell:?}"
+            );
+        }
+        // Without a break the cell parses exactly as before
+        assert_eq!(
+            parse_cell("9-17 (L)").map(|parsed| parsed.shifts),
+            parse_shifts("9-17 (L)")
+        );
+    }
+
+    #[test]
+    fn test_cells_are_classified() {
+        assert_eq!(classify(""), Cell::Blank);
+        assert_eq!(classify("X"), Cell::DayOff);
+        assert_eq!(classify("vv"), Cell::Note("vv".to_string()));
+        assert_eq!(
+            format_cell(&classify("7.30-15.30 (30 min)")),
+            "07:30-15:30 (30)"
+        );
+        assert_eq!(
+            format_cell(&classify("8-12\n16-20")),
+            "08:00-12:00, 16:00-20:00"
+        );
+    }
+
+    /// A time the way cells write them, e.g. "7", "7.30", "07:30" or "7,5"
+    fn time() -> impl Strategy<Value = String> {
+        r"[0-9]{1,2}([.,:][0-9]{1,2})?"
+    }
+
+    /// Text following the cell grammar: ranges joined by commas or lines, maybe with a break
+    /// annotation, or a code
+    fn grammar_cell() -> impl Strategy<Value = String> {
+        let range = (time(), time()).prop_map(|(start, end)| format!("{start}-{end}"));
+        let ranges = prop::collection::vec(range, 1..4).prop_flat_map(|ranges| {
+            prop::sample::select(vec![", ", ",", "\n", " , "])
+                .prop_map(move |separator| ranges.join(separator))
+        });
+        let annotation = prop::option::of(prop_oneof![
+            r"\([0-9]{1,3}\)",
+            r" \( ?[0-9]{1,3} ?(min|m)? ?\)",
+            r" \([A-Za-z]{1,8}\)",
+        ]);
+        prop_oneof![
+            (ranges, annotation)
+                .prop_map(|(ranges, annotation)| ranges + &annotation.unwrap_or_default()),
+            r"(x|X|vv|VL|loma|vp|koulutus|Toive vp|S)",
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_grammar_cells_round_trip(text in grammar_cell()) {
+            let cell = classify(&text);
+            prop_assert_eq!(classify(&format_cell(&cell)), cell);
+        }
+
+        #[test]
+        fn prop_arbitrary_cells_round_trip(text in any::<String>()) {
+            let cell = classify(&text);
+            prop_assert_eq!(classify(&format_cell(&cell)), cell);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Finding the schedule's JSON array in a model's response.
+//!
+//! Models are asked for the bare array but often wrap it in a ```json fence or add a line of
+//! commentary before or after it, which may contain brackets of its own.
+
+use crate::web::model::WorkDayExtraction;
+use serde_json::from_str;
+use tracing::warn;
+
+/// Index one past the `]` closing the array opened at `start`, skipping brackets inside
+/// strings, or None if the array isn't closed
+fn array_end(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, byte) in text.as_bytes()[start..].iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(start + offset + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Extract the schedule's days from a model's response: the whole response when it's the
+/// bare array, or else the first balanced array in it holding days. An empty array is only
+/// taken when no array with days follows.
+pub fn extract_json_array(text: &str) -> Result<Vec<WorkDayExtraction>, String> {
+    let direct_error = match from_str::<Vec<WorkDayExtraction>>(text) {
+        Ok(days) => return Ok(days),
+        Err(e) => e,
+    };
+    warn!("Failed to parse JSON directly: {}", direct_error);
+
+    let mut empty = None;
+    let mut first_error = None;
+    for (start, _) in text.match_indices('[') {
+        let Some(end) = array_end(text, start) else {
+            continue;
+        };
+        match from_str::<Vec<WorkDayExtraction>>(&text[start..end]) {
+            Ok(days) if days.is_empty() => empty = empty.or(Some(days)),
+            Ok(days) => return Ok(days),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (empty, first_error) {
+        (Some(days), _) => Ok(days),
+        (None, Some(e)) => Err(format!("Failed to parse extracted JSON array: {e}")),
+        (None, None) if text.contains('[') => Err("Invalid JSON array structure".to_string()),
+        (None, None) => Err("No JSON array found in response".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn dates(days: &[WorkDayExtraction]) -> Vec<(&str, &str)> {
+        days.iter()
+            .map(|day| (day.date.as_str(), day.work_hours.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_observed_gemini_responses() {
+        let fenced = extract_json_array(include_str!(
+            "../../../tests/fixtures/gemini_response_fenced.txt"
+        ))
+        .unwrap();
+        assert_eq!(
+            dates(&fenced),
+            [
+                ("2025-03-10", "7-15"),
+                ("2025-03-11", "x"),
+                ("2025-03-12", "9-17 (30)"),
+            ]
+        );
+
+        let commentary = extract_json_array(include_str!(
+            "../../../tests/fixtures/gemini_response_commentary.txt"
+        ))
+        .unwrap();
+        assert_eq!(
+            dates(&commentary),
+            [
+                ("2025-03-17", "8-16 [L]"),
+                ("2025-03-18", ""),
+                ("2025-03-19", "vv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_responses_without_days_fail() {
+        assert_eq!(
+            extract_json_array("I couldn't read the image.").unwrap_err(),
+            "No JSON array found in response"
+        );
+        assert_eq!(
+            extract_json_array("[{\"date\": \"2025-03-10\"").unwrap_err(),
+            "Invalid JSON array structure"
+        );
+        assert!(extract_json_array("[1, 2] and [\"a\"]")
+            .unwrap_err()
+            .starts_with("Failed to parse extracted JSON array"));
+        assert!(extract_json_array("No shifts: [] at all")
+            .unwrap()
+            .is_empty());
+    }
+
+    /// A day whose values may contain brackets, braces and quotes
+    fn day() -> impl Strategy<Value = (String, String)> {
+        ("[0-9-]{0,10}", r#"[ -~\[\]{}"\\]{0,16}"#)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_junk_around_an_array_is_skipped(
+            days in prop::collection::vec(day(), 1..5),
+            before in any::<String>(),
+            after in any::<String>(),
+        ) {
+            let json = serde_json::to_string(
+                &days
+                    .iter()
+                    .map(|(date, work_hours)| serde_json::json!({"date": date, "work_hours": work_hours}))
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+            let extracted = extract_json_array(&format!("{before}```json\n{json}\n```{after}")).unwrap();
+            let extracted: Vec<(String, String)> = extracted
+                .into_iter()
+                .map(|day| (day.date, day.work_hours))
+                .collect();
+            prop_assert_eq!(extracted, days);
+        }
+
+        #[test]
+        fn prop_arbitrary_text_never_panics(text in any::<String>()) {
+            let _ = extract_json_array(&text);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+pub mod cell;
+pub mod json_extract;
+pub mod llamaindex;
+mod rig_parser;
+
+use crate::components::work_schedule::parse_failures::{ModelExchange, ParseFailureStage};
+use std::fmt;
+
+use crate::web::model::WorkDayExtraction;
+
+pub use json_extract::extract_json_array;
+pub use llamaindex::convert_to_work_schedule;
+pub use llamaindex::extract_schedule_days;
+pub use llamaindex::parse_schedule_image;
+
+/// Model provider used to read the schedule from the image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Provider {
+    /// Google Gemini, configured with `GEMINI_API_KEY` and `GEMINI_MODEL`
+    #[default]
+    Gemini,
+    /// OpenAI, configured with `OPENAI_API_KEY` and `OPENAI_MODEL`
+    #[value(name = "openai")]
+    OpenAi,
+}
+
+impl Provider {
+    /// Name of the provider as given with `--provider`
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini",
+            Provider::OpenAi => "openai",
+        }
+    }
+
+    /// Environment variables that must be set for parsing with this provider, including the
+    /// LlamaIndex key used to read the image before the model
+    pub fn required_env_vars(self) -> [&'static str; 2] {
+        match self {
+            Provider::Gemini => ["LLAMA_API_KEY", "GEMINI_API_KEY"],
+            Provider::OpenAi => ["LLAMA_API_KEY", "OPENAI_API_KEY"],
+        }
+    }
+}
+
+/// Error prefixes meaning the parsing service itself couldn't be used, as opposed to the image
+/// being unreadable
+const UNAVAILABLE_ERROR_PREFIXES: [&str; 5] = [
+    "LLAMA_API_KEY environment variable not set",
+    "Failed to send request to LlamaIndex",
+    "LlamaIndex parsing service returned error",
+    "Failed to poll job status",
+    "Job polling timed out",
+];
+
+/// Check whether a parse error was caused by the parser being unavailable
+pub fn is_parser_unavailable(error: &str) -> bool {
+    UNAVAILABLE_ERROR_PREFIXES
+        .iter()
+        .any(|prefix| error.starts_with(prefix))
+}
+
+/// Why a parse failed, with the model's response when it was the response that failed
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    /// Where the parse failed and the prompt and response behind it
+    pub failure: Option<Box<(ParseFailureStage, ModelExchange)>>,
+}
+
+impl ParseError {
+    /// A parse that failed on the model's response
+    pub fn with_exchange(
+        message: impl Into<String>,
+        stage: ParseFailureStage,
+        exchange: ModelExchange,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            failure: Some(Box::new((stage, exchange))),
+        }
+    }
+}
+
+impl From<String> for ParseError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            failure: None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Read the days from a model's response, keeping the prompt and response with the error when
+/// no schedule can be read from it
+pub fn read_model_response(
+    exchange: ModelExchange,
+) -> Result<(Vec<WorkDayExtraction>, ModelExchange), ParseError> {
+    match extract_json_array(&exchange.response) {
+        Ok(days) => Ok((days, exchange)),
+        Err(e) => Err(ParseError::with_exchange(
+            e,
+            ParseFailureStage::Extraction,
+            exchange,
+        )),
+    }
+}
@@ -1,11 +1,14 @@
-use crate::model::WorkDayExtraction;
+use super::{read_model_response, ParseError, Provider};
+use crate::components::work_schedule::parse_failures::ModelExchange;
+use crate::web::model::WorkDayExtraction;
 use base64::{self, engine::Engine};
+use rig::client::CompletionClient;
 use rig::completion::{Chat, Message};
 use rig::message::{ContentFormat, Image, ImageMediaType};
 use rig::providers::gemini::Client as GeminiClient;
-use serde_json::from_str;
+use rig::providers::openai::Client as OpenAiClient;
 use std::env;
-use tracing::{error, info};
+use tracing::info;
 
 const SYSTEM_PROMPT: &str = "You are a work schedule parser. You need to analyze the given text that describes work schedules and extract dates and work hours information. Output your findings as a JSON array with each entry containing a date and work_hours fields.";
 
@@ -79,25 +82,16 @@ const MARKDOWN_PLACEHOLDER: &str = "[PRELIMINARY_MARKDOWN]";
 const NAME_PLACEHOLDER: &str = "[EMPLOYEE_NAME]";
 const YEAR_PLACEHOLDER: &str = "[YEAR]";
 
-/// Parse markdown and image using Rig with Google Gemini
+/// Parse markdown and image using Rig with the selected provider, returning the days with
+/// the prompt and response they were read from
 pub async fn parse_with_rig(
     image_data: &[u8],
     markdown: &str,
     name: &str,
     year: u32,
-) -> Result<Vec<WorkDayExtraction>, String> {
-    info!("Parsing work schedule with Rig and Google Gemini");
-
-    // Get API key from environment variable
-    let api_key = env::var("GEMINI_API_KEY")
-        .map_err(|_| "GEMINI_API_KEY environment variable not set".to_string())?;
-
-    // Get model name from environment variable
-    let model = env::var("GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.5-pro".to_string());
-    info!("Using Gemini model: {}", model);
-
-    // Initialize Gemini client with API key
-    let gemini_client = GeminiClient::new(&api_key);
+    provider: Provider,
+) -> Result<(Vec<WorkDayExtraction>, ModelExchange), ParseError> {
+    info!("Parsing work schedule with Rig and {:?}", provider);
 
     // Base64 encode the image
     let base64_image = base64::engine::general_purpose::STANDARD.encode(image_data);
@@ -110,59 +104,64 @@ pub async fn parse_with_rig(
     };
     let messages = vec![Message::from(image)];
 
-    // Prepare the prompt for Gemini
+    // Prepare the prompt for the model
     let user_prompt = USER_PROMPT_TEMPLATE
         .replace(MARKDOWN_PLACEHOLDER, markdown)
         .replace(NAME_PLACEHOLDER, name)
         .replace(YEAR_PLACEHOLDER, &year.to_string());
 
-    // Create chat messages
-    let agent = gemini_client
-        .agent(&model)
-        .preamble(SYSTEM_PROMPT)
-        .temperature(0.0)
-        .build();
+    let (model, response) = match provider {
+        Provider::Gemini => {
+            // Get API key and model name from environment variables
+            let api_key = env::var("GEMINI_API_KEY")
+                .map_err(|_| "GEMINI_API_KEY environment variable not set".to_string())?;
+            let model = env::var("GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.5-pro".to_string());
+            info!("Using Gemini model: {}", model);
 
-    // Make the request to Gemini directly using the existing runtime
-    let response = agent
-        .chat(user_prompt, messages)
-        .await
-        .map_err(|e| format!("Rig API request failed: {e}"))?;
+            let response = chat(GeminiClient::new(&api_key), &model, user_prompt, messages).await?;
+            (model, response)
+        }
+        Provider::OpenAi => {
+            let api_key = env::var("OPENAI_API_KEY")
+                .map_err(|_| "OPENAI_API_KEY environment variable not set".to_string())?;
+            let model = env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-4o".to_string());
+            info!("Using OpenAI model: {}", model);
+
+            let response = chat(OpenAiClient::new(&api_key), &model, user_prompt, messages).await?;
+            (model, response)
+        }
+    };
 
     // Get the response content
-    info!("Received response from Gemini");
+    info!("Received response from {:?}", provider);
+
+    let exchange = ModelExchange {
+        provider: provider.name().to_string(),
+        model,
+        employee: name.to_string(),
+        year,
+        markdown: markdown.to_string(),
+        response,
+    };
 
-    // Attempt to parse JSON from the response
-    parse_json_from_response(&response)
+    read_model_response(exchange)
 }
 
-/// Attempt to parse JSON from the model response
-fn parse_json_from_response(response: &str) -> Result<Vec<WorkDayExtraction>, String> {
-    // Try to extract JSON array from the text
-    if let Some(json_start) = response.find('[') {
-        if let Some(json_end) = response.rfind(']') {
-            if json_start < json_end {
-                let json_str = &response[json_start..=json_end];
-                match from_str::<Vec<WorkDayExtraction>>(json_str) {
-                    Ok(days) => return Ok(days),
-                    Err(e) => {
-                        error!("Failed to parse JSON from response: {}", e);
-                        error!("JSON string: {}", json_str);
-                    }
-                }
-            }
-        }
-    }
-
-    // Try to parse the entire response as JSON (in case it's already clean JSON)
-    match from_str::<Vec<WorkDayExtraction>>(response) {
-        Ok(days) => return Ok(days),
-        Err(e) => {
-            error!("Failed to parse entire response as JSON: {}", e);
-        }
-    }
+/// Send the prompt and image to a model and return its reply
+async fn chat<C: CompletionClient>(
+    client: C,
+    model: &str,
+    user_prompt: String,
+    messages: Vec<Message>,
+) -> Result<String, String> {
+    let agent = client
+        .agent(model)
+        .preamble(SYSTEM_PROMPT)
+        .temperature(0.0)
+        .build();
 
-    // Could not extract valid JSON
-    error!("Could not extract valid JSON from response: {}", response);
-    Err("Could not extract valid JSON from the model response".to_string())
+    agent
+        .chat(user_prompt, messages)
+        .await
+        .map_err(|e| format!("Rig API request failed: {e}"))
 }
@@ -0,0 +1,73 @@
+use crate::components::work_schedule::uploads::{PeriodIssue, UploadSummary};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::web::model::WorkSchedule;
+use crate::web::parser::Provider;
+use crate::web::preprocess::ImageFormat;
+
+/// How long a held upload waits for the uploader's confirmation
+const PENDING_UPLOAD_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Uploads held at once. The oldest is dropped to make room, since each holds its image.
+const MAX_PENDING_UPLOADS: usize = 20;
+
+/// A parsed upload held back until the uploader confirms it
+pub struct PendingUpload {
+    /// Display name of the employee the schedule is for
+    pub employee: String,
+    /// The preprocessed image
+    pub data: Vec<u8>,
+    pub format: ImageFormat,
+    pub provider: Provider,
+    /// Hash of the image contents
+    pub hash: String,
+    pub schedule: WorkSchedule,
+    pub issue: PeriodIssue,
+    pub summary: UploadSummary,
+}
+
+/// Uploads waiting for confirmation, kept in memory so an unconfirmed one is simply forgotten
+#[derive(Default)]
+pub struct PendingUploads {
+    uploads: Mutex<HashMap<String, (Instant, PendingUpload)>>,
+}
+
+impl PendingUploads {
+    /// Hold an upload, returning the id to confirm it with
+    pub fn hold(&self, upload: PendingUpload) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let mut uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        uploads.retain(|_, (held_at, _)| held_at.elapsed() < PENDING_UPLOAD_TTL);
+        if uploads.len() >= MAX_PENDING_UPLOADS {
+            let oldest = uploads
+                .iter()
+                .min_by_key(|(_, (held_at, _))| *held_at)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                uploads.remove(&oldest);
+            }
+        }
+        uploads.insert(id.clone(), (Instant::now(), upload));
+        id
+    }
+
+    /// What a held upload would store and why it was held
+    pub fn get(&self, id: &str) -> Option<(UploadSummary, PeriodIssue)> {
+        let uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        uploads
+            .get(id)
+            .filter(|(held_at, _)| held_at.elapsed() < PENDING_UPLOAD_TTL)
+            .map(|(_, upload)| (upload.summary.clone(), upload.issue))
+    }
+
+    /// Take a held upload to store it. Each upload can be taken once.
+    pub fn take(&self, id: &str) -> Option<PendingUpload> {
+        let mut uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        uploads
+            .remove(id)
+            .filter(|(held_at, _)| held_at.elapsed() < PENDING_UPLOAD_TTL)
+            .map(|(_, upload)| upload)
+    }
+}
@@ -0,0 +1,379 @@
+use image::ImageDecoder;
+use std::io::{self, BufRead, Cursor, Read, Seek};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Default cap on the pixels of an uploaded image, 40 megapixels
+pub const DEFAULT_MAX_IMAGE_PIXELS: u64 = 40_000_000;
+
+/// Image formats accepted for schedule uploads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Bmp,
+    WebP,
+}
+
+impl ImageFormat {
+    /// Detect the format from the file's signature bytes
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None; // Too small to be a valid image
+        }
+
+        match &data[0..4] {
+            // JPEG signature (0xFF 0xD8 0xFF)
+            [0xFF, 0xD8, 0xFF, ..] => Some(Self::Jpeg),
+
+            // PNG signature (0x89 'P' 'N' 'G')
+            [0x89, 0x50, 0x4E, 0x47] => Some(Self::Png),
+
+            // GIF signatures ('G' 'I' 'F' '8')
+            [0x47, 0x49, 0x46, 0x38] => Some(Self::Gif),
+
+            // BMP signature ('B' 'M')
+            [0x42, 0x4D, ..] => Some(Self::Bmp),
+
+            // WebP signature ('R' 'I' 'F' 'F' ... 'W' 'E' 'B' 'P')
+            [0x52, 0x49, 0x46, 0x46]
+                if data.len() >= 12 && data[8..12] == [0x57, 0x45, 0x42, 0x50] =>
+            {
+                Some(Self::WebP)
+            }
+
+            // Unknown format
+            _ => None,
+        }
+    }
+
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Gif => "gif",
+            Self::Bmp => "bmp",
+            Self::WebP => "webp",
+        }
+    }
+
+    /// The image crate's codec for the format
+    fn codec(&self) -> image::ImageFormat {
+        match self {
+            Self::Jpeg => image::ImageFormat::Jpeg,
+            Self::Png => image::ImageFormat::Png,
+            Self::Gif => image::ImageFormat::Gif,
+            Self::Bmp => image::ImageFormat::Bmp,
+            Self::WebP => image::ImageFormat::WebP,
+        }
+    }
+
+    /// MIME type for the format
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Bmp => "image/bmp",
+            Self::WebP => "image/webp",
+        }
+    }
+}
+
+/// Image work running at once unless `PREPROCESS_WORKERS` says otherwise: half the CPUs, so
+/// uploads can't take all of them from the requests served meanwhile
+pub fn default_preprocess_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, |cpus| (cpus.get() / 2).max(1))
+}
+
+/// Runs the synchronous image work of uploads on tokio's blocking threads, a bounded number
+/// at a time, so it doesn't stall the async runtime serving other requests
+pub struct PreprocessPool {
+    workers: Arc<Semaphore>,
+}
+
+impl PreprocessPool {
+    /// Pool running at most `workers` jobs at once
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+        }
+    }
+
+    /// Pool sized from `PREPROCESS_WORKERS`
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("PREPROCESS_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(default_preprocess_workers),
+        )
+    }
+
+    /// Run `job` once a worker is free.
+    ///
+    /// When the request is dropped, e.g. because the uploader went away, a job that hasn't
+    /// started yet is skipped. One already running can't be interrupted and keeps its worker
+    /// until it returns, so split long work into several jobs.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let worker = self
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| format!("Preprocessing pool closed: {e}"))?;
+        let abandoned = CancelOnDrop::default();
+        let cancelled = abandoned.0.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let _worker = worker;
+            (!cancelled.load(Ordering::Relaxed)).then(job)
+        })
+        .await;
+
+        match result {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err("Preprocessing cancelled".to_string()),
+            Err(e) => Err(format!("Preprocessing failed: {e}")),
+        }
+    }
+}
+
+/// Flag set when the future waiting for a job is dropped before the job ends
+#[derive(Default)]
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Longest side of an image sent to the parser; a printed schedule stays legible well below it
+pub const MAX_PARSE_EDGE: u32 = 3000;
+
+/// Prepare an uploaded image for the parser, returning the bytes to send and their format.
+///
+/// Phone photos are turned upright by their EXIF orientation and shrunk to [`MAX_PARSE_EDGE`],
+/// and then re-encoded as JPEG, as are formats other than JPEG and PNG. An image needing none
+/// of that is passed on unchanged.
+pub fn preprocess_image(mut reader: impl BufRead + Seek) -> Result<(Vec<u8>, ImageFormat), String> {
+    let format = detect_format(&mut reader)?;
+    let mut decoder = image::ImageReader::with_format(&mut reader, format.codec())
+        .into_decoder()
+        .map_err(|e| format!("Failed to read image: {e}"))?;
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image =
+        image::DynamicImage::from_decoder(decoder).map_err(|e| format!("Invalid image: {e}"))?;
+
+    let upright = orientation == image::metadata::Orientation::NoTransforms;
+    let oversized = image.width().max(image.height()) > MAX_PARSE_EDGE;
+    if upright && !oversized && matches!(format, ImageFormat::Jpeg | ImageFormat::Png) {
+        let mut data = Vec::new();
+        reader
+            .rewind()
+            .and_then(|()| reader.read_to_end(&mut data))
+            .map_err(|e| format!("Failed to read image: {e}"))?;
+        return Ok((data, format));
+    }
+
+    image.apply_orientation(orientation);
+    if oversized {
+        image = image.resize(
+            MAX_PARSE_EDGE,
+            MAX_PARSE_EDGE,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+    let mut jpeg = Vec::new();
+    image::DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Failed to re-encode image: {e}"))?;
+    Ok((jpeg, ImageFormat::Jpeg))
+}
+
+/// Read the width and height of an image from its header, without decoding the pixels.
+///
+/// Returns the format too, detected from the same header.
+pub fn image_dimensions(
+    mut reader: impl BufRead + Seek,
+) -> Result<(ImageFormat, u32, u32), String> {
+    let format = detect_format(&mut reader)?;
+    let (width, height) = image::ImageReader::with_format(reader, format.codec())
+        .into_dimensions()
+        .map_err(|e| format!("Invalid {} header: {e}", format.extension()))?;
+    Ok((format, width, height))
+}
+
+/// Detect the format from the reader's first bytes, leaving it back at the start
+fn detect_format(reader: &mut (impl Read + Seek)) -> Result<ImageFormat, String> {
+    let mut head = [0u8; 12];
+    let mut read = 0;
+    while read < head.len() {
+        match reader.read(&mut head[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(format!("Failed to read image: {e}")),
+        }
+    }
+    reader
+        .rewind()
+        .map_err(|e| format!("Failed to read image: {e}"))?;
+    ImageFormat::detect(&head[..read]).ok_or_else(|| "Unsupported image format".to_string())
+}
+
+/// PNG signature, IHDR chunk and an empty IDAT chunk of an image of the given size, without
+/// any pixel data
+#[cfg(test)]
+pub(crate) fn png_header(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut chunk = |kind: &[u8], data: &[u8]| {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32fast::hash(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    };
+    let mut ihdr = [width.to_be_bytes(), height.to_be_bytes()].concat();
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(b"IHDR", &ihdr);
+    chunk(b"IDAT", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_dimensions_from_headers() {
+        assert_eq!(
+            image_dimensions(Cursor::new(&png_header(20_000, 20_000)[..])),
+            Ok((ImageFormat::Png, 20_000, 20_000))
+        );
+
+        for (format, codec) in [
+            (ImageFormat::Jpeg, image::ImageFormat::Jpeg),
+            (ImageFormat::Gif, image::ImageFormat::Gif),
+            (ImageFormat::Bmp, image::ImageFormat::Bmp),
+            (ImageFormat::WebP, image::ImageFormat::WebP),
+        ] {
+            assert_eq!(
+                image_dimensions(Cursor::new(encoded(80, 60, codec))),
+                Ok((format, 80, 60))
+            );
+        }
+
+        // Headers cut short aren't guessed at
+        assert!(image_dimensions(Cursor::new(&png_header(10, 10)[..24])).is_err());
+        let jpeg = encoded(80, 60, image::ImageFormat::Jpeg);
+        assert!(image_dimensions(Cursor::new(&jpeg[..12])).is_err());
+        assert!(image_dimensions(Cursor::new(&b"definitely not an image"[..])).is_err());
+    }
+
+    #[test]
+    fn test_detect_image_format() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::detect(b"RIFF\0\0\0\0WAVEfmt "), None);
+        assert_eq!(ImageFormat::detect(b"\xFF\xD8\xFF"), None);
+        assert!(preprocess_image(Cursor::new(b"definitely not an image")).is_err());
+    }
+
+    /// Encode a blank image of the given size
+    fn encoded(width: u32, height: u32, format: image::ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::DynamicImage::new_rgb8(width, height)
+            .write_to(&mut Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_preprocess_image() {
+        // Small enough and already in a format the parser takes
+        let png = encoded(40, 30, image::ImageFormat::Png);
+        assert_eq!(
+            preprocess_image(Cursor::new(&png)),
+            Ok((png.clone(), ImageFormat::Png))
+        );
+
+        // Other formats are re-encoded
+        let bmp = encoded(40, 30, image::ImageFormat::Bmp);
+        let (jpeg, format) = preprocess_image(Cursor::new(&bmp)).unwrap();
+        assert_eq!(format, ImageFormat::Jpeg);
+        assert_eq!(
+            image_dimensions(Cursor::new(&jpeg[..])),
+            Ok((ImageFormat::Jpeg, 40, 30))
+        );
+
+        // Large images are shrunk, keeping their aspect ratio
+        let large = encoded(MAX_PARSE_EDGE * 2, 20, image::ImageFormat::Png);
+        let (jpeg, format) = preprocess_image(Cursor::new(&large)).unwrap();
+        assert_eq!(format, ImageFormat::Jpeg);
+        assert_eq!(
+            image_dimensions(Cursor::new(&jpeg[..])),
+            Ok((ImageFormat::Jpeg, MAX_PARSE_EDGE, 10))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_bounds_jobs_and_skips_abandoned_ones() {
+        let pool = Arc::new(PreprocessPool::new(1));
+        let running = Arc::new(AtomicBool::new(false));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let job = |running: Arc<AtomicBool>, overlapped: Arc<AtomicBool>| {
+            move || {
+                if running.swap(true, Ordering::SeqCst) {
+                    overlapped.store(true, Ordering::SeqCst);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                running.store(false, Ordering::SeqCst);
+            }
+        };
+        let (first, second) = tokio::join!(
+            pool.run(job(running.clone(), overlapped.clone())),
+            pool.run(job(running.clone(), overlapped.clone())),
+        );
+        assert!(first.is_ok() && second.is_ok());
+        assert!(!overlapped.load(Ordering::SeqCst));
+
+        // A request dropped while waiting for the busy worker never starts its job
+        let started = Arc::new(AtomicBool::new(false));
+        let busy = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(|| std::thread::sleep(std::time::Duration::from_millis(100)))
+                    .await
+            }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let waiting = {
+            let started = started.clone();
+            pool.run(move || started.store(true, Ordering::SeqCst))
+        };
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), waiting)
+                .await
+                .is_err()
+        );
+        busy.await.unwrap().unwrap();
+        assert!(!started.load(Ordering::SeqCst));
+    }
+}
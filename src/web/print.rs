@@ -0,0 +1,163 @@
+use crate::utils::time::get_weekly_date_range;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Html,
+    Extension,
+};
+use chrono::{Duration, Local, NaiveDate};
+use serde::Deserialize;
+
+use crate::web::auth::JwtAuth;
+use crate::web::feed::{build_feed, load_schedules, DayType, Feed, FeedDay};
+use crate::web::render::html_escape;
+use crate::web::AppState;
+
+/// Styles for the printed page: large text, borders that survive printing and one week per page
+const PRINT_STYLE: &str = "\
+@page { size: A4 landscape; margin: 1cm; }
+body { font-family: sans-serif; font-size: 14pt; color: #000; background: #fff; }
+h1 { font-size: 20pt; margin: 0 0 0.5em; }
+table { width: 100%; border-collapse: collapse; table-layout: fixed; }
+th, td { border: 1px solid #000; padding: 0.3em; text-align: center; vertical-align: top; }
+th:first-child, td:first-child { text-align: left; }
+td.off { font-style: italic; }
+.note { font-size: 10pt; }
+section.week { break-after: page; page-break-after: always; }
+section.week table { break-inside: avoid; page-break-inside: avoid; }
+footer { margin-top: 0.5em; font-size: 10pt; }";
+
+/// Query parameters of the print view
+#[derive(Debug, Deserialize)]
+pub struct PrintQuery {
+    /// First date of the week (YYYY-MM-DD), the current week by default
+    start: Option<String>,
+    /// Whether day notes are printed
+    notes: Option<bool>,
+}
+
+/// Render one employee's day as a table cell. Days without an entry stay blank.
+fn render_cell(day: &FeedDay, include_notes: bool) -> String {
+    let (class, value) = match day.day_type {
+        DayType::Work => (
+            "work",
+            day.shifts
+                .iter()
+                .map(|shift| {
+                    format!(
+                        "{}–{}",
+                        shift.start.as_deref().unwrap_or("?"),
+                        shift.end.as_deref().unwrap_or("?")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("<br>"),
+        ),
+        DayType::Off => ("off", "Off".to_string()),
+        DayType::Unscheduled => ("blank", String::new()),
+    };
+    let note = match day.notes.as_deref().filter(|_| include_notes) {
+        Some(note) if !note.trim().is_empty() => {
+            format!("<div class=\"note\">{}</div>", html_escape(note))
+        }
+        _ => String::new(),
+    };
+    format!("<td class=\"{class}\">{value}{note}</td>")
+}
+
+/// Render the week of the feed as a printable HTML page
+pub fn render_print_week(feed: &Feed, include_notes: bool, generated_at: &str) -> String {
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+    let dates: Vec<NaiveDate> = match (parse(&feed.start_date), parse(&feed.end_date)) {
+        (Some(start), Some(end)) => start.iter_days().take_while(|date| *date <= end).collect(),
+        _ => Vec::new(),
+    };
+    let title = match (dates.first(), dates.last()) {
+        (Some(first), Some(last)) => format!(
+            "Work schedule {} – {}",
+            first.format("%d.%m."),
+            last.format("%d.%m.%Y")
+        ),
+        _ => "Work schedule".to_string(),
+    };
+
+    let header: String = dates
+        .iter()
+        .map(|date| format!("<th>{}</th>", date.format("%a %d.%m.")))
+        .collect();
+    let rows: String = feed
+        .employees
+        .iter()
+        .map(|employee| {
+            let cells: String = employee
+                .days
+                .iter()
+                .map(|day| render_cell(day, include_notes))
+                .collect();
+            format!(
+                "<tr><th scope=\"row\">{}</th>{cells}</tr>\n",
+                html_escape(&employee.name)
+            )
+        })
+        .collect();
+    let coverage = match feed.coverage_through.as_deref().and_then(parse) {
+        Some(date) => format!("Schedules cover dates through {}", date.format("%d.%m.%Y")),
+        None => "Some employees have no schedule data".to_string(),
+    };
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+{PRINT_STYLE}
+</style>
+</head>
+<body>
+<section class=\"week\">
+<h1>{title}</h1>
+<table>
+<thead><tr><th>Employee</th>{header}</tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+<footer>Generated {generated_at} · {coverage}</footer>
+</section>
+</body>
+</html>
+"
+    )
+}
+
+/// Printable table of a week's schedules for every employee
+pub async fn print_week_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Query(query): Query<PrintQuery>,
+) -> Result<Html<String>, StatusCode> {
+    if !auth.claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let start = match query.start.as_deref() {
+        Some(start) => {
+            NaiveDate::parse_from_str(start, "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST)?
+        }
+        None => {
+            let (start, _) = get_weekly_date_range(&Local::now(), state.week_start);
+            NaiveDate::parse_from_str(&start, "%Y-%m-%d")
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        }
+    };
+    let end = start + Duration::days(6);
+
+    let feed = build_feed(load_schedules(&state).await?, start, end);
+    let generated_at = Local::now().format("%d.%m.%Y %H:%M").to_string();
+    Ok(Html(render_print_week(
+        &feed,
+        query.notes.unwrap_or(true),
+        &generated_at,
+    )))
+}
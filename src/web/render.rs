@@ -0,0 +1,180 @@
+use crate::components::work_schedule::stats::HoursBudget;
+use crate::web::model::{WorkDay, WorkSchedule};
+use chrono::NaiveDate;
+
+/// Escape text for safe inclusion in HTML
+pub fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Datalist for the upload form's name input, filled from the suggestion endpoint as the user
+/// types
+pub fn render_name_suggestions(max_query_len: usize) -> String {
+    format!(
+        r#"<datalist id="employee-suggestions"></datalist>
+                    <script>
+                        (() => {{
+                            const input = document.getElementById('name');
+                            const list = document.getElementById('employee-suggestions');
+                            let timer;
+                            input.addEventListener('input', () => {{
+                                clearTimeout(timer);
+                                timer = setTimeout(async () => {{
+                                    const query = input.value.trim().slice(0, {max_query_len});
+                                    if (!query) {{
+                                        list.replaceChildren();
+                                        return;
+                                    }}
+                                    const response = await fetch('/api/v1/employees/suggest?q=' + encodeURIComponent(query));
+                                    if (!response.ok) {{
+                                        return;
+                                    }}
+                                    const names = await response.json();
+                                    list.replaceChildren(...names.map((name) => {{
+                                        const option = document.createElement('option');
+                                        option.value = name;
+                                        return option;
+                                    }}));
+                                }}, 200);
+                            }});
+                        }})();
+                    </script>"#
+    )
+}
+
+/// Render a single day as a colored chip
+fn render_day_chip(day: &WorkDay) -> String {
+    let label = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+        .map(|date| date.format("%a %d.%m.").to_string())
+        .unwrap_or_else(|_| day.date.clone());
+
+    let (classes, value) = if day.is_day_off {
+        ("bg-red-900 text-red-200", "Off".to_string())
+    } else {
+        let hours: Option<Vec<String>> = day
+            .shifts
+            .iter()
+            .map(|shift| Some(format!("{}-{}", shift.start.as_ref()?, shift.end.as_ref()?)))
+            .collect();
+        let break_note = day
+            .break_minutes
+            .map(|minutes| format!(" ({minutes} min break)"))
+            .unwrap_or_default();
+        match hours.filter(|hours| !hours.is_empty()) {
+            Some(hours) => (
+                "bg-green-900 text-green-200",
+                hours.join(", ") + &break_note,
+            ),
+            None => (
+                "bg-gray-700 text-gray-300",
+                day.notes.clone().unwrap_or_else(|| "-".to_string()),
+            ),
+        }
+    };
+
+    // Links come from the bot, but only ever render web links
+    let context = day
+        .context_link
+        .as_ref()
+        .filter(|link| link.url.starts_with("https://"))
+        .map(|link| {
+            format!(
+                " <a href=\"{}\" title=\"{}\" target=\"_blank\" rel=\"noopener\" class=\"underline\">📎 context</a>",
+                html_escape(&link.url),
+                html_escape(&link.excerpt)
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        "<span class=\"{classes} text-xs px-2 py-1 rounded\">{}: {}{context}</span>",
+        html_escape(&label),
+        html_escape(&value)
+    )
+}
+
+/// Weekly totals against the employee's contract hours for the days shown, if they have any
+fn render_week_totals(schedule: &WorkSchedule, days: &[&WorkDay], budget: &HoursBudget) -> String {
+    let dates: Vec<NaiveDate> = days
+        .iter()
+        .filter_map(|day| NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").ok())
+        .collect();
+    let (Some(start), Some(end)) = (dates.iter().min(), dates.iter().max()) else {
+        return String::new();
+    };
+
+    let entries: Vec<_> = days.iter().map(|day| day.to_entry()).collect();
+    let summaries = budget.week_summaries(&schedule.employee_name, &entries, *start, *end);
+    if summaries.is_empty() {
+        return String::new();
+    }
+
+    let lines: String = summaries
+        .iter()
+        .map(|summary| {
+            format!(
+                "        <p class=\"text-sm text-gray-300\">{}</p>\n",
+                html_escape(summary)
+            )
+        })
+        .collect();
+    format!("\n    <div class=\"mt-2\">\n{lines}    </div>")
+}
+
+/// Render an employee schedule card, optionally only including days on or after `from_date`.
+///
+/// With a budget the card also shows each week's hours against the employee's contract.
+pub fn render_schedule_card(
+    schedule: &WorkSchedule,
+    from_date: Option<NaiveDate>,
+    budget: Option<&HoursBudget>,
+) -> String {
+    let mut days: Vec<&WorkDay> = schedule
+        .days
+        .iter()
+        .filter(|day| match from_date {
+            Some(from) => NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+                .map(|date| date >= from)
+                .unwrap_or(false),
+            None => true,
+        })
+        .collect();
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+
+    let totals = budget
+        .map(|budget| render_week_totals(schedule, &days, budget))
+        .unwrap_or_default();
+    let chips = if days.is_empty() {
+        "<span class=\"text-sm text-gray-400\">No upcoming shifts</span>".to_string()
+    } else {
+        days.into_iter()
+            .map(render_day_chip)
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        r#"<div class="border border-gray-700 rounded-md p-4 hover:bg-gray-700">
+    <div class="flex justify-between items-center">
+        <h3 class="font-medium text-gray-100">{}</h3>
+        <span class="text-sm text-gray-400">Last updated: {}</span>
+    </div>
+    <div class="mt-2 flex flex-wrap gap-2">
+{chips}
+    </div>{totals}
+</div>"#,
+        html_escape(&schedule.employee_name),
+        schedule.last_updated.format("%d.%m.%Y %H:%M")
+    )
+}
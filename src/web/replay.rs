@@ -0,0 +1,405 @@
+//! Converting the model's stored days of earlier uploads again with the current code.
+//!
+//! A change to what a cell means, such as a code becoming a day type, otherwise only applies to
+//! schedules uploaded after it. Only an employee's latest upload is replayed, since the days of
+//! earlier ones have been replaced by it. Actual hours and context links recorded for a day are
+//! kept.
+
+use crate::components::work_schedule::audit::AuditRecord;
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::redact::Redacted;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::{error, info};
+
+use crate::web::auth::JwtAuth;
+use crate::web::locks::EmployeeLocks;
+use crate::web::model::{StoredExtraction, WorkDay, WorkDayExtraction, WorkHoursDb, WorkSchedule};
+use crate::web::parser::convert_to_work_schedule;
+use crate::web::validation::validate_schedule;
+use crate::web::AppState;
+
+/// Audit source of days rewritten by a replay
+const AUDIT_SOURCE: &str = "replay";
+
+/// Converts the model's days into a schedule, [`convert_to_work_schedule`] outside tests
+pub type Convert = fn(&str, Vec<WorkDayExtraction>) -> Result<WorkSchedule, String>;
+
+/// Which uploads to replay
+#[derive(Debug, Default, Deserialize)]
+pub struct ReplayFilter {
+    /// Only this employee's upload
+    pub employee: Option<String>,
+    /// Only uploads made on or after this date
+    pub since: Option<NaiveDate>,
+}
+
+impl ReplayFilter {
+    fn matches(&self, extraction: &StoredExtraction) -> bool {
+        let employee = self.employee.as_deref().is_none_or(|employee| {
+            EmployeeId::new(employee) == EmployeeId::new(&extraction.employee)
+        });
+        let since = self.since.is_none_or(|since| {
+            DateTime::from_timestamp(extraction.extracted_at, 0)
+                .is_some_and(|at| at.date_naive() >= since)
+        });
+        employee && since
+    }
+}
+
+/// A day the current code reads differently, as shown before and after
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayChange {
+    pub date: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// What replaying an upload found
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UploadReplay {
+    pub upload_id: String,
+    pub employee: String,
+    pub changes: Vec<DayChange>,
+    /// Validation issues of the converted days
+    pub issues: Vec<String>,
+    /// Why the upload wasn't replayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+/// What a replay found, and whether it was written
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayReport {
+    pub dry_run: bool,
+    pub uploads: Vec<UploadReplay>,
+}
+
+/// A day as the report shows it
+fn describe(days: &[&WorkDay]) -> Option<String> {
+    if days.is_empty() {
+        return None;
+    }
+    let described: Vec<String> = days
+        .iter()
+        .map(|day| {
+            let hours = day.to_entry().format();
+            match &day.notes {
+                Some(note) => format!("{hours} · {note}"),
+                None => hours,
+            }
+        })
+        .collect();
+    Some(described.join(" / "))
+}
+
+fn by_date(days: &[WorkDay]) -> BTreeMap<&str, Vec<&WorkDay>> {
+    let mut by_date: BTreeMap<&str, Vec<&WorkDay>> = BTreeMap::new();
+    for day in days {
+        by_date.entry(&day.date).or_default().push(day);
+    }
+    by_date
+}
+
+/// Days of the stored schedule converted again, keeping what was recorded for them since
+fn convert_again(
+    stored: &WorkSchedule,
+    extraction: &StoredExtraction,
+    convert: Convert,
+) -> Result<(Vec<WorkDay>, Vec<String>), String> {
+    let replayed = convert(&extraction.employee, extraction.days.clone())?;
+    let report = validate_schedule(&extraction.days, &replayed, None, None);
+    let issues = report.issues.iter().map(ToString::to_string).collect();
+
+    let kept = by_date(&stored.days);
+    let days = replayed
+        .days
+        .into_iter()
+        .map(|mut day| {
+            if let Some(before) = kept.get(day.date.as_str()).and_then(|days| days.first()) {
+                day.actual_start = before.actual_start.clone();
+                day.actual_end = before.actual_end.clone();
+                day.context_link = before.context_link.clone();
+            }
+            day
+        })
+        .collect();
+    Ok((days, issues))
+}
+
+/// Replay an upload against the employee's stored schedule, writing the changes unless it's a
+/// dry run
+async fn replay_upload(
+    db: &dyn WorkHoursDb,
+    extraction: &StoredExtraction,
+    convert: Convert,
+    dry_run: bool,
+    actor: &str,
+) -> Result<UploadReplay, String> {
+    let mut replay = UploadReplay {
+        upload_id: extraction.upload_id.clone(),
+        employee: extraction.employee.clone(),
+        changes: Vec::new(),
+        issues: Vec::new(),
+        skipped: None,
+    };
+    let stored = db.get_schedule(&extraction.employee).await?;
+    let Some(mut stored) =
+        stored.filter(|stored| stored.upload_id.as_deref() == Some(extraction.upload_id.as_str()))
+    else {
+        replay.skipped = Some("replaced by a later upload".to_string());
+        return Ok(replay);
+    };
+    let (days, issues) = match convert_again(&stored, extraction, convert) {
+        Ok(converted) => converted,
+        Err(e) => {
+            replay.skipped = Some(e);
+            return Ok(replay);
+        }
+    };
+    replay.issues = issues;
+
+    let (before, after) = (by_date(&stored.days), by_date(&days));
+    let mut dates: Vec<&str> = before.keys().chain(after.keys()).copied().collect();
+    dates.sort_unstable();
+    dates.dedup();
+    let mut records = Vec::new();
+    let at = Utc::now().timestamp();
+    for date in dates {
+        let (old, new) = (before.get(date), after.get(date));
+        if old == new {
+            continue;
+        }
+        replay.changes.push(DayChange {
+            date: date.to_string(),
+            before: old.and_then(|days| describe(days)),
+            after: new.and_then(|days| describe(days)),
+        });
+        records.push(AuditRecord {
+            seq: 0,
+            at,
+            employee: stored.employee_name.clone(),
+            date: date.to_string(),
+            before: old.and_then(|days| days.first()).map(|day| day.to_entry()),
+            after: new.and_then(|days| days.first()).map(|day| day.to_entry()),
+            source: AUDIT_SOURCE.to_string(),
+            actor: Some(actor.to_string()),
+        });
+    }
+    if dry_run || replay.changes.is_empty() {
+        return Ok(replay);
+    }
+
+    let removed: Vec<String> = replay
+        .changes
+        .iter()
+        .filter(|change| change.after.is_none())
+        .map(|change| change.date.clone())
+        .collect();
+    stored.days = days;
+    stored.last_updated = Utc::now();
+    db.set_schedule(&extraction.employee, &stored).await?;
+    if !removed.is_empty() {
+        db.remove_days(&extraction.employee, &removed).await?;
+    }
+    db.record_audit(records).await?;
+    Ok(replay)
+}
+
+/// Replay the kept uploads `filter` selects, oldest first
+pub async fn replay(
+    db: &dyn WorkHoursDb,
+    locks: &EmployeeLocks,
+    filter: &ReplayFilter,
+    convert: Convert,
+    dry_run: bool,
+    actor: &str,
+) -> Result<ReplayReport, String> {
+    let mut uploads = Vec::new();
+    for extraction in db.list_extractions().await? {
+        if !filter.matches(&extraction) {
+            continue;
+        }
+        // Don't interleave with an upload for the same employee
+        let _guard = locks.lock(&EmployeeId::new(&extraction.employee)).await;
+        uploads.push(replay_upload(db, &extraction, convert, dry_run, actor).await?);
+    }
+    Ok(ReplayReport { dry_run, uploads })
+}
+
+/// Query parameters of a replay
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    #[serde(flatten)]
+    filter: ReplayFilter,
+    /// Only report what would change
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Handler replaying kept uploads with the current conversion (admin only)
+pub async fn replay_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Query(query): Query<ReplayQuery>,
+) -> Response {
+    if !auth.claims.is_admin() {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    match replay(
+        state.db.as_ref(),
+        &state.upload_locks,
+        &query.filter,
+        convert_to_work_schedule,
+        query.dry_run,
+        &auth.claims.sub,
+    )
+    .await
+    {
+        Ok(report) => {
+            info!(
+                "Replayed {} uploads{}",
+                report.uploads.len(),
+                if report.dry_run { " as a dry run" } else { "" }
+            );
+            Json(report).into_response()
+        }
+        Err(e) => {
+            error!("Failed to replay uploads: {}", Redacted(&e));
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::model::InMemoryDb;
+
+    /// The conversion as it would be with `vp` read as a day off rather than as a note
+    fn vp_as_day_off(employee: &str, days: Vec<WorkDayExtraction>) -> Result<WorkSchedule, String> {
+        let mut schedule = convert_to_work_schedule(employee, days)?;
+        for day in &mut schedule.days {
+            if day.notes.as_deref() == Some("vp") {
+                day.is_day_off = true;
+            }
+        }
+        Ok(schedule)
+    }
+
+    fn extraction(upload_id: &str, extracted_at: i64, cells: &[(&str, &str)]) -> StoredExtraction {
+        StoredExtraction {
+            upload_id: upload_id.to_string(),
+            employee: "Anna".to_string(),
+            extracted_at,
+            days: cells
+                .iter()
+                .map(|(date, work_hours)| WorkDayExtraction {
+                    date: date.to_string(),
+                    work_hours: work_hours.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Store an upload the way the upload handler does
+    async fn upload(db: &InMemoryDb, extraction: &StoredExtraction) {
+        let mut schedule = convert_to_work_schedule("Anna", extraction.days.clone()).unwrap();
+        schedule.upload_id = Some(extraction.upload_id.clone());
+        db.set_schedule("Anna", &schedule).await.unwrap();
+        db.record_extraction(extraction).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_applies_a_changed_day_type() {
+        let db = InMemoryDb::default();
+        let locks = EmployeeLocks::default();
+        let cells = [
+            ("2025-03-10", "7-15"),
+            ("2025-03-11", "vp"),
+            ("2025-03-12", "x"),
+        ];
+        // An older upload, replaced by the latest one
+        upload(&db, &extraction("anna-1", 1_741_000_000, &cells[..1])).await;
+        upload(&db, &extraction("anna-2", 1_741_500_000, &cells)).await;
+
+        // Hours recorded after the upload are kept
+        let mut stored = db.get_schedule("Anna").await.unwrap().unwrap();
+        stored.days[1].actual_start = Some("08:00".to_string());
+        stored.days[1].actual_end = Some("12:00".to_string());
+        db.set_schedule("Anna", &stored).await.unwrap();
+
+        let all = ReplayFilter::default();
+        let report = replay(&db, &locks, &all, vp_as_day_off, true, "admin")
+            .await
+            .unwrap();
+        assert_eq!(report.uploads.len(), 2);
+        assert_eq!(
+            report.uploads[0].skipped.as_deref(),
+            Some("replaced by a later upload")
+        );
+        let latest = &report.uploads[1];
+        assert_eq!(latest.upload_id, "anna-2");
+        assert_eq!(latest.skipped, None);
+        assert_eq!(
+            latest.changes,
+            [DayChange {
+                date: "2025-03-11".to_string(),
+                before: Some("No scheduled hours · vp".to_string()),
+                after: Some("Day off · vp".to_string()),
+            }]
+        );
+        assert!(latest.issues.iter().any(|issue| issue.contains("vp")));
+
+        // A dry run leaves everything as it was
+        let stored = db.get_schedule("Anna").await.unwrap().unwrap();
+        assert!(!stored.days[1].is_day_off);
+        assert!(db.audit_records().await.is_empty());
+
+        let report = replay(&db, &locks, &all, vp_as_day_off, false, "admin")
+            .await
+            .unwrap();
+        assert_eq!(report.uploads[1].changes.len(), 1);
+        let stored = db.get_schedule("Anna").await.unwrap().unwrap();
+        assert_eq!(stored.upload_id.as_deref(), Some("anna-2"));
+        assert!(stored.days[1].is_day_off);
+        assert_eq!(stored.days[1].actual_start.as_deref(), Some("08:00"));
+        assert!(!stored.days[0].is_day_off);
+        let audit = db.audit_records().await;
+        assert_eq!(audit.len(), 1);
+        assert_eq!(
+            (audit[0].date.as_str(), audit[0].source.as_str()),
+            ("2025-03-11", "replay")
+        );
+
+        // Replaying again finds nothing left to change
+        let report = replay(&db, &locks, &all, vp_as_day_off, false, "admin")
+            .await
+            .unwrap();
+        assert!(report.uploads[1].changes.is_empty());
+        assert_eq!(db.audit_records().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_filter() {
+        let filter = ReplayFilter {
+            employee: Some("anna".to_string()),
+            since: NaiveDate::from_ymd_opt(2025, 3, 5),
+        };
+        // 2025-03-05 12:00 UTC
+        assert!(filter.matches(&extraction("anna-1", 1_741_176_000, &[])));
+        // A day earlier
+        assert!(!filter.matches(&extraction("anna-1", 1_741_089_600, &[])));
+        let mut other = extraction("pekka-1", 1_741_176_000, &[]);
+        other.employee = "Pekka".to_string();
+        assert!(!filter.matches(&other));
+        assert!(ReplayFilter::default().matches(&other));
+    }
+}
@@ -0,0 +1,700 @@
+use crate::components::work_schedule::actuals::{
+    check_actuals_date, parse_actual_times, ActualsRefusal, ACTUALS_AUDIT_SOURCE,
+};
+use crate::components::work_schedule::audit::AuditRecord;
+use crate::components::work_schedule::models::ShiftRange;
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::redact::Redacted;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::{error, info};
+
+use crate::web::auth::JwtAuth;
+use crate::web::handlers::authorize_employee_access;
+use crate::web::import::import_time;
+use crate::web::model::{WorkDay, WorkHoursDb, WorkSchedule};
+use crate::web::AppState;
+
+/// Most days a single import may contain, and the longest span they may cover
+pub const MAX_IMPORT_DAYS: i64 = 90;
+
+/// Audit source of days written by the JSON import
+const AUDIT_SOURCE: &str = "json_import";
+
+/// Query parameters of the export
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// First date to export (YYYY-MM-DD), from the start of the schedule when left out
+    from: Option<String>,
+    /// Last date to export (YYYY-MM-DD), to the end of the schedule when left out
+    to: Option<String>,
+}
+
+/// How imported days are combined with the stored ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Replace the stored days the payload has and keep the rest
+    #[default]
+    Merge,
+    /// Make the payload's date range match the payload, removing stored days it lacks
+    Overwrite,
+}
+
+/// Query parameters of the import
+#[derive(Debug, Deserialize)]
+pub struct ScheduleImportQuery {
+    #[serde(default)]
+    mode: ImportMode,
+}
+
+/// What a JSON import changed, or why it was rejected
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ScheduleImportReport {
+    pub mode: ImportMode,
+    /// Dates that had no entry before
+    pub added: Vec<String>,
+    /// Dates whose entry was replaced with a different one
+    pub changed: Vec<String>,
+    /// Dates removed by an overwrite
+    pub removed: Vec<String>,
+    /// Days in the payload identical to the stored ones
+    pub unchanged: usize,
+    /// Why the payload was rejected; nothing is written when there are any
+    pub errors: Vec<String>,
+}
+
+/// Read an optional YYYY-MM-DD query date
+fn query_date(date: Option<&str>) -> Result<Option<NaiveDate>, StatusCode> {
+    date.map(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d"))
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Normalize a shift to HH:MM times, rejecting shifts without either time
+fn validate_shift(shift: &ShiftRange) -> Result<ShiftRange, String> {
+    if shift.start.is_none() && shift.end.is_none() {
+        return Err("shift has neither a start nor an end".to_string());
+    }
+    let time = |time: &Option<String>| time.as_deref().map(import_time).transpose();
+    Ok(ShiftRange {
+        start: time(&shift.start)?,
+        end: time(&shift.end)?,
+    })
+}
+
+/// Check an import payload and normalize its days, ordered by date. Every problem found is
+/// returned, each prefixed with the date it concerns.
+pub fn validate_import(days: Vec<WorkDay>) -> Result<Vec<WorkDay>, Vec<String>> {
+    if days.is_empty() {
+        return Err(vec!["the payload has no days".to_string()]);
+    }
+    if days.len() as i64 > MAX_IMPORT_DAYS {
+        return Err(vec![format!(
+            "the payload has {} days, at most {MAX_IMPORT_DAYS} are allowed",
+            days.len()
+        )]);
+    }
+
+    let mut errors = Vec::new();
+    let mut by_date: BTreeMap<NaiveDate, WorkDay> = BTreeMap::new();
+    for mut day in days {
+        let date = match NaiveDate::parse_from_str(day.date.trim(), "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                errors.push(format!(
+                    "{}: invalid date, expected YYYY-MM-DD",
+                    day.date.trim()
+                ));
+                continue;
+            }
+        };
+        day.date = date.format("%Y-%m-%d").to_string();
+
+        match day.shifts.iter().map(validate_shift).collect() {
+            Ok(shifts) => day.shifts = shifts,
+            Err(e) => errors.push(format!("{}: {e}", day.date)),
+        }
+        if day.is_day_off && !day.shifts.is_empty() {
+            errors.push(format!("{}: a day off can't have shifts", day.date));
+        }
+        if by_date.contains_key(&date) {
+            errors.push(format!("{}: the date appears more than once", day.date));
+        }
+        by_date.insert(date, day);
+    }
+
+    if let (Some(first), Some(last)) = (by_date.keys().next(), by_date.keys().next_back()) {
+        let span = (*last - *first).num_days() + 1;
+        if span > MAX_IMPORT_DAYS {
+            errors.push(format!(
+                "the days span {span} days from {first} to {last}, at most {MAX_IMPORT_DAYS} are allowed"
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(by_date.into_values().collect())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Apply validated days to a stored schedule, keeping the actual hours recorded for them.
+/// Returns the audit records of what changed, with the report filled in; the records are
+/// numbered when they're written.
+fn apply_import(
+    schedule: &mut WorkSchedule,
+    days: Vec<WorkDay>,
+    mode: ImportMode,
+    actor: &str,
+    report: &mut ScheduleImportReport,
+) -> Vec<AuditRecord> {
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Vec::new();
+    };
+    let (first, last) = (first.date.clone(), last.date.clone());
+
+    let mut stored: BTreeMap<String, WorkDay> = schedule
+        .days
+        .drain(..)
+        .map(|day| (day.date.clone(), day))
+        .collect();
+    let mut changes: Vec<(String, Option<WorkDay>, Option<WorkDay>)> = Vec::new();
+
+    if mode == ImportMode::Overwrite {
+        let missing: Vec<String> = stored
+            .range(first..=last)
+            .map(|(date, _)| date.clone())
+            .filter(|date| !days.iter().any(|day| &day.date == date))
+            .collect();
+        for date in missing {
+            let before = stored.remove(&date);
+            report.removed.push(date.clone());
+            changes.push((date, before, None));
+        }
+    }
+
+    for mut day in days {
+        // Actual hours are only recorded through their own endpoint, with its date checks
+        let kept = stored.get(&day.date);
+        day.actual_start = kept.and_then(|kept| kept.actual_start.clone());
+        day.actual_end = kept.and_then(|kept| kept.actual_end.clone());
+        match stored.insert(day.date.clone(), day.clone()) {
+            Some(before) if before == day => report.unchanged += 1,
+            Some(before) => {
+                report.changed.push(day.date.clone());
+                changes.push((day.date.clone(), Some(before), Some(day)));
+            }
+            None => {
+                report.added.push(day.date.clone());
+                changes.push((day.date.clone(), None, Some(day)));
+            }
+        }
+    }
+    schedule.days = stored.into_values().collect();
+
+    let at = Utc::now().timestamp();
+    changes
+        .into_iter()
+        .map(|(date, before, after)| AuditRecord {
+            seq: 0,
+            at,
+            employee: schedule.employee_name.clone(),
+            date,
+            before: before.map(WorkDay::into),
+            after: after.map(WorkDay::into),
+            source: AUDIT_SOURCE.to_string(),
+            actor: Some(actor.to_string()),
+        })
+        .collect()
+}
+
+/// Handler exporting an employee's stored days as day entries, ordered by date
+pub async fn export_schedule_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(employee_name): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Json<Vec<WorkDay>>, StatusCode> {
+    authorize_employee_access(&state, &auth.claims, &employee_name).await?;
+    let from = query_date(query.from.as_deref())?;
+    let to = query_date(query.to.as_deref())?;
+    if matches!((from, to), (Some(from), Some(to)) if from > to) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let schedule = match state.db.get_schedule(&employee_name).await {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!(
+                "Failed to load schedule for {}: {}",
+                Redacted(&employee_name),
+                e
+            );
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let mut days: Vec<WorkDay> = schedule
+        .days
+        .into_iter()
+        .filter(|day| {
+            let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d") else {
+                return false;
+            };
+            from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+        })
+        .collect();
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(Json(days))
+}
+
+/// Write validated days into an employee's schedule with their audit records
+async fn store_import(
+    state: &AppState,
+    employee: &str,
+    days: Vec<WorkDay>,
+    mode: ImportMode,
+    actor: &str,
+    report: &mut ScheduleImportReport,
+) -> Result<(), String> {
+    // Don't interleave with an upload for the same employee
+    let _guard = state.upload_locks.lock(&EmployeeId::new(employee)).await;
+
+    let mut schedule = state
+        .db
+        .get_schedule(employee)
+        .await?
+        .unwrap_or_else(|| WorkSchedule::new(employee.to_string()));
+    let records = apply_import(&mut schedule, days, mode, actor, report);
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    schedule.last_updated = Utc::now();
+    state.db.set_schedule(employee, &schedule).await?;
+    if !report.removed.is_empty() {
+        state.db.remove_days(employee, &report.removed).await?;
+    }
+    state.publish_update(
+        employee,
+        records.iter().map(|record| record.date.clone()).collect(),
+    );
+    state.db.record_audit(records).await
+}
+
+/// Handler importing day entries into an employee's schedule (admin only)
+pub async fn import_schedule_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path(employee_name): Path<String>,
+    Query(query): Query<ScheduleImportQuery>,
+    Json(days): Json<Vec<WorkDay>>,
+) -> Response {
+    if !auth.claims.is_admin() {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    if EmployeeId::new(&employee_name).is_empty() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let mut report = ScheduleImportReport {
+        mode: query.mode,
+        ..ScheduleImportReport::default()
+    };
+    let days = match validate_import(days) {
+        Ok(days) => days,
+        Err(errors) => {
+            info!(
+                "Rejected schedule import for {} with {} errors",
+                Redacted(&employee_name),
+                errors.len()
+            );
+            report.errors = errors;
+            return (StatusCode::BAD_REQUEST, Json(report)).into_response();
+        }
+    };
+
+    if let Err(e) = store_import(
+        &state,
+        &employee_name,
+        days,
+        query.mode,
+        &auth.claims.sub,
+        &mut report,
+    )
+    .await
+    {
+        error!(
+            "Failed to store imported schedule for {}: {}",
+            Redacted(&employee_name),
+            Redacted(&e)
+        );
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    info!(
+        "Imported schedule for {}: {} added, {} changed, {} removed",
+        Redacted(&employee_name),
+        report.added.len(),
+        report.changed.len(),
+        report.removed.len()
+    );
+    Json(report).into_response()
+}
+
+/// Body recording the hours actually worked; leave both times out to clear them
+#[derive(Debug, Deserialize)]
+pub struct ActualHoursBody {
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    end: Option<String>,
+}
+
+/// Check recorded actual hours for `date` on `today`, None clearing them
+fn validate_actual_hours(
+    body: ActualHoursBody,
+    date: NaiveDate,
+    today: NaiveDate,
+) -> Result<Option<(String, String)>, ActualsRefusal> {
+    check_actuals_date(date, today)?;
+    match (body.start, body.end) {
+        (Some(start), Some(end)) => parse_actual_times(&start, &end).map(Some),
+        (None, None) => Ok(None),
+        (start, end) => Err(ActualsRefusal::InvalidRange(format!(
+            "{}-{}",
+            start.unwrap_or_default(),
+            end.unwrap_or_default()
+        ))),
+    }
+}
+
+/// Handler recording the hours an employee actually worked on a stored past day (admin only)
+pub async fn actual_hours_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Path((employee_name, date)): Path<(String, String)>,
+    Json(body): Json<ActualHoursBody>,
+) -> Response {
+    if !auth.claims.is_admin() {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let Ok(parsed) = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let actual = match validate_actual_hours(body, parsed, Local::now().date_naive()) {
+        Ok(actual) => actual,
+        Err(refusal) => return (StatusCode::BAD_REQUEST, refusal.to_string()).into_response(),
+    };
+    let date = parsed.format("%Y-%m-%d").to_string();
+
+    // Don't interleave with an upload or import for the same employee
+    let _guard = state
+        .upload_locks
+        .lock(&EmployeeId::new(&employee_name))
+        .await;
+    let stored = match state.db.get_schedule(&employee_name).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            error!(
+                "Failed to load schedule for {}: {}",
+                Redacted(&employee_name),
+                e
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let Some(mut schedule) = stored else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(day) = schedule.days.iter_mut().find(|day| day.date == date) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let before = day.clone();
+    (day.actual_start, day.actual_end) = actual.unzip();
+    let after = day.clone();
+
+    let record = AuditRecord {
+        seq: 0,
+        at: Utc::now().timestamp(),
+        employee: schedule.employee_name.clone(),
+        date: date.clone(),
+        before: Some(before.into()),
+        after: Some(after.clone().into()),
+        source: ACTUALS_AUDIT_SOURCE.to_string(),
+        actor: Some(auth.claims.sub.clone()),
+    };
+    let stored = async {
+        state.db.set_schedule(&employee_name, &schedule).await?;
+        state.db.record_audit(vec![record]).await
+    };
+    if let Err(e) = stored.await {
+        error!(
+            "Failed to store actual hours for {}: {}",
+            Redacted(&employee_name),
+            Redacted(&e)
+        );
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    info!(
+        "Set actual hours of {} on {}: {}",
+        Redacted(&employee_name),
+        date,
+        after.actual_start.is_some()
+    );
+    Json(after).into_response()
+}
+
+/// A day as a row of the CSV export, in the import's columns followed by the actual hours
+#[derive(Debug, PartialEq, Serialize)]
+struct CsvExportRow {
+    employee: String,
+    date: String,
+    day_type: &'static str,
+    shifts: String,
+    break_minutes: Option<u16>,
+    notes: String,
+    actual_start: Option<String>,
+    actual_end: Option<String>,
+}
+
+impl CsvExportRow {
+    fn new(employee: &str, day: &WorkDay) -> Self {
+        let entry = day.to_entry();
+        let day_type = if day.is_day_off {
+            "off"
+        } else if !day.shifts.is_empty() {
+            "work"
+        } else if entry.is_vacation() {
+            "vacation"
+        } else {
+            "note"
+        };
+        let shifts = day
+            .shifts
+            .iter()
+            .map(|shift| {
+                format!(
+                    "{}-{}",
+                    shift.start.as_deref().unwrap_or_default(),
+                    shift.end.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        Self {
+            employee: employee.to_string(),
+            date: day.date.clone(),
+            day_type,
+            shifts,
+            break_minutes: day.break_minutes,
+            notes: day.notes.clone().unwrap_or_default(),
+            actual_start: day.actual_start.clone(),
+            actual_end: day.actual_end.clone(),
+        }
+    }
+}
+
+/// Every employee's stored schedule
+pub async fn all_schedules(db: &dyn WorkHoursDb) -> Result<Vec<WorkSchedule>, String> {
+    let mut schedules = Vec::new();
+    for employee in db.list_employees().await? {
+        if let Some(schedule) = db.get_schedule(&employee).await? {
+            schedules.push(schedule);
+        }
+    }
+    Ok(schedules)
+}
+
+/// Write the days of the schedules between `from` and `to` as CSV, by employee and date
+pub fn export_csv(
+    schedules: &[WorkSchedule],
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Vec<u8>, String> {
+    let mut rows: Vec<CsvExportRow> = schedules
+        .iter()
+        .flat_map(|schedule| {
+            schedule
+                .days
+                .iter()
+                .filter(|day| {
+                    NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").is_ok_and(|date| {
+                        from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+                    })
+                })
+                .map(|day| CsvExportRow::new(&schedule.employee_name, day))
+        })
+        .collect();
+    rows.sort_by(|a, b| a.employee.cmp(&b.employee).then(a.date.cmp(&b.date)));
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|e| format!("CSV write error: {e}"))?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| format!("CSV write error: {e}"))
+}
+
+/// Handler exporting every employee's stored days as CSV (admin only)
+pub async fn export_csv_handler(
+    State(state): State<AppState>,
+    Extension(auth): Extension<JwtAuth>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    if !auth.claims.is_admin() {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let (Ok(from), Ok(to)) = (
+        query_date(query.from.as_deref()),
+        query_date(query.to.as_deref()),
+    ) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let csv = all_schedules(state.db.as_ref())
+        .await
+        .and_then(|schedules| export_csv(&schedules, from, to));
+    match csv {
+        Ok(csv) => ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response(),
+        Err(e) => {
+            error!("Failed to export schedules as CSV: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str, shifts: &[(&str, &str)]) -> WorkDay {
+        WorkDay {
+            date: date.to_string(),
+            shifts: shifts
+                .iter()
+                .map(|(start, end)| ShiftRange::new(*start, *end))
+                .collect(),
+            is_day_off: shifts.is_empty(),
+            notes: None,
+            break_minutes: None,
+            context_link: None,
+            actual_start: None,
+            actual_end: None,
+        }
+    }
+
+    #[test]
+    fn test_import_is_validated_and_normalized() {
+        let days = validate_import(vec![
+            day("2024-03-05", &[]),
+            day("2024-03-04", &[("8:00", "16:30:00")]),
+        ])
+        .unwrap();
+        assert_eq!(days[0].date, "2024-03-04");
+        assert_eq!(days[0].shifts, [ShiftRange::new("08:00", "16:30")]);
+
+        let mut day_off = day("2024-03-06", &[("08:00", "16:00")]);
+        day_off.is_day_off = true;
+        let errors = validate_import(vec![
+            day("04.03.2024", &[]),
+            day("2024-03-05", &[("08:00", "25:00")]),
+            day_off,
+            day("2024-03-07", &[]),
+            day("2024-03-07", &[]),
+        ])
+        .unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert!(errors[0].starts_with("04.03.2024: invalid date"));
+        assert!(errors[1].starts_with("2024-03-05: invalid time \"25:00\""));
+        assert_eq!(errors[2], "2024-03-06: a day off can't have shifts");
+        assert_eq!(errors[3], "2024-03-07: the date appears more than once");
+    }
+
+    #[test]
+    fn test_import_size_limits() {
+        assert!(validate_import(Vec::new()).is_err());
+
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let days = |count: i64| -> Vec<WorkDay> {
+            (0..count)
+                .map(|offset| {
+                    let date = start + chrono::Duration::days(offset);
+                    day(&date.format("%Y-%m-%d").to_string(), &[])
+                })
+                .collect()
+        };
+        assert_eq!(validate_import(days(90)).unwrap().len(), 90);
+        assert!(validate_import(days(91)).is_err());
+
+        let errors =
+            validate_import(vec![day("2024-01-01", &[]), day("2024-03-31", &[])]).unwrap_err();
+        assert_eq!(
+            errors,
+            ["the days span 91 days from 2024-01-01 to 2024-03-31, at most 90 are allowed"]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_and_overwrite_removes_missing_days() {
+        let mut stored = WorkSchedule::new("Anna".to_string());
+        stored.days = vec![
+            day("2024-03-04", &[("08:00", "16:00")]),
+            day("2024-03-05", &[("08:00", "16:00")]),
+            day("2024-03-06", &[]),
+            day("2024-03-20", &[]),
+        ];
+        let payload = vec![
+            day("2024-03-04", &[("08:00", "16:00")]),
+            day("2024-03-06", &[("10:00", "18:00")]),
+            day("2024-03-07", &[]),
+        ];
+
+        let mut merged = stored.clone();
+        let mut report = ScheduleImportReport::default();
+        let records = apply_import(
+            &mut merged,
+            payload.clone(),
+            ImportMode::Merge,
+            "admin",
+            &mut report,
+        );
+        assert_eq!(merged.days.len(), 5);
+        assert_eq!(report.added, ["2024-03-07"]);
+        assert_eq!(report.changed, ["2024-03-06"]);
+        assert!(report.removed.is_empty());
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(records.len(), 2);
+        assert!(records[0].before.as_ref().unwrap().is_day_off);
+
+        let mut overwritten = stored;
+        let mut report = ScheduleImportReport::default();
+        let records = apply_import(
+            &mut overwritten,
+            payload,
+            ImportMode::Overwrite,
+            "admin",
+            &mut report,
+        );
+        let dates: Vec<&str> = overwritten.days.iter().map(|d| d.date.as_str()).collect();
+        // Days outside the payload's range are kept
+        assert_eq!(
+            dates,
+            ["2024-03-04", "2024-03-06", "2024-03-07", "2024-03-20"]
+        );
+        assert_eq!(report.removed, ["2024-03-05"]);
+        assert_eq!(records[0].after, None);
+    }
+}
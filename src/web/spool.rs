@@ -0,0 +1,90 @@
+//! Uploaded files written to disk as they arrive, so a large upload is never held in memory
+//! whole before it has been checked.
+
+use axum::extract::multipart::Field;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// An uploaded file in a temporary file, removed when dropped
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: PathBuf,
+    len: usize,
+}
+
+impl SpooledFile {
+    /// Size of the file in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Open the file for reading from the start
+    pub fn open(&self) -> std::io::Result<std::fs::File> {
+        std::fs::File::open(&self.path)
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Why an upload couldn't be spooled
+#[derive(Debug)]
+pub enum SpoolError {
+    /// The file grew past the size limit
+    TooLarge,
+    /// The request body couldn't be read, e.g. because it exceeded the body limit
+    Read(String),
+    /// The temporary file couldn't be written
+    Write(String),
+}
+
+impl fmt::Display for SpoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge => write!(f, "file exceeds the size limit"),
+            Self::Read(e) => write!(f, "failed to read upload: {e}"),
+            Self::Write(e) => write!(f, "failed to write upload: {e}"),
+        }
+    }
+}
+
+/// Write a multipart field to a temporary file in `dir`, giving up as soon as it grows past
+/// `max_len` bytes
+pub async fn spool_field(
+    mut field: Field<'_>,
+    dir: &Path,
+    max_len: usize,
+) -> Result<SpooledFile, SpoolError> {
+    let write_error = |e: std::io::Error| SpoolError::Write(e.to_string());
+    tokio::fs::create_dir_all(dir).await.map_err(write_error)?;
+    let path = dir.join(format!(".upload-{}.part", uuid::Uuid::new_v4()));
+    let mut file = tokio::fs::File::create(&path).await.map_err(write_error)?;
+    // From here on a partial file is removed on any error
+    let mut spooled = SpooledFile { path, len: 0 };
+
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| SpoolError::Read(e.to_string()))?
+    {
+        spooled.len += chunk.len();
+        if spooled.len > max_len {
+            return Err(SpoolError::TooLarge);
+        }
+        file.write_all(&chunk).await.map_err(write_error)?;
+    }
+    file.flush().await.map_err(write_error)?;
+
+    Ok(spooled)
+}
@@ -0,0 +1,488 @@
+use crate::components::work_schedule::overlap::{merge_entries, OverlapKind};
+use crate::components::work_schedule::quality::ParseRecord;
+use crate::components::work_schedule::uploads::PeriodIssue;
+use crate::components::work_schedule::EmployeeId;
+use crate::utils::redact::Redacted;
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+use crate::web::model::{WorkDay, WorkDayExtraction, WorkSchedule};
+use crate::web::parser::Provider;
+
+/// Something in a parsed schedule worth a second look
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The model returned a date that couldn't be parsed, so the day was dropped
+    InvalidDate(String),
+    /// More than one entry for the same date
+    Duplicate(String, OverlapKind),
+    /// A shift starting and ending at the same time
+    ZeroLength(String),
+    /// A day outside the requested date range
+    OutOfRange(String),
+    /// Hours that weren't recognized and were kept as a note
+    Note(String, String),
+    /// A run of this many empty days starting on the date, while the table read from the
+    /// image has entries for them
+    SuspectBlank(String, usize),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::InvalidDate(date) => write!(f, "{date}: invalid date, day dropped"),
+            Issue::Duplicate(date, kind) => write!(f, "{date}: duplicate entries ({kind:?})"),
+            Issue::ZeroLength(date) => write!(f, "{date}: shift has zero length"),
+            Issue::OutOfRange(date) => write!(f, "{date}: outside the requested range"),
+            Issue::Note(date, note) => write!(f, "{date}: unrecognized hours kept as note: {note}"),
+            Issue::SuspectBlank(date, days) => write!(
+                f,
+                "{date}: {days} empty days although the table has entries for them"
+            ),
+        }
+    }
+}
+
+/// Result of checking a parsed schedule
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Number of days in the schedule
+    pub days: usize,
+    /// Problems found, in date order
+    pub issues: Vec<Issue>,
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} days, {} issues", self.days, self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  - {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check a schedule converted from the model's extraction.
+///
+/// Days outside `start`..=`end` are reported but kept, since the range is only a hint.
+pub fn validate_schedule(
+    extracted: &[WorkDayExtraction],
+    schedule: &WorkSchedule,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> ValidationReport {
+    let mut issues: Vec<Issue> = extracted
+        .iter()
+        .filter(|day| NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").is_err())
+        .map(|day| Issue::InvalidDate(day.date.clone()))
+        .collect();
+
+    let duplicates = schedule.duplicate_dates();
+    for (date, days) in &duplicates {
+        let entries: Vec<_> = days.iter().map(|day| day.to_entry()).collect();
+        let kind = merge_entries(&entries)
+            .and_then(|merged| merged.overlap)
+            .unwrap_or(OverlapKind::Identical);
+        issues.push(Issue::Duplicate(date.to_string(), kind));
+    }
+
+    for day in &schedule.days {
+        let in_range = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d").is_ok_and(|date| {
+            start.is_none_or(|start| date >= start) && end.is_none_or(|end| date <= end)
+        });
+        if !in_range {
+            issues.push(Issue::OutOfRange(day.date.clone()));
+        }
+        if is_zero_length(day) && !duplicates.contains_key(day.date.as_str()) {
+            issues.push(Issue::ZeroLength(day.date.clone()));
+        }
+        if let Some(note) = &day.notes {
+            issues.push(Issue::Note(day.date.clone(), note.clone()));
+        }
+    }
+
+    issues.sort_by(|a, b| issue_date(a).cmp(issue_date(b)));
+    issues.dedup();
+
+    ValidationReport {
+        days: schedule.days.len(),
+        issues,
+    }
+}
+
+/// Parse quality of an uploaded schedule for the quality trend. Cells kept as notes count as
+/// low confidence unless they're a vacation code, and the other issues are kept as warnings.
+pub fn parse_record(
+    upload_id: &str,
+    parsed_at: i64,
+    provider: Provider,
+    schedule: &WorkSchedule,
+) -> ParseRecord {
+    let report = validate_schedule(&[], schedule, None, None);
+    ParseRecord {
+        upload_id: upload_id.to_string(),
+        parsed_at,
+        employee: schedule.employee_name.clone(),
+        provider: provider.name().to_string(),
+        days: schedule.days.len(),
+        empty_cells: schedule
+            .days
+            .iter()
+            .filter(|day| day.shifts.is_empty() && !day.is_day_off && day.notes.is_none())
+            .count(),
+        low_confidence: schedule
+            .days
+            .iter()
+            .filter(|day| day.notes.is_some() && !day.to_entry().is_vacation())
+            .count(),
+        warnings: report
+            .issues
+            .iter()
+            .filter(|issue| !matches!(issue, Issue::Note(..)))
+            .map(Issue::to_string)
+            .collect(),
+        manual_edits: 0,
+    }
+}
+
+fn is_zero_length(day: &WorkDay) -> bool {
+    merge_entries(&[day.to_entry()]).and_then(|entry| entry.overlap)
+        == Some(OverlapKind::ZeroLength)
+}
+
+fn issue_date(issue: &Issue) -> &str {
+    match issue {
+        Issue::InvalidDate(date)
+        | Issue::Duplicate(date, _)
+        | Issue::ZeroLength(date)
+        | Issue::OutOfRange(date)
+        | Issue::Note(date, _)
+        | Issue::SuspectBlank(date, _) => date,
+    }
+}
+
+/// Shortest run of empty days that makes a parse suspect
+const SUSPECT_BLANK_DAYS: usize = 7;
+
+/// Start of the error returned for a parse rejected as suspect
+pub const SUSPECT_PARSE_ERROR: &str = "Parsed schedule is suspect";
+
+/// Number of parses rejected as suspect since startup, logged with each rejection
+static SUSPECT_PARSES: AtomicU64 = AtomicU64::new(0);
+
+/// Check whether a parse error means the parse was rejected as suspect
+pub fn is_suspect_parse(error: &str) -> bool {
+    error.starts_with(SUSPECT_PARSE_ERROR)
+}
+
+/// Day and month of a table header cell like "10.3.", "ma 10.3." or "10.3.2025"
+fn header_day_month(cell: &str) -> Option<(u32, u32)> {
+    let token = cell.split_whitespace().last()?;
+    let mut parts = token.trim_end_matches('.').split('.');
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    ((1..=31).contains(&day) && (1..=12).contains(&month)).then_some((day, month))
+}
+
+/// Cells of the employee's row in the markdown table read from the image, keyed by the day
+/// and month of their column. The first table row with dates is taken as the header.
+fn markdown_row(markdown: &str, employee: &str) -> HashMap<(u32, u32), String> {
+    let employee = EmployeeId::new(employee);
+    let mut header: Option<Vec<Option<(u32, u32)>>> = None;
+
+    for line in markdown.lines().map(str::trim) {
+        if !line.starts_with('|') {
+            continue;
+        }
+        let cells: Vec<&str> = line.trim_matches('|').split('|').map(str::trim).collect();
+        if cells
+            .iter()
+            .all(|cell| cell.chars().all(|c| matches!(c, '-' | ':' | ' ')))
+        {
+            continue;
+        }
+
+        match &header {
+            None => {
+                let columns: Vec<_> = cells.iter().map(|cell| header_day_month(cell)).collect();
+                if columns.iter().any(Option::is_some) {
+                    header = Some(columns);
+                }
+            }
+            Some(columns)
+                if cells
+                    .first()
+                    .is_some_and(|name| EmployeeId::new(name) == employee) =>
+            {
+                return columns
+                    .iter()
+                    .zip(&cells)
+                    .filter_map(|(column, cell)| column.map(|key| (key, cell.to_string())))
+                    .collect();
+            }
+            Some(_) => {}
+        }
+    }
+
+    HashMap::new()
+}
+
+/// Look for a run of at least a week of empty days that the markdown table read from the image
+/// has entries for in the employee's row, which happens when the model reads the wrong row.
+///
+/// A run counts when the table has entries for most of its days. Without a row for the
+/// employee in the table there's nothing to compare against and nothing is reported.
+pub fn check_blank_days(
+    extracted: &[WorkDayExtraction],
+    markdown: &str,
+    employee: &str,
+) -> Option<Issue> {
+    let row = markdown_row(markdown, employee);
+    if row.is_empty() {
+        return None;
+    }
+
+    let mut days: Vec<(NaiveDate, bool)> = extracted
+        .iter()
+        .filter_map(|day| {
+            NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (date, day.work_hours.trim().is_empty()))
+        })
+        .collect();
+    days.sort();
+    days.dedup();
+
+    let mut runs: Vec<Vec<NaiveDate>> = Vec::new();
+    for (date, empty) in days {
+        match runs.last_mut() {
+            Some(run) if empty && run.last().and_then(|last| last.succ_opt()) == Some(date) => {
+                run.push(date)
+            }
+            _ if empty => runs.push(vec![date]),
+            _ => runs.push(Vec::new()),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|run| run.len() >= SUSPECT_BLANK_DAYS)
+        .find(|run| {
+            let filled = run
+                .iter()
+                .filter(|date| {
+                    row.get(&(date.day(), date.month()))
+                        .is_some_and(|cell| !cell.is_empty())
+                })
+                .count();
+            filled * 2 > run.len()
+        })
+        .map(|run| Issue::SuspectBlank(run[0].format("%Y-%m-%d").to_string(), run.len()))
+}
+
+/// Reject a parse that came back blank for days the table read from the image has entries
+/// for, so a week of blanks isn't stored as if there were no shifts
+pub fn reject_suspect_parse(
+    extracted: &[WorkDayExtraction],
+    markdown: &str,
+    employee: &str,
+) -> Result<(), String> {
+    let Some(issue) = check_blank_days(extracted, markdown, employee) else {
+        return Ok(());
+    };
+
+    let count = SUSPECT_PARSES.fetch_add(1, Ordering::Relaxed) + 1;
+    warn!(
+        suspect_parses = count,
+        "Rejecting suspect parse for {}: {}",
+        Redacted(employee),
+        issue
+    );
+    Err(format!("{SUSPECT_PARSE_ERROR}: {issue}"))
+}
+
+/// Check that parsed dates cover the upcoming period an upload is expected to be for, which
+/// starts on `expected_start` and runs for about five weeks.
+///
+/// The dates are suspect when more than half of them are before the period starts, e.g. an old
+/// schedule uploaded again, or when they end before the Monday after `expected_start`, so
+/// nothing of the coming weeks would be stored. How far the dates reach past the period isn't
+/// checked since longer schedules are fine.
+pub fn check_period(dates: &[NaiveDate], expected_start: NaiveDate) -> Option<PeriodIssue> {
+    let last = dates.iter().max()?;
+
+    let past = dates.iter().filter(|date| **date < expected_start).count();
+    if past * 2 > dates.len() {
+        return Some(PeriodIssue::MostlyPast);
+    }
+
+    let days_to_monday = 7 - i64::from(expected_start.weekday().num_days_from_monday());
+    let next_monday = expected_start + chrono::Duration::days(days_to_monday);
+    (*last < next_monday).then_some(PeriodIssue::EndsBeforeNextWeek)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::parser::{convert_to_work_schedule, extract_json_array};
+
+    const EXTRACTION: &str = include_str!("../../tests/fixtures/work_hours_extraction.json");
+    const BLANK_WEEK: &str = include_str!("../../tests/fixtures/schedule_blank_week.json");
+    const BLANK_WEEK_TABLE: &str = include_str!("../../tests/fixtures/schedule_blank_week.md");
+
+    fn date(value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+    }
+
+    #[test]
+    fn test_fixture_extraction_converts_and_validates() {
+        let days = extract_json_array(EXTRACTION).unwrap();
+        let schedule = convert_to_work_schedule("Anna", days.clone()).unwrap();
+        let report = validate_schedule(&days, &schedule, date("2025-01-06"), date("2025-01-12"));
+
+        assert_eq!(report.days, 8);
+        assert_eq!(
+            report.issues,
+            vec![
+                Issue::Duplicate("2025-01-08".to_string(), OverlapKind::Overlapping),
+                Issue::Note("2025-01-09".to_string(), "koulutus".to_string()),
+                Issue::ZeroLength("2025-01-10".to_string()),
+                Issue::OutOfRange("2025-01-13".to_string()),
+                Issue::InvalidDate("2025-01-32".to_string()),
+            ]
+        );
+
+        // Out of range days are reported but kept
+        let json = serde_json::to_value(&schedule).unwrap();
+        assert_eq!(json["days"][0]["shifts"][0]["start"], "07:00");
+        assert_eq!(json["days"][1]["is_day_off"], true);
+        assert_eq!(json["days"][6]["shifts"][1]["start"], "16:00");
+        assert_eq!(json["days"][7]["date"], "2025-01-13");
+    }
+
+    #[test]
+    fn test_parse_record_counts_cells_and_warnings() {
+        let days = extract_json_array(EXTRACTION).unwrap();
+        let mut schedule = convert_to_work_schedule("Anna", days).unwrap();
+        for (date, notes) in [
+            ("2025-01-12", None),
+            ("2025-01-14", Some("loma".to_string())),
+        ] {
+            schedule.add_day(WorkDay {
+                date: date.to_string(),
+                shifts: Vec::new(),
+                is_day_off: false,
+                notes,
+                break_minutes: None,
+                context_link: None,
+                actual_start: None,
+                actual_end: None,
+            });
+        }
+
+        let record = parse_record("anna-1736150400", 1736150400, Provider::OpenAi, &schedule);
+        assert_eq!(record.provider, "openai");
+        assert_eq!(record.days, 10);
+        assert_eq!(record.empty_cells, 1);
+        // "koulutus" wasn't recognized, while "loma" is a vacation code
+        assert_eq!(record.low_confidence, 1);
+        assert_eq!(
+            record.warnings,
+            [
+                "2025-01-08: duplicate entries (Overlapping)",
+                "2025-01-10: shift has zero length",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blank_week_with_entries_in_the_table_is_rejected() {
+        let days = extract_json_array(BLANK_WEEK).unwrap();
+        let before = SUSPECT_PARSES.load(Ordering::Relaxed);
+
+        assert_eq!(
+            check_blank_days(&days, BLANK_WEEK_TABLE, "Anna"),
+            Some(Issue::SuspectBlank("2025-03-10".to_string(), 7))
+        );
+        let error = reject_suspect_parse(&days, BLANK_WEEK_TABLE, "anna").unwrap_err();
+        assert!(is_suspect_parse(&error));
+        assert!(SUSPECT_PARSES.load(Ordering::Relaxed) > before);
+    }
+
+    #[test]
+    fn test_genuinely_empty_week_passes() {
+        let days = extract_json_array(BLANK_WEEK).unwrap();
+
+        // Pekka's row is empty in the table too
+        assert_eq!(check_blank_days(&days, BLANK_WEEK_TABLE, "Pekka"), None);
+        assert!(reject_suspect_parse(&days, BLANK_WEEK_TABLE, "Pekka").is_ok());
+        // Without a row to compare against the parse is trusted
+        assert_eq!(check_blank_days(&days, BLANK_WEEK_TABLE, "Liisa"), None);
+    }
+
+    #[test]
+    fn test_short_blank_runs_pass() {
+        let mut days = extract_json_array(BLANK_WEEK).unwrap();
+        days[3].work_hours = "9-17".to_string();
+
+        assert_eq!(check_blank_days(&days, BLANK_WEEK_TABLE, "Anna"), None);
+    }
+
+    fn days(first: &str, count: u64) -> Vec<NaiveDate> {
+        date(first)
+            .unwrap()
+            .iter_days()
+            .take(count as usize)
+            .collect()
+    }
+
+    #[test]
+    fn test_past_uploads_are_suspicious() {
+        // Wednesday
+        let today = date("2025-03-12").unwrap();
+
+        // Last month's schedule uploaded again
+        assert_eq!(
+            check_period(&days("2025-02-03", 28), today),
+            Some(PeriodIssue::MostlyPast)
+        );
+        // Only the rest of this week
+        assert_eq!(
+            check_period(&days("2025-03-12", 5), today),
+            Some(PeriodIssue::EndsBeforeNextWeek)
+        );
+    }
+
+    #[test]
+    fn test_future_uploads_pass() {
+        let today = date("2025-03-12").unwrap();
+
+        assert_eq!(check_period(&days("2025-03-17", 35), today), None);
+        // Just reaching next Monday is enough
+        assert_eq!(check_period(&days("2025-03-12", 6), today), None);
+        // Nothing parsed is left for the other checks
+        assert_eq!(check_period(&[], today), None);
+    }
+
+    #[test]
+    fn test_straddling_uploads_depend_on_the_share_of_past_days() {
+        let today = date("2025-03-12").unwrap();
+
+        // Started last week, mostly ahead
+        assert_eq!(check_period(&days("2025-03-03", 28), today), None);
+        // Half past is still fine
+        assert_eq!(check_period(&days("2025-03-06", 12), today), None);
+        // Ends next week, but most of it is gone
+        assert_eq!(
+            check_period(&days("2025-03-03", 16), today),
+            Some(PeriodIssue::MostlyPast)
+        );
+        // The same days are fine for a period that started earlier
+        assert_eq!(
+            check_period(&days("2025-03-03", 16), date("2025-03-01").unwrap()),
+            None
+        );
+    }
+}
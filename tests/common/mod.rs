@@ -0,0 +1,48 @@
+//! Helpers shared by the integration test binaries. Each binary includes this module with
+//! `#[path = "common/mod.rs"]`, so it also resolves when they're compiled as modules of
+//! tests/mod.rs.
+#![allow(dead_code)]
+
+use mussubotti::components::redis_service::RedisActorHandle;
+use mussubotti::components::work_schedule::keys::{
+    dates_key, day_key, WORK_HOURS_EMPLOYEES, WORK_HOURS_EMPLOYEE_NAMES,
+};
+use mussubotti::components::work_schedule::models::WorkScheduleEntry;
+use mussubotti::components::work_schedule::EmployeeId;
+
+/// Store an entry the way the work hours upload does
+pub async fn store_entry(redis_handle: &RedisActorHandle, name: &str, entry: &WorkScheduleEntry) {
+    store_raw_entry(
+        redis_handle,
+        name,
+        &entry.date,
+        serde_json::to_string(entry).unwrap(),
+    )
+    .await;
+}
+
+/// Store a day entry's raw JSON the way the work hours upload does
+pub async fn store_raw_entry(
+    redis_handle: &RedisActorHandle,
+    name: &str,
+    date: &str,
+    json: String,
+) {
+    let employee = EmployeeId::new(name);
+    redis_handle
+        .sadd(&WORK_HOURS_EMPLOYEES, employee.slug())
+        .await
+        .unwrap();
+    redis_handle
+        .hset(&WORK_HOURS_EMPLOYEE_NAMES, employee.slug(), name)
+        .await
+        .unwrap();
+    redis_handle
+        .set(&day_key(&employee, date).unwrap(), json)
+        .await
+        .unwrap();
+    redis_handle
+        .sadd(&dates_key(&employee).unwrap(), date)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,19 @@
+//! The store suite against the in-memory Redis substitute
+
+use mussubotti::components::redis_service::{FakeClock, RedisActorHandle};
+
+/// Handle to a fresh store whose keys never expire on their own
+fn handle() -> RedisActorHandle {
+    RedisActorHandle::fake()
+}
+
+/// Handle to a fresh store expiring keys according to `clock`
+fn handle_with_clock(clock: FakeClock) -> RedisActorHandle {
+    RedisActorHandle::fake_with_clock(clock)
+}
+
+#[path = "common/mod.rs"]
+mod common;
+
+#[path = "suites/store.rs"]
+mod suite;
@@ -1,13 +1,19 @@
-use mussubotti::components::google_calendar::models::CalendarEvent;
+use mussubotti::components::google_calendar::format_day_lines;
+use mussubotti::components::google_calendar::models::{visible_events, CalendarEvent};
+use mussubotti::components::google_calendar::time::EventWindow;
+use mussubotti::components::google_calendar::{mark_announced, remember_events};
+use mussubotti::components::redis_service::RedisActorHandle;
 use mussubotti::config::Config;
 use mussubotti::error::BotResult;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// Mock implementation of Google Calendar actor handle for testing
-#[derive(Debug, Clone, Default)]
+#[derive(Clone)]
 pub struct MockGoogleCalendarHandle {
     events: Vec<CalendarEvent>,
+    window: EventWindow,
+    redis_handle: RedisActorHandle,
 }
 
 impl MockGoogleCalendarHandle {
@@ -34,7 +40,11 @@ impl MockGoogleCalendarHandle {
             },
         ];
 
-        Self { events }
+        Self {
+            events,
+            window: EventWindow::around(utc("2023-01-01T00:00:00Z"), 0, 28),
+            redis_handle: RedisActorHandle::fake(),
+        }
     }
 
     /// Get upcoming events from the mock
@@ -42,11 +52,12 @@ impl MockGoogleCalendarHandle {
         Ok(self.events.clone())
     }
 
-    /// Simulate checking for new events
+    /// Check for new events, diffing against the events remembered in the fake Redis, and
+    /// announce them
     pub async fn check_new_events(&self) -> BotResult<Vec<CalendarEvent>> {
-        // In a real implementation, this would compare with previous events
-        // Here we just return a subset of the events as "new"
-        Ok(vec![self.events[0].clone()])
+        let new_events = remember_events(&self.redis_handle, &self.events, self.window).await?;
+        mark_announced(&self.redis_handle, &new_events).await?;
+        Ok(new_events)
     }
 
     /// Shutdown the mock
@@ -56,6 +67,12 @@ impl MockGoogleCalendarHandle {
     }
 }
 
+impl Default for MockGoogleCalendarHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Test that demonstrates how to use the mock
 #[tokio::test]
 async fn test_google_calendar_mock() {
@@ -70,35 +87,126 @@ async fn test_google_calendar_mock() {
     assert_eq!(events[0].id, "event1");
     assert_eq!(events[1].id, "event2");
 
+    // The previous fetch only had the second event
+    mock_handle
+        .redis_handle
+        .save_events(vec![events[1].clone()])
+        .await
+        .unwrap();
+
     // Check new events
     let new_events = mock_handle.check_new_events().await.unwrap();
     assert_eq!(new_events.len(), 1);
     assert_eq!(new_events[0].id, "event1");
+
+    // Both events are known now
+    let new_events = mock_handle.check_new_events().await.unwrap();
+    assert!(new_events.is_empty());
+}
+
+fn utc(value: &str) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .unwrap()
+        .to_utc()
+}
+
+fn event_at(id: &str, start: &str) -> CalendarEvent {
+    CalendarEvent {
+        id: id.to_string(),
+        start_date_time: Some(start.to_string()),
+        end_date_time: Some(start.replace("T10:", "T11:")),
+        ..Default::default()
+    }
+}
+
+/// Remember a fetch and announce its new events
+async fn remember_and_announce(
+    redis_handle: &RedisActorHandle,
+    events: &[CalendarEvent],
+    window: EventWindow,
+) -> BotResult<Vec<CalendarEvent>> {
+    let new_events = remember_events(redis_handle, events, window).await?;
+    mark_announced(redis_handle, &new_events).await?;
+    Ok(new_events)
+}
+
+/// Events that only entered the window as it slid forward aren't new
+#[tokio::test]
+async fn test_window_shift_only_reports_events_in_both_windows() {
+    let redis_handle = RedisActorHandle::fake();
+    let monday = EventWindow::around(utc("2025-03-10T12:00:00Z"), 1, 28);
+    let tuesday = EventWindow::around(utc("2025-03-11T12:00:00Z"), 1, 28);
+
+    let meeting = event_at("meeting", "2025-03-20T10:00:00Z");
+    let new_events = remember_and_announce(&redis_handle, std::slice::from_ref(&meeting), monday)
+        .await
+        .unwrap();
+    assert_eq!(new_events.len(), 1);
+
+    // A day later the window reaches a day further, bringing in an event created long ago,
+    // while an event created meanwhile inside the old window is new
+    let entered = event_at("entered", "2025-04-08T10:00:00Z");
+    let created = event_at("created", "2025-03-25T10:00:00Z");
+    let new_events = remember_and_announce(
+        &redis_handle,
+        &[meeting.clone(), created.clone(), entered.clone()],
+        tuesday,
+    )
+    .await
+    .unwrap();
+    let ids: Vec<&str> = new_events.iter().map(|event| event.id.as_str()).collect();
+    assert_eq!(ids, ["created"]);
+
+    // The entered event is known from then on
+    let wednesday = EventWindow::around(utc("2025-03-12T12:00:00Z"), 1, 28);
+    let new_events = remember_and_announce(&redis_handle, &[meeting, created, entered], wednesday)
+        .await
+        .unwrap();
+    assert!(new_events.is_empty());
+
+    // Windows that don't overlap at all have nothing to compare
+    let later = EventWindow::around(utc("2025-06-01T12:00:00Z"), 1, 28);
+    let summer = event_at("summer", "2025-06-10T10:00:00Z");
+    let new_events = remember_and_announce(&redis_handle, &[summer], later)
+        .await
+        .unwrap();
+    assert!(new_events.is_empty());
+}
+
+/// Snapshots stored before windows were recorded are compared in full
+#[tokio::test]
+async fn test_snapshot_without_window_compares_every_event() {
+    let redis_handle = RedisActorHandle::fake();
+    redis_handle
+        .save_events(vec![event_at("old", "2025-03-12T10:00:00Z")])
+        .await
+        .unwrap();
+
+    let window = EventWindow::around(utc("2025-03-10T12:00:00Z"), 0, 28);
+    let events = [
+        event_at("old", "2025-03-12T10:00:00Z"),
+        event_at("far", "2025-04-07T10:00:00Z"),
+    ];
+    let new_events = remember_events(&redis_handle, &events, window)
+        .await
+        .unwrap();
+    assert_eq!(new_events.len(), 1);
+    assert_eq!(new_events[0].id, "far");
+}
+
+/// Configuration for the calendar tests
+fn test_config() -> Config {
+    Config {
+        show_private_event_details_channel_ids: vec![555],
+        ..Config::for_tests()
+    }
 }
 
 /// Test the full configuration and calendar service
 #[tokio::test]
 async fn test_calendar_with_config() {
     // Create a test configuration
-    let config = Arc::new(RwLock::new(Config {
-        discord_token: "test_token".to_string(),
-        google_client_id: "test_client_id".to_string(),
-        google_client_secret: "test_client_secret".to_string(),
-        google_calendar_id: "test_calendar_id".to_string(),
-        calendar_channel_id: 123456789,
-        guild_id: 987654321,
-        components: std::collections::HashMap::new(),
-        timezone: "UTC".to_string(),
-        activity: "Testing".to_string(),
-        redis_url: "redis://127.0.0.1:6379".to_string(),
-        daily_notification_time: "06:00".to_string(),
-        weekly_notification_time: "06:00".to_string(),
-        bot_locale: "en-US".to_string(),
-        new_events_check_interval: 300,
-        llama_api_key: "test_llama_api_key".to_string(),
-        disable_work_schedule_daily_notifications: false,
-        disable_work_schedule_weekly_notifications: false,
-    }));
+    let config = Arc::new(RwLock::new(test_config()));
 
     // Create a mock calendar handle
     let mock_handle = MockGoogleCalendarHandle::new();
@@ -115,3 +223,34 @@ async fn test_calendar_with_config() {
     let events = mock_handle.get_upcoming_events().await.unwrap();
     assert!(!events.is_empty());
 }
+
+/// Private events are masked in the calendar channel and shown in full in the allow-listed one
+#[tokio::test]
+async fn test_private_events_are_masked_outside_allowed_channels() {
+    let config = test_config();
+    let mut events = MockGoogleCalendarHandle::new()
+        .get_upcoming_events()
+        .await
+        .unwrap();
+    events[0].visibility = Some("private".to_string());
+    events[0].location = Some("Room 2".to_string());
+    let date = chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+
+    let public = format_day_lines(
+        &visible_events(
+            events.clone(),
+            config.shows_private_event_details(config.calendar_channel_id),
+        ),
+        date,
+    );
+    assert_eq!(public.len(), 1);
+    assert!(public[0].contains("Private event"));
+    assert!(!public[0].contains("Test Event 1"));
+    assert!(!public[0].contains("Room 2"));
+
+    let allowed = format_day_lines(
+        &visible_events(events, config.shows_private_event_details(555)),
+        date,
+    );
+    assert!(allowed[0].contains("Test Event 1 (Room 2)"));
+}
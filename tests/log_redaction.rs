@@ -0,0 +1,123 @@
+#[path = "common/mod.rs"]
+mod common;
+
+use common::store_raw_entry;
+use mussubotti::components::event_bus::EventBus;
+use mussubotti::components::redis_service::RedisActorHandle;
+use mussubotti::components::work_schedule::keys::{duplicate_field, WORK_HOURS_DUPLICATES};
+use mussubotti::components::work_schedule::models::{ShiftRange, WorkScheduleEntry};
+use mussubotti::components::work_schedule::overlap::KeepChoice;
+use mussubotti::components::work_schedule::{EmployeeId, WorkScheduleHandle};
+use mussubotti::config::Config;
+use mussubotti::utils::redact::{redact_name, set_log_redaction};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+/// Log output collected in memory so it can be searched like a log file
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+fn test_config() -> Arc<RwLock<Config>> {
+    Arc::new(RwLock::new(Config {
+        log_redaction: true,
+        ..Config::for_tests()
+    }))
+}
+
+/// Turns log redaction on until dropped. The flag is process-wide, so this test keeps to its
+/// own binary and switches it back off even when an assertion fails.
+struct RedactionGuard;
+
+impl RedactionGuard {
+    fn enable() -> Self {
+        set_log_redaction(true);
+        Self
+    }
+}
+
+impl Drop for RedactionGuard {
+    fn drop(&mut self) {
+        set_log_redaction(false);
+    }
+}
+
+#[tokio::test]
+async fn test_redacted_logs_contain_no_raw_names() {
+    let _redaction = RedactionGuard::enable();
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let date = "2025-01-06";
+    let redis_handle = RedisActorHandle::fake();
+    let mut first = WorkScheduleEntry::new(date.to_string());
+    first.shifts.push(ShiftRange::new("08:00", "16:00"));
+    let mut last = WorkScheduleEntry::new(date.to_string());
+    last.shifts.push(ShiftRange::new("10:00", "18:00"));
+    store_raw_entry(
+        &redis_handle,
+        "Anna Mäkinen",
+        date,
+        serde_json::to_string(&first).unwrap(),
+    )
+    .await;
+    redis_handle
+        .hset(
+            &WORK_HOURS_DUPLICATES,
+            &duplicate_field(&EmployeeId::new("Anna Mäkinen"), date),
+            serde_json::to_string(&[&first, &last]).unwrap(),
+        )
+        .await
+        .unwrap();
+    // An unreadable entry gets its employee logged as an error
+    store_raw_entry(&redis_handle, "Pekka Virtanen", date, "{".to_string()).await;
+
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle, EventBus::new());
+    handle
+        .resolve_duplicate("Anna Mäkinen", date, KeepChoice::Last, None)
+        .await
+        .unwrap();
+    let day = handle.get_schedule_for_date(date).await.unwrap();
+    assert!(day.get("Anna Mäkinen").is_some(), "{day:?}");
+
+    // Error messages end up in Discord error embeds
+    let error = handle
+        .resolve_duplicate("Pekka Virtanen", date, KeepChoice::First, None)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains(&redact_name("Pekka Virtanen")), "{error}");
+
+    let output = logs.contents();
+    assert!(output.contains(&redact_name("Anna Mäkinen")), "{output}");
+    assert!(output.contains(&redact_name("Pekka Virtanen")), "{output}");
+    for text in [&output, &error] {
+        let text = text.to_lowercase();
+        for raw in ["anna", "mäkinen", "makinen", "pekka", "virtanen"] {
+            assert!(!text.contains(raw), "{raw} in {text}");
+        }
+    }
+}
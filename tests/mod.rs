@@ -1,9 +1,17 @@
+mod fake_redis;
 mod google_calendar_mock;
 mod redis_mock;
 mod smoke_tests;
+mod sqlite_store;
 
 // This file organizes the integration tests into a cohesive test suite.
 // Each module tests a specific aspect of the application:
 // - smoke_tests: Basic functionality tests to ensure nothing is broken
 // - google_calendar_mock: Mocking the Google Calendar API for testing
+// - fake_redis: Work schedule, notification claim, token storage, stored entry inspection and
+//   schedule uploads from Discord
+//   against the fake Redis
+// - sqlite_store: The same tests against the SQLite storage backend
 // - redis_mock: Mocking Redis for testing without a real Redis instance
+//
+// log_redaction only runs as its own binary, since it switches on process-wide log redaction
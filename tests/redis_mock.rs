@@ -1,71 +1,11 @@
 use mussubotti::components::google_calendar::models::CalendarEvent;
-use mussubotti::error::{google_calendar_error, BotResult};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use mussubotti::components::redis_service::RedisActorHandle;
 
-/// Mock implementation of Redis for testing
-#[derive(Debug, Clone, Default)]
-pub struct MockRedis {
-    data: Arc<Mutex<HashMap<String, String>>>,
-}
-
-impl MockRedis {
-    /// Create a new mock Redis instance
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    /// Save events to the mock Redis
-    pub async fn save_events(&self, events: Vec<CalendarEvent>) -> BotResult<()> {
-        let events_json = serde_json::to_string(&events)
-            .map_err(|e| google_calendar_error(&format!("Failed to serialize events: {e}")))?;
-        let mut data = self.data.lock().await;
-        data.insert("google_calendar_events".to_string(), events_json);
-        Ok(())
-    }
-
-    /// Get events from the mock Redis
-    pub async fn get_events(&self) -> BotResult<Vec<CalendarEvent>> {
-        let data = self.data.lock().await;
-
-        if let Some(events_json) = data.get("google_calendar_events") {
-            let events: Vec<CalendarEvent> = serde_json::from_str(events_json).map_err(|e| {
-                google_calendar_error(&format!("Failed to deserialize events: {e}"))
-            })?;
-            Ok(events)
-        } else {
-            Ok(Vec::new())
-        }
-    }
-
-    /// Save a token to the mock Redis
-    pub async fn save_token(&self, token: serde_json::Value) -> BotResult<()> {
-        let token_json = token.to_string();
-        let mut data = self.data.lock().await;
-        data.insert("google_calendar_token".to_string(), token_json);
-        Ok(())
-    }
-
-    /// Get a token from the mock Redis
-    pub async fn get_token(&self) -> BotResult<Option<serde_json::Value>> {
-        let data = self.data.lock().await;
-
-        if let Some(token_json) = data.get("google_calendar_token") {
-            let token: serde_json::Value = serde_json::from_str(token_json)
-                .map_err(|e| google_calendar_error(&format!("Failed to deserialize token: {e}")))?;
-            Ok(Some(token))
-        } else {
-            Ok(None)
-        }
-    }
-}
-
-/// Basic test for the Redis mock
+/// Basic test for event and token storage in the fake Redis
 #[tokio::test]
 async fn test_redis_mock() {
-    // Create a new mock Redis
-    let mock_redis = MockRedis::new();
+    // Create a new fake Redis
+    let mock_redis = RedisActorHandle::fake();
 
     // Create some test events
     let events = vec![CalendarEvent {
@@ -77,6 +17,9 @@ async fn test_redis_mock() {
         start_date: None,
         end_date_time: Some("2023-01-01T11:00:00Z".to_string()),
         end_date: None,
+        color_id: None,
+        location: None,
+        visibility: None,
     }];
 
     // Save events to Redis
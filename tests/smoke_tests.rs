@@ -16,17 +16,8 @@ async fn test_config_loads() {
         google_calendar_id: String::new(),
         calendar_channel_id: 0,
         guild_id: 0,
-        components: std::collections::HashMap::new(),
-        timezone: "UTC".to_string(),
-        activity: "Testing".to_string(),
-        redis_url: "redis://127.0.0.1:6379".to_string(),
-        daily_notification_time: "06:00".to_string(),
-        weekly_notification_time: "06:00".to_string(),
         bot_locale: "en".to_string(),
-        new_events_check_interval: 300,
-        llama_api_key: "test_llama_api_key".to_string(),
-        disable_work_schedule_daily_notifications: false,
-        disable_work_schedule_weekly_notifications: false,
+        ..Config::for_tests()
     };
 
     assert_eq!(config.redis_url, "redis://127.0.0.1:6379");
@@ -57,6 +48,9 @@ async fn mock_get_events(_redis_handle: &RedisActorHandle) -> BotResult<Vec<Cale
             start_date: None,
             end_date_time: Some("2023-01-01T11:00:00Z".to_string()),
             end_date: None,
+            color_id: None,
+            location: None,
+            visibility: None,
         },
         CalendarEvent {
             id: "event2".to_string(),
@@ -67,6 +61,9 @@ async fn mock_get_events(_redis_handle: &RedisActorHandle) -> BotResult<Vec<Cale
             start_date: None,
             end_date_time: Some("2023-01-02T11:00:00Z".to_string()),
             end_date: None,
+            color_id: None,
+            location: None,
+            visibility: None,
         },
     ];
     Ok(events)
@@ -94,23 +91,13 @@ async fn test_calendar_events() {
 async fn test_config_from_env() {
     // Create a test configuration with Arc and RwLock
     let config = Arc::new(RwLock::new(Config {
-        discord_token: "test_token".to_string(),
-        google_calendar_id: "test_calendar_id".to_string(),
         redis_url: "redis://localhost:6379".to_string(),
         google_client_id: String::new(),
         google_client_secret: String::new(),
         calendar_channel_id: 0,
         guild_id: 0,
-        components: std::collections::HashMap::new(),
-        timezone: "UTC".to_string(),
-        activity: "Testing".to_string(),
-        daily_notification_time: "06:00".to_string(),
-        weekly_notification_time: "06:00".to_string(),
         bot_locale: "en".to_string(),
-        new_events_check_interval: 300,
-        llama_api_key: "test_llama_api_key".to_string(),
-        disable_work_schedule_daily_notifications: false,
-        disable_work_schedule_weekly_notifications: false,
+        ..Config::for_tests()
     }));
 
     // Test reading from the config
@@ -126,7 +113,7 @@ async fn test_config_from_env() {
 #[tokio::test]
 async fn test_component_initialization_order() {
     use async_trait::async_trait;
-    use mussubotti::components::{Component, ComponentManager};
+    use mussubotti::components::{Component, ComponentManager, EventBus};
     use mussubotti::error::BotResult;
     use poise::serenity_prelude as serenity;
     use std::sync::{Arc, Mutex};
@@ -153,11 +140,11 @@ async fn test_component_initialization_order() {
             "redis_service"
         }
 
-        async fn init(
+        async fn create(
             &self,
-            _ctx: &serenity::Context,
             _config: Arc<RwLock<Config>>,
             _redis_handle: RedisActorHandle,
+            _bus: EventBus,
         ) -> BotResult<()> {
             // Record initialization with an incrementing counter
             let order = INIT_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
@@ -184,11 +171,11 @@ async fn test_component_initialization_order() {
             "google_calendar"
         }
 
-        async fn init(
+        async fn create(
             &self,
-            _ctx: &serenity::Context,
             _config: Arc<RwLock<Config>>,
             _redis_handle: RedisActorHandle,
+            _bus: EventBus,
         ) -> BotResult<()> {
             // Record initialization with an incrementing counter
             let order = INIT_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
@@ -226,34 +213,22 @@ async fn test_component_initialization_order() {
         google_calendar_id: String::new(),
         calendar_channel_id: 0,
         guild_id: 0,
-        components: std::collections::HashMap::new(),
-        timezone: "UTC".to_string(),
-        activity: "Testing".to_string(),
-        redis_url: "redis://127.0.0.1:6379".to_string(),
-        daily_notification_time: "06:00".to_string(),
-        weekly_notification_time: "06:00".to_string(),
         bot_locale: "en".to_string(),
-        new_events_check_interval: 300,
-        llama_api_key: "test_llama_api_key".to_string(),
-        disable_work_schedule_daily_notifications: false,
-        disable_work_schedule_weekly_notifications: false,
+        ..Config::for_tests()
     }));
 
     // Create component manager
     let mut component_manager = ComponentManager::new(Arc::clone(&config));
 
-    // Create and register components
-    let redis_component = MockRedisComponent {
-        order_recorder: Arc::clone(&order_recorder),
-    };
-
-    let calendar_component = MockGCalendarComponent {
-        order_recorder: Arc::clone(&order_recorder),
-    };
-
     // Register the components in the expected order
-    component_manager.register(redis_component);
-    component_manager.register(calendar_component);
+    let recorder = Arc::clone(&order_recorder);
+    component_manager.register(move || MockRedisComponent {
+        order_recorder: Arc::clone(&recorder),
+    });
+    let recorder = Arc::clone(&order_recorder);
+    component_manager.register(move || MockGCalendarComponent {
+        order_recorder: Arc::clone(&recorder),
+    });
 
     // Create a custom init function to replace ComponentManager.init_all()
     // since we can't easily create a real Context
@@ -284,7 +259,12 @@ async fn test_component_initialization_order() {
 
                 // Init the component
                 component
-                    .init(ctx_ref, Arc::clone(&config), redis_handle.clone())
+                    .init(
+                        ctx_ref,
+                        Arc::clone(&config),
+                        redis_handle.clone(),
+                        manager.bus(),
+                    )
                     .await?;
             }
         }
@@ -326,3 +306,90 @@ async fn test_component_initialization_order() {
         "Google Calendar must be initialized after Redis service"
     );
 }
+
+/// Components disabled in the config are never registered, so they are neither initialized nor
+/// shut down, and their commands can tell they are disabled
+#[tokio::test]
+async fn test_disabled_component_is_skipped() {
+    use async_trait::async_trait;
+    use mussubotti::components::{Component, ComponentManager, EventBus};
+    use mussubotti::error::BotResult;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingComponent {
+        name: &'static str,
+        shutdowns: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Component for CountingComponent {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn create(
+            &self,
+            _config: Arc<RwLock<Config>>,
+            _redis_handle: RedisActorHandle,
+            _bus: EventBus,
+        ) -> BotResult<()> {
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> BotResult<()> {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    let mut components = std::collections::HashMap::new();
+    components.insert("google_calendar".to_string(), false);
+    let config = Arc::new(RwLock::new(Config {
+        discord_token: String::new(),
+        google_client_id: String::new(),
+        google_client_secret: String::new(),
+        google_calendar_id: String::new(),
+        calendar_channel_id: 0,
+        guild_id: 0,
+        components,
+        bot_locale: "en".to_string(),
+        ..Config::for_tests()
+    }));
+
+    let calendar_shutdowns = Arc::new(AtomicUsize::new(0));
+    let schedule_shutdowns = Arc::new(AtomicUsize::new(0));
+    let mut component_manager = ComponentManager::new(Arc::clone(&config));
+    let shutdowns = Arc::clone(&calendar_shutdowns);
+    component_manager.register(move || CountingComponent {
+        name: "google_calendar",
+        shutdowns: Arc::clone(&shutdowns),
+    });
+    // Not listed in the config, so enabled
+    let shutdowns = Arc::clone(&schedule_shutdowns);
+    component_manager.register(move || CountingComponent {
+        name: "work_schedule",
+        shutdowns: Arc::clone(&shutdowns),
+    });
+
+    assert!(component_manager
+        .get_component_by_name("google_calendar")
+        .is_none());
+    assert!(component_manager
+        .get_component_by_name("work_schedule")
+        .is_some());
+    assert!(component_manager.is_disabled("google_calendar"));
+    assert!(!component_manager.is_disabled("work_schedule"));
+    assert_eq!(
+        component_manager.component_states(),
+        vec![("work_schedule", true), ("google_calendar", false)]
+    );
+
+    component_manager.shutdown_all().await.unwrap();
+    assert_eq!(calendar_shutdowns.load(Ordering::SeqCst), 0);
+    assert_eq!(schedule_shutdowns.load(Ordering::SeqCst), 1);
+}
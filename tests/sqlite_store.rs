@@ -0,0 +1,23 @@
+//! The store suite against the SQLite backend, proving it answers like Redis
+#![cfg(feature = "sqlite")]
+
+use mussubotti::components::redis_service::{FakeClock, RedisActorHandle, SqliteRedis};
+
+/// Handle to a fresh in-memory database whose keys never expire on their own
+fn handle() -> RedisActorHandle {
+    RedisActorHandle::sqlite_serving(SqliteRedis::in_memory().unwrap())
+}
+
+/// Handle to a fresh in-memory database expiring keys according to `clock`
+fn handle_with_clock(clock: FakeClock) -> RedisActorHandle {
+    RedisActorHandle::sqlite_serving(SqliteRedis::in_memory().unwrap().with_clock(clock))
+}
+
+#[allow(clippy::duplicate_mod)]
+#[path = "common/mod.rs"]
+mod common;
+
+// Compiled once per backend on purpose
+#[allow(clippy::duplicate_mod)]
+#[path = "suites/store.rs"]
+mod suite;
@@ -0,0 +1,919 @@
+//! Tests run against every store fronted by `RedisActorHandle`: the including file provides
+//! `handle` and `handle_with_clock` for its backend.
+
+use super::common::store_entry;
+use super::{handle, handle_with_clock};
+use mussubotti::builder::BotComponents;
+use mussubotti::components::event_bus::{EventBus, ScheduleChanged};
+use mussubotti::components::google_calendar::token::TokenManager;
+use mussubotti::components::redis_service::{FakeClock, RedisActorHandle};
+use mussubotti::components::work_schedule::audit::load_audit_records;
+use mussubotti::components::work_schedule::corrections::parse_correction_value;
+use mussubotti::components::work_schedule::inspect::{stored_dates, stored_entry, Inconsistency};
+use mussubotti::components::work_schedule::keys::{
+    dates_key, day_key, duplicate_field, WORK_HOURS_DUPLICATES, WORK_HOURS_EMPLOYEES,
+    WORK_HOURS_EMPLOYEE_NAMES,
+};
+use mussubotti::components::work_schedule::models::{ContextLink, ShiftRange, WorkScheduleEntry};
+use mussubotti::components::work_schedule::overlap::KeepChoice;
+use mussubotti::components::work_schedule::reconcile::ReconcileMode;
+use mussubotti::components::work_schedule::upload_channel::{
+    confirm_upload, upload_image, upload_step, PostedAttachment, PostedMessage, UploadStep,
+};
+use mussubotti::components::work_schedule::uploads::{
+    upload_error_message, PeriodIssue, UploadPipeline, UploadResponse, UploadSummary, MAX_FILE_SIZE,
+};
+use mussubotti::components::work_schedule::{EmployeeId, WorkScheduleHandle};
+use mussubotti::config::Config;
+use mussubotti::user_preferences::{set_user_preferences, UserPreferences};
+use mussubotti::utils::scheduler::{
+    claim_in_redis, release_claim, NotificationType, CLAIM_TTL_SECS,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+fn test_config() -> Arc<RwLock<Config>> {
+    Arc::new(RwLock::new(Config::for_tests()))
+}
+
+#[tokio::test]
+async fn test_day_schedules_are_in_finnish_name_order() {
+    let redis_handle = handle();
+    for employee in ["Öhman", "anna", "Åsa", "Zacharias", "Ärla", "Bertta"] {
+        store_entry(
+            &redis_handle,
+            employee,
+            &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+        )
+        .await;
+    }
+
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle, EventBus::new());
+
+    let day = handle.get_schedule_for_date("2025-01-06").await.unwrap();
+    let employees: Vec<_> = day.iter().map(|(employee, _)| employee.as_str()).collect();
+    assert_eq!(
+        employees,
+        ["anna", "Bertta", "Zacharias", "Åsa", "Ärla", "Öhman"]
+    );
+}
+
+/// Components built for another binary read the schedule without Discord
+#[tokio::test]
+async fn test_builder_serves_schedules_without_discord() {
+    let redis_handle = handle();
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    let components = BotComponents::with_shared_config(test_config())
+        .redis(redis_handle)
+        .work_schedule()
+        .build()
+        .await
+        .unwrap();
+    assert!(components.google_calendar.is_none());
+
+    let schedule = components.work_schedule.as_ref().unwrap();
+    let anna = schedule
+        .get_schedule_for_date_range("Anna", "2025-01-06", "2025-01-07")
+        .await
+        .unwrap();
+    assert_eq!(
+        anna.schedule[0],
+        WorkScheduleEntry::working("2025-01-06", "08:00", "16:00")
+    );
+    assert!(anna.schedule[1].shifts.is_empty());
+
+    components.shutdown.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_work_schedule_reads_stored_entries() {
+    let redis_handle = handle();
+    store_entry(
+        &redis_handle,
+        "Anna Mäkinen",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+    store_entry(
+        &redis_handle,
+        "Anna Mäkinen",
+        &WorkScheduleEntry::working("2025-01-07", "12:00", "20:00"),
+    )
+    .await;
+
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle, EventBus::new());
+
+    assert_eq!(handle.get_employees().await.unwrap(), ["Anna Mäkinen"]);
+
+    let day = handle.get_schedule_for_date("2025-01-06").await.unwrap();
+    assert_eq!(
+        day.get("Anna Mäkinen").unwrap().shifts,
+        [ShiftRange::new("08:00", "16:00")]
+    );
+
+    // Employees without an entry for the day are listed as missing, not as blank entries
+    let day = handle.get_schedule_for_date("2025-01-08").await.unwrap();
+    assert!(day.is_empty());
+    assert_eq!(day.missing(), ["Anna Mäkinen"]);
+
+    let week = handle
+        .get_schedule_for_date_range("Anna Mäkinen", "2025-01-06", "2025-01-12")
+        .await
+        .unwrap();
+    let dates: Vec<_> = week
+        .schedule
+        .iter()
+        .map(|entry| entry.date.as_str())
+        .collect();
+    assert!(dates.contains(&"2025-01-06"), "{dates:?}");
+    assert!(dates.contains(&"2025-01-07"), "{dates:?}");
+}
+
+#[tokio::test]
+async fn test_coverage_of_every_employee() {
+    let redis_handle = handle();
+    // Dense: a full week with the weekend off
+    for day in 6..=12 {
+        let date = format!("2025-01-{day:02}");
+        let entry = if day >= 11 {
+            WorkScheduleEntry {
+                is_day_off: true,
+                ..WorkScheduleEntry::new(date)
+            }
+        } else {
+            WorkScheduleEntry::working(&date, "08:00", "16:00")
+        };
+        store_entry(&redis_handle, "Cecilia", &entry).await;
+    }
+    // Sparse: two days months apart
+    store_entry(
+        &redis_handle,
+        "Bertil",
+        &WorkScheduleEntry::working("2025-03-20", "10:00", "18:00"),
+    )
+    .await;
+    store_entry(
+        &redis_handle,
+        "Bertil",
+        &WorkScheduleEntry::working("2025-01-06", "10:00", "18:00"),
+    )
+    .await;
+    // Empty: known employee without any dates
+    let anna = EmployeeId::new("Anna");
+    redis_handle
+        .sadd(&WORK_HOURS_EMPLOYEES, anna.slug())
+        .await
+        .unwrap();
+    redis_handle
+        .hset(&WORK_HOURS_EMPLOYEE_NAMES, anna.slug(), "Anna")
+        .await
+        .unwrap();
+
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle, EventBus::new());
+    let coverage = handle.get_employees_coverage().await.unwrap();
+
+    let summary: Vec<_> = coverage
+        .iter()
+        .map(|info| {
+            (
+                info.employee.as_str(),
+                info.first_date.as_deref(),
+                info.last_date.as_deref(),
+                info.day_count,
+                info.working_day_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("Anna", None, None, 0, 0),
+            ("Bertil", Some("2025-01-06"), Some("2025-03-20"), 2, 2),
+            ("Cecilia", Some("2025-01-06"), Some("2025-01-12"), 7, 5),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_resolving_a_duplicate_keeps_the_chosen_entry() {
+    let redis_handle = handle();
+    let anna = EmployeeId::new("Anna");
+    let first = WorkScheduleEntry::working("2025-01-06", "08:00", "16:00");
+    let last = WorkScheduleEntry::working("2025-01-06", "10:00", "18:00");
+    store_entry(&redis_handle, "Anna", &first).await;
+    redis_handle
+        .hset(
+            &WORK_HOURS_DUPLICATES,
+            &duplicate_field(&anna, "2025-01-06"),
+            serde_json::to_string(&[&first, &last]).unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let bus = EventBus::new();
+    let mut changes = bus.subscribe::<ScheduleChanged>();
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle, bus);
+    assert_eq!(
+        handle
+            .get_duplicates("2025-01-06", "2025-01-12")
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    handle
+        .resolve_duplicate("Anna", "2025-01-06", KeepChoice::Last, Some(42))
+        .await
+        .unwrap();
+    assert_eq!(
+        changes.recv().await.unwrap(),
+        ScheduleChanged {
+            employee: "Anna".to_string(),
+            date: "2025-01-06".to_string(),
+            before: "08:00–16:00 / 10:00–18:00".to_string(),
+            after: "10:00–18:00".to_string(),
+            changed_by: Some(42),
+        }
+    );
+
+    let day = handle.get_schedule_for_date("2025-01-06").await.unwrap();
+    assert_eq!(
+        day.get("Anna").unwrap().shifts,
+        [ShiftRange::new("10:00", "18:00")]
+    );
+    assert!(handle
+        .get_duplicates("2025-01-06", "2025-01-12")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_approved_correction_replaces_the_stored_entry() {
+    let redis_handle = handle();
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    let bus = EventBus::new();
+    let mut changes = bus.subscribe::<ScheduleChanged>();
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle.clone(), bus);
+    let entry = parse_correction_value("9-17", "2025-01-06").unwrap();
+    handle.correct_entry("anna", entry, Some(42)).await.unwrap();
+    assert_eq!(
+        changes.recv().await.unwrap(),
+        ScheduleChanged {
+            employee: "Anna".to_string(),
+            date: "2025-01-06".to_string(),
+            before: "08:00–16:00".to_string(),
+            after: "09:00–17:00".to_string(),
+            changed_by: Some(42),
+        }
+    );
+
+    // A day without an entry yet is added to the employee's dates
+    let entry = parse_correction_value("x", "2025-01-07").unwrap();
+    handle.correct_entry("Anna", entry, Some(42)).await.unwrap();
+    let anna = EmployeeId::new("Anna");
+    assert_eq!(
+        stored_dates(&redis_handle, &anna).await.unwrap(),
+        ["2025-01-06", "2025-01-07"]
+    );
+    let schedule = handle
+        .get_schedule_for_date_range("Anna", "2025-01-06", "2025-01-07")
+        .await
+        .unwrap();
+    assert_eq!(
+        schedule.schedule[0].shifts,
+        [ShiftRange::new("09:00", "17:00")]
+    );
+    assert!(schedule.schedule[1].is_day_off);
+
+    // Only employees with a stored schedule can be corrected
+    let entry = parse_correction_value("9-17", "2025-01-06").unwrap();
+    assert!(handle.correct_entry("Pekka", entry, None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_actual_hours_are_audited_without_a_change() {
+    let redis_handle = handle();
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    let bus = EventBus::new();
+    let mut changes = bus.subscribe::<ScheduleChanged>();
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle.clone(), bus);
+    let actual = Some(("07:45".to_string(), "16:30".to_string()));
+    let recorded = handle
+        .set_actual_hours("anna", "2025-01-06", actual, "matti")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recorded.actual_minutes(), Some(525));
+    assert_eq!(recorded.shifts, [ShiftRange::new("08:00", "16:00")]);
+    assert!(changes.try_recv().is_err());
+
+    let records = load_audit_records(&redis_handle, 0).await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].actor.as_deref(), Some("matti"));
+    assert_eq!(records[0].before.as_ref().unwrap().actual_start, None);
+    assert_eq!(records[0].after.as_ref(), Some(&recorded));
+
+    // Days without a stored entry aren't created
+    let actual = Some(("08:00".to_string(), "16:00".to_string()));
+    assert_eq!(
+        handle
+            .set_actual_hours("Anna", "2025-01-07", actual, "matti")
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_context_link_is_set_and_removed_without_a_change() {
+    let redis_handle = handle();
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    let bus = EventBus::new();
+    let mut changes = bus.subscribe::<ScheduleChanged>();
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle.clone(), bus);
+    let link = ContextLink {
+        url: "https://discord.com/channels/1/2/3".to_string(),
+        excerpt: "Vaihdoin vuoron".to_string(),
+    };
+    let linked = handle
+        .set_context_link("anna", "2025-01-06", Some(link.clone()))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(linked.context_link.as_ref(), Some(&link));
+    assert_eq!(linked.shifts, [ShiftRange::new("08:00", "16:00")]);
+
+    let day = handle.get_schedule_for_date("2025-01-06").await.unwrap();
+    assert_eq!(day.values().next().unwrap().context_link, Some(link));
+
+    let unlinked = handle
+        .set_context_link("Anna", "2025-01-06", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(unlinked.context_link, None);
+    // Links aren't schedule changes
+    assert!(changes.try_recv().is_err());
+
+    // Days without a stored entry aren't created
+    assert_eq!(
+        handle
+            .set_context_link("Anna", "2025-01-07", None)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        stored_dates(&redis_handle, &EmployeeId::new("Anna"))
+            .await
+            .unwrap(),
+        ["2025-01-06"]
+    );
+}
+
+#[tokio::test]
+async fn test_claims_expire_after_their_ttl() {
+    let clock = FakeClock::new();
+    let redis_handle = handle_with_clock(clock.clone());
+    let daily = NotificationType::Daily;
+
+    assert!(
+        claim_in_redis(&redis_handle, "work_schedule", &daily, "2025-01-06")
+            .await
+            .unwrap()
+    );
+    assert!(
+        !claim_in_redis(&redis_handle, "work_schedule", &daily, "2025-01-06")
+            .await
+            .unwrap()
+    );
+    // Other components and days are claimed separately
+    assert!(
+        claim_in_redis(&redis_handle, "google_calendar", &daily, "2025-01-06")
+            .await
+            .unwrap()
+    );
+    assert!(
+        claim_in_redis(&redis_handle, "work_schedule", &daily, "2025-01-07")
+            .await
+            .unwrap()
+    );
+
+    clock.advance(Duration::from_secs(CLAIM_TTL_SECS - 1));
+    assert!(
+        !claim_in_redis(&redis_handle, "work_schedule", &daily, "2025-01-06")
+            .await
+            .unwrap()
+    );
+
+    clock.advance(Duration::from_secs(1));
+    assert!(
+        claim_in_redis(&redis_handle, "work_schedule", &daily, "2025-01-06")
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_released_claim_can_be_taken_again() {
+    let redis_handle = handle();
+    let weekly = NotificationType::Weekly;
+
+    assert!(
+        claim_in_redis(&redis_handle, "work_schedule", &weekly, "2025-01-06")
+            .await
+            .unwrap()
+    );
+    release_claim(&redis_handle, "work_schedule", &weekly, "2025-01-06")
+        .await
+        .unwrap();
+    assert!(
+        claim_in_redis(&redis_handle, "work_schedule", &weekly, "2025-01-06")
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_stored_token_is_used_until_it_expires() {
+    let redis_handle = handle();
+    let tokens = TokenManager::new(test_config(), redis_handle.clone());
+
+    let error = tokens.get_token().await.unwrap_err();
+    assert!(error.is_auth(), "{error}");
+
+    let expires_at = chrono::Utc::now().timestamp() + 3600;
+    tokens
+        .set_token(serde_json::json!({
+            "access_token": "test_token",
+            "refresh_token": "test_refresh",
+            "expires_at": expires_at,
+        }))
+        .await
+        .unwrap();
+
+    let token = tokens.get_token().await.unwrap();
+    assert_eq!(token["access_token"], "test_token");
+
+    // The token is shared through Redis, not kept in the manager
+    let stored = redis_handle.get_token().await.unwrap().unwrap();
+    assert_eq!(stored["expires_at"], expires_at);
+}
+
+#[tokio::test]
+async fn test_stored_entry_reports_index_inconsistencies() {
+    let redis_handle = handle();
+    let anna = EmployeeId::new("Anna");
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    let stored = stored_entry(&redis_handle, &anna, "2025-01-06")
+        .await
+        .unwrap();
+    assert_eq!(
+        stored.key,
+        day_key(&anna, "2025-01-06").unwrap().to_string()
+    );
+    assert!(stored.raw.unwrap().contains("08:00"));
+    assert_eq!(stored.ttl_secs, None);
+    assert!(stored.in_dates);
+
+    // An entry whose date was never indexed
+    let orphan = day_key(&anna, "2025-01-07").unwrap();
+    redis_handle.set(&orphan, "{}").await.unwrap();
+    redis_handle.expire(&orphan, 3600).await.unwrap();
+    let stored = stored_entry(&redis_handle, &anna, "2025-01-07")
+        .await
+        .unwrap();
+    assert_eq!(stored.ttl_secs, Some(3600));
+    assert_eq!(
+        stored.inconsistency(),
+        Some(Inconsistency::MissingFromDates)
+    );
+
+    // A date indexed without an entry
+    redis_handle
+        .sadd(&dates_key(&anna).unwrap(), "2025-01-08")
+        .await
+        .unwrap();
+    let stored = stored_entry(&redis_handle, &anna, "2025-01-08")
+        .await
+        .unwrap();
+    assert!(stored.raw.is_none());
+    assert_eq!(stored.inconsistency(), Some(Inconsistency::MissingEntry));
+
+    let stored = stored_entry(&redis_handle, &anna, "2025-01-09")
+        .await
+        .unwrap();
+    assert_eq!(stored.inconsistency(), None);
+
+    assert_eq!(
+        stored_dates(&redis_handle, &anna).await.unwrap(),
+        ["2025-01-06", "2025-01-08"]
+    );
+}
+
+#[tokio::test]
+async fn test_reconcile_finds_and_repairs_inconsistencies() {
+    let redis_handle = handle();
+    let anna = EmployeeId::new("Anna");
+    store_entry(
+        &redis_handle,
+        "Anna",
+        &WorkScheduleEntry::working("2025-01-06", "08:00", "16:00"),
+    )
+    .await;
+
+    // An entry whose date was never indexed
+    redis_handle
+        .set(&day_key(&anna, "2025-01-07").unwrap(), "{}")
+        .await
+        .unwrap();
+    // A date indexed without an entry
+    redis_handle
+        .sadd(&dates_key(&anna).unwrap(), "2025-01-08")
+        .await
+        .unwrap();
+    // An employee whose only date has no entry
+    let liisa = EmployeeId::new("Liisa");
+    redis_handle
+        .sadd(&WORK_HOURS_EMPLOYEES, liisa.slug())
+        .await
+        .unwrap();
+    redis_handle
+        .sadd(&dates_key(&liisa).unwrap(), "2025-01-06")
+        .await
+        .unwrap();
+    // An entry of someone outside the employees set
+    let pekka = EmployeeId::new("Pekka");
+    redis_handle
+        .set(&day_key(&pekka, "2025-01-06").unwrap(), "{}")
+        .await
+        .unwrap();
+
+    let handle = WorkScheduleHandle::new(test_config(), redis_handle.clone(), EventBus::new());
+
+    let entry = |member: &EmployeeId, date: &str| (member.slug().to_string(), date.to_string());
+    let report = handle.reconcile(ReconcileMode::Report).await.unwrap();
+    assert_eq!(report.empty_employees, [liisa.slug()]);
+    assert_eq!(
+        report.missing_entries,
+        [entry(&anna, "2025-01-08"), entry(&liisa, "2025-01-06")]
+    );
+    assert_eq!(report.unindexed_entries, [entry(&anna, "2025-01-07")]);
+    assert_eq!(report.orphaned_entries, [entry(&pekka, "2025-01-06")]);
+
+    // Reporting leaves everything in place
+    assert_eq!(
+        handle.reconcile(ReconcileMode::Report).await.unwrap(),
+        report
+    );
+
+    let repaired = handle.reconcile(ReconcileMode::Repair).await.unwrap();
+    assert_eq!(repaired.mode, ReconcileMode::Repair);
+    assert_eq!(repaired.orphaned_entries, report.orphaned_entries);
+
+    assert_eq!(
+        stored_dates(&redis_handle, &anna).await.unwrap(),
+        ["2025-01-06", "2025-01-07"]
+    );
+    let employees: Vec<String> = redis_handle.smembers(&WORK_HOURS_EMPLOYEES).await.unwrap();
+    assert_eq!(employees, [anna.slug()]);
+    let orphan: Option<String> = redis_handle
+        .get(&day_key(&pekka, "2025-01-06").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(orphan, None);
+
+    assert!(handle
+        .reconcile(ReconcileMode::Report)
+        .await
+        .unwrap()
+        .is_clean());
+}
+
+/// Upload pipeline answering every upload the same way, remembering what it was given
+struct CannedPipeline {
+    response: Option<UploadResponse>,
+    uploads: std::sync::Mutex<Vec<(String, Vec<u8>)>>,
+}
+
+#[async_trait::async_trait]
+impl UploadPipeline for CannedPipeline {
+    async fn upload(
+        &self,
+        employee: &str,
+        data: Vec<u8>,
+    ) -> mussubotti::error::BotResult<UploadResponse> {
+        self.uploads
+            .lock()
+            .unwrap()
+            .push((employee.to_string(), data));
+        self.response
+            .clone()
+            .ok_or_else(|| mussubotti::error::other_error("work_hours is down"))
+    }
+
+    async fn confirm(&self, _pending_id: &str) -> mussubotti::error::BotResult<UploadResponse> {
+        // Confirming stores what was held
+        match self.response.clone() {
+            Some(UploadResponse::SuspiciousPeriod { summary, .. }) => {
+                Ok(UploadResponse::Stored(summary))
+            }
+            response => {
+                response.ok_or_else(|| mussubotti::error::other_error("work_hours is down"))
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_images_in_the_upload_channel_are_uploaded_for_the_linked_employee() {
+    let redis_handle = handle();
+    let posted = |channel_id: u64, author_id: u64, files: &[(&str, Option<&str>)]| PostedMessage {
+        channel_id,
+        author_id,
+        from_bot: false,
+        attachments: files
+            .iter()
+            .map(|(file_name, content_type)| PostedAttachment {
+                file_name: file_name.to_string(),
+                content_type: content_type.map(str::to_string),
+                size: 1024,
+            })
+            .collect(),
+    };
+    let schedule = [("notes.txt", Some("text/plain")), ("LISTA.JPG", None)];
+
+    // Unlinked posters are asked whose schedule it is; only the image is picked
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &posted(10, 1, &schedule)).await,
+        UploadStep::AskEmployee { attachment: 1 }
+    );
+
+    let preferences = UserPreferences {
+        employee: Some("Anna".to_string()),
+        ..UserPreferences::default()
+    };
+    set_user_preferences(&redis_handle, 1, &preferences)
+        .await
+        .unwrap();
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &posted(10, 1, &schedule)).await,
+        UploadStep::Upload {
+            attachment: 1,
+            employee: "Anna".to_string()
+        }
+    );
+
+    // Images over the upload limit are refused before they're downloaded
+    let mut oversized = posted(10, 1, &schedule);
+    oversized.attachments[1].size = MAX_FILE_SIZE as u32 + 1;
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &oversized).await,
+        UploadStep::TooLarge
+    );
+
+    // Other channels, messages without images, bots and a missing channel are ignored
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &posted(11, 1, &schedule)).await,
+        UploadStep::Ignore
+    );
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &posted(10, 1, &schedule[..1])).await,
+        UploadStep::Ignore
+    );
+    let mut from_bot = posted(10, 1, &schedule);
+    from_bot.from_bot = true;
+    assert_eq!(
+        upload_step(&redis_handle, Some(10), &from_bot).await,
+        UploadStep::Ignore
+    );
+    assert_eq!(
+        upload_step(&redis_handle, None, &posted(10, 1, &schedule)).await,
+        UploadStep::Ignore
+    );
+}
+
+#[tokio::test]
+async fn test_upload_replies_describe_the_pipeline_answer() {
+    let pipeline = |response| CannedPipeline {
+        response,
+        uploads: std::sync::Mutex::new(Vec::new()),
+    };
+
+    let mut entry = WorkScheduleEntry::new("2025-03-11".to_string());
+    entry.notes = Some("Toive vp".to_string());
+    let summary = UploadSummary::new(
+        "Anna",
+        &[
+            WorkScheduleEntry::new("2025-03-10".to_string()),
+            entry,
+            WorkScheduleEntry::new("2025-03-12".to_string()),
+        ],
+    );
+    let stored = pipeline(Some(UploadResponse::Stored(summary.clone())));
+    let reply = upload_image(&stored, "Anna", vec![1, 2, 3]).await;
+    assert_eq!(reply.pending_id, None);
+    let view = reply.view;
+    assert_eq!(
+        *stored.uploads.lock().unwrap(),
+        [("Anna".to_string(), vec![1, 2, 3])]
+    );
+    let description = view.description.clone().unwrap();
+    assert!(description.contains("2025-03-10") && description.contains("2025-03-12"));
+    assert_eq!(view.fields.len(), 1);
+    assert_eq!(view.fields[0].lines[0].text, "2025-03-11: Toive vp");
+
+    // The hours are compared to the contract when the employee has one
+    let over = UploadSummary {
+        contract_delta_minutes: Some(90),
+        ..summary.clone()
+    };
+    let view = upload_image(
+        &pipeline(Some(UploadResponse::Stored(over))),
+        "Anna",
+        Vec::new(),
+    )
+    .await
+    .view;
+    assert_eq!(view.fields.len(), 2);
+    assert!(view.fields[1].lines[0].text.contains('+'));
+
+    let rejected = pipeline(Some(UploadResponse::Rejected {
+        code: "bad_format".to_string(),
+        detail: Some("no table found".to_string()),
+    }));
+    let view = upload_image(&rejected, "Anna", Vec::new()).await.view;
+    let description = view.description.clone().unwrap();
+    assert!(description.starts_with(&upload_error_message("bad_format").unwrap()));
+    assert!(description.ends_with("`no table found`"));
+
+    // Stored again and failed uploads get a reply of their own, without fields
+    let again = upload_image(
+        &pipeline(Some(UploadResponse::AlreadyStored)),
+        "Anna",
+        Vec::new(),
+    )
+    .await
+    .view;
+    let failed = upload_image(&pipeline(None), "Anna", Vec::new()).await.view;
+    assert!(again.description.unwrap().contains("Anna"));
+    assert_ne!(again.color, failed.color);
+    assert!(failed.fields.is_empty());
+
+    // A held upload shows the detected range and is stored once confirmed
+    let held = pipeline(Some(UploadResponse::SuspiciousPeriod {
+        pending_id: "abc".to_string(),
+        issue: PeriodIssue::MostlyPast,
+        summary,
+    }));
+    let reply = upload_image(&held, "Anna", Vec::new()).await;
+    assert_eq!(reply.pending_id.as_deref(), Some("abc"));
+    let description = reply.view.description.clone().unwrap();
+    assert!(description.contains("2025-03-10") && description.contains("2025-03-12"));
+    assert!(description.contains(&PeriodIssue::MostlyPast.message()));
+    let confirmed = confirm_upload(&held, "Anna", "abc").await;
+    assert_ne!(confirmed.color, reply.view.color);
+    assert!(!confirmed
+        .description
+        .unwrap()
+        .contains(&PeriodIssue::MostlyPast.message()));
+}
+
+/// Restarting a component shuts its instance down and creates a fresh one from its factory
+#[tokio::test]
+async fn test_restarting_a_component_replaces_its_instance() {
+    use async_trait::async_trait;
+    use mussubotti::components::{Component, ComponentManager};
+    use mussubotti::error::BotResult;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Calls {
+        created: AtomicUsize,
+        inits: AtomicUsize,
+        starts: AtomicUsize,
+        shutdowns: AtomicUsize,
+    }
+
+    struct CountingComponent {
+        instance: usize,
+        calls: Arc<Calls>,
+    }
+
+    #[async_trait]
+    impl Component for CountingComponent {
+        fn name(&self) -> &'static str {
+            "work_schedule"
+        }
+
+        async fn create(
+            &self,
+            _config: Arc<RwLock<Config>>,
+            _redis_handle: RedisActorHandle,
+            _bus: EventBus,
+        ) -> BotResult<()> {
+            self.calls.inits.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn start_background(
+            &self,
+            _config: Arc<RwLock<Config>>,
+            _redis_handle: RedisActorHandle,
+        ) -> BotResult<()> {
+            self.calls.starts.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> BotResult<()> {
+            self.calls.shutdowns.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    let calls = Arc::new(Calls::default());
+    let mut component_manager = ComponentManager::new(test_config());
+    let factory_calls = Arc::clone(&calls);
+    component_manager.register(move || CountingComponent {
+        instance: factory_calls.created.fetch_add(1, Ordering::SeqCst),
+        calls: Arc::clone(&factory_calls),
+    });
+    let instance = |manager: &ComponentManager| {
+        manager
+            .get_component_by_name("work_schedule")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<CountingComponent>()
+            .unwrap()
+            .instance
+    };
+    assert_eq!(instance(&component_manager), 0);
+
+    let redis_handle = handle();
+    let report = component_manager
+        .restart("work_schedule", redis_handle.clone(), true)
+        .await
+        .unwrap();
+    assert!(report.succeeded() && report.shutdown_error.is_none());
+    assert_eq!(instance(&component_manager), 1);
+
+    // A follower doesn't start the background tasks
+    let report = component_manager
+        .restart("work_schedule", redis_handle.clone(), false)
+        .await
+        .unwrap();
+    assert!(report.succeeded());
+    assert_eq!(instance(&component_manager), 2);
+
+    assert_eq!(calls.created.load(Ordering::SeqCst), 3);
+    assert_eq!(calls.shutdowns.load(Ordering::SeqCst), 2);
+    assert_eq!(calls.inits.load(Ordering::SeqCst), 2);
+    assert_eq!(calls.starts.load(Ordering::SeqCst), 1);
+
+    assert!(component_manager
+        .restart("google_calendar", redis_handle, true)
+        .await
+        .is_none());
+    assert_eq!(calls.created.load(Ordering::SeqCst), 3);
+
+    // Shutting the bot down reaches the current instance only
+    component_manager.shutdown_all().await.unwrap();
+    assert_eq!(calls.shutdowns.load(Ordering::SeqCst), 3);
+}